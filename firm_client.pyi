@@ -2,10 +2,44 @@ from __future__ import annotations
 
 from enum import IntEnum
 from types import TracebackType
-from typing import ClassVar, Optional, Type
+from typing import Callable, ClassVar, Literal, Optional, Type
 
 __version__: str
 
+def read_one(
+    port: str | None = None, timeout: float = 2.0, baud_rate: int = 2_000_000
+) -> FIRMDataPacket: ...
+"""Open the port, wait up to `timeout` seconds for one data packet, close the port and return it.
+
+If `port` is None, the only FIRM port detected is used, or the only serial port if none look
+like FIRM; OSError is raised if there are several, so pass `port` explicitly then. Raises
+TimeoutError if no packet arrives in time.
+"""
+
+def read_csv(
+    path: str, repair_timestamps: bool = False, inf_sentinel: str = "inf"
+) -> list[FIRMDataPacket]: ...
+"""Read a CSV written by the logging tools back into packets.
+
+Columns are matched by name; missing columns and empty cells become NaN, and `inf_sentinel`
+(see `NonFinitePolicy`) becomes infinity. Raises ValueError
+listing every bad row (with its line number), including rows whose timestamp isn't after the
+previous ones. With `repair_timestamps=True` rows are sorted by timestamp and duplicates
+dropped instead. Like the client's exceptions, the error's args are `(message, code)`.
+"""
+
+class NonFinitePolicy:
+    """How CSV cells represent NaN and infinity, shared with the Rust CSV writer: NaN is an
+    empty cell and infinity the sentinel, with a leading `-` when negative."""
+
+    inf_sentinel: str
+    """Cell written for positive infinity."""
+
+    def __init__(self, inf_sentinel: str = "inf") -> None: ...
+    def csv_cell(self, value: float) -> str:
+        """Returns the CSV cell for `value`, an empty string for NaN."""
+        ...
+
 class DeviceProtocol(IntEnum):
     """Enum of the supported device communication protocols."""
 
@@ -26,6 +60,8 @@ class DeviceConfig:
     name: str
     frequency: int
     protocol: DeviceProtocol
+    suspect: bool
+    """True if the device reported fields out of protocol range, so the others are a best guess."""
 
 class CalibrationValues:
     """Represents the calibration values for the FIRM device."""
@@ -116,6 +152,10 @@ class FIRMDataPacket:
     est_quaternion_z: float
     """Estimated orientation quaternion vector component (Z)."""
 
+    pressure_altitude_meters: float
+    """Barometric altitude computed by the client: above the zeroed ground level, or above
+    mean sea level if the client hasn't been zeroed. 0 in packets not read by a client."""
+
     def __init__(
         self,
         timestamp_seconds: float,
@@ -146,9 +186,11 @@ class FIRMDataPacket:
         est_quaternion_x: float,
         est_quaternion_y: float,
         est_quaternion_z: float,
+        pressure_altitude_meters: float = 0.0,
     ) -> None:
         """
-        Initialize a new immutable FIRMDataPacket. All fields are required.
+        Initialize a new immutable FIRMDataPacket. All fields except
+        pressure_altitude_meters are required.
         """
         ...
 
@@ -166,6 +208,13 @@ class FIRMDataPacket:
         """
         ...
 
+    def to_json(self) -> str:
+        """
+        Returns the packet as a JSON object keyed by field name. NaN and infinity, which JSON
+        has no number for, are written as null.
+        """
+        ...
+
 class MockDeviceHandle:
     """Handle for controlling an in-process mock device."""
 
@@ -175,6 +224,43 @@ class MockDeviceHandle:
     def wait_for_command_identifier(self, timeout_seconds: float) -> int | None: ...
     """Wait up to timeout_seconds for a command to be observed; returns its identifier or None."""
 
+class AlarmEvent:
+    """An alarm raised or cleared, passed to callbacks registered with `FIRMClient.on_alarm`."""
+
+    raised: bool
+    rule: str
+    message: str
+    """What tripped the alarm; empty when it clears."""
+
+class ErrorEvent:
+    """An error reported by the client's background threads, see `FIRMClient.get_warnings`."""
+
+    time: float
+    """When it was reported, in seconds since the Unix epoch."""
+    message: str
+    code: str
+    """The stable error code, e.g. "E_LINK_IDLE"."""
+    fatal: bool
+
+class SessionInfo:
+    """What the device reported about itself when the client started, see
+    `FIRMClient.set_session_capture`."""
+
+    device_info: DeviceInfo | None
+    device_config: DeviceConfig | None
+    partial: bool
+    """Set when the device didn't answer both requests."""
+
+class ThresholdEvent:
+    """A threshold crossing, passed to callbacks registered with `FIRMClient.on_threshold`."""
+
+    field: str
+    threshold: float
+    direction: str
+    """"rising" or "falling"."""
+    value: float
+    timestamp_seconds: float
+
 class FIRMClient:
     """Client for communicating with the FIRM device.
 
@@ -182,6 +268,15 @@ class FIRMClient:
         port_name: The name of the serial port to connect to.
         baud_rate: The baud rate for the serial connection. Default is 2,000,000.
         timeout: Read timeout used when get_data_packets(block=True). Default is 0.1 seconds.
+
+    Errors that stop the background reader thread are raised by the next call that checks for
+    them: ConnectionError when the device or bridge disconnects, RuntimeError when the reader
+    thread panicked, and OSError for other I/O failures. Errors it carries on after, such as a
+    callback raising or the link going idle, are collected for `get_warnings` instead.
+
+    Every exception raised by the client has `args == (message, code)`, where `code` is a
+    stable identifier such as "E_TIMEOUT" or "E_PORT_NOT_FOUND" shared with the Rust and web
+    clients.
     """
 
     def __init__(
@@ -191,12 +286,107 @@ class FIRMClient:
     def new_mock(timeout: float = 0.1) -> tuple[FIRMClient, MockDeviceHandle]: ...
     """Create a client + mock device pair for testing."""
 
+    @staticmethod
+    def connect_tcp(addr: str, timeout: float = 0.1, reconnect: bool = False) -> FIRMClient: ...
+    """Connect to a FIRM bridge that serves the serial stream over TCP (e.g. "192.168.1.20:5000").
+
+    Args:
+        timeout: Connect and read timeout in seconds.
+        reconnect: Keep reconnecting when the connection drops instead of stopping.
+    """
+
     def start(self) -> None: ...
-    """Start the background reader thread."""
+    """Start the background reader thread. Does nothing if it's already running. If the reader
+    stopped after a connection error, the port is reopened first, raising the usual open
+    errors (e.g. FileNotFoundError) if that fails. With session capture on, it then waits for
+    the device's info and config, see `session_info`."""
+
+    def get_warnings(self) -> list[ErrorEvent]: ...
+    """Errors the client carried on after since the last call, oldest first. At most the last
+    64 are kept."""
+
+    def set_session_capture(self, timeout_seconds: float | None = None) -> None: ...
+    """Make start() ask the device for its info and config, waiting up to timeout_seconds for
+    each reply. None turns it off. Raw recordings started afterwards include the answers in
+    their header."""
+
+    def session_info(self) -> SessionInfo | None: ...
+    """What the device reported at the last start() with session capture on, or what a raw
+    recording's header says. None if nothing was captured."""
 
     def stop(self) -> None: ...
     """Stop the background reader thread and close the serial port."""
 
+    def stop_with_timeout(self, timeout_seconds: float) -> None: ...
+    """Like stop, but raise TimeoutError instead of waiting longer than timeout_seconds for
+    a blocked reader thread. The thread is abandoned and the next start() reopens the port."""
+
+    @staticmethod
+    def from_raw_recording(path: str) -> FIRMClient: ...
+    """Replay a raw recording made by `record_raw` through the parser as fast as possible.
+    The client stops by itself when the recording ends."""
+
+    @staticmethod
+    def from_log_file(path: str, speed: float = 1.0) -> FIRMClient: ...
+    """Play a FIRM log file (.frm) back as if a device were streaming it, paced by its
+    timestamps. `speed` scales the pacing (2.0 is twice as fast, 0 as fast as possible). Only
+    the raw sensor fields are filled in. `is_running()` turns False when the log ends.
+    Commands raise NotImplementedError; use `pause`, `resume`, `seek` and `set_speed`."""
+
+    def is_replay(self) -> bool: ...
+    """True for clients replaying a log file or raw recording."""
+
+    def pause(self) -> None: ...
+    """Pause a log file playback. Raises NotImplementedError for other clients."""
+
+    def resume(self) -> None: ...
+    """Resume a paused log file playback from where it stopped."""
+
+    def seek(self, timestamp_seconds: float) -> None: ...
+    """Jump a log file playback to the first packet at or after timestamp_seconds."""
+
+    def set_speed(self, speed: float) -> None: ...
+    """Change a log file playback's speed (0 plays as fast as possible)."""
+
+    def record_raw(self, path: str) -> None: ...
+    """Record every byte read from the device to `path` (with a header describing the
+    connection) until `stop_recording` is called. The file is flushed on `stop`."""
+
+    def stop_recording(self) -> bool: ...
+    """Stop and flush the raw recording. Returns False if none was running."""
+
+    def enable_default_alarms(self, expected_frequency_hz: float) -> None: ...
+    """Evaluate the standard alarm rules while running: any dropped packet in 5 s, more than
+    1 CRC failure per second, and packet rate under 80% of `expected_frequency_hz` for 10 s."""
+
+    def disable_alarms(self) -> None: ...
+    """Stop evaluating alarm rules."""
+
+    def active_alarms(self) -> list[str]: ...
+    """Names of the alarms currently raised."""
+
+    def get_alarm_events(self) -> list[AlarmEvent]: ...
+    """Alarm events emitted since the last call, oldest first."""
+
+    def on_alarm(self, callback: Callable[[AlarmEvent], None]) -> None: ...
+    """Call `callback(event)` from the reader thread whenever an alarm is raised or cleared.
+    Exceptions raised by the callback are printed and otherwise ignored."""
+
+    def on_threshold(
+        self,
+        field: str,
+        thresholds: list[float],
+        callback: Callable[[ThresholdEvent], None],
+        direction: str = "both",
+        hysteresis: float = 0.0,
+    ) -> None: ...
+    """Call `callback(event)` from the reader thread whenever `field` crosses one of
+    `thresholds`, once per crossing. direction is "rising", "falling" or "both". The value must
+    leave the band of +-hysteresis around a threshold before it counts as crossed, so noise
+    doesn't repeat events. For altitude use "est_position_z_meters" (filtered) or
+    "pressure_altitude_meters". Raises ValueError for an unknown field or direction.
+    """
+
     def get_data_packets(self, block: bool = False) -> list[FIRMDataPacket]: ...
     """Retrieve currently-available data packets.
 
@@ -204,9 +394,50 @@ class FIRMClient:
         block: If True, blocks up to `timeout` (from __init__) waiting for packets.
     """
 
+    def set_max_packet_age(self, max_age_seconds: float | None = None) -> None: ...
+    """Drop packets older than max_age_seconds of device time (relative to the newest packet
+    seen) when they are consumed, so a stalled consumer resumes near real time. None disables it.
+    """
+
+    def set_read_buffer_size(self, size: int) -> None: ...
+    """Set how many bytes the reader thread reads at once (1 to 65536, default 1024). Larger
+    buffers cost fewer syscalls at high baud rates. Takes effect at the next start().
+    """
+
+    def set_read_strategy(self, poll_interval_seconds: float | None = None) -> None: ...
+    """Poll for available bytes every poll_interval_seconds instead of blocking in read, trading
+    latency for CPU. None (the default) uses blocking reads. Takes effect at the next start().
+    """
+
+    def flush_stale(self) -> list[FIRMDataPacket]: ...
+    """Drop queued packets older than the max packet age and return the fresh ones."""
+
+    def aged_out_count(self) -> int: ...
+    """Number of packets dropped so far by the max packet age policy."""
+
+    def get_latest_packet(self) -> FIRMDataPacket | None: ...
+    """Return only the newest available packet, discarding the older ones queued before it."""
+
+    def set_latest_only(self, enabled: bool) -> None: ...
+    """While enabled, keep only the newest packet for get_latest_packet instead of queueing
+    every packet. get_data_packets only sees packets queued while it is disabled.
+    """
+
+    def set_decimation(self, n: int | None = None) -> None: ...
+    """Keep only every nth data packet, dropping the rest before they cross into Python.
+    None keeps every packet again. Raises ValueError if n is 0.
+    """
+
+    def skipped_packet_count(self) -> int: ...
+    """Number of packets dropped because a newer one replaced them."""
+
     def get_device_info(self, timeout_seconds: float = 5.0) -> DeviceInfo | None: ...
     """Request device info and wait up to timeout_seconds."""
 
+    def ping(self, timeout_seconds: float = 1.0) -> float: ...
+    """Check the device is answering, even while it isn't streaming, and return the round
+    trip time in seconds. Raises TimeoutError if there's no reply within timeout_seconds."""
+
     def get_device_config(
         self, timeout_seconds: float = 5.0
     ) -> DeviceConfig | None: ...
@@ -218,8 +449,11 @@ class FIRMClient:
         frequency: int,
         protocol: DeviceProtocol,
         timeout_seconds: float = 5.0,
+        verify: bool = False,
     ) -> bool: ...
-    """Set device config and wait up to timeout_seconds for acknowledgement."""
+    """Set device config and wait up to timeout_seconds for acknowledgement. With verify, the
+    config is read back too and False is returned unless it matches (names are cut to 32
+    bytes)."""
 
     def set_magnetometer_calibration(
         self,
@@ -250,12 +484,70 @@ class FIRMClient:
     ) -> CalibrationValues | None: ...
     """Request calibration values and wait up to timeout_seconds."""
 
+    def set_calibration(
+        self, calibration: CalibrationValues, timeout_seconds: float = 5.0
+    ) -> bool: ...
+    """Write a full calibration (e.g. one from get_calibration, modified) and wait up to
+    timeout_seconds for each of the IMU and magnetometer acknowledgements."""
+
     def cancel(self, timeout_seconds: float = 5.0) -> bool: ...
     """Send cancel and wait up to timeout_seconds for acknowledgement."""
 
     def reboot(self) -> None: ...
     """Send reboot command."""
 
+    def reboot_and_reconnect(self, timeout_seconds: float = 10.0) -> None: ...
+    """Reboot the device, wait for its port to come back and restart streaming.
+
+    Raises ConnectionError if the port doesn't come back within `timeout_seconds`, and
+    TimeoutError if it does but no packet arrives in that time.
+    """
+
+    def send_break(self, duration_seconds: float) -> None: ...
+    """Hold the serial line in the break condition for duration_seconds. Safe while streaming."""
+
+    def set_baud_rate(self, baud_rate: int) -> None: ...
+    """Switch the host side of the serial link to baud_rate without recreating the client.
+    Safe while streaming; bytes received at the old rate are dropped. Raises
+    NotImplementedError if the connection isn't a serial port."""
+
+    def set_dtr(self, level: bool) -> None: ...
+    """Drive the serial port's DTR line. Safe while streaming. Raises NotImplementedError if
+    the connection isn't a serial port."""
+
+    def set_rts(self, level: bool) -> None: ...
+    """Drive the serial port's RTS line. Safe while streaming. Raises NotImplementedError if
+    the connection isn't a serial port."""
+
+    def pulse_reset(self, pattern: list[tuple[bool, float]]) -> None: ...
+    """Drive DTR through pattern, holding each (level, hold_seconds) pair in turn, e.g. to reset
+    the board into its bootloader. Reads pause for the length of the pattern. Raises
+    NotImplementedError if the connection isn't a serial port."""
+
+    def purge_buffers(self, direction: Literal["input", "output", "all"] = "all") -> None: ...
+    """Discard bytes waiting in the OS receive and/or transmit buffers."""
+
+    def recover_link(
+        self, step_timeout_seconds: float = 1.0
+    ) -> Literal["purge_buffers", "break", "cancel"] | None: ...
+    """Try to unstick a wedged device: purge buffers, send a break, then send Cancel, waiting
+    up to step_timeout_seconds for a valid frame after each. Returns the step that brought the
+    stream back, or None if none did."""
+
+    def zero_out_pressure_altitude(
+        self, samples: int = 50, timeout_seconds: float = 5.0
+    ) -> float | None: ...
+    """Average the pressure of the next `samples` packets and report pressure_altitude_meters
+    relative to it from then on. Returns the ground altitude above sea level, or None if not
+    enough packets arrived before the timeout. Calling it again re-baselines."""
+
+    def set_sea_level_pressure(self, pascals: float) -> None: ...
+    """Report pressure_altitude_meters above mean sea level using `pascals` as the sea level
+    pressure. Clears the ground reference set by zero_out_pressure_altitude."""
+
+    def ground_reference_altitude(self) -> float | None: ...
+    """The ground altitude above sea level set by zero_out_pressure_altitude, if any."""
+
     def start_mock_log_stream(
         self,
         log_path: str,
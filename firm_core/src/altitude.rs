@@ -0,0 +1,202 @@
+//! Barometric altitude from a pressure reading, as `SerialParser` fills in
+//! `FIRMData::pressure_altitude_meters`.
+//!
+//! `pressure_altitude_meters` is the standard atmosphere formula, which assumes the standard
+//! temperature lapse from 15 °C at the reference level. `temperature_corrected_altitude_meters`
+//! uses the temperature measured alongside the pressure instead.
+
+/// Standard atmosphere pressure at mean sea level, in pascals.
+pub const STANDARD_SEA_LEVEL_PRESSURE_PASCALS: f32 = 101_325.0;
+
+/// Standard atmosphere temperature lapse rate, in kelvin per meter.
+const LAPSE_RATE_KELVIN_PER_METER: f32 = 0.0065;
+
+/// Exponent of the barometric formula, `R * L / (g * M)` for dry air.
+const BAROMETRIC_EXPONENT: f32 = 0.1903;
+
+/// Altitude above the level where the pressure is `reference_pressure_pascals` for
+/// `pressure_pascals`, from the standard atmosphere formula `h = 44330 * (1 - (p / p0)^0.1903)`.
+///
+/// # Arguments
+///
+/// - `pressure_pascals` (`f32`) - The measured pressure.
+/// - `reference_pressure_pascals` (`f32`) - The pressure at the altitude to measure from,
+///   `p0`: `STANDARD_SEA_LEVEL_PRESSURE_PASCALS` or the local sea level pressure for altitude
+///   above sea level, or the pressure on the ground for height above it.
+///
+/// # Returns
+///
+/// - `f32` - The altitude in meters, negative below the reference level.
+pub fn pressure_altitude_meters(pressure_pascals: f32, reference_pressure_pascals: f32) -> f32 {
+    44_330.0
+        * (1.0
+            - powf(
+                pressure_pascals / reference_pressure_pascals,
+                BAROMETRIC_EXPONENT,
+            ))
+}
+
+/// Like `pressure_altitude_meters`, but from the hypsometric formula
+/// `h = ((p0 / p)^0.1903 - 1) * T / 0.0065`, with `T` the air temperature where the
+/// pressure was measured rather than the standard atmosphere's. Closer on days far from
+/// standard conditions, as long as the sensor reads the outside air.
+///
+/// # Arguments
+///
+/// - `pressure_pascals` (`f32`) - The measured pressure.
+/// - `reference_pressure_pascals` (`f32`) - The pressure at the altitude to measure from.
+/// - `temperature_celsius` (`f32`) - The air temperature where `pressure_pascals` was measured.
+///
+/// # Returns
+///
+/// - `f32` - The altitude in meters, negative below the reference level.
+pub fn temperature_corrected_altitude_meters(
+    pressure_pascals: f32,
+    reference_pressure_pascals: f32,
+    temperature_celsius: f32,
+) -> f32 {
+    let ratio = powf(
+        reference_pressure_pascals / pressure_pascals,
+        BAROMETRIC_EXPONENT,
+    );
+    (ratio - 1.0) * (temperature_celsius + 273.15) / LAPSE_RATE_KELVIN_PER_METER
+}
+
+#[cfg(feature = "std")]
+fn powf(base: f32, exponent: f32) -> f32 {
+    base.powf(exponent)
+}
+
+#[cfg(not(feature = "std"))]
+fn powf(base: f32, exponent: f32) -> f32 {
+    core_powf(base, exponent)
+}
+
+/// `base.powf(exponent)` for a non-negative `base`, from `core` alone, since without `std`
+/// there's no `f32::powf` to call. Worked out as `exp(exponent * ln(base))` in `f64`, which
+/// keeps it within an `f32` rounding of `powf` for the pressure ratios passed in here.
+#[cfg(any(test, not(feature = "std")))]
+fn core_powf(base: f32, exponent: f32) -> f32 {
+    use core::f64::consts::{LN_2, SQRT_2};
+
+    if base.is_nan() || base < 0.0 {
+        return f32::NAN;
+    }
+    if base == 1.0 || exponent == 0.0 {
+        return 1.0;
+    }
+    if base == 0.0 || base.is_infinite() {
+        return if (exponent > 0.0) == (base == 0.0) {
+            0.0
+        } else {
+            f32::INFINITY
+        };
+    }
+
+    // ln(base) = e * ln(2) + ln(m) for base = m * 2^e, with m kept within [1/√2, √2] so the
+    // series for ln(m) = 2 * atanh((m - 1) / (m + 1)) converges within a few terms.
+    let bits = f64::from(base).to_bits();
+    let mut e = ((bits >> 52) & 0x7FF) as i64 - 1023;
+    let mut m = f64::from_bits((bits & ((1 << 52) - 1)) | (1023 << 52));
+    if m > SQRT_2 {
+        m /= 2.0;
+        e += 1;
+    }
+    let s = (m - 1.0) / (m + 1.0);
+    let mut term = s;
+    let mut ln_m = 0.0;
+    for k in 0..12 {
+        ln_m += term / f64::from(2 * k + 1);
+        term *= s * s;
+    }
+    let z = f64::from(exponent) * (e as f64 * LN_2 + 2.0 * ln_m);
+    if z > 128.0 * LN_2 {
+        return f32::INFINITY;
+    }
+    if z < -150.0 * LN_2 {
+        return 0.0;
+    }
+
+    // exp(z) = 2^k * exp(r) for z = k * ln(2) + r, with |r| at most ln(2) / 2.
+    let k = (z / LN_2 + if z < 0.0 { -0.5 } else { 0.5 }) as i64;
+    let r = z - k as f64 * LN_2;
+    let mut term = 1.0;
+    let mut exp_r = 1.0;
+    for n in 1..16 {
+        term *= r / f64::from(n);
+        exp_r += term;
+    }
+    (exp_r * f64::from_bits(((k + 1023) as u64) << 52)) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `(altitude in meters, pressure in pascals, temperature in °C)` in the standard
+    /// atmosphere.
+    const STANDARD_ATMOSPHERE: [(f32, f32, f32); 5] = [
+        (0.0, 101_325.0, 15.0),
+        (500.0, 95_460.8, 11.75),
+        (1_000.0, 89_874.6, 8.5),
+        (5_000.0, 54_019.9, -17.5),
+        (11_000.0, 22_632.1, -56.5),
+    ];
+
+    #[test]
+    fn test_altitude_matches_the_standard_atmosphere() {
+        for (altitude, pressure, temperature) in STANDARD_ATMOSPHERE {
+            let standard = pressure_altitude_meters(pressure, STANDARD_SEA_LEVEL_PRESSURE_PASCALS);
+            assert!(
+                (standard - altitude).abs() < 5.0,
+                "{altitude} m: {standard}"
+            );
+            let corrected = temperature_corrected_altitude_meters(
+                pressure,
+                STANDARD_SEA_LEVEL_PRESSURE_PASCALS,
+                temperature,
+            );
+            assert!(
+                (corrected - altitude).abs() < 5.0,
+                "{altitude} m: {corrected}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_core_powf_matches_powf() {
+        for base in [1e-6, 0.2, 0.5, 0.7, 0.9, 1.0, 1.001, 1.5, 4.0, 1e6] {
+            for exponent in [-3.0, -0.5, 0.0, 0.1903, 1.0, 2.5] {
+                let expected = f32::powf(base, exponent);
+                let got = core_powf(base, exponent);
+                assert!(
+                    (got - expected).abs() <= expected * 1e-6,
+                    "{base}^{exponent}: {got} != {expected}"
+                );
+            }
+        }
+        assert_eq!(core_powf(0.0, 0.1903), 0.0);
+        assert_eq!(core_powf(0.0, -1.0), f32::INFINITY);
+        assert_eq!(core_powf(f32::INFINITY, 0.1903), f32::INFINITY);
+        assert!(core_powf(-1.0, 0.1903).is_nan());
+        assert!(core_powf(f32::NAN, 0.1903).is_nan());
+    }
+
+    #[test]
+    fn test_reference_pressure_sets_the_zero() {
+        assert_eq!(pressure_altitude_meters(95_000.0, 95_000.0), 0.0);
+        assert_eq!(
+            temperature_corrected_altitude_meters(95_000.0, 95_000.0, 30.0),
+            0.0
+        );
+        // A higher sea level pressure puts the same reading higher up, and a reading above
+        // the reference pressure is below it.
+        assert!(pressure_altitude_meters(100_000.0, 102_000.0) > 100.0);
+        assert!(pressure_altitude_meters(102_000.0, 100_000.0) < -100.0);
+        // Warmer air is less dense, so the same pressure drop spans more height.
+        assert!(
+            temperature_corrected_altitude_meters(90_000.0, 100_000.0, 35.0)
+                > temperature_corrected_altitude_meters(90_000.0, 100_000.0, -5.0)
+        );
+    }
+}
@@ -0,0 +1,773 @@
+//! Streaming adapters for analysing packet streams, e.g. from `LogDecoder` or a live client.
+//!
+//! `windows_by_time` groups packets into fixed windows of device time and `resample` puts them
+//! on a regular time grid. Both only hold on to the packets they need for the next output, so
+//! they work on logs far larger than memory. `spectrogram` builds on `resample` to show the
+//! vibration frequencies on an accelerometer axis, skipping and counting packets whose axis
+//! reading is NaN or infinite, as `crate::non_finite` describes. Packets are expected in
+//! strictly increasing timestamp order; for a live stream, see `TimestampPolicy` in
+//! `data_parser`.
+//!
+//! ```
+//! use firm_core::analysis::{Interpolation, PacketStreamExt};
+//! use firm_core::firm_packets::FIRMData;
+//!
+//! let packets = (0..250).map(|i| FIRMData::from_fields(i as f64 * 0.01, [0.0; 27]));
+//! let windows: Vec<Vec<FIRMData>> = packets.clone().windows_by_time(1.0).collect();
+//! assert_eq!(windows.len(), 3);
+//!
+//! let grid: Vec<FIRMData> = packets.resample(50.0, Interpolation::Linear, 0.1).collect();
+//! assert_eq!(grid.len(), 125);
+//! ```
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::f64::consts::PI;
+
+use crate::firm_packets::FIRMData;
+use crate::non_finite::is_finite_sample;
+
+/// How `resample` fills grid points between two packets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Repeats the last packet at or before the grid point.
+    Hold,
+    /// Interpolates linearly between the packets either side of the grid point.
+    Linear,
+}
+
+/// Iterator adapters for streams of `FIRMData`.
+pub trait PacketStreamExt: Iterator<Item = FIRMData> + Sized {
+    /// Groups packets into windows of `duration_seconds` of device time, aligned to multiples
+    /// of the duration (so 1 s windows cover 0-1 s, 1-2 s, ...). Windows without packets are
+    /// skipped.
+    ///
+    /// # Arguments
+    ///
+    /// - `duration_seconds` (`f64`) - The window length. Panics unless it's positive.
+    ///
+    /// # Returns
+    ///
+    /// - `TimeWindows<Self>` - An iterator of non-empty windows, each in packet order.
+    fn windows_by_time(self, duration_seconds: f64) -> TimeWindows<Self> {
+        assert!(
+            duration_seconds > 0.0 && duration_seconds.is_finite(),
+            "window duration must be positive, got {duration_seconds}"
+        );
+        TimeWindows {
+            packets: self,
+            duration_seconds,
+            pending: None,
+        }
+    }
+
+    /// Resamples the stream onto a regular grid starting at the first packet's timestamp and
+    /// ending at or before the last one's.
+    ///
+    /// A grid point between two packets more than `max_gap_seconds` apart has every field set
+    /// to NaN, so dropouts show up as gaps instead of invented data. A field that's NaN or
+    /// infinite in a packet is interpolated like any other, so it stays non-finite at the grid
+    /// points next to that packet.
+    ///
+    /// # Arguments
+    ///
+    /// - `rate_hz` (`f64`) - The grid rate. Panics unless it's positive.
+    /// - `interpolation` (`Interpolation`) - How to fill grid points between packets.
+    /// - `max_gap_seconds` (`f64`) - Largest gap between packets to interpolate across.
+    ///
+    /// # Returns
+    ///
+    /// - `Resample<Self>` - An iterator of packets timestamped on the grid.
+    fn resample(
+        self,
+        rate_hz: f64,
+        interpolation: Interpolation,
+        max_gap_seconds: f64,
+    ) -> Resample<Self> {
+        assert!(
+            rate_hz > 0.0 && rate_hz.is_finite(),
+            "resample rate must be positive, got {rate_hz}"
+        );
+        Resample {
+            packets: self,
+            rate_hz,
+            interpolation,
+            max_gap_seconds,
+            start_seconds: 0.0,
+            index: 0,
+            previous: None,
+            next: None,
+        }
+    }
+
+    /// Computes short-time FFTs of one accelerometer axis, resampling the stream onto a
+    /// regular grid first so jittery timestamps don't smear the frequencies. Each window is
+    /// Hann-weighted. Packets whose reading on the axis isn't finite are skipped before
+    /// resampling, so the grid is interpolated across them, and counted in
+    /// `SpectrogramColumns::ignored_sample_count`.
+    ///
+    /// # Arguments
+    ///
+    /// - `options` (`SpectrogramOptions`) - The axis and windowing. Panics if they're invalid.
+    ///
+    /// # Returns
+    ///
+    /// - `SpectrogramColumns<Self>` - An iterator of one column per full window.
+    fn spectrogram(self, options: SpectrogramOptions) -> SpectrogramColumns<Self> {
+        options.validate();
+        let n = options.window_size;
+        let finite = FiniteAxisSamples {
+            packets: self,
+            axis: options.axis,
+            ignored_samples: 0,
+        };
+        SpectrogramColumns {
+            samples: finite.resample(
+                options.sample_rate_hz,
+                Interpolation::Linear,
+                options.max_gap_seconds,
+            ),
+            options,
+            window: VecDeque::with_capacity(n),
+            // The periodic Hann window, which overlaps evenly at 50%.
+            hann: (0..n)
+                .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f64 / n as f64).cos())
+                .collect(),
+        }
+    }
+}
+
+impl<I: Iterator<Item = FIRMData>> PacketStreamExt for I {}
+
+/// Iterator returned by `PacketStreamExt::windows_by_time`.
+pub struct TimeWindows<I> {
+    packets: I,
+    duration_seconds: f64,
+    /// First packet of the next window, read while finding the end of the current one.
+    pending: Option<FIRMData>,
+}
+
+/// Returns the index of the window of `duration_seconds` containing `timestamp_seconds`.
+fn window_index(timestamp_seconds: f64, duration_seconds: f64) -> i64 {
+    let position = timestamp_seconds / duration_seconds;
+    let index = position as i64;
+    // `as` truncates towards zero; step back for negative times to floor instead.
+    if (index as f64) > position {
+        index - 1
+    } else {
+        index
+    }
+}
+
+impl<I: Iterator<Item = FIRMData>> Iterator for TimeWindows<I> {
+    type Item = Vec<FIRMData>;
+
+    fn next(&mut self) -> Option<Vec<FIRMData>> {
+        let first = self.pending.take().or_else(|| self.packets.next())?;
+        let index = window_index(first.timestamp_seconds, self.duration_seconds);
+        let mut window = Vec::from([first]);
+        for packet in self.packets.by_ref() {
+            if window_index(packet.timestamp_seconds, self.duration_seconds) != index {
+                self.pending = Some(packet);
+                break;
+            }
+            window.push(packet);
+        }
+        Some(window)
+    }
+}
+
+/// Iterator returned by `PacketStreamExt::resample`.
+pub struct Resample<I> {
+    packets: I,
+    rate_hz: f64,
+    interpolation: Interpolation,
+    max_gap_seconds: f64,
+    start_seconds: f64,
+    /// Index of the next grid point.
+    index: u64,
+    /// The last packet at or before the next grid point, and the packet after it.
+    previous: Option<FIRMData>,
+    next: Option<FIRMData>,
+}
+
+impl<I: Iterator<Item = FIRMData>> Iterator for Resample<I> {
+    type Item = FIRMData;
+
+    fn next(&mut self) -> Option<FIRMData> {
+        if self.previous.is_none() {
+            if self.index > 0 {
+                return None;
+            }
+            let first = self.packets.next()?;
+            self.start_seconds = first.timestamp_seconds;
+            self.previous = Some(first);
+            self.next = self.packets.next();
+        }
+
+        // Grid points are computed from the start rather than accumulated, so they don't drift.
+        let t = self.start_seconds + self.index as f64 / self.rate_hz;
+        while let Some(next) = self.next.take() {
+            if next.timestamp_seconds > t {
+                self.next = Some(next);
+                break;
+            }
+            self.previous = Some(next);
+            self.next = self.packets.next();
+        }
+
+        let previous = self.previous.as_ref()?;
+        let sample = match &self.next {
+            _ if previous.timestamp_seconds == t => previous.clone(),
+            // Past the last packet.
+            None => return None,
+            Some(next) => interpolate(previous, next, t, self.interpolation, self.max_gap_seconds),
+        };
+        self.index += 1;
+        Some(FIRMData {
+            timestamp_seconds: t,
+            ..sample
+        })
+    }
+}
+
+/// Returns the packet at `t`, between `previous` and `next`.
+fn interpolate(
+    previous: &FIRMData,
+    next: &FIRMData,
+    t: f64,
+    interpolation: Interpolation,
+    max_gap_seconds: f64,
+) -> FIRMData {
+    let gap = next.timestamp_seconds - previous.timestamp_seconds;
+    if gap > max_gap_seconds {
+        let mut sample = FIRMData::from_fields(t, [f32::NAN; FIRMData::NUM_F32_FIELDS]);
+        sample.pressure_altitude_meters = f32::NAN;
+        return sample;
+    }
+    match interpolation {
+        Interpolation::Hold => previous.clone(),
+        Interpolation::Linear => {
+            let fraction = (t - previous.timestamp_seconds) / gap;
+            let lerp = |a: f32, b: f32| (a as f64 + (b as f64 - a as f64) * fraction) as f32;
+            let (a, b) = (previous.f32_fields(), next.f32_fields());
+            let mut sample = FIRMData::from_fields(t, core::array::from_fn(|i| lerp(a[i], b[i])));
+            sample.pressure_altitude_meters = lerp(
+                previous.pressure_altitude_meters,
+                next.pressure_altitude_meters,
+            );
+            sample
+        }
+    }
+}
+
+/// Which raw accelerometer axis `Spectrogram` analyses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccelAxis {
+    X,
+    Y,
+    Z,
+}
+
+impl AccelAxis {
+    fn value(self, packet: &FIRMData) -> f32 {
+        match self {
+            AccelAxis::X => packet.raw_acceleration_x_gs,
+            AccelAxis::Y => packet.raw_acceleration_y_gs,
+            AccelAxis::Z => packet.raw_acceleration_z_gs,
+        }
+    }
+}
+
+/// How `Spectrogram` splits the stream into short-time FFTs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpectrogramOptions {
+    pub axis: AccelAxis,
+    /// Rate the packets are resampled to before the FFT. Frequencies above half of it are lost.
+    pub sample_rate_hz: f64,
+    /// Samples per FFT. Must be a power of two.
+    pub window_size: usize,
+    /// Samples shared by consecutive windows. Must be less than `window_size`.
+    pub overlap: usize,
+    /// Largest gap between packets to interpolate across, see `PacketStreamExt::resample`.
+    pub max_gap_seconds: f64,
+}
+
+impl Default for SpectrogramOptions {
+    fn default() -> Self {
+        Self {
+            axis: AccelAxis::Z,
+            sample_rate_hz: 1000.0,
+            window_size: 256,
+            overlap: 128,
+            max_gap_seconds: 0.1,
+        }
+    }
+}
+
+impl SpectrogramOptions {
+    /// Returns the frequency of each magnitude bin, from 0 Hz up to half the sample rate.
+    pub fn frequencies_hz(&self) -> Vec<f64> {
+        let resolution = self.sample_rate_hz / self.window_size as f64;
+        (0..=self.window_size / 2)
+            .map(|k| k as f64 * resolution)
+            .collect()
+    }
+
+    fn validate(&self) {
+        assert!(
+            self.window_size >= 2 && self.window_size.is_power_of_two(),
+            "window size must be a power of two, got {}",
+            self.window_size
+        );
+        assert!(
+            self.overlap < self.window_size,
+            "overlap must be less than the window size, got {}",
+            self.overlap
+        );
+    }
+}
+
+/// One short-time FFT of a `Spectrogram`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpectrogramColumn {
+    /// Device time at the middle of the window.
+    pub time_seconds: f64,
+    /// Amplitude at each of `SpectrogramOptions::frequencies_hz`, in g. Bin 0 is the mean,
+    /// e.g. gravity. Every bin is NaN if the window spans a dropout.
+    pub magnitudes: Vec<f32>,
+}
+
+/// A spectrogram of one accelerometer axis, for finding vibration frequencies.
+///
+/// Magnitudes are stored row-major, one row of `frequencies_hz.len()` bins per time, so they
+/// can be handed to a plot or a typed array as they are.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Spectrogram {
+    pub times_seconds: Vec<f64>,
+    pub frequencies_hz: Vec<f64>,
+    pub magnitudes: Vec<f32>,
+    /// Packets skipped because their reading on the axis was NaN or infinite.
+    pub ignored_samples: usize,
+}
+
+impl Spectrogram {
+    /// Computes the spectrogram of a whole stream. For a live stream, use
+    /// `PacketStreamExt::spectrogram` to get columns as their windows fill.
+    ///
+    /// # Arguments
+    ///
+    /// - `packets` (`impl IntoIterator<Item = FIRMData>`) - The packets, in timestamp order.
+    /// - `options` (`&SpectrogramOptions`) - The axis and windowing. Panics if they're invalid.
+    ///
+    /// # Returns
+    ///
+    /// - `Spectrogram` - One column per full window; empty if the stream is shorter than one.
+    pub fn compute(
+        packets: impl IntoIterator<Item = FIRMData>,
+        options: &SpectrogramOptions,
+    ) -> Self {
+        let mut spectrogram = Spectrogram {
+            frequencies_hz: options.frequencies_hz(),
+            ..Default::default()
+        };
+        let mut columns = packets.into_iter().spectrogram(*options);
+        for column in columns.by_ref() {
+            spectrogram.times_seconds.push(column.time_seconds);
+            spectrogram.magnitudes.extend(column.magnitudes);
+        }
+        spectrogram.ignored_samples = columns.ignored_sample_count();
+        spectrogram
+    }
+
+    /// Returns the magnitudes of column `index`, or `None` if there's no such column.
+    pub fn column(&self, index: usize) -> Option<&[f32]> {
+        let bins = self.frequencies_hz.len();
+        self.magnitudes.get(index * bins..(index + 1) * bins)
+    }
+
+    /// Returns every bin as `(time_seconds, frequency_hz, magnitude)`, column by column.
+    pub fn bins(&self) -> impl Iterator<Item = (f64, f64, f32)> + '_ {
+        self.times_seconds
+            .iter()
+            .enumerate()
+            .flat_map(move |(i, &t)| {
+                self.frequencies_hz
+                    .iter()
+                    .zip(self.column(i).unwrap_or_default())
+                    .map(move |(&f, &m)| (t, f, m))
+            })
+    }
+}
+
+/// The packets of a stream whose reading on `axis` is finite, counting the rest.
+struct FiniteAxisSamples<I> {
+    packets: I,
+    axis: AccelAxis,
+    ignored_samples: usize,
+}
+
+impl<I: Iterator<Item = FIRMData>> Iterator for FiniteAxisSamples<I> {
+    type Item = FIRMData;
+
+    fn next(&mut self) -> Option<FIRMData> {
+        for packet in self.packets.by_ref() {
+            if is_finite_sample(&[self.axis.value(&packet)]) {
+                return Some(packet);
+            }
+            self.ignored_samples += 1;
+        }
+        None
+    }
+}
+
+/// Iterator returned by `PacketStreamExt::spectrogram`.
+pub struct SpectrogramColumns<I> {
+    samples: Resample<FiniteAxisSamples<I>>,
+    options: SpectrogramOptions,
+    /// The samples of the window being filled, as `(timestamp_seconds, value)`.
+    window: VecDeque<(f64, f32)>,
+    hann: Vec<f64>,
+}
+
+impl<I> SpectrogramColumns<I> {
+    /// Returns how many packets read so far were skipped because their reading on the axis
+    /// was NaN or infinite.
+    pub fn ignored_sample_count(&self) -> usize {
+        self.samples.packets.ignored_samples
+    }
+}
+
+impl<I: Iterator<Item = FIRMData>> Iterator for SpectrogramColumns<I> {
+    type Item = SpectrogramColumn;
+
+    fn next(&mut self) -> Option<SpectrogramColumn> {
+        while self.window.len() < self.options.window_size {
+            let sample = self.samples.next()?;
+            self.window
+                .push_back((sample.timestamp_seconds, self.options.axis.value(&sample)));
+        }
+
+        let n = self.options.window_size;
+        let time_seconds = (self.window[0].0 + self.window[n - 1].0) / 2.0;
+        let magnitudes = if self.window.iter().any(|(_, v)| v.is_nan()) {
+            alloc::vec![f32::NAN; n / 2 + 1]
+        } else {
+            let mut re: Vec<f64> = self
+                .window
+                .iter()
+                .zip(&self.hann)
+                .map(|((_, v), w)| *v as f64 * w)
+                .collect();
+            let mut im = alloc::vec![0.0; n];
+            fft(&mut re, &mut im);
+            // Scaled so a sine's bin reads its amplitude, undoing the window's attenuation.
+            let window_sum: f64 = self.hann.iter().sum();
+            (0..=n / 2)
+                .map(|k| {
+                    let scale = if k == 0 || k == n / 2 { 1.0 } else { 2.0 };
+                    (re[k].hypot(im[k]) * scale / window_sum) as f32
+                })
+                .collect()
+        };
+
+        self.window.drain(..n - self.options.overlap);
+        Some(SpectrogramColumn {
+            time_seconds,
+            magnitudes,
+        })
+    }
+}
+
+/// In-place radix-2 FFT. `re` and `im` must have the same power-of-two length.
+fn fft(re: &mut [f64], im: &mut [f64]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f64;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f64).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let (tr, ti) = (re[b] * cos - im[b] * sin, re[b] * sin + im[b] * cos);
+                re[b] = re[a] - tr;
+                im[b] = im[a] - ti;
+                re[a] += tr;
+                im[a] += ti;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Small deterministic generator so the random streams are reproducible.
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next_f64(&mut self) -> f64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (self.0 >> 11) as f64 / (1u64 << 53) as f64
+        }
+    }
+
+    /// A stream with jittery spacing, the occasional dropout and random field values.
+    fn random_stream(seed: u64) -> Vec<FIRMData> {
+        let mut rng = Lcg(seed);
+        let len = (rng.next_f64() * 300.0) as usize;
+        let mut t = rng.next_f64() * 10.0 - 5.0;
+        (0..len)
+            .map(|_| {
+                t += if rng.next_f64() < 0.05 {
+                    0.2 + rng.next_f64()
+                } else {
+                    0.001 + rng.next_f64() * 0.02
+                };
+                let mut packet = FIRMData::from_fields(
+                    t,
+                    core::array::from_fn(|_| (rng.next_f64() * 200.0 - 100.0) as f32),
+                );
+                packet.pressure_altitude_meters = rng.next_f64() as f32;
+                packet
+            })
+            .collect()
+    }
+
+    /// Compares packets bit for bit so NaN fields compare equal.
+    fn bits(packets: &[FIRMData]) -> Vec<(u64, [u32; FIRMData::NUM_F32_FIELDS], u32)> {
+        packets
+            .iter()
+            .map(|p| {
+                (
+                    p.timestamp_seconds.to_bits(),
+                    p.f32_fields().map(f32::to_bits),
+                    p.pressure_altitude_meters.to_bits(),
+                )
+            })
+            .collect()
+    }
+
+    fn naive_windows(packets: &[FIRMData], duration: f64) -> Vec<Vec<FIRMData>> {
+        let mut windows: Vec<Vec<FIRMData>> = Vec::new();
+        let mut last_index = None;
+        for packet in packets {
+            let index = (packet.timestamp_seconds / duration).floor() as i64;
+            if last_index != Some(index) {
+                windows.push(Vec::new());
+                last_index = Some(index);
+            }
+            windows.last_mut().unwrap().push(packet.clone());
+        }
+        windows
+    }
+
+    fn naive_resample(
+        packets: &[FIRMData],
+        rate_hz: f64,
+        interpolation: Interpolation,
+        max_gap: f64,
+    ) -> Vec<FIRMData> {
+        let (Some(first), Some(last)) = (packets.first(), packets.last()) else {
+            return Vec::new();
+        };
+        let mut out = Vec::new();
+        for k in 0.. {
+            let t = first.timestamp_seconds + k as f64 / rate_hz;
+            if t > last.timestamp_seconds {
+                break;
+            }
+            let i = packets
+                .iter()
+                .rposition(|p| p.timestamp_seconds <= t)
+                .unwrap();
+            let sample = if packets[i].timestamp_seconds == t {
+                packets[i].clone()
+            } else {
+                interpolate(&packets[i], &packets[i + 1], t, interpolation, max_gap)
+            };
+            out.push(FIRMData {
+                timestamp_seconds: t,
+                ..sample
+            });
+        }
+        out
+    }
+
+    #[test]
+    fn test_windows_match_naive_grouping() {
+        for seed in 0..200 {
+            let packets = random_stream(seed);
+            for duration in [0.05, 0.5, 1.0, 3.0] {
+                let windows: Vec<Vec<FIRMData>> =
+                    packets.iter().cloned().windows_by_time(duration).collect();
+                let expected = naive_windows(&packets, duration);
+                assert_eq!(windows.len(), expected.len(), "seed {seed}");
+                for (window, expected) in windows.iter().zip(&expected) {
+                    assert_eq!(bits(window), bits(expected), "seed {seed}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_resample_matches_naive_batch() {
+        for seed in 0..200 {
+            let packets = random_stream(seed);
+            for interpolation in [Interpolation::Hold, Interpolation::Linear] {
+                for rate_hz in [7.0, 100.0, 250.0] {
+                    let resampled: Vec<FIRMData> = packets
+                        .iter()
+                        .cloned()
+                        .resample(rate_hz, interpolation, 0.1)
+                        .collect();
+                    let expected = naive_resample(&packets, rate_hz, interpolation, 0.1);
+                    assert_eq!(bits(&resampled), bits(&expected), "seed {seed}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_resample_interpolates_and_marks_gaps() {
+        let packet = |t: f64, value: f32| {
+            let mut packet = FIRMData::from_fields(t, [0.0; FIRMData::NUM_F32_FIELDS]);
+            packet.pressure_pascals = value;
+            packet
+        };
+        let packets = [packet(0.0, 0.0), packet(0.1, 10.0), packet(1.0, 20.0)];
+        let pressures = |interpolation| -> Vec<f32> {
+            packets
+                .iter()
+                .cloned()
+                .resample(20.0, interpolation, 0.5)
+                .map(|p| p.pressure_pascals)
+                .collect()
+        };
+
+        let linear = pressures(Interpolation::Linear);
+        assert_eq!(linear.len(), 21);
+        assert_eq!(linear[..3], [0.0, 5.0, 10.0]);
+        assert!(linear[3..20].iter().all(|p| p.is_nan()));
+        assert_eq!(linear[20], 20.0);
+        assert_eq!(pressures(Interpolation::Hold)[..3], [0.0, 0.0, 10.0]);
+    }
+
+    /// Accelerometer Z samples of a 50 Hz sine of amplitude 1 g plus a 120 Hz one of 0.5 g,
+    /// at about 1 kHz with jittery timestamps.
+    fn vibration_stream(seconds: f64) -> Vec<FIRMData> {
+        let mut rng = Lcg(7);
+        let mut t = 0.0;
+        let mut packets = Vec::new();
+        while t < seconds {
+            let mut packet = FIRMData::from_fields(t, [0.0; FIRMData::NUM_F32_FIELDS]);
+            packet.raw_acceleration_z_gs =
+                ((2.0 * PI * 50.0 * t).sin() + 0.5 * (2.0 * PI * 120.0 * t).sin()) as f32;
+            packets.push(packet);
+            t += 0.0008 + rng.next_f64() * 0.0004;
+        }
+        packets
+    }
+
+    #[test]
+    fn test_spectrogram_finds_the_vibration_frequencies() {
+        let options = SpectrogramOptions::default();
+        let spectrogram = Spectrogram::compute(vibration_stream(2.0), &options);
+        let resolution = options.sample_rate_hz / options.window_size as f64;
+        assert!(spectrogram.times_seconds.len() > 10);
+        assert_eq!(
+            spectrogram.magnitudes.len(),
+            spectrogram.times_seconds.len() * spectrogram.frequencies_hz.len()
+        );
+
+        for i in 0..spectrogram.times_seconds.len() {
+            let column = spectrogram.column(i).unwrap();
+            // The two largest local maxima are the two tones, in amplitude order.
+            let mut peaks: Vec<usize> = (1..column.len() - 1)
+                .filter(|&k| column[k] > column[k - 1] && column[k] >= column[k + 1])
+                .collect();
+            peaks.sort_by(|&a, &b| column[b].total_cmp(&column[a]));
+            let [first, second] = [peaks[0], peaks[1]].map(|k| spectrogram.frequencies_hz[k]);
+            assert!((first - 50.0).abs() <= resolution, "{first} Hz");
+            assert!((second - 120.0).abs() <= resolution, "{second} Hz");
+            // Off-bin tones lose some height to the window, but keep roughly their ratio.
+            let ratio = column[peaks[1]] / column[peaks[0]];
+            assert!((0.3..0.7).contains(&ratio), "{ratio}");
+        }
+    }
+
+    #[test]
+    fn test_spectrogram_windows_overlap_and_mark_dropouts() {
+        let options = SpectrogramOptions {
+            window_size: 64,
+            overlap: 48,
+            max_gap_seconds: 0.01,
+            ..Default::default()
+        };
+        let packets: Vec<FIRMData> = (0..1000)
+            .filter(|i| !(400..450).contains(i))
+            .map(|i| FIRMData::from_fields(i as f64 / 1000.0, [0.0; FIRMData::NUM_F32_FIELDS]))
+            .collect();
+        let columns: Vec<SpectrogramColumn> = packets.into_iter().spectrogram(options).collect();
+
+        // 1000 resampled points, 64 per window, a new window every 16.
+        assert_eq!(columns.len(), (1000 - 64) / 16 + 1);
+        assert!((columns[0].time_seconds - 0.0315).abs() < 1e-9);
+        assert!((columns[1].time_seconds - columns[0].time_seconds - 0.016).abs() < 1e-9);
+        for column in &columns {
+            assert_eq!(column.magnitudes.len(), 33);
+            let (start, end) = (column.time_seconds - 0.0315, column.time_seconds + 0.0315);
+            // Grid points 0.400 to 0.449 fall in the dropout.
+            let spans_dropout = start < 0.4495 && end > 0.3995;
+            assert_eq!(
+                column.magnitudes.iter().all(|m| m.is_nan()),
+                spans_dropout,
+                "{}",
+                column.time_seconds
+            );
+        }
+    }
+
+    #[test]
+    fn test_spectrogram_skips_and_counts_non_finite_samples() {
+        let options = SpectrogramOptions::default();
+        let clean = Spectrogram::compute(vibration_stream(1.0), &options);
+        assert_eq!(clean.ignored_samples, 0);
+
+        let mut packets = vibration_stream(1.0);
+        for (i, value) in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY]
+            .into_iter()
+            .enumerate()
+        {
+            packets[200 + 300 * i].raw_acceleration_z_gs = value;
+        }
+        // A bad reading on another axis doesn't matter to this one.
+        packets[100].raw_acceleration_x_gs = f32::NAN;
+        let spectrogram = Spectrogram::compute(packets, &options);
+        assert_eq!(spectrogram.ignored_samples, 3);
+        assert_eq!(spectrogram.times_seconds, clean.times_seconds);
+        // Each bad sample is interpolated across rather than blanking its columns.
+        assert!(spectrogram.magnitudes.iter().all(|m| m.is_finite()));
+        for (a, b) in spectrogram.magnitudes.iter().zip(&clean.magnitudes) {
+            assert!((a - b).abs() < 0.05, "{a} vs {b}");
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use crate::firm_packets::FIRMData;
+use crate::non_finite::is_finite_sample;
+use alloc::vec::Vec;
 use nalgebra::{Matrix3, Vector3};
-use std::vec::Vec;
 
 /// Stores the result of a magnetometer calibration.
 #[derive(Debug, Clone, Copy)]
@@ -68,6 +69,8 @@ impl MagnetometerCalibration {
 pub struct MagnetometerCalibrator {
     /// Buffer of collected points (x, y, z).
     samples: Vec<Vector3<f32>>,
+    /// Number of samples skipped because they contained NaN or infinity.
+    ignored_samples: usize,
     /// Whether we are currently accepting new data points.
     is_collecting: bool,
 }
@@ -83,6 +86,7 @@ impl MagnetometerCalibrator {
     pub fn new() -> Self {
         Self {
             samples: Vec::new(),
+            ignored_samples: 0,
             is_collecting: false,
         }
     }
@@ -90,6 +94,7 @@ impl MagnetometerCalibrator {
     /// Starts the calibration process. Clears previous data.
     pub fn start(&mut self) {
         self.samples.clear();
+        self.ignored_samples = 0;
         self.is_collecting = true;
     }
 
@@ -100,19 +105,26 @@ impl MagnetometerCalibrator {
 
     /// Adds a data packet to the calibration buffer if collecting.
     pub fn add_sample(&mut self, data: &FIRMData) {
-        if self.is_collecting {
-            self.samples.push(Vector3::new(
-                data.magnetic_field_x_microteslas,
-                data.magnetic_field_y_microteslas,
-                data.magnetic_field_z_microteslas,
-            ));
-        }
+        self.add_sample_xyz(
+            data.magnetic_field_x_microteslas,
+            data.magnetic_field_y_microteslas,
+            data.magnetic_field_z_microteslas,
+        );
     }
 
     /// Adds a raw magnetometer sample (x, y, z) to the calibration buffer if collecting.
+    ///
+    /// Samples containing NaN or infinity (e.g. from a disabled sensor) are skipped and counted
+    /// in `ignored_sample_count`, following `crate::non_finite`, since a single one would
+    /// poison the whole fit.
     pub fn add_sample_xyz(&mut self, x: f32, y: f32, z: f32) {
-        if self.is_collecting {
+        if !self.is_collecting {
+            return;
+        }
+        if is_finite_sample(&[x, y, z]) {
             self.samples.push(Vector3::new(x, y, z));
+        } else {
+            self.ignored_samples += 1;
         }
     }
 
@@ -121,6 +133,11 @@ impl MagnetometerCalibrator {
         self.samples.len()
     }
 
+    /// Returns the number of samples skipped because they contained NaN or infinity.
+    pub fn ignored_sample_count(&self) -> usize {
+        self.ignored_samples
+    }
+
     /// Performs the math to solve for Hard Iron and Soft Iron parameters.
     ///
     /// This fits the equation: (x-c)' A (x-c) = 1
@@ -238,3 +255,25 @@ impl MagnetometerCalibrator {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_non_finite_samples_are_ignored() {
+        let mut calibrator = MagnetometerCalibrator::new();
+        calibrator.start();
+
+        calibrator.add_sample_xyz(1.0, 2.0, 3.0);
+        calibrator.add_sample_xyz(f32::NAN, 2.0, 3.0);
+        calibrator.add_sample_xyz(1.0, f32::INFINITY, 3.0);
+        calibrator.add_sample_xyz(1.0, 2.0, f32::NEG_INFINITY);
+
+        assert_eq!(calibrator.sample_count(), 1);
+        assert_eq!(calibrator.ignored_sample_count(), 3);
+
+        calibrator.start();
+        assert_eq!(calibrator.ignored_sample_count(), 0);
+    }
+}
@@ -1,31 +1,65 @@
 use alloc::vec::Vec;
 
 use crate::constants::command::{
-    CALIBRATION_OFFSETS_LENGTH, CALIBRATION_SCALE_MATRIX_LENGTH, DEVICE_NAME_LENGTH, FIRMCommand,
-    FREQUENCY_LENGTH, IMU_CALIBRATION_PAYLOAD_LENGTH, NUMBER_OF_CALIBRATION_OFFSETS,
-    NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS,
+    DEVICE_NAME_LENGTH, FIRMCommand, IMU_CALIBRATION_PAYLOAD_LENGTH,
+    MAGNETOMETER_CALIBRATION_PAYLOAD_LENGTH, NUMBER_OF_CALIBRATION_OFFSETS,
+    NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS, SET_DEVICE_CONFIG_PAYLOAD_LENGTH,
 };
 use crate::constants::log_parsing::FIRMLogPacketType;
 use crate::constants::packet::PacketHeader;
 use crate::{
     firm_packets::*,
-    framed_packet::{Framed, FramedPacket},
+    framed_packet::{FrameError, Framed, FramedPacket},
     utils::str_to_bytes,
 };
 
+/// Converts a dynamically sized slice (e.g. from Python or JS) into a fixed-size calibration
+/// array, returning `FrameError::LengthMismatch` if it has the wrong number of elements.
+pub fn calibration_array<const N: usize>(values: &[f32]) -> Result<[f32; N], FrameError> {
+    values.try_into().map_err(|_| FrameError::LengthMismatch {
+        expected: N,
+        got: values.len(),
+    })
+}
+
 pub struct FIRMCommandPacket {
     command_type: FIRMCommand,
     frame: FramedPacket,
 }
 
 impl FIRMCommandPacket {
+    /// Creates a command packet. Payloads longer than the command's maximum are a programming
+    /// error here; use `try_new` for payloads whose size is only known at runtime.
     pub fn new(command_type: FIRMCommand, payload: Vec<u8>) -> Self {
-        let header = PacketHeader::Command;
-        let identifier = command_type as u16;
+        debug_assert!(payload.len() <= command_type.max_payload_length());
+        let frame = FramedPacket::new_checked(PacketHeader::Command, command_type as u16, payload)
+            .expect("every FIRMCommand is a command identifier");
         Self {
             command_type,
-            frame: FramedPacket::new(header, identifier, payload),
+            frame,
+        }
+    }
+
+    /// Creates a command packet, rejecting payloads longer than the firmware accepts.
+    ///
+    /// # Arguments
+    ///
+    /// - `command_type` (`FIRMCommand`) - The command to send.
+    /// - `payload` (`Vec<u8>`) - The raw command payload.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<Self, FrameError>` - `FrameError::PayloadTooLarge` if the payload exceeds
+    ///   `command_type.max_payload_length()`.
+    pub fn try_new(command_type: FIRMCommand, payload: Vec<u8>) -> Result<Self, FrameError> {
+        let max = command_type.max_payload_length();
+        if payload.len() > max {
+            return Err(FrameError::PayloadTooLarge {
+                max,
+                got: payload.len(),
+            });
         }
+        Ok(Self::new(command_type, payload))
     }
 
     pub fn command_type(&self) -> FIRMCommand {
@@ -53,7 +87,7 @@ impl FIRMCommandPacket {
     }
 
     pub fn build_set_device_config_command(config: DeviceConfig) -> Self {
-        let mut payload = Vec::with_capacity(DEVICE_NAME_LENGTH + FREQUENCY_LENGTH + 1);
+        let mut payload = Vec::with_capacity(SET_DEVICE_CONFIG_PAYLOAD_LENGTH);
         let name_bytes = str_to_bytes::<DEVICE_NAME_LENGTH>(&config.name);
         payload.extend_from_slice(&name_bytes);
         payload.extend_from_slice(&config.frequency.to_le_bytes());
@@ -67,8 +101,7 @@ impl FIRMCommandPacket {
         offsets: [f32; NUMBER_OF_CALIBRATION_OFFSETS],
         scale_matrix: [f32; NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS],
     ) -> Self {
-        let mut payload =
-            Vec::with_capacity(CALIBRATION_OFFSETS_LENGTH + CALIBRATION_SCALE_MATRIX_LENGTH);
+        let mut payload = Vec::with_capacity(MAGNETOMETER_CALIBRATION_PAYLOAD_LENGTH);
         for offset in &offsets {
             payload.extend_from_slice(&offset.to_le_bytes());
         }
@@ -104,6 +137,40 @@ impl FIRMCommandPacket {
         Self::new(FIRMCommand::SetIMUCalibration, payload)
     }
 
+    /// Builds a magnetometer calibration command from dynamically sized slices.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<Self, FrameError>` - `FrameError::LengthMismatch` if a slice has the wrong length.
+    pub fn try_build_set_magnetometer_calibration_command(
+        offsets: &[f32],
+        scale_matrix: &[f32],
+    ) -> Result<Self, FrameError> {
+        Ok(Self::build_set_magnetometer_calibration_command(
+            calibration_array(offsets)?,
+            calibration_array(scale_matrix)?,
+        ))
+    }
+
+    /// Builds an IMU calibration command from dynamically sized slices.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<Self, FrameError>` - `FrameError::LengthMismatch` if a slice has the wrong length.
+    pub fn try_build_set_imu_calibration_command(
+        accel_offsets: &[f32],
+        accel_scale_matrix: &[f32],
+        gyro_offsets: &[f32],
+        gyro_scale_matrix: &[f32],
+    ) -> Result<Self, FrameError> {
+        Ok(Self::build_set_imu_calibration_command(
+            calibration_array(accel_offsets)?,
+            calibration_array(accel_scale_matrix)?,
+            calibration_array(gyro_offsets)?,
+            calibration_array(gyro_scale_matrix)?,
+        ))
+    }
+
     pub fn build_get_calibration_command() -> Self {
         Self::new(FIRMCommand::GetCalibration, Vec::new())
     }
@@ -133,12 +200,26 @@ pub struct FIRMLogPacket {
 
 impl FIRMLogPacket {
     pub fn new(packet_type: FIRMLogPacketType, payload: Vec<u8>) -> Self {
-        let header = PacketHeader::LogSensor;
-        let identifier = packet_type as u16;
-        Self {
-            packet_type,
-            frame: FramedPacket::new(header, identifier, payload),
+        let frame = FramedPacket::new_checked(PacketHeader::LogSensor, packet_type as u16, payload)
+            .expect("every FIRMLogPacketType is a log sensor identifier");
+        Self { packet_type, frame }
+    }
+
+    /// Creates a log packet, rejecting payloads longer than the firmware accepts for its type.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<Self, FrameError>` - `FrameError::PayloadTooLarge` if the payload exceeds
+    ///   `packet_type.max_payload_length()`.
+    pub fn try_new(packet_type: FIRMLogPacketType, payload: Vec<u8>) -> Result<Self, FrameError> {
+        let max = packet_type.max_payload_length();
+        if payload.len() > max {
+            return Err(FrameError::PayloadTooLarge {
+                max,
+                got: payload.len(),
+            });
         }
+        Ok(Self::new(packet_type, payload))
     }
 
     pub fn packet_type(&self) -> FIRMLogPacketType {
@@ -165,11 +246,14 @@ mod tests {
     use super::{FIRMCommandPacket, FIRMLogPacket};
     use crate::constants::command::{
         CRC_LENGTH, DEVICE_NAME_LENGTH, FIRMCommand, FREQUENCY_LENGTH,
-        IMU_CALIBRATION_PAYLOAD_LENGTH,
+        IMU_CALIBRATION_PAYLOAD_LENGTH, MAGNETOMETER_CALIBRATION_PAYLOAD_LENGTH,
+    };
+    use crate::constants::log_parsing::{
+        FIRMLogPacketType, HEADER_TOTAL_SIZE, MAX_SENSOR_LOG_PACKET_PAYLOAD_LENGTH,
     };
-    use crate::constants::log_parsing::FIRMLogPacketType;
     use crate::constants::packet::PacketHeader;
     use crate::firm_packets::{DeviceConfig, DeviceProtocol};
+    use crate::framed_packet::FrameError;
     use crate::framed_packet::Framed;
     use crate::utils::{crc16_ccitt, str_to_bytes};
 
@@ -211,12 +295,14 @@ mod tests {
             u32::from_le_bytes(command_packet[4..8].try_into().unwrap()),
             0
         );
-        assert_eq!(command_packet.len(), 4 + 4 + 0 + CRC_LENGTH);
+        assert_eq!(command_packet.len(), 4 + 4 + CRC_LENGTH);
     }
 
+    type CommandBuilder = fn() -> FIRMCommandPacket;
+
     #[test]
     fn test_firm_command_packet_to_bytes_zero_payload_commands() {
-        let cases: &[(u16, fn() -> FIRMCommandPacket)] = &[
+        let cases: &[(u16, CommandBuilder)] = &[
             (
                 FIRMCommand::GetDeviceInfo as u16,
                 FIRMCommandPacket::build_get_device_info_command,
@@ -250,6 +336,7 @@ mod tests {
             name: "FIRM".to_string(),
             frequency: 50,
             protocol: DeviceProtocol::UART,
+            suspect: false,
         };
 
         let command_packet =
@@ -313,20 +400,92 @@ mod tests {
         let payload = &command_packet[8..8 + payload_len];
 
         // Layout: [accel offsets 3][accel matrix 9][gyro offsets 3][gyro matrix 9]
-        for i in 0..3 {
-            assert_eq!(f32_from_payload(payload, i), accel_offsets[i]);
+        for (i, value) in accel_offsets.iter().enumerate() {
+            assert_eq!(f32_from_payload(payload, i), *value);
         }
-        for i in 0..9 {
-            assert_eq!(f32_from_payload(payload, 3 + i), accel_matrix[i]);
+        for (i, value) in accel_matrix.iter().enumerate() {
+            assert_eq!(f32_from_payload(payload, 3 + i), *value);
         }
-        for i in 0..3 {
-            assert_eq!(f32_from_payload(payload, 3 + 9 + i), gyro_offsets[i]);
+        for (i, value) in gyro_offsets.iter().enumerate() {
+            assert_eq!(f32_from_payload(payload, 3 + 9 + i), *value);
         }
-        for i in 0..9 {
-            assert_eq!(f32_from_payload(payload, 3 + 9 + 3 + i), gyro_matrix[i]);
+        for (i, value) in gyro_matrix.iter().enumerate() {
+            assert_eq!(f32_from_payload(payload, 3 + 9 + 3 + i), *value);
         }
     }
 
+    #[test]
+    fn test_command_try_new_payload_limits() {
+        let cases = [
+            (FIRMCommand::GetDeviceInfo, 0),
+            (
+                FIRMCommand::SetDeviceConfig,
+                DEVICE_NAME_LENGTH + FREQUENCY_LENGTH + 1,
+            ),
+            (
+                FIRMCommand::SetMagnetometerCalibration,
+                MAGNETOMETER_CALIBRATION_PAYLOAD_LENGTH,
+            ),
+            (
+                FIRMCommand::SetIMUCalibration,
+                IMU_CALIBRATION_PAYLOAD_LENGTH,
+            ),
+        ];
+
+        for (command, max) in cases {
+            assert_eq!(command.max_payload_length(), max);
+            assert!(FIRMCommandPacket::try_new(command, vec![0u8; max]).is_ok());
+            assert_eq!(
+                FIRMCommandPacket::try_new(command, vec![0u8; max + 1]).err(),
+                Some(FrameError::PayloadTooLarge { max, got: max + 1 })
+            );
+        }
+    }
+
+    #[test]
+    fn test_try_build_calibration_commands_check_lengths() {
+        assert!(
+            FIRMCommandPacket::try_build_set_magnetometer_calibration_command(&[0.0; 3], &[0.0; 9])
+                .is_ok()
+        );
+        assert_eq!(
+            FIRMCommandPacket::try_build_set_magnetometer_calibration_command(&[0.0; 2], &[0.0; 9])
+                .err(),
+            Some(FrameError::LengthMismatch {
+                expected: 3,
+                got: 2
+            })
+        );
+        assert_eq!(
+            FIRMCommandPacket::try_build_set_imu_calibration_command(
+                &[0.0; 3], &[0.0; 9], &[0.0; 3], &[0.0; 10]
+            )
+            .err(),
+            Some(FrameError::LengthMismatch {
+                expected: 9,
+                got: 10
+            })
+        );
+    }
+
+    #[test]
+    fn test_log_packet_try_new_payload_limits() {
+        let max = FIRMLogPacketType::IMUPacket.max_payload_length();
+        assert_eq!(max, MAX_SENSOR_LOG_PACKET_PAYLOAD_LENGTH);
+        assert!(FIRMLogPacket::try_new(FIRMLogPacketType::IMUPacket, vec![0u8; max]).is_ok());
+        assert_eq!(
+            FIRMLogPacket::try_new(FIRMLogPacketType::IMUPacket, vec![0u8; max + 1]).err(),
+            Some(FrameError::PayloadTooLarge { max, got: max + 1 })
+        );
+        assert!(
+            FIRMLogPacket::try_new(
+                FIRMLogPacketType::HeaderPacket,
+                vec![0u8; HEADER_TOTAL_SIZE]
+            )
+            .is_ok()
+        );
+    }
+
     #[test]
     fn test_firm_mock_packet_new() {
         let payload = vec![1u8, 2, 3];
@@ -349,7 +508,9 @@ mod tests {
             packet.len()
         );
         assert_eq!(
-            u16::from_le_bytes(bytes[bytes.len() - 2..].try_into().unwrap()),
+            u32::from(u16::from_le_bytes(
+                bytes[bytes.len() - 2..].try_into().unwrap()
+            )),
             packet.crc()
         );
         assert_eq!(&bytes[8..bytes.len() - 2], packet.payload());
@@ -0,0 +1,343 @@
+//! Consistent Overhead Byte Stuffing, an optional wire format that takes every `0x00` out of a
+//! frame so that one can mark where each frame ends, see `WireFormat::Cobs`.
+//!
+//! In the raw format a frame is only found by its start word and its length, so a corrupted
+//! length can hold the parser up for bytes that belong to the frames after it, and a start
+//! word inside a payload can pass for a frame. A COBS frame ends at the next `0x00` whatever
+//! happened to its bytes, so a damaged frame costs nothing but itself.
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+use crate::{
+    constants::packet::MIN_PACKET_SIZE,
+    data_parser::SerialParser,
+    framed_packet::{FrameError, FramedPacket},
+};
+
+/// Ends every COBS frame. It never appears inside one.
+pub const COBS_DELIMITER: u8 = 0x00;
+
+/// Longest run of non-zero bytes one code byte can cover.
+const MAX_RUN: usize = 254;
+
+/// Returns the most bytes `cobs_encode` can write for `len` input bytes, not counting the
+/// delimiter.
+pub fn cobs_max_encoded_len(len: usize) -> usize {
+    len + len / MAX_RUN + 1
+}
+
+/// Encodes `bytes` with COBS, without the delimiter.
+///
+/// # Arguments
+///
+/// - `bytes` (`&[u8]`) - The bytes to encode, e.g. a frame from `FramedPacket::to_bytes`.
+///
+/// # Returns
+///
+/// - `Vec<u8>` - The encoded bytes, none of them `COBS_DELIMITER`.
+pub fn cobs_encode(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(cobs_max_encoded_len(bytes.len()));
+    cobs_encode_into(bytes, &mut out);
+    out
+}
+
+/// Like `cobs_encode`, appending to `out` so its allocation can be reused.
+pub fn cobs_encode_into(bytes: &[u8], out: &mut Vec<u8>) {
+    let mut code_index = out.len();
+    out.push(0);
+    let mut run = 0;
+    for &byte in bytes {
+        if byte != 0 {
+            out.push(byte);
+            run += 1;
+            if run < MAX_RUN {
+                continue;
+            }
+        }
+        // A zero, or a run as long as a code byte can cover, closes the block.
+        out[code_index] = run as u8 + 1;
+        code_index = out.len();
+        out.push(0);
+        run = 0;
+    }
+    out[code_index] = run as u8 + 1;
+}
+
+/// Decodes one COBS frame, without its delimiter.
+///
+/// # Arguments
+///
+/// - `encoded` (`&[u8]`) - The encoded bytes.
+///
+/// # Returns
+///
+/// - `Result<Vec<u8>, FrameError>` - The decoded bytes, or `FrameError::BadEncoding` if a code
+///   byte is zero or runs past the end of `encoded`, or a zero appears inside a block.
+pub fn cobs_decode(encoded: &[u8]) -> Result<Vec<u8>, FrameError> {
+    let mut out = Vec::with_capacity(encoded.len());
+    cobs_decode_into(encoded, &mut out)?;
+    Ok(out)
+}
+
+/// Like `cobs_decode`, appending to `out` so its allocation can be reused. On an error, `out`
+/// holds whatever was decoded before it.
+pub fn cobs_decode_into(encoded: &[u8], out: &mut Vec<u8>) -> Result<(), FrameError> {
+    let mut rest = encoded;
+    while let Some((&code, tail)) = rest.split_first() {
+        let run = usize::from(code)
+            .checked_sub(1)
+            .filter(|&run| run <= tail.len())
+            .ok_or(FrameError::BadEncoding)?;
+        let (block, tail) = tail.split_at(run);
+        if block.contains(&COBS_DELIMITER) {
+            return Err(FrameError::BadEncoding);
+        }
+        out.extend_from_slice(block);
+        rest = tail;
+        // Every block but a full one stands for a zero after it, except the last.
+        if usize::from(code) <= MAX_RUN && !rest.is_empty() {
+            out.push(0);
+        }
+    }
+    Ok(())
+}
+
+/// Splits a COBS stream at each `COBS_DELIMITER`, decodes each frame and checks that it holds
+/// exactly one valid `FramedPacket`.
+///
+/// A frame that doesn't decode, fails its CRC or has bytes left over is reported as an error
+/// from `next_frame`, and the next frame is found at the next delimiter. Empty frames, e.g. a
+/// delimiter sent before the first frame to flush the line, are skipped.
+#[derive(Debug, Clone)]
+pub struct CobsFrameScanner {
+    /// Encoded bytes of the unfinished frame, empty once it's too long to keep.
+    encoded: Vec<u8>,
+    /// Where frames are decoded, kept to reuse its allocation.
+    decoded: Vec<u8>,
+    /// Encoded bytes seen of the unfinished frame, including any too many to keep.
+    frame_len: usize,
+    max_frame_len: usize,
+    frames: VecDeque<Result<FramedPacket, FrameError>>,
+}
+
+impl Default for CobsFrameScanner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CobsFrameScanner {
+    /// Creates a scanner that keeps frames of up to `SerialParser::DEFAULT_MAX_BUFFER_LEN`
+    /// decoded bytes.
+    pub fn new() -> Self {
+        Self {
+            encoded: Vec::new(),
+            decoded: Vec::new(),
+            frame_len: 0,
+            max_frame_len: SerialParser::DEFAULT_MAX_BUFFER_LEN,
+            frames: VecDeque::new(),
+        }
+    }
+
+    /// Sets the longest frame, decoded, the scanner keeps. The bytes of a longer one are
+    /// dropped as they arrive, and it's reported as `FrameError::PayloadTooLarge` with `got`
+    /// counting its encoded bytes.
+    ///
+    /// # Arguments
+    ///
+    /// - `max_frame_len` (`usize`) - The limit in bytes, at least `MIN_PACKET_SIZE`.
+    pub fn set_max_frame_len(&mut self, max_frame_len: usize) {
+        self.max_frame_len = max_frame_len.max(MIN_PACKET_SIZE);
+    }
+
+    /// Returns the limit set by `set_max_frame_len`.
+    pub fn max_frame_len(&self) -> usize {
+        self.max_frame_len
+    }
+
+    /// Appends `bytes` to the stream and queues what each frame they finish holds.
+    pub fn push(&mut self, bytes: &[u8]) {
+        let mut frames = core::mem::take(&mut self.frames);
+        self.split(bytes, |_, _, decoded| {
+            frames.push_back(decoded.and_then(FramedPacket::from_bytes));
+        });
+        self.frames = frames;
+    }
+
+    /// Pops the oldest finished frame, or why it was rejected.
+    pub fn next_frame(&mut self) -> Option<Result<FramedPacket, FrameError>> {
+        self.frames.pop_front()
+    }
+
+    /// Returns how many encoded bytes are buffered waiting for the rest of a frame.
+    pub fn buffered_len(&self) -> usize {
+        self.encoded.len()
+    }
+
+    /// Returns the encoded bytes buffered waiting for the rest of a frame.
+    pub(crate) fn buffered(&self) -> &[u8] {
+        &self.encoded
+    }
+
+    /// Drops the unfinished frame, returning how many of its bytes were buffered.
+    pub(crate) fn clear(&mut self) -> usize {
+        let buffered = self.encoded.len();
+        self.encoded.clear();
+        self.frame_len = 0;
+        buffered
+    }
+
+    /// Appends `bytes` to the stream and calls `on_frame` with each non-empty frame they
+    /// finish: the index in `bytes` just past its delimiter, its length on the wire including
+    /// the delimiter, and its decoded bytes or why they couldn't be decoded.
+    pub(crate) fn split(
+        &mut self,
+        bytes: &[u8],
+        mut on_frame: impl FnMut(usize, usize, Result<&[u8], FrameError>),
+    ) {
+        let mut rest = bytes;
+        while let Some(delimiter) = rest.iter().position(|&byte| byte == COBS_DELIMITER) {
+            self.extend(&rest[..delimiter]);
+            rest = &rest[delimiter + 1..];
+            if self.frame_len == 0 {
+                continue;
+            }
+            let end = bytes.len() - rest.len();
+            let wire_len = self.frame_len + 1;
+            let too_large = FrameError::PayloadTooLarge {
+                max: self.max_frame_len - MIN_PACKET_SIZE,
+                got: self.frame_len.saturating_sub(MIN_PACKET_SIZE),
+            };
+            let decoded = if self.encoded.len() < self.frame_len {
+                Err(too_large)
+            } else {
+                self.decoded.clear();
+                match cobs_decode_into(&self.encoded, &mut self.decoded) {
+                    Ok(()) if self.decoded.len() > self.max_frame_len => Err(too_large),
+                    Ok(()) => Ok(self.decoded.as_slice()),
+                    Err(error) => Err(error),
+                }
+            };
+            on_frame(end, wire_len, decoded);
+            self.clear();
+        }
+        self.extend(rest);
+    }
+
+    /// Appends encoded bytes to the unfinished frame, or only counts them once it's too long.
+    fn extend(&mut self, bytes: &[u8]) {
+        self.frame_len += bytes.len();
+        if self.frame_len <= cobs_max_encoded_len(self.max_frame_len) {
+            self.encoded.extend_from_slice(bytes);
+        } else {
+            self.encoded.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::packet::PacketHeader;
+    use alloc::vec;
+
+    #[test]
+    fn test_encoding_matches_the_reference_examples() {
+        let examples: [(&[u8], &[u8]); 6] = [
+            (&[], &[0x01]),
+            (&[0x00], &[0x01, 0x01]),
+            (&[0x00, 0x00], &[0x01, 0x01, 0x01]),
+            (&[0x11, 0x22, 0x00, 0x33], &[0x03, 0x11, 0x22, 0x02, 0x33]),
+            (&[0x11, 0x22, 0x33, 0x44], &[0x05, 0x11, 0x22, 0x33, 0x44]),
+            (&[0x11, 0x00, 0x00, 0x00], &[0x02, 0x11, 0x01, 0x01, 0x01]),
+        ];
+        for (decoded, encoded) in examples {
+            assert_eq!(cobs_encode(decoded), encoded);
+            assert_eq!(cobs_decode(encoded).as_deref(), Ok(decoded));
+        }
+    }
+
+    #[test]
+    fn test_round_trips_around_the_longest_run() {
+        for len in [1, 253, 254, 255, 508, 509, 1000] {
+            let ones = vec![0x01u8; len];
+            let mixed: Vec<u8> = (0..len).map(|i| (i % 7) as u8).collect();
+            for bytes in [ones, mixed] {
+                let encoded = cobs_encode(&bytes);
+                assert!(!encoded.contains(&COBS_DELIMITER));
+                assert!(encoded.len() <= cobs_max_encoded_len(len), "{len}");
+                assert_eq!(cobs_decode(&encoded), Ok(bytes));
+            }
+        }
+    }
+
+    #[test]
+    fn test_malformed_encodings_are_rejected() {
+        for encoded in [
+            &[0x00][..],
+            &[0x03, 0x11],
+            &[0x03, 0x11, 0x00],
+            &[0x02, 0x11, 0x05],
+        ] {
+            assert_eq!(
+                cobs_decode(encoded),
+                Err(FrameError::BadEncoding),
+                "{encoded:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_scanner_finds_frames_at_any_split_and_skips_damaged_ones() {
+        let frames: Vec<FramedPacket> = (0..4)
+            .map(|i| FramedPacket::new(PacketHeader::Data, i, vec![0, i as u8, 0, 0xA5]))
+            .collect();
+        let mut stream = vec![COBS_DELIMITER];
+        for (i, frame) in frames.iter().enumerate() {
+            let mut bytes = frame.to_bytes();
+            if i == 1 {
+                bytes[11] ^= 0x40;
+            }
+            cobs_encode_into(&bytes, &mut stream);
+            stream.push(COBS_DELIMITER);
+        }
+
+        for chunk_len in [1, 5, stream.len()] {
+            let mut scanner = CobsFrameScanner::new();
+            let mut found = Vec::new();
+            for chunk in stream.chunks(chunk_len) {
+                scanner.push(chunk);
+                found.extend(core::iter::from_fn(|| scanner.next_frame()));
+            }
+            assert_eq!(found.len(), 4, "{chunk_len}");
+            assert_eq!(found[0], Ok(frames[0].clone()));
+            assert!(matches!(found[1], Err(FrameError::BadCrc { .. })));
+            assert_eq!(found[2..], [Ok(frames[2].clone()), Ok(frames[3].clone())]);
+            assert_eq!(scanner.buffered_len(), 0);
+        }
+    }
+
+    #[test]
+    fn test_scanner_drops_frames_longer_than_its_limit() {
+        let long = FramedPacket::new(PacketHeader::Data, 0, vec![1; 200]);
+        let short = FramedPacket::new(PacketHeader::Data, 1, vec![2; 4]);
+        let mut scanner = CobsFrameScanner::new();
+        scanner.set_max_frame_len(64);
+
+        let encoded = long.to_cobs_bytes();
+        for chunk in encoded.chunks(16) {
+            scanner.push(chunk);
+            assert!(scanner.buffered_len() <= cobs_max_encoded_len(64));
+        }
+        scanner.push(&short.to_cobs_bytes());
+        assert_eq!(
+            scanner.next_frame(),
+            Some(Err(FrameError::PayloadTooLarge {
+                max: 64 - MIN_PACKET_SIZE,
+                got: encoded.len() - 1 - MIN_PACKET_SIZE,
+            }))
+        );
+        assert_eq!(scanner.next_frame(), Some(Ok(short)));
+        assert_eq!(scanner.next_frame(), None);
+    }
+}
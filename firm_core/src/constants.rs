@@ -1,20 +1,97 @@
+//! Wire protocol constants.
+//!
+//! `PROTOCOL` is the one place each value is written down. The named constants below are
+//! defined from it, and copies that have to stay literal (e.g. for cbindgen) are checked
+//! against it with static assertions, so editing a value in one place only fails the build.
+
+/// The values that several modules and frontends need to agree on, see `PROTOCOL`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProtocolTable {
+    pub data_header: u16,
+    pub response_header: u16,
+    pub log_sensor_header: u16,
+    pub command_header: u16,
+    pub header_size: usize,
+    pub identifier_size: usize,
+    pub length_size: usize,
+    pub crc_size: usize,
+    pub device_name_length: usize,
+    pub device_id_length: usize,
+    pub firmware_version_length: usize,
+    pub frequency_length: usize,
+    /// Size of the firmware's command receive buffer, which holds a whole command frame. It is
+    /// `COMMAND_BUFFER_PACKETS` packets of `USB_FULL_SPEED_BULK_PACKET_SIZE` bytes.
+    pub command_buffer_size: usize,
+}
+
+/// Largest packet a USB full-speed bulk endpoint carries (USB 2.0 specification, section
+/// 5.8.3). The device's serial endpoint hands each received packet to the command reader.
+pub const USB_FULL_SPEED_BULK_PACKET_SIZE: usize = 64;
+
+/// How many USB packets the firmware's command receive buffer holds. A frame has to arrive
+/// whole within them before the device parses it. The firmware source isn't part of this
+/// repository, so if the device's buffer changes, this is the value to update.
+pub const COMMAND_BUFFER_PACKETS: usize = 2;
+
+/// The authoritative protocol table.
+pub const PROTOCOL: ProtocolTable = ProtocolTable {
+    data_header: 0xA55A,
+    response_header: 0x5AA5,
+    log_sensor_header: 0x6BB6,
+    command_header: 0xB66B,
+    header_size: 2,
+    identifier_size: 2,
+    length_size: 4,
+    crc_size: 2,
+    device_name_length: 32,
+    device_id_length: 8,
+    firmware_version_length: 8,
+    frequency_length: 2,
+    command_buffer_size: COMMAND_BUFFER_PACKETS * USB_FULL_SPEED_BULK_PACKET_SIZE,
+};
+
 pub mod packet {
+    use super::PROTOCOL;
+    use super::command::FIRMCommand;
+    use super::log_parsing::FIRMLogPacketType;
+
     /// Header is stored as two little-endian u16s on the wire.
-    pub const HEADER_SIZE: usize = 2;
-    pub const IDENTIFIER_SIZE: usize = 2;
-    pub const LENGTH_SIZE: usize = 4;
-    pub const CRC_SIZE: usize = 2;
+    pub const HEADER_SIZE: usize = PROTOCOL.header_size;
+    pub const IDENTIFIER_SIZE: usize = PROTOCOL.identifier_size;
+    pub const LENGTH_SIZE: usize = PROTOCOL.length_size;
+    pub const CRC_SIZE: usize = PROTOCOL.crc_size;
 
     pub const MIN_PACKET_SIZE: usize = HEADER_SIZE + IDENTIFIER_SIZE + LENGTH_SIZE + CRC_SIZE;
 
+    /// Set in a frame's identifier field when a `u16` sequence number follows the length, so
+    /// identifiers themselves only use the low 15 bits. Frames without it carry no sequence
+    /// number, as all firmware before sequence numbers sends.
+    pub const SEQUENCE_FLAG: u16 = 0x8000;
+    /// Size of the sequence number in frames with `SEQUENCE_FLAG` set.
+    pub const SEQUENCE_SIZE: usize = 2;
+
+    /// Identifier of the full telemetry data packet, `FIRMData`.
+    pub const DATA_PACKET_IDENTIFIER: u16 = 0x0000;
+    /// Identifier of the compact secondary barometer data packet, `FIRMBaroPacket`.
+    pub const BARO_PACKET_IDENTIFIER: u16 = 0x0001;
+    /// Payload layout: [timestamp (f64)][pressure (f32)][temperature (f32)]
+    pub const BARO_PACKET_PAYLOAD_LENGTH: usize = 8 + 4 + 4;
+    /// Payload layout: [timestamp (f64)][27 f32 fields, in `FIRMData` order]. Firmware that
+    /// appends fields sends longer payloads, which start the same way.
+    pub const DATA_PACKET_PAYLOAD_LENGTH: usize = 8 + 4 * 27;
+    /// Payload layout of firmware without the state estimator: [timestamp (f64)][11 f32 raw
+    /// sensor fields, temperature through magnetic field z].
+    pub const RAW_SENSOR_PACKET_PAYLOAD_LENGTH: usize = 8 + 4 * 11;
+
     /// First u16 in the framed header.
     #[repr(u16)]
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub enum PacketHeader {
-        Data = 0xA55A,
-        Response = 0x5AA5,
-        LogSensor = 0x6BB6,
-        Command = 0xB66B,
+        Data = PROTOCOL.data_header,
+        Response = PROTOCOL.response_header,
+        LogSensor = PROTOCOL.log_sensor_header,
+        Command = PROTOCOL.command_header,
     }
 
     impl PacketHeader {
@@ -31,10 +108,27 @@ pub mod packet {
                 _ => None,
             }
         }
+
+        /// Whether `identifier` is one the device knows under this header: a data packet
+        /// identifier for `Data`, a command marker for `Command` and `Response`, and a log
+        /// packet type for `LogSensor`.
+        pub fn accepts_identifier(self, identifier: u16) -> bool {
+            match self {
+                PacketHeader::Data => {
+                    identifier == DATA_PACKET_IDENTIFIER || identifier == BARO_PACKET_IDENTIFIER
+                }
+                PacketHeader::Response | PacketHeader::Command => {
+                    FIRMCommand::from_u16(identifier).is_ok()
+                }
+                PacketHeader::LogSensor => FIRMLogPacketType::from_u16(identifier).is_some(),
+            }
+        }
     }
 }
 
 pub mod command {
+    use super::PROTOCOL;
+    use super::packet::MIN_PACKET_SIZE;
     use crate::framed_packet::FrameError;
 
     #[repr(u16)]
@@ -78,13 +172,32 @@ pub mod command {
                 _ => Err(FrameError::UnknownIdentifier(identifier)),
             }
         }
+
+        /// Largest payload the firmware accepts for this command. Anything longer is silently
+        /// dropped by the device, so builders reject it up front.
+        pub const fn max_payload_length(self) -> usize {
+            match self {
+                FIRMCommand::SetDeviceConfig => SET_DEVICE_CONFIG_PAYLOAD_LENGTH,
+                FIRMCommand::SetMagnetometerCalibration => MAGNETOMETER_CALIBRATION_PAYLOAD_LENGTH,
+                FIRMCommand::SetIMUCalibration => IMU_CALIBRATION_PAYLOAD_LENGTH,
+                FIRMCommand::GetDeviceInfo
+                | FIRMCommand::GetDeviceConfig
+                | FIRMCommand::Reboot
+                | FIRMCommand::Mock
+                | FIRMCommand::GetCalibration
+                | FIRMCommand::Cancel => 0,
+            }
+        }
     }
 
-    pub const CRC_LENGTH: usize = 2;
-    pub const DEVICE_NAME_LENGTH: usize = 32;
-    pub const DEVICE_ID_LENGTH: usize = 8;
-    pub const FIRMWARE_VERSION_LENGTH: usize = 8;
-    pub const FREQUENCY_LENGTH: usize = 2;
+    pub use super::packet::CRC_SIZE as CRC_LENGTH;
+    pub const DEVICE_NAME_LENGTH: usize = PROTOCOL.device_name_length;
+    pub const DEVICE_ID_LENGTH: usize = PROTOCOL.device_id_length;
+    pub const FIRMWARE_VERSION_LENGTH: usize = PROTOCOL.firmware_version_length;
+    pub const FREQUENCY_LENGTH: usize = PROTOCOL.frequency_length;
+    /// Data output frequency range accepted by `SetDeviceConfig`, in Hz.
+    pub const MIN_FREQUENCY_HZ: u16 = 1;
+    pub const MAX_FREQUENCY_HZ: u16 = 1000;
     pub const NUMBER_OF_CALIBRATION_OFFSETS: usize = 3;
     pub const NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS: usize = 9;
     pub const CALIBRATION_OFFSETS_LENGTH: usize = NUMBER_OF_CALIBRATION_OFFSETS * 4;
@@ -98,11 +211,56 @@ pub mod command {
     pub const IMU_CALIBRATION_PAYLOAD_LENGTH: usize = (CALIBRATION_OFFSETS_LENGTH
         + CALIBRATION_SCALE_MATRIX_LENGTH)
         * NUMBER_OF_IMU_CALIBRATION_SETS;
+
+    /// Payload layout: [name (32 bytes)][frequency (u16)][protocol (u8)]
+    pub const SET_DEVICE_CONFIG_PAYLOAD_LENGTH: usize = DEVICE_NAME_LENGTH + FREQUENCY_LENGTH + 1;
+
+    /// Payload layout: [offsets (3 f32)][matrix (9 f32)]
+    pub const MAGNETOMETER_CALIBRATION_PAYLOAD_LENGTH: usize =
+        CALIBRATION_OFFSETS_LENGTH + CALIBRATION_SCALE_MATRIX_LENGTH;
+
+    /// Size of the firmware's command receive buffer, see `PROTOCOL.command_buffer_size`. A
+    /// frame longer than this overflows it and is dropped by the device.
+    pub const COMMAND_BUFFER_SIZE: usize = PROTOCOL.command_buffer_size;
+
+    /// Largest command payload that fits `COMMAND_BUFFER_SIZE` along with the frame's header,
+    /// identifier, length and CRC.
+    pub const MAX_COMMAND_PAYLOAD_LENGTH: usize = COMMAND_BUFFER_SIZE - MIN_PACKET_SIZE;
+
+    // Every command the builders write has to fit the device's buffer.
+    const _: () = {
+        let commands = [
+            FIRMCommand::GetDeviceInfo,
+            FIRMCommand::GetDeviceConfig,
+            FIRMCommand::SetDeviceConfig,
+            FIRMCommand::Reboot,
+            FIRMCommand::Mock,
+            FIRMCommand::SetMagnetometerCalibration,
+            FIRMCommand::SetIMUCalibration,
+            FIRMCommand::GetCalibration,
+            FIRMCommand::Cancel,
+        ];
+        let mut i = 0;
+        while i < commands.len() {
+            assert!(commands[i].max_payload_length() <= MAX_COMMAND_PAYLOAD_LENGTH);
+            i += 1;
+        }
+    };
+    // The frequency and protocol fields must fit the wire types used by the builder.
+    const _: () = assert!(FREQUENCY_LENGTH == core::mem::size_of::<u16>());
 }
 
 pub mod log_parsing {
+    use crate::constants::command::{CALIBRATION_OFFSETS_LENGTH, CALIBRATION_SCALE_MATRIX_LENGTH};
     use crate::constants::packet::PacketHeader;
-    use std::time::Duration;
+    use core::time::Duration;
+
+    // The log header stores the same device fields the device info and config responses do.
+    pub use crate::constants::command::{
+        DEVICE_ID_LENGTH as HEADER_UID_SIZE, DEVICE_NAME_LENGTH as HEADER_DEVICE_NAME_LEN,
+        FIRMWARE_VERSION_LENGTH as HEADER_FIRMWARE_VERSION_SIZE,
+        FREQUENCY_LENGTH as HEADER_FREQUENCY_SIZE,
+    };
 
     pub const LOG_SENSOR_PACKET_HEADER: u16 = PacketHeader::LogSensor as u16;
     /// Log sensor packet type identifier stored in the second u16 header field.
@@ -135,6 +293,16 @@ pub mod log_parsing {
             // SAFETY: All enum variants are valid ASCII values.
             self as u8 as char
         }
+
+        /// Largest payload the firmware accepts for this log packet type.
+        pub const fn max_payload_length(self) -> usize {
+            match self {
+                Self::HeaderPacket => HEADER_TOTAL_SIZE,
+                Self::BarometerPacket => LOG_PACKET_TIMESTAMP_SIZE + BMP581_SIZE,
+                Self::IMUPacket => LOG_PACKET_TIMESTAMP_SIZE + ICM45686_SIZE,
+                Self::MagnetometerPacket => LOG_PACKET_TIMESTAMP_SIZE + MMC5983MA_SIZE,
+            }
+        }
     }
 
     pub const HEADER_ID: u8 = b'H';
@@ -149,15 +317,26 @@ pub mod log_parsing {
 
     pub const LOG_FILE_EOF_PADDING_LENGTH: usize = 20;
     pub const LOG_PACKET_TIMESTAMP_SIZE: usize = 4;
+    /// Rate of the 32-bit clock that timestamps log packets, in Hz.
+    pub const LOG_CLOCK_HZ: f64 = 168e6;
+
+    /// Largest sensor log packet payload: [timestamp][largest sensor reading].
+    pub const MAX_SENSOR_LOG_PACKET_PAYLOAD_LENGTH: usize =
+        LOG_PACKET_TIMESTAMP_SIZE + max_size(max_size(BMP581_SIZE, ICM45686_SIZE), MMC5983MA_SIZE);
+
+    const fn max_size(a: usize, b: usize) -> usize {
+        if a > b { a } else { b }
+    }
 
     pub const HEADER_SIZE_TEXT: usize = 14; // "FIRM LOG vx.x"
-    pub const HEADER_UID_SIZE: usize = 8;
-    pub const HEADER_DEVICE_NAME_LEN: usize = 32;
+    /// Every log file header starts with this, followed by the version.
+    pub const LOG_FILE_MAGIC: &[u8] = b"FIRM LOG";
     pub const HEADER_COMM_SIZE: usize = 4; // 1 byte usb, 1 byte uart, 1 byte spi, 1 byte i2c
-    pub const HEADER_FIRMWARE_VERSION_SIZE: usize = 8; // "vX.X.X.X"
-    pub const HEADER_FREQUENCY_SIZE: usize = 2;
     pub const HEADER_PADDING_SIZE: usize = 2;
-    pub const HEADER_CAL_SIZE: usize = (3 + 9) * 3 * 4; // (offsets + 3x3 matrix) * 3 sensors * 4 bytes
+    /// Number of calibrated sensors in the header: accelerometer, gyroscope and magnetometer.
+    pub const HEADER_CALIBRATED_SENSORS: usize = 3;
+    pub const HEADER_CAL_SIZE: usize =
+        (CALIBRATION_OFFSETS_LENGTH + CALIBRATION_SCALE_MATRIX_LENGTH) * HEADER_CALIBRATED_SENSORS;
     pub const HEADER_NUM_SCALE_FACTOR_SIZE: usize = 5 * 4; // 5 floats
 
     pub const HEADER_TOTAL_SIZE: usize = HEADER_SIZE_TEXT
@@ -171,4 +350,8 @@ pub mod log_parsing {
         + HEADER_NUM_SCALE_FACTOR_SIZE;
 
     pub const HEADER_PARSE_DELAY: Duration = Duration::from_millis(100);
+
+    const _: () = assert!(LOG_FILE_MAGIC.len() < HEADER_SIZE_TEXT);
+    // The header layout is fixed by the firmware; moving a field size must not go unnoticed.
+    const _: () = assert!(HEADER_TOTAL_SIZE == 234);
 }
@@ -0,0 +1,399 @@
+//! A corpus of example frames, one for each distinct frame shape the parser has seen.
+//!
+//! With a `FrameCorpus` set on a `SerialParser`, every frame the parser accepts or rejects is
+//! keyed by its `FrameShape` (header, identifier, length and what the parser did with it) and
+//! the first frame of each shape is kept. Field sessions then add the odd responses and
+//! corrupted frames a real link produces, without keeping gigabytes of repeated data packets.
+//!
+//! A corpus is written as a directory of one small binary file per shape, plus a `manifest.txt`
+//! with one line per file:
+//!
+//! ```text
+//! # file header identifier length outcome
+//! a55a_0000_120_accepted.bin 0xa55a 0x0000 120 accepted
+//! ```
+//!
+//! Replaying each file through `FrameCorpus::classify` and comparing against the manifest
+//! catches any change in what the parser accepts.
+//!
+//! With the `no-alloc` feature there's no `FrameCorpus`, just the `FrameShape` and
+//! `FrameOutcome` the parser classifies frames with.
+use crate::constants::packet::{HEADER_SIZE, IDENTIFIER_SIZE, LENGTH_SIZE};
+#[cfg(not(feature = "no-alloc"))]
+use crate::data_parser::SerialParser;
+#[cfg(not(feature = "no-alloc"))]
+use alloc::{format, string::String, vec::Vec};
+
+/// What `SerialParser` did with a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FrameOutcome {
+    /// Decoded as a data packet, barometer reading or response.
+    Accepted,
+    /// The CRC didn't match, so the parser skipped a byte and kept looking.
+    BadCrc,
+    /// The CRC matched but the packet couldn't be decoded, so it went to `get_raw_frame`.
+    Undecodable,
+    /// The CRC matched but the bytes aren't a well-formed frame, so the parser skipped a byte.
+    Malformed,
+}
+
+impl FrameOutcome {
+    pub const ALL: [FrameOutcome; 4] = [
+        FrameOutcome::Accepted,
+        FrameOutcome::BadCrc,
+        FrameOutcome::Undecodable,
+        FrameOutcome::Malformed,
+    ];
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            FrameOutcome::Accepted => "accepted",
+            FrameOutcome::BadCrc => "bad_crc",
+            FrameOutcome::Undecodable => "undecodable",
+            FrameOutcome::Malformed => "malformed",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|outcome| outcome.as_str() == name)
+    }
+}
+
+/// The structure of a frame, which decides how the parser handles it. Two frames with the
+/// same shape only differ in their payload bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FrameShape {
+    pub header: u16,
+    pub identifier: u16,
+    /// The payload length from the frame's length field.
+    pub length: u32,
+    pub outcome: FrameOutcome,
+}
+
+impl FrameShape {
+    /// Reads the shape of the frame at the start of `bytes`.
+    ///
+    /// # Arguments
+    ///
+    /// - `bytes` (`&[u8]`) - The frame, starting at its header.
+    /// - `outcome` (`FrameOutcome`) - What the parser did with it.
+    ///
+    /// # Returns
+    ///
+    /// - `Option<FrameShape>` - The shape, or `None` if `bytes` is shorter than a frame header.
+    pub fn of(bytes: &[u8], outcome: FrameOutcome) -> Option<Self> {
+        let length_start = HEADER_SIZE + IDENTIFIER_SIZE;
+        let length = bytes.get(length_start..length_start + LENGTH_SIZE)?;
+        Some(Self {
+            header: u16::from_le_bytes([bytes[0], bytes[1]]),
+            identifier: u16::from_le_bytes([bytes[HEADER_SIZE], bytes[HEADER_SIZE + 1]]),
+            length: u32::from_le_bytes(length.try_into().unwrap()),
+            outcome,
+        })
+    }
+}
+
+#[cfg(not(feature = "no-alloc"))]
+impl FrameShape {
+    /// Returns the name of this shape's file in a corpus directory.
+    pub fn file_name(&self) -> String {
+        format!(
+            "{:04x}_{:04x}_{}_{}.bin",
+            self.header,
+            self.identifier,
+            self.length,
+            self.outcome.as_str()
+        )
+    }
+
+    /// Returns this shape's line in a corpus manifest.
+    pub fn manifest_line(&self) -> String {
+        format!(
+            "{} {:#06x} {:#06x} {} {}",
+            self.file_name(),
+            self.header,
+            self.identifier,
+            self.length,
+            self.outcome.as_str()
+        )
+    }
+
+    /// Parses a manifest line written by `manifest_line`.
+    ///
+    /// # Returns
+    ///
+    /// - `Option<(String, FrameShape)>` - The file name and shape, or `None` if the line isn't
+    ///   a manifest entry.
+    pub fn parse_manifest_line(line: &str) -> Option<(String, FrameShape)> {
+        let mut fields = line.split_whitespace();
+        let file_name = fields.next()?;
+        let mut hex = || u16::from_str_radix(fields.next()?.strip_prefix("0x")?, 16).ok();
+        let (header, identifier) = (hex()?, hex()?);
+        let length = fields.next()?.parse().ok()?;
+        let outcome = FrameOutcome::from_name(fields.next()?)?;
+        if fields.next().is_some() {
+            return None;
+        }
+        let shape = FrameShape {
+            header,
+            identifier,
+            length,
+            outcome,
+        };
+        Some((file_name.into(), shape))
+    }
+}
+
+/// One example frame for each shape seen, up to a fixed number of shapes.
+#[cfg(not(feature = "no-alloc"))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameCorpus {
+    max_entries: usize,
+    /// Shapes and their first frame, in the order they were first seen.
+    entries: Vec<(FrameShape, Vec<u8>)>,
+    /// Frames passed to `record`, including the ones whose shape was already kept.
+    frames_seen: u64,
+}
+
+#[cfg(not(feature = "no-alloc"))]
+impl FrameCorpus {
+    /// Name of the manifest in a corpus directory.
+    pub const MANIFEST_FILE_NAME: &'static str = "manifest.txt";
+
+    /// Creates an empty corpus that keeps at most `max_entries` shapes.
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries,
+            entries: Vec::new(),
+            frames_seen: 0,
+        }
+    }
+
+    /// Keeps `bytes` if it's the first frame of its shape and the corpus isn't full.
+    ///
+    /// # Arguments
+    ///
+    /// - `bytes` (`&[u8]`) - The whole frame, header to CRC.
+    /// - `outcome` (`FrameOutcome`) - What the parser did with it.
+    ///
+    /// # Returns
+    ///
+    /// - `bool` - Whether the frame was added.
+    pub fn record(&mut self, bytes: &[u8], outcome: FrameOutcome) -> bool {
+        self.frames_seen += 1;
+        let Some(shape) = FrameShape::of(bytes, outcome) else {
+            return false;
+        };
+        if self.is_full() || self.entries.iter().any(|(known, _)| *known == shape) {
+            return false;
+        }
+        self.entries.push((shape, bytes.to_vec()));
+        true
+    }
+
+    /// Returns the number of shapes kept.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns true once `max_entries` shapes are kept; later new shapes are not added.
+    pub fn is_full(&self) -> bool {
+        self.entries.len() >= self.max_entries
+    }
+
+    /// Returns the number of frames offered to `record`.
+    pub fn frames_seen(&self) -> u64 {
+        self.frames_seen
+    }
+
+    /// Returns the shapes and their example frames, in the order they were first seen.
+    pub fn entries(&self) -> impl ExactSizeIterator<Item = (&FrameShape, &[u8])> {
+        self.entries
+            .iter()
+            .map(|(shape, bytes)| (shape, bytes.as_slice()))
+    }
+
+    /// Returns the manifest listing every entry, as written to `MANIFEST_FILE_NAME`.
+    pub fn manifest(&self) -> String {
+        manifest_for(self.entries.iter().map(|(shape, _)| shape))
+    }
+
+    /// Runs `bytes` through a new parser and returns the shape of the first frame it saw,
+    /// i.e. how the parser currently handles a corpus file.
+    ///
+    /// # Returns
+    ///
+    /// - `Option<FrameShape>` - The shape, or `None` if the parser didn't find a frame at all.
+    pub fn classify(bytes: &[u8]) -> Option<FrameShape> {
+        let mut parser = SerialParser::new();
+        parser.set_corpus(Some(FrameCorpus::new(1)));
+        parser.parse_bytes(bytes);
+        let corpus = parser.take_corpus()?;
+        corpus.entries.first().map(|(shape, _)| *shape)
+    }
+}
+
+/// Returns the manifest listing `shapes`, one per line after a comment naming the columns.
+#[cfg(not(feature = "no-alloc"))]
+fn manifest_for<'a>(shapes: impl Iterator<Item = &'a FrameShape>) -> String {
+    let mut manifest = String::from("# file header identifier length outcome\n");
+    for shape in shapes {
+        manifest.push_str(&shape.manifest_line());
+        manifest.push('\n');
+    }
+    manifest
+}
+
+#[cfg(feature = "std")]
+impl FrameCorpus {
+    /// Writes one file per entry and the manifest into `dir`, creating it if needed. Files
+    /// already in `dir` are kept, so a corpus can be written over an older one to grow it.
+    pub fn write_dir(&self, dir: &std::path::Path) -> std::io::Result<()> {
+        std::fs::create_dir_all(dir)?;
+        let mut entries: Vec<(FrameShape, Vec<u8>)> = match Self::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        for (shape, bytes) in &self.entries {
+            if !entries.iter().any(|(known, _)| known == shape) {
+                std::fs::write(dir.join(shape.file_name()), bytes)?;
+                entries.push((*shape, bytes.clone()));
+            }
+        }
+        entries.sort_by_key(|(shape, _)| *shape);
+        let manifest = manifest_for(entries.iter().map(|(shape, _)| shape));
+        std::fs::write(dir.join(Self::MANIFEST_FILE_NAME), manifest)
+    }
+
+    /// Reads the entries of a corpus directory written by `write_dir`, in manifest order.
+    ///
+    /// # Returns
+    ///
+    /// - `io::Result<Vec<(FrameShape, Vec<u8>)>>` - The shape the manifest records for each file
+    ///   and the file's bytes. Fails with `InvalidData` on a line that isn't a manifest entry.
+    pub fn read_dir(dir: &std::path::Path) -> std::io::Result<Vec<(FrameShape, Vec<u8>)>> {
+        let manifest = std::fs::read_to_string(dir.join(Self::MANIFEST_FILE_NAME))?;
+        let mut entries = Vec::new();
+        for line in manifest.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (file_name, shape) = FrameShape::parse_manifest_line(line).ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("bad manifest line '{line}'"),
+                )
+            })?;
+            entries.push((shape, std::fs::read(dir.join(file_name))?));
+        }
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::packet::PacketHeader;
+    use crate::framed_packet::FramedPacket;
+
+    fn frame(header: PacketHeader, identifier: u16, payload: &[u8]) -> Vec<u8> {
+        FramedPacket::new(header, identifier, payload.to_vec()).to_bytes()
+    }
+
+    #[test]
+    fn test_corpus_keeps_one_frame_per_shape_up_to_its_limit() {
+        let mut corpus = FrameCorpus::new(3);
+        let data = frame(PacketHeader::Data, 0, &[1; 120]);
+        let other_data = frame(PacketHeader::Data, 0, &[2; 120]);
+        assert!(corpus.record(&data, FrameOutcome::Accepted));
+        assert!(!corpus.record(&other_data, FrameOutcome::Accepted));
+        assert!(corpus.record(&other_data, FrameOutcome::BadCrc));
+        assert!(corpus.record(
+            &frame(PacketHeader::Response, 7, &[1]),
+            FrameOutcome::Accepted
+        ));
+        assert!(corpus.is_full());
+        assert!(!corpus.record(
+            &frame(PacketHeader::Response, 8, &[1]),
+            FrameOutcome::Accepted
+        ));
+        assert!(!corpus.record(&[0x5a], FrameOutcome::BadCrc));
+
+        assert_eq!(corpus.len(), 3);
+        assert_eq!(corpus.frames_seen(), 6);
+        let (shape, bytes) = corpus.entries().next().unwrap();
+        assert_eq!(bytes, data.as_slice());
+        assert_eq!(
+            *shape,
+            FrameShape {
+                header: PacketHeader::Data as u16,
+                identifier: 0,
+                length: 120,
+                outcome: FrameOutcome::Accepted,
+            }
+        );
+    }
+
+    #[test]
+    fn test_manifest_lines_round_trip() {
+        let mut corpus = FrameCorpus::new(8);
+        corpus.record(
+            &frame(PacketHeader::Response, 0x12, &[]),
+            FrameOutcome::Undecodable,
+        );
+        corpus.record(
+            &frame(PacketHeader::Data, 1, &[0; 14]),
+            FrameOutcome::BadCrc,
+        );
+
+        let manifest = corpus.manifest();
+        let parsed: Vec<(String, FrameShape)> = manifest
+            .lines()
+            .skip(1)
+            .map(|line| FrameShape::parse_manifest_line(line).unwrap())
+            .collect();
+        let expected: Vec<(String, FrameShape)> = corpus
+            .entries()
+            .map(|(shape, _)| (shape.file_name(), *shape))
+            .collect();
+        assert_eq!(parsed, expected);
+        assert!(FrameShape::parse_manifest_line("a.bin 0x0001 0x0002 3 crispy").is_none());
+        assert!(FrameShape::parse_manifest_line("a.bin 0x0001 0x0002 3").is_none());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_corpus_directory_round_trips_and_grows() {
+        let dir = std::env::temp_dir().join(format!("firm_core_corpus_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let first = frame(PacketHeader::Response, 3, &[1]);
+        let second = frame(PacketHeader::Data, 0, &[0; 120]);
+
+        let mut corpus = FrameCorpus::new(8);
+        corpus.record(&first, FrameOutcome::Accepted);
+        corpus.write_dir(&dir).unwrap();
+        let mut corpus = FrameCorpus::new(8);
+        corpus.record(&first, FrameOutcome::Accepted);
+        corpus.record(&second, FrameOutcome::Accepted);
+        corpus.write_dir(&dir).unwrap();
+
+        let entries = FrameCorpus::read_dir(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let frames: Vec<&[u8]> = entries.iter().map(|(_, bytes)| bytes.as_slice()).collect();
+        // Sorted by shape, so responses come before data packets.
+        assert_eq!(frames, [first.as_slice(), second.as_slice()]);
+        assert!(
+            entries
+                .iter()
+                .all(|(shape, bytes)| FrameCorpus::classify(bytes) == Some(*shape))
+        );
+    }
+}
@@ -0,0 +1,530 @@
+//! CSV export and import of `FIRMData`, one row per packet with a column per field.
+//!
+//! Every value is written with Rust's shortest round-trip float formatting, so parsing a cell
+//! back as the field's type gives the exact same bits. In particular `timestamp_seconds` keeps
+//! its full `f64` precision, which lets the comparison tools align packets from different runs.
+//!
+//! NaN and infinities are written, and read back, as `crate::non_finite::NonFinitePolicy`
+//! says: an empty cell for NaN and a sentinel, `inf` by default, for infinity.
+//!
+//! Secondary barometer packets arrive at their own rate, so they go in a separate table,
+//! `baro_csv_header`/`write_baro_csv_row`, usually written to a file next to the packet one.
+//!
+//! `parse_csv`/`read_csv` read such a file back, e.g. after someone trimmed rows or fixed a
+//! column by hand. Columns are matched by name, so their order doesn't matter and unknown
+//! columns are ignored.
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::firm_packets::{FIRMBaroPacket, FIRMData};
+use crate::non_finite::NonFinitePolicy;
+
+/// Returns the CSV header line (without a trailing newline): the `FIRMData` field names.
+pub fn csv_header() -> String {
+    FIRMData::field_names().join(",")
+}
+
+/// Appends one CSV row for `data`, including the trailing newline, to `out`.
+///
+/// # Arguments
+///
+/// - `out` (`&mut String`) - The buffer to append to.
+/// - `data` (`&FIRMData`) - The packet to write, in `csv_header` column order.
+/// - `policy` (`&NonFinitePolicy`) - How to write NaN and infinity.
+pub fn write_csv_row(out: &mut String, data: &FIRMData, policy: &NonFinitePolicy) {
+    policy.write_cell(out, data.timestamp_seconds);
+    for value in data.f32_fields() {
+        out.push(',');
+        policy.write_f32_cell(out, value);
+    }
+    out.push(',');
+    policy.write_f32_cell(out, data.pressure_altitude_meters);
+    out.push('\n');
+}
+
+/// Returns the header line of the secondary barometer table (without a trailing newline).
+pub fn baro_csv_header() -> String {
+    "timestamp_seconds,pressure_pascals,temperature_celsius".to_string()
+}
+
+/// Appends one row of the secondary barometer table for `packet`, including the trailing
+/// newline, to `out`.
+///
+/// # Arguments
+///
+/// - `out` (`&mut String`) - The buffer to append to.
+/// - `packet` (`&FIRMBaroPacket`) - The reading to write, in `baro_csv_header` column order.
+/// - `policy` (`&NonFinitePolicy`) - How to write NaN and infinity.
+pub fn write_baro_csv_row(out: &mut String, packet: &FIRMBaroPacket, policy: &NonFinitePolicy) {
+    policy.write_cell(out, packet.timestamp_seconds);
+    out.push(',');
+    policy.write_f32_cell(out, packet.pressure_pascals);
+    out.push(',');
+    policy.write_f32_cell(out, packet.temperature_celsius);
+    out.push('\n');
+}
+
+/// Options for `parse_csv` and `read_csv`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CsvImportOptions {
+    /// Sort rows by timestamp and drop rows repeating an earlier timestamp instead of
+    /// rejecting the file when timestamps aren't strictly increasing.
+    pub repair_timestamps: bool,
+    /// How the file writes NaN and infinity.
+    pub non_finite: NonFinitePolicy,
+}
+
+/// A problem with one row of an imported CSV.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvRowError {
+    /// 1-based line number in the file, counting the header as line 1.
+    pub line: usize,
+    pub message: String,
+}
+
+/// Why a CSV file couldn't be imported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CsvImportError {
+    /// The file couldn't be read.
+    Io(String),
+    /// The file has no header line.
+    Empty,
+    /// `timestamp_seconds` is missing from the header. Every other column is optional.
+    MissingTimestampColumn,
+    /// A column name appears more than once in the header.
+    DuplicateColumn(String),
+    /// One or more rows couldn't be parsed, in file order.
+    InvalidRows(Vec<CsvRowError>),
+}
+
+impl core::fmt::Display for CsvImportError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CsvImportError::Io(message) => write!(f, "failed to read CSV: {message}"),
+            CsvImportError::Empty => write!(f, "CSV file is empty"),
+            CsvImportError::MissingTimestampColumn => {
+                write!(f, "CSV header has no timestamp_seconds column")
+            }
+            CsvImportError::DuplicateColumn(column) => {
+                write!(f, "CSV header has column {column} more than once")
+            }
+            CsvImportError::InvalidRows(errors) => {
+                write!(f, "{} invalid CSV row(s):", errors.len())?;
+                for error in errors {
+                    write!(f, "\n  line {}: {}", error.line, error.message)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl core::error::Error for CsvImportError {}
+
+/// Parses CSV text in the format written by `csv_header`/`write_csv_row`.
+///
+/// `#` comment lines before the header, e.g. the session details a live export starts with,
+/// are skipped. Columns missing from the header (other than `timestamp_seconds`) become NaN.
+/// Empty cells are NaN too, and `nan` and the options' infinity sentinel are accepted in any
+/// case. Every row problem is collected, so one import attempt reports all of them.
+///
+/// # Arguments
+///
+/// - `text` (`&str`) - The CSV file contents.
+/// - `options` (`CsvImportOptions`) - Whether to repair out of order timestamps, and the
+///   NaN/infinity policy the file was written with.
+///
+/// # Returns
+///
+/// - `Result<Vec<FIRMData>, CsvImportError>` - One packet per row, in timestamp order.
+pub fn parse_csv(text: &str, options: CsvImportOptions) -> Result<Vec<FIRMData>, CsvImportError> {
+    let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line));
+    let (_, header) = lines
+        .by_ref()
+        .find(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+        .ok_or(CsvImportError::Empty)?;
+
+    // For each file column, the index into `fields` it fills, or `TIMESTAMP`/`IGNORED`.
+    // `fields` is the `f32_fields` followed by `pressure_altitude_meters`.
+    const TIMESTAMP: usize = usize::MAX;
+    const IGNORED: usize = usize::MAX - 1;
+    let field_names = FIRMData::field_names();
+    let mut columns = Vec::new();
+    let mut seen: Vec<&str> = Vec::new();
+    for name in header.split(',').map(clean_cell) {
+        if seen.contains(&name) {
+            return Err(CsvImportError::DuplicateColumn(name.to_string()));
+        }
+        seen.push(name);
+        columns.push(match field_names.iter().position(|field| *field == name) {
+            Some(0) => TIMESTAMP,
+            Some(i) => i - 1,
+            None => IGNORED,
+        });
+    }
+    if !columns.contains(&TIMESTAMP) {
+        return Err(CsvImportError::MissingTimestampColumn);
+    }
+
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+    for (line, text) in lines {
+        if text.trim().is_empty() {
+            continue;
+        }
+        let cells: Vec<&str> = text.split(',').collect();
+        if cells.len() != columns.len() {
+            errors.push(CsvRowError {
+                line,
+                message: format!("expected {} cells, found {}", columns.len(), cells.len()),
+            });
+            continue;
+        }
+
+        let mut timestamp = f64::NAN;
+        let mut fields = [f32::NAN; FIRMData::NUM_F32_FIELDS + 1];
+        let mut row_ok = true;
+        for ((&column, cell), name) in columns.iter().zip(&cells).zip(&seen) {
+            if column == IGNORED {
+                continue;
+            }
+            let cell = clean_cell(cell);
+            // Parsed as the field's own type so the value round-trips bit for bit.
+            let policy = &options.non_finite;
+            let parsed = if column == TIMESTAMP {
+                policy
+                    .parse_cell(cell, f64::NAN, f64::INFINITY)
+                    .map(|value| timestamp = value)
+            } else {
+                policy
+                    .parse_cell(cell, f32::NAN, f32::INFINITY)
+                    .map(|value| fields[column] = value)
+            };
+            match parsed {
+                Some(()) => {}
+                None => {
+                    errors.push(CsvRowError {
+                        line,
+                        message: format!("{name}: {cell:?} is not a number"),
+                    });
+                    row_ok = false;
+                }
+            }
+        }
+        // Checked only once the cell itself parsed, so a bad cell isn't reported twice.
+        if row_ok && !timestamp.is_finite() {
+            errors.push(CsvRowError {
+                line,
+                message: "timestamp_seconds must be a finite number".to_string(),
+            });
+            row_ok = false;
+        }
+        if row_ok {
+            let wire_fields = fields[..FIRMData::NUM_F32_FIELDS].try_into().unwrap();
+            let mut data = FIRMData::from_fields(timestamp, wire_fields);
+            data.pressure_altitude_meters = fields[FIRMData::NUM_F32_FIELDS];
+            rows.push((line, data));
+        }
+    }
+
+    if options.repair_timestamps {
+        rows.sort_by(|(_, a), (_, b)| a.timestamp_seconds.total_cmp(&b.timestamp_seconds));
+        rows.dedup_by(|(_, later), (_, earlier)| {
+            later.timestamp_seconds == earlier.timestamp_seconds
+        });
+    } else {
+        let mut latest = f64::NEG_INFINITY;
+        for (line, row) in &rows {
+            if row.timestamp_seconds <= latest {
+                errors.push(CsvRowError {
+                    line: *line,
+                    message: format!(
+                        "timestamp {} is not after an earlier row's {latest}",
+                        row.timestamp_seconds
+                    ),
+                });
+            }
+            latest = latest.max(row.timestamp_seconds);
+        }
+    }
+
+    if !errors.is_empty() {
+        errors.sort_by_key(|error| error.line);
+        return Err(CsvImportError::InvalidRows(errors));
+    }
+    Ok(rows.into_iter().map(|(_, data)| data).collect())
+}
+
+/// Reads and parses a CSV file, see `parse_csv`.
+///
+/// # Arguments
+///
+/// - `path` (`impl AsRef<std::path::Path>`) - The CSV file to read.
+/// - `options` (`CsvImportOptions`) - Whether to repair out of order timestamps, and the
+///   NaN/infinity policy the file was written with.
+#[cfg(feature = "std")]
+pub fn read_csv(
+    path: impl AsRef<std::path::Path>,
+    options: CsvImportOptions,
+) -> Result<Vec<FIRMData>, CsvImportError> {
+    let text = std::fs::read_to_string(path).map_err(|e| CsvImportError::Io(e.to_string()))?;
+    parse_csv(&text, options)
+}
+
+/// Trims whitespace and the quotes spreadsheets sometimes add around a cell.
+fn clean_cell(cell: &str) -> &str {
+    let cell = cell.trim();
+    cell.strip_prefix('"')
+        .and_then(|c| c.strip_suffix('"'))
+        .unwrap_or(cell)
+        .trim()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(timestamp_seconds: f64) -> FIRMData {
+        let mut fields = [0.0f32; FIRMData::NUM_F32_FIELDS];
+        for (i, field) in fields.iter_mut().enumerate() {
+            *field = 0.1 * i as f32 - 1.3;
+        }
+        FIRMData::from_fields(timestamp_seconds, fields)
+    }
+
+    /// Parses a row written by `write_csv_row`, the way a CSV reader would.
+    fn parse_row(row: &str) -> FIRMData {
+        let cells: Vec<&str> = row.trim_end().split(',').collect();
+        let parse_f32 = |cell: &str| match cell {
+            "" => f32::NAN,
+            "inf" => f32::INFINITY,
+            "-inf" => f32::NEG_INFINITY,
+            cell => cell.parse().unwrap(),
+        };
+        let mut fields = [0.0f32; FIRMData::NUM_F32_FIELDS];
+        for (field, cell) in fields.iter_mut().zip(&cells[1..]) {
+            *field = parse_f32(cell);
+        }
+        FIRMData::from_fields(cells[0].parse().unwrap(), fields)
+    }
+
+    #[test]
+    fn test_header_matches_fields() {
+        let header = csv_header();
+        assert!(header.starts_with("timestamp_seconds,temperature_celsius,pressure_pascals,"));
+        assert!(header.ends_with(",est_quaternion_z,pressure_altitude_meters"));
+        assert_eq!(header.split(',').count(), 2 + FIRMData::NUM_F32_FIELDS);
+    }
+
+    #[test]
+    fn test_timestamps_round_trip_bit_exact() {
+        let timestamps = [
+            0.0,
+            0.1 + 0.2,
+            1.0e-7,
+            123.456_789_012_345_67,
+            86_399.999_999_999,
+            // One microsecond ticks accumulated in floating point drift off "round" values.
+            (0..1_000_003).fold(0.0, |t: f64, _| t + 1e-6),
+            f64::MIN_POSITIVE,
+            f64::from_bits(1),
+            1.0e15 + 0.5,
+        ];
+        for timestamp in timestamps {
+            let mut row = String::new();
+            write_csv_row(&mut row, &packet(timestamp), &NonFinitePolicy::default());
+            let parsed = parse_row(&row);
+            assert_eq!(
+                parsed.timestamp_seconds.to_bits(),
+                timestamp.to_bits(),
+                "{row}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_f32_fields_round_trip_in_shortest_form() {
+        let mut data = packet(1.5);
+        data.temperature_celsius = 0.1;
+        data.pressure_pascals = 101_325.3;
+        let mut row = String::new();
+        write_csv_row(&mut row, &data, &NonFinitePolicy::default());
+        assert_eq!(parse_row(&row), data);
+        // Printed as the f32s they are rather than their f64 widenings.
+        assert!(row.starts_with("1.5,0.1,101325.3,"), "{row}");
+    }
+
+    #[test]
+    fn test_non_finite_values_use_empty_cells_and_sentinels() {
+        let mut fields = [1.0f32; FIRMData::NUM_F32_FIELDS];
+        fields[0] = f32::NAN;
+        fields[1] = f32::INFINITY;
+        fields[2] = f32::NEG_INFINITY;
+        let mut row = String::new();
+        let policy = NonFinitePolicy::default();
+        write_csv_row(&mut row, &FIRMData::from_fields(2.0, fields), &policy);
+        assert!(row.starts_with("2,,inf,-inf,1,"), "{row}");
+
+        let parsed = parse_row(&row);
+        assert!(parsed.temperature_celsius.is_nan());
+        assert_eq!(parsed.pressure_pascals, f32::INFINITY);
+        assert_eq!(parsed.raw_acceleration_x_gs, f32::NEG_INFINITY);
+    }
+
+    /// Writes `packets` the way the logging tools do.
+    fn export(packets: &[FIRMData], policy: &NonFinitePolicy) -> String {
+        let mut out = csv_header();
+        out.push('\n');
+        for packet in packets {
+            write_csv_row(&mut out, packet, policy);
+        }
+        out
+    }
+
+    fn row_errors(result: Result<Vec<FIRMData>, CsvImportError>) -> Vec<CsvRowError> {
+        match result {
+            Err(CsvImportError::InvalidRows(errors)) => errors,
+            other => panic!("expected row errors, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_baro_table_rows() {
+        let mut out = baro_csv_header();
+        out.push('\n');
+        write_baro_csv_row(
+            &mut out,
+            &FIRMBaroPacket {
+                timestamp_seconds: 0.0025,
+                pressure_pascals: 101_325.1,
+                temperature_celsius: f32::NAN,
+            },
+            &NonFinitePolicy::default(),
+        );
+        assert_eq!(
+            out,
+            "timestamp_seconds,pressure_pascals,temperature_celsius\n0.0025,101325.1,\n"
+        );
+    }
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let mut packets: Vec<FIRMData> = (0..5).map(|i| packet(0.1 * i as f64 + 0.7)).collect();
+        packets[2].temperature_celsius = f32::NAN;
+        packets[3].pressure_pascals = f32::NEG_INFINITY;
+        packets[4].pressure_altitude_meters = 12.5;
+
+        let imported = parse_csv(
+            &export(&packets, &NonFinitePolicy::default()),
+            CsvImportOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(imported.len(), packets.len());
+        for (imported, original) in imported.iter().zip(&packets) {
+            assert_eq!(
+                imported.timestamp_seconds.to_bits(),
+                original.timestamp_seconds.to_bits()
+            );
+            for (a, b) in imported.f32_fields().iter().zip(original.f32_fields()) {
+                assert_eq!(a.to_bits(), b.to_bits());
+            }
+            assert_eq!(
+                imported.pressure_altitude_meters,
+                original.pressure_altitude_meters
+            );
+        }
+    }
+
+    #[test]
+    fn test_custom_inf_sentinel_round_trips() {
+        let mut data = packet(1.0);
+        data.pressure_pascals = f32::INFINITY;
+        data.temperature_celsius = f32::NEG_INFINITY;
+        let policy = NonFinitePolicy::new("1e999");
+        let text = export(&[data], &policy);
+        assert!(
+            text.lines().nth(1).unwrap().starts_with("1,-1e999,1e999,"),
+            "{text}"
+        );
+
+        let options = CsvImportOptions {
+            non_finite: policy,
+            ..Default::default()
+        };
+        let imported = parse_csv(&text, options).unwrap();
+        assert_eq!(imported[0].pressure_pascals, f32::INFINITY);
+        assert_eq!(imported[0].temperature_celsius, f32::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_import_matches_columns_by_name() {
+        let text = "# device_id=42\r\n\
+                    pressure_pascals, \"timestamp_seconds\",notes\r\n\
+                    101325.5,1.25,launch\r\n\
+                    \r\n\
+                    NaN,1.5,\r\n";
+        let imported = parse_csv(text, CsvImportOptions::default()).unwrap();
+        assert_eq!(imported.len(), 2);
+        assert_eq!(imported[0].timestamp_seconds, 1.25);
+        assert_eq!(imported[0].pressure_pascals, 101_325.5);
+        // Columns that aren't in the file come back as NaN.
+        assert!(imported[0].temperature_celsius.is_nan());
+        assert!(imported[1].pressure_pascals.is_nan());
+    }
+
+    #[test]
+    fn test_malformed_rows_are_reported_with_line_numbers() {
+        let text = "timestamp_seconds,pressure_pascals\n\
+                    1.0,100\n\
+                    2.0,abc\n\
+                    3.0\n\
+                    ,100\n\
+                    5.0,100\n";
+        let errors = row_errors(parse_csv(text, CsvImportOptions::default()));
+        let lines: Vec<usize> = errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![3, 4, 5]);
+        assert_eq!(
+            errors[0].message,
+            "pressure_pascals: \"abc\" is not a number"
+        );
+        assert_eq!(errors[1].message, "expected 2 cells, found 1");
+        assert!(errors[2].message.contains("finite"));
+
+        assert_eq!(
+            parse_csv("pressure_pascals\n1\n", CsvImportOptions::default()),
+            Err(CsvImportError::MissingTimestampColumn)
+        );
+        assert_eq!(
+            parse_csv("", CsvImportOptions::default()),
+            Err(CsvImportError::Empty)
+        );
+        assert!(matches!(
+            read_csv("/nonexistent/firm.csv", CsvImportOptions::default()),
+            Err(CsvImportError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_non_monotonic_timestamps_are_rejected_or_repaired() {
+        let text = "timestamp_seconds,temperature_celsius\n\
+                    1.0,10\n\
+                    3.0,30\n\
+                    2.0,20\n\
+                    3.0,31\n";
+        let errors = row_errors(parse_csv(text, CsvImportOptions::default()));
+        let lines: Vec<usize> = errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![4, 5]);
+
+        let repaired = parse_csv(
+            text,
+            CsvImportOptions {
+                repair_timestamps: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let temperatures: Vec<f32> = repaired.iter().map(|p| p.temperature_celsius).collect();
+        assert_eq!(temperatures, vec![10.0, 20.0, 30.0]);
+    }
+}
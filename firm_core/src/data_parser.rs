@@ -1,21 +1,458 @@
+use crate::altitude::STANDARD_SEA_LEVEL_PRESSURE_PASCALS;
+#[cfg(not(feature = "no-alloc"))]
+use crate::cobs::CobsFrameScanner;
 use crate::constants::packet::{PacketHeader, *};
-use crate::firm_packets::{FIRMDataPacket, FIRMResponsePacket};
-use crate::framed_packet::Framed;
-use crate::utils::crc16_ccitt;
-use alloc::collections::VecDeque;
-use alloc::vec::Vec;
+#[cfg(not(feature = "no-alloc"))]
+use crate::corpus::FrameCorpus;
+use crate::corpus::FrameOutcome;
+use crate::firm_packets::{FIRMBaroPacket, FIRMData, FIRMDataPacket, FIRMResponsePacket};
+use crate::framed_packet::{
+    FrameError, FrameScan, Framed, FramedPacket, SequenceGap, WireFormat, scan_frame,
+};
+use crate::packet_sink::PacketSink;
+use crate::packet_view::PacketView;
+use crate::parser_storage::{ByteBuffer, DefaultStorage, FixedQueue, PacketQueue, ParserStorage};
+#[cfg(not(feature = "no-alloc"))]
+use alloc::{collections::VecDeque, vec::Vec};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Counters describing how cleanly the byte stream has been parsed so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ParserStats {
+    /// Frames decoded into a data, barometer or response packet.
+    pub packets_parsed: u64,
+    /// Frames whose start word and length looked valid but whose CRC did not match.
+    pub crc_failures: u64,
+    /// Start words followed by a length no frame can have, see `SerialParser::MAX_PAYLOAD_LENGTH`.
+    pub length_rejects: u64,
+    /// Start words dropped because their frame wouldn't fit in the buffer, see
+    /// `SerialParser::set_max_buffer_len`.
+    pub overflow_discards: u64,
+    /// Bytes discarded while searching for the next valid frame.
+    pub bytes_skipped: u64,
+    /// Times a valid frame was found after skipping one or more bytes.
+    pub resyncs: u64,
+    /// Data packets dropped by `TimestampPolicy::Strict` for being out of order.
+    pub timestamps_dropped: u64,
+    /// Data packets moved forward by `TimestampPolicy::Nudge`.
+    pub timestamps_nudged: u64,
+    /// Packets dropped because their queue was full, see `OverflowPolicy`.
+    pub packets_dropped: u64,
+    /// CRC-valid frames that didn't decode as a known packet, kept for `get_raw_frame`.
+    pub decode_failures: u64,
+}
+
+/// What one `SerialParser::parse_bytes` call did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ParseSummary {
+    /// Data packets queued or handed over, after the timestamp policy.
+    pub data_packets: usize,
+    /// Command responses queued or handed over.
+    pub responses: usize,
+    /// Buffered bytes the call was done with, whether parsed into frames or skipped. This
+    /// includes bytes left over from earlier calls.
+    pub bytes_consumed: usize,
+    /// Bytes left buffered waiting for the rest of a frame.
+    pub bytes_buffered: usize,
+}
+
+/// Why `SerialParser` lost sync with the frames in the stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ResyncReason {
+    /// A start word was followed by a length no frame can have or the buffer can't hold, or by
+    /// a payload that doesn't decode as its packet. In `WireFormat::Cobs`, also a frame that
+    /// doesn't decode or holds more than one packet.
+    LengthMismatch,
+    /// A frame failed its CRC.
+    BadCrc,
+    /// Bytes arrived that don't start a frame.
+    NoStartBytes,
+}
+
+impl core::fmt::Display for ResyncReason {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ResyncReason::LengthMismatch => write!(f, "a frame with an impossible length"),
+            ResyncReason::BadCrc => write!(f, "a frame that failed its CRC"),
+            ResyncReason::NoStartBytes => write!(f, "bytes that don't start a frame"),
+        }
+    }
+}
+
+/// Where in the stream `SerialParser` lost sync, see `SerialParser::set_resync_log`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ResyncEvent {
+    /// Offset of the first skipped byte, counted from the first byte the parser was given.
+    pub byte_offset: u64,
+    pub reason: ResyncReason,
+}
+
+/// A frame the parser rejected or couldn't decode, see `SerialParser::collect_parse_errors`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// Offset of the frame's first byte, counted from the first byte the parser was given.
+    pub byte_offset: u64,
+    pub error: FrameError,
+}
+
+/// The events kept by `SerialParser::set_resync_log`.
+#[cfg(not(feature = "no-alloc"))]
+#[derive(Debug, Clone)]
+struct ResyncLog {
+    events: VecDeque<ResyncEvent>,
+    capacity: usize,
+}
+
+/// What `SerialParser` does with a data packet whose timestamp isn't after the previous
+/// packet's. Some firmware builds repeat a timestamp, or step back slightly, within a FIFO
+/// batch, which the `analysis` adapters and other downstream code don't expect.
+///
+/// Under every policy but `Off`, a timestamp that jumps back to within
+/// `DEVICE_RESTART_WINDOW_SECONDS` of zero is taken to be the device rebooting: the packet is
+/// passed through, a `TimestampEvent::DeviceRestartDetected` is reported, and later packets
+/// are compared with it instead of the timestamps from before the reboot. Under `Strict` and
+/// `Nudge`, once `TIMESTAMP_RESYNC_PACKETS` packets in a row have been behind the last
+/// timestamp, the next one is passed through as the new reference and a
+/// `TimestampEvent::Resynced` is reported.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TimestampPolicy {
+    /// Pass every packet through unchanged.
+    #[default]
+    Off,
+    /// Drop the out of order packet.
+    Strict,
+    /// Move the packet to `TIMESTAMP_NUDGE_SECONDS` after the previous one, keeping the
+    /// original in `FIRMDataPacket::original_timestamp_seconds`.
+    Nudge,
+    /// Pass the packet through unchanged, but report a `TimestampEvent::OutOfOrder`.
+    Warn,
+}
+
+#[cfg(not(feature = "no-alloc"))]
+impl TimestampPolicy {
+    fn to_byte(self) -> u8 {
+        match self {
+            TimestampPolicy::Off => 0,
+            TimestampPolicy::Strict => 1,
+            TimestampPolicy::Nudge => 2,
+            TimestampPolicy::Warn => 3,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(TimestampPolicy::Off),
+            1 => Some(TimestampPolicy::Strict),
+            2 => Some(TimestampPolicy::Nudge),
+            3 => Some(TimestampPolicy::Warn),
+            _ => None,
+        }
+    }
+}
+
+/// A data packet whose timestamp wasn't after the previous one's, see
+/// `SerialParser::get_timestamp_event`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TimestampEvent {
+    /// Reported by `TimestampPolicy::Warn` for a packet that was passed through anyway.
+    OutOfOrder {
+        previous_seconds: f64,
+        timestamp_seconds: f64,
+    },
+    /// The timestamps started over near zero, as they do when the device reboots.
+    DeviceRestartDetected {
+        previous_seconds: f64,
+        timestamp_seconds: f64,
+    },
+    /// `TIMESTAMP_RESYNC_PACKETS` packets in a row were behind `previous_seconds`, e.g. after
+    /// one corrupt timestamp far in the future, so this packet became the new reference.
+    Resynced {
+        previous_seconds: f64,
+        timestamp_seconds: f64,
+    },
+}
+
+/// What `TimestampPolicy` does to one data packet.
+enum TimestampCorrection {
+    Keep,
+    Drop,
+    Nudge(f64),
+}
+
+/// Which packet `SerialParser` drops when a packet queue is full, either at
+/// `SerialParser::queue_capacity` or at the capacity of fixed storage like `FixedStorage`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum OverflowPolicy {
+    /// Drop the oldest queued packet to make room for the new one.
+    #[default]
+    DropOldest,
+    /// Keep the queue as it is and drop the new packet.
+    DropNewest,
+}
+
+#[cfg(not(feature = "no-alloc"))]
+impl OverflowPolicy {
+    fn to_byte(self) -> u8 {
+        match self {
+            OverflowPolicy::DropOldest => 0,
+            OverflowPolicy::DropNewest => 1,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(OverflowPolicy::DropOldest),
+            1 => Some(OverflowPolicy::DropNewest),
+            _ => None,
+        }
+    }
+}
+
+/// How far `TimestampPolicy::Nudge` moves a packet past the previous one.
+pub const TIMESTAMP_NUDGE_SECONDS: f64 = 1e-6;
+
+/// A timestamp this close to zero, after stepping back further than this, is taken to be the
+/// device rebooting rather than an out of order packet. FIRM is streaming well within this
+/// long of powering on.
+pub const DEVICE_RESTART_WINDOW_SECONDS: f64 = 5.0;
+
+/// How many data packets in a row `TimestampPolicy::Strict` drops, or `Nudge` moves, before
+/// the next one is taken as the new reference, see `TimestampEvent::Resynced`.
+pub const TIMESTAMP_RESYNC_PACKETS: u32 = 8;
+
+/// Whether a data packet's timestamp going from `previous_seconds` to `timestamp_seconds`
+/// looks like the device rebooting, see `DEVICE_RESTART_WINDOW_SECONDS`.
+pub fn is_device_restart(previous_seconds: f64, timestamp_seconds: f64) -> bool {
+    timestamp_seconds < DEVICE_RESTART_WINDOW_SECONDS
+        && previous_seconds - timestamp_seconds > DEVICE_RESTART_WINDOW_SECONDS
+}
+
+/// First bytes of every `SerialParser::snapshot`.
+pub const SNAPSHOT_MAGIC: [u8; 4] = *b"FPSN";
+/// Layout version written by `SerialParser::snapshot`. Bumped whenever the layout changes;
+/// `restore` only accepts this version.
+pub const SNAPSHOT_VERSION: u16 = 12;
+
+/// Why `SerialParser::restore` rejected a snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// The bytes don't start with `SNAPSHOT_MAGIC`, so they aren't a parser snapshot.
+    BadMagic,
+    /// The snapshot was written by a different version of the layout.
+    UnsupportedVersion { found: u16, supported: u16 },
+    /// The snapshot ended early or has bytes left over.
+    Truncated,
+    /// A queued frame in the snapshot doesn't decode.
+    InvalidFrame(FrameError),
+}
+
+impl core::fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            SnapshotError::BadMagic => write!(f, "not a parser snapshot"),
+            SnapshotError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "snapshot version {found} is not supported (expected {supported})"
+            ),
+            SnapshotError::Truncated => write!(f, "snapshot is truncated or has trailing bytes"),
+            SnapshotError::InvalidFrame(e) => write!(f, "snapshot holds an invalid frame: {e}"),
+        }
+    }
+}
+
+impl core::error::Error for SnapshotError {}
+
+impl From<FrameError> for SnapshotError {
+    fn from(e: FrameError) -> Self {
+        SnapshotError::InvalidFrame(e)
+    }
+}
+
+/// Reads the little-endian fields of a snapshot in order.
+#[cfg(not(feature = "no-alloc"))]
+struct SnapshotReader<'a> {
+    bytes: &'a [u8],
+}
+
+#[cfg(not(feature = "no-alloc"))]
+impl<'a> SnapshotReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SnapshotError> {
+        if len > self.bytes.len() {
+            return Err(SnapshotError::Truncated);
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, SnapshotError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, SnapshotError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, SnapshotError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, SnapshotError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    /// Reads a timestamp written by `push_timestamp`.
+    fn timestamp(&mut self) -> Result<Option<f64>, SnapshotError> {
+        match self.u8()? {
+            0 => Ok(None),
+            _ => Ok(Some(f64::from_le_bytes(self.take(8)?.try_into().unwrap()))),
+        }
+    }
+
+    /// Reads a `u32` length followed by that many bytes.
+    fn chunk(&mut self) -> Result<&'a [u8], SnapshotError> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    /// Reads a `u32` count followed by that many length-prefixed frames.
+    fn frames<T>(
+        &mut self,
+        decode: impl Fn(&[u8]) -> Result<T, FrameError>,
+    ) -> Result<Vec<T>, SnapshotError> {
+        let count = self.u32()? as usize;
+        let mut frames = Vec::new();
+        for _ in 0..count {
+            frames.push(decode(self.chunk()?)?);
+        }
+        Ok(frames)
+    }
+}
+
+#[cfg(not(feature = "no-alloc"))]
+fn push_chunk(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    out.extend_from_slice(bytes);
+}
+
+/// Writes an optional timestamp as a presence byte, then the `f64` if there is one.
+#[cfg(not(feature = "no-alloc"))]
+fn push_timestamp(out: &mut Vec<u8>, timestamp: Option<f64>) {
+    match timestamp {
+        Some(timestamp) => {
+            out.push(1);
+            out.extend_from_slice(&timestamp.to_le_bytes());
+        }
+        None => out.push(0),
+    }
+}
+
+#[cfg(not(feature = "no-alloc"))]
+fn push_frames<T>(out: &mut Vec<u8>, frames: &impl PacketQueue<T>, encode: impl Fn(&T) -> Vec<u8>) {
+    out.extend_from_slice(&(frames.len() as u32).to_le_bytes());
+    for frame in frames.iter() {
+        push_chunk(out, &encode(frame));
+    }
+}
 
 /// Streaming parser that accumulates serial bytes and queues wire-level frames.
-pub struct SerialParser {
+///
+/// The buffers and queues are `S`'s, see `parser_storage`. `SerialParser` on its own uses
+/// `DefaultStorage`, which is `AllocStorage` unless built with the `no-alloc` feature:
+///
+/// ```
+/// use firm_core::data_parser::SerialParser;
+/// use firm_core::parser_storage::FixedStorage;
+///
+/// let heap = SerialParser::new();
+/// let fixed = SerialParser::<FixedStorage<1024, 16>>::with_storage();
+/// # let _ = (heap, fixed);
+/// ```
+pub struct SerialParser<S: ParserStorage = DefaultStorage> {
     /// Rolling buffer of unprocessed serial bytes.
-    serial_bytes: Vec<u8>,
+    serial_bytes: S::Bytes,
     /// Queue of framed data packets ready to be consumed.
-    parsed_data_packets: VecDeque<FIRMDataPacket>,
+    parsed_data_packets: S::DataPackets,
+    /// Queue of secondary barometer readings ready to be consumed.
+    parsed_baro_packets: S::BaroPackets,
     /// Queue of framed responses ready to be consumed.
-    parsed_response_packets: VecDeque<FIRMResponsePacket>,
+    parsed_response_packets: S::Responses,
+    /// Queue of CRC-valid frames that could not be decoded as a known packet, oldest first.
+    parsed_raw_frames: S::RawFrames,
+    /// Running parse counters.
+    stats: ParserStats,
+    /// Whether bytes have been skipped since the last valid frame.
+    out_of_sync: bool,
+    timestamp_policy: TimestampPolicy,
+    overflow_policy: OverflowPolicy,
+    /// `p0` for data packets' `pressure_altitude_meters`, see `set_sea_level_pressure`.
+    sea_level_pressure_pascals: f32,
+    /// Most packets kept in each packet queue, see `set_queue_capacity`.
+    queue_capacity: usize,
+    /// Most bytes kept buffered between calls, see `set_max_buffer_len`.
+    max_buffer_len: usize,
+    wire_format: WireFormat,
+    /// Splits the stream into frames in `WireFormat::Cobs`, holding the unfinished one.
+    #[cfg(not(feature = "no-alloc"))]
+    cobs: CobsFrameScanner,
+    /// Timestamp of the last data packet queued, after any correction.
+    last_timestamp: Option<f64>,
+    /// How many data packets in a row the timestamp policy has dropped or nudged.
+    rewound_in_a_row: u32,
+    /// Collects an example of each frame shape, see `set_corpus`.
+    #[cfg(not(feature = "no-alloc"))]
+    corpus: Option<FrameCorpus>,
+    /// Bytes appended to the buffer since the parser was created.
+    bytes_received: u64,
+    /// Where the stream lost sync, see `set_resync_log`.
+    #[cfg(not(feature = "no-alloc"))]
+    resync_log: Option<ResyncLog>,
+    /// Rejected frames, oldest first, while `collect_parse_errors` is on.
+    parse_errors: Option<ParseErrors>,
+    /// Timestamp warnings and restarts, oldest first, see `get_timestamp_event`.
+    timestamp_events: FixedQueue<TimestampEvent, { SerialParser::MAX_TIMESTAMP_EVENTS }>,
+    /// Sequence number the next numbered frame should have.
+    next_sequence: Option<u16>,
+    /// Breaks in the frames' sequence numbers, oldest first, see `get_sequence_gap`.
+    sequence_gaps: FixedQueue<SequenceGap, { SerialParser::MAX_SEQUENCE_GAPS }>,
 }
 
+/// The errors kept by `SerialParser::collect_parse_errors`.
+type ParseErrors = FixedQueue<ParseError, { SerialParser::MAX_PARSE_ERRORS }>;
+
 impl SerialParser {
+    /// Most undecodable frames kept for `get_raw_frame`; older ones are dropped first so a
+    /// caller that never reads them doesn't grow the parser without bound.
+    pub const MAX_RAW_FRAMES: usize = 64;
+
+    /// Longest payload a frame's length field may claim. A start word followed by a longer
+    /// length is taken to be noise rather than waiting for the bytes of a frame that can't
+    /// exist. This is several times FIRM's largest payload, the calibration response.
+    pub const MAX_PAYLOAD_LENGTH: usize = 1024;
+
+    /// Most errors kept for `get_parse_error`; older ones are dropped first.
+    pub const MAX_PARSE_ERRORS: usize = 64;
+
+    /// Most events kept for `get_timestamp_event`; older ones are dropped first.
+    pub const MAX_TIMESTAMP_EVENTS: usize = 64;
+
+    /// Most gaps kept for `get_sequence_gap`; older ones are dropped first.
+    pub const MAX_SEQUENCE_GAPS: usize = 64;
+
+    /// Default for `set_max_buffer_len`, room for a few dozen data packets.
+    pub const DEFAULT_MAX_BUFFER_LEN: usize = 4096;
+
+    /// Default for `set_queue_capacity`, ten seconds of data packets at 1 kHz.
+    pub const DEFAULT_QUEUE_CAPACITY: usize = 10_000;
+
     /// Creates a new empty `SerialParser`.
     ///
     /// # Arguments
@@ -26,193 +463,1324 @@ impl SerialParser {
     ///
     /// - `Self` - A new parser instance with empty internal state.
     pub fn new() -> Self {
+        Self::with_storage()
+    }
+
+    /// Rebuilds a parser from a `snapshot`, see `restore_with_storage`.
+    #[cfg(not(feature = "no-alloc"))]
+    pub fn restore(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        Self::restore_with_storage(bytes)
+    }
+}
+
+impl<S: ParserStorage> SerialParser<S> {
+    /// Creates a new empty parser on storage `S`, e.g. `FixedStorage`. Its buffer limit starts
+    /// at `DEFAULT_MAX_BUFFER_LEN`, or the storage's capacity if that's smaller.
+    ///
+    /// # Returns
+    ///
+    /// - `Self` - A new parser instance with empty internal state.
+    pub fn with_storage() -> Self {
+        let serial_bytes = S::Bytes::default();
+        let max_buffer_len = serial_bytes
+            .capacity()
+            .map_or(SerialParser::DEFAULT_MAX_BUFFER_LEN, |capacity| {
+                capacity.min(SerialParser::DEFAULT_MAX_BUFFER_LEN)
+            });
+        #[cfg(not(feature = "no-alloc"))]
+        let mut cobs = CobsFrameScanner::new();
+        #[cfg(not(feature = "no-alloc"))]
+        cobs.set_max_frame_len(max_buffer_len);
         SerialParser {
-            serial_bytes: Vec::new(),
-            parsed_data_packets: VecDeque::new(),
-            parsed_response_packets: VecDeque::new(),
+            serial_bytes,
+            parsed_data_packets: Default::default(),
+            parsed_baro_packets: Default::default(),
+            parsed_response_packets: Default::default(),
+            parsed_raw_frames: Default::default(),
+            stats: ParserStats::default(),
+            out_of_sync: false,
+            timestamp_policy: TimestampPolicy::Off,
+            overflow_policy: OverflowPolicy::DropOldest,
+            sea_level_pressure_pascals: STANDARD_SEA_LEVEL_PRESSURE_PASCALS,
+            queue_capacity: SerialParser::DEFAULT_QUEUE_CAPACITY,
+            max_buffer_len,
+            wire_format: WireFormat::Raw,
+            #[cfg(not(feature = "no-alloc"))]
+            cobs,
+            last_timestamp: None,
+            rewound_in_a_row: 0,
+            #[cfg(not(feature = "no-alloc"))]
+            corpus: None,
+            bytes_received: 0,
+            #[cfg(not(feature = "no-alloc"))]
+            resync_log: None,
+            parse_errors: None,
+            timestamp_events: FixedQueue::default(),
+            next_sequence: None,
+            sequence_gaps: FixedQueue::default(),
         }
     }
 
-    /// Feeds new bytes into the parser and queues any fully decoded data packets or command
-    /// responses. How this function works is that it appends incoming bytes to an internal
-    /// buffer, then scans through that buffer looking for data packets or responses. When
-    /// it finds one, it extracts and decodes it and then queues it for later retrieval.
-    ///
-    /// Additionally, command responses have the same amount of bytes as data packets, so
-    /// they follow the same length and CRC rules. However, they have different start bytes.
+    /// Sets which packet is dropped when a queue is full from now on, see `OverflowPolicy`.
+    pub fn set_overflow_policy(&mut self, policy: OverflowPolicy) {
+        self.overflow_policy = policy;
+    }
+
+    /// Returns the policy set by `set_overflow_policy`.
+    pub fn overflow_policy(&self) -> OverflowPolicy {
+        self.overflow_policy
+    }
+
+    /// Sets how many packets each of the data, barometer and response queues may hold, so a
+    /// caller that stops reading one doesn't grow the parser without bound. Once a queue is
+    /// full, each new packet drops one by the `OverflowPolicy` and counts it in
+    /// `ParserStats::packets_dropped`. Queues already longer keep their packets until read.
     ///
     /// # Arguments
     ///
-    /// - `bytes` (`&[u8]`) - Incoming raw bytes read from the FIRM serial stream.
+    /// - `capacity` (`usize`) - Packets per queue, at least 1. Storage with a smaller fixed
+    ///   capacity fills up first.
+    pub fn set_queue_capacity(&mut self, capacity: usize) {
+        self.queue_capacity = capacity.max(1);
+    }
+
+    /// Returns the limit set by `set_queue_capacity`.
+    pub fn queue_capacity(&self) -> usize {
+        self.queue_capacity
+    }
+
+    /// Sets how out of order data packets are handled from now on, see `TimestampPolicy`.
+    pub fn set_timestamp_policy(&mut self, policy: TimestampPolicy) {
+        self.timestamp_policy = policy;
+    }
+
+    /// Returns the policy set by `set_timestamp_policy`.
+    pub fn timestamp_policy(&self) -> TimestampPolicy {
+        self.timestamp_policy
+    }
+
+    /// Sets the reference pressure `p0` data packets' `pressure_altitude_meters` is computed
+    /// from with `altitude::pressure_altitude_meters`, for packets parsed from now on. Views
+    /// from `parse_bytes_with` compute it from the same pressure.
     ///
-    /// # Returns
+    /// # Arguments
     ///
-    /// - `()` - No direct return; parsed packets are stored internally for `get_packet`.
-    pub fn parse_bytes(&mut self, bytes: &[u8]) {
-        // Append new bytes onto the rolling buffer.
-        self.serial_bytes.extend(bytes);
+    /// - `pascals` (`f32`) - The local sea level pressure (e.g. the QNH) for altitude above
+    ///   sea level, or the pressure on the ground for height above it. Defaults to
+    ///   `altitude::STANDARD_SEA_LEVEL_PRESSURE_PASCALS`.
+    pub fn set_sea_level_pressure(&mut self, pascals: f32) {
+        self.sea_level_pressure_pascals = pascals;
+    }
 
-        let mut position = 0usize;
-        // Scan through the buffer looking for start words and valid packets.
-        while position + 1 < self.serial_bytes.len() {
-            // Need at least the 2-byte message id to consider a start.
-            let potential_header =
-                u16::from_le_bytes([self.serial_bytes[position], self.serial_bytes[position + 1]]);
-            // TODO: when adding new packet types, extend this check to use a switch statement
-            let is_data = potential_header == PacketHeader::Data as u16;
-            let is_response = potential_header == PacketHeader::Response as u16;
-            if !is_data && !is_response {
-                position += 1;
-                continue;
-            }
+    /// Returns the reference pressure set by `set_sea_level_pressure`.
+    pub fn sea_level_pressure(&self) -> f32 {
+        self.sea_level_pressure_pascals
+    }
 
-            let header_start = position;
+    /// Sets how many bytes the parser may hold on to while waiting for the rest of a frame.
+    /// A start word whose frame, going by its length field, wouldn't fit is skipped and
+    /// counted in `ParserStats::overflow_discards`, so a corrupted length or a stream that
+    /// stops mid-frame can't grow the buffer past this. Frames longer than this can't be
+    /// parsed at all, so it must be at least as long as the longest frame expected.
+    ///
+    /// # Arguments
+    ///
+    /// - `max_buffer_len` (`usize`) - The limit in bytes, at least `MIN_PACKET_SIZE` and at
+    ///   most the storage's capacity.
+    pub fn set_max_buffer_len(&mut self, max_buffer_len: usize) {
+        let capacity = self.serial_bytes.capacity().unwrap_or(usize::MAX);
+        self.max_buffer_len = max_buffer_len.max(MIN_PACKET_SIZE).min(capacity);
+        #[cfg(not(feature = "no-alloc"))]
+        self.cobs.set_max_frame_len(self.max_buffer_len);
+    }
 
-            // Need at least header+len+crc.
-            if header_start + MIN_PACKET_SIZE > self.serial_bytes.len() {
-                break;
-            }
+    /// Returns the limit set by `set_max_buffer_len`.
+    pub fn max_buffer_len(&self) -> usize {
+        self.max_buffer_len
+    }
 
-            let length_start = header_start + HEADER_SIZE + IDENTIFIER_SIZE;
-            let length_bytes: [u8; LENGTH_SIZE] = self.serial_bytes
-                [length_start..length_start + LENGTH_SIZE]
-                .try_into()
-                .unwrap();
-            let length = u32::from_le_bytes(length_bytes) as usize;
+    /// Returns how many bytes are buffered waiting for the rest of a frame. Never more than
+    /// `max_buffer_len` once `parse_bytes` returns, or in `WireFormat::Cobs` than a frame that
+    /// long takes encoded.
+    pub fn buffered_len(&self) -> usize {
+        #[cfg(not(feature = "no-alloc"))]
+        return self.serial_bytes.len() + self.cobs.buffered_len();
+        #[cfg(feature = "no-alloc")]
+        self.serial_bytes.len()
+    }
 
-            let payload_start = length_start + LENGTH_SIZE;
-            let crc_start = payload_start + length;
-            let packet_end = crc_start + CRC_SIZE;
+    /// Sets how frames are laid out in the bytes parsed from now on, e.g. `WireFormat::Cobs`
+    /// for a link that stuffs its frames. Bytes still buffered in the old format are dropped
+    /// and counted as skipped.
+    ///
+    /// # Arguments
+    ///
+    /// - `wire_format` (`WireFormat`) - The format. Defaults to `WireFormat::Raw`.
+    pub fn set_wire_format(&mut self, wire_format: WireFormat) {
+        if wire_format != self.wire_format {
+            #[cfg(not(feature = "no-alloc"))]
+            let buffered = self.serial_bytes.len() + self.cobs.clear();
+            #[cfg(feature = "no-alloc")]
+            let buffered = self.serial_bytes.len();
+            self.serial_bytes.consume(self.serial_bytes.len());
+            self.stats.bytes_skipped += buffered as u64;
+            self.wire_format = wire_format;
+        }
+    }
 
-            // If we don't have the full packet yet, wait for more bytes
-            if packet_end > self.serial_bytes.len() {
-                break;
-            }
+    /// Returns the format set by `set_wire_format`.
+    pub fn wire_format(&self) -> WireFormat {
+        self.wire_format
+    }
 
-            // Compute CRC over [header][identifier][len][payload].
-            let data_to_crc = &self.serial_bytes[header_start..crc_start];
-            let data_crc = crc16_ccitt(data_to_crc);
-            let crc_value = u16::from_le_bytes([
-                self.serial_bytes[crc_start],
-                self.serial_bytes[crc_start + 1],
-            ]);
+    /// Starts or stops collecting an example of each frame shape the parser sees, see
+    /// `FrameCorpus`. Frames are offered to the corpus whether they're accepted or rejected.
+    ///
+    /// # Arguments
+    ///
+    /// - `corpus` (`Option<FrameCorpus>`) - The corpus to add to, or `None` to stop collecting.
+    #[cfg(not(feature = "no-alloc"))]
+    pub fn set_corpus(&mut self, corpus: Option<FrameCorpus>) {
+        self.corpus = corpus;
+    }
 
-            // If CRC doesn't match, skip this start byte and keep looking
-            if data_crc != crc_value {
-                position += 1;
-                continue;
-            }
+    /// Returns the corpus set by `set_corpus`, with the frames collected so far.
+    #[cfg(not(feature = "no-alloc"))]
+    pub fn corpus(&self) -> Option<&FrameCorpus> {
+        self.corpus.as_ref()
+    }
 
-            let packet_bytes = &self.serial_bytes[header_start..packet_end];
+    /// Removes the corpus set by `set_corpus` and returns it, which stops collecting.
+    #[cfg(not(feature = "no-alloc"))]
+    pub fn take_corpus(&mut self) -> Option<FrameCorpus> {
+        self.corpus.take()
+    }
 
-            if is_data {
-                // If we successfully parse, queue the frame, otherwise keep looking
-                if let Ok(frame) = FIRMDataPacket::from_bytes(packet_bytes) {
-                    self.parsed_data_packets.push_back(frame);
-                } else {
-                    position += 1;
-                    continue;
-                }
-            } else if let Ok(frame) = FIRMResponsePacket::from_bytes(packet_bytes) {
-                self.parsed_response_packets.push_back(frame);
-            } else {
-                position += 1;
-                continue;
+    /// Starts recording where the stream loses sync: the offset of the first byte skipped
+    /// each time the parser stops finding frames, and why. Only the latest `capacity` events
+    /// are kept until `take_resync_events`.
+    ///
+    /// # Arguments
+    ///
+    /// - `capacity` (`Option<usize>`) - Most events kept, or `None` to stop recording and drop
+    ///   the events kept so far.
+    #[cfg(not(feature = "no-alloc"))]
+    pub fn set_resync_log(&mut self, capacity: Option<usize>) {
+        self.resync_log = capacity.map(|capacity| {
+            let mut events = self
+                .resync_log
+                .take()
+                .map(|log| log.events)
+                .unwrap_or_default();
+            while events.len() > capacity {
+                events.pop_front();
             }
+            ResyncLog { events, capacity }
+        });
+    }
 
-            position = packet_end;
+    /// Removes and returns the events recorded since the last call, oldest first. Empty unless
+    /// `set_resync_log` turned recording on.
+    #[cfg(not(feature = "no-alloc"))]
+    pub fn take_resync_events(&mut self) -> Vec<ResyncEvent> {
+        self.resync_log
+            .as_mut()
+            .map_or_else(Vec::new, |log| log.events.drain(..).collect())
+    }
+
+    /// Turns collecting parse errors on or off. While on, every frame rejected for its length
+    /// or CRC, or that doesn't decode as its packet, is kept as a `ParseError` for
+    /// `get_parse_error`. Off by default. Turning it off drops the errors not yet taken.
+    ///
+    /// # Arguments
+    ///
+    /// - `enabled` (`bool`) - Whether to collect parse errors.
+    pub fn collect_parse_errors(&mut self, enabled: bool) {
+        match (enabled, &self.parse_errors) {
+            (true, None) => self.parse_errors = Some(ParseErrors::default()),
+            (false, Some(_)) => self.parse_errors = None,
+            _ => {}
         }
+    }
 
-        // Drop all bytes that were processed, we keep only the tail for next call.
-        self.serial_bytes = self.serial_bytes[position..].to_vec();
+    /// Pops the oldest error collected by `collect_parse_errors`, if any.
+    pub fn get_parse_error(&mut self) -> Option<ParseError> {
+        self.parse_errors.as_mut()?.pop_front()
     }
 
-    /// Pops the next parsed packet from the internal queue, if available.
+    /// Pops the oldest `TimestampEvent` reported under the timestamp policy, if any. Only the
+    /// latest `MAX_TIMESTAMP_EVENTS` are kept.
+    pub fn get_timestamp_event(&mut self) -> Option<TimestampEvent> {
+        self.timestamp_events.pop_front()
+    }
+
+    /// Pops the oldest `SequenceGap` between frames that carry sequence numbers, if any.
+    /// Only the latest `MAX_SEQUENCE_GAPS` are kept.
+    pub fn get_sequence_gap(&mut self) -> Option<SequenceGap> {
+        self.sequence_gaps.pop_front()
+    }
+
+    /// Returns how many bytes the parser has been given since it was created, the offset
+    /// `ResyncEvent::byte_offset` counts from. A chunk larger than a fixed buffer can hold is
+    /// counted as its bytes get in.
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received
+    }
+
+    /// Drops all buffered bytes, queued packets and stats, like a new parser, but keeps the
+    /// timestamp and overflow policies, sea level pressure, queue capacity, buffer limit, wire
+    /// format, corpus, resync log, parse errors, timestamp events, sequence gaps and byte
+    /// offset. Used when the stream restarts, e.g. after a reconnect, so the next sequence
+    /// number isn't checked.
+    pub fn reset(&mut self) {
+        #[cfg(not(feature = "no-alloc"))]
+        let mut cobs = core::mem::take(&mut self.cobs);
+        #[cfg(not(feature = "no-alloc"))]
+        cobs.clear();
+        *self = Self {
+            timestamp_policy: self.timestamp_policy,
+            overflow_policy: self.overflow_policy,
+            sea_level_pressure_pascals: self.sea_level_pressure_pascals,
+            queue_capacity: self.queue_capacity,
+            max_buffer_len: self.max_buffer_len,
+            wire_format: self.wire_format,
+            #[cfg(not(feature = "no-alloc"))]
+            cobs,
+            #[cfg(not(feature = "no-alloc"))]
+            corpus: self.corpus.take(),
+            bytes_received: self.bytes_received,
+            #[cfg(not(feature = "no-alloc"))]
+            resync_log: self.resync_log.take(),
+            parse_errors: self.parse_errors.take(),
+            timestamp_events: core::mem::take(&mut self.timestamp_events),
+            sequence_gaps: core::mem::take(&mut self.sequence_gaps),
+            ..Self::with_storage()
+        };
+    }
+
+    /// Feeds new bytes into the parser and queues any fully decoded data packets or command
+    /// responses. How this function works is that it appends incoming bytes to an internal
+    /// buffer, then scans through that buffer looking for data packets or responses. When
+    /// it finds one, it extracts and decodes it and then queues it for later retrieval.
+    ///
+    /// Additionally, command responses have the same amount of bytes as data packets, so
+    /// they follow the same length and CRC rules. However, they have different start bytes.
     ///
     /// # Arguments
     ///
-    /// - *None* - Operates on the parser's existing queued packets.
+    /// - `bytes` (`&[u8]`) - Incoming raw bytes read from the FIRM serial stream.
     ///
     /// # Returns
     ///
-    /// - `Option<FIRMDataPacket>` - `Some(frame)` if a frame is available, otherwise `None`.
-    pub fn get_data_packet(&mut self) -> Option<FIRMDataPacket> {
-        self.parsed_data_packets.pop_front()
+    /// - `ParseSummary` - How many packets the call parsed and bytes it used up. The packets
+    ///   themselves are stored internally for `get_packet`.
+    pub fn parse_bytes(&mut self, bytes: &[u8]) -> ParseSummary {
+        self.parse_into(bytes, &mut Output::Queue)
     }
 
-    /// Pops the next parsed command response from the internal queue, if available.
+    /// Like `parse_bytes`, but hands each data packet to `visit` as a `PacketView` borrowing
+    /// its payload from the parser's buffer, instead of decoding and queuing it. Fields are
+    /// decoded only when `visit` asks for them. The timestamp policy still applies, and
+    /// barometer readings, responses and raw frames are queued as usual.
     ///
     /// # Arguments
     ///
-    /// - *None* - Operates on the parser's existing queued responses.
+    /// - `bytes` (`&[u8]`) - Incoming raw bytes read from the FIRM serial stream.
+    /// - `visit` (`impl FnMut(PacketView<'_>)`) - Called with each data packet, in order.
     ///
     /// # Returns
     ///
-    /// - `Option<FIRMResponsePacket>` - `Some(frame)` if a frame is available, otherwise `None`.
-    pub fn get_response_packet(&mut self) -> Option<FIRMResponsePacket> {
-        self.parsed_response_packets.pop_front()
+    /// - `ParseSummary` - As for `parse_bytes`, counting the data packets `visit` was given.
+    pub fn parse_bytes_with(
+        &mut self,
+        bytes: &[u8],
+        mut visit: impl FnMut(PacketView<'_>),
+    ) -> ParseSummary {
+        self.parse_into(bytes, &mut Output::View(&mut visit))
     }
-}
 
-impl Default for SerialParser {
-    fn default() -> Self {
-        Self::new()
+    /// Like `parse_bytes`, but hands data packets and responses to `sink` as each is decoded
+    /// instead of queuing them, in the order they arrived. Barometer readings go to the sink
+    /// too, and are queued if it hands them back, as by default. The timestamp policy still
+    /// applies, and raw frames, parse errors and resync events are kept as usual.
+    ///
+    /// # Arguments
+    ///
+    /// - `bytes` (`&[u8]`) - Incoming raw bytes read from the FIRM serial stream.
+    /// - `sink` (`&mut P`) - Where the packets go.
+    ///
+    /// # Returns
+    ///
+    /// - `ParseSummary` - As for `parse_bytes`, counting the packets `sink` was given.
+    pub fn parse_bytes_into<P: PacketSink>(&mut self, bytes: &[u8], sink: &mut P) -> ParseSummary {
+        self.parse_into(bytes, &mut Output::Sink(sink))
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::SerialParser;
-    use crate::constants::command::FIRMCommand;
-    use crate::constants::packet::{PacketHeader, *};
-    use crate::framed_packet::FramedPacket;
 
-    fn build_framed_packet(header: PacketHeader, identifier: u16, payload: &[u8]) -> Vec<u8> {
-        FramedPacket::new(header, identifier, payload.to_vec()).to_bytes()
+    /// Parses `bytes`, sending what it finds to `out`.
+    fn parse_into(&mut self, bytes: &[u8], out: &mut Output<'_>) -> ParseSummary {
+        match self.wire_format {
+            WireFormat::Raw => self.parse_raw_into(bytes, out),
+            #[cfg(not(feature = "no-alloc"))]
+            WireFormat::Cobs => self.parse_cobs_into(bytes, out),
+        }
     }
-    #[test]
-    fn test_serial_parser_parses_data_packet() {
-        let mut payload = vec![0u8; 120];
-        payload[0..8].copy_from_slice(&42.0f64.to_le_bytes());
-        payload[8..12].copy_from_slice(&25.0f32.to_le_bytes());
-
-        let bytes = build_framed_packet(PacketHeader::Data, 0, &payload);
-        let mut parser = SerialParser::new();
-        parser.parse_bytes(&bytes);
 
-        let packet = parser.get_data_packet().expect("expected one data frame");
-        assert_eq!(packet.data().timestamp_seconds, 42.0);
-        assert_eq!(packet.data().temperature_celsius, 25.0);
-        assert!(parser.get_data_packet().is_none());
-        assert!(parser.get_response_packet().is_none());
+    /// `parse_into` for `WireFormat::Raw`.
+    fn parse_raw_into(&mut self, mut bytes: &[u8], out: &mut Output<'_>) -> ParseSummary {
+        let mut summary = ParseSummary::default();
+        loop {
+            // Append new bytes onto the rolling buffer, as many as fit.
+            let appended = self.serial_bytes.extend_from(bytes);
+            bytes = &bytes[appended..];
+            self.bytes_received += appended as u64;
+            self.scan_buffer(out, &mut summary);
+            if bytes.is_empty() {
+                break;
+            }
+            // Scanning leaves less than a frame behind, so only a buffer too small to hold a
+            // frame header can still be full. Drop a byte so the rest can get in.
+            if self.serial_bytes.capacity() == Some(self.serial_bytes.len()) {
+                self.stats.overflow_discards += 1;
+                let mut position = 0;
+                self.skip_byte(&mut position, ResyncReason::LengthMismatch, out);
+                self.serial_bytes.consume(1);
+                summary.bytes_consumed += 1;
+            }
+        }
+        summary.bytes_buffered = self.serial_bytes.len();
+        summary
     }
 
-    #[test]
-    fn test_serial_parser_parses_response_packet_split_across_calls() {
-        // Identifier is in the identifier for response packets; payload is just the response data.
-        let payload = [1u8];
-        let bytes = build_framed_packet(
-            PacketHeader::Response,
-            FIRMCommand::SetDeviceConfig as u16,
-            &payload,
-        );
-        let mid = bytes.len() / 2;
-
-        let mut parser = SerialParser::new();
-        parser.parse_bytes(&bytes[..mid]);
-        // When we first call it, it hasnt parsed the full packet yet
-        assert!(parser.get_response_packet().is_none());
+    /// `parse_into` for `WireFormat::Cobs`. Each frame is decoded and scanned on its own, and
+    /// whatever it leaves over is dropped, so a damaged frame can't hold up the frames after
+    /// it. Offsets into a frame count from its first byte on the wire.
+    #[cfg(not(feature = "no-alloc"))]
+    fn parse_cobs_into(&mut self, bytes: &[u8], out: &mut Output<'_>) -> ParseSummary {
+        let mut summary = ParseSummary::default();
+        let buffered = self.cobs.buffered_len();
+        let received = self.bytes_received;
+        let mut cobs = core::mem::take(&mut self.cobs);
+        cobs.split(bytes, |end, wire_len, decoded| {
+            let frame_start = received + end as u64 - wire_len as u64;
+            // With nothing else buffered, `stream_offset` counts from the frame's start.
+            let mut position = 0;
+            match decoded {
+                Ok(decoded) => {
+                    let appended = self.serial_bytes.extend_from(decoded);
+                    self.bytes_received = frame_start + appended as u64;
+                    self.scan_buffer(out, &mut summary);
+                    let left_over = self.serial_bytes.len();
+                    self.skip_bytes(&mut position, left_over, ResyncReason::LengthMismatch, out);
+                    self.serial_bytes.consume(left_over);
+                }
+                Err(error) => {
+                    if matches!(error, FrameError::PayloadTooLarge { .. }) {
+                        self.stats.overflow_discards += 1;
+                    }
+                    self.bytes_received = frame_start;
+                    Self::push_parse_error(&mut self.parse_errors, out, frame_start, error);
+                    self.skip_bytes(&mut position, wire_len, ResyncReason::LengthMismatch, out);
+                }
+            }
+        });
+        self.cobs = cobs;
+        self.bytes_received = received + bytes.len() as u64;
+        summary.bytes_buffered = self.cobs.buffered_len();
+        summary.bytes_consumed = buffered + bytes.len() - summary.bytes_buffered;
+        summary
+    }
 
-        parser.parse_bytes(&bytes[mid..]);
-        let _frame = parser
-            .get_response_packet()
-            .expect("expected one response frame");
-        assert!(parser.get_response_packet().is_none());
+    /// Parses every complete frame in the buffer, dropping the bytes that were processed, and
+    /// counts what it found in `summary`.
+    fn scan_buffer(&mut self, out: &mut Output<'_>, summary: &mut ParseSummary) {
+        let mut position = 0usize;
+        // Scan through the buffer looking for start words and valid packets.
+        while position + 1 < self.serial_bytes.len() {
+            let header_start = position;
+            let frame_offset = self.stream_offset(header_start);
+            let (sequence, payload_start, crc_start, packet_end) = match scan_frame(
+                self.serial_bytes.as_slice(),
+                position,
+                &START_WORDS,
+                SerialParser::MAX_PAYLOAD_LENGTH,
+                self.max_buffer_len,
+            ) {
+                FrameScan::Noise(count) => {
+                    self.skip_bytes(&mut position, count, ResyncReason::NoStartBytes, out);
+                    continue;
+                }
+                // If we don't have the full packet yet, wait for more bytes
+                FrameScan::Incomplete => break,
+                FrameScan::PayloadTooLong { length } => {
+                    self.stats.length_rejects += 1;
+                    Self::push_parse_error(
+                        &mut self.parse_errors,
+                        out,
+                        frame_offset,
+                        FrameError::PayloadTooLarge {
+                            max: SerialParser::MAX_PAYLOAD_LENGTH,
+                            got: length,
+                        },
+                    );
+                    self.skip_byte(&mut position, ResyncReason::LengthMismatch, out);
+                    continue;
+                }
+                FrameScan::FrameTooLong { length } => {
+                    self.stats.overflow_discards += 1;
+                    Self::push_parse_error(
+                        &mut self.parse_errors,
+                        out,
+                        frame_offset,
+                        FrameError::PayloadTooLarge {
+                            max: self.max_buffer_len - MIN_PACKET_SIZE,
+                            got: length,
+                        },
+                    );
+                    self.skip_byte(&mut position, ResyncReason::LengthMismatch, out);
+                    continue;
+                }
+                // If CRC doesn't match, skip this start byte and keep looking
+                FrameScan::BadCrc { end, expected, got } => {
+                    self.stats.crc_failures += 1;
+                    Self::push_parse_error(
+                        &mut self.parse_errors,
+                        out,
+                        frame_offset,
+                        FrameError::BadCrc { expected, got },
+                    );
+                    self.record_frame(header_start..end, FrameOutcome::BadCrc);
+                    self.skip_byte(&mut position, ResyncReason::BadCrc, out);
+                    continue;
+                }
+                FrameScan::Frame {
+                    sequence,
+                    payload_start,
+                    crc_start,
+                    end,
+                } => (sequence, payload_start, crc_start, end),
+            };
+            if let Some(sequence) = sequence {
+                self.check_sequence(sequence, out);
+            }
+
+            // The start word is either a data packet's or a response's.
+            // TODO: when adding new packet types, extend START_WORDS and use a switch statement
+            let is_data = self.serial_bytes.as_slice()[header_start..header_start + HEADER_SIZE]
+                == START_WORDS[0];
+
+            let packet_bytes = &self.serial_bytes.as_slice()[header_start..packet_end];
+
+            let identifier = u16::from_le_bytes([
+                self.serial_bytes.as_slice()[header_start + HEADER_SIZE],
+                self.serial_bytes.as_slice()[header_start + HEADER_SIZE + 1],
+            ]) & !SEQUENCE_FLAG;
+
+            let outcome = if is_data && identifier == BARO_PACKET_IDENTIFIER {
+                // Barometer readings skip the full packet decode and its framed copy.
+                match FIRMBaroPacket::from_bytes(
+                    &self.serial_bytes.as_slice()[payload_start..crc_start],
+                ) {
+                    Ok(packet) => {
+                        let unclaimed = match out {
+                            Output::Sink(sink) => sink.on_baro(packet),
+                            _ => Some(packet),
+                        };
+                        if let Some(packet) = unclaimed {
+                            enqueue(
+                                &mut self.parsed_baro_packets,
+                                packet,
+                                self.overflow_policy,
+                                self.queue_capacity,
+                                &mut self.stats,
+                            );
+                        }
+                        FrameOutcome::Accepted
+                    }
+                    Err(e) => {
+                        Self::push_parse_error(&mut self.parse_errors, out, frame_offset, e);
+                        Self::queue_raw_frame(&mut self.parsed_raw_frames, packet_bytes)
+                    }
+                }
+            } else if is_data && let Output::View(visit) = out {
+                let payload = &self.serial_bytes.as_slice()[payload_start..crc_start];
+                match FIRMData::check_payload(payload) {
+                    Ok(_) => {
+                        let view = PacketView::new(payload)
+                            .unwrap()
+                            .with_sea_level_pressure(self.sea_level_pressure_pascals);
+                        let (correction, event) = Self::correct_timestamp(
+                            self.timestamp_policy,
+                            &mut self.last_timestamp,
+                            &mut self.rewound_in_a_row,
+                            &mut self.stats,
+                            view.timestamp_seconds(),
+                        );
+                        if let Some(event) = event {
+                            Self::push_timestamp_event(&mut self.timestamp_events, event);
+                        }
+                        match correction {
+                            TimestampCorrection::Keep => {
+                                visit(view);
+                                summary.data_packets += 1;
+                            }
+                            TimestampCorrection::Nudge(timestamp) => {
+                                visit(view.nudged(timestamp));
+                                summary.data_packets += 1;
+                            }
+                            TimestampCorrection::Drop => {}
+                        }
+                        FrameOutcome::Accepted
+                    }
+                    Err(e) => {
+                        Self::push_parse_error(&mut self.parse_errors, out, frame_offset, e.into());
+                        Self::queue_raw_frame(&mut self.parsed_raw_frames, packet_bytes)
+                    }
+                }
+            } else if is_data {
+                // If we successfully parse, queue the frame, otherwise keep looking
+                match FIRMDataPacket::from_bytes(packet_bytes) {
+                    Ok(frame) => {
+                        if self.deliver_data_packet(frame, out) {
+                            summary.data_packets += 1;
+                        }
+                        FrameOutcome::Accepted
+                    }
+                    Err(e) => {
+                        Self::push_parse_error(&mut self.parse_errors, out, frame_offset, e);
+                        Self::queue_raw_frame(&mut self.parsed_raw_frames, packet_bytes)
+                    }
+                }
+            } else {
+                match FIRMResponsePacket::from_bytes(packet_bytes) {
+                    Ok(frame) => {
+                        match out {
+                            Output::Sink(sink) => sink.on_response(frame),
+                            _ => enqueue(
+                                &mut self.parsed_response_packets,
+                                frame,
+                                self.overflow_policy,
+                                self.queue_capacity,
+                                &mut self.stats,
+                            ),
+                        }
+                        summary.responses += 1;
+                        FrameOutcome::Accepted
+                    }
+                    Err(e) => {
+                        Self::push_parse_error(&mut self.parse_errors, out, frame_offset, e);
+                        Self::queue_raw_frame(&mut self.parsed_raw_frames, packet_bytes)
+                    }
+                }
+            };
+            self.record_frame(header_start..packet_end, outcome);
+            match outcome {
+                FrameOutcome::Accepted => self.stats.packets_parsed += 1,
+                FrameOutcome::Undecodable => self.stats.decode_failures += 1,
+                _ => {}
+            }
+            if outcome == FrameOutcome::Malformed {
+                self.skip_byte(&mut position, ResyncReason::LengthMismatch, out);
+                continue;
+            }
+
+            if self.out_of_sync {
+                self.stats.resyncs += 1;
+                self.out_of_sync = false;
+            }
+            position = packet_end;
+        }
+
+        // Drop all bytes that were processed, we keep only the tail for next call.
+        self.serial_bytes.consume(position);
+        summary.bytes_consumed += position;
+    }
+
+    /// Applies the timestamp policy to a data packet sent at `timestamp`, updating the last
+    /// timestamp and the counters. Also returns the event to report, if any.
+    fn correct_timestamp(
+        policy: TimestampPolicy,
+        last_timestamp: &mut Option<f64>,
+        rewound_in_a_row: &mut u32,
+        stats: &mut ParserStats,
+        timestamp: f64,
+    ) -> (TimestampCorrection, Option<TimestampEvent>) {
+        let mut event = None;
+        let correction = match *last_timestamp {
+            Some(last) if timestamp <= last => match policy {
+                TimestampPolicy::Off => TimestampCorrection::Keep,
+                _ if is_device_restart(last, timestamp) => {
+                    event = Some(TimestampEvent::DeviceRestartDetected {
+                        previous_seconds: last,
+                        timestamp_seconds: timestamp,
+                    });
+                    TimestampCorrection::Keep
+                }
+                // The last timestamp is the odd one out, not every packet since.
+                _ if *rewound_in_a_row >= TIMESTAMP_RESYNC_PACKETS => {
+                    event = Some(TimestampEvent::Resynced {
+                        previous_seconds: last,
+                        timestamp_seconds: timestamp,
+                    });
+                    TimestampCorrection::Keep
+                }
+                TimestampPolicy::Strict => {
+                    stats.timestamps_dropped += 1;
+                    *rewound_in_a_row += 1;
+                    return (TimestampCorrection::Drop, None);
+                }
+                TimestampPolicy::Nudge => {
+                    stats.timestamps_nudged += 1;
+                    *rewound_in_a_row += 1;
+                    TimestampCorrection::Nudge(last + TIMESTAMP_NUDGE_SECONDS)
+                }
+                TimestampPolicy::Warn => {
+                    event = Some(TimestampEvent::OutOfOrder {
+                        previous_seconds: last,
+                        timestamp_seconds: timestamp,
+                    });
+                    TimestampCorrection::Keep
+                }
+            },
+            _ => TimestampCorrection::Keep,
+        };
+        *last_timestamp = Some(match correction {
+            TimestampCorrection::Nudge(nudged) => nudged,
+            _ => {
+                *rewound_in_a_row = 0;
+                timestamp
+            }
+        });
+        (correction, event)
+    }
+
+    /// Checks a frame's sequence number against the last one, reporting a gap to the sink and
+    /// keeping it for `get_sequence_gap`, dropping the oldest if there are too many.
+    fn check_sequence(&mut self, sequence: u16, out: &mut Output<'_>) {
+        let Some(gap) = SequenceGap::check(&mut self.next_sequence, sequence) else {
+            return;
+        };
+        if let Output::Sink(sink) = out {
+            sink.on_sequence_gap(&gap);
+        }
+        push_dropping_oldest(&mut self.sequence_gaps, gap);
+    }
+
+    /// Keeps `event` for `get_timestamp_event`, dropping the oldest if there are too many.
+    fn push_timestamp_event(
+        events: &mut FixedQueue<TimestampEvent, { SerialParser::MAX_TIMESTAMP_EVENTS }>,
+        event: TimestampEvent,
+    ) {
+        push_dropping_oldest(events, event);
+    }
+
+    /// Queues a data packet, or hands it to the sink if there is one, applying the timestamp
+    /// policy. Returns whether the policy let it through.
+    fn deliver_data_packet(&mut self, mut packet: FIRMDataPacket, out: &mut Output<'_>) -> bool {
+        let (correction, event) = Self::correct_timestamp(
+            self.timestamp_policy,
+            &mut self.last_timestamp,
+            &mut self.rewound_in_a_row,
+            &mut self.stats,
+            packet.data().timestamp_seconds,
+        );
+        if let Some(event) = event {
+            if let Output::Sink(sink) = out {
+                sink.on_timestamp_event(&event);
+            }
+            Self::push_timestamp_event(&mut self.timestamp_events, event);
+        }
+        match correction {
+            TimestampCorrection::Keep => {}
+            TimestampCorrection::Nudge(timestamp) => packet.nudge_timestamp(timestamp),
+            TimestampCorrection::Drop => return false,
+        }
+        packet.fill_pressure_altitude(self.sea_level_pressure_pascals);
+        match out {
+            Output::Sink(sink) => sink.on_data(packet),
+            _ => enqueue(
+                &mut self.parsed_data_packets,
+                packet,
+                self.overflow_policy,
+                self.queue_capacity,
+                &mut self.stats,
+            ),
+        }
+        true
+    }
+
+    /// Queues a CRC-valid frame that isn't a known packet (e.g. a prototype identifier).
+    /// Returns `FrameOutcome::Malformed` if the bytes aren't a well-formed frame at all.
+    fn queue_raw_frame(raw_frames: &mut S::RawFrames, packet_bytes: &[u8]) -> FrameOutcome {
+        let Ok(frame) = FramedPacket::from_bytes(packet_bytes) else {
+            return FrameOutcome::Malformed;
+        };
+        if raw_frames.len() >= SerialParser::MAX_RAW_FRAMES {
+            raw_frames.pop_front();
+        }
+        push_dropping_oldest(raw_frames, frame);
+        FrameOutcome::Undecodable
+    }
+
+    /// Offers the frame at `range` of the buffer to the corpus, if one is set. There's no
+    /// corpus with the `no-alloc` feature.
+    fn record_frame(&mut self, range: core::ops::Range<usize>, outcome: FrameOutcome) {
+        #[cfg(not(feature = "no-alloc"))]
+        if let Some(corpus) = &mut self.corpus {
+            corpus.record(&self.serial_bytes.as_slice()[range], outcome);
+        }
+        #[cfg(feature = "no-alloc")]
+        let _ = (range, outcome);
+    }
+
+    /// Returns the offset into the stream of `position` in the buffer.
+    fn stream_offset(&self, position: usize) -> u64 {
+        self.bytes_received - self.serial_bytes.len() as u64 + position as u64
+    }
+
+    /// Keeps `error` for `get_parse_error` if `collect_parse_errors` is on, and shows it to the
+    /// sink if there is one.
+    fn push_parse_error(
+        parse_errors: &mut Option<ParseErrors>,
+        out: &mut Output<'_>,
+        byte_offset: u64,
+        error: FrameError,
+    ) {
+        let error = ParseError { byte_offset, error };
+        if let Output::Sink(sink) = out {
+            sink.on_parse_error(&error);
+        }
+        if let Some(errors) = parse_errors {
+            push_dropping_oldest(errors, error);
+        }
+    }
+
+    /// Advances past one byte that could not start a valid frame, recording a `ResyncEvent`
+    /// for `reason` if it's the first byte skipped since the last valid frame.
+    fn skip_byte(&mut self, position: &mut usize, reason: ResyncReason, out: &mut Output<'_>) {
+        self.skip_bytes(position, 1, reason, out);
+    }
+
+    /// Like `skip_byte`, for `count` bytes in a row, none of which could start a valid frame.
+    fn skip_bytes(
+        &mut self,
+        position: &mut usize,
+        count: usize,
+        reason: ResyncReason,
+        out: &mut Output<'_>,
+    ) {
+        if count == 0 {
+            return;
+        }
+        if !self.out_of_sync {
+            let event = ResyncEvent {
+                byte_offset: self.stream_offset(*position),
+                reason,
+            };
+            if let Output::Sink(sink) = out {
+                sink.on_lost_sync(&event);
+            }
+            #[cfg(not(feature = "no-alloc"))]
+            if let Some(log) = &mut self.resync_log
+                && log.capacity > 0
+            {
+                if log.events.len() == log.capacity {
+                    log.events.pop_front();
+                }
+                log.events.push_back(event);
+            }
+        }
+        *position += count;
+        self.stats.bytes_skipped += count as u64;
+        self.out_of_sync = true;
+    }
+
+    /// Returns the parse counters accumulated since the parser was created or the stats were
+    /// last reset.
+    ///
+    /// # Returns
+    ///
+    /// - `ParserStats` - A snapshot of the parsed packet, CRC failure, length reject, skipped
+    ///   byte and resync counters.
+    pub fn stats(&self) -> ParserStats {
+        self.stats
+    }
+
+    /// Zeroes the parse counters, leaving buffered bytes and queued packets alone.
+    pub fn reset_stats(&mut self) {
+        self.stats = ParserStats::default();
+    }
+
+    /// Pops the next parsed packet from the internal queue, if available.
+    ///
+    /// # Arguments
+    ///
+    /// - *None* - Operates on the parser's existing queued packets.
+    ///
+    /// # Returns
+    ///
+    /// - `Option<FIRMDataPacket>` - `Some(frame)` if a frame is available, otherwise `None`.
+    pub fn get_data_packet(&mut self) -> Option<FIRMDataPacket> {
+        self.parsed_data_packets.pop_front()
+    }
+
+    /// Takes every queued data packet at once, oldest first.
+    ///
+    /// # Returns
+    ///
+    /// - `Vec<FIRMDataPacket>` - The packets `get_data_packet` would have returned one by one.
+    #[cfg(not(feature = "no-alloc"))]
+    pub fn drain_packets(&mut self) -> Vec<FIRMDataPacket> {
+        core::iter::from_fn(|| self.parsed_data_packets.pop_front()).collect()
+    }
+
+    /// Returns how many data packets are queued.
+    pub fn packet_count(&self) -> usize {
+        self.parsed_data_packets.len()
+    }
+
+    /// Pops the next secondary barometer reading, see `FIRMBaroPacket`.
+    ///
+    /// # Returns
+    ///
+    /// - `Option<FIRMBaroPacket>` - `Some(packet)` if a reading is available, otherwise `None`.
+    pub fn get_baro_packet(&mut self) -> Option<FIRMBaroPacket> {
+        self.parsed_baro_packets.pop_front()
+    }
+
+    /// Pops the next CRC-valid frame that could not be decoded as a data packet or response,
+    /// such as a response with an identifier this crate doesn't know yet.
+    ///
+    /// # Returns
+    ///
+    /// - `Option<FramedPacket>` - `Some(frame)` if a frame is available, otherwise `None`.
+    pub fn get_raw_frame(&mut self) -> Option<FramedPacket> {
+        self.parsed_raw_frames.pop_front()
+    }
+
+    /// Pops the next parsed command response from the internal queue, if available.
+    ///
+    /// # Arguments
+    ///
+    /// - *None* - Operates on the parser's existing queued responses.
+    ///
+    /// # Returns
+    ///
+    /// - `Option<FIRMResponsePacket>` - `Some(frame)` if a frame is available, otherwise `None`.
+    pub fn get_response_packet(&mut self) -> Option<FIRMResponsePacket> {
+        self.parsed_response_packets.pop_front()
+    }
+
+    /// Takes every queued command response at once, oldest first.
+    ///
+    /// # Returns
+    ///
+    /// - `Vec<FIRMResponsePacket>` - The responses `get_response_packet` would have returned
+    ///   one by one.
+    #[cfg(not(feature = "no-alloc"))]
+    pub fn drain_responses(&mut self) -> Vec<FIRMResponsePacket> {
+        core::iter::from_fn(|| self.parsed_response_packets.pop_front()).collect()
+    }
+
+    /// Returns how many command responses are queued.
+    pub fn response_count(&self) -> usize {
+        self.parsed_response_packets.len()
+    }
+
+    /// Serializes the parser's whole state: the unparsed tail of the stream, the stats and
+    /// every queued packet, so a page reload can pick up mid-stream with `restore`.
+    ///
+    /// The layout is `SNAPSHOT_MAGIC`, a `u16` `SNAPSHOT_VERSION`, the stats counters as
+    /// `u64`, the out-of-sync flag, the timestamp and overflow policies and the wire format as
+    /// one byte each, the sea level pressure as an `f32`, the queue capacity, buffer limit and
+    /// received byte count as `u64`s, the last timestamp, the `u32` count of packets in a row
+    /// behind it, the next sequence number as a `u16`, the buffered bytes, still encoded
+    /// in `WireFormat::Cobs`, then the data packets as wire frames each followed by its
+    /// corrected timestamp, the response and raw frame queues as wire frames, and the
+    /// barometer queue as payloads. Every integer is
+    /// little-endian, every byte run and queue is prefixed by its `u32` length, and optional
+    /// values are a presence byte followed by the value. The corpus, resync log, parse errors
+    /// and reported events aren't included.
+    ///
+    /// # Returns
+    ///
+    /// - `Vec<u8>` - The snapshot bytes.
+    #[cfg(not(feature = "no-alloc"))]
+    pub fn snapshot(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&SNAPSHOT_MAGIC);
+        out.extend_from_slice(&SNAPSHOT_VERSION.to_le_bytes());
+        out.extend_from_slice(&self.stats.packets_parsed.to_le_bytes());
+        out.extend_from_slice(&self.stats.crc_failures.to_le_bytes());
+        out.extend_from_slice(&self.stats.length_rejects.to_le_bytes());
+        out.extend_from_slice(&self.stats.overflow_discards.to_le_bytes());
+        out.extend_from_slice(&self.stats.bytes_skipped.to_le_bytes());
+        out.extend_from_slice(&self.stats.resyncs.to_le_bytes());
+        out.extend_from_slice(&self.stats.timestamps_dropped.to_le_bytes());
+        out.extend_from_slice(&self.stats.timestamps_nudged.to_le_bytes());
+        out.extend_from_slice(&self.stats.packets_dropped.to_le_bytes());
+        out.extend_from_slice(&self.stats.decode_failures.to_le_bytes());
+        out.push(self.out_of_sync as u8);
+        out.push(self.timestamp_policy.to_byte());
+        out.push(self.overflow_policy.to_byte());
+        out.push(self.wire_format.to_byte());
+        out.extend_from_slice(&self.sea_level_pressure_pascals.to_le_bytes());
+        out.extend_from_slice(&(self.queue_capacity as u64).to_le_bytes());
+        out.extend_from_slice(&(self.max_buffer_len as u64).to_le_bytes());
+        out.extend_from_slice(&self.bytes_received.to_le_bytes());
+        push_timestamp(&mut out, self.last_timestamp);
+        out.extend_from_slice(&self.rewound_in_a_row.to_le_bytes());
+        match self.next_sequence {
+            Some(sequence) => {
+                out.push(1);
+                out.extend_from_slice(&sequence.to_le_bytes());
+            }
+            None => out.push(0),
+        }
+        let buffered = match self.wire_format {
+            WireFormat::Raw => self.serial_bytes.as_slice(),
+            WireFormat::Cobs => self.cobs.buffered(),
+        };
+        push_chunk(&mut out, buffered);
+        out.extend_from_slice(&(self.parsed_data_packets.len() as u32).to_le_bytes());
+        for packet in self.parsed_data_packets.iter() {
+            push_chunk(&mut out, &packet.to_bytes());
+            let nudged = packet
+                .original_timestamp_seconds()
+                .map(|_| packet.data().timestamp_seconds);
+            push_timestamp(&mut out, nudged);
+        }
+        push_frames(&mut out, &self.parsed_response_packets, Framed::to_bytes);
+        push_frames(&mut out, &self.parsed_raw_frames, FramedPacket::to_bytes);
+        push_frames(
+            &mut out,
+            &self.parsed_baro_packets,
+            FIRMBaroPacket::to_bytes,
+        );
+        out
+    }
+
+    /// Rebuilds a parser on storage `S` from a `snapshot`. Parsing the rest of the stream with
+    /// it gives the same packets and stats as the parser the snapshot was taken from, as long
+    /// as `S` has room for everything the snapshot holds; otherwise queued packets are dropped
+    /// by the snapshot's `OverflowPolicy`.
+    ///
+    /// # Arguments
+    ///
+    /// - `bytes` (`&[u8]`) - Bytes returned by `snapshot`.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<Self, SnapshotError>` - The restored parser, or why the snapshot was rejected.
+    ///   The version is checked before anything else is read.
+    #[cfg(not(feature = "no-alloc"))]
+    pub fn restore_with_storage(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        let mut reader = SnapshotReader { bytes };
+        if reader.take(SNAPSHOT_MAGIC.len()).ok() != Some(&SNAPSHOT_MAGIC[..]) {
+            return Err(SnapshotError::BadMagic);
+        }
+        let version = reader.u16()?;
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion {
+                found: version,
+                supported: SNAPSHOT_VERSION,
+            });
+        }
+
+        let stats = ParserStats {
+            packets_parsed: reader.u64()?,
+            crc_failures: reader.u64()?,
+            length_rejects: reader.u64()?,
+            overflow_discards: reader.u64()?,
+            bytes_skipped: reader.u64()?,
+            resyncs: reader.u64()?,
+            timestamps_dropped: reader.u64()?,
+            timestamps_nudged: reader.u64()?,
+            packets_dropped: reader.u64()?,
+            decode_failures: reader.u64()?,
+        };
+        let out_of_sync = reader.u8()? != 0;
+        let timestamp_policy =
+            TimestampPolicy::from_byte(reader.u8()?).ok_or(SnapshotError::Truncated)?;
+        let overflow_policy =
+            OverflowPolicy::from_byte(reader.u8()?).ok_or(SnapshotError::Truncated)?;
+        let wire_format = WireFormat::from_byte(reader.u8()?).ok_or(SnapshotError::Truncated)?;
+        let sea_level_pressure_pascals = f32::from_bits(reader.u32()?);
+        let queue_capacity = reader.u64()? as usize;
+        let max_buffer_len = reader.u64()? as usize;
+        let bytes_received = reader.u64()?;
+        let last_timestamp = reader.timestamp()?;
+        let rewound_in_a_row = reader.u32()?;
+        let next_sequence = match reader.u8()? {
+            0 => None,
+            _ => Some(reader.u16()?),
+        };
+        let serial_bytes = reader.chunk()?;
+        let mut data_packets = Vec::new();
+        for _ in 0..reader.u32()? {
+            let mut packet = FIRMDataPacket::from_bytes(reader.chunk()?)?;
+            packet.fill_pressure_altitude(sea_level_pressure_pascals);
+            if let Some(timestamp) = reader.timestamp()? {
+                packet.nudge_timestamp(timestamp);
+            }
+            data_packets.push(packet);
+        }
+        let responses = reader.frames(FIRMResponsePacket::from_bytes)?;
+        let raw_frames = reader.frames(FramedPacket::from_bytes)?;
+        let baro_packets = reader.frames(FIRMBaroPacket::from_bytes)?;
+        if !reader.bytes.is_empty() {
+            return Err(SnapshotError::Truncated);
+        }
+
+        let mut parser = Self::with_storage();
+        parser.stats = stats;
+        parser.out_of_sync = out_of_sync;
+        parser.timestamp_policy = timestamp_policy;
+        parser.overflow_policy = overflow_policy;
+        parser.wire_format = wire_format;
+        parser.sea_level_pressure_pascals = sea_level_pressure_pascals;
+        parser.set_queue_capacity(queue_capacity);
+        parser.set_max_buffer_len(max_buffer_len);
+        parser.last_timestamp = last_timestamp;
+        parser.rewound_in_a_row = rewound_in_a_row;
+        parser.next_sequence = next_sequence;
+        // The buffered bytes are counted again as they're appended below.
+        parser.bytes_received = bytes_received
+            .checked_sub(serial_bytes.len() as u64)
+            .ok_or(SnapshotError::Truncated)?;
+        for packet in data_packets {
+            enqueue(
+                &mut parser.parsed_data_packets,
+                packet,
+                overflow_policy,
+                parser.queue_capacity,
+                &mut parser.stats,
+            );
+        }
+        for response in responses {
+            enqueue(
+                &mut parser.parsed_response_packets,
+                response,
+                overflow_policy,
+                parser.queue_capacity,
+                &mut parser.stats,
+            );
+        }
+        for frame in raw_frames {
+            enqueue(
+                &mut parser.parsed_raw_frames,
+                frame,
+                OverflowPolicy::DropOldest,
+                SerialParser::MAX_RAW_FRAMES,
+                &mut parser.stats,
+            );
+        }
+        for baro in baro_packets {
+            enqueue(
+                &mut parser.parsed_baro_packets,
+                baro,
+                overflow_policy,
+                parser.queue_capacity,
+                &mut parser.stats,
+            );
+        }
+        if wire_format == WireFormat::Cobs {
+            // Without a delimiter, the unfinished frame is only buffered again.
+            parser.parse_bytes(serial_bytes);
+            return Ok(parser);
+        }
+        // A tail too long for `S`'s buffer is parsed as far as it goes.
+        let appended = parser.serial_bytes.extend_from(serial_bytes);
+        parser.bytes_received += appended as u64;
+        if appended < serial_bytes.len() {
+            parser.parse_bytes(&serial_bytes[appended..]);
+        }
+        Ok(parser)
+    }
+}
+
+impl<S: ParserStorage> Default for SerialParser<S> {
+    fn default() -> Self {
+        Self::with_storage()
+    }
+}
+
+/// The start words the parser looks for, as they appear on the wire.
+const START_WORDS: [[u8; HEADER_SIZE]; 2] = [
+    PacketHeader::Data.as_u16().to_le_bytes(),
+    PacketHeader::Response.as_u16().to_le_bytes(),
+];
+
+/// Where `SerialParser::parse_into` sends what it parses.
+enum Output<'a> {
+    /// The parser's own queues, as `parse_bytes` uses.
+    Queue,
+    /// `parse_bytes_with`'s visitor for data packets, with everything else queued.
+    View(&'a mut dyn FnMut(PacketView<'_>)),
+    /// `parse_bytes_into`'s sink.
+    Sink(&'a mut dyn PacketSink),
+}
+
+/// Queues `item`, dropping the oldest item first if the queue is full.
+fn push_dropping_oldest<T>(queue: &mut impl PacketQueue<T>, item: T) {
+    if let Err(item) = queue.push_back(item) {
+        queue.pop_front();
+        let _ = queue.push_back(item);
+    }
+}
+
+/// Queues `item`, dropping a packet as `policy` says if the queue holds `capacity` packets or
+/// its storage is full.
+fn enqueue<T>(
+    queue: &mut impl PacketQueue<T>,
+    item: T,
+    policy: OverflowPolicy,
+    capacity: usize,
+    stats: &mut ParserStats,
+) {
+    let item = if queue.len() < capacity {
+        match queue.push_back(item) {
+            Ok(()) => return,
+            Err(item) => item,
+        }
+    } else {
+        item
+    };
+    stats.packets_dropped += 1;
+    if policy == OverflowPolicy::DropOldest {
+        queue.pop_front();
+        let _ = queue.push_back(item);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        OverflowPolicy, ParseError, ParseSummary, ParserStats, ResyncEvent, ResyncReason,
+        SNAPSHOT_VERSION, START_WORDS, SerialParser, SnapshotError, TIMESTAMP_NUDGE_SECONDS,
+        TIMESTAMP_RESYNC_PACKETS, TimestampEvent, TimestampPolicy,
+    };
+    use crate::altitude::{STANDARD_SEA_LEVEL_PRESSURE_PASCALS, pressure_altitude_meters};
+    use crate::cobs::{COBS_DELIMITER, cobs_encode};
+    use crate::constants::command::FIRMCommand;
+    use crate::constants::packet::{PacketHeader, *};
+    use crate::corpus::{FrameCorpus, FrameOutcome};
+    use crate::firm_packets::{
+        DataLayout, FIRMBaroPacket, FIRMData, FIRMDataPacket, FIRMResponsePacket,
+    };
+    use crate::framed_packet::find_start_word;
+    use crate::framed_packet::{FrameError, Framed, FramedPacket, SequenceGap, WireFormat};
+    use crate::packet_sink::PacketSink;
+    use crate::parser_storage::{AllocStorage, ByteBuffer, FixedStorage, ParserStorage};
+    use crate::utils::crc16_ccitt;
+
+    /// The fixed storage the tests run against, big enough for every stream they build.
+    type TestFixedStorage = FixedStorage<2048, 64>;
+
+    fn build_framed_packet(header: PacketHeader, identifier: u16, payload: &[u8]) -> Vec<u8> {
+        FramedPacket::new(header, identifier, payload.to_vec()).to_bytes()
+    }
+
+    fn test_serial_parser_parses_data_packet<S: ParserStorage>() {
+        let mut payload = vec![0u8; 120];
+        payload[0..8].copy_from_slice(&42.0f64.to_le_bytes());
+        payload[8..12].copy_from_slice(&25.0f32.to_le_bytes());
+
+        let bytes = build_framed_packet(PacketHeader::Data, 0, &payload);
+        let mut parser = SerialParser::<S>::with_storage();
+        parser.parse_bytes(&bytes);
+
+        let packet = parser.get_data_packet().expect("expected one data frame");
+        assert_eq!(packet.data().timestamp_seconds, 42.0);
+        assert_eq!(packet.data().temperature_celsius, 25.0);
         assert!(parser.get_data_packet().is_none());
+        assert!(parser.get_response_packet().is_none());
     }
 
-    #[test]
-    fn test_serial_parser_rejects_bad_crc() {
+    fn test_serial_parser_parses_response_packet_split_across_calls<S: ParserStorage>() {
+        // Identifier is in the identifier for response packets; payload is just the response data.
+        let payload = [1u8];
+        let bytes = build_framed_packet(
+            PacketHeader::Response,
+            FIRMCommand::SetDeviceConfig as u16,
+            &payload,
+        );
+        let mid = bytes.len() / 2;
+
+        let mut parser = SerialParser::<S>::with_storage();
+        let summary = parser.parse_bytes(&bytes[..mid]);
+        // When we first call it, it hasnt parsed the full packet yet
+        assert!(parser.get_response_packet().is_none());
+        assert_eq!(
+            summary,
+            ParseSummary {
+                bytes_buffered: mid,
+                ..ParseSummary::default()
+            }
+        );
+
+        let summary = parser.parse_bytes(&bytes[mid..]);
+        assert_eq!(
+            summary,
+            ParseSummary {
+                data_packets: 0,
+                responses: 1,
+                bytes_consumed: bytes.len(),
+                bytes_buffered: 0,
+            }
+        );
+        let _frame = parser
+            .get_response_packet()
+            .expect("expected one response frame");
+        assert!(parser.get_response_packet().is_none());
+        assert!(parser.get_data_packet().is_none());
+    }
+
+    fn test_parse_summaries_add_up_across_chunks<S: ParserStorage>() {
+        let mut bytes = vec![0x55; 7];
+        for i in 0..6 {
+            bytes.extend(data_frame_at(i as f64));
+            if i % 2 == 0 {
+                bytes.extend(build_framed_packet(
+                    PacketHeader::Response,
+                    FIRMCommand::SetDeviceConfig as u16,
+                    &[1],
+                ));
+            }
+        }
+        // A repeated timestamp, dropped by the strict policy, and the start of another frame.
+        bytes.extend(data_frame_at(5.0));
+        let tail = data_frame_at(6.0);
+        bytes.extend(&tail[..10]);
+
+        for chunk_len in [1, 17, 100, bytes.len()] {
+            let mut parser = SerialParser::<S>::with_storage();
+            parser.set_timestamp_policy(TimestampPolicy::Strict);
+            let mut total = ParseSummary::default();
+            for chunk in bytes.chunks(chunk_len) {
+                let buffered = parser.buffered_len();
+                let summary = parser.parse_bytes(chunk);
+                assert_eq!(
+                    buffered + chunk.len(),
+                    summary.bytes_consumed + summary.bytes_buffered
+                );
+                assert_eq!(summary.bytes_buffered, parser.buffered_len());
+                total.data_packets += summary.data_packets;
+                total.responses += summary.responses;
+                total.bytes_consumed += summary.bytes_consumed;
+            }
+            assert_eq!((total.data_packets, total.responses), (6, 3));
+            assert_eq!(total.bytes_consumed, bytes.len() - 10);
+            assert_eq!(parser.packet_count(), 6);
+        }
+
+        // The other paths count the packets they hand over.
+        let mut parser = SerialParser::<S>::with_storage();
+        let summary = parser.parse_bytes_with(&bytes, |_| {});
+        assert_eq!((summary.data_packets, summary.responses), (7, 3));
+        assert_eq!(parser.packet_count(), 0);
+    }
+
+    fn test_serial_parser_rejects_bad_crc<S: ParserStorage>() {
         let payload = vec![0u8; 120];
         let mut bytes = build_framed_packet(PacketHeader::Data, 0, &payload);
 
@@ -220,9 +1788,1538 @@ mod tests {
         let payload_start = HEADER_SIZE + IDENTIFIER_SIZE + LENGTH_SIZE;
         bytes[payload_start] ^= 0x01;
 
-        let mut parser = SerialParser::new();
+        let mut parser = SerialParser::<S>::with_storage();
         parser.parse_bytes(&bytes);
         assert!(parser.get_data_packet().is_none());
         assert!(parser.get_response_packet().is_none());
+        assert_eq!(parser.stats().crc_failures, 1);
+    }
+
+    fn test_serial_parser_drains_queues_in_order<S: ParserStorage>() {
+        let mut bytes = Vec::new();
+        for i in 0..5u8 {
+            bytes.extend(build_framed_packet(PacketHeader::Data, 0, &[i; 120]));
+            bytes.extend(build_framed_packet(
+                PacketHeader::Response,
+                FIRMCommand::GetDeviceConfig as u16,
+                &[b'a' + i; 35],
+            ));
+        }
+
+        let mut parser = SerialParser::<S>::with_storage();
+        parser.parse_bytes(&bytes);
+        assert_eq!((parser.packet_count(), parser.response_count()), (5, 5));
+
+        let packets = parser.drain_packets();
+        let first_bytes: Vec<u8> = packets.iter().map(|p| p.payload()[0]).collect();
+        assert_eq!(first_bytes, [0, 1, 2, 3, 4]);
+        let responses = parser.drain_responses();
+        let first_bytes: Vec<u8> = responses.iter().map(|r| r.payload()[0]).collect();
+        assert_eq!(first_bytes, *b"abcde");
+
+        assert_eq!((parser.packet_count(), parser.response_count()), (0, 0));
+        assert!(parser.drain_packets().is_empty());
+        assert!(parser.get_response_packet().is_none());
+    }
+
+    fn test_serial_parser_queues_unknown_identifiers_as_raw_frames<S: ParserStorage>() {
+        let mut bytes = build_framed_packet(PacketHeader::Response, 0x0042, &[1, 2, 3]);
+        bytes.extend(build_framed_packet(PacketHeader::Data, 0, &[0u8; 120]));
+
+        let mut parser = SerialParser::<S>::with_storage();
+        parser.parse_bytes(&bytes);
+
+        let frame = parser.get_raw_frame().expect("expected one raw frame");
+        assert_eq!(frame.header(), PacketHeader::Response);
+        assert_eq!(frame.identifier(), 0x0042);
+        assert_eq!(frame.payload(), &[1, 2, 3]);
+        assert!(parser.get_raw_frame().is_none());
+        assert!(parser.get_response_packet().is_none());
+        assert!(parser.get_data_packet().is_some());
+        assert_eq!(parser.stats().bytes_skipped, 0);
+    }
+
+    fn test_serial_parser_decodes_each_data_layout<S: ParserStorage>() {
+        let fields: [f32; FIRMData::NUM_F32_FIELDS] = core::array::from_fn(|i| i as f32 + 0.5);
+        let mut full = FIRMData::from_fields(1.0, fields);
+        full.pressure_altitude_meters =
+            pressure_altitude_meters(full.pressure_pascals, STANDARD_SEA_LEVEL_PRESSURE_PASCALS);
+        let mut payload = full.timestamp_seconds.to_le_bytes().to_vec();
+        payload.extend(fields.iter().flat_map(|field| field.to_le_bytes()));
+        let mut extended = payload.clone();
+        extended.extend([0xEE; 4]);
+        let raw_sensors = &payload[..RAW_SENSOR_PACKET_PAYLOAD_LENGTH];
+        let unknown = &payload[..RAW_SENSOR_PACKET_PAYLOAD_LENGTH + 8];
+
+        let mut bytes = Vec::new();
+        for payload in [raw_sensors, &payload, unknown, &extended, raw_sensors] {
+            bytes.extend(build_framed_packet(PacketHeader::Data, 0, payload));
+        }
+
+        // The estimated fields, after the 11 raw sensor ones, aren't in the short layout.
+        let mut raw_fields = fields;
+        raw_fields[11..].fill(0.0);
+        let decoded_raw_sensors = (
+            DataLayout::RawSensors,
+            FIRMData {
+                pressure_altitude_meters: full.pressure_altitude_meters,
+                ..FIRMData::from_fields(1.0, raw_fields)
+            },
+        );
+        let decoded_full = (DataLayout::Full, full);
+        let decoded = [
+            decoded_raw_sensors.clone(),
+            decoded_full.clone(),
+            decoded_full,
+            decoded_raw_sensors,
+        ];
+
+        for chunk_size in [bytes.len(), 5] {
+            let mut parser = SerialParser::<S>::with_storage();
+            let mut viewed = SerialParser::<S>::with_storage();
+            let mut views = Vec::new();
+            for chunk in bytes.chunks(chunk_size) {
+                parser.parse_bytes(chunk);
+                viewed.parse_bytes_with(chunk, |view| views.push((view.layout(), view.to_data())));
+            }
+
+            let packets: Vec<_> = parser
+                .drain_packets()
+                .iter()
+                .map(|p| (p.layout(), p.data().clone()))
+                .collect();
+            assert_eq!(packets, decoded);
+            assert_eq!(views, decoded);
+
+            // The CRC-valid frame of no known layout is kept for callers to log.
+            for parser in [&mut parser, &mut viewed] {
+                let frame = parser.get_raw_frame().unwrap();
+                assert_eq!(frame.payload(), unknown);
+                assert!(parser.get_raw_frame().is_none());
+                assert_eq!(parser.stats().crc_failures, 0);
+            }
+        }
+    }
+
+    fn test_serial_parser_finds_frame_ends_from_their_lengths_at_any_split<S: ParserStorage>() {
+        let ack = build_framed_packet(
+            PacketHeader::Response,
+            FIRMCommand::SetDeviceConfig as u16,
+            &[1],
+        );
+        let raw_sensors = build_framed_packet(
+            PacketHeader::Data,
+            0,
+            &[0; RAW_SENSOR_PACKET_PAYLOAD_LENGTH],
+        );
+        let mut bytes = Vec::new();
+        for frame in [
+            &raw_sensors,
+            &ack,
+            &ack,
+            &data_frame_at(1.0),
+            &ack,
+            &raw_sensors,
+        ] {
+            bytes.extend_from_slice(frame);
+        }
+
+        for split in 0..=bytes.len() {
+            let mut parser = SerialParser::<S>::with_storage();
+            let (head, tail) = bytes.split_at(split);
+            parser.parse_bytes(head);
+            // Nothing is taken from a truncated tail before the rest of it arrives.
+            assert_eq!(parser.stats().bytes_skipped, 0, "split at {split}");
+            parser.parse_bytes(tail);
+
+            let layouts: Vec<_> = parser.drain_packets().iter().map(|p| p.layout()).collect();
+            assert_eq!(
+                layouts,
+                [
+                    DataLayout::RawSensors,
+                    DataLayout::Full,
+                    DataLayout::RawSensors
+                ],
+                "split at {split}"
+            );
+            assert_eq!(parser.drain_responses().len(), 3, "split at {split}");
+            assert_eq!(parser.stats().packets_parsed, 6);
+            assert_eq!(parser.stats().crc_failures, 0);
+        }
+    }
+
+    fn test_serial_parser_raw_frames_are_bounded<S: ParserStorage>() {
+        let mut parser = SerialParser::<S>::with_storage();
+        for i in 0..(SerialParser::MAX_RAW_FRAMES + 10) as u16 {
+            parser.parse_bytes(&build_framed_packet(
+                PacketHeader::Response,
+                0x4000 + i,
+                &[],
+            ));
+        }
+
+        let identifiers: Vec<u16> = core::iter::from_fn(|| parser.get_raw_frame())
+            .map(|frame| frame.identifier())
+            .collect();
+        assert_eq!(identifiers.len(), SerialParser::MAX_RAW_FRAMES);
+        assert_eq!(identifiers[0], 0x4000 + 10);
+    }
+
+    fn test_serial_parser_counts_skipped_bytes_and_resyncs<S: ParserStorage>() {
+        let mut bytes = vec![0x00, 0x11, 0x22];
+        bytes.extend(build_framed_packet(PacketHeader::Data, 0, &[0u8; 120]));
+        bytes.extend(build_framed_packet(PacketHeader::Data, 0, &[0u8; 120]));
+
+        let mut parser = SerialParser::<S>::with_storage();
+        parser.parse_bytes(&bytes);
+
+        assert!(parser.get_data_packet().is_some());
+        assert!(parser.get_data_packet().is_some());
+        let stats = parser.stats();
+        assert_eq!(stats.bytes_skipped, 3);
+        assert_eq!(stats.resyncs, 1);
+        assert_eq!(stats.crc_failures, 0);
+    }
+
+    fn test_serial_parser_counts_corrupted_frames<S: ParserStorage>() {
+        let good = build_framed_packet(PacketHeader::Data, 0, &[0u8; 120]);
+        let mut bad_crc = good.clone();
+        bad_crc[HEADER_SIZE + IDENTIFIER_SIZE + LENGTH_SIZE + 7] ^= 0x80;
+        let mut bad_length = good[..HEADER_SIZE + IDENTIFIER_SIZE].to_vec();
+        bad_length.extend_from_slice(&u32::MAX.to_le_bytes());
+
+        let mut bytes = good.clone();
+        bytes.extend(&bad_crc);
+        bytes.extend(&bad_length);
+        bytes.extend(build_framed_packet(
+            PacketHeader::Response,
+            FIRMCommand::GetDeviceConfig as u16,
+            &[0u8; 35],
+        ));
+        bytes.extend(&good);
+
+        let mut parser = SerialParser::<S>::with_storage();
+        // Byte by byte, so the resync paths also see frames split across calls.
+        for byte in &bytes {
+            parser.parse_bytes(core::slice::from_ref(byte));
+        }
+
+        let stats = parser.stats();
+        assert_eq!(stats.packets_parsed, 3);
+        assert_eq!(stats.crc_failures, 1);
+        assert_eq!(stats.length_rejects, 1);
+        assert_eq!(
+            stats.bytes_skipped,
+            (bad_crc.len() + bad_length.len()) as u64
+        );
+        assert_eq!(stats.resyncs, 1);
+        assert!(parser.get_data_packet().is_some());
+        assert!(parser.get_response_packet().is_some());
+        assert!(parser.get_data_packet().is_some());
+
+        parser.reset_stats();
+        assert_eq!(parser.stats(), ParserStats::default());
+        parser.parse_bytes(&good);
+        assert_eq!(parser.stats().packets_parsed, 1);
+    }
+
+    fn test_serial_parser_reports_where_sync_was_lost<S: ParserStorage>() {
+        let frames: Vec<Vec<u8>> = (0..6).map(|i| data_frame_at(i as f64)).collect();
+        let frame_len = frames[0].len() as u64;
+        let mut bytes = Vec::new();
+        for (i, frame) in frames.iter().enumerate() {
+            let mut frame = frame.clone();
+            match i {
+                1 => frame[20] ^= 0xFF,
+                3 => bytes.extend([0x11; 3]),
+                4 => frame[4..8].copy_from_slice(&u32::MAX.to_le_bytes()),
+                _ => {}
+            }
+            bytes.extend(frame);
+        }
+        let expected = [
+            (frame_len, ResyncReason::BadCrc),
+            (3 * frame_len, ResyncReason::NoStartBytes),
+            (4 * frame_len + 3, ResyncReason::LengthMismatch),
+        ];
+        let offsets = |events: Vec<ResyncEvent>| -> Vec<(u64, ResyncReason)> {
+            events.iter().map(|e| (e.byte_offset, e.reason)).collect()
+        };
+
+        for chunk_size in [bytes.len(), 7] {
+            let mut parser = SerialParser::<S>::with_storage();
+            parser.set_resync_log(Some(16));
+            for chunk in bytes.chunks(chunk_size) {
+                parser.parse_bytes(chunk);
+            }
+            assert_eq!(offsets(parser.take_resync_events()), expected);
+            assert!(parser.take_resync_events().is_empty());
+            assert_eq!(parser.bytes_received(), bytes.len() as u64);
+            assert_eq!(parser.stats().resyncs, 3);
+        }
+
+        // Offsets carry on across a snapshot taken mid-frame.
+        let (first, rest) = bytes.split_at(3 * frame_len as usize + 50);
+        let mut parser = SerialParser::<S>::with_storage();
+        parser.parse_bytes(first);
+        let mut restored = SerialParser::<S>::restore_with_storage(&parser.snapshot()).unwrap();
+        restored.set_resync_log(Some(16));
+        restored.parse_bytes(rest);
+        assert_eq!(offsets(restored.take_resync_events()), expected[2..]);
+
+        // Only the latest events are kept, and none without a log.
+        let mut parser = SerialParser::<S>::with_storage();
+        parser.parse_bytes(&bytes);
+        assert!(parser.take_resync_events().is_empty());
+        parser.set_resync_log(Some(2));
+        parser.reset();
+        parser.parse_bytes(&bytes);
+        assert_eq!(
+            offsets(parser.take_resync_events()),
+            [
+                (
+                    bytes.len() as u64 + 3 * frame_len,
+                    ResyncReason::NoStartBytes
+                ),
+                (
+                    bytes.len() as u64 + 4 * frame_len + 3,
+                    ResyncReason::LengthMismatch
+                ),
+            ]
+        );
+    }
+
+    fn test_serial_parser_collects_parse_errors_with_offsets<S: ParserStorage>() {
+        let clean = data_frame_at(1.0);
+        let mut bad_crc = data_frame_at(2.0);
+        let crc_start = bad_crc.len() - CRC_SIZE;
+        let expected_crc = crc16_ccitt(&bad_crc[..crc_start]);
+        bad_crc[crc_start] ^= 0x5A;
+        let got_crc = u16::from_le_bytes([bad_crc[crc_start], bad_crc[crc_start + 1]]);
+        let unknown = build_framed_packet(PacketHeader::Response, 0x00AB, &[1]);
+        let mut bad_length = data_frame_at(3.0);
+        bad_length[4..8].copy_from_slice(&5000u32.to_le_bytes());
+
+        let mut bytes = Vec::new();
+        let mut offsets = Vec::new();
+        for frame in [&clean, &bad_crc, &clean, &unknown, &bad_length, &clean] {
+            offsets.push(bytes.len() as u64);
+            bytes.extend_from_slice(frame);
+        }
+        let expected = [
+            ParseError {
+                byte_offset: offsets[1],
+                error: FrameError::BadCrc {
+                    expected: expected_crc.into(),
+                    got: got_crc.into(),
+                },
+            },
+            ParseError {
+                byte_offset: offsets[3],
+                error: FrameError::UnknownIdentifier(0x00AB),
+            },
+            ParseError {
+                byte_offset: offsets[4],
+                error: FrameError::PayloadTooLarge {
+                    max: SerialParser::MAX_PAYLOAD_LENGTH,
+                    got: 5000,
+                },
+            },
+        ];
+
+        for chunk_size in [bytes.len(), 9] {
+            let mut parser = SerialParser::<S>::with_storage();
+            parser.collect_parse_errors(true);
+            for chunk in bytes.chunks(chunk_size) {
+                parser.parse_bytes(chunk);
+            }
+            let errors: Vec<_> = core::iter::from_fn(|| parser.get_parse_error()).collect();
+            assert_eq!(errors, expected);
+            assert_eq!(parser.drain_packets().len(), 3);
+        }
+
+        // Off by default, and turning it off drops what was collected.
+        let mut parser = SerialParser::<S>::with_storage();
+        parser.parse_bytes(&bytes);
+        assert_eq!(parser.get_parse_error(), None);
+        parser.collect_parse_errors(true);
+        parser.parse_bytes(&bad_crc);
+        parser.collect_parse_errors(false);
+        parser.collect_parse_errors(true);
+        assert_eq!(parser.get_parse_error(), None);
+    }
+
+    fn test_serial_parser_buffer_stays_bounded_by_near_miss_garbage<S: ParserStorage>() {
+        // Start words with lengths that are plausible but whose frames never complete, and
+        // chunks ending in half a start word.
+        let mut near_miss = Vec::new();
+        for length in [900u32, 1024, 130, 5000] {
+            near_miss.extend_from_slice(&(PacketHeader::Data as u16).to_le_bytes());
+            near_miss.extend_from_slice(&0u16.to_le_bytes());
+            near_miss.extend_from_slice(&length.to_le_bytes());
+            near_miss.extend((0..61u8).map(|i| i.wrapping_mul(37)));
+        }
+        near_miss.push(0x5A);
+
+        let mut parser = SerialParser::<S>::with_storage();
+        let mut fed = 0;
+        while fed < 4 << 20 {
+            parser.parse_bytes(&near_miss);
+            fed += near_miss.len();
+            assert!(parser.serial_bytes.len() <= SerialParser::DEFAULT_MAX_BUFFER_LEN);
+        }
+        assert!(parser.get_data_packet().is_none());
+
+        // A frame far enough past the garbage that every pending start word is resolved.
+        let good = build_framed_packet(PacketHeader::Data, 0, &[0u8; 120]);
+        parser.parse_bytes(&[0u8; 2 * SerialParser::MAX_PAYLOAD_LENGTH]);
+        parser.parse_bytes(&good);
+        assert!(parser.get_data_packet().is_some());
+        assert!(parser.stats().length_rejects > 0);
+    }
+
+    fn test_serial_parser_skips_frames_longer_than_the_buffer<S: ParserStorage>() {
+        let long = build_framed_packet(PacketHeader::Response, 0x0042, &[0u8; 200]);
+        let good = build_framed_packet(PacketHeader::Data, 0, &[0u8; 120]);
+        let mut bytes = long.clone();
+        bytes.extend(&good);
+
+        // The same bytes in one call and a byte at a time give the same result.
+        for chunk_len in [bytes.len(), 1] {
+            let mut parser = SerialParser::<S>::with_storage();
+            parser.set_max_buffer_len(good.len());
+            for chunk in bytes.chunks(chunk_len) {
+                parser.parse_bytes(chunk);
+                assert!(parser.serial_bytes.len() <= good.len());
+            }
+            assert!(parser.get_raw_frame().is_none());
+            assert!(parser.get_data_packet().is_some());
+            assert_eq!(parser.stats().overflow_discards, 1);
+            assert_eq!(parser.stats().bytes_skipped, long.len() as u64);
+        }
+
+        let mut parser = SerialParser::<S>::with_storage();
+        parser.set_max_buffer_len(4);
+        assert_eq!(parser.max_buffer_len(), MIN_PACKET_SIZE);
+        parser.reset();
+        assert_eq!(parser.max_buffer_len(), MIN_PACKET_SIZE);
+    }
+
+    fn test_serial_parser_separates_interleaved_baro_packets<S: ParserStorage>() {
+        let mut data_payload = vec![0u8; 120];
+        let mut bytes = Vec::new();
+        let mut expected_baro = Vec::new();
+        for i in 0..10 {
+            data_payload[0..8].copy_from_slice(&(i as f64 * 0.01).to_le_bytes());
+            bytes.extend(build_framed_packet(PacketHeader::Data, 0, &data_payload));
+            for j in 0..4 {
+                let baro = FIRMBaroPacket {
+                    timestamp_seconds: i as f64 * 0.01 + j as f64 * 0.0025,
+                    pressure_pascals: 100_000.0 - (i * 4 + j) as f32,
+                    temperature_celsius: 20.0,
+                };
+                bytes.extend(baro.to_frame().to_bytes());
+                expected_baro.push(baro);
+            }
+        }
+
+        // Feed it in uneven chunks so frames of both kinds get split.
+        let mut parser = SerialParser::<S>::with_storage();
+        for chunk in bytes.chunks(37) {
+            parser.parse_bytes(chunk);
+        }
+
+        let baro: Vec<FIRMBaroPacket> = core::iter::from_fn(|| parser.get_baro_packet()).collect();
+        assert_eq!(baro, expected_baro);
+        let timestamps: Vec<f64> = core::iter::from_fn(|| parser.get_data_packet())
+            .map(|packet| packet.data().timestamp_seconds)
+            .collect();
+        assert_eq!(
+            timestamps,
+            (0..10).map(|i| i as f64 * 0.01).collect::<Vec<_>>()
+        );
+        assert!(parser.get_raw_frame().is_none());
+        assert_eq!(parser.stats().bytes_skipped, 0);
+    }
+
+    fn test_serial_parser_keeps_malformed_baro_packets_as_raw_frames<S: ParserStorage>() {
+        let bytes = build_framed_packet(PacketHeader::Data, BARO_PACKET_IDENTIFIER, &[0u8; 12]);
+        let mut parser = SerialParser::<S>::with_storage();
+        parser.parse_bytes(&bytes);
+        assert!(parser.get_baro_packet().is_none());
+        assert_eq!(parser.get_raw_frame().unwrap().payload(), &[0u8; 12]);
+    }
+
+    /// A stream with a bit of everything: noise, data, responses, barometer readings and an
+    /// unknown frame.
+    fn mixed_stream() -> Vec<u8> {
+        let mut bytes = vec![0x00, 0x11];
+        let mut payload = vec![0u8; 120];
+        for i in 0..20 {
+            payload[0..8].copy_from_slice(&(i as f64 * 0.01).to_le_bytes());
+            bytes.extend(build_framed_packet(PacketHeader::Data, 0, &payload));
+            let baro = FIRMBaroPacket {
+                timestamp_seconds: i as f64 * 0.01,
+                pressure_pascals: 100_000.0 - i as f32,
+                temperature_celsius: 20.0,
+            };
+            bytes.extend(baro.to_frame().to_bytes());
+            if i % 5 == 0 {
+                bytes.extend(build_framed_packet(
+                    PacketHeader::Response,
+                    FIRMCommand::SetDeviceConfig as u16,
+                    &[1],
+                ));
+                bytes.extend(build_framed_packet(
+                    PacketHeader::Response,
+                    0x0042,
+                    &[i as u8],
+                ));
+                bytes.push(0x5A);
+            }
+        }
+        bytes
+    }
+
+    fn test_serial_parser_collects_one_frame_per_shape<S: ParserStorage>() {
+        let mut stream = mixed_stream();
+        let mut corrupted = build_framed_packet(PacketHeader::Data, 0, &[0u8; 120]);
+        corrupted[40] ^= 0xff;
+        stream.extend(corrupted);
+        stream.extend(build_framed_packet(
+            PacketHeader::Data,
+            BARO_PACKET_IDENTIFIER,
+            &[0u8; 12],
+        ));
+
+        let mut parser = SerialParser::<S>::with_storage();
+        parser.set_corpus(Some(FrameCorpus::new(16)));
+        for chunk in stream.chunks(7) {
+            parser.parse_bytes(chunk);
+        }
+        parser.reset();
+        let corpus = parser.take_corpus().unwrap();
+
+        let shapes: Vec<(u16, u32, FrameOutcome)> = corpus
+            .entries()
+            .map(|(shape, _)| (shape.identifier, shape.length, shape.outcome))
+            .collect();
+        assert_eq!(
+            shapes,
+            [
+                (0, 120, FrameOutcome::Accepted),
+                (
+                    BARO_PACKET_IDENTIFIER,
+                    BARO_PACKET_PAYLOAD_LENGTH as u32,
+                    FrameOutcome::Accepted
+                ),
+                (
+                    FIRMCommand::SetDeviceConfig as u16,
+                    1,
+                    FrameOutcome::Accepted
+                ),
+                (0x0042, 1, FrameOutcome::Undecodable),
+                (0, 120, FrameOutcome::BadCrc),
+                (BARO_PACKET_IDENTIFIER, 12, FrameOutcome::Undecodable),
+            ]
+        );
+        assert_eq!(corpus.frames_seen(), 50);
+        for (shape, bytes) in corpus.entries() {
+            assert_eq!(FrameCorpus::classify(bytes), Some(*shape));
+        }
+        assert!(parser.corpus().is_none());
+    }
+
+    /// Drains every queue, in a form that can be compared.
+    fn drain<S: ParserStorage>(
+        parser: &mut SerialParser<S>,
+    ) -> (Vec<f64>, Vec<FIRMBaroPacket>, usize, Vec<Vec<u8>>) {
+        let data = core::iter::from_fn(|| parser.get_data_packet())
+            .map(|packet| packet.data().timestamp_seconds)
+            .collect();
+        let baro = core::iter::from_fn(|| parser.get_baro_packet()).collect();
+        let responses = core::iter::from_fn(|| parser.get_response_packet()).count();
+        let raw = core::iter::from_fn(|| parser.get_raw_frame())
+            .map(|frame| frame.to_bytes())
+            .collect();
+        (data, baro, responses, raw)
+    }
+
+    fn test_serial_parser_restored_mid_stream_continues_identically<S: ParserStorage>() {
+        let bytes = mixed_stream();
+        let mut uninterrupted = SerialParser::<S>::with_storage();
+        uninterrupted.parse_bytes(&bytes);
+
+        // Cut partway through a frame, so the snapshot holds a partial frame and queued packets.
+        let cut = bytes.len() / 2 + 7;
+        let mut before = SerialParser::<S>::with_storage();
+        before.parse_bytes(&bytes[..cut]);
+        let snapshot = before.snapshot();
+        let mut restored = SerialParser::<S>::restore_with_storage(&snapshot).unwrap();
+        assert_eq!(restored.snapshot(), snapshot);
+        for chunk in bytes[cut..].chunks(29) {
+            restored.parse_bytes(chunk);
+        }
+
+        assert_eq!(restored.stats(), uninterrupted.stats());
+        assert!(restored.stats().resyncs > 1);
+        let expected = drain(&mut uninterrupted);
+        assert_eq!(expected.0.len(), 20);
+        assert_eq!(drain(&mut restored), expected);
+    }
+
+    fn test_serial_parser_restore_rejects_bad_snapshots<S: ParserStorage>() {
+        let mut parser = SerialParser::<S>::with_storage();
+        parser.parse_bytes(&mixed_stream()[..500]);
+        let snapshot = parser.snapshot();
+
+        let mut other_version = snapshot.clone();
+        other_version[4..6].copy_from_slice(&(SNAPSHOT_VERSION + 1).to_le_bytes());
+        assert_eq!(
+            SerialParser::<S>::restore_with_storage(&other_version).err(),
+            Some(SnapshotError::UnsupportedVersion {
+                found: SNAPSHOT_VERSION + 1,
+                supported: SNAPSHOT_VERSION,
+            })
+        );
+        assert_eq!(
+            SerialParser::<S>::restore_with_storage(b"nope").err(),
+            Some(SnapshotError::BadMagic)
+        );
+        for len in [6, 20, snapshot.len() - 1] {
+            assert_eq!(
+                SerialParser::<S>::restore_with_storage(&snapshot[..len]).err(),
+                Some(SnapshotError::Truncated),
+                "{len}"
+            );
+        }
+        let mut trailing = snapshot.clone();
+        trailing.push(0);
+        assert_eq!(
+            SerialParser::<S>::restore_with_storage(&trailing).err(),
+            Some(SnapshotError::Truncated)
+        );
+    }
+
+    fn data_frame_at(timestamp_seconds: f64) -> Vec<u8> {
+        let mut payload = vec![0u8; 120];
+        payload[0..8].copy_from_slice(&timestamp_seconds.to_le_bytes());
+        build_framed_packet(PacketHeader::Data, 0, &payload)
+    }
+
+    fn test_timestamp_policies_handle_duplicate_and_backwards_timestamps<S: ParserStorage>() {
+        // A repeat of 0.02, then 0.025 which is behind the 0.03 before it.
+        let timestamps = [0.01, 0.02, 0.02, 0.03, 0.025, 0.04];
+        let bytes: Vec<u8> = timestamps.iter().flat_map(|&t| data_frame_at(t)).collect();
+        let parse = |policy| {
+            let mut parser = SerialParser::<S>::with_storage();
+            parser.set_timestamp_policy(policy);
+            parser.parse_bytes(&bytes);
+            let packets: Vec<_> = core::iter::from_fn(|| parser.get_data_packet()).collect();
+            (packets, parser.stats())
+        };
+
+        let (packets, stats) = parse(TimestampPolicy::Off);
+        let off: Vec<f64> = packets.iter().map(|p| p.data().timestamp_seconds).collect();
+        assert_eq!(off, timestamps);
+        assert_eq!((stats.timestamps_dropped, stats.timestamps_nudged), (0, 0));
+
+        let (packets, stats) = parse(TimestampPolicy::Strict);
+        let strict: Vec<f64> = packets.iter().map(|p| p.data().timestamp_seconds).collect();
+        assert_eq!(strict, [0.01, 0.02, 0.03, 0.04]);
+        assert_eq!((stats.timestamps_dropped, stats.timestamps_nudged), (2, 0));
+
+        let (packets, stats) = parse(TimestampPolicy::Nudge);
+        let nudged: Vec<f64> = packets.iter().map(|p| p.data().timestamp_seconds).collect();
+        assert_eq!(
+            nudged,
+            [
+                0.01,
+                0.02,
+                0.02 + TIMESTAMP_NUDGE_SECONDS,
+                0.03,
+                0.03 + TIMESTAMP_NUDGE_SECONDS,
+                0.04
+            ]
+        );
+        let originals: Vec<Option<f64>> = packets
+            .iter()
+            .map(|p| p.original_timestamp_seconds())
+            .collect();
+        assert_eq!(originals, [None, None, Some(0.02), None, Some(0.025), None]);
+        assert_eq!((stats.timestamps_dropped, stats.timestamps_nudged), (0, 2));
+        assert!(nudged.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    fn test_queue_capacity_applies_the_overflow_policy<S: ParserStorage>() {
+        let bytes: Vec<u8> = (0..10).flat_map(|i| data_frame_at(i as f64)).collect();
+        let parse = |policy| {
+            let mut parser = SerialParser::<S>::with_storage();
+            parser.set_overflow_policy(policy);
+            parser.set_queue_capacity(3);
+            parser.parse_bytes(&bytes);
+            let timestamps: Vec<f64> = parser
+                .drain_packets()
+                .iter()
+                .map(|packet| packet.data().timestamp_seconds)
+                .collect();
+            (timestamps, parser.stats())
+        };
+
+        let (newest, stats) = parse(OverflowPolicy::DropOldest);
+        assert_eq!(newest, [7.0, 8.0, 9.0]);
+        assert_eq!((stats.packets_parsed, stats.packets_dropped), (10, 7));
+        let (oldest, stats) = parse(OverflowPolicy::DropNewest);
+        assert_eq!(oldest, [0.0, 1.0, 2.0]);
+        assert_eq!((stats.packets_parsed, stats.packets_dropped), (10, 7));
+
+        // The capacity is kept through a snapshot and a reset.
+        let mut parser = SerialParser::<S>::with_storage();
+        parser.set_queue_capacity(0);
+        assert_eq!(parser.queue_capacity(), 1);
+        parser.set_queue_capacity(2);
+        parser.parse_bytes(&bytes[..data_frame_at(0.0).len() * 2]);
+        let mut restored = SerialParser::<S>::restore_with_storage(&parser.snapshot()).unwrap();
+        assert_eq!(restored.queue_capacity(), 2);
+        restored.parse_bytes(&bytes);
+        assert_eq!(restored.packet_count(), 2);
+        assert_eq!(restored.stats().packets_dropped, 10);
+        restored.reset();
+        assert_eq!(restored.queue_capacity(), 2);
+    }
+
+    fn test_cobs_wire_format_parses_at_any_split<S: ParserStorage>() {
+        let response = FramedPacket::new(
+            PacketHeader::Response,
+            FIRMCommand::SetDeviceConfig as u16,
+            vec![1],
+        );
+        let mut bytes = vec![COBS_DELIMITER];
+        for i in 0..6 {
+            bytes.extend(
+                FramedPacket::from_bytes(&data_frame_at(i as f64))
+                    .unwrap()
+                    .to_cobs_bytes(),
+            );
+            if i % 2 == 0 {
+                bytes.extend(response.to_cobs_bytes());
+            }
+        }
+
+        for chunk_len in [1, 17, bytes.len()] {
+            let mut parser = SerialParser::<S>::with_storage();
+            parser.set_wire_format(WireFormat::Cobs);
+            let mut total = ParseSummary::default();
+            for chunk in bytes.chunks(chunk_len) {
+                let buffered = parser.buffered_len();
+                let summary = parser.parse_bytes(chunk);
+                assert_eq!(
+                    buffered + chunk.len(),
+                    summary.bytes_consumed + summary.bytes_buffered
+                );
+                assert_eq!(summary.bytes_buffered, parser.buffered_len());
+                total.data_packets += summary.data_packets;
+                total.responses += summary.responses;
+            }
+            assert_eq!((total.data_packets, total.responses), (6, 3));
+            assert_eq!(parser.stats().bytes_skipped, 0);
+            assert_eq!(parser.bytes_received(), bytes.len() as u64);
+        }
+
+        // The format and the unfinished frame are kept through a snapshot and a reset.
+        let cut = bytes.len() / 2 + 9;
+        let mut parser = SerialParser::<S>::with_storage();
+        parser.set_wire_format(WireFormat::Cobs);
+        parser.parse_bytes(&bytes[..cut]);
+        assert!(parser.buffered_len() > 0);
+        let mut restored = SerialParser::<S>::restore_with_storage(&parser.snapshot()).unwrap();
+        assert_eq!(restored.wire_format(), WireFormat::Cobs);
+        assert_eq!(restored.buffered_len(), parser.buffered_len());
+        restored.parse_bytes(&bytes[cut..]);
+        assert_eq!((restored.packet_count(), restored.response_count()), (6, 3));
+        restored.reset();
+        assert_eq!(
+            (restored.wire_format(), restored.buffered_len()),
+            (WireFormat::Cobs, 0)
+        );
+
+        // Raw frames don't parse as COBS, and switching back drops what was buffered.
+        let mut parser = SerialParser::<S>::with_storage();
+        parser.set_wire_format(WireFormat::Cobs);
+        parser.parse_bytes(&data_frame_at(0.0));
+        assert_eq!(parser.packet_count(), 0);
+        let skipped = parser.stats().bytes_skipped + parser.buffered_len() as u64;
+        parser.set_wire_format(WireFormat::Raw);
+        assert_eq!(
+            (parser.buffered_len(), parser.stats().bytes_skipped),
+            (0, skipped)
+        );
+        parser.parse_bytes(&data_frame_at(1.0));
+        assert_eq!(parser.packet_count(), 1);
+    }
+
+    fn test_cobs_resyncs_at_the_next_frame_after_corruption<S: ParserStorage>() {
+        // The first frame's length claims 888 bytes. Raw framing waits for all of them before
+        // its CRC fails; in COBS the frame ends at its delimiter.
+        let mut frames: Vec<Vec<u8>> = (0..10).map(|i| data_frame_at(i as f64)).collect();
+        frames[0][5] = 0x03;
+        let raw: Vec<u8> = frames.concat();
+        let encoded: Vec<Vec<u8>> = frames
+            .iter()
+            .map(|frame| [cobs_encode(frame), vec![COBS_DELIMITER]].concat())
+            .collect();
+        let cobs = encoded.concat();
+
+        let bytes_until_first_packet = |wire_format, bytes: &[u8]| {
+            let mut parser = SerialParser::<S>::with_storage();
+            parser.set_wire_format(wire_format);
+            parser.collect_parse_errors(true);
+            let fed = bytes
+                .chunks(1)
+                .position(|byte| parser.parse_bytes(byte).data_packets > 0)
+                .unwrap()
+                + 1;
+            parser.parse_bytes(&bytes[fed..]);
+            let mut timestamps = vec![parser.get_data_packet().unwrap().data().timestamp_seconds];
+            timestamps.extend(
+                parser
+                    .drain_packets()
+                    .iter()
+                    .map(|packet| packet.data().timestamp_seconds),
+            );
+            (fed, timestamps, parser.get_parse_error())
+        };
+
+        let (raw_fed, raw_timestamps, _) = bytes_until_first_packet(WireFormat::Raw, &raw);
+        let (cobs_fed, cobs_timestamps, cobs_error) =
+            bytes_until_first_packet(WireFormat::Cobs, &cobs);
+        assert!(raw_fed > 888, "{raw_fed}");
+        assert_eq!(cobs_fed, encoded[0].len() + encoded[1].len());
+        let expected: Vec<f64> = (1..10).map(|i| i as f64).collect();
+        assert_eq!(
+            (raw_timestamps, cobs_timestamps),
+            (expected.clone(), expected)
+        );
+        assert_eq!(cobs_error, None);
+
+        // Damage to the stuffing itself only costs the frame it's in, and is reported there.
+        let mut stuffed = encoded[1..4].concat();
+        stuffed[encoded[1].len()] = 0xFE;
+        let mut parser = SerialParser::<S>::with_storage();
+        parser.set_wire_format(WireFormat::Cobs);
+        parser.collect_parse_errors(true);
+        parser.parse_bytes(&stuffed);
+        assert_eq!(parser.packet_count(), 2);
+        assert_eq!(
+            parser.get_parse_error(),
+            Some(ParseError {
+                byte_offset: encoded[1].len() as u64,
+                error: FrameError::BadEncoding,
+            })
+        );
+        assert_eq!(parser.stats().bytes_skipped, encoded[2].len() as u64);
+    }
+
+    fn test_timestamp_policies_report_warnings_and_device_restarts<S: ParserStorage>() {
+        let mut parser = SerialParser::<S>::with_storage();
+        parser.set_timestamp_policy(TimestampPolicy::Warn);
+        for timestamp in [10.0, 11.0, 10.5, 12.0] {
+            parser.parse_bytes(&data_frame_at(timestamp));
+        }
+        assert_eq!(parser.drain_packets().len(), 4);
+        assert_eq!(
+            parser.get_timestamp_event(),
+            Some(TimestampEvent::OutOfOrder {
+                previous_seconds: 11.0,
+                timestamp_seconds: 10.5,
+            })
+        );
+        assert_eq!(parser.get_timestamp_event(), None);
+
+        // A reboot mid-stream starts the timestamps over near zero: one restart event, and the
+        // packets after it are in order again rather than all behind the old ones.
+        let before = (0..2000).map(|i| 100.0 + i as f64 * 0.001);
+        let after = (1..2000).map(|i| i as f64 * 0.001);
+        let timestamps: Vec<f64> = before.chain(after).collect();
+        for policy in [
+            TimestampPolicy::Strict,
+            TimestampPolicy::Nudge,
+            TimestampPolicy::Warn,
+        ] {
+            let mut parser = SerialParser::<S>::with_storage();
+            parser.set_timestamp_policy(policy);
+            let mut packets = 0;
+            for &timestamp in &timestamps {
+                parser.parse_bytes(&data_frame_at(timestamp));
+                packets += parser.drain_packets().len();
+            }
+            assert_eq!(packets, timestamps.len());
+            assert_eq!(
+                parser.get_timestamp_event(),
+                Some(TimestampEvent::DeviceRestartDetected {
+                    previous_seconds: 100.0 + 1999.0 * 0.001,
+                    timestamp_seconds: 0.001,
+                })
+            );
+            assert_eq!(parser.get_timestamp_event(), None);
+            let stats = parser.stats();
+            assert_eq!((stats.timestamps_dropped, stats.timestamps_nudged), (0, 0));
+        }
+
+        // The default reports nothing at all.
+        let mut parser = SerialParser::<S>::with_storage();
+        for &timestamp in &timestamps {
+            parser.parse_bytes(&data_frame_at(timestamp));
+        }
+        parser.parse_bytes(&data_frame_at(0.0005));
+        assert_eq!(parser.get_timestamp_event(), None);
+    }
+
+    fn test_timestamp_policies_resync_after_a_far_future_outlier<S: ParserStorage>() {
+        // One corrupt timestamp far in the future, then the stream carries on as before.
+        let mut timestamps = vec![100.0, 100.01, 1e9];
+        timestamps.extend((2..40).map(|i| 100.0 + i as f64 * 0.01));
+        for policy in [TimestampPolicy::Strict, TimestampPolicy::Nudge] {
+            let mut parser = SerialParser::<S>::with_storage();
+            parser.set_timestamp_policy(policy);
+            let mut delivered = Vec::new();
+            for &timestamp in &timestamps {
+                parser.parse_bytes(&data_frame_at(timestamp));
+                delivered.extend(
+                    parser
+                        .drain_packets()
+                        .iter()
+                        .map(|packet| packet.data().timestamp_seconds),
+                );
+            }
+            // `Nudge` has moved the outlier's timestamp on a little by now.
+            match parser.get_timestamp_event() {
+                Some(TimestampEvent::Resynced {
+                    previous_seconds,
+                    timestamp_seconds,
+                }) => {
+                    assert!((previous_seconds - 1e9).abs() < 1e-3, "{previous_seconds}");
+                    assert_eq!(
+                        timestamp_seconds,
+                        100.0 + (2 + TIMESTAMP_RESYNC_PACKETS) as f64 * 0.01
+                    );
+                }
+                other => panic!("expected Resynced, got {other:?}"),
+            }
+            assert_eq!(parser.get_timestamp_event(), None);
+            // Only the packets right after the outlier are held back, the rest come through
+            // with their own timestamps.
+            let resynced = 3 + TIMESTAMP_RESYNC_PACKETS as usize;
+            assert_eq!(
+                &delivered[delivered.len() - (timestamps.len() - resynced)..],
+                &timestamps[resynced..]
+            );
+            let stats = parser.stats();
+            assert_eq!(
+                stats.timestamps_dropped + stats.timestamps_nudged,
+                TIMESTAMP_RESYNC_PACKETS as u64
+            );
+        }
+    }
+
+    fn test_timestamp_policy_survives_reset_and_snapshot<S: ParserStorage>() {
+        let mut parser = SerialParser::<S>::with_storage();
+        parser.set_timestamp_policy(TimestampPolicy::Nudge);
+        parser.parse_bytes(&data_frame_at(1.0));
+        parser.parse_bytes(&data_frame_at(1.0));
+
+        let mut restored = SerialParser::<S>::restore_with_storage(&parser.snapshot()).unwrap();
+        assert_eq!(restored.timestamp_policy(), TimestampPolicy::Nudge);
+        restored.get_data_packet().unwrap();
+        let nudged = restored.get_data_packet().unwrap();
+        assert_eq!(
+            nudged.data().timestamp_seconds,
+            1.0 + TIMESTAMP_NUDGE_SECONDS
+        );
+        assert_eq!(nudged.original_timestamp_seconds(), Some(1.0));
+        // The restored parser still knows the last timestamp.
+        restored.parse_bytes(&data_frame_at(0.5));
+        assert_eq!(restored.stats().timestamps_nudged, 2);
+
+        // A reset starts the timestamps over, e.g. for a rebooted device, but keeps the policy.
+        restored.reset();
+        assert_eq!(restored.timestamp_policy(), TimestampPolicy::Nudge);
+        restored.parse_bytes(&data_frame_at(0.5));
+        assert_eq!(
+            restored
+                .get_data_packet()
+                .unwrap()
+                .original_timestamp_seconds(),
+            None
+        );
+        assert_eq!(restored.stats().timestamps_nudged, 0);
+    }
+
+    fn test_parse_bytes_with_matches_the_owned_path<S: ParserStorage>() {
+        let mut bytes: Vec<u8> = [0.01f64, 0.02, 0.02, 0.015, 0.03]
+            .iter()
+            .flat_map(|&t| {
+                let mut payload = vec![0u8; 120];
+                payload[0..8].copy_from_slice(&t.to_le_bytes());
+                // Some field bytes, so the fields aren't all zero.
+                payload[12..16].copy_from_slice(&(t as f32 * 3.0).to_le_bytes());
+                build_framed_packet(PacketHeader::Data, 0, &payload)
+            })
+            .collect();
+        bytes.extend(build_framed_packet(
+            PacketHeader::Response,
+            FIRMCommand::SetDeviceConfig as u16,
+            &[1],
+        ));
+        bytes.extend([0xAA, 0x55, 0x00]);
+
+        for chunk_size in [bytes.len(), 7] {
+            let mut owned = SerialParser::<S>::with_storage();
+            owned.set_timestamp_policy(TimestampPolicy::Nudge);
+            let mut viewed = SerialParser::<S>::with_storage();
+            viewed.set_timestamp_policy(TimestampPolicy::Nudge);
+            let mut views = Vec::new();
+            for chunk in bytes.chunks(chunk_size) {
+                owned.parse_bytes(chunk);
+                viewed.parse_bytes_with(chunk, |view| {
+                    views.push((view.to_data(), view.original_timestamp_seconds()));
+                });
+            }
+
+            let packets: Vec<_> = owned
+                .drain_packets()
+                .iter()
+                .map(|p| (p.data().clone(), p.original_timestamp_seconds()))
+                .collect();
+            assert_eq!(views, packets);
+            assert_eq!(viewed.packet_count(), 0);
+            assert_eq!(viewed.stats(), owned.stats());
+            assert_eq!(viewed.stats().timestamps_nudged, 2);
+            assert_eq!(viewed.drain_responses().len(), 1);
+        }
+    }
+
+    /// What a `RecordingSink` was handed, in order.
+    #[derive(Debug, PartialEq)]
+    enum SinkEvent {
+        Data(f64),
+        Response(FIRMCommand),
+        Baro(f64),
+        ParseError(u64),
+        LostSync(u64, ResyncReason),
+        SequenceGap(SequenceGap),
+    }
+
+    /// Records everything it's handed, keeping barometer readings if `takes_baro`.
+    struct RecordingSink {
+        events: Vec<SinkEvent>,
+        takes_baro: bool,
+    }
+
+    impl PacketSink for RecordingSink {
+        fn on_data(&mut self, packet: FIRMDataPacket) {
+            self.events
+                .push(SinkEvent::Data(packet.data().timestamp_seconds));
+        }
+
+        fn on_response(&mut self, packet: FIRMResponsePacket) {
+            self.events.push(SinkEvent::Response(packet.command_type()));
+        }
+
+        fn on_baro(&mut self, packet: FIRMBaroPacket) -> Option<FIRMBaroPacket> {
+            if !self.takes_baro {
+                return Some(packet);
+            }
+            self.events.push(SinkEvent::Baro(packet.timestamp_seconds));
+            None
+        }
+
+        fn on_parse_error(&mut self, error: &ParseError) {
+            self.events.push(SinkEvent::ParseError(error.byte_offset));
+        }
+
+        fn on_lost_sync(&mut self, event: &ResyncEvent) {
+            self.events
+                .push(SinkEvent::LostSync(event.byte_offset, event.reason));
+        }
+
+        fn on_sequence_gap(&mut self, gap: &SequenceGap) {
+            self.events.push(SinkEvent::SequenceGap(*gap));
+        }
     }
+
+    fn test_sequence_numbers_report_gaps_and_leave_plain_frames_alone<S: ParserStorage>() {
+        let numbered = |t: f64, sequence: u16| {
+            FramedPacket::from_bytes(&data_frame_at(t))
+                .unwrap()
+                .with_sequence(sequence)
+                .to_bytes()
+        };
+        let plain_response =
+            build_framed_packet(PacketHeader::Response, FIRMCommand::Cancel as u16, &[1]);
+        // Frames 3 and 4 are lost, and the count wraps after 65535. Frames without a sequence
+        // number, as older firmware sends, don't take part.
+        let bytes = [
+            numbered(1.0, 1),
+            data_frame_at(1.5),
+            numbered(2.0, 2),
+            plain_response.clone(),
+            numbered(5.0, 5),
+            numbered(6.0, 65535),
+            numbered(7.0, 0),
+        ]
+        .concat();
+        let expected_gaps = [
+            SequenceGap {
+                expected: 3,
+                got: 5,
+            },
+            SequenceGap {
+                expected: 6,
+                got: 65535,
+            },
+        ];
+        assert_eq!(expected_gaps[0].missing(), 2);
+
+        for chunk_len in [1, 13, bytes.len()] {
+            let mut parser = SerialParser::<S>::with_storage();
+            for chunk in bytes.chunks(chunk_len) {
+                parser.parse_bytes(chunk);
+            }
+            assert_eq!((parser.packet_count(), parser.response_count()), (6, 1));
+            let timestamps: Vec<f64> = core::iter::from_fn(|| parser.get_data_packet())
+                .map(|packet| packet.data().timestamp_seconds)
+                .collect();
+            assert_eq!(timestamps, [1.0, 1.5, 2.0, 5.0, 6.0, 7.0]);
+            let gaps: Vec<SequenceGap> =
+                core::iter::from_fn(|| parser.get_sequence_gap()).collect();
+            assert_eq!(gaps, expected_gaps);
+            assert_eq!(parser.stats().bytes_skipped, 0);
+        }
+
+        // Views take their payloads from after the sequence number.
+        let mut parser = SerialParser::<S>::with_storage();
+        let mut viewed = Vec::new();
+        parser.parse_bytes_with(&bytes, |view| viewed.push(view.timestamp_seconds()));
+        assert_eq!(viewed, [1.0, 1.5, 2.0, 5.0, 6.0, 7.0]);
+
+        // A sink hears of each gap before the frame after it.
+        let mut sink = RecordingSink {
+            events: Vec::new(),
+            takes_baro: false,
+        };
+        SerialParser::<S>::with_storage().parse_bytes_into(&bytes, &mut sink);
+        let gap_at = sink
+            .events
+            .iter()
+            .position(|event| *event == SinkEvent::SequenceGap(expected_gaps[0]))
+            .unwrap();
+        assert_eq!(sink.events[gap_at + 1], SinkEvent::Data(5.0));
+
+        // A plain stream reports nothing.
+        let mut parser = SerialParser::<S>::with_storage();
+        parser.parse_bytes(&[data_frame_at(1.0), plain_response, data_frame_at(2.0)].concat());
+        assert_eq!(parser.packet_count(), 2);
+        assert_eq!(parser.get_sequence_gap(), None);
+
+        // The count carries through a snapshot, and starts over on a reset.
+        let first = numbered(1.0, 1);
+        let mut parser = SerialParser::<S>::with_storage();
+        parser.parse_bytes(&first);
+        let mut restored = SerialParser::<S>::restore_with_storage(&parser.snapshot()).unwrap();
+        restored.parse_bytes(&numbered(2.0, 4));
+        assert_eq!(
+            restored.get_sequence_gap(),
+            Some(SequenceGap {
+                expected: 2,
+                got: 4
+            })
+        );
+        parser.reset();
+        parser.parse_bytes(&numbered(2.0, 4));
+        assert_eq!(parser.get_sequence_gap(), None);
+    }
+
+    fn test_data_packets_with_invalid_values_are_rejected_on_every_path<S: ParserStorage>() {
+        let mut nan_payload = vec![0u8; 120];
+        nan_payload[..8].copy_from_slice(&f64::NAN.to_le_bytes());
+        let nan_frame = build_framed_packet(PacketHeader::Data, 0, &nan_payload);
+        let bytes = [data_frame_at(1.0), nan_frame.clone(), data_frame_at(2.0)].concat();
+        let expected = ParseError {
+            byte_offset: data_frame_at(1.0).len() as u64,
+            error: FrameError::InvalidValue {
+                field: "timestamp_seconds".into(),
+            },
+        };
+
+        let mut owned = SerialParser::<S>::with_storage();
+        let mut viewed = SerialParser::<S>::with_storage();
+        let mut views = Vec::new();
+        for parser in [&mut owned, &mut viewed] {
+            parser.collect_parse_errors(true);
+        }
+        owned.parse_bytes(&bytes);
+        viewed.parse_bytes_with(&bytes, |view| views.push(view.timestamp_seconds()));
+        let timestamps: Vec<f64> = core::iter::from_fn(|| owned.get_data_packet())
+            .map(|packet| packet.data().timestamp_seconds)
+            .collect();
+        assert_eq!(timestamps, [1.0, 2.0]);
+        assert_eq!(views, [1.0, 2.0]);
+        for parser in [&mut owned, &mut viewed] {
+            assert_eq!(parser.get_parse_error(), Some(expected.clone()));
+            assert_eq!(parser.get_raw_frame().unwrap().to_bytes(), nan_frame);
+            assert_eq!(parser.stats().decode_failures, 1);
+            assert_eq!(parser.stats().packets_parsed, 2);
+            // The frame itself was whole, so the parser didn't lose sync over it.
+            assert_eq!(parser.stats().bytes_skipped, 0);
+        }
+
+        let mut sink = RecordingSink {
+            events: Vec::new(),
+            takes_baro: false,
+        };
+        SerialParser::<S>::with_storage().parse_bytes_into(&bytes, &mut sink);
+        assert_eq!(
+            sink.events,
+            [
+                SinkEvent::Data(1.0),
+                SinkEvent::ParseError(expected.byte_offset),
+                SinkEvent::Data(2.0),
+            ]
+        );
+    }
+
+    fn test_sea_level_pressure_sets_the_altitude_of_parsed_packets<S: ParserStorage>() {
+        let frame_at = |pressure_pascals: f32| {
+            let mut payload = vec![0u8; 120];
+            payload[12..16].copy_from_slice(&pressure_pascals.to_le_bytes());
+            build_framed_packet(PacketHeader::Data, 0, &payload)
+        };
+        let altitude = |parser: &mut SerialParser<S>| {
+            parser
+                .get_data_packet()
+                .unwrap()
+                .data()
+                .pressure_altitude_meters
+        };
+
+        let mut parser = SerialParser::<S>::with_storage();
+        assert_eq!(
+            parser.sea_level_pressure(),
+            STANDARD_SEA_LEVEL_PRESSURE_PASCALS
+        );
+        parser.parse_bytes(&frame_at(89_874.6));
+        assert!((altitude(&mut parser) - 1000.0).abs() < 1.0);
+
+        // Re-baselining on the ground pressure gives height above the ground.
+        parser.set_sea_level_pressure(95_000.0);
+        parser.parse_bytes(&frame_at(95_000.0));
+        assert_eq!(altitude(&mut parser), 0.0);
+        let mut viewed = Vec::new();
+        parser.parse_bytes_with(&frame_at(94_000.0), |view| {
+            viewed.push(view.to_data().pressure_altitude_meters);
+        });
+        assert_eq!(viewed, [pressure_altitude_meters(94_000.0, 95_000.0)]);
+
+        // The reference pressure lasts through a reset and a snapshot, and packets still
+        // queued in the snapshot keep their altitude.
+        parser.reset();
+        assert_eq!(parser.sea_level_pressure(), 95_000.0);
+        parser.parse_bytes(&frame_at(94_000.0));
+        let mut restored = SerialParser::<S>::restore_with_storage(&parser.snapshot()).unwrap();
+        assert_eq!(restored.sea_level_pressure(), 95_000.0);
+        assert_eq!(
+            altitude(&mut restored),
+            pressure_altitude_meters(94_000.0, 95_000.0)
+        );
+    }
+
+    fn test_parse_bytes_into_hands_over_packets_in_wire_order<S: ParserStorage>() {
+        let baro = |t: f64| {
+            let packet = FIRMBaroPacket {
+                timestamp_seconds: t,
+                pressure_pascals: 101_325.0,
+                temperature_celsius: 20.0,
+            };
+            build_framed_packet(
+                PacketHeader::Data,
+                BARO_PACKET_IDENTIFIER,
+                &packet.to_bytes(),
+            )
+        };
+        let response = |command: FIRMCommand| {
+            build_framed_packet(PacketHeader::Response, command as u16, &[1])
+        };
+        let mut corrupted = data_frame_at(9.0);
+        corrupted[20] ^= 0xFF;
+        let parts = [
+            data_frame_at(1.0),
+            response(FIRMCommand::SetDeviceConfig),
+            baro(1.5),
+            corrupted.clone(),
+            data_frame_at(2.0),
+            vec![0x11, 0x22],
+            baro(2.5),
+            response(FIRMCommand::Cancel),
+            data_frame_at(3.0),
+        ];
+        let offset_of = |index: usize| parts[..index].iter().map(Vec::len).sum::<usize>() as u64;
+        let bytes = parts.concat();
+
+        let expected = vec![
+            SinkEvent::Data(1.0),
+            SinkEvent::Response(FIRMCommand::SetDeviceConfig),
+            SinkEvent::Baro(1.5),
+            SinkEvent::ParseError(offset_of(3)),
+            SinkEvent::LostSync(offset_of(3), ResyncReason::BadCrc),
+            SinkEvent::Data(2.0),
+            SinkEvent::LostSync(offset_of(5), ResyncReason::NoStartBytes),
+            SinkEvent::Baro(2.5),
+            SinkEvent::Response(FIRMCommand::Cancel),
+            SinkEvent::Data(3.0),
+        ];
+        for chunk_size in [bytes.len(), 7, 1] {
+            let mut parser = SerialParser::<S>::with_storage();
+            let mut sink = RecordingSink {
+                events: Vec::new(),
+                takes_baro: true,
+            };
+            for chunk in bytes.chunks(chunk_size) {
+                parser.parse_bytes_into(chunk, &mut sink);
+            }
+            assert_eq!(sink.events, expected, "{chunk_size} byte reads");
+            assert_eq!(parser.packet_count(), 0);
+            assert_eq!(parser.response_count(), 0);
+            assert!(parser.get_baro_packet().is_none());
+        }
+
+        // A sink that hands barometer readings back leaves them queued, and sees the rest.
+        let mut parser = SerialParser::<S>::with_storage();
+        let mut sink = RecordingSink {
+            events: Vec::new(),
+            takes_baro: false,
+        };
+        parser.parse_bytes_into(&bytes, &mut sink);
+        let without_baro: Vec<_> = expected
+            .into_iter()
+            .filter(|event| !matches!(event, SinkEvent::Baro(_)))
+            .collect();
+        assert_eq!(sink.events, without_baro);
+        let queued: Vec<f64> = core::iter::from_fn(|| parser.get_baro_packet())
+            .map(|packet| packet.timestamp_seconds)
+            .collect();
+        assert_eq!(queued, [1.5, 2.5]);
+    }
+
+    #[test]
+    fn test_fixed_storage_applies_the_overflow_policy() {
+        let bytes: Vec<u8> = (0..10).flat_map(|i| data_frame_at(i as f64)).collect();
+        let parse = |policy| {
+            let mut parser = SerialParser::<FixedStorage<256, 4>>::with_storage();
+            parser.set_overflow_policy(policy);
+            parser.parse_bytes(&bytes);
+            let timestamps: Vec<f64> = parser
+                .drain_packets()
+                .iter()
+                .map(|packet| packet.data().timestamp_seconds)
+                .collect();
+            (timestamps, parser.stats())
+        };
+
+        let (newest, stats) = parse(OverflowPolicy::DropOldest);
+        assert_eq!(newest, [6.0, 7.0, 8.0, 9.0]);
+        assert_eq!((stats.packets_parsed, stats.packets_dropped), (10, 6));
+        let (oldest, stats) = parse(OverflowPolicy::DropNewest);
+        assert_eq!(oldest, [0.0, 1.0, 2.0, 3.0]);
+        assert_eq!((stats.packets_parsed, stats.packets_dropped), (10, 6));
+    }
+
+    #[test]
+    fn test_queues_stop_at_the_default_capacity() {
+        let frame = data_frame_at(0.0);
+        let extra = 5;
+        let bytes = frame.repeat(SerialParser::DEFAULT_QUEUE_CAPACITY + extra);
+        let mut parser = SerialParser::new();
+        assert_eq!(
+            parser.queue_capacity(),
+            SerialParser::DEFAULT_QUEUE_CAPACITY
+        );
+        parser.parse_bytes(&bytes);
+        assert_eq!(parser.packet_count(), SerialParser::DEFAULT_QUEUE_CAPACITY);
+        assert_eq!(parser.stats().packets_dropped, extra as u64);
+    }
+
+    #[test]
+    fn test_fixed_storage_parses_chunks_larger_than_its_buffer() {
+        let bytes: Vec<u8> = (0..40).flat_map(|i| data_frame_at(i as f64)).collect();
+        let mut parser = SerialParser::<FixedStorage<256, 64>>::with_storage();
+        assert_eq!(parser.max_buffer_len(), 256);
+        parser.parse_bytes(&bytes);
+        assert_eq!(parser.packet_count(), 40);
+        assert_eq!(parser.stats().bytes_skipped, 0);
+
+        // Too small for a frame header: bytes still go through, they just can't be parsed.
+        let mut tiny = SerialParser::<FixedStorage<4, 4>>::with_storage();
+        tiny.parse_bytes(&bytes);
+        assert_eq!(tiny.packet_count(), 0);
+        assert!(tiny.serial_bytes.len() <= 4);
+    }
+
+    /// Runs each generic test above against both storage backends.
+    macro_rules! storage_tests {
+        ($($test:ident),* $(,)?) => {
+            mod alloc_storage {
+                $(#[test]
+                fn $test() {
+                    super::$test::<super::AllocStorage>();
+                })*
+            }
+
+            mod fixed_storage {
+                $(#[test]
+                fn $test() {
+                    super::$test::<super::TestFixedStorage>();
+                })*
+            }
+        };
+    }
+
+    #[test]
+    fn test_fixed_storage_parser_reads_frames_across_the_buffer_end() {
+        // Reads that don't line up with the frames leave start words and frames split across
+        // the end of a small buffer at every offset.
+        let frames: Vec<Vec<u8>> = (0..40)
+            .map(|i| {
+                let mut payload = vec![0; RAW_SENSOR_PACKET_PAYLOAD_LENGTH];
+                payload[..8].copy_from_slice(&f64::from(i).to_le_bytes());
+                build_framed_packet(PacketHeader::Data, 0, &payload)
+            })
+            .collect();
+        let bytes: Vec<u8> = frames.concat();
+        for chunk_size in 1..=128 {
+            let mut parser = SerialParser::<FixedStorage<128, 4>>::with_storage();
+            let mut timestamps = Vec::new();
+            for chunk in bytes.chunks(chunk_size) {
+                parser.parse_bytes(chunk);
+                timestamps.extend(
+                    parser
+                        .drain_packets()
+                        .iter()
+                        .map(|p| p.data().timestamp_seconds),
+                );
+            }
+            let expected: Vec<f64> = (0..40).map(f64::from).collect();
+            assert_eq!(timestamps, expected, "{chunk_size} byte reads");
+            assert_eq!(parser.stats().bytes_skipped, 0);
+            assert_eq!(parser.buffered_len(), 0);
+        }
+    }
+
+    /// Random bytes, heavy on start word halves, with a data frame now and then.
+    fn noisy_stream(seed: u64, len: usize) -> Vec<u8> {
+        let mut state = seed | 1;
+        let mut bytes = Vec::new();
+        while bytes.len() < len {
+            // xorshift64
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            match state % 16 {
+                0 => bytes.extend(data_frame_at(bytes.len() as f64)),
+                1..=3 => bytes.extend(START_WORDS[(state >> 8) as usize % 2]),
+                4..=7 => bytes.push(START_WORDS[(state >> 8) as usize % 2][0]),
+                _ => bytes.extend(&state.to_le_bytes()[..(state >> 8) as usize % 8]),
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_find_start_word_matches_a_byte_by_byte_scan() {
+        let linear = |bytes: &[u8], from: usize| {
+            (from..bytes.len().saturating_sub(1))
+                .find(|&i| START_WORDS.iter().any(|word| word[..] == bytes[i..i + 2]))
+        };
+        for seed in 0..50 {
+            let bytes = noisy_stream(seed, 300);
+            for end in [0, 1, 2, 7, 8, 9, 17, bytes.len()] {
+                let bytes = &bytes[..end.min(bytes.len())];
+                for from in 0..=bytes.len() {
+                    assert_eq!(
+                        find_start_word(bytes, from, &START_WORDS),
+                        linear(bytes, from),
+                        "seed {seed}, {} bytes from {from}",
+                        bytes.len()
+                    );
+                }
+            }
+        }
+        // A start word split across two of the eight byte words is still found.
+        let mut bytes = [0u8; 16];
+        bytes[7..9].copy_from_slice(&START_WORDS[1]);
+        assert_eq!(find_start_word(&bytes, 0, &START_WORDS), Some(7));
+    }
+
+    fn test_serial_parser_skips_noise_the_same_whatever_the_split<S: ParserStorage>() {
+        for seed in 0..20 {
+            let bytes = noisy_stream(seed, 1500);
+            let parse = |chunk_size: usize| {
+                let mut parser = SerialParser::<S>::with_storage();
+                parser.set_resync_log(Some(1000));
+                let mut timestamps = Vec::new();
+                for chunk in bytes.chunks(chunk_size) {
+                    parser.parse_bytes(chunk);
+                    // Drained as it goes, so fixed storage can't fill up.
+                    timestamps.extend(
+                        parser
+                            .drain_packets()
+                            .iter()
+                            .map(|p| p.data().timestamp_seconds),
+                    );
+                }
+                (timestamps, parser.stats(), parser.take_resync_events())
+            };
+            // One byte at a time never gets a whole eight byte word to check for start words.
+            let whole = parse(bytes.len());
+            assert!(!whole.0.is_empty());
+            assert_eq!(parse(1), whole, "seed {seed}");
+            assert_eq!(parse(13), whole, "seed {seed}");
+        }
+    }
+
+    storage_tests!(
+        test_serial_parser_parses_data_packet,
+        test_serial_parser_parses_response_packet_split_across_calls,
+        test_parse_summaries_add_up_across_chunks,
+        test_serial_parser_rejects_bad_crc,
+        test_serial_parser_drains_queues_in_order,
+        test_serial_parser_queues_unknown_identifiers_as_raw_frames,
+        test_serial_parser_decodes_each_data_layout,
+        test_serial_parser_finds_frame_ends_from_their_lengths_at_any_split,
+        test_serial_parser_raw_frames_are_bounded,
+        test_serial_parser_counts_skipped_bytes_and_resyncs,
+        test_serial_parser_skips_noise_the_same_whatever_the_split,
+        test_serial_parser_counts_corrupted_frames,
+        test_serial_parser_reports_where_sync_was_lost,
+        test_serial_parser_collects_parse_errors_with_offsets,
+        test_serial_parser_buffer_stays_bounded_by_near_miss_garbage,
+        test_serial_parser_skips_frames_longer_than_the_buffer,
+        test_serial_parser_separates_interleaved_baro_packets,
+        test_serial_parser_keeps_malformed_baro_packets_as_raw_frames,
+        test_serial_parser_collects_one_frame_per_shape,
+        test_serial_parser_restored_mid_stream_continues_identically,
+        test_serial_parser_restore_rejects_bad_snapshots,
+        test_timestamp_policies_handle_duplicate_and_backwards_timestamps,
+        test_timestamp_policies_report_warnings_and_device_restarts,
+        test_timestamp_policies_resync_after_a_far_future_outlier,
+        test_timestamp_policy_survives_reset_and_snapshot,
+        test_parse_bytes_with_matches_the_owned_path,
+        test_parse_bytes_into_hands_over_packets_in_wire_order,
+        test_queue_capacity_applies_the_overflow_policy,
+        test_cobs_wire_format_parses_at_any_split,
+        test_cobs_resyncs_at_the_next_frame_after_corruption,
+        test_sequence_numbers_report_gaps_and_leave_plain_frames_alone,
+        test_sea_level_pressure_sets_the_altitude_of_parsed_packets,
+        test_data_packets_with_invalid_values_are_rejected_on_every_path,
+    );
 }
@@ -0,0 +1,238 @@
+//! Stable error codes shared by every frontend, so the Python, web and Rust tools can show the
+//! same help for the same problem.
+//!
+//! Each error type implements `ErrorCoded`. Codes are never renamed or reused once released;
+//! new ones are added to `REGISTRY`.
+use crate::csv::CsvImportError;
+use crate::data_parser::SnapshotError;
+use crate::framed_packet::FrameError;
+
+/// A registered error code and the help shown to users for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ErrorCode {
+    /// The stable identifier, e.g. `"E_TIMEOUT"`.
+    pub code: &'static str,
+    /// User-facing help: what went wrong and what to try.
+    pub help: &'static str,
+}
+
+/// Errors that carry a registered `ErrorCode`.
+pub trait ErrorCoded {
+    /// Returns the code for this error.
+    fn error_code(&self) -> ErrorCode;
+}
+
+macro_rules! error_codes {
+    ($($name:ident = $code:literal, $help:literal;)*) => {
+        $(pub const $name: ErrorCode = ErrorCode { code: $code, help: $help };)*
+
+        /// Every registered code.
+        pub const REGISTRY: &[ErrorCode] = &[$($name),*];
+    };
+}
+
+error_codes! {
+    FRAME_TOO_SHORT = "E_FRAME_TOO_SHORT",
+        "A packet ended early. The connection may be dropping bytes.";
+    FRAME_LENGTH = "E_FRAME_LENGTH",
+        "A packet's length didn't match its header. The connection may be dropping bytes.";
+    FRAME_ENCODING = "E_FRAME_ENCODING",
+        "A COBS frame's byte stuffing was malformed. Check that both ends use the same wire format.";
+    INVALID_VALUE = "E_INVALID_VALUE",
+        "A packet passed its checksum but held an impossible value, such as a NaN timestamp. Check the firmware version.";
+    CRC = "E_CRC",
+        "A packet failed its checksum. Check the cable and baud rate.";
+    UNKNOWN_IDENTIFIER = "E_UNKNOWN_IDENTIFIER",
+        "The device sent a packet this client doesn't know. Update the client or firmware so they match.";
+    PAYLOAD_TOO_LARGE = "E_PAYLOAD_TOO_LARGE",
+        "The command is larger than the firmware accepts.";
+    INVALID_ARGUMENT = "E_INVALID_ARGUMENT",
+        "A value passed in was out of range or the wrong shape.";
+    IO = "E_IO",
+        "Reading or writing a file failed. Check the path and permissions.";
+    CSV_EMPTY = "E_CSV_EMPTY",
+        "The CSV file is empty.";
+    CSV_NO_TIMESTAMP = "E_CSV_NO_TIMESTAMP",
+        "The CSV file has no timestamp_seconds column.";
+    CSV_DUPLICATE_COLUMN = "E_CSV_DUPLICATE_COLUMN",
+        "The CSV file has the same column twice.";
+    CSV_INVALID_ROWS = "E_CSV_INVALID_ROWS",
+        "Some CSV rows couldn't be read. The message lists their line numbers.";
+    PORT_NOT_FOUND = "E_PORT_NOT_FOUND",
+        "The serial port doesn't exist. Check the port name (e.g. COM8, /dev/ttyACM0) and that FIRM is plugged in.";
+    PORT_PERMISSION = "E_PORT_PERMISSION",
+        "Permission to open the serial port was denied. Run as admin or fix the udev rules.";
+    PORT_BUSY = "E_PORT_BUSY",
+        "The serial port is in use by another program. Close it and try again.";
+    PORT_OPEN = "E_PORT_OPEN",
+        "The serial port couldn't be opened.";
+    CONNECT = "E_CONNECT",
+        "Couldn't connect to the bridge. Check the address and that the bridge is running.";
+    CANNOT_REOPEN = "E_CANNOT_REOPEN",
+        "The connection was lost and can't be reopened. Create a new client.";
+    READ = "E_READ",
+        "Reading from the device failed.";
+    WRITE = "E_WRITE",
+        "Sending to the device failed.";
+    TIMEOUT = "E_TIMEOUT",
+        "The device didn't answer in time. Check that it's powered and connected.";
+    DISCONNECTED = "E_DISCONNECTED",
+        "The device disconnected. Check the cable or bridge.";
+    CALLBACK_PANICKED = "E_CALLBACK_PANICKED",
+        "A packet callback crashed and was removed.";
+    RECORDING = "E_RECORDING",
+        "Writing the recording failed, so it was stopped. Check the disk.";
+    MOCK_STREAM = "E_MOCK_STREAM",
+        "Streaming the mock log to the device failed.";
+    NOT_SUPPORTED = "E_NOT_SUPPORTED",
+        "This isn't supported on this kind of connection, e.g. sending commands to a log replay.";
+    THREAD_PANICKED = "E_THREAD_PANICKED",
+        "The reader crashed. Starting the client again reopens the connection.";
+    STOP_TIMEOUT = "E_STOP_TIMEOUT",
+        "The reader didn't stop in time. The connection is reopened on the next start.";
+    RECONNECT_TIMEOUT = "E_RECONNECT_TIMEOUT",
+        "The device didn't come back after rebooting. Check that it's still plugged in.";
+    NO_PACKETS_AFTER_RECONNECT = "E_NO_PACKETS_AFTER_RECONNECT",
+        "The device came back after rebooting but isn't sending data. Check its config.";
+    LOST_SYNC = "E_LOST_SYNC",
+        "The stream lost sync and bytes were skipped. Check the cable and baud rate.";
+    TIMESTAMP_ORDER = "E_TIMESTAMP_ORDER",
+        "A packet arrived with an earlier timestamp than the one before it.";
+    DEVICE_RESTART = "E_DEVICE_RESTART",
+        "The device's timestamps started over, so it probably rebooted.";
+    TIMESTAMP_RESYNC = "E_TIMESTAMP_RESYNC",
+        "Too many packets in a row were behind an earlier timestamp, so it was dropped as the reference.";
+    LINK_IDLE = "E_LINK_IDLE",
+        "No packets have arrived for a while. Check that the device is powered and still streaming.";
+    SHUT_DOWN = "E_SHUT_DOWN",
+        "The client was shut down by its controller. Create a new client.";
+    PROTOCOL_VIOLATION = "E_PROTOCOL_VIOLATION",
+        "The device sent a response with out-of-range fields. Check its firmware version.";
+    IMPLAUSIBLE_DATA = "E_IMPLAUSIBLE_DATA",
+        "A data packet held readings no flight could produce. A sensor may have failed, or the link let noise through.";
+    SNAPSHOT_VERSION = "E_SNAPSHOT_VERSION",
+        "The saved parser state is from a different version of the client. Start a new parser.";
+    SNAPSHOT_CORRUPT = "E_SNAPSHOT_CORRUPT",
+        "The saved parser state is damaged or isn't parser state. Start a new parser.";
+}
+
+/// Looks up a registered code by its identifier.
+///
+/// # Arguments
+///
+/// - `code` (`&str`) - The identifier, e.g. `"E_CRC"`.
+///
+/// # Returns
+///
+/// - `Option<ErrorCode>` - The registered code, or `None` if there's no such code.
+pub fn lookup(code: &str) -> Option<ErrorCode> {
+    REGISTRY.iter().copied().find(|entry| entry.code == code)
+}
+
+impl ErrorCoded for FrameError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            FrameError::TooShort => FRAME_TOO_SHORT,
+            FrameError::LengthMismatch { .. } => FRAME_LENGTH,
+            FrameError::BadCrc { .. } => CRC,
+            FrameError::UnknownIdentifier(_) => UNKNOWN_IDENTIFIER,
+            FrameError::PayloadTooLarge { .. } => PAYLOAD_TOO_LARGE,
+            FrameError::BadEncoding => FRAME_ENCODING,
+            FrameError::InvalidValue { .. } => INVALID_VALUE,
+        }
+    }
+}
+
+impl ErrorCoded for CsvImportError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            CsvImportError::Io(_) => IO,
+            CsvImportError::Empty => CSV_EMPTY,
+            CsvImportError::MissingTimestampColumn => CSV_NO_TIMESTAMP,
+            CsvImportError::DuplicateColumn(_) => CSV_DUPLICATE_COLUMN,
+            CsvImportError::InvalidRows(_) => CSV_INVALID_ROWS,
+        }
+    }
+}
+
+impl ErrorCoded for SnapshotError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            SnapshotError::UnsupportedVersion { .. } => SNAPSHOT_VERSION,
+            SnapshotError::BadMagic | SnapshotError::Truncated => SNAPSHOT_CORRUPT,
+            SnapshotError::InvalidFrame(e) => e.error_code(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_registry_codes_are_unique_and_well_formed() {
+        for (i, entry) in REGISTRY.iter().enumerate() {
+            assert!(entry.code.starts_with("E_"), "{}", entry.code);
+            assert!(
+                entry
+                    .code
+                    .bytes()
+                    .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit() || b == b'_'),
+                "{}",
+                entry.code
+            );
+            assert!(!entry.help.is_empty(), "{}", entry.code);
+            assert!(
+                REGISTRY[i + 1..]
+                    .iter()
+                    .all(|other| other.code != entry.code),
+                "{} registered twice",
+                entry.code
+            );
+            assert_eq!(lookup(entry.code), Some(*entry));
+        }
+        assert_eq!(lookup("E_NOT_A_CODE"), None);
+    }
+
+    #[test]
+    fn test_every_core_error_has_a_registered_code() {
+        let frame_errors = [
+            FrameError::TooShort,
+            FrameError::LengthMismatch {
+                expected: 1,
+                got: 2,
+            },
+            FrameError::BadCrc {
+                expected: 1,
+                got: 2,
+            },
+            FrameError::UnknownIdentifier(0),
+            FrameError::PayloadTooLarge { max: 1, got: 2 },
+            FrameError::BadEncoding,
+            FrameError::InvalidValue {
+                field: String::new(),
+            },
+        ];
+        let csv_errors = [
+            CsvImportError::Io(String::new()),
+            CsvImportError::Empty,
+            CsvImportError::MissingTimestampColumn,
+            CsvImportError::DuplicateColumn(String::new()),
+            CsvImportError::InvalidRows(Vec::new()),
+        ];
+        let codes: Vec<ErrorCode> = frame_errors
+            .iter()
+            .map(ErrorCoded::error_code)
+            .chain(csv_errors.iter().map(ErrorCoded::error_code))
+            .collect();
+        for code in &codes {
+            assert!(REGISTRY.contains(code), "{code:?} isn't registered");
+        }
+        // Every variant gets its own code, so help text can be specific.
+        for (i, code) in codes.iter().enumerate() {
+            assert!(!codes[i + 1..].contains(code), "{code:?} shared");
+        }
+    }
+}
@@ -1,7 +1,23 @@
+use crate::altitude::pressure_altitude_meters;
 use crate::constants::command::*;
-use crate::framed_packet::{FrameError, Framed, FramedPacket};
-use crate::utils::{bytes_to_str, parse_bytes_to_f32, parse_bytes_to_many_f32s};
+use crate::constants::packet::{
+    BARO_PACKET_IDENTIFIER, BARO_PACKET_PAYLOAD_LENGTH, DATA_PACKET_IDENTIFIER,
+    DATA_PACKET_PAYLOAD_LENGTH, PacketHeader, RAW_SENSOR_PACKET_PAYLOAD_LENGTH,
+};
+#[cfg(feature = "no-alloc")]
+use crate::fixed::FixedString;
+use crate::framed_packet::{
+    FieldName, FrameError, Framed, FramedPacket, Payload, payload_from_slice,
+};
+#[cfg(not(feature = "no-alloc"))]
+use crate::non_finite::{write_json_f32, write_json_number};
+use crate::utils::{bytes_to_str, parse_bytes_to_f32, str_to_bytes};
+#[cfg(not(feature = "no-alloc"))]
+use crate::validation::{FieldFault, ValidationConfig};
+#[cfg(not(feature = "no-alloc"))]
+use alloc::{format, string::String, vec::Vec};
 use field_names::FieldNames;
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "python")]
@@ -11,9 +27,37 @@ use pythonize::pythonize;
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
+/// `DeviceInfo::firmware_version`: a `String`, or with the `no-alloc` feature a `FixedString`
+/// as long as the field on the wire.
+#[cfg(not(feature = "no-alloc"))]
+pub type FirmwareVersion = String;
+/// `DeviceInfo::firmware_version`: a `String`, or with the `no-alloc` feature a `FixedString`
+/// as long as the field on the wire.
+#[cfg(feature = "no-alloc")]
+pub type FirmwareVersion = FixedString<FIRMWARE_VERSION_LENGTH>;
+
+/// `DeviceConfig::name`: a `String`, or with the `no-alloc` feature a `FixedString` as long as
+/// the field on the wire.
+#[cfg(not(feature = "no-alloc"))]
+pub type DeviceName = String;
+/// `DeviceConfig::name`: a `String`, or with the `no-alloc` feature a `FixedString` as long as
+/// the field on the wire.
+#[cfg(feature = "no-alloc")]
+pub type DeviceName = FixedString<DEVICE_NAME_LENGTH>;
+
+/// The message of a `FIRMResponse::Error`: a `String`, or a `&'static str` with the `no-alloc`
+/// feature.
+#[cfg(not(feature = "no-alloc"))]
+pub type ErrorMessage = String;
+/// The message of a `FIRMResponse::Error`: a `String`, or a `&'static str` with the `no-alloc`
+/// feature.
+#[cfg(feature = "no-alloc")]
+pub type ErrorMessage = &'static str;
+
 /// Represents the communication protocol used by the FIRM device.
 #[repr(u8)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "python", pyclass(eq, eq_int))]
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub enum DeviceProtocol {
@@ -23,11 +67,28 @@ pub enum DeviceProtocol {
     SPI = 4,
 }
 
+impl TryFrom<u8> for DeviceProtocol {
+    /// The byte, when it isn't a protocol.
+    type Error = u8;
+
+    /// Decodes the protocol byte of a device config.
+    fn try_from(byte: u8) -> Result<Self, u8> {
+        match byte {
+            1 => Ok(DeviceProtocol::USB),
+            2 => Ok(DeviceProtocol::UART),
+            3 => Ok(DeviceProtocol::I2C),
+            4 => Ok(DeviceProtocol::SPI),
+            _ => Err(byte),
+        }
+    }
+}
+
 /// Represents the information of the FIRM device.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "python", pyclass(get_all, set_all))]
 pub struct DeviceInfo {
-    pub firmware_version: String, // Max 8 characters
+    pub firmware_version: FirmwareVersion, // Max 8 characters
     #[cfg_attr(feature = "wasm", serde(serialize_with = "serialize_u64_as_string"))]
     // We need this because JS can't handle u64
     pub id: u64,
@@ -35,7 +96,8 @@ pub struct DeviceInfo {
 
 /// Represents the calibration values for the FIRM device. All matrices
 /// are stored in row-major order.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "python", pyclass(get_all, set_all))]
 pub struct CalibrationValues {
     pub imu_accelerometer_offsets: [f32; NUMBER_OF_CALIBRATION_OFFSETS],
@@ -46,6 +108,51 @@ pub struct CalibrationValues {
     pub magnetometer_scale_matrix: [f32; NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS],
 }
 
+impl CalibrationValues {
+    /// Size of the `GetCalibration` response payload.
+    pub const ENCODED_LENGTH: usize =
+        IMU_CALIBRATION_PAYLOAD_LENGTH + MAGNETOMETER_CALIBRATION_PAYLOAD_LENGTH;
+
+    /// Decodes a `GetCalibration` response payload: the `SetIMUCalibration` payload followed by
+    /// the `SetMagnetometerCalibration` one.
+    ///
+    /// # Arguments
+    ///
+    /// - `bytes` (`&[u8]`) - The payload, at least `ENCODED_LENGTH` bytes long.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let mut idx = 0;
+        let mut next = || parse_bytes_to_f32(bytes, &mut idx);
+        Self {
+            imu_accelerometer_offsets: core::array::from_fn(|_| next()),
+            imu_accelerometer_scale_matrix: core::array::from_fn(|_| next()),
+            imu_gyroscope_offsets: core::array::from_fn(|_| next()),
+            imu_gyroscope_scale_matrix: core::array::from_fn(|_| next()),
+            magnetometer_offsets: core::array::from_fn(|_| next()),
+            magnetometer_scale_matrix: core::array::from_fn(|_| next()),
+        }
+    }
+
+    /// Encodes the values in the `GetCalibration` response layout, the inverse of `from_bytes`.
+    /// The first `IMU_CALIBRATION_PAYLOAD_LENGTH` bytes are the `SetIMUCalibration` payload and
+    /// the rest the `SetMagnetometerCalibration` one, so values read from the device can be
+    /// written straight back.
+    #[cfg(not(feature = "no-alloc"))]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [
+            &self.imu_accelerometer_offsets[..],
+            &self.imu_accelerometer_scale_matrix,
+            &self.imu_gyroscope_offsets,
+            &self.imu_gyroscope_scale_matrix,
+            &self.magnetometer_offsets,
+            &self.magnetometer_scale_matrix,
+        ]
+        .concat()
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+    }
+}
+
 /// Serializes a u64 as a string for WASM compatibility. JS gets unhappy with
 /// large integers, such as the device ID, so we serialize it as a string.
 #[cfg(feature = "wasm")]
@@ -57,18 +164,120 @@ where
 }
 
 /// Represents the configuration settings of the FIRM device.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "python", pyclass(get_all, set_all))]
 pub struct DeviceConfig {
-    pub name: String, // Max 32 characters
+    pub name: DeviceName, // Max 32 characters
     pub frequency: u16,
     pub protocol: DeviceProtocol,
+    /// Set on a decoded `GetDeviceConfig` response that broke the protocol, in which case the
+    /// other fields are the decoder's best guess. See `FIRMResponsePacket::violations`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub suspect: bool,
+}
+
+/// A field of a response that's outside what the protocol allows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ResponseViolation {
+    /// The data output frequency isn't within `MIN_FREQUENCY_HZ..=MAX_FREQUENCY_HZ`.
+    FrequencyOutOfRange(u16),
+    /// The protocol byte isn't a `DeviceProtocol`.
+    InvalidProtocol(u8),
+    /// The device name isn't valid UTF-8.
+    NameNotUtf8,
+    /// The device name is empty.
+    EmptyName,
+    /// The payload is too short to hold every field, so none of them were checked.
+    PayloadTooShort { expected: usize, got: usize },
+}
+
+impl core::fmt::Display for ResponseViolation {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ResponseViolation::FrequencyOutOfRange(frequency) => write!(
+                f,
+                "frequency {frequency} Hz is outside {MIN_FREQUENCY_HZ}-{MAX_FREQUENCY_HZ} Hz"
+            ),
+            ResponseViolation::InvalidProtocol(byte) => {
+                write!(f, "protocol byte {byte:#04x} isn't a known protocol")
+            }
+            ResponseViolation::NameNotUtf8 => write!(f, "device name isn't valid UTF-8"),
+            ResponseViolation::EmptyName => write!(f, "device name is empty"),
+            ResponseViolation::PayloadTooShort { expected, got } => {
+                write!(f, "payload is {got} bytes, expected at least {expected}")
+            }
+        }
+    }
+}
+
+impl DeviceConfig {
+    /// Checks a `GetDeviceConfig` response payload against the protocol.
+    ///
+    /// # Arguments
+    ///
+    /// - `payload` (`&[u8]`) - The response payload: name, frequency and protocol byte.
+    ///
+    /// # Returns
+    ///
+    /// - `Vec<ResponseViolation>` - Every field that's out of bounds, in payload order, or
+    ///   just `ResponseViolation::PayloadTooShort` if the fields don't all fit.
+    #[cfg(not(feature = "no-alloc"))]
+    pub fn payload_violations(payload: &[u8]) -> Vec<ResponseViolation> {
+        let mut violations = Vec::new();
+        Self::visit_payload_violations(payload, |violation| violations.push(violation));
+        violations
+    }
+
+    /// Like `payload_violations`, handing each violation to `visit` instead of collecting
+    /// them.
+    fn visit_payload_violations(payload: &[u8], mut visit: impl FnMut(ResponseViolation)) {
+        let expected = SET_DEVICE_CONFIG_PAYLOAD_LENGTH;
+        if payload.len() < expected {
+            return visit(ResponseViolation::PayloadTooShort {
+                expected,
+                got: payload.len(),
+            });
+        }
+
+        let name = &payload[..DEVICE_NAME_LENGTH];
+        let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+        if core::str::from_utf8(name).is_err() {
+            visit(ResponseViolation::NameNotUtf8);
+        } else if name.is_empty() {
+            visit(ResponseViolation::EmptyName);
+        }
+
+        let frequency_bytes = &payload[DEVICE_NAME_LENGTH..DEVICE_NAME_LENGTH + FREQUENCY_LENGTH];
+        let frequency = u16::from_le_bytes(frequency_bytes.try_into().unwrap());
+        if !(MIN_FREQUENCY_HZ..=MAX_FREQUENCY_HZ).contains(&frequency) {
+            visit(ResponseViolation::FrequencyOutOfRange(frequency));
+        }
+
+        if let Err(byte) = DeviceProtocol::try_from(payload[DEVICE_NAME_LENGTH + FREQUENCY_LENGTH])
+        {
+            visit(ResponseViolation::InvalidProtocol(byte));
+        }
+    }
+
+    /// Returns this config as the device stores it and reports it back: the name is cut to
+    /// `DEVICE_NAME_LENGTH` bytes (and at any NUL), the same way the set command encodes it.
+    /// It isn't `suspect`, as that only describes how a response was decoded.
+    pub fn as_stored(&self) -> DeviceConfig {
+        DeviceConfig {
+            name: bytes_to_str(&str_to_bytes::<DEVICE_NAME_LENGTH>(&self.name)),
+            suspect: false,
+            ..self.clone()
+        }
+    }
 }
 
 /// Represents a decoded FIRM telemetry packet with converted physical units. In our Python code
 /// it's called FIRMDataPacket, but to avoid confusion with the Rust packet struct
 /// we name this FIRMData.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FieldNames)]
+#[derive(Debug, Clone, PartialEq, FieldNames)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(
     feature = "python",
     pyo3::pyclass(name = "FIRMDataPacket", get_all, freelist = 20, frozen)
@@ -111,6 +320,11 @@ pub struct FIRMData {
     pub est_quaternion_x: f32,
     pub est_quaternion_y: f32,
     pub est_quaternion_z: f32,
+
+    /// Barometric altitude computed on the host from `pressure_pascals`, not sent by the
+    /// device. `FIRMClient` fills it in (relative to the ground once zeroed); it is 0 in
+    /// freshly parsed packets.
+    pub pressure_altitude_meters: f32,
 }
 
 #[cfg(feature = "python")]
@@ -122,6 +336,7 @@ impl FIRMData {
     }
 
     #[new]
+    #[pyo3(signature = (timestamp_seconds, temperature_celsius, pressure_pascals, raw_acceleration_x_gs, raw_acceleration_y_gs, raw_acceleration_z_gs, raw_angular_rate_x_deg_per_s, raw_angular_rate_y_deg_per_s, raw_angular_rate_z_deg_per_s, magnetic_field_x_microteslas, magnetic_field_y_microteslas, magnetic_field_z_microteslas, est_position_x_meters, est_position_y_meters, est_position_z_meters, est_velocity_x_meters_per_s, est_velocity_y_meters_per_s, est_velocity_z_meters_per_s, est_acceleration_x_gs, est_acceleration_y_gs, est_acceleration_z_gs, est_angular_rate_x_rad_per_s, est_angular_rate_y_rad_per_s, est_angular_rate_z_rad_per_s, est_quaternion_w, est_quaternion_x, est_quaternion_y, est_quaternion_z, pressure_altitude_meters = 0.0))]
     #[allow(clippy::too_many_arguments)]
     fn new(
         timestamp_seconds: f64,
@@ -152,6 +367,7 @@ impl FIRMData {
         est_quaternion_x: f32,
         est_quaternion_y: f32,
         est_quaternion_z: f32,
+        pressure_altitude_meters: f32,
     ) -> Self {
         FIRMData {
             timestamp_seconds,
@@ -182,6 +398,7 @@ impl FIRMData {
             est_quaternion_x,
             est_quaternion_y,
             est_quaternion_z,
+            pressure_altitude_meters,
         }
     }
 
@@ -216,9 +433,15 @@ impl FIRMData {
             est_quaternion_x: 0.0,
             est_quaternion_y: 0.0,
             est_quaternion_z: 0.0,
+            pressure_altitude_meters: 0.0,
         }
     }
 
+    #[pyo3(name = "to_json")]
+    fn py_to_json(&self) -> String {
+        self.to_json()
+    }
+
     fn as_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
         pythonize(py, self).map_err(|e| {
             use pyo3::exceptions::PyValueError;
@@ -227,7 +450,8 @@ impl FIRMData {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum FIRMResponse {
     GetDeviceInfo(DeviceInfo),
     GetDeviceConfig(DeviceConfig),
@@ -237,7 +461,70 @@ pub enum FIRMResponse {
     GetCalibration(CalibrationValues),
     Mock(bool),
     Cancel(bool),
-    Error(String),
+    Error(ErrorMessage),
+}
+
+/// The data packet payload layouts firmware sends, told apart by their length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataLayout {
+    /// The timestamp and raw sensor fields only. The estimated fields decode as 0.
+    RawSensors,
+    /// Every `FIRMData` field the device sends, plus any fields newer firmware appends.
+    Full,
+}
+
+impl DataLayout {
+    /// Finds the layout of a data packet payload.
+    ///
+    /// # Arguments
+    ///
+    /// - `length` (`usize`) - The payload length from the frame.
+    ///
+    /// # Returns
+    ///
+    /// - `Option<Self>` - The layout, or `None` if no firmware sends data payloads this long.
+    pub fn from_payload_length(length: usize) -> Option<Self> {
+        match length {
+            RAW_SENSOR_PACKET_PAYLOAD_LENGTH => Some(Self::RawSensors),
+            length if length >= DATA_PACKET_PAYLOAD_LENGTH => Some(Self::Full),
+            _ => None,
+        }
+    }
+}
+
+/// Why a payload couldn't be decoded, see `FIRMData::from_bytes`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PacketDecodeError {
+    /// The payload ends before the shortest layout that would hold it.
+    TooShort { need: usize, got: usize },
+    /// A field holds a value the device can't have sent, e.g. a NaN timestamp.
+    InvalidValue { field: FieldName },
+}
+
+impl core::fmt::Display for PacketDecodeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PacketDecodeError::TooShort { need, got } => {
+                write!(f, "payload too short: need {need} bytes, got {got}")
+            }
+            PacketDecodeError::InvalidValue { field } => write!(f, "invalid value for {field}"),
+        }
+    }
+}
+
+impl core::error::Error for PacketDecodeError {}
+
+impl From<PacketDecodeError> for FrameError {
+    fn from(error: PacketDecodeError) -> Self {
+        match error {
+            PacketDecodeError::TooShort { need, got } => FrameError::LengthMismatch {
+                expected: need,
+                got,
+            },
+            PacketDecodeError::InvalidValue { field } => FrameError::InvalidValue { field },
+        }
+    }
 }
 
 /// Wire-level framed data packet.
@@ -247,12 +534,84 @@ pub enum FIRMResponse {
 pub struct FIRMDataPacket {
     frame: FramedPacket,
     data: FIRMData,
+    original_timestamp_seconds: Option<f64>,
 }
 
 impl FIRMDataPacket {
+    /// Frames `data` the way the device sends it, e.g. to replay recorded packets over a mock
+    /// port. `Framed::to_bytes` gives the wire bytes, which `from_bytes` decodes back to this
+    /// packet.
+    ///
+    /// # Arguments
+    ///
+    /// - `data` (`FIRMData`) - The telemetry to send. `pressure_altitude_meters` isn't sent,
+    ///   so it's 0 in the packet, as in a parsed one.
+    ///
+    /// # Returns
+    ///
+    /// - `Self` - A `DataLayout::Full` packet.
+    pub fn new(data: FIRMData) -> Self {
+        let frame = FramedPacket::new(PacketHeader::Data, DATA_PACKET_IDENTIFIER, data.to_bytes());
+        Self {
+            frame,
+            data: FIRMData {
+                pressure_altitude_meters: 0.0,
+                ..data
+            },
+            original_timestamp_seconds: None,
+        }
+    }
+
     pub fn data(&self) -> &FIRMData {
         &self.data
     }
+
+    /// The layout the payload was decoded with.
+    pub fn layout(&self) -> DataLayout {
+        // `from_bytes` only builds packets with a known layout.
+        DataLayout::from_payload_length(self.frame.payload().len()).unwrap()
+    }
+
+    /// The timestamp the device sent, if `TimestampPolicy::Nudge` moved it to keep the stream
+    /// in order. `data().timestamp_seconds` is the corrected one.
+    pub fn original_timestamp_seconds(&self) -> Option<f64> {
+        self.original_timestamp_seconds
+    }
+
+    /// Checks the packet against the default `ValidationConfig`, without a previous packet to
+    /// check its timestamp against.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<(), Vec<FieldFault>>` - Every field that's not finite or out of range.
+    #[cfg(not(feature = "no-alloc"))]
+    pub fn validate(&self) -> Result<(), Vec<FieldFault>> {
+        self.validate_with(&ValidationConfig::default(), None)
+    }
+
+    /// Checks the packet against `config`, see `ValidationConfig::check`.
+    #[cfg(not(feature = "no-alloc"))]
+    pub fn validate_with(
+        &self,
+        config: &ValidationConfig,
+        previous: Option<&FIRMData>,
+    ) -> Result<(), Vec<FieldFault>> {
+        config.check(&self.data, previous)
+    }
+
+    /// Moves the packet to `timestamp_seconds`, remembering the one it arrived with.
+    pub(crate) fn nudge_timestamp(&mut self, timestamp_seconds: f64) {
+        self.original_timestamp_seconds
+            .get_or_insert(self.data.timestamp_seconds);
+        self.data.timestamp_seconds = timestamp_seconds;
+    }
+
+    /// Computes `pressure_altitude_meters` from the packet's pressure and
+    /// `reference_pressure_pascals`.
+    pub(crate) fn fill_pressure_altitude(&mut self, reference_pressure_pascals: f32) {
+        self.data.pressure_altitude_meters =
+            pressure_altitude_meters(self.data.pressure_pascals, reference_pressure_pascals);
+    }
 }
 
 impl Framed for FIRMDataPacket {
@@ -263,15 +622,120 @@ impl Framed for FIRMDataPacket {
     fn from_bytes(bytes: &[u8]) -> Result<Self, FrameError> {
         let frame = FramedPacket::from_bytes(bytes)?;
         Ok(Self {
-            data: FIRMData::from_bytes(frame.payload()),
+            data: FIRMData::from_bytes(frame.payload())?,
             frame,
+            original_timestamp_seconds: None,
+        })
+    }
+}
+
+/// Compact reading from the secondary barometer, sent as a data packet on
+/// `BARO_PACKET_IDENTIFIER` at a higher rate than the full `FIRMData` packet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FIRMBaroPacket {
+    pub timestamp_seconds: f64,
+    pub pressure_pascals: f32,
+    pub temperature_celsius: f32,
+}
+
+impl FIRMBaroPacket {
+    /// Decodes a barometer packet payload.
+    ///
+    /// # Arguments
+    ///
+    /// - `payload` (`&[u8]`) - The frame payload, laid out as `BARO_PACKET_PAYLOAD_LENGTH` describes.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<Self, FrameError>` - The reading, or `FrameError::LengthMismatch` if the payload isn't exactly `BARO_PACKET_PAYLOAD_LENGTH` bytes.
+    pub fn from_bytes(payload: &[u8]) -> Result<Self, FrameError> {
+        let payload: &[u8; BARO_PACKET_PAYLOAD_LENGTH] =
+            payload.try_into().map_err(|_| FrameError::LengthMismatch {
+                expected: BARO_PACKET_PAYLOAD_LENGTH,
+                got: payload.len(),
+            })?;
+        let mut idx = 8;
+        Ok(Self {
+            timestamp_seconds: f64::from_le_bytes(*payload.first_chunk().unwrap()),
+            pressure_pascals: parse_bytes_to_f32(payload, &mut idx),
+            temperature_celsius: parse_bytes_to_f32(payload, &mut idx),
         })
     }
+
+    /// Encodes the reading as a frame payload, the inverse of `from_bytes`.
+    pub fn to_bytes(&self) -> Payload {
+        let mut payload = Payload::new();
+        payload.extend_from_slice(&self.timestamp_seconds.to_le_bytes());
+        payload.extend_from_slice(&self.pressure_pascals.to_le_bytes());
+        payload.extend_from_slice(&self.temperature_celsius.to_le_bytes());
+        payload
+    }
+
+    /// Frames the reading the way the device sends it.
+    pub fn to_frame(&self) -> FramedPacket {
+        FramedPacket::new(PacketHeader::Data, BARO_PACKET_IDENTIFIER, self.to_bytes())
+    }
 }
 
+const _: () = assert!(8 + 4 * FIRMData::NUM_F32_FIELDS == DATA_PACKET_PAYLOAD_LENGTH);
+
 impl FIRMData {
-    /// Constructs a `FIRMData` from a raw payload byte slice.
-    pub fn from_bytes(bytes: &[u8]) -> Self {
+    /// Constructs a `FIRMData` from a data packet payload.
+    ///
+    /// # Arguments
+    ///
+    /// - `bytes` (`&[u8]`) - The payload, in one of the `DataLayout`s.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<Self, PacketDecodeError>` - The telemetry, `PacketDecodeError::TooShort` if
+    ///   the payload's length matches no `DataLayout`, or `PacketDecodeError::InvalidValue` if
+    ///   the timestamp isn't finite.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PacketDecodeError> {
+        Self::check_payload(bytes)?;
+        Ok(Self::from_bytes_unchecked(bytes))
+    }
+
+    /// Checks that `from_bytes` would accept `bytes`, without decoding the rest of them.
+    ///
+    /// # Arguments
+    ///
+    /// - `bytes` (`&[u8]`) - The payload.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<DataLayout, PacketDecodeError>` - The payload's layout, or the error
+    ///   `from_bytes` would return.
+    #[cfg_attr(feature = "no-alloc", allow(clippy::useless_conversion))]
+    pub fn check_payload(bytes: &[u8]) -> Result<DataLayout, PacketDecodeError> {
+        let got = bytes.len();
+        let Some(layout) = DataLayout::from_payload_length(got) else {
+            let need = if got < RAW_SENSOR_PACKET_PAYLOAD_LENGTH {
+                RAW_SENSOR_PACKET_PAYLOAD_LENGTH
+            } else {
+                DATA_PACKET_PAYLOAD_LENGTH
+            };
+            return Err(PacketDecodeError::TooShort { need, got });
+        };
+        // Timestamps order the stream, so one that can't be compared is rejected.
+        if !f64::from_le_bytes(*bytes.first_chunk().unwrap()).is_finite() {
+            return Err(PacketDecodeError::InvalidValue {
+                field: "timestamp_seconds".into(),
+            });
+        }
+        Ok(layout)
+    }
+
+    /// Like `from_bytes`, for a payload already checked with `check_payload`. It never
+    /// panics: fields past the end of a shorter payload, e.g. a `DataLayout::RawSensors` one,
+    /// are 0, and nothing is checked.
+    pub fn from_bytes_unchecked(bytes: &[u8]) -> Self {
+        if bytes.len() < DATA_PACKET_PAYLOAD_LENGTH {
+            let mut padded = [0u8; DATA_PACKET_PAYLOAD_LENGTH];
+            padded[..bytes.len()].copy_from_slice(bytes);
+            return Self::from_bytes_unchecked(&padded);
+        }
         let mut idx = 0;
 
         let timestamp_seconds: f64 = f64::from_le_bytes([
@@ -359,6 +823,185 @@ impl FIRMData {
             est_quaternion_x,
             est_quaternion_y,
             est_quaternion_z,
+            pressure_altitude_meters: 0.0,
+        }
+    }
+
+    /// Encodes the fields the device sends as a `DataLayout::Full` payload, the inverse of
+    /// `from_bytes`. `pressure_altitude_meters` is computed on the host and isn't included.
+    pub fn to_bytes(&self) -> Payload {
+        let mut payload = [0; DATA_PACKET_PAYLOAD_LENGTH];
+        self.to_bytes_into(&mut payload)
+            .expect("the buffer is a whole payload long");
+        payload_from_slice(&payload).expect("a data payload fits in any frame")
+    }
+
+    /// Writes the payload `to_bytes` returns to the front of `out`, without allocating.
+    ///
+    /// # Arguments
+    ///
+    /// - `out` (`&mut [u8]`) - Where to write the payload. Bytes past it are left as they are.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<usize, FrameError>` - How many bytes were written,
+    ///   `DATA_PACKET_PAYLOAD_LENGTH`, or `FrameError::PayloadTooLarge` if `out` is shorter
+    ///   than that. Nothing is written then.
+    pub fn to_bytes_into(&self, out: &mut [u8]) -> Result<usize, FrameError> {
+        let Some(out) = out.get_mut(..DATA_PACKET_PAYLOAD_LENGTH) else {
+            return Err(FrameError::PayloadTooLarge {
+                max: out.len(),
+                got: DATA_PACKET_PAYLOAD_LENGTH,
+            });
+        };
+        let (timestamp, fields) = out.split_at_mut(8);
+        timestamp.copy_from_slice(&self.timestamp_seconds.to_le_bytes());
+        for (bytes, value) in fields.chunks_exact_mut(4).zip(self.f32_fields()) {
+            bytes.copy_from_slice(&value.to_le_bytes());
+        }
+        Ok(DATA_PACKET_PAYLOAD_LENGTH)
+    }
+
+    /// Returns the field names, in declaration order.
+    pub fn field_names() -> &'static [&'static str] {
+        &Self::FIELDS
+    }
+
+    /// Returns the packet as a JSON object keyed by field name. NaN and infinity, which JSON
+    /// has no number for, are written as `null`, see `crate::non_finite`.
+    #[cfg(not(feature = "no-alloc"))]
+    pub fn to_json(&self) -> String {
+        let (timestamp_name, f32_names) = Self::field_names().split_first().unwrap();
+        let mut out = format!("{{\"{timestamp_name}\":");
+        write_json_number(&mut out, self.timestamp_seconds);
+        let values = self
+            .f32_fields()
+            .into_iter()
+            .chain([self.pressure_altitude_meters]);
+        for (name, value) in f32_names.iter().zip(values) {
+            out.push_str(&format!(",\"{name}\":"));
+            write_json_f32(&mut out, value);
+        }
+        out.push('}');
+        out
+    }
+
+    /// Number of `f32` fields the device sends after the `f64` timestamp.
+    pub const NUM_F32_FIELDS: usize = 27;
+
+    /// Returns every field the device sends after `timestamp_seconds`, in declaration order
+    /// (which is also the order of the wire payload). `pressure_altitude_meters` is computed
+    /// on the host and isn't included.
+    pub fn f32_fields(&self) -> [f32; Self::NUM_F32_FIELDS] {
+        [
+            self.temperature_celsius,
+            self.pressure_pascals,
+            self.raw_acceleration_x_gs,
+            self.raw_acceleration_y_gs,
+            self.raw_acceleration_z_gs,
+            self.raw_angular_rate_x_deg_per_s,
+            self.raw_angular_rate_y_deg_per_s,
+            self.raw_angular_rate_z_deg_per_s,
+            self.magnetic_field_x_microteslas,
+            self.magnetic_field_y_microteslas,
+            self.magnetic_field_z_microteslas,
+            self.est_position_x_meters,
+            self.est_position_y_meters,
+            self.est_position_z_meters,
+            self.est_velocity_x_meters_per_s,
+            self.est_velocity_y_meters_per_s,
+            self.est_velocity_z_meters_per_s,
+            self.est_acceleration_x_gs,
+            self.est_acceleration_y_gs,
+            self.est_acceleration_z_gs,
+            self.est_angular_rate_x_rad_per_s,
+            self.est_angular_rate_y_rad_per_s,
+            self.est_angular_rate_z_rad_per_s,
+            self.est_quaternion_w,
+            self.est_quaternion_x,
+            self.est_quaternion_y,
+            self.est_quaternion_z,
+        ]
+    }
+
+    /// Returns the value of the field called `name` (one of `field_names()`), or `None` if
+    /// there is no such field.
+    pub fn field_value(&self, name: &str) -> Option<f64> {
+        match name {
+            "timestamp_seconds" => Some(self.timestamp_seconds),
+            "pressure_altitude_meters" => Some(self.pressure_altitude_meters as f64),
+            _ => {
+                // FIELDS is the timestamp, then the `f32_fields` in order, then the host field.
+                let index = Self::FIELDS[1..=Self::NUM_F32_FIELDS]
+                    .iter()
+                    .position(|field| *field == name)?;
+                Some(self.f32_fields()[index] as f64)
+            }
+        }
+    }
+
+    /// Builds a `FIRMData` from a timestamp and the rest of the fields in the order returned
+    /// by `f32_fields`.
+    pub fn from_fields(timestamp_seconds: f64, fields: [f32; Self::NUM_F32_FIELDS]) -> Self {
+        let [
+            temperature_celsius,
+            pressure_pascals,
+            raw_acceleration_x_gs,
+            raw_acceleration_y_gs,
+            raw_acceleration_z_gs,
+            raw_angular_rate_x_deg_per_s,
+            raw_angular_rate_y_deg_per_s,
+            raw_angular_rate_z_deg_per_s,
+            magnetic_field_x_microteslas,
+            magnetic_field_y_microteslas,
+            magnetic_field_z_microteslas,
+            est_position_x_meters,
+            est_position_y_meters,
+            est_position_z_meters,
+            est_velocity_x_meters_per_s,
+            est_velocity_y_meters_per_s,
+            est_velocity_z_meters_per_s,
+            est_acceleration_x_gs,
+            est_acceleration_y_gs,
+            est_acceleration_z_gs,
+            est_angular_rate_x_rad_per_s,
+            est_angular_rate_y_rad_per_s,
+            est_angular_rate_z_rad_per_s,
+            est_quaternion_w,
+            est_quaternion_x,
+            est_quaternion_y,
+            est_quaternion_z,
+        ] = fields;
+        Self {
+            timestamp_seconds,
+            temperature_celsius,
+            pressure_pascals,
+            raw_acceleration_x_gs,
+            raw_acceleration_y_gs,
+            raw_acceleration_z_gs,
+            raw_angular_rate_x_deg_per_s,
+            raw_angular_rate_y_deg_per_s,
+            raw_angular_rate_z_deg_per_s,
+            magnetic_field_x_microteslas,
+            magnetic_field_y_microteslas,
+            magnetic_field_z_microteslas,
+            est_position_x_meters,
+            est_position_y_meters,
+            est_position_z_meters,
+            est_velocity_x_meters_per_s,
+            est_velocity_y_meters_per_s,
+            est_velocity_z_meters_per_s,
+            est_acceleration_x_gs,
+            est_acceleration_y_gs,
+            est_acceleration_z_gs,
+            est_angular_rate_x_rad_per_s,
+            est_angular_rate_y_rad_per_s,
+            est_angular_rate_z_rad_per_s,
+            est_quaternion_w,
+            est_quaternion_x,
+            est_quaternion_y,
+            est_quaternion_z,
+            pressure_altitude_meters: 0.0,
         }
     }
 }
@@ -381,6 +1024,16 @@ impl FIRMResponsePacket {
     pub fn response(&self) -> &FIRMResponse {
         &self.response
     }
+
+    /// Returns the fields of this response that break the protocol. Decoding still produces a
+    /// response for them, flagged as suspect where the type has a flag for it.
+    #[cfg(not(feature = "no-alloc"))]
+    pub fn violations(&self) -> Vec<ResponseViolation> {
+        match self.command_type {
+            FIRMCommand::GetDeviceConfig => DeviceConfig::payload_violations(self.frame.payload()),
+            _ => Vec::new(),
+        }
+    }
 }
 
 impl Framed for FIRMResponsePacket {
@@ -391,6 +1044,13 @@ impl Framed for FIRMResponsePacket {
     fn from_bytes(bytes: &[u8]) -> Result<Self, FrameError> {
         let frame = FramedPacket::from_bytes(bytes)?;
         let command_type = FIRMCommand::from_u16(frame.identifier())?;
+        let min_length = FIRMResponse::min_payload_length(command_type);
+        if frame.payload().len() < min_length {
+            return Err(FrameError::LengthMismatch {
+                expected: min_length,
+                got: frame.payload().len(),
+            });
+        }
         let response = FIRMResponse::from_command_and_bytes(command_type, frame.payload());
 
         Ok(Self {
@@ -402,7 +1062,19 @@ impl Framed for FIRMResponsePacket {
 }
 
 impl FIRMResponse {
-    /// Constructs a decoded `FIRMResponse` from a command and raw payload bytes.
+    /// Returns the shortest payload `from_command_and_bytes` can decode for `command`.
+    pub const fn min_payload_length(command: FIRMCommand) -> usize {
+        match command {
+            FIRMCommand::GetDeviceInfo => DEVICE_ID_LENGTH + FIRMWARE_VERSION_LENGTH,
+            FIRMCommand::GetDeviceConfig => DEVICE_NAME_LENGTH + FREQUENCY_LENGTH + 1,
+            FIRMCommand::GetCalibration => CalibrationValues::ENCODED_LENGTH,
+            _ => 0,
+        }
+    }
+
+    /// Constructs a decoded `FIRMResponse` from a command and raw payload bytes, which must be
+    /// at least `min_payload_length` long.
+    #[cfg_attr(feature = "no-alloc", allow(clippy::useless_conversion))]
     pub fn from_command_and_bytes(command: FIRMCommand, data: &[u8]) -> Self {
         match command {
             FIRMCommand::GetDeviceInfo => {
@@ -429,19 +1101,18 @@ impl FIRMResponse {
                         .try_into()
                         .unwrap(),
                 );
-                let protocol_byte = data[DEVICE_NAME_LENGTH + FREQUENCY_LENGTH];
-                let protocol = match protocol_byte {
-                    1 => DeviceProtocol::USB,
-                    2 => DeviceProtocol::UART,
-                    3 => DeviceProtocol::I2C,
-                    4 => DeviceProtocol::SPI,
-                    _ => DeviceProtocol::USB, // Fallback for invalid values
-                };
+                // Invalid values fall back to USB, and flag the config as suspect.
+                let protocol =
+                    DeviceProtocol::try_from(data[DEVICE_NAME_LENGTH + FREQUENCY_LENGTH])
+                        .unwrap_or(DeviceProtocol::USB);
 
+                let mut suspect = false;
+                DeviceConfig::visit_payload_violations(data, |_| suspect = true);
                 let config = DeviceConfig {
                     frequency,
                     protocol,
                     name,
+                    suspect,
                 };
 
                 FIRMResponse::GetDeviceConfig(config)
@@ -467,43 +1138,10 @@ impl FIRMResponse {
                 FIRMResponse::SetIMUCalibration(success)
             }
             FIRMCommand::GetCalibration => {
-                let mut idx = 0;
-                let imu_accelerometer_offsets =
-                    parse_bytes_to_many_f32s(data, NUMBER_OF_CALIBRATION_OFFSETS, &mut idx);
-                let imu_accelerometer_scale_matrix = parse_bytes_to_many_f32s(
-                    data,
-                    NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS,
-                    &mut idx,
-                );
-                let imu_gyroscope_offsets =
-                    parse_bytes_to_many_f32s(data, NUMBER_OF_CALIBRATION_OFFSETS, &mut idx);
-                let imu_gyroscope_scale_matrix = parse_bytes_to_many_f32s(
-                    data,
-                    NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS,
-                    &mut idx,
-                );
-                let magnetometer_offsets =
-                    parse_bytes_to_many_f32s(data, NUMBER_OF_CALIBRATION_OFFSETS, &mut idx);
-                let magnetometer_scale_matrix = parse_bytes_to_many_f32s(
-                    data,
-                    NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS,
-                    &mut idx,
-                );
-                FIRMResponse::GetCalibration(CalibrationValues {
-                    imu_accelerometer_offsets: imu_accelerometer_offsets.try_into().unwrap(),
-                    imu_accelerometer_scale_matrix: imu_accelerometer_scale_matrix
-                        .try_into()
-                        .unwrap(),
-                    imu_gyroscope_offsets: imu_gyroscope_offsets.try_into().unwrap(),
-                    imu_gyroscope_scale_matrix: imu_gyroscope_scale_matrix.try_into().unwrap(),
-                    magnetometer_offsets: magnetometer_offsets.try_into().unwrap(),
-                    magnetometer_scale_matrix: magnetometer_scale_matrix.try_into().unwrap(),
-                })
+                FIRMResponse::GetCalibration(CalibrationValues::from_bytes(data))
             }
             // Reboot currently has no decoded response type.
-            FIRMCommand::Reboot => {
-                FIRMResponse::Error("No decoded response for Reboot".to_string())
-            }
+            FIRMCommand::Reboot => FIRMResponse::Error("No decoded response for Reboot".into()),
         }
     }
 }
@@ -511,13 +1149,18 @@ impl FIRMResponse {
 #[cfg(test)]
 mod tests {
     use super::{
-        DeviceConfig, DeviceInfo, DeviceProtocol, FIRMData, FIRMResponse, FIRMResponsePacket,
+        CalibrationValues, DataLayout, DeviceConfig, DeviceInfo, DeviceProtocol, FIRMBaroPacket,
+        FIRMData, FIRMDataPacket, FIRMResponse, FIRMResponsePacket, PacketDecodeError,
+        ResponseViolation,
     };
+    use crate::client_packets::FIRMCommandPacket;
     use crate::constants::command::{
         DEVICE_ID_LENGTH, DEVICE_NAME_LENGTH, FIRMCommand, FIRMWARE_VERSION_LENGTH,
-        FREQUENCY_LENGTH,
+        FREQUENCY_LENGTH, IMU_CALIBRATION_PAYLOAD_LENGTH,
+    };
+    use crate::constants::packet::{
+        DATA_PACKET_PAYLOAD_LENGTH, PacketHeader, RAW_SENSOR_PACKET_PAYLOAD_LENGTH,
     };
-    use crate::constants::packet::PacketHeader;
     use crate::framed_packet::{FrameError, Framed, FramedPacket};
     use crate::utils::str_to_bytes;
 
@@ -542,6 +1185,91 @@ mod tests {
         FIRMResponsePacket::from_bytes(&bytes)
     }
 
+    /// A `GetCalibration` response payload with small offsets and near-identity matrices.
+    const CALIBRATION_PAYLOAD: [u8; CalibrationValues::ENCODED_LENGTH] = [
+        0xf0, 0x85, 0x49, 0x3c, 0x11, 0xc7, 0x3a, 0xbd, 0x53, 0x96, 0xa1, 0x3d, //
+        0x89, 0x41, 0x80, 0x3f, 0x6f, 0x12, 0x83, 0x3a, 0xa6, 0x9b, 0x44, 0xbb, //
+        0x6f, 0x12, 0x03, 0x3b, 0xee, 0x7c, 0x7f, 0x3f, 0x6f, 0x12, 0x83, 0x3b, //
+        0x6f, 0x12, 0x83, 0xba, 0xa6, 0x9b, 0x44, 0x3b, 0xd7, 0xa3, 0x80, 0x3f, //
+        0x00, 0x00, 0x00, 0x3f, 0x00, 0x00, 0x80, 0xbe, 0x00, 0x00, 0x00, 0x3e, //
+        0x00, 0x00, 0x80, 0x3f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x3f, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x3f, //
+        0x00, 0x00, 0x48, 0x41, 0x00, 0x00, 0xf6, 0xc1, 0x00, 0x00, 0x80, 0x40, //
+        0xec, 0x51, 0x78, 0x3f, 0x0a, 0xd7, 0xa3, 0x3c, 0x00, 0x00, 0x00, 0x00, //
+        0x0a, 0xd7, 0xa3, 0x3c, 0x0a, 0xd7, 0x83, 0x3f, 0x0a, 0xd7, 0x23, 0xbc, //
+        0x00, 0x00, 0x00, 0x00, 0x0a, 0xd7, 0x23, 0xbc, 0xae, 0x47, 0x81, 0x3f, //
+    ];
+
+    #[test]
+    fn test_calibration_response_decodes_captured_payload() {
+        let packet =
+            build_response_packet(FIRMCommand::GetCalibration.to_u16(), &CALIBRATION_PAYLOAD)
+                .unwrap();
+        let FIRMResponse::GetCalibration(calibration) = packet.response() else {
+            panic!(
+                "expected a calibration response, got {:?}",
+                packet.response()
+            );
+        };
+        assert_eq!(
+            *calibration,
+            CalibrationValues {
+                imu_accelerometer_offsets: [0.0123, -0.0456, 0.0789],
+                imu_accelerometer_scale_matrix: [
+                    1.002, 0.001, -0.003, 0.002, 0.998, 0.004, -0.001, 0.003, 1.005
+                ],
+                imu_gyroscope_offsets: [0.5, -0.25, 0.125],
+                imu_gyroscope_scale_matrix: [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0],
+                magnetometer_offsets: [12.5, -30.75, 4.0],
+                magnetometer_scale_matrix: [0.97, 0.02, 0.0, 0.02, 1.03, -0.01, 0.0, -0.01, 1.01],
+            }
+        );
+    }
+
+    #[test]
+    fn test_calibration_round_trips_through_set_command_payloads() {
+        let calibration = CalibrationValues::from_bytes(&CALIBRATION_PAYLOAD);
+        assert_eq!(calibration.to_bytes(), CALIBRATION_PAYLOAD);
+
+        let imu = FIRMCommandPacket::build_set_imu_calibration_command(
+            calibration.imu_accelerometer_offsets,
+            calibration.imu_accelerometer_scale_matrix,
+            calibration.imu_gyroscope_offsets,
+            calibration.imu_gyroscope_scale_matrix,
+        );
+        let magnetometer = FIRMCommandPacket::build_set_magnetometer_calibration_command(
+            calibration.magnetometer_offsets,
+            calibration.magnetometer_scale_matrix,
+        );
+        let (imu_bytes, magnetometer_bytes) =
+            CALIBRATION_PAYLOAD.split_at(IMU_CALIBRATION_PAYLOAD_LENGTH);
+        assert_eq!(imu.payload(), imu_bytes);
+        assert_eq!(magnetometer.payload(), magnetometer_bytes);
+    }
+
+    #[test]
+    fn test_device_config_as_stored_truncates_name() {
+        let config = DeviceConfig {
+            name: "x".repeat(DEVICE_NAME_LENGTH + 8),
+            frequency: 500,
+            protocol: DeviceProtocol::SPI,
+            suspect: false,
+        };
+        let stored = config.as_stored();
+        assert_eq!(stored.name, "x".repeat(DEVICE_NAME_LENGTH));
+        assert_eq!(
+            (stored.frequency, stored.protocol),
+            (500, DeviceProtocol::SPI)
+        );
+
+        let short = DeviceConfig {
+            name: "FIRM".to_string(),
+            ..config
+        };
+        assert_eq!(short.as_stored(), short);
+    }
+
     #[test]
     fn test_firm_data_packet_from_bytes() {
         let mut payload = [0u8; 120];
@@ -552,12 +1280,93 @@ mod tests {
         payload[8..12].copy_from_slice(&temperature.to_le_bytes());
         payload[12..16].copy_from_slice(&pressure.to_le_bytes());
 
-        let pkt = FIRMData::from_bytes(&payload);
+        let pkt = FIRMData::from_bytes(&payload).unwrap();
         assert_eq!(pkt.timestamp_seconds, timestamp);
         assert_eq!(pkt.temperature_celsius, temperature);
         assert_eq!(pkt.pressure_pascals, pressure);
     }
 
+    #[test]
+    fn test_firm_data_from_bytes_rejects_short_and_invalid_payloads() {
+        let payload = FIRMData::from_fields(1.0, [0.5; FIRMData::NUM_F32_FIELDS]).to_bytes();
+        assert!(FIRMData::from_bytes(&payload[..RAW_SENSOR_PACKET_PAYLOAD_LENGTH]).is_ok());
+        assert_eq!(
+            FIRMData::from_bytes(&payload[..10]),
+            Err(PacketDecodeError::TooShort {
+                need: RAW_SENSOR_PACKET_PAYLOAD_LENGTH,
+                got: 10,
+            })
+        );
+        assert_eq!(
+            FIRMData::from_bytes(&payload[..DATA_PACKET_PAYLOAD_LENGTH - 1]),
+            Err(PacketDecodeError::TooShort {
+                need: DATA_PACKET_PAYLOAD_LENGTH,
+                got: DATA_PACKET_PAYLOAD_LENGTH - 1,
+            })
+        );
+        assert!(FIRMData::from_bytes(&[]).is_err());
+
+        for timestamp in [f64::NAN, f64::INFINITY] {
+            let data = FIRMData::from_fields(timestamp, [0.5; FIRMData::NUM_F32_FIELDS]);
+            let error = PacketDecodeError::InvalidValue {
+                field: "timestamp_seconds".to_string(),
+            };
+            assert_eq!(FIRMData::from_bytes(&data.to_bytes()), Err(error.clone()));
+            let frame = FramedPacket::new(PacketHeader::Data, 0, data.to_bytes()).to_bytes();
+            assert_eq!(FIRMDataPacket::from_bytes(&frame), Err(error.into()));
+        }
+
+        // Every slice either decodes or is rejected, without panicking.
+        for len in 0..=payload.len() {
+            let decoded = FIRMData::from_bytes(&payload[..len]);
+            assert_eq!(
+                decoded.is_ok(),
+                DataLayout::from_payload_length(len).is_some()
+            );
+        }
+    }
+
+    #[test]
+    fn test_field_value_by_name() {
+        let fields: [f32; FIRMData::NUM_F32_FIELDS] = core::array::from_fn(|i| i as f32);
+        let mut data = FIRMData::from_fields(1.5, fields);
+        data.pressure_altitude_meters = 120.0;
+
+        assert_eq!(data.field_value("timestamp_seconds"), Some(1.5));
+        assert_eq!(data.field_value("temperature_celsius"), Some(0.0));
+        assert_eq!(data.field_value("est_position_z_meters"), Some(13.0));
+        assert_eq!(data.field_value("est_quaternion_z"), Some(26.0));
+        assert_eq!(data.field_value("pressure_altitude_meters"), Some(120.0));
+        assert_eq!(data.field_value("altitude"), None);
+        for name in FIRMData::field_names() {
+            assert!(data.field_value(name).is_some(), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_to_json_writes_non_finite_values_as_null() {
+        let mut fields = [1.0f32; FIRMData::NUM_F32_FIELDS];
+        fields[0] = f32::NAN;
+        fields[1] = f32::INFINITY;
+        fields[2] = 0.1;
+        let mut data = FIRMData::from_fields(2.5, fields);
+        data.pressure_altitude_meters = f32::NEG_INFINITY;
+
+        let json = data.to_json();
+        assert!(
+            json.starts_with(
+                "{\"timestamp_seconds\":2.5,\"temperature_celsius\":null,\
+                 \"pressure_pascals\":null,\"raw_acceleration_x_gs\":0.1,"
+            ),
+            "{json}"
+        );
+        assert!(
+            json.ends_with(",\"pressure_altitude_meters\":null}"),
+            "{json}"
+        );
+        assert_eq!(json.matches(':').count(), FIRMData::field_names().len());
+    }
+
     #[test]
     fn test_firm_response_packet_from_bytes_get_device_info() {
         let mut payload = [0u8; DEVICE_ID_LENGTH + FIRMWARE_VERSION_LENGTH];
@@ -600,14 +1409,111 @@ mod tests {
                 name: "MyDevice".to_string(),
                 frequency,
                 protocol: DeviceProtocol::I2C,
+                suspect: false,
             })
         );
         assert_eq!(pkt.command_type(), FIRMCommand::GetDeviceConfig);
     }
 
+    /// A `GetDeviceConfig` response payload with the given fields.
+    fn device_config_payload(
+        name: &[u8],
+        frequency: u16,
+        protocol: u8,
+    ) -> [u8; DEVICE_NAME_LENGTH + FREQUENCY_LENGTH + 1] {
+        let mut payload = [0u8; DEVICE_NAME_LENGTH + FREQUENCY_LENGTH + 1];
+        payload[..name.len()].copy_from_slice(name);
+        payload[DEVICE_NAME_LENGTH..DEVICE_NAME_LENGTH + FREQUENCY_LENGTH]
+            .copy_from_slice(&frequency.to_le_bytes());
+        payload[DEVICE_NAME_LENGTH + FREQUENCY_LENGTH] = protocol;
+        payload
+    }
+
+    #[test]
+    fn test_device_config_violations_flag_the_response_as_suspect() {
+        let cases: &[(&[u8], u16, u8, ResponseViolation)] = &[
+            (b"FIRM", 0, 1, ResponseViolation::FrequencyOutOfRange(0)),
+            (
+                b"FIRM",
+                1001,
+                2,
+                ResponseViolation::FrequencyOutOfRange(1001),
+            ),
+            (b"FIRM", 100, 0, ResponseViolation::InvalidProtocol(0)),
+            (b"FIRM", 100, 5, ResponseViolation::InvalidProtocol(5)),
+            (b"FI\xffRM", 100, 3, ResponseViolation::NameNotUtf8),
+            (b"", 100, 4, ResponseViolation::EmptyName),
+        ];
+        for (name, frequency, protocol, violation) in cases {
+            let payload = device_config_payload(name, *frequency, *protocol);
+            let pkt = build_response_packet(FIRMCommand::GetDeviceConfig as u16, &payload).unwrap();
+            assert_eq!(pkt.violations(), vec![violation.clone()]);
+            let FIRMResponse::GetDeviceConfig(config) = pkt.response() else {
+                panic!("expected a device config, got {:?}", pkt.response());
+            };
+            assert!(config.suspect, "{violation} wasn't flagged");
+        }
+
+        let payload = device_config_payload(b"", 0, 9);
+        assert_eq!(
+            DeviceConfig::payload_violations(&payload),
+            vec![
+                ResponseViolation::EmptyName,
+                ResponseViolation::FrequencyOutOfRange(0),
+                ResponseViolation::InvalidProtocol(9),
+            ]
+        );
+        for frequency in [1, 1000] {
+            let payload = device_config_payload(b"FIRM", frequency, 1);
+            assert!(DeviceConfig::payload_violations(&payload).is_empty());
+        }
+
+        // A payload cut short is reported rather than read past its end.
+        let payload = device_config_payload(b"FIRM", 100, 1);
+        for len in [0, DEVICE_NAME_LENGTH, payload.len() - 1] {
+            assert_eq!(
+                DeviceConfig::payload_violations(&payload[..len]),
+                vec![ResponseViolation::PayloadTooShort {
+                    expected: payload.len(),
+                    got: len,
+                }]
+            );
+        }
+
+        for protocol in [
+            DeviceProtocol::USB,
+            DeviceProtocol::UART,
+            DeviceProtocol::I2C,
+            DeviceProtocol::SPI,
+        ] {
+            assert_eq!(DeviceProtocol::try_from(protocol as u8), Ok(protocol));
+        }
+        assert_eq!(DeviceProtocol::try_from(0), Err(0));
+    }
+
+    #[test]
+    fn test_firm_response_packet_rejects_short_payloads() {
+        for command in [
+            FIRMCommand::GetDeviceInfo,
+            FIRMCommand::GetDeviceConfig,
+            FIRMCommand::GetCalibration,
+        ] {
+            let expected = FIRMResponse::min_payload_length(command);
+            for got in [0, expected - 1] {
+                assert_eq!(
+                    build_response_packet(command as u16, &vec![0u8; got]).unwrap_err(),
+                    FrameError::LengthMismatch { expected, got }
+                );
+            }
+            assert!(build_response_packet(command as u16, &vec![1u8; expected]).is_ok());
+        }
+    }
+
+    type AckResponseBuilder = fn(bool) -> FIRMResponse;
+
     #[test]
     fn test_firm_response_packet_from_bytes_set_device_config() {
-        let cases: &[(u16, FIRMCommand, fn(bool) -> FIRMResponse)] = &[
+        let cases: &[(u16, FIRMCommand, AckResponseBuilder)] = &[
             (
                 FIRMCommand::SetDeviceConfig as u16,
                 FIRMCommand::SetDeviceConfig,
@@ -630,4 +1536,106 @@ mod tests {
         let err = build_response_packet(0x00AB, &payload).unwrap_err();
         assert_eq!(err, FrameError::UnknownIdentifier(0x00AB));
     }
+
+    /// A barometer packet as the device frames it: t = 12.5 s, 101325 Pa, 21.5 °C.
+    const BARO_FRAME_FIXTURE: [u8; 26] = [
+        0x5A, 0xA5, 0x01, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x29,
+        0x40, 0x80, 0xE6, 0xC5, 0x47, 0x00, 0x00, 0xAC, 0x41, 0xF6, 0x63,
+    ];
+
+    #[test]
+    fn test_baro_packet_decodes_fixture() {
+        let frame = FramedPacket::from_bytes(&BARO_FRAME_FIXTURE).unwrap();
+        let packet = FIRMBaroPacket::from_bytes(frame.payload()).unwrap();
+        assert_eq!(
+            packet,
+            FIRMBaroPacket {
+                timestamp_seconds: 12.5,
+                pressure_pascals: 101_325.0,
+                temperature_celsius: 21.5,
+            }
+        );
+        assert_eq!(packet.to_frame().to_bytes(), BARO_FRAME_FIXTURE);
+    }
+
+    #[test]
+    fn test_baro_packet_rejects_other_lengths() {
+        for len in [0, 15, 17, 120] {
+            assert_eq!(
+                FIRMBaroPacket::from_bytes(&vec![0u8; len]),
+                Err(FrameError::LengthMismatch {
+                    expected: 16,
+                    got: len
+                })
+            );
+        }
+    }
+
+    /// Small deterministic generator so the random packets are reproducible.
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next_u32(&mut self) -> u32 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (self.0 >> 32) as u32
+        }
+    }
+
+    #[test]
+    fn test_data_payload_round_trips_any_field_values() {
+        let mut rng = Lcg(0xF12A);
+        for _ in 0..500 {
+            // Any bit pattern, NaNs and infinities included, comes back bit for bit.
+            let fields = core::array::from_fn(|_| f32::from_bits(rng.next_u32()));
+            let timestamp = f64::from_bits(u64::from(rng.next_u32()) << 32 | 0x1234_5678);
+            let mut data = FIRMData::from_fields(timestamp, fields);
+            data.pressure_altitude_meters = 123.0;
+
+            let payload = data.to_bytes();
+            assert_eq!(payload.len(), DATA_PACKET_PAYLOAD_LENGTH);
+            // `from_bytes` would reject the non-finite timestamps.
+            let decoded = FIRMData::from_bytes_unchecked(&payload);
+            assert_eq!(decoded.timestamp_seconds.to_bits(), timestamp.to_bits());
+            assert_eq!(
+                decoded.f32_fields().map(f32::to_bits),
+                fields.map(f32::to_bits)
+            );
+            assert_eq!(decoded.pressure_altitude_meters, 0.0);
+
+            let mut out = [0xEE; DATA_PACKET_PAYLOAD_LENGTH + 2];
+            assert_eq!(data.to_bytes_into(&mut out), Ok(DATA_PACKET_PAYLOAD_LENGTH));
+            assert_eq!(out[..DATA_PACKET_PAYLOAD_LENGTH], payload[..]);
+            assert_eq!(out[DATA_PACKET_PAYLOAD_LENGTH..], [0xEE; 2]);
+        }
+
+        let data = FIRMData::from_fields(1.0, [2.0; FIRMData::NUM_F32_FIELDS]);
+        let mut short = [0xEE; DATA_PACKET_PAYLOAD_LENGTH - 1];
+        assert_eq!(
+            data.to_bytes_into(&mut short),
+            Err(FrameError::PayloadTooLarge {
+                max: DATA_PACKET_PAYLOAD_LENGTH - 1,
+                got: DATA_PACKET_PAYLOAD_LENGTH,
+            })
+        );
+        assert!(short.iter().all(|&byte| byte == 0xEE));
+    }
+
+    #[test]
+    fn test_data_packet_round_trips_through_its_frame() {
+        let mut rng = Lcg(7);
+        for _ in 0..100 {
+            // Finite values, so the packets compare equal.
+            let fields = core::array::from_fn(|_| rng.next_u32() as f32 / 1000.0 - 2.0e6);
+            let data = FIRMData::from_fields(f64::from(rng.next_u32()) / 1.0e4, fields);
+            let packet = FIRMDataPacket::new(data.clone());
+            assert_eq!(packet.data(), &data);
+            assert_eq!(packet.layout(), DataLayout::Full);
+
+            let bytes = packet.to_bytes();
+            assert_eq!(FIRMDataPacket::from_bytes(&bytes), Ok(packet));
+        }
+    }
 }
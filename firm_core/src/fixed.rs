@@ -0,0 +1,249 @@
+//! Fixed-capacity stand-ins for `Vec<u8>` and `String`. Under the `no-alloc` feature frames
+//! keep their payloads in a `FixedBytes`, see `framed_packet::Payload`, and responses keep
+//! their text in `FixedString`s, so decoding a packet never touches the heap.
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+
+/// Up to `N` bytes stored inline.
+#[derive(Clone)]
+pub struct FixedBytes<const N: usize> {
+    bytes: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> FixedBytes<N> {
+    /// Most bytes it can hold.
+    pub const CAPACITY: usize = N;
+
+    /// Creates an empty buffer.
+    pub const fn new() -> Self {
+        Self {
+            bytes: [0; N],
+            len: 0,
+        }
+    }
+
+    /// Copies `bytes` into a new buffer.
+    ///
+    /// # Arguments
+    ///
+    /// - `bytes` (`&[u8]`) - The bytes to copy.
+    ///
+    /// # Returns
+    ///
+    /// - `Option<Self>` - The buffer, or `None` if `bytes` is longer than `N`.
+    pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+        let mut out = Self::new();
+        out.bytes.get_mut(..bytes.len())?.copy_from_slice(bytes);
+        out.len = bytes.len();
+        Some(out)
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    /// Appends `bytes`, like `Vec::extend_from_slice`.
+    ///
+    /// # Panics
+    ///
+    /// If the bytes don't fit in the `N - len()` bytes left.
+    pub fn extend_from_slice(&mut self, bytes: &[u8]) {
+        let end = self.len + bytes.len();
+        assert!(end <= N, "{end} bytes don't fit in a FixedBytes<{N}>");
+        self.bytes[self.len..end].copy_from_slice(bytes);
+        self.len = end;
+    }
+
+    /// Sets the length to `len`, filling any new bytes with `value`, like `Vec::resize`.
+    ///
+    /// # Panics
+    ///
+    /// If `len` is more than `N`.
+    pub fn resize(&mut self, len: usize, value: u8) {
+        assert!(len <= N, "{len} bytes don't fit in a FixedBytes<{N}>");
+        if len > self.len {
+            self.bytes[self.len..len].fill(value);
+        }
+        self.len = len;
+    }
+}
+
+impl<const N: usize> Default for FixedBytes<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Deref for FixedBytes<N> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl<const N: usize> DerefMut for FixedBytes<N> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.bytes[..self.len]
+    }
+}
+
+impl<const N: usize> AsRef<[u8]> for FixedBytes<N> {
+    fn as_ref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl<const N: usize> PartialEq for FixedBytes<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<const N: usize> Eq for FixedBytes<N> {}
+
+impl<const N: usize> fmt::Debug for FixedBytes<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_slice().fmt(f)
+    }
+}
+
+/// UTF-8 text of up to `N` bytes stored inline.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct FixedString<const N: usize> {
+    bytes: FixedBytes<N>,
+}
+
+impl<const N: usize> FixedString<N> {
+    /// Creates an empty string.
+    pub const fn new() -> Self {
+        Self {
+            bytes: FixedBytes::new(),
+        }
+    }
+
+    /// Decodes `bytes` like `String::from_utf8_lossy`, replacing invalid sequences with
+    /// U+FFFD, and keeps as many whole characters as fit in `N` bytes.
+    ///
+    /// # Arguments
+    ///
+    /// - `bytes` (`&[u8]`) - The text to decode.
+    pub fn from_utf8_lossy(bytes: &[u8]) -> Self {
+        let mut out = Self::new();
+        for chunk in bytes.utf8_chunks() {
+            let invalid = if chunk.invalid().is_empty() {
+                ""
+            } else {
+                "\u{FFFD}"
+            };
+            for text in [chunk.valid(), invalid] {
+                if !out.push_str_truncated(text) {
+                    return out;
+                }
+            }
+        }
+        out
+    }
+
+    pub fn as_str(&self) -> &str {
+        // Only whole characters are ever copied in.
+        core::str::from_utf8(self.bytes.as_slice()).unwrap()
+    }
+
+    /// Appends as many whole characters of `text` as fit, returning whether all of them did.
+    fn push_str_truncated(&mut self, text: &str) -> bool {
+        let room = N - self.bytes.len();
+        let mut end = text.len().min(room);
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.bytes.extend_from_slice(&text.as_bytes()[..end]);
+        end == text.len()
+    }
+}
+
+impl<const N: usize> From<&str> for FixedString<N> {
+    /// Copies as many whole characters of `text` as fit in `N` bytes.
+    fn from(text: &str) -> Self {
+        let mut out = Self::new();
+        out.push_str_truncated(text);
+        out
+    }
+}
+
+impl<const N: usize> Deref for FixedString<N> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> AsRef<str> for FixedString<N> {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> PartialEq<str> for FixedString<N> {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<const N: usize> PartialEq<&str> for FixedString<N> {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl<const N: usize> fmt::Debug for FixedString<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+impl<const N: usize> fmt::Display for FixedString<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_bytes_stay_within_capacity() {
+        assert_eq!(
+            FixedBytes::<4>::from_slice(&[1, 2, 3]).unwrap().as_slice(),
+            [1, 2, 3]
+        );
+        assert!(FixedBytes::<4>::from_slice(&[0; 5]).is_none());
+
+        let mut bytes = FixedBytes::<4>::new();
+        bytes.extend_from_slice(&[7]);
+        bytes.resize(3, 9);
+        assert_eq!(*bytes, [7, 9, 9]);
+        bytes[0] = 1;
+        bytes.resize(1, 0);
+        assert_eq!(bytes, FixedBytes::from_slice(&[1]).unwrap());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_fixed_bytes_panic_past_capacity() {
+        FixedBytes::<2>::new().extend_from_slice(&[0; 3]);
+    }
+
+    #[test]
+    fn test_fixed_strings_keep_whole_characters() {
+        assert_eq!(FixedString::<8>::from("FIRM"), "FIRM");
+        // "é" is two bytes, so it doesn't fit after "abc" in four.
+        assert_eq!(FixedString::<4>::from("abcé"), "abc");
+        assert_eq!(FixedString::<5>::from("abcé"), "abcé");
+        assert_eq!(FixedString::<8>::from_utf8_lossy(b"v1\xFF2"), "v1\u{FFFD}2");
+        assert_eq!(FixedString::<4>::from_utf8_lossy(b"v1\xFF2"), "v1");
+    }
+}
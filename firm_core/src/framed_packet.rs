@@ -1,15 +1,104 @@
-use alloc::vec::Vec;
+#[cfg(not(feature = "no-alloc"))]
+use alloc::{collections::VecDeque, string::String, vec, vec::Vec};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
-use crate::{constants::packet::*, utils::crc16_ccitt};
+#[cfg(feature = "no-alloc")]
+use crate::fixed::FixedBytes;
+#[cfg(not(feature = "no-alloc"))]
+use crate::{
+    cobs::{COBS_DELIMITER, CobsFrameScanner, cobs_encode_into, cobs_max_encoded_len},
+    parser_storage::{ByteBuffer, RollingBuffer},
+};
+use crate::{
+    constants::packet::*,
+    data_parser::SerialParser,
+    utils::{crc16_ccitt, crc16_ccitt_update, crc32, crc32_update},
+};
+
+/// What a `FramedPacket` keeps its payload in: a `Vec`, or with the `no-alloc` feature a
+/// `FixedBytes` with room for the longest payload `SerialParser` accepts.
+#[cfg(not(feature = "no-alloc"))]
+pub type Payload = Vec<u8>;
+/// What a `FramedPacket` keeps its payload in: a `Vec`, or with the `no-alloc` feature a
+/// `FixedBytes` with room for the longest payload `SerialParser` accepts.
+#[cfg(feature = "no-alloc")]
+pub type Payload = FixedBytes<{ SerialParser::MAX_PAYLOAD_LENGTH }>;
+
+/// Copies `bytes` into a `Payload`, or returns `FrameError::PayloadTooLarge` if there's no room
+/// for them, which only happens with the `no-alloc` feature.
+pub(crate) fn payload_from_slice(bytes: &[u8]) -> Result<Payload, FrameError> {
+    #[cfg(not(feature = "no-alloc"))]
+    return Ok(bytes.to_vec());
+    #[cfg(feature = "no-alloc")]
+    Payload::from_slice(bytes).ok_or(FrameError::PayloadTooLarge {
+        max: Payload::CAPACITY,
+        got: bytes.len(),
+    })
+}
+
+/// The field named by `FrameError::InvalidValue` and `PacketDecodeError::InvalidValue`: a
+/// `String`, or a `&'static str` with the `no-alloc` feature.
+#[cfg(not(feature = "no-alloc"))]
+pub type FieldName = String;
+/// The field named by `FrameError::InvalidValue` and `PacketDecodeError::InvalidValue`: a
+/// `String`, or a `&'static str` with the `no-alloc` feature.
+#[cfg(feature = "no-alloc")]
+pub type FieldName = &'static str;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum FrameError {
     TooShort,
-    LengthMismatch { expected: usize, got: usize },
-    BadCrc { expected: u16, got: u16 },
+    LengthMismatch {
+        expected: usize,
+        got: usize,
+    },
+    BadCrc {
+        expected: u32,
+        got: u32,
+    },
     UnknownIdentifier(u16),
+    /// The payload is larger than the firmware accepts for this packet type.
+    PayloadTooLarge {
+        max: usize,
+        got: usize,
+    },
+    /// A `WireFormat::Cobs` frame's stuffing is malformed, see `crate::cobs`.
+    BadEncoding,
+    /// The payload has a value its packet can't hold, see `PacketDecodeError::InvalidValue`.
+    InvalidValue {
+        field: FieldName,
+    },
+}
+
+impl core::fmt::Display for FrameError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            FrameError::TooShort => write!(f, "frame is too short"),
+            FrameError::LengthMismatch { expected, got } => {
+                write!(f, "length mismatch: expected {expected}, got {got}")
+            }
+            FrameError::BadCrc { expected, got } => {
+                write!(f, "bad CRC: expected {expected:#06X}, got {got:#06X}")
+            }
+            FrameError::UnknownIdentifier(identifier) => {
+                write!(f, "unknown identifier {identifier:#06X}")
+            }
+            FrameError::PayloadTooLarge { max, got } => {
+                write!(
+                    f,
+                    "payload too large: at most {max} bytes allowed, got {got}"
+                )
+            }
+            FrameError::BadEncoding => write!(f, "malformed COBS encoding"),
+            FrameError::InvalidValue { field } => write!(f, "invalid value for {field}"),
+        }
+    }
 }
 
+impl core::error::Error for FrameError {}
+
 /// Trait implemented by all packet types that are framed using FramedPacket.
 pub trait Framed: Sized {
     fn frame(&self) -> &FramedPacket;
@@ -36,41 +125,207 @@ pub trait Framed: Sized {
         self.frame().is_empty()
     }
 
-    fn crc(&self) -> u16 {
+    fn crc(&self) -> u32 {
         self.frame().crc()
     }
 
+    fn sequence(&self) -> Option<u16> {
+        self.frame().sequence()
+    }
+
+    fn checksum(&self) -> Checksum {
+        self.frame().checksum()
+    }
+
+    #[cfg(not(feature = "no-alloc"))]
     fn to_bytes(&self) -> Vec<u8> {
         self.frame().to_bytes()
     }
+
+    fn encoded_len(&self) -> usize {
+        self.frame().encoded_len()
+    }
+
+    fn to_bytes_into(&self, out: &mut [u8]) -> Result<usize, FrameError> {
+        self.frame().to_bytes_into(out)
+    }
+
+    #[cfg(not(feature = "no-alloc"))]
+    fn to_cobs_bytes(&self) -> Vec<u8> {
+        self.frame().to_cobs_bytes()
+    }
+}
+
+/// Which checksum a frame ends with. The trailer is as long as the checksum, see `size`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Checksum {
+    /// CRC-16/CCITT in a 2 byte trailer, what FIRM sends today.
+    #[default]
+    Crc16Ccitt,
+    /// CRC-32 in a 4 byte trailer, for log downloads from newer firmware.
+    Crc32,
+}
+
+impl Checksum {
+    /// Returns the checksum a frame with this header and identifier ends with, which is what
+    /// `FramedPacket::new`, `FramedPacket::from_bytes` and the stream parsers go by. Every
+    /// frame FIRM sends today ends with `Crc16Ccitt`; frames the firmware moves to `Crc32`
+    /// are to be matched here.
+    pub const fn for_frame(_header: PacketHeader, _identifier: u16) -> Self {
+        Checksum::Crc16Ccitt
+    }
+
+    /// Returns how many bytes the checksum takes at the end of a frame.
+    pub const fn size(self) -> usize {
+        match self {
+            Checksum::Crc16Ccitt => CRC_SIZE,
+            Checksum::Crc32 => 4,
+        }
+    }
+
+    /// Computes the checksum of `bytes`.
+    ///
+    /// # Arguments
+    ///
+    /// - `bytes` (`&[u8]`) - What the checksum covers, for a frame everything before its
+    ///   trailer.
+    ///
+    /// # Returns
+    ///
+    /// - `u32` - The checksum, widened from `u16` for `Crc16Ccitt`.
+    pub fn compute(self, bytes: &[u8]) -> u32 {
+        match self {
+            Checksum::Crc16Ccitt => crc16_ccitt(bytes).into(),
+            Checksum::Crc32 => crc32(bytes),
+        }
+    }
+
+    /// Like `compute`, over `parts` one after another.
+    fn compute_parts(self, parts: &[&[u8]]) -> u32 {
+        match self {
+            Checksum::Crc16Ccitt => parts
+                .iter()
+                .fold(0, |crc, part| crc16_ccitt_update(crc, part))
+                .into(),
+            Checksum::Crc32 => !parts.iter().fold(!0, |crc, part| crc32_update(crc, part)),
+        }
+    }
+
+    /// Reads a checksum of this kind from the front of `bytes`, which holds at least `size`.
+    fn read(self, bytes: &[u8]) -> u32 {
+        match self {
+            Checksum::Crc16Ccitt => u16::from_le_bytes([bytes[0], bytes[1]]).into(),
+            Checksum::Crc32 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        }
+    }
 }
 
 /// Shared packet framing for the wire format:
-/// `[header(2)][identifier(2)][length(4)][payload(len)][crc(2)]`.
+/// `[header(2)][identifier(2)][length(4)][payload(len)][crc(2 or 4)]`.
 ///
 /// CRC is computed over everything before the CRC: `header + identifier + len + payload`.
+/// Which CRC, and so how long the trailer is, is the frame's `Checksum`.
+///
+/// A frame made `with_sequence` also carries a sequence number, so a receiver can tell when
+/// frames go missing: `SEQUENCE_FLAG` is set in the identifier field and the number follows
+/// the length, `[header(2)][identifier(2)][length(4)][sequence(2)][payload(len)][crc]`, with
+/// the CRC covering it too. `identifier` never includes the flag.
+///
+/// With serde, a frame is its `header`, `identifier`, `payload`, `checksum`, `crc` and, if it
+/// has one, `sequence`. The payload is a byte array, or a base64 string with the `base64`
+/// feature, a missing checksum is `Crc16Ccitt`, and a frame whose CRC doesn't match is
+/// rejected when deserialized. `Display` prints a hexdump of the wire bytes with
+/// each field annotated.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(into = "FramedPacketFields", try_from = "FramedPacketFields")
+)]
 pub struct FramedPacket {
     header: PacketHeader,
     identifier: u16,
     // There is also a length field in the header, but we store it implicitly as the length of the payload.
-    payload: Vec<u8>,
-    crc: u16,
+    payload: Payload,
+    checksum: Checksum,
+    sequence: Option<u16>,
+    crc: u32,
 }
 
 impl FramedPacket {
+    /// Size of the shortest frame, an empty payload with a `Checksum::Crc16Ccitt` trailer.
     pub const MIN_SIZE: usize = HEADER_SIZE + IDENTIFIER_SIZE + LENGTH_SIZE + CRC_SIZE;
 
-    pub fn new(header: PacketHeader, identifier: u16, payload: Vec<u8>) -> Self {
-        let crc = Self::compute_crc(header, identifier, payload.len() as u32, &payload);
+    /// Creates a frame without checking that `identifier` makes sense under `header`, for raw
+    /// use such as tests and fuzzing. See `new_checked`. `identifier` must leave
+    /// `SEQUENCE_FLAG` clear, or the frame won't parse back as sent.
+    pub fn new(header: PacketHeader, identifier: u16, payload: Payload) -> Self {
+        Self::with_checksum(
+            header,
+            identifier,
+            payload,
+            Checksum::for_frame(header, identifier),
+        )
+    }
+
+    /// Creates a frame, rejecting identifiers the device doesn't know under `header`, such as
+    /// a log packet type sent as a command. The device drops those without saying why.
+    ///
+    /// # Arguments
+    ///
+    /// - `header` (`PacketHeader`) - The kind of frame.
+    /// - `identifier` (`u16`) - The frame's identifier, checked with
+    ///   `PacketHeader::accepts_identifier`.
+    /// - `payload` (`Payload`) - The frame's payload.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<Self, FrameError>` - The frame, or `FrameError::UnknownIdentifier` if
+    ///   `header` has no such identifier.
+    pub fn new_checked(
+        header: PacketHeader,
+        identifier: u16,
+        payload: Payload,
+    ) -> Result<Self, FrameError> {
+        if !header.accepts_identifier(identifier) {
+            return Err(FrameError::UnknownIdentifier(identifier));
+        }
+        Ok(Self::new(header, identifier, payload))
+    }
+
+    /// Like `new`, ending the frame with `checksum` whatever `Checksum::for_frame` says.
+    pub fn with_checksum(
+        header: PacketHeader,
+        identifier: u16,
+        payload: Payload,
+        checksum: Checksum,
+    ) -> Self {
+        let crc = Self::compute_crc(checksum, header, identifier, payload.len() as u32, &payload);
         Self {
             header,
             identifier,
             payload,
+            checksum,
+            sequence: None,
             crc,
         }
     }
 
+    /// Returns the frame numbered `sequence`, sent with `SEQUENCE_FLAG` set and the number
+    /// after its length, e.g. for a mock device whose stream should show dropped frames.
+    pub fn with_sequence(mut self, sequence: u16) -> Self {
+        self.sequence = Some(sequence);
+        self.crc = Self::crc_of(
+            self.checksum,
+            self.header,
+            self.identifier,
+            self.sequence,
+            &self.payload,
+        );
+        self
+    }
+
     pub fn header(&self) -> PacketHeader {
         self.header
     }
@@ -83,10 +338,19 @@ impl FramedPacket {
         &self.payload
     }
 
-    pub fn crc(&self) -> u16 {
+    pub fn crc(&self) -> u32 {
         self.crc
     }
 
+    pub fn checksum(&self) -> Checksum {
+        self.checksum
+    }
+
+    /// Returns the frame's sequence number, or `None` for a frame sent without one.
+    pub fn sequence(&self) -> Option<u16> {
+        self.sequence
+    }
+
     pub fn len(&self) -> u32 {
         self.payload.len() as u32
     }
@@ -95,22 +359,124 @@ impl FramedPacket {
         self.payload.is_empty()
     }
 
+    #[cfg(not(feature = "no-alloc"))]
     pub fn to_bytes(&self) -> Vec<u8> {
-        let len = self.payload.len() as u32;
-        let mut out = Vec::with_capacity(
-            HEADER_SIZE + IDENTIFIER_SIZE + LENGTH_SIZE + self.payload.len() + CRC_SIZE,
-        );
-        out.extend_from_slice(&self.header.as_u16().to_le_bytes());
-        out.extend_from_slice(&self.identifier.to_le_bytes());
-        out.extend_from_slice(&len.to_le_bytes());
-        out.extend_from_slice(&self.payload);
-        out.extend_from_slice(&self.crc.to_le_bytes());
+        let mut out = vec![0; self.encoded_len()];
+        self.to_bytes_into(&mut out)
+            .expect("the buffer is sized by encoded_len");
+        out
+    }
+
+    /// Returns how many bytes `to_bytes` and `to_bytes_into` write for this frame.
+    pub fn encoded_len(&self) -> usize {
+        HEADER_SIZE
+            + IDENTIFIER_SIZE
+            + LENGTH_SIZE
+            + self.sequence_size()
+            + self.payload.len()
+            + self.checksum.size()
+    }
+
+    /// Returns how many bytes the sequence number takes on the wire.
+    fn sequence_size(&self) -> usize {
+        match self.sequence {
+            Some(_) => SEQUENCE_SIZE,
+            None => 0,
+        }
+    }
+
+    /// Returns the identifier field as sent, with `SEQUENCE_FLAG` set if there's a sequence
+    /// number.
+    fn wire_identifier(identifier: u16, sequence: Option<u16>) -> u16 {
+        match sequence {
+            Some(_) => identifier | SEQUENCE_FLAG,
+            None => identifier,
+        }
+    }
+
+    /// Writes the frame to the front of `out`, as `to_bytes` would return it, without
+    /// allocating, e.g. straight into a DMA buffer.
+    ///
+    /// # Arguments
+    ///
+    /// - `out` (`&mut [u8]`) - Where to write the frame. Bytes past the frame are left as they
+    ///   are.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<usize, FrameError>` - How many bytes were written, `encoded_len`, or
+    ///   `FrameError::PayloadTooLarge` with the most payload `out` has room for if the frame
+    ///   doesn't fit. Nothing is written then.
+    pub fn to_bytes_into(&self, out: &mut [u8]) -> Result<usize, FrameError> {
+        let encoded_len = self.encoded_len();
+        let Some(out) = out.get_mut(..encoded_len) else {
+            return Err(FrameError::PayloadTooLarge {
+                max: out.len().saturating_sub(encoded_len - self.payload.len()),
+                got: self.payload.len(),
+            });
+        };
+        let (header, rest) = out.split_at_mut(HEADER_SIZE);
+        let (identifier, rest) = rest.split_at_mut(IDENTIFIER_SIZE);
+        let (len, rest) = rest.split_at_mut(LENGTH_SIZE);
+        let (sequence, rest) = rest.split_at_mut(self.sequence_size());
+        let (payload, crc) = rest.split_at_mut(self.payload.len());
+        header.copy_from_slice(&self.header.as_u16().to_le_bytes());
+        identifier
+            .copy_from_slice(&Self::wire_identifier(self.identifier, self.sequence).to_le_bytes());
+        len.copy_from_slice(&(self.payload.len() as u32).to_le_bytes());
+        if let Some(number) = self.sequence {
+            sequence.copy_from_slice(&number.to_le_bytes());
+        }
+        payload.copy_from_slice(&self.payload);
+        crc.copy_from_slice(&self.crc.to_le_bytes()[..self.checksum.size()]);
+        Ok(encoded_len)
+    }
+
+    /// Returns the frame COBS encoded and followed by `COBS_DELIMITER`, as sent in
+    /// `WireFormat::Cobs`.
+    #[cfg(not(feature = "no-alloc"))]
+    pub fn to_cobs_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(cobs_max_encoded_len(self.encoded_len()) + 1);
+        cobs_encode_into(&self.to_bytes(), &mut out);
+        out.push(COBS_DELIMITER);
         out
     }
 
     /// Parses a single framed packet from `bytes`, requiring that `bytes` contains
-    /// exactly one full frame.
+    /// exactly one full frame. Its trailer is the `Checksum::for_frame` of its header and
+    /// identifier.
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, FrameError> {
+        Self::decode(bytes, None, true).map(|(frame, _)| frame)
+    }
+
+    /// Like `from_bytes`, for a frame known to end with `checksum`.
+    pub fn from_bytes_with_checksum(bytes: &[u8], checksum: Checksum) -> Result<Self, FrameError> {
+        Self::decode(bytes, Some(checksum), true).map(|(frame, _)| frame)
+    }
+
+    /// Parses the frame at the start of `bytes`, ignoring whatever follows it, e.g. in a buffer
+    /// holding a frame and a half.
+    ///
+    /// # Arguments
+    ///
+    /// - `bytes` (`&[u8]`) - Bytes starting with a frame.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<(Self, usize), FrameError>` - The frame and how many bytes of `bytes` it took.
+    ///   `FrameError::TooShort` means the frame hasn't all arrived, so it's worth trying again
+    ///   with more bytes; any other error means these bytes don't start a valid frame.
+    pub fn parse_prefix(bytes: &[u8]) -> Result<(Self, usize), FrameError> {
+        Self::decode(bytes, None, false)
+    }
+
+    /// Parses the frame at the start of `bytes`, returning it and its length. If `exact`,
+    /// bytes left over are an error.
+    fn decode(
+        bytes: &[u8],
+        checksum: Option<Checksum>,
+        exact: bool,
+    ) -> Result<(Self, usize), FrameError> {
         if bytes.len() < Self::MIN_SIZE {
             return Err(FrameError::TooShort);
         }
@@ -118,11 +484,17 @@ impl FramedPacket {
         let header_raw = u16::from_le_bytes(bytes[0..HEADER_SIZE].try_into().unwrap());
         let header =
             PacketHeader::from_u16(header_raw).ok_or(FrameError::UnknownIdentifier(header_raw))?;
-        let identifier = u16::from_le_bytes(
+        let identifier_field = u16::from_le_bytes(
             bytes[HEADER_SIZE..HEADER_SIZE + IDENTIFIER_SIZE]
                 .try_into()
                 .unwrap(),
         );
+        let identifier = identifier_field & !SEQUENCE_FLAG;
+        let sequence_size = match identifier_field & SEQUENCE_FLAG {
+            0 => 0,
+            _ => SEQUENCE_SIZE,
+        };
+        let checksum = checksum.unwrap_or(Checksum::for_frame(header, identifier));
 
         let len_start = HEADER_SIZE + IDENTIFIER_SIZE;
         let len = u32::from_le_bytes(
@@ -131,24 +503,34 @@ impl FramedPacket {
                 .unwrap(),
         ) as usize;
 
-        let expected = HEADER_SIZE + IDENTIFIER_SIZE + LENGTH_SIZE + len + CRC_SIZE;
-        if bytes.len() != expected {
+        // The length is untrusted, and on 32-bit targets adding it to the rest can overflow.
+        let overhead =
+            HEADER_SIZE + IDENTIFIER_SIZE + LENGTH_SIZE + sequence_size + checksum.size();
+        let expected = len
+            .checked_add(overhead)
+            .ok_or(FrameError::PayloadTooLarge {
+                max: usize::MAX - overhead,
+                got: len,
+            })?;
+        if !exact && bytes.len() < expected {
+            return Err(FrameError::TooShort);
+        }
+        if exact && bytes.len() != expected {
             return Err(FrameError::LengthMismatch {
                 expected,
                 got: bytes.len(),
             });
         }
 
-        let payload_start = HEADER_SIZE + IDENTIFIER_SIZE + LENGTH_SIZE;
+        let sequence_start = HEADER_SIZE + IDENTIFIER_SIZE + LENGTH_SIZE;
+        let payload_start = sequence_start + sequence_size;
+        let sequence = (sequence_size > 0)
+            .then(|| u16::from_le_bytes([bytes[sequence_start], bytes[sequence_start + 1]]));
         let payload_end = payload_start + len;
-        let payload = bytes[payload_start..payload_end].to_vec();
+        let payload = payload_from_slice(&bytes[payload_start..payload_end])?;
 
-        let received_crc = u16::from_le_bytes(
-            bytes[payload_end..payload_end + CRC_SIZE]
-                .try_into()
-                .unwrap(),
-        );
-        let computed_crc = Self::compute_crc(header, identifier, len as u32, &payload);
+        let received_crc = checksum.read(&bytes[payload_end..]);
+        let computed_crc = Self::crc_of(checksum, header, identifier, sequence, &payload);
         if received_crc != computed_crc {
             return Err(FrameError::BadCrc {
                 expected: computed_crc,
@@ -156,23 +538,627 @@ impl FramedPacket {
             });
         }
 
-        Ok(Self {
+        let frame = Self {
             header,
             identifier,
             payload,
+            checksum,
+            sequence,
             crc: received_crc,
+        };
+        Ok((frame, expected))
+    }
+
+    /// Computes `checksum` over `[header][identifier][length][payload]`.
+    pub fn compute_crc(
+        checksum: Checksum,
+        header: PacketHeader,
+        identifier: u16,
+        len: u32,
+        payload: &[u8],
+    ) -> u32 {
+        checksum.compute_parts(&[
+            &header.as_u16().to_le_bytes(),
+            &identifier.to_le_bytes(),
+            &len.to_le_bytes(),
+            payload,
+        ])
+    }
+
+    /// Like `compute_crc`, covering the sequence number, and the flag announcing it, too.
+    fn crc_of(
+        checksum: Checksum,
+        header: PacketHeader,
+        identifier: u16,
+        sequence: Option<u16>,
+        payload: &[u8],
+    ) -> u32 {
+        let Some(sequence) = sequence else {
+            return Self::compute_crc(checksum, header, identifier, payload.len() as u32, payload);
+        };
+        checksum.compute_parts(&[
+            &header.as_u16().to_le_bytes(),
+            &(identifier | SEQUENCE_FLAG).to_le_bytes(),
+            &(payload.len() as u32).to_le_bytes(),
+            &sequence.to_le_bytes(),
+            payload,
+        ])
+    }
+}
+
+/// Returns where the first of `start_words` at or after `from` begins, or `None` if there
+/// isn't one. Eight bytes are checked at a time for a start word's first byte, so long
+/// stretches of noise are passed over quickly.
+pub(crate) fn find_start_word(
+    bytes: &[u8],
+    from: usize,
+    start_words: &[[u8; HEADER_SIZE]],
+) -> Option<usize> {
+    const ONES: u64 = u64::from_ne_bytes([0x01; 8]);
+    const HIGH_BITS: u64 = u64::from_ne_bytes([0x80; 8]);
+    // Whether any byte of `word` is `byte`.
+    let contains = |word: u64, byte: u8| {
+        let matched = word ^ (ONES * u64::from(byte));
+        matched.wrapping_sub(ONES) & !matched & HIGH_BITS != 0
+    };
+    let is_start = |index: usize| {
+        bytes
+            .get(index..index + HEADER_SIZE)
+            .is_some_and(|pair| start_words.iter().any(|word| word == pair))
+    };
+
+    // In sync, the next frame starts right where the last one ended.
+    if is_start(from) {
+        return Some(from);
+    }
+    let mut position = from;
+    while let Some(word) = bytes
+        .get(position..)
+        .and_then(|rest| rest.first_chunk::<8>())
+    {
+        let word = u64::from_ne_bytes(*word);
+        if start_words.iter().any(|start| contains(word, start[0]))
+            && let Some(start) = (position..position + 8).find(|&index| is_start(index))
+        {
+            return Some(start);
+        }
+        position += 8;
+    }
+    (position..bytes.len()).find(|&index| is_start(index))
+}
+
+/// What `scan_frame` found at a position in a buffer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum FrameScan {
+    /// This many bytes don't start a frame. The last byte of the buffer is never counted, as
+    /// it may be the first half of a start word still arriving.
+    Noise(usize),
+    /// The frame hasn't all arrived yet.
+    Incomplete,
+    /// A start word followed by a payload length over the limit.
+    PayloadTooLong { length: usize },
+    /// A frame, going by its length field, longer than the buffer can hold.
+    FrameTooLong { length: usize },
+    /// A whole frame ending at `end` whose CRC doesn't match.
+    BadCrc { end: usize, expected: u32, got: u32 },
+    /// A whole, CRC-valid frame ending at `end`, its payload starting at `payload_start` and
+    /// its trailer at `crc_start`, with its sequence number if it has one.
+    Frame {
+        sequence: Option<u16>,
+        payload_start: usize,
+        crc_start: usize,
+        end: usize,
+    },
+}
+
+/// Looks for a frame starting at `position` in `bytes`, applying the framing rules shared by
+/// `SerialParser` and `FrameScanner`. How a caller skips past what it finds is up to it.
+///
+/// # Arguments
+///
+/// - `bytes` (`&[u8]`) - The buffered stream.
+/// - `position` (`usize`) - Where to start looking, before the last byte of `bytes`.
+/// - `start_words` (`&[[u8; HEADER_SIZE]]`) - The headers a frame may start with, as on the wire.
+/// - `max_payload_len` (`usize`) - Longest payload a length field may claim.
+/// - `max_frame_len` (`usize`) - Longest frame the buffer can hold.
+///
+/// # Returns
+///
+/// - `FrameScan` - What starts at `position`.
+pub(crate) fn scan_frame(
+    bytes: &[u8],
+    position: usize,
+    start_words: &[[u8; HEADER_SIZE]],
+    max_payload_len: usize,
+    max_frame_len: usize,
+) -> FrameScan {
+    let start = find_start_word(bytes, position, start_words).unwrap_or(bytes.len() - 1);
+    if start > position {
+        return FrameScan::Noise(start - position);
+    }
+
+    // Need at least header+len+crc.
+    if position + MIN_PACKET_SIZE > bytes.len() {
+        return FrameScan::Incomplete;
+    }
+    let length_start = position + HEADER_SIZE + IDENTIFIER_SIZE;
+    let length_bytes: [u8; LENGTH_SIZE] = bytes[length_start..length_start + LENGTH_SIZE]
+        .try_into()
+        .unwrap();
+    let length = u32::from_le_bytes(length_bytes) as usize;
+    if length > max_payload_len {
+        return FrameScan::PayloadTooLong { length };
+    }
+
+    let header = u16::from_le_bytes([bytes[position], bytes[position + 1]]);
+    let identifier = u16::from_le_bytes([bytes[position + 2], bytes[position + 3]]);
+    let checksum = PacketHeader::from_u16(header).map_or(Checksum::Crc16Ccitt, |header| {
+        Checksum::for_frame(header, identifier & !SEQUENCE_FLAG)
+    });
+    let sequence_start = length_start + LENGTH_SIZE;
+    let payload_start = match identifier & SEQUENCE_FLAG {
+        0 => sequence_start,
+        _ => sequence_start + SEQUENCE_SIZE,
+    };
+    let crc_start = payload_start + length;
+    let end = crc_start + checksum.size();
+    // Checked whether or not the frame has arrived, so the outcome doesn't depend on how the
+    // stream was split into reads.
+    if end - position > max_frame_len {
+        return FrameScan::FrameTooLong { length };
+    }
+    if end > bytes.len() {
+        return FrameScan::Incomplete;
+    }
+
+    // The CRC covers [header][identifier][len][sequence][payload].
+    let expected = checksum.compute(&bytes[position..crc_start]);
+    let got = checksum.read(&bytes[crc_start..]);
+    if expected != got {
+        return FrameScan::BadCrc { end, expected, got };
+    }
+    let sequence = (payload_start > sequence_start)
+        .then(|| u16::from_le_bytes([bytes[sequence_start], bytes[sequence_start + 1]]));
+    FrameScan::Frame {
+        sequence,
+        payload_start,
+        crc_start,
+        end,
+    }
+}
+
+/// A break in the sequence numbers of a stream's frames: the frame numbered `got` arrived when
+/// `expected` was next, because frames in between went missing or the sender started counting
+/// again. Reported by `SerialParser::get_sequence_gap` and `FrameScanner::next_sequence_gap`.
+/// Frames without a sequence number are ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SequenceGap {
+    pub expected: u16,
+    pub got: u16,
+}
+
+impl SequenceGap {
+    /// Returns how many frames are missing, counting on from `expected` to `got`.
+    pub fn missing(&self) -> u16 {
+        self.got.wrapping_sub(self.expected)
+    }
+
+    /// Checks a frame numbered `sequence` against `next`, the number the stream should be on,
+    /// and moves `next` past it.
+    pub(crate) fn check(next: &mut Option<u16>, sequence: u16) -> Option<Self> {
+        let expected = next.replace(sequence.wrapping_add(1))?;
+        (sequence != expected).then_some(Self {
+            expected,
+            got: sequence,
         })
     }
+}
+
+/// How frames are laid out on the wire, for `SerialParser::set_wire_format` and
+/// `FrameScanner::set_wire_format`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum WireFormat {
+    /// Frames back to back, found by their start words and lengths. What FIRM sends.
+    #[default]
+    Raw,
+    /// Each frame COBS encoded and followed by `COBS_DELIMITER`, as `to_cobs_bytes` writes
+    /// it, so a damaged frame can't take the frames after it down with it. See `crate::cobs`.
+    /// Not available with the `no-alloc` feature.
+    #[cfg(not(feature = "no-alloc"))]
+    Cobs,
+}
+
+#[cfg(not(feature = "no-alloc"))]
+impl WireFormat {
+    pub(crate) fn to_byte(self) -> u8 {
+        match self {
+            WireFormat::Raw => 0,
+            WireFormat::Cobs => 1,
+        }
+    }
+
+    pub(crate) fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(WireFormat::Raw),
+            1 => Some(WireFormat::Cobs),
+            _ => None,
+        }
+    }
+}
+
+/// Pulls `FramedPacket`s out of a byte stream that arrives in arbitrary chunks, e.g. reads
+/// from a port. Bytes that don't start a frame, frames that fail their CRC and frames too long
+/// for the buffer are skipped a byte at a time until the next valid frame, the same way
+/// `SerialParser` does, but frames are kept whole rather than decoded. In `WireFormat::Cobs`
+/// the stream is split at each delimiter first, and each frame is scanned on its own.
+///
+/// Only the unfinished end of the stream stays buffered between pushes, so the buffer never
+/// holds more than `max_buffer_len` bytes once `push` returns. Complete frames wait in a
+/// queue for `next_frame`.
+#[cfg(not(feature = "no-alloc"))]
+#[derive(Debug, Clone)]
+pub struct FrameScanner {
+    bytes: RollingBuffer,
+    frames: VecDeque<FramedPacket>,
+    start_words: Vec<[u8; HEADER_SIZE]>,
+    max_buffer_len: usize,
+    wire_format: WireFormat,
+    /// Splits the stream into frames in `WireFormat::Cobs`.
+    cobs: CobsFrameScanner,
+    bytes_skipped: u64,
+    crc_failures: u64,
+    /// Sequence number the next numbered frame should have.
+    next_sequence: Option<u16>,
+    /// Oldest first, at most `SerialParser::MAX_SEQUENCE_GAPS`.
+    sequence_gaps: VecDeque<SequenceGap>,
+}
+
+#[cfg(not(feature = "no-alloc"))]
+impl Default for FrameScanner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(not(feature = "no-alloc"))]
+impl FrameScanner {
+    /// Creates a scanner that finds frames with any `PacketHeader`, buffering at most
+    /// `SerialParser::DEFAULT_MAX_BUFFER_LEN` bytes.
+    pub fn new() -> Self {
+        Self::for_headers(&[
+            PacketHeader::Data,
+            PacketHeader::Response,
+            PacketHeader::LogSensor,
+            PacketHeader::Command,
+        ])
+    }
+
+    /// Creates a scanner that only finds frames starting with one of `headers`, e.g.
+    /// `PacketHeader::Command` on the device's side of the link.
+    ///
+    /// # Arguments
+    ///
+    /// - `headers` (`&[PacketHeader]`) - The headers to look for.
+    ///
+    /// # Returns
+    ///
+    /// - `Self` - An empty scanner.
+    pub fn for_headers(headers: &[PacketHeader]) -> Self {
+        Self {
+            bytes: RollingBuffer::default(),
+            frames: VecDeque::new(),
+            start_words: headers
+                .iter()
+                .map(|header| header.as_u16().to_le_bytes())
+                .collect(),
+            max_buffer_len: SerialParser::DEFAULT_MAX_BUFFER_LEN,
+            wire_format: WireFormat::Raw,
+            cobs: CobsFrameScanner::new(),
+            bytes_skipped: 0,
+            crc_failures: 0,
+            next_sequence: None,
+            sequence_gaps: VecDeque::new(),
+        }
+    }
+
+    /// Sets how frames are laid out in the bytes pushed from now on. Bytes still buffered in
+    /// the old format are dropped and counted as skipped.
+    pub fn set_wire_format(&mut self, wire_format: WireFormat) {
+        if wire_format != self.wire_format {
+            let buffered = self.bytes.len() + self.cobs.clear();
+            self.bytes.consume(self.bytes.len());
+            self.bytes_skipped += buffered as u64;
+            self.wire_format = wire_format;
+        }
+    }
+
+    /// Returns the format set by `set_wire_format`.
+    pub fn wire_format(&self) -> WireFormat {
+        self.wire_format
+    }
+
+    /// Sets how many bytes the scanner may hold on to while waiting for the rest of a frame.
+    /// Frames longer than this are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// - `max_buffer_len` (`usize`) - The limit in bytes, at least `MIN_PACKET_SIZE`.
+    pub fn set_max_buffer_len(&mut self, max_buffer_len: usize) {
+        self.max_buffer_len = max_buffer_len.max(MIN_PACKET_SIZE);
+        self.cobs.set_max_frame_len(self.max_buffer_len);
+    }
+
+    /// Returns the limit set by `set_max_buffer_len`.
+    pub fn max_buffer_len(&self) -> usize {
+        self.max_buffer_len
+    }
 
-    /// Computes CRC over `[header][identifier][length][payload]`.
-    pub fn compute_crc(header: PacketHeader, identifier: u16, len: u32, payload: &[u8]) -> u16 {
-        let mut crc_input =
-            Vec::with_capacity(HEADER_SIZE + IDENTIFIER_SIZE + LENGTH_SIZE + payload.len());
-        crc_input.extend_from_slice(&header.as_u16().to_le_bytes());
-        crc_input.extend_from_slice(&identifier.to_le_bytes());
-        crc_input.extend_from_slice(&len.to_le_bytes());
-        crc_input.extend_from_slice(payload);
-        crc16_ccitt(&crc_input)
+    /// Appends `bytes` to the stream and queues every frame they complete.
+    pub fn push(&mut self, bytes: &[u8]) {
+        match self.wire_format {
+            WireFormat::Raw => {
+                self.bytes.extend_from(bytes);
+                self.scan();
+            }
+            WireFormat::Cobs => {
+                let mut cobs = core::mem::take(&mut self.cobs);
+                cobs.split(bytes, |_, wire_len, decoded| match decoded {
+                    // Whatever the frames in it leave over can't be the start of another.
+                    Ok(decoded) => {
+                        self.bytes.extend_from(decoded);
+                        self.scan();
+                        self.bytes_skipped += self.bytes.len() as u64;
+                        self.bytes.consume(self.bytes.len());
+                    }
+                    Err(_) => self.bytes_skipped += wire_len as u64,
+                });
+                self.cobs = cobs;
+            }
+        }
+    }
+
+    /// Queues every complete frame in the buffer, dropping the bytes that were processed.
+    fn scan(&mut self) {
+        let mut position = 0;
+        while position + 1 < self.bytes.len() {
+            let buffered = self.bytes.as_slice();
+            let skipped = match scan_frame(
+                buffered,
+                position,
+                &self.start_words,
+                SerialParser::MAX_PAYLOAD_LENGTH,
+                self.max_buffer_len,
+            ) {
+                FrameScan::Noise(count) => count,
+                FrameScan::Incomplete => break,
+                FrameScan::PayloadTooLong { .. } | FrameScan::FrameTooLong { .. } => 1,
+                FrameScan::BadCrc { .. } => {
+                    self.crc_failures += 1;
+                    1
+                }
+                FrameScan::Frame { .. } => {
+                    match FramedPacket::parse_prefix(&buffered[position..]) {
+                        Ok((frame, consumed)) => {
+                            let gap = frame.sequence().and_then(|sequence| {
+                                SequenceGap::check(&mut self.next_sequence, sequence)
+                            });
+                            if let Some(gap) = gap {
+                                if self.sequence_gaps.len() >= SerialParser::MAX_SEQUENCE_GAPS {
+                                    self.sequence_gaps.pop_front();
+                                }
+                                self.sequence_gaps.push_back(gap);
+                            }
+                            self.frames.push_back(frame);
+                            position += consumed;
+                            continue;
+                        }
+                        Err(_) => 1,
+                    }
+                }
+            };
+            self.bytes_skipped += skipped as u64;
+            position += skipped;
+        }
+        self.bytes.consume(position);
+    }
+
+    /// Pops the oldest complete frame, with its header.
+    pub fn next_frame(&mut self) -> Option<(PacketHeader, FramedPacket)> {
+        self.frames.pop_front().map(|frame| (frame.header(), frame))
+    }
+
+    /// Pops the oldest gap in the frames' sequence numbers, if any. Only the latest
+    /// `SerialParser::MAX_SEQUENCE_GAPS` are kept.
+    pub fn next_sequence_gap(&mut self) -> Option<SequenceGap> {
+        self.sequence_gaps.pop_front()
+    }
+
+    /// Returns how many bytes are buffered waiting for the rest of a frame, encoded in
+    /// `WireFormat::Cobs`.
+    pub fn buffered_len(&self) -> usize {
+        self.bytes.len() + self.cobs.buffered_len()
+    }
+
+    /// Returns how many bytes have been skipped looking for frames.
+    pub fn bytes_skipped(&self) -> u64 {
+        self.bytes_skipped
+    }
+
+    /// Returns how many whole frames failed their CRC.
+    pub fn crc_failures(&self) -> u64 {
+        self.crc_failures
+    }
+}
+
+/// How serde sees a `FramedPacket`.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "FramedPacket")]
+struct FramedPacketFields {
+    header: PacketHeader,
+    identifier: u16,
+    #[cfg_attr(feature = "base64", serde(with = "base64_payload"))]
+    payload: Vec<u8>,
+    #[serde(default)]
+    checksum: Checksum,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sequence: Option<u16>,
+    crc: u32,
+}
+
+#[cfg(feature = "serde")]
+impl From<FramedPacket> for FramedPacketFields {
+    fn from(packet: FramedPacket) -> Self {
+        Self {
+            header: packet.header,
+            identifier: packet.identifier,
+            payload: packet.payload,
+            checksum: packet.checksum,
+            sequence: packet.sequence,
+            crc: packet.crc,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<FramedPacketFields> for FramedPacket {
+    type Error = FrameError;
+
+    fn try_from(fields: FramedPacketFields) -> Result<Self, FrameError> {
+        let mut packet = FramedPacket::with_checksum(
+            fields.header,
+            fields.identifier,
+            fields.payload,
+            fields.checksum,
+        );
+        if let Some(sequence) = fields.sequence {
+            packet = packet.with_sequence(sequence);
+        }
+        if packet.crc != fields.crc {
+            return Err(FrameError::BadCrc {
+                expected: packet.crc,
+                got: fields.crc,
+            });
+        }
+        Ok(packet)
+    }
+}
+
+/// Serde for payloads as base64 strings.
+#[cfg(feature = "base64")]
+mod base64_payload {
+    use crate::utils::{base64_decode, base64_encode};
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub(super) fn serialize<S: Serializer>(
+        payload: &[u8],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base64_encode(payload))
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<u8>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        base64_decode(&text).ok_or_else(|| D::Error::custom("payload isn't valid base64"))
+    }
+}
+
+#[cfg(not(feature = "no-alloc"))]
+impl core::fmt::Display for FramedPacket {
+    /// Prints the wire bytes sixteen to a line, each line with its offset and the bytes as
+    /// ASCII, then where each field sits and what it holds.
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let bytes = self.to_bytes();
+        for (line, chunk) in bytes.chunks(16).enumerate() {
+            write!(f, "{:04x}  ", line * 16)?;
+            for column in 0..16 {
+                match chunk.get(column) {
+                    Some(byte) => write!(f, "{byte:02x} ")?,
+                    None => write!(f, "   ")?,
+                }
+                if column == 7 {
+                    write!(f, " ")?;
+                }
+            }
+            write!(f, " |")?;
+            for &byte in chunk {
+                let shown = if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                };
+                write!(f, "{shown}")?;
+            }
+            writeln!(f, "|")?;
+        }
+
+        let length_start = HEADER_SIZE + IDENTIFIER_SIZE;
+        let sequence_start = length_start + LENGTH_SIZE;
+        let payload_start = sequence_start + self.sequence_size();
+        let crc_start = payload_start + self.payload.len();
+        let sequence = self.sequence.map(|sequence| {
+            (
+                "sequence",
+                sequence_start,
+                payload_start,
+                alloc::format!("{sequence}"),
+            )
+        });
+        let fields = [
+            (
+                "header",
+                0,
+                HEADER_SIZE,
+                alloc::format!("{:#06X} ({:?})", self.header.as_u16(), self.header),
+            ),
+            (
+                "identifier",
+                HEADER_SIZE,
+                length_start,
+                alloc::format!(
+                    "{:#06X}",
+                    Self::wire_identifier(self.identifier, self.sequence)
+                ),
+            ),
+            (
+                "length",
+                length_start,
+                sequence_start,
+                alloc::format!("{}", self.payload.len()),
+            ),
+        ];
+        let fields = fields.into_iter().chain(sequence).chain([
+            (
+                "payload",
+                payload_start,
+                crc_start,
+                alloc::format!("{} bytes", self.payload.len()),
+            ),
+            (
+                "crc",
+                crc_start,
+                bytes.len(),
+                alloc::format!(
+                    "{:#0width$X}",
+                    self.crc,
+                    width = 2 + 2 * self.checksum.size()
+                ),
+            ),
+        ]);
+        for (i, (name, start, end, value)) in fields.enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            let range = alloc::format!("{start}..{end}");
+            write!(f, "  {name:<12}{range:<10}{value}")?;
+        }
+        Ok(())
     }
 }
 
@@ -194,4 +1180,517 @@ mod tests {
         assert_eq!(parsed.payload(), payload.as_slice());
         assert_eq!(parsed.crc(), pkt.crc());
     }
+
+    #[test]
+    fn test_display_prints_an_annotated_hexdump() {
+        let packet =
+            FramedPacket::new(PacketHeader::Response, 0x0003, b"FIRM ok!\x00\x01".to_vec());
+        assert_eq!(
+            alloc::format!("{packet}"),
+            "\
+0000  a5 5a 03 00 0a 00 00 00  46 49 52 4d 20 6f 6b 21  |.Z......FIRM ok!|
+0010  00 01 3f 87                                       |..?.|
+  header      0..2      0x5AA5 (Response)
+  identifier  2..4      0x0003
+  length      4..8      10
+  payload     8..18     10 bytes
+  crc         18..20    0x873F"
+        );
+
+        let empty = FramedPacket::new(PacketHeader::Command, 0x00FF, Vec::new());
+        assert_eq!(
+            alloc::format!("{empty}"),
+            "\
+0000  6b b6 ff 00 00 00 00 00  dd 81                    |k.........|
+  header      0..2      0xB66B (Command)
+  identifier  2..4      0x00FF
+  length      4..8      0
+  payload     8..8      0 bytes
+  crc         8..10     0x81DD"
+        );
+    }
+
+    #[test]
+    fn test_serde_fields_round_trip_and_check_the_crc() {
+        let packet = FramedPacket::new(PacketHeader::Data, 0x0001, vec![1, 2, 3]);
+        let fields = FramedPacketFields::from(packet.clone());
+        assert_eq!(fields.crc, packet.crc());
+        assert_eq!(FramedPacket::try_from(fields), Ok(packet.clone()));
+
+        let tampered = FramedPacketFields {
+            payload: vec![1, 2, 4],
+            ..FramedPacketFields::from(packet.clone())
+        };
+        assert_eq!(
+            FramedPacket::try_from(tampered),
+            Err(FrameError::BadCrc {
+                expected: FramedPacket::new(PacketHeader::Data, 0x0001, vec![1, 2, 4]).crc(),
+                got: packet.crc(),
+            })
+        );
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn test_base64_round_trips_every_padding() {
+        use crate::utils::{base64_decode, base64_encode};
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+        for len in 0..20 {
+            let bytes: Vec<u8> = (0..len).map(|i| (i * 37) as u8).collect();
+            assert_eq!(base64_decode(&base64_encode(&bytes)), Some(bytes));
+        }
+        for bad in ["Zg=", "Zg=a", "Z===", "Zg==Zg==", "Zm9*"] {
+            assert_eq!(base64_decode(bad), None, "{bad}");
+        }
+    }
+
+    /// A response frame, `FIRM ok!\0\x01` as `SetDeviceConfig`'s reply, with each trailer.
+    const FIXTURE_BODY: [u8; 18] = [
+        0xA5, 0x5A, 0x03, 0x00, 0x0A, 0x00, 0x00, 0x00, 0x46, 0x49, 0x52, 0x4D, 0x20, 0x6F, 0x6B,
+        0x21, 0x00, 0x01,
+    ];
+    const FIXTURE_CRC16_TRAILER: [u8; 2] = [0x3F, 0x87];
+    const FIXTURE_CRC32_TRAILER: [u8; 4] = [0xF2, 0x16, 0x23, 0x9E];
+
+    #[test]
+    fn test_checksums_match_their_check_values() {
+        assert_eq!(Checksum::Crc16Ccitt.compute(b"123456789"), 0x2189);
+        assert_eq!(Checksum::Crc32.compute(b"123456789"), 0xCBF4_3926);
+        assert_eq!(Checksum::Crc32.compute(b""), 0);
+        assert_eq!(
+            (Checksum::Crc16Ccitt.size(), Checksum::Crc32.size()),
+            (2, 4)
+        );
+        assert_eq!(
+            Checksum::for_frame(PacketHeader::LogSensor, b'I' as u16),
+            Checksum::Crc16Ccitt
+        );
+    }
+
+    #[test]
+    fn test_frames_match_the_fixture_vectors() {
+        let fixtures: [(Checksum, &[u8], u32); 2] = [
+            (Checksum::Crc16Ccitt, &FIXTURE_CRC16_TRAILER, 0x873F),
+            (Checksum::Crc32, &FIXTURE_CRC32_TRAILER, 0x9E23_16F2),
+        ];
+        for (checksum, trailer, crc) in fixtures {
+            let bytes = [&FIXTURE_BODY[..], trailer].concat();
+            let packet = FramedPacket::with_checksum(
+                PacketHeader::Response,
+                0x0003,
+                b"FIRM ok!\x00\x01".to_vec(),
+                checksum,
+            );
+            assert_eq!((packet.checksum(), packet.crc()), (checksum, crc));
+            assert_eq!(packet.encoded_len(), bytes.len());
+            assert_eq!(packet.to_bytes(), bytes);
+            assert_eq!(
+                FramedPacket::from_bytes_with_checksum(&bytes, checksum),
+                Ok(packet.clone())
+            );
+            let fields = FramedPacketFields::from(packet.clone());
+            assert_eq!(FramedPacket::try_from(fields), Ok(packet));
+
+            let mut corrupted = bytes.clone();
+            *corrupted.last_mut().unwrap() ^= 0x01;
+            assert_eq!(
+                FramedPacket::from_bytes_with_checksum(&corrupted, checksum),
+                Err(FrameError::BadCrc {
+                    expected: crc,
+                    got: crc ^ (0x01 << (8 * (checksum.size() - 1))),
+                })
+            );
+        }
+
+        // The header and identifier imply the short trailer, so the long one is two bytes over.
+        let crc32_frame = [&FIXTURE_BODY[..], &FIXTURE_CRC32_TRAILER].concat();
+        assert_eq!(
+            FramedPacket::from_bytes(&[&FIXTURE_BODY[..], &FIXTURE_CRC16_TRAILER].concat())
+                .map(|packet| packet.checksum()),
+            Ok(Checksum::Crc16Ccitt)
+        );
+        assert_eq!(
+            FramedPacket::from_bytes(&crc32_frame),
+            Err(FrameError::LengthMismatch {
+                expected: 20,
+                got: 22,
+            })
+        );
+
+        let dump = alloc::format!(
+            "{}",
+            FramedPacket::from_bytes_with_checksum(&crc32_frame, Checksum::Crc32).unwrap()
+        );
+        assert!(
+            dump.ends_with("  crc         18..22    0x9E2316F2"),
+            "{dump}"
+        );
+    }
+
+    #[test]
+    fn test_to_bytes_into_writes_what_to_bytes_returns() {
+        for payload_len in [0usize, 1, 56, 120, 266] {
+            let payload: Vec<u8> = (0..payload_len).map(|i| i as u8).collect();
+            let packet = FramedPacket::new(PacketHeader::Response, 0x0102, payload);
+            let expected = packet.to_bytes();
+            assert_eq!(packet.encoded_len(), expected.len());
+
+            // Bytes past the frame are left alone.
+            let mut out = vec![0xEE; expected.len() + 3];
+            assert_eq!(packet.to_bytes_into(&mut out), Ok(expected.len()));
+            assert_eq!(out[..expected.len()], expected[..]);
+            assert_eq!(out[expected.len()..], [0xEE; 3]);
+            assert_eq!(
+                FramedPacket::from_bytes(&out[..expected.len()]),
+                Ok(packet.clone())
+            );
+
+            let mut short = vec![0xEE; expected.len() - 1];
+            assert_eq!(
+                packet.to_bytes_into(&mut short),
+                Err(FrameError::PayloadTooLarge {
+                    max: payload_len.saturating_sub(1),
+                    got: payload_len,
+                })
+            );
+            assert!(short.iter().all(|&byte| byte == 0xEE));
+        }
+    }
+
+    #[test]
+    fn test_sequence_numbers_round_trip_without_changing_plain_frames() {
+        let plain = FramedPacket::new(PacketHeader::Response, 0x0003, b"FIRM ok!\x00\x01".to_vec());
+        let numbered = plain.clone().with_sequence(0x1234);
+        assert_eq!(plain.sequence(), None);
+        assert_eq!(numbered.sequence(), Some(0x1234));
+        assert_eq!(numbered.identifier(), 0x0003);
+        assert_ne!(numbered.crc(), plain.crc());
+
+        let bytes = numbered.to_bytes();
+        assert_eq!(bytes.len(), plain.encoded_len() + SEQUENCE_SIZE);
+        assert_eq!(numbered.encoded_len(), bytes.len());
+        assert_eq!(bytes[2..4], (0x0003 | SEQUENCE_FLAG).to_le_bytes());
+        assert_eq!(bytes[8..10], [0x34, 0x12]);
+        assert_eq!(bytes[10..20], *b"FIRM ok!\x00\x01");
+        assert_eq!(FramedPacket::from_bytes(&bytes), Ok(numbered.clone()));
+        let mut stream = bytes.clone();
+        stream.extend(plain.to_bytes());
+        assert_eq!(
+            FramedPacket::parse_prefix(&stream),
+            Ok((numbered.clone(), bytes.len()))
+        );
+        assert_eq!(
+            FramedPacket::parse_prefix(&stream[bytes.len()..]),
+            Ok((plain.clone(), plain.encoded_len()))
+        );
+
+        // The CRC covers the sequence number.
+        let mut renumbered = bytes.clone();
+        renumbered[8] ^= 0x01;
+        assert!(matches!(
+            FramedPacket::from_bytes(&renumbered),
+            Err(FrameError::BadCrc { .. })
+        ));
+
+        let fields = FramedPacketFields::from(numbered.clone());
+        assert_eq!(fields.sequence, Some(0x1234));
+        assert_eq!(FramedPacket::try_from(fields), Ok(numbered.clone()));
+        assert_eq!(FramedPacketFields::from(plain).sequence, None);
+
+        let shown = alloc::format!("{numbered}");
+        assert!(
+            shown.contains("  identifier  2..4      0x8003\n"),
+            "{shown}"
+        );
+        assert!(shown.contains("  sequence    8..10     4660\n"), "{shown}");
+        assert!(
+            shown.contains("  payload     10..20    10 bytes\n"),
+            "{shown}"
+        );
+    }
+
+    #[test]
+    fn test_frame_scanner_reports_sequence_gaps() {
+        let numbered = |sequence: u16| {
+            FramedPacket::new(PacketHeader::Data, 0, vec![7u8; 20]).with_sequence(sequence)
+        };
+        let plain = FramedPacket::new(PacketHeader::Command, 0x0001, vec![]);
+        let frames = [
+            numbered(10),
+            plain.clone(),
+            numbered(11),
+            numbered(14),
+            plain,
+            numbered(15),
+        ];
+        let bytes: Vec<u8> = frames.iter().flat_map(FramedPacket::to_bytes).collect();
+        for chunk_len in [1, 7, bytes.len()] {
+            let mut scanner = FrameScanner::new();
+            let mut found = Vec::new();
+            for chunk in bytes.chunks(chunk_len) {
+                scanner.push(chunk);
+                found.extend(drain(&mut scanner).into_iter().map(|(_, frame)| frame));
+            }
+            assert_eq!(found, frames);
+            assert_eq!(
+                scanner.next_sequence_gap(),
+                Some(SequenceGap {
+                    expected: 12,
+                    got: 14
+                })
+            );
+            assert_eq!(scanner.next_sequence_gap(), None);
+            assert_eq!(scanner.bytes_skipped(), 0);
+        }
+    }
+
+    #[test]
+    fn test_new_checked_accepts_only_identifiers_known_under_the_header() {
+        use crate::constants::command::FIRMCommand;
+        use crate::constants::log_parsing::FIRMLogPacketType;
+
+        let commands = [
+            FIRMCommand::GetDeviceInfo,
+            FIRMCommand::GetDeviceConfig,
+            FIRMCommand::SetDeviceConfig,
+            FIRMCommand::Reboot,
+            FIRMCommand::Mock,
+            FIRMCommand::SetMagnetometerCalibration,
+            FIRMCommand::SetIMUCalibration,
+            FIRMCommand::GetCalibration,
+            FIRMCommand::Cancel,
+        ]
+        .map(FIRMCommand::to_u16);
+        let log_types = [
+            FIRMLogPacketType::HeaderPacket,
+            FIRMLogPacketType::BarometerPacket,
+            FIRMLogPacketType::IMUPacket,
+            FIRMLogPacketType::MagnetometerPacket,
+        ]
+        .map(FIRMLogPacketType::as_u16);
+        let data = [DATA_PACKET_IDENTIFIER, BARO_PACKET_IDENTIFIER];
+        let unknown = [0x0009, 0x00AB, 0x00FE, 0x0100, 0xFFFF];
+
+        // (header, identifiers it accepts)
+        let table: [(PacketHeader, &[u16]); 4] = [
+            (PacketHeader::Data, &data),
+            (PacketHeader::Response, &commands),
+            (PacketHeader::Command, &commands),
+            (PacketHeader::LogSensor, &log_types),
+        ];
+        let every_identifier: Vec<u16> = [&data[..], &commands, &log_types, &unknown].concat();
+
+        for (header, accepted) in table {
+            for &identifier in &every_identifier {
+                let checked = FramedPacket::new_checked(header, identifier, vec![1, 2, 3]);
+                if accepted.contains(&identifier) {
+                    assert_eq!(
+                        checked,
+                        Ok(FramedPacket::new(header, identifier, vec![1, 2, 3])),
+                        "{header:?} {identifier:#06X}"
+                    );
+                } else {
+                    assert_eq!(
+                        checked,
+                        Err(FrameError::UnknownIdentifier(identifier)),
+                        "{header:?} {identifier:#06X}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_prefix_reports_how_much_it_consumed() {
+        let packet = FramedPacket::new(PacketHeader::Data, 0, vec![1, 2, 3, 4, 5]);
+        let bytes = packet.to_bytes();
+
+        // Exact fit.
+        assert_eq!(
+            FramedPacket::parse_prefix(&bytes),
+            Ok((packet.clone(), bytes.len()))
+        );
+
+        // Trailing garbage is left for the caller.
+        let mut with_garbage = bytes.clone();
+        with_garbage.extend_from_slice(&[0xDE, 0xAD, 0xBE]);
+        assert_eq!(
+            FramedPacket::parse_prefix(&with_garbage),
+            Ok((packet.clone(), bytes.len()))
+        );
+        assert_eq!(
+            FramedPacket::from_bytes(&with_garbage),
+            Err(FrameError::LengthMismatch {
+                expected: bytes.len(),
+                got: with_garbage.len(),
+            })
+        );
+
+        // A second frame that has only half arrived.
+        let second = FramedPacket::new(PacketHeader::Response, 7, vec![9; 20]);
+        let second_bytes = second.to_bytes();
+        let mut stream = bytes.clone();
+        stream.extend_from_slice(&second_bytes[..second_bytes.len() / 2]);
+        let (first, consumed) = FramedPacket::parse_prefix(&stream).unwrap();
+        assert_eq!((first, consumed), (packet, bytes.len()));
+        assert_eq!(
+            FramedPacket::parse_prefix(&stream[consumed..]),
+            Err(FrameError::TooShort)
+        );
+        stream.extend_from_slice(&second_bytes[second_bytes.len() / 2..]);
+        assert_eq!(
+            FramedPacket::parse_prefix(&stream[consumed..]),
+            Ok((second, second_bytes.len()))
+        );
+
+        for cut in 0..bytes.len() {
+            assert_eq!(
+                FramedPacket::parse_prefix(&bytes[..cut]),
+                Err(FrameError::TooShort)
+            );
+        }
+    }
+
+    #[test]
+    fn test_decode_rejects_a_length_that_overflows_the_frame_size() {
+        for identifier in [0, SEQUENCE_FLAG] {
+            let mut bytes = frame(PacketHeader::Data, 0, &[0; 8]);
+            bytes[HEADER_SIZE..HEADER_SIZE + IDENTIFIER_SIZE]
+                .copy_from_slice(&identifier.to_le_bytes());
+            let len_start = HEADER_SIZE + IDENTIFIER_SIZE;
+            bytes[len_start..len_start + LENGTH_SIZE].copy_from_slice(&u32::MAX.to_le_bytes());
+
+            let prefix = FramedPacket::parse_prefix(&bytes);
+            let exact = FramedPacket::from_bytes(&bytes);
+            if usize::BITS > u32::BITS {
+                // The sum fits, it's just far more than arrived.
+                assert_eq!(prefix, Err(FrameError::TooShort));
+                assert!(matches!(exact, Err(FrameError::LengthMismatch { .. })));
+            } else {
+                assert!(matches!(prefix, Err(FrameError::PayloadTooLarge { .. })));
+                assert!(matches!(exact, Err(FrameError::PayloadTooLarge { .. })));
+            }
+        }
+    }
+
+    fn frame(header: PacketHeader, identifier: u16, payload: &[u8]) -> Vec<u8> {
+        FramedPacket::new(header, identifier, payload.to_vec()).to_bytes()
+    }
+
+    fn drain(scanner: &mut FrameScanner) -> Vec<(PacketHeader, FramedPacket)> {
+        core::iter::from_fn(|| scanner.next_frame()).collect()
+    }
+
+    #[test]
+    fn test_frame_scanner_finds_frames_at_every_split() {
+        let mut bytes = frame(PacketHeader::Data, 0, &[7u8; 120]);
+        bytes.extend(frame(PacketHeader::Response, 0x0003, &[1]));
+        bytes.extend(frame(PacketHeader::Command, 0x0001, &[]));
+        let expected = {
+            let mut scanner = FrameScanner::new();
+            scanner.push(&bytes);
+            drain(&mut scanner)
+        };
+        let headers: Vec<PacketHeader> = expected.iter().map(|(header, _)| *header).collect();
+        assert_eq!(
+            headers,
+            [
+                PacketHeader::Data,
+                PacketHeader::Response,
+                PacketHeader::Command
+            ]
+        );
+        assert_eq!(expected[0].1.payload(), &[7u8; 120]);
+
+        for first in 0..=bytes.len() {
+            for second in first..=bytes.len() {
+                let mut scanner = FrameScanner::new();
+                let mut frames = Vec::new();
+                for chunk in [&bytes[..first], &bytes[first..second], &bytes[second..]] {
+                    scanner.push(chunk);
+                    frames.extend(drain(&mut scanner));
+                }
+                assert_eq!(frames, expected, "split at {first} and {second}");
+                assert_eq!(scanner.buffered_len(), 0);
+                assert_eq!(scanner.bytes_skipped(), 0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_frame_scanner_resyncs_past_noise_and_corrupted_frames() {
+        let good = frame(PacketHeader::Data, 0, &[3u8; 120]);
+        let mut corrupted = good.clone();
+        corrupted[20] ^= 0x01;
+        // A start word claiming a payload far longer than any frame.
+        let mut bogus_length = PacketHeader::Response.as_u16().to_le_bytes().to_vec();
+        bogus_length.extend([0, 0, 0xFF, 0xFF, 0xFF, 0x00]);
+
+        let mut bytes = vec![0x42, 0x13, 0x37];
+        bytes.extend(&corrupted);
+        bytes.extend(&bogus_length);
+        bytes.extend(&good);
+        for chunk_len in [1, 5, 64, bytes.len()] {
+            let mut scanner = FrameScanner::new();
+            let mut frames = Vec::new();
+            for chunk in bytes.chunks(chunk_len) {
+                scanner.push(chunk);
+                frames.extend(drain(&mut scanner));
+            }
+            assert_eq!(frames.len(), 1, "{chunk_len} byte chunks");
+            assert_eq!(frames[0].1.to_bytes(), good);
+            assert_eq!(scanner.crc_failures(), 1);
+            assert_eq!(scanner.bytes_skipped(), (bytes.len() - good.len()) as u64);
+        }
+    }
+
+    #[test]
+    fn test_frame_scanner_splits_cobs_frames_and_drops_damaged_ones() {
+        let good = FramedPacket::new(PacketHeader::Data, 0, vec![3u8; 120]);
+        let mut corrupted = good.to_cobs_bytes();
+        corrupted[20] ^= 0x01;
+        let mut bytes = vec![0x42, 0x13, COBS_DELIMITER];
+        bytes.extend(&corrupted);
+        bytes.extend(good.to_cobs_bytes());
+        bytes.extend(FramedPacket::new(PacketHeader::Command, 0x0001, vec![]).to_cobs_bytes());
+        for chunk_len in [1, 5, 64, bytes.len()] {
+            let mut scanner = FrameScanner::new();
+            scanner.set_wire_format(WireFormat::Cobs);
+            let mut frames = Vec::new();
+            for chunk in bytes.chunks(chunk_len) {
+                scanner.push(chunk);
+                frames.extend(drain(&mut scanner));
+            }
+            assert_eq!(frames.len(), 2, "{chunk_len} byte chunks");
+            assert_eq!(frames[0].1, good);
+            assert_eq!(frames[1].0, PacketHeader::Command);
+            assert_eq!(scanner.crc_failures(), 1);
+            assert_eq!(scanner.buffered_len(), 0);
+        }
+    }
+
+    #[test]
+    fn test_frame_scanner_keeps_its_buffer_bounded() {
+        let mut scanner = FrameScanner::for_headers(&[PacketHeader::Data]);
+        scanner.set_max_buffer_len(64);
+        // The start of a frame that would be too long for the buffer, then one that fits.
+        let long = frame(PacketHeader::Data, 0, &[0u8; 120]);
+        let short = frame(PacketHeader::Data, 0, &[5u8; 16]);
+        for _ in 0..10 {
+            scanner.push(&long[..40]);
+            assert!(scanner.buffered_len() <= 64);
+        }
+        scanner.push(&short);
+        let frames = drain(&mut scanner);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].1.payload(), &[5u8; 16]);
+
+        // Frames with other headers are noise to this scanner.
+        scanner.push(&frame(PacketHeader::Response, 0, &[1]));
+        assert!(scanner.next_frame().is_none());
+    }
 }
@@ -1,11 +1,43 @@
-#![cfg_attr(not(feature = "default"), no_std)]
+#![cfg_attr(not(feature = "std"), no_std)]
+#[cfg(not(feature = "no-alloc"))]
 extern crate alloc;
 
+#[cfg(all(feature = "no-alloc", any(feature = "std", feature = "serde")))]
+compile_error!(
+    "the `no-alloc` feature builds firm_core without `alloc`, so it can't be combined with \
+     `std`, `serde` or the features that need them; build with `--no-default-features`"
+);
+
+pub mod altitude;
+#[cfg(feature = "std")]
+pub mod analysis;
+#[cfg(feature = "std")]
 pub mod calibration;
+#[cfg(not(feature = "no-alloc"))]
 pub mod client_packets;
+#[cfg(not(feature = "no-alloc"))]
+pub mod cobs;
 pub mod constants;
+pub mod corpus;
+#[cfg(not(feature = "no-alloc"))]
+pub mod csv;
 pub mod data_parser;
+#[cfg(not(feature = "no-alloc"))]
+pub mod error_codes;
 pub mod firm_packets;
+pub mod fixed;
 pub mod framed_packet;
+#[cfg(feature = "std")]
+pub mod log_decoding;
+#[cfg(not(feature = "no-alloc"))]
 pub mod log_parsing;
+#[cfg(feature = "std")]
+pub mod log_tools;
+#[cfg(not(feature = "no-alloc"))]
+pub mod non_finite;
+pub mod packet_sink;
+pub mod packet_view;
+pub mod parser_storage;
 pub mod utils;
+#[cfg(not(feature = "no-alloc"))]
+pub mod validation;
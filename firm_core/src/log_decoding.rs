@@ -0,0 +1,267 @@
+//! Converts the raw sensor readings stored in a FIRM log file into `FIRMData`, so a log can be
+//! played back without a device.
+//!
+//! Readings are decoded with the sensors' datasheet formats and default scales:
+//!
+//! - BMP581: `[temperature: i24 LE][pressure: u24 LE]`, 1/65536 °C and 1/64 Pa per count.
+//! - ICM45686: `[accel xyz: i16 LE][gyro xyz: i16 LE][3 bytes of extra low bits]`, forming
+//!   20-bit readings at 16384 counts per g and 131.072 counts per °/s. Byte `i` of the
+//!   extension holds the 4 low bits of accel axis `i` in its high nibble and gyro axis `i` in
+//!   its low nibble.
+//! - MMC5983MA: `[x, y, z high 16 bits: u16 BE][2 low bits of x, y, z]`, forming 18-bit
+//!   readings centred on 131072 at 16384 counts per gauss.
+//!
+//! Only the raw sensor fields are in the log; the estimated state fields are left at zero.
+use crate::client_packets::FIRMLogPacket;
+use crate::constants::log_parsing::*;
+use crate::firm_packets::FIRMData;
+use crate::framed_packet::Framed;
+
+const BMP581_TEMPERATURE_COUNTS_PER_CELSIUS: f32 = 65536.0;
+const BMP581_PRESSURE_COUNTS_PER_PASCAL: f32 = 64.0;
+const ICM45686_ACCEL_COUNTS_PER_G: f32 = 16384.0;
+const ICM45686_GYRO_COUNTS_PER_DEG_PER_S: f32 = 131.072;
+const MMC5983MA_NULL_COUNTS: i32 = 1 << 17;
+const MMC5983MA_COUNTS_PER_MICROTESLA: f32 = 16384.0 / 100.0;
+
+/// Decodes a BMP581 reading into `(temperature_celsius, pressure_pascals)`.
+pub fn decode_barometer(raw: &[u8; BMP581_SIZE]) -> (f32, f32) {
+    let temperature = i32::from_le_bytes([0, raw[0], raw[1], raw[2]]) >> 8;
+    let pressure = u32::from_le_bytes([raw[3], raw[4], raw[5], 0]);
+    (
+        temperature as f32 / BMP581_TEMPERATURE_COUNTS_PER_CELSIUS,
+        pressure as f32 / BMP581_PRESSURE_COUNTS_PER_PASCAL,
+    )
+}
+
+/// Encodes a BMP581 reading, the inverse of `decode_barometer`. Used to build synthetic logs.
+pub fn encode_barometer(temperature_celsius: f32, pressure_pascals: f32) -> [u8; BMP581_SIZE] {
+    let temperature = (temperature_celsius * BMP581_TEMPERATURE_COUNTS_PER_CELSIUS).round() as i32;
+    let pressure = (pressure_pascals * BMP581_PRESSURE_COUNTS_PER_PASCAL).round() as u32;
+    let t = temperature.to_le_bytes();
+    let p = pressure.to_le_bytes();
+    [t[0], t[1], t[2], p[0], p[1], p[2]]
+}
+
+/// Decodes an ICM45686 reading into `(acceleration_gs, angular_rate_deg_per_s)`.
+pub fn decode_imu(raw: &[u8; ICM45686_SIZE]) -> ([f32; 3], [f32; 3]) {
+    let mut accel = [0.0; 3];
+    let mut gyro = [0.0; 3];
+    for axis in 0..3 {
+        let extension = raw[12 + axis];
+        let accel_high = i16::from_le_bytes([raw[axis * 2], raw[axis * 2 + 1]]) as i32;
+        let gyro_high = i16::from_le_bytes([raw[6 + axis * 2], raw[7 + axis * 2]]) as i32;
+        let accel_counts = (accel_high << 4) | (extension >> 4) as i32;
+        let gyro_counts = (gyro_high << 4) | (extension & 0x0F) as i32;
+        accel[axis] = accel_counts as f32 / ICM45686_ACCEL_COUNTS_PER_G;
+        gyro[axis] = gyro_counts as f32 / ICM45686_GYRO_COUNTS_PER_DEG_PER_S;
+    }
+    (accel, gyro)
+}
+
+/// Encodes an ICM45686 reading, the inverse of `decode_imu`. Used to build synthetic logs.
+pub fn encode_imu(
+    acceleration_gs: [f32; 3],
+    angular_rate_deg_per_s: [f32; 3],
+) -> [u8; ICM45686_SIZE] {
+    let mut raw = [0u8; ICM45686_SIZE];
+    for axis in 0..3 {
+        let accel = (acceleration_gs[axis] * ICM45686_ACCEL_COUNTS_PER_G).round() as i32;
+        let gyro =
+            (angular_rate_deg_per_s[axis] * ICM45686_GYRO_COUNTS_PER_DEG_PER_S).round() as i32;
+        raw[axis * 2..axis * 2 + 2].copy_from_slice(&((accel >> 4) as i16).to_le_bytes());
+        raw[6 + axis * 2..8 + axis * 2].copy_from_slice(&((gyro >> 4) as i16).to_le_bytes());
+        raw[12 + axis] = (((accel & 0x0F) << 4) | (gyro & 0x0F)) as u8;
+    }
+    raw
+}
+
+/// Decodes an MMC5983MA reading into magnetic field in microteslas.
+pub fn decode_magnetometer(raw: &[u8; MMC5983MA_SIZE]) -> [f32; 3] {
+    let mut field = [0.0; 3];
+    for (axis, value) in field.iter_mut().enumerate() {
+        let high = u16::from_be_bytes([raw[axis * 2], raw[axis * 2 + 1]]) as i32;
+        let low = ((raw[6] >> (6 - 2 * axis)) & 0b11) as i32;
+        let counts = (high << 2) | low;
+        *value = (counts - MMC5983MA_NULL_COUNTS) as f32 / MMC5983MA_COUNTS_PER_MICROTESLA;
+    }
+    field
+}
+
+/// Encodes an MMC5983MA reading, the inverse of `decode_magnetometer`. Used to build synthetic
+/// logs.
+pub fn encode_magnetometer(field_microteslas: [f32; 3]) -> [u8; MMC5983MA_SIZE] {
+    let mut raw = [0u8; MMC5983MA_SIZE];
+    for (axis, value) in field_microteslas.into_iter().enumerate() {
+        let counts = ((value * MMC5983MA_COUNTS_PER_MICROTESLA).round() as i32
+            + MMC5983MA_NULL_COUNTS)
+            .clamp(0, (1 << 18) - 1);
+        raw[axis * 2..axis * 2 + 2].copy_from_slice(&((counts >> 2) as u16).to_be_bytes());
+        raw[6] |= ((counts & 0b11) as u8) << (6 - 2 * axis);
+    }
+    raw
+}
+
+/// Turns a stream of log packets from `LogParser` into `FIRMData` snapshots.
+///
+/// Each sensor packet updates that sensor's fields and yields the latest value of every field,
+/// so a sensor's reading is held until its next packet.
+pub struct LogDecoder {
+    latest: FIRMData,
+    /// Log clock ticks since the first packet, unwrapped past the 32-bit counter's rollover.
+    ticks: Option<u64>,
+    last_clock_count: u32,
+}
+
+impl Default for LogDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LogDecoder {
+    /// Creates a decoder with every field at zero.
+    pub fn new() -> Self {
+        Self {
+            latest: FIRMData::from_fields(0.0, [0.0; FIRMData::NUM_F32_FIELDS]),
+            ticks: None,
+            last_clock_count: 0,
+        }
+    }
+
+    /// Applies one log packet.
+    ///
+    /// # Arguments
+    ///
+    /// - `packet` (`&FIRMLogPacket`) - A packet from `LogParser`.
+    ///
+    /// # Returns
+    ///
+    /// - `Option<FIRMData>` - The latest data after this packet, or `None` for header packets
+    ///   and payloads too short for their sensor.
+    pub fn decode(&mut self, packet: &FIRMLogPacket) -> Option<FIRMData> {
+        let payload = packet.payload();
+        let (clock, raw) = payload.split_first_chunk::<LOG_PACKET_TIMESTAMP_SIZE>()?;
+        let data = &mut self.latest;
+        match packet.packet_type() {
+            FIRMLogPacketType::HeaderPacket => return None,
+            FIRMLogPacketType::BarometerPacket => {
+                let (temperature, pressure) = decode_barometer(raw.first_chunk()?);
+                data.temperature_celsius = temperature;
+                data.pressure_pascals = pressure;
+            }
+            FIRMLogPacketType::IMUPacket => {
+                let (accel, gyro) = decode_imu(raw.first_chunk()?);
+                [
+                    data.raw_acceleration_x_gs,
+                    data.raw_acceleration_y_gs,
+                    data.raw_acceleration_z_gs,
+                ] = accel;
+                [
+                    data.raw_angular_rate_x_deg_per_s,
+                    data.raw_angular_rate_y_deg_per_s,
+                    data.raw_angular_rate_z_deg_per_s,
+                ] = gyro;
+            }
+            FIRMLogPacketType::MagnetometerPacket => {
+                [
+                    data.magnetic_field_x_microteslas,
+                    data.magnetic_field_y_microteslas,
+                    data.magnetic_field_z_microteslas,
+                ] = decode_magnetometer(raw.first_chunk()?);
+            }
+        }
+
+        let clock_count = u32::from_le_bytes(*clock);
+        let ticks = match self.ticks {
+            None => clock_count as u64,
+            Some(ticks) => ticks + clock_count.wrapping_sub(self.last_clock_count) as u64,
+        };
+        self.ticks = Some(ticks);
+        self.last_clock_count = clock_count;
+        data.timestamp_seconds = ticks as f64 / LOG_CLOCK_HZ;
+
+        Some(data.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_packet(packet_type: FIRMLogPacketType, clock_count: u32, raw: &[u8]) -> FIRMLogPacket {
+        let mut payload = clock_count.to_le_bytes().to_vec();
+        payload.extend_from_slice(raw);
+        FIRMLogPacket::new(packet_type, payload)
+    }
+
+    #[test]
+    fn test_sensor_readings_round_trip() {
+        let (temperature, pressure) = decode_barometer(&encode_barometer(-12.5, 101_325.0));
+        assert_eq!(temperature, -12.5);
+        assert_eq!(pressure, 101_325.0);
+
+        let (accel, gyro) = decode_imu(&encode_imu([0.5, -1.0, 15.25], [-250.0, 0.0, 1000.5]));
+        assert_eq!(accel, [0.5, -1.0, 15.25]);
+        for (decoded, expected) in gyro.iter().zip([-250.0, 0.0, 1000.5]) {
+            assert!((decoded - expected).abs() < 0.01, "{decoded} != {expected}");
+        }
+
+        let field = decode_magnetometer(&encode_magnetometer([50.0, -25.0, 0.0]));
+        assert_eq!(field, [50.0, -25.0, 0.0]);
+    }
+
+    #[test]
+    fn test_decoder_holds_latest_reading_of_each_sensor() {
+        let mut decoder = LogDecoder::new();
+        let baro = log_packet(
+            FIRMLogPacketType::BarometerPacket,
+            168_000_000,
+            &encode_barometer(20.0, 100_000.0),
+        );
+        let mag = log_packet(
+            FIRMLogPacketType::MagnetometerPacket,
+            168_000_000 + 16_800_000,
+            &encode_magnetometer([12.5, 25.0, 50.0]),
+        );
+
+        let first = decoder.decode(&baro).unwrap();
+        assert_eq!(first.timestamp_seconds, 1.0);
+        assert_eq!(first.pressure_pascals, 100_000.0);
+        assert_eq!(first.magnetic_field_x_microteslas, 0.0);
+
+        let second = decoder.decode(&mag).unwrap();
+        assert!((second.timestamp_seconds - 1.1).abs() < 1e-12);
+        assert_eq!(second.pressure_pascals, 100_000.0);
+        assert_eq!(second.magnetic_field_z_microteslas, 50.0);
+    }
+
+    #[test]
+    fn test_decoder_unwraps_clock_rollover_and_skips_headers() {
+        let mut decoder = LogDecoder::new();
+        let raw = encode_magnetometer([0.0; 3]);
+        let header =
+            FIRMLogPacket::new(FIRMLogPacketType::HeaderPacket, vec![0; HEADER_TOTAL_SIZE]);
+        assert!(decoder.decode(&header).is_none());
+
+        decoder.decode(&log_packet(
+            FIRMLogPacketType::MagnetometerPacket,
+            u32::MAX,
+            &raw,
+        ));
+        let after = decoder
+            .decode(&log_packet(
+                FIRMLogPacketType::MagnetometerPacket,
+                167,
+                &raw,
+            ))
+            .unwrap();
+        assert_eq!(
+            after.timestamp_seconds,
+            (u32::MAX as u64 + 168) as f64 / LOG_CLOCK_HZ
+        );
+
+        let short = FIRMLogPacket::new(FIRMLogPacketType::IMUPacket, vec![0; 6]);
+        assert!(decoder.decode(&short).is_none());
+    }
+}
@@ -4,10 +4,11 @@ use alloc::vec::Vec;
 use crate::client_packets::FIRMLogPacket;
 use crate::constants::log_parsing::FIRMLogPacketType;
 use crate::constants::log_parsing::*;
+use crate::parser_storage::{ByteBuffer, RollingBuffer};
 
 pub struct LogParser {
     /// Rolling buffer of unprocessed bytes.
-    bytes: Vec<u8>,
+    bytes: RollingBuffer,
     /// Queue of parsed log packets and their inter-packet delay.
     parsed_packets: VecDeque<(FIRMLogPacket, f64)>,
 
@@ -34,7 +35,7 @@ impl LogParser {
     /// Creates a new empty `LogParser`.
     pub fn new() -> Self {
         Self {
-            bytes: Vec::new(),
+            bytes: RollingBuffer::default(),
             parsed_packets: VecDeque::new(),
             header_parsed: false,
             last_clock_count: None,
@@ -48,7 +49,7 @@ impl LogParser {
         assert_eq!(header_bytes.len(), HEADER_TOTAL_SIZE);
 
         // Reset streaming state for a fresh playback run.
-        self.bytes.clear();
+        self.bytes = RollingBuffer::default();
         self.parsed_packets.clear();
         self.last_clock_count = None;
         self.num_repeat_whitespace = 0;
@@ -67,21 +68,21 @@ impl LogParser {
             return;
         }
 
-        self.bytes.extend_from_slice(chunk);
+        self.bytes.extend_from(chunk);
 
         // Parse log packets
         let mut position = 0usize;
         while position < self.bytes.len() {
             let log_packet_start = position;
 
-            let id = self.bytes[position];
+            let id = self.bytes.as_slice()[position];
             if id == 0 {
                 // whitespace padding between log packets
                 self.num_repeat_whitespace += 1;
                 // End-of-data if whitespace repeats enough times, matching the Python decoder.
                 if self.num_repeat_whitespace > LOG_FILE_EOF_PADDING_LENGTH {
                     // Treat as EOF padding; drop buffered bytes.
-                    self.bytes.clear();
+                    self.bytes = RollingBuffer::default();
                     self.eof_reached = true;
                     break;
                 }
@@ -97,7 +98,7 @@ impl LogParser {
             }
 
             position += 1;
-            let timestamp = &self.bytes[position..position + LOG_PACKET_TIMESTAMP_SIZE];
+            let timestamp = &self.bytes.as_slice()[position..position + LOG_PACKET_TIMESTAMP_SIZE];
             position += LOG_PACKET_TIMESTAMP_SIZE;
             let clock_count = u32::from_le_bytes(
                 timestamp
@@ -111,12 +112,10 @@ impl LogParser {
                 None => 0.0,
                 Some(prev) => {
                     let delta = clock_count.wrapping_sub(prev);
-                    (delta as f64) / 168e6
+                    (delta as f64) / LOG_CLOCK_HZ
                 }
             };
 
-            self.last_clock_count = Some(clock_count);
-
             let (packet_type, size) = match id {
                 BMP581_ID => (FIRMLogPacketType::BarometerPacket, BMP581_SIZE),
                 ICM45686_ID => (FIRMLogPacketType::IMUPacket, ICM45686_SIZE),
@@ -134,7 +133,11 @@ impl LogParser {
                 break;
             }
 
-            let raw = &self.bytes[position..position + size];
+            // Only a complete, known packet moves the clock on, so neither garbage nor a packet
+            // split across chunks throws off the next delay.
+            self.last_clock_count = Some(clock_count);
+
+            let raw = &self.bytes.as_slice()[position..position + size];
             position += size;
 
             let mut payload = Vec::with_capacity(LOG_PACKET_TIMESTAMP_SIZE + size);
@@ -144,12 +147,7 @@ impl LogParser {
             self.parsed_packets.push_back((pkt, delay_seconds));
         }
 
-        if position >= self.bytes.len() {
-            self.bytes.clear();
-            return;
-        }
-
-        self.bytes = self.bytes[position..].to_vec();
+        self.bytes.consume(position.min(self.bytes.len()));
     }
 
     /// Pops the next parsed log packet and returns it with its delay since the last one.
@@ -174,9 +172,7 @@ mod tests {
     use crate::framed_packet::Framed;
 
     fn make_header() -> Vec<u8> {
-        let mut header = Vec::new();
-        header.resize(HEADER_TOTAL_SIZE, 0u8);
-        header
+        vec![0u8; HEADER_TOTAL_SIZE]
     }
 
     fn make_log_packet_bytes(id: u8, clock_count: u32, raw_len: usize) -> Vec<u8> {
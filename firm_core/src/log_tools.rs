@@ -0,0 +1,453 @@
+//! Batch processing of a directory of FIRM log files, e.g. everything pulled off the SD cards
+//! after a launch day.
+//!
+//! `process_directory` finds the logs by their `FIRM LOG` header rather than by extension,
+//! decodes each one with `LogParser`/`LogDecoder`, and hands the packets and a per-file summary
+//! to a `LogSink`. Files are processed on a pool of threads. A file that can't be read or
+//! decoded is recorded as failed in the summary and the rest of the batch carries on.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::constants::log_parsing::{HEADER_TOTAL_SIZE, LOG_FILE_MAGIC};
+use crate::csv::{csv_header, write_csv_row};
+use crate::firm_packets::FIRMData;
+use crate::log_decoding::LogDecoder;
+use crate::log_parsing::LogParser;
+use crate::non_finite::NonFinitePolicy;
+
+/// Options for `process_directory`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProcessOptions {
+    /// Number of files processed at once. 0 uses the number of available CPUs.
+    pub threads: usize,
+}
+
+/// What was found in one log file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogFileSummary {
+    pub path: PathBuf,
+    /// Number of decoded packets.
+    pub packets: usize,
+    pub start_seconds: f64,
+    pub duration_seconds: f64,
+    /// Lowest barometer pressure in the log, i.e. the highest point of the flight.
+    pub min_pressure_pascals: f32,
+    /// Largest magnitude of the raw acceleration vector.
+    pub max_acceleration_gs: f32,
+    /// Why the file couldn't be processed, if it couldn't. The other fields are then zero.
+    pub error: Option<String>,
+}
+
+impl LogFileSummary {
+    fn failed(path: PathBuf, error: String) -> Self {
+        Self {
+            path,
+            packets: 0,
+            start_seconds: 0.0,
+            duration_seconds: 0.0,
+            min_pressure_pascals: 0.0,
+            max_acceleration_gs: 0.0,
+            error: Some(error),
+        }
+    }
+
+    fn from_packets(path: PathBuf, packets: &[FIRMData]) -> Self {
+        let start_seconds = packets.first().map_or(0.0, |p| p.timestamp_seconds);
+        let end_seconds = packets.last().map_or(0.0, |p| p.timestamp_seconds);
+        let min_pressure_pascals = packets
+            .iter()
+            .map(|p| p.pressure_pascals)
+            .filter(|&pressure| pressure > 0.0)
+            .fold(f32::NAN, f32::min);
+        let max_acceleration_gs = packets
+            .iter()
+            .map(|p| {
+                (p.raw_acceleration_x_gs.powi(2)
+                    + p.raw_acceleration_y_gs.powi(2)
+                    + p.raw_acceleration_z_gs.powi(2))
+                .sqrt()
+            })
+            .fold(0.0, f32::max);
+        Self {
+            path,
+            packets: packets.len(),
+            start_seconds,
+            duration_seconds: end_seconds - start_seconds,
+            min_pressure_pascals,
+            max_acceleration_gs,
+            error: None,
+        }
+    }
+}
+
+/// The result of a `process_directory` run, one entry per log file in path order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BatchSummary {
+    pub files: Vec<LogFileSummary>,
+}
+
+impl BatchSummary {
+    /// Returns the files that failed.
+    pub fn failures(&self) -> impl Iterator<Item = &LogFileSummary> {
+        self.files.iter().filter(|file| file.error.is_some())
+    }
+
+    /// Returns the summary as a CSV table with a row per file. The `error` cell is empty for
+    /// files that were processed and holds the reason for those that weren't.
+    ///
+    /// # Arguments
+    ///
+    /// - `policy` (`&NonFinitePolicy`) - How to write NaN and infinity, e.g. the minimum
+    ///   pressure of a log without barometer readings.
+    pub fn to_csv(&self, policy: &NonFinitePolicy) -> String {
+        let mut out = String::from(
+            "file,packets,start_seconds,duration_seconds,min_pressure_pascals,max_acceleration_gs,error\n",
+        );
+        for file in &self.files {
+            let name = file.path.file_name().map_or_else(
+                || file.path.display().to_string(),
+                |name| name.to_string_lossy().into_owned(),
+            );
+            let error = file.error.as_deref().unwrap_or("");
+            out.push_str(&format!("{},{},", csv_cell(&name), file.packets));
+            policy.write_cell(&mut out, file.start_seconds);
+            out.push(',');
+            policy.write_cell(&mut out, file.duration_seconds);
+            out.push(',');
+            policy.write_f32_cell(&mut out, file.min_pressure_pascals);
+            out.push(',');
+            policy.write_f32_cell(&mut out, file.max_acceleration_gs);
+            out.push(',');
+            out.push_str(&csv_cell(error));
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Quotes a cell if it contains a comma, quote or newline.
+fn csv_cell(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Where `process_directory` writes its results. Called from several threads at once.
+pub trait LogSink: Sync {
+    /// Writes the decoded packets of one log file.
+    fn write_log(&self, source: &Path, packets: &[FIRMData]) -> Result<(), String>;
+
+    /// Writes the summary of the whole batch, once every file has been processed.
+    fn write_summary(&self, summary: &BatchSummary) -> Result<(), String>;
+}
+
+/// Writes `<log name>.csv` per log file and `summary.csv` into a directory.
+pub struct CsvDirectorySink {
+    output_dir: PathBuf,
+}
+
+impl CsvDirectorySink {
+    /// The name of the combined summary table in the output directory.
+    pub const SUMMARY_FILE_NAME: &'static str = "summary.csv";
+
+    /// Creates a sink writing into `output_dir`, creating it if needed.
+    pub fn new(output_dir: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let output_dir = output_dir.into();
+        fs::create_dir_all(&output_dir)?;
+        Ok(Self { output_dir })
+    }
+
+    /// Returns the CSV path `write_log` uses for `source`.
+    pub fn csv_path(&self, source: &Path) -> PathBuf {
+        let stem = source.file_stem().unwrap_or(source.as_os_str());
+        self.output_dir.join(stem).with_extension("csv")
+    }
+}
+
+impl LogSink for CsvDirectorySink {
+    fn write_log(&self, source: &Path, packets: &[FIRMData]) -> Result<(), String> {
+        let mut out = csv_header();
+        out.push('\n');
+        let policy = NonFinitePolicy::default();
+        for packet in packets {
+            write_csv_row(&mut out, packet, &policy);
+        }
+        let path = self.csv_path(source);
+        fs::write(&path, out).map_err(|e| format!("failed to write {}: {e}", path.display()))
+    }
+
+    fn write_summary(&self, summary: &BatchSummary) -> Result<(), String> {
+        let path = self.output_dir.join(Self::SUMMARY_FILE_NAME);
+        fs::write(&path, summary.to_csv(&NonFinitePolicy::default()))
+            .map_err(|e| format!("failed to write {}: {e}", path.display()))
+    }
+}
+
+/// Returns the FIRM log files directly inside `dir`, in path order.
+///
+/// A file is a log if it starts with `LOG_FILE_MAGIC`, whatever its extension. Subdirectories
+/// aren't searched.
+pub fn find_log_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut logs = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+        let path = entry.path();
+        if has_log_magic(&path)? {
+            logs.push(path);
+        }
+    }
+    logs.sort();
+    Ok(logs)
+}
+
+fn has_log_magic(path: &Path) -> std::io::Result<bool> {
+    use std::io::Read;
+
+    let mut magic = [0u8; LOG_FILE_MAGIC.len()];
+    let mut file = fs::File::open(path)?;
+    match file.read_exact(&mut magic) {
+        Ok(()) => Ok(magic == LOG_FILE_MAGIC),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Decodes every sensor packet in a log file's contents, header included.
+///
+/// # Arguments
+///
+/// - `bytes` (`&[u8]`) - The whole log file.
+///
+/// # Returns
+///
+/// - `Result<Vec<FIRMData>, String>` - The decoded packets, or why the log is unusable: a
+///   truncated header or no sensor packets at all.
+pub fn decode_log(bytes: &[u8]) -> Result<Vec<FIRMData>, String> {
+    if bytes.len() < HEADER_TOTAL_SIZE {
+        return Err(format!(
+            "log header is truncated ({} of {HEADER_TOTAL_SIZE} bytes)",
+            bytes.len()
+        ));
+    }
+    let (header, body) = bytes.split_at(HEADER_TOTAL_SIZE);
+    let mut parser = LogParser::new();
+    parser.read_header(header);
+    parser.parse_bytes(body);
+
+    let mut decoder = LogDecoder::new();
+    let mut packets = Vec::new();
+    while let Some(packet) = parser.get_packet() {
+        packets.extend(decoder.decode(&packet));
+    }
+    if packets.is_empty() {
+        return Err("log contains no sensor packets".to_string());
+    }
+    Ok(packets)
+}
+
+fn process_file(path: &Path, sink: &dyn LogSink) -> LogFileSummary {
+    let packets = match fs::read(path) {
+        Ok(bytes) => decode_log(&bytes),
+        Err(e) => Err(format!("failed to read file: {e}")),
+    };
+    let packets = match packets {
+        Ok(packets) => packets,
+        Err(error) => return LogFileSummary::failed(path.to_path_buf(), error),
+    };
+    let mut summary = LogFileSummary::from_packets(path.to_path_buf(), &packets);
+    if let Err(error) = sink.write_log(path, &packets) {
+        summary.error = Some(error);
+    }
+    summary
+}
+
+/// Decodes every FIRM log in `dir` and writes the results to `sink`.
+///
+/// A failure in one file (unreadable, corrupt, or rejected by the sink) is noted in that file's
+/// summary entry and doesn't stop the others.
+///
+/// # Arguments
+///
+/// - `dir` (`&Path`) - The directory to search, see `find_log_files`.
+/// - `options` (`ProcessOptions`) - How many files to process in parallel.
+/// - `sink` (`&dyn LogSink`) - Receives each file's packets and then the batch summary.
+///
+/// # Returns
+///
+/// - `Result<BatchSummary, String>` - The summary, or an error if `dir` can't be listed or the
+///   summary can't be written.
+pub fn process_directory(
+    dir: &Path,
+    options: ProcessOptions,
+    sink: &dyn LogSink,
+) -> Result<BatchSummary, String> {
+    let logs = find_log_files(dir).map_err(|e| format!("failed to list {}: {e}", dir.display()))?;
+    let threads = match options.threads {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    }
+    .min(logs.len())
+    .max(1);
+
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(vec![None; logs.len()]);
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(path) = logs.get(index) else {
+                        break;
+                    };
+                    let summary = process_file(path, sink);
+                    results.lock().unwrap()[index] = Some(summary);
+                }
+            });
+        }
+    });
+
+    let summary = BatchSummary {
+        files: results
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .flatten()
+            .collect(),
+    };
+    sink.write_summary(&summary)?;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::log_parsing::{
+        BMP581_ID, ICM45686_ID, LOG_CLOCK_HZ, LOG_FILE_EOF_PADDING_LENGTH,
+    };
+    use crate::log_decoding::{encode_barometer, encode_imu};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("firm_core_{}_{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Builds a log with a barometer and IMU packet every 10 ms, bottoming out at
+    /// `min_pressure` halfway through.
+    fn make_log(seconds: f64, min_pressure: f32) -> Vec<u8> {
+        let mut log = vec![0u8; HEADER_TOTAL_SIZE];
+        log[..13].copy_from_slice(b"FIRM LOG v1.0");
+        let count = (seconds * 100.0) as usize;
+        for i in 0..count {
+            let t = i as f64 / 100.0;
+            let clock_count = ((t * LOG_CLOCK_HZ) as u64 as u32).to_le_bytes();
+            let progress = (i as f32 / (count - 1) as f32 * 2.0 - 1.0).abs();
+            let pressure = min_pressure + (101_325.0 - min_pressure) * progress;
+            log.push(BMP581_ID);
+            log.extend_from_slice(&clock_count);
+            log.extend_from_slice(&encode_barometer(20.0, pressure));
+            log.push(ICM45686_ID);
+            log.extend_from_slice(&clock_count);
+            log.extend_from_slice(&encode_imu([0.0, 0.0, 1.0], [0.0; 3]));
+        }
+        log.extend(std::iter::repeat_n(0u8, LOG_FILE_EOF_PADDING_LENGTH + 1));
+        log
+    }
+
+    #[test]
+    fn test_process_directory_survives_a_corrupt_log() {
+        let input = temp_dir("batch_input");
+        fs::write(input.join("flight_a.bin"), make_log(2.0, 90_000.0)).unwrap();
+        // No extension: logs are found by their header.
+        fs::write(input.join("flight_b"), make_log(1.0, 95_000.0)).unwrap();
+        let mut corrupt = make_log(1.0, 95_000.0);
+        corrupt.truncate(HEADER_TOTAL_SIZE / 2);
+        fs::write(input.join("flight_c.bin"), corrupt).unwrap();
+        fs::write(input.join("notes.txt"), "not a log").unwrap();
+
+        let output = temp_dir("batch_output");
+        let sink = CsvDirectorySink::new(&output).unwrap();
+        let options = ProcessOptions { threads: 2 };
+        let summary = process_directory(&input, options, &sink).unwrap();
+
+        let names: Vec<_> = summary
+            .files
+            .iter()
+            .map(|file| file.path.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(names, ["flight_a.bin", "flight_b", "flight_c.bin"]);
+
+        let a = &summary.files[0];
+        assert_eq!(a.error, None);
+        assert_eq!(a.packets, 400);
+        assert!((a.duration_seconds - 1.99).abs() < 1e-6);
+        assert!((a.min_pressure_pascals - 90_000.0).abs() < 100.0);
+        assert!((a.max_acceleration_gs - 1.0).abs() < 0.01);
+        assert_eq!(summary.files[1].packets, 200);
+
+        let failures: Vec<_> = summary.failures().collect();
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].error.as_ref().unwrap().contains("truncated"));
+
+        let csv = fs::read_to_string(output.join("flight_a.csv")).unwrap();
+        assert_eq!(csv.lines().next().unwrap(), csv_header());
+        assert_eq!(csv.lines().count(), 401);
+        assert!(output.join("flight_b.csv").exists());
+        assert!(!output.join("flight_c.csv").exists());
+
+        let table = fs::read_to_string(output.join(CsvDirectorySink::SUMMARY_FILE_NAME)).unwrap();
+        let rows: Vec<_> = table.lines().collect();
+        assert_eq!(rows.len(), 4);
+        assert!(rows[1].starts_with("flight_a.bin,400,"));
+        assert!(rows[3].starts_with("flight_c.bin,0,"));
+        assert!(rows[3].contains("truncated"));
+        assert_eq!(
+            rows,
+            summary
+                .to_csv(&NonFinitePolicy::default())
+                .lines()
+                .collect::<Vec<_>>()
+        );
+
+        fs::remove_dir_all(&input).unwrap();
+        fs::remove_dir_all(&output).unwrap();
+    }
+
+    #[test]
+    fn test_summary_writes_non_finite_values_through_the_policy() {
+        let mut file = LogFileSummary::from_packets(PathBuf::from("flight.bin"), &[]);
+        file.max_acceleration_gs = f32::INFINITY;
+        file.start_seconds = f64::NEG_INFINITY;
+        let summary = BatchSummary { files: vec![file] };
+        let row =
+            |policy: &NonFinitePolicy| summary.to_csv(policy).lines().nth(1).unwrap().to_string();
+        // Without barometer readings the minimum pressure is NaN, an empty cell.
+        assert_eq!(
+            row(&NonFinitePolicy::default()),
+            "flight.bin,0,-inf,0,,inf,"
+        );
+        assert_eq!(
+            row(&NonFinitePolicy::new("1e999")),
+            "flight.bin,0,-1e999,0,,1e999,"
+        );
+    }
+
+    #[test]
+    fn test_decode_log_rejects_logs_without_packets() {
+        let mut log = vec![0u8; HEADER_TOTAL_SIZE];
+        log[..8].copy_from_slice(LOG_FILE_MAGIC);
+        log.extend_from_slice(b"garbage bytes");
+        assert_eq!(
+            decode_log(&log),
+            Err("log contains no sensor packets".to_string())
+        );
+    }
+}
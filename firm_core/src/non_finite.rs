@@ -0,0 +1,251 @@
+//! How exported data represents NaN and infinity, shared by the CSV and JSON writers, the
+//! calibrator and the Python bindings so every pipeline treats them the same way.
+//!
+//! Disabled sensors and corrupted floats produce both, and each consumer breaks on them
+//! differently: spreadsheets show "NaN" strings, JSON has no number for them, and a single NaN
+//! poisons a fit or a min/max. So:
+//!
+//! - CSV writes NaN as an empty cell and infinity as a sentinel (`inf` unless configured
+//!   otherwise), with a leading `-` when negative. Pandas reads the empty cells back as NaN,
+//!   which also makes plots break the line there instead of drawing through bad data.
+//! - JSON writes both as `null`.
+//! - Analysis skips a sample holding either and counts it, see `is_finite_sample`.
+use alloc::string::String;
+use core::fmt::{Display, Write};
+
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+
+/// Sentinel `NonFinitePolicy::default` writes for positive infinity.
+pub const DEFAULT_INF_SENTINEL: &str = "inf";
+
+/// How CSV cells represent NaN and infinity, see the module docs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "python", pyclass(get_all, set_all))]
+pub struct NonFinitePolicy {
+    /// Cell written for positive infinity; negative infinity gets a leading `-`.
+    pub inf_sentinel: String,
+}
+
+impl Default for NonFinitePolicy {
+    fn default() -> Self {
+        Self::new(DEFAULT_INF_SENTINEL)
+    }
+}
+
+impl NonFinitePolicy {
+    /// Creates a policy writing `inf_sentinel` for infinity.
+    ///
+    /// # Arguments
+    ///
+    /// - `inf_sentinel` (`impl Into<String>`) - The cell for positive infinity, e.g. `inf` or
+    ///   `1e999`. It shouldn't contain a comma or be empty, or the cell won't read back.
+    pub fn new(inf_sentinel: impl Into<String>) -> Self {
+        Self {
+            inf_sentinel: inf_sentinel.into(),
+        }
+    }
+
+    /// Appends the CSV cell for `value` to `out`, using shortest round-trip formatting for
+    /// finite values.
+    ///
+    /// # Arguments
+    ///
+    /// - `out` (`&mut String`) - The buffer to append to.
+    /// - `value` (`f64`) - The value to write.
+    pub fn write_cell(&self, out: &mut String, value: f64) {
+        self.write_number(out, value, value);
+    }
+
+    /// Appends the CSV cell for an `f32` value to `out`, see `write_cell`.
+    ///
+    /// # Arguments
+    ///
+    /// - `out` (`&mut String`) - The buffer to append to.
+    /// - `value` (`f32`) - The value to write.
+    pub fn write_f32_cell(&self, out: &mut String, value: f32) {
+        // Widening to f64 would print the f32's exact binary value (0.1f32 becomes
+        // 0.10000000149011612), so format it as an f32 to keep the shortest form.
+        self.write_number(out, value, value as f64);
+    }
+
+    /// Parses a CSV cell written under this policy. Empty cells and `nan` are NaN, and the
+    /// sentinel is accepted in any case, with an optional sign.
+    ///
+    /// # Arguments
+    ///
+    /// - `cell` (`&str`) - The cell, with surrounding whitespace and quotes already removed.
+    /// - `nan` (`T`) - The NaN of the field's type.
+    /// - `infinity` (`T`) - The positive infinity of the field's type.
+    ///
+    /// # Returns
+    ///
+    /// - `Option<T>` - The value, or `None` if the cell isn't a number or a sentinel.
+    pub fn parse_cell<T>(&self, cell: &str, nan: T, infinity: T) -> Option<T>
+    where
+        T: core::str::FromStr + core::ops::Neg<Output = T>,
+    {
+        if cell.is_empty() || cell.eq_ignore_ascii_case("nan") {
+            return Some(nan);
+        }
+        if let Some(magnitude) = cell.strip_prefix('-')
+            && magnitude.eq_ignore_ascii_case(&self.inf_sentinel)
+        {
+            return Some(-infinity);
+        }
+        if cell
+            .strip_prefix('+')
+            .unwrap_or(cell)
+            .eq_ignore_ascii_case(&self.inf_sentinel)
+        {
+            return Some(infinity);
+        }
+        cell.parse().ok()
+    }
+
+    fn write_number(&self, out: &mut String, value: impl Display, as_f64: f64) {
+        if as_f64.is_nan() {
+            return;
+        }
+        if as_f64.is_infinite() {
+            if as_f64 < 0.0 {
+                out.push('-');
+            }
+            out.push_str(&self.inf_sentinel);
+            return;
+        }
+        let _ = write!(out, "{value}");
+    }
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl NonFinitePolicy {
+    #[new]
+    #[pyo3(signature = (inf_sentinel=DEFAULT_INF_SENTINEL.to_string()))]
+    fn py_new(inf_sentinel: String) -> Self {
+        Self::new(inf_sentinel)
+    }
+
+    /// Returns the CSV cell for `value`, an empty string for NaN.
+    fn csv_cell(&self, value: f64) -> String {
+        let mut out = String::new();
+        self.write_cell(&mut out, value);
+        out
+    }
+}
+
+/// Appends `value` to `out` as a JSON number, or `null` if it isn't finite.
+///
+/// # Arguments
+///
+/// - `out` (`&mut String`) - The buffer to append to.
+/// - `value` (`f64`) - The value to write.
+pub fn write_json_number(out: &mut String, value: f64) {
+    write_json(out, value, value.is_finite());
+}
+
+/// Appends an `f32` value to `out` as a JSON number in its shortest form, or `null` if it
+/// isn't finite.
+///
+/// # Arguments
+///
+/// - `out` (`&mut String`) - The buffer to append to.
+/// - `value` (`f32`) - The value to write.
+pub fn write_json_f32(out: &mut String, value: f32) {
+    write_json(out, value, value.is_finite());
+}
+
+fn write_json(out: &mut String, value: impl Display, finite: bool) {
+    if finite {
+        let _ = write!(out, "{value}");
+    } else {
+        out.push_str("null");
+    }
+}
+
+/// Returns whether a sample can be used by analysis: analysis code skips, and counts, every
+/// sample for which this is false.
+///
+/// # Arguments
+///
+/// - `values` (`&[f32]`) - The sample's components, e.g. a magnetometer reading's x, y and z.
+pub fn is_finite_sample(values: &[f32]) -> bool {
+    values.iter().all(|value| value.is_finite())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(policy: &NonFinitePolicy, value: f32) -> String {
+        let mut out = String::new();
+        policy.write_f32_cell(&mut out, value);
+        out
+    }
+
+    #[test]
+    fn test_cells_follow_the_policy() {
+        let policy = NonFinitePolicy::default();
+        assert_eq!(cell(&policy, f32::NAN), "");
+        assert_eq!(cell(&policy, f32::INFINITY), "inf");
+        assert_eq!(cell(&policy, f32::NEG_INFINITY), "-inf");
+        assert_eq!(cell(&policy, 0.1), "0.1");
+
+        let policy = NonFinitePolicy::new("1e999");
+        assert_eq!(cell(&policy, f32::INFINITY), "1e999");
+        assert_eq!(cell(&policy, f32::NEG_INFINITY), "-1e999");
+        let mut out = String::new();
+        policy.write_cell(&mut out, f64::NAN);
+        assert_eq!(out, "");
+    }
+
+    #[test]
+    fn test_cells_parse_back() {
+        let policy = NonFinitePolicy::new("INF");
+        assert!(
+            policy
+                .parse_cell("", f32::NAN, f32::INFINITY)
+                .unwrap()
+                .is_nan()
+        );
+        assert!(
+            policy
+                .parse_cell("NaN", f32::NAN, f32::INFINITY)
+                .unwrap()
+                .is_nan()
+        );
+        assert_eq!(
+            policy.parse_cell("inf", f32::NAN, f32::INFINITY),
+            Some(f32::INFINITY)
+        );
+        assert_eq!(
+            policy.parse_cell("+Inf", f32::NAN, f32::INFINITY),
+            Some(f32::INFINITY)
+        );
+        assert_eq!(
+            policy.parse_cell("-inf", f64::NAN, f64::INFINITY),
+            Some(f64::NEG_INFINITY)
+        );
+        assert_eq!(policy.parse_cell("2.5", f32::NAN, f32::INFINITY), Some(2.5));
+        assert_eq!(policy.parse_cell("abc", f32::NAN, f32::INFINITY), None);
+    }
+
+    #[test]
+    fn test_json_numbers_become_null_when_not_finite() {
+        let mut out = String::new();
+        for value in [1.5, f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            write_json_number(&mut out, value);
+            out.push(',');
+        }
+        write_json_f32(&mut out, 0.1);
+        assert_eq!(out, "1.5,null,null,null,0.1");
+    }
+
+    #[test]
+    fn test_finite_samples() {
+        assert!(is_finite_sample(&[1.0, -2.0, 0.0]));
+        assert!(!is_finite_sample(&[1.0, f32::NAN, 0.0]));
+        assert!(!is_finite_sample(&[f32::NEG_INFINITY]));
+    }
+}
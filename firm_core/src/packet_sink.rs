@@ -0,0 +1,39 @@
+//! Where `SerialParser::parse_bytes_into` hands packets, instead of the parser's own queues.
+//!
+//! A sink sees each packet as soon as the parser has decoded it, so a consumer can route them
+//! straight to a static buffer, a channel or a callback without draining the queues after
+//! every call.
+use crate::data_parser::{ParseError, ResyncEvent, TimestampEvent};
+use crate::firm_packets::{FIRMBaroPacket, FIRMDataPacket, FIRMResponsePacket};
+use crate::framed_packet::SequenceGap;
+
+/// Receives what `SerialParser::parse_bytes_into` parses, in the order it arrived.
+pub trait PacketSink {
+    /// Called with each data packet, after the timestamp policy.
+    fn on_data(&mut self, packet: FIRMDataPacket);
+
+    /// Called with each command response.
+    fn on_response(&mut self, packet: FIRMResponsePacket);
+
+    /// Called with each barometer reading.
+    ///
+    /// # Returns
+    ///
+    /// - `Option<FIRMBaroPacket>` - The reading, to have the parser queue it for
+    ///   `get_baro_packet` as usual, or `None` if the sink took it. The default hands it back.
+    fn on_baro(&mut self, packet: FIRMBaroPacket) -> Option<FIRMBaroPacket> {
+        Some(packet)
+    }
+
+    /// Called with each frame the parser rejects, whether or not `collect_parse_errors` is on.
+    fn on_parse_error(&mut self, _error: &ParseError) {}
+
+    /// Called where the stream loses sync, whether or not there's a resync log.
+    fn on_lost_sync(&mut self, _event: &ResyncEvent) {}
+
+    /// Called with each event the timestamp policy reports, before the packet it's about.
+    fn on_timestamp_event(&mut self, _event: &TimestampEvent) {}
+
+    /// Called with each gap in the frames' sequence numbers, before the frame after it.
+    fn on_sequence_gap(&mut self, _gap: &SequenceGap) {}
+}
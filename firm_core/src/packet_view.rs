@@ -0,0 +1,196 @@
+//! Borrowed access to data packets still in `SerialParser`'s buffer, see
+//! `SerialParser::parse_bytes_with`.
+//!
+//! A `PacketView` reads each field straight out of the payload bytes when it's asked for,
+//! so a consumer that only wants a few fields never copies or decodes the rest.
+use crate::altitude::pressure_altitude_meters;
+use crate::firm_packets::{DataLayout, FIRMData};
+
+/// A data packet's payload, borrowed from the parser's buffer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PacketView<'a> {
+    payload: &'a [u8],
+    /// The timestamp `TimestampPolicy::Nudge` moved the packet to, if it did.
+    nudged_timestamp: Option<f64>,
+    /// The parser's `set_sea_level_pressure`, if the view came from a parser.
+    sea_level_pressure_pascals: Option<f32>,
+}
+
+/// Defines an accessor per `f32` field, in payload order.
+macro_rules! f32_accessors {
+    ($($field:ident),* $(,)?) => {
+        f32_accessors!(@at 0usize; $($field),*);
+    };
+    (@at $index:expr; $field:ident $(, $rest:ident)*) => {
+        #[doc = concat!("Decodes `FIRMData::", stringify!($field), "`.")]
+        pub fn $field(&self) -> f32 {
+            self.f32_at($index)
+        }
+        f32_accessors!(@at $index + 1; $($rest),*);
+    };
+    (@at $index:expr;) => {};
+}
+
+impl<'a> PacketView<'a> {
+    /// Wraps a data packet payload.
+    ///
+    /// # Arguments
+    ///
+    /// - `payload` (`&[u8]`) - The frame payload, in one of the `DataLayout`s.
+    ///
+    /// # Returns
+    ///
+    /// - `Option<Self>` - The view, or `None` if the payload's length matches no `DataLayout`.
+    ///   Use `FIRMData::check_payload` to also reject the values `FIRMData::from_bytes` does.
+    pub fn new(payload: &'a [u8]) -> Option<Self> {
+        DataLayout::from_payload_length(payload.len()).map(|_| Self {
+            payload,
+            nudged_timestamp: None,
+            sea_level_pressure_pascals: None,
+        })
+    }
+
+    /// The layout the payload is read with.
+    pub fn layout(&self) -> DataLayout {
+        DataLayout::from_payload_length(self.payload.len()).unwrap()
+    }
+
+    /// Returns the view with its timestamp replaced, as `TimestampPolicy::Nudge` does.
+    pub(crate) fn nudged(self, timestamp_seconds: f64) -> Self {
+        Self {
+            nudged_timestamp: Some(timestamp_seconds),
+            ..self
+        }
+    }
+
+    /// Returns the view with `pressure_altitude_meters` computed from `pascals`, as the
+    /// parser does for the packets it queues.
+    pub(crate) fn with_sea_level_pressure(self, pascals: f32) -> Self {
+        Self {
+            sea_level_pressure_pascals: Some(pascals),
+            ..self
+        }
+    }
+
+    /// Returns the payload bytes the view reads from.
+    pub fn payload(&self) -> &'a [u8] {
+        self.payload
+    }
+
+    /// Decodes `FIRMData::timestamp_seconds`, after any `TimestampPolicy` correction.
+    pub fn timestamp_seconds(&self) -> f64 {
+        self.nudged_timestamp
+            .unwrap_or_else(|| self.sent_timestamp_seconds())
+    }
+
+    /// The timestamp the device sent, if `TimestampPolicy::Nudge` moved it. Matches
+    /// `FIRMDataPacket::original_timestamp_seconds`.
+    pub fn original_timestamp_seconds(&self) -> Option<f64> {
+        self.nudged_timestamp.map(|_| self.sent_timestamp_seconds())
+    }
+
+    // Fields a `DataLayout::RawSensors` payload doesn't have read as 0, as in `to_data`.
+    f32_accessors!(
+        temperature_celsius,
+        pressure_pascals,
+        raw_acceleration_x_gs,
+        raw_acceleration_y_gs,
+        raw_acceleration_z_gs,
+        raw_angular_rate_x_deg_per_s,
+        raw_angular_rate_y_deg_per_s,
+        raw_angular_rate_z_deg_per_s,
+        magnetic_field_x_microteslas,
+        magnetic_field_y_microteslas,
+        magnetic_field_z_microteslas,
+        est_position_x_meters,
+        est_position_y_meters,
+        est_position_z_meters,
+        est_velocity_x_meters_per_s,
+        est_velocity_y_meters_per_s,
+        est_velocity_z_meters_per_s,
+        est_acceleration_x_gs,
+        est_acceleration_y_gs,
+        est_acceleration_z_gs,
+        est_angular_rate_x_rad_per_s,
+        est_angular_rate_y_rad_per_s,
+        est_angular_rate_z_rad_per_s,
+        est_quaternion_w,
+        est_quaternion_x,
+        est_quaternion_y,
+        est_quaternion_z,
+    );
+
+    /// Computes `FIRMData::pressure_altitude_meters` from the parser's sea level pressure.
+    /// Reads as 0 for a view made with `new`, as `FIRMData::from_bytes` leaves it.
+    pub fn pressure_altitude_meters(&self) -> f32 {
+        self.sea_level_pressure_pascals.map_or(0.0, |p0| {
+            pressure_altitude_meters(self.pressure_pascals(), p0)
+        })
+    }
+
+    /// Decodes every field into an owned `FIRMData`, the same one the owned path queues.
+    pub fn to_data(&self) -> FIRMData {
+        let mut data = FIRMData::from_bytes_unchecked(self.payload);
+        data.timestamp_seconds = self.timestamp_seconds();
+        data.pressure_altitude_meters = self.pressure_altitude_meters();
+        data
+    }
+
+    fn sent_timestamp_seconds(&self) -> f64 {
+        f64::from_le_bytes(*self.payload.first_chunk().unwrap())
+    }
+
+    fn f32_at(&self, index: usize) -> f32 {
+        self.payload[8 + 4 * index..]
+            .first_chunk()
+            .map_or(0.0, |bytes| f32::from_le_bytes(*bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::packet::{DATA_PACKET_PAYLOAD_LENGTH, RAW_SENSOR_PACKET_PAYLOAD_LENGTH};
+
+    #[test]
+    fn test_view_reads_the_same_fields_as_from_bytes() {
+        let fields: [f32; FIRMData::NUM_F32_FIELDS] = core::array::from_fn(|i| i as f32 * 1.5);
+        let data = FIRMData::from_fields(12.25, fields);
+        let mut payload = data.timestamp_seconds.to_le_bytes().to_vec();
+        payload.extend(fields.iter().flat_map(|field| field.to_le_bytes()));
+
+        let view = PacketView::new(&payload).unwrap();
+        assert_eq!(view.timestamp_seconds(), 12.25);
+        assert_eq!(view.temperature_celsius(), data.temperature_celsius);
+        assert_eq!(view.raw_acceleration_z_gs(), data.raw_acceleration_z_gs);
+        assert_eq!(view.est_quaternion_z(), data.est_quaternion_z);
+        assert_eq!(view.original_timestamp_seconds(), None);
+        assert_eq!(view.to_data(), data);
+
+        let nudged = view.nudged(13.0);
+        assert_eq!(nudged.timestamp_seconds(), 13.0);
+        assert_eq!(nudged.original_timestamp_seconds(), Some(12.25));
+        assert_eq!(nudged.to_data().timestamp_seconds, 13.0);
+
+        let from_parser = view.with_sea_level_pressure(1.5);
+        assert_eq!(view.pressure_altitude_meters(), 0.0);
+        assert_eq!(from_parser.pressure_altitude_meters(), 0.0);
+        assert!(
+            view.with_sea_level_pressure(3.0)
+                .to_data()
+                .pressure_altitude_meters
+                > 0.0
+        );
+
+        let raw_sensors = PacketView::new(&payload[..RAW_SENSOR_PACKET_PAYLOAD_LENGTH]).unwrap();
+        assert_eq!(raw_sensors.layout(), DataLayout::RawSensors);
+        assert_eq!(
+            raw_sensors.magnetic_field_z_microteslas(),
+            data.magnetic_field_z_microteslas
+        );
+        assert_eq!(raw_sensors.est_position_x_meters(), 0.0);
+        assert_eq!(raw_sensors.to_data().est_quaternion_z, 0.0);
+
+        assert!(PacketView::new(&payload[..DATA_PACKET_PAYLOAD_LENGTH - 1]).is_none());
+    }
+}
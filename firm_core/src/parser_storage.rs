@@ -0,0 +1,342 @@
+//! Where `SerialParser` keeps its buffered bytes and parsed packets.
+//!
+//! `AllocStorage`, the default, grows `Vec`s and `VecDeque`s as needed. `FixedStorage` keeps
+//! the rolling byte buffer and the packet queues in arrays sized at compile time, so their
+//! memory use is bounded up front; when one of its queues is full, the parser's
+//! `OverflowPolicy` decides which packet is dropped. The parsing logic is the same for both.
+//!
+//! On its own `FixedStorage` only bounds the buffers: the packets in it still keep their
+//! payloads and response text in `Vec`s and `String`s. Built with the `no-alloc` feature,
+//! `firm_core` doesn't link `alloc` at all. Packets keep those fields in the fixed-capacity
+//! types from `fixed` instead, `AllocStorage` is left out and `FixedStorage` is the default,
+//! so parsing never touches the heap.
+//!
+//! Both byte buffers drop parsed bytes by moving a start index forward, and only move what's
+//! left back to the front when they run out of room at the end, so the usual parse, which
+//! leaves at most part of a frame behind, copies nothing.
+use crate::firm_packets::{FIRMBaroPacket, FIRMDataPacket, FIRMResponsePacket};
+use crate::framed_packet::FramedPacket;
+#[cfg(not(feature = "no-alloc"))]
+use alloc::{collections::VecDeque, vec::Vec};
+
+/// The rolling buffer of bytes that haven't been parsed yet.
+pub trait ByteBuffer {
+    /// Returns the buffered bytes, oldest first.
+    fn as_slice(&self) -> &[u8];
+
+    /// Appends as many of `bytes` as there is room for.
+    ///
+    /// # Returns
+    ///
+    /// - `usize` - How many bytes from the front of `bytes` were appended.
+    fn extend_from(&mut self, bytes: &[u8]) -> usize;
+
+    /// Drops the oldest `count` bytes.
+    fn consume(&mut self, count: usize);
+
+    /// Returns the most bytes the buffer can hold, or `None` if it grows as needed.
+    fn capacity(&self) -> Option<usize>;
+
+    fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A first-in, first-out queue of parsed packets.
+pub trait PacketQueue<T> {
+    /// Appends `item`, or hands it back if the queue is full.
+    fn push_back(&mut self, item: T) -> Result<(), T>;
+
+    /// Removes the oldest item.
+    fn pop_front(&mut self) -> Option<T>;
+
+    /// Returns the queued items, oldest first.
+    fn iter<'a>(&'a self) -> impl Iterator<Item = &'a T>
+    where
+        T: 'a;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The set of containers a `SerialParser` is built on.
+pub trait ParserStorage {
+    type Bytes: ByteBuffer + Default;
+    type DataPackets: PacketQueue<FIRMDataPacket> + Default;
+    type BaroPackets: PacketQueue<FIRMBaroPacket> + Default;
+    type Responses: PacketQueue<FIRMResponsePacket> + Default;
+    type RawFrames: PacketQueue<FramedPacket> + Default;
+}
+
+/// The storage `SerialParser` uses unless told otherwise: `AllocStorage`, or with the
+/// `no-alloc` feature a `FixedStorage` with room for a few frames of the longest payload.
+#[cfg(not(feature = "no-alloc"))]
+pub type DefaultStorage = AllocStorage;
+/// The storage `SerialParser` uses unless told otherwise: `AllocStorage`, or with the
+/// `no-alloc` feature a `FixedStorage` with room for a few frames of the longest payload.
+#[cfg(feature = "no-alloc")]
+pub type DefaultStorage = FixedStorage<4096, 16>;
+
+/// Heap storage that grows as needed, the default.
+#[cfg(not(feature = "no-alloc"))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AllocStorage;
+
+#[cfg(not(feature = "no-alloc"))]
+impl ParserStorage for AllocStorage {
+    type Bytes = RollingBuffer;
+    type DataPackets = VecDeque<FIRMDataPacket>;
+    type BaroPackets = VecDeque<FIRMBaroPacket>;
+    type Responses = VecDeque<FIRMResponsePacket>;
+    type RawFrames = VecDeque<FramedPacket>;
+}
+
+/// A byte buffer that grows as needed. Its allocation is reused once it's big enough for the
+/// longest stretch of bytes kept at once.
+#[cfg(not(feature = "no-alloc"))]
+#[derive(Debug, Clone, Default)]
+pub struct RollingBuffer {
+    bytes: Vec<u8>,
+    /// Where the unconsumed bytes start.
+    head: usize,
+}
+
+#[cfg(not(feature = "no-alloc"))]
+impl ByteBuffer for RollingBuffer {
+    fn as_slice(&self) -> &[u8] {
+        &self.bytes[self.head..]
+    }
+
+    fn extend_from(&mut self, bytes: &[u8]) -> usize {
+        // Reuse the consumed space at the front before growing.
+        if self.head > 0 && self.bytes.capacity() - self.bytes.len() < bytes.len() {
+            self.bytes.drain(..self.head);
+            self.head = 0;
+        }
+        self.bytes.extend_from_slice(bytes);
+        bytes.len()
+    }
+
+    fn consume(&mut self, count: usize) {
+        self.head += count;
+        assert!(self.head <= self.bytes.len());
+        if self.head == self.bytes.len() {
+            self.bytes.clear();
+            self.head = 0;
+        }
+    }
+
+    fn capacity(&self) -> Option<usize> {
+        None
+    }
+}
+
+#[cfg(not(feature = "no-alloc"))]
+impl<T> PacketQueue<T> for VecDeque<T> {
+    fn push_back(&mut self, item: T) -> Result<(), T> {
+        VecDeque::push_back(self, item);
+        Ok(())
+    }
+
+    fn pop_front(&mut self) -> Option<T> {
+        VecDeque::pop_front(self)
+    }
+
+    fn iter<'a>(&'a self) -> impl Iterator<Item = &'a T>
+    where
+        T: 'a,
+    {
+        VecDeque::iter(self)
+    }
+
+    fn len(&self) -> usize {
+        VecDeque::len(self)
+    }
+}
+
+/// Fixed-capacity storage: a `BUFFER` byte rolling buffer and queues of up to `PACKETS`
+/// packets each, sized at compile time. Unless built with `no-alloc`, the packets themselves
+/// still allocate, see the module docs. `BUFFER` also limits the longest frame the parser can
+/// read, see `SerialParser::set_max_buffer_len`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FixedStorage<const BUFFER: usize, const PACKETS: usize>;
+
+impl<const BUFFER: usize, const PACKETS: usize> ParserStorage for FixedStorage<BUFFER, PACKETS> {
+    type Bytes = FixedBuffer<BUFFER>;
+    type DataPackets = FixedQueue<FIRMDataPacket, PACKETS>;
+    type BaroPackets = FixedQueue<FIRMBaroPacket, PACKETS>;
+    type Responses = FixedQueue<FIRMResponsePacket, PACKETS>;
+    type RawFrames = FixedQueue<FramedPacket, PACKETS>;
+}
+
+/// A byte buffer holding at most `N` bytes.
+#[derive(Debug, Clone)]
+pub struct FixedBuffer<const N: usize> {
+    bytes: [u8; N],
+    /// Where the unconsumed bytes start.
+    head: usize,
+    /// Where they end.
+    end: usize,
+}
+
+impl<const N: usize> Default for FixedBuffer<N> {
+    fn default() -> Self {
+        Self {
+            bytes: [0; N],
+            head: 0,
+            end: 0,
+        }
+    }
+}
+
+impl<const N: usize> ByteBuffer for FixedBuffer<N> {
+    fn as_slice(&self) -> &[u8] {
+        &self.bytes[self.head..self.end]
+    }
+
+    fn extend_from(&mut self, bytes: &[u8]) -> usize {
+        // Move the unconsumed bytes to the front only when there isn't room after them.
+        if self.head > 0 && N - self.end < bytes.len() {
+            self.bytes.copy_within(self.head..self.end, 0);
+            self.end -= self.head;
+            self.head = 0;
+        }
+        let count = bytes.len().min(N - self.end);
+        self.bytes[self.end..self.end + count].copy_from_slice(&bytes[..count]);
+        self.end += count;
+        count
+    }
+
+    fn consume(&mut self, count: usize) {
+        self.head += count;
+        assert!(self.head <= self.end);
+        if self.head == self.end {
+            self.head = 0;
+            self.end = 0;
+        }
+    }
+
+    fn capacity(&self) -> Option<usize> {
+        Some(N)
+    }
+}
+
+/// A ring buffer queue holding at most `N` items.
+#[derive(Debug, Clone)]
+pub struct FixedQueue<T, const N: usize> {
+    slots: [Option<T>; N],
+    head: usize,
+    len: usize,
+}
+
+impl<T, const N: usize> Default for FixedQueue<T, N> {
+    fn default() -> Self {
+        Self {
+            slots: core::array::from_fn(|_| None),
+            head: 0,
+            len: 0,
+        }
+    }
+}
+
+impl<T, const N: usize> PacketQueue<T> for FixedQueue<T, N> {
+    fn push_back(&mut self, item: T) -> Result<(), T> {
+        if self.len == N {
+            return Err(item);
+        }
+        self.slots[(self.head + self.len) % N] = Some(item);
+        self.len += 1;
+        Ok(())
+    }
+
+    fn pop_front(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        let item = self.slots[self.head].take();
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        item
+    }
+
+    fn iter<'a>(&'a self) -> impl Iterator<Item = &'a T>
+    where
+        T: 'a,
+    {
+        (0..self.len).filter_map(|i| self.slots[(self.head + i) % N].as_ref())
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_containers_stop_at_capacity() {
+        let mut buffer = FixedBuffer::<4>::default();
+        assert_eq!(buffer.extend_from(&[1, 2, 3]), 3);
+        assert_eq!(buffer.extend_from(&[4, 5]), 1);
+        buffer.consume(2);
+        assert_eq!(buffer.as_slice(), [3, 4]);
+        assert_eq!(buffer.extend_from(&[5, 6, 7]), 2);
+        assert_eq!(buffer.as_slice(), [3, 4, 5, 6]);
+
+        let mut queue = FixedQueue::<u32, 3>::default();
+        for i in 0..3 {
+            queue.push_back(i).unwrap();
+        }
+        assert_eq!(queue.push_back(3), Err(3));
+        assert_eq!(queue.pop_front(), Some(0));
+        queue.push_back(3).unwrap();
+        // The queue has wrapped around the end of its slots.
+        assert_eq!(queue.iter().copied().collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!(core::iter::from_fn(|| queue.pop_front()).count(), 3);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_byte_buffers_keep_bytes_straddling_the_end() {
+        // A start word whose first byte lands in the last slot, with the second still to come.
+        let mut fixed = FixedBuffer::<8>::default();
+        assert_eq!(fixed.extend_from(&[1, 2, 3, 4, 5, 6, 7, 0x5A]), 8);
+        fixed.consume(7);
+        assert_eq!(fixed.as_slice(), [0x5A]);
+        assert_eq!(fixed.extend_from(&[0xA5, 9, 10]), 3);
+        assert_eq!(fixed.as_slice(), [0x5A, 0xA5, 9, 10]);
+        // Room after the bytes is used before anything is moved.
+        fixed.consume(1);
+        assert_eq!(fixed.extend_from(&[11, 12, 13, 14]), 4);
+        assert_eq!(fixed.head, 1);
+        assert_eq!(fixed.as_slice(), [0xA5, 9, 10, 11, 12, 13, 14]);
+        assert_eq!(fixed.extend_from(&[15, 16]), 1);
+        assert_eq!(fixed.as_slice(), [0xA5, 9, 10, 11, 12, 13, 14, 15]);
+
+        let mut rolling = RollingBuffer::default();
+        rolling.extend_from(&[0; 64]);
+        rolling.consume(63);
+        rolling.extend_from(&[0; 4]);
+        let capacity = rolling.bytes.capacity();
+        for round in 0..1000u32 {
+            // Keep the last byte back each time, like the first half of a start word.
+            let bytes = round.to_le_bytes();
+            rolling.extend_from(&bytes);
+            let kept = *rolling.as_slice().last().unwrap();
+            rolling.consume(rolling.len() - 1);
+            assert_eq!(rolling.as_slice(), [kept]);
+        }
+        assert_eq!(rolling.bytes.capacity(), capacity);
+        rolling.consume(1);
+        assert!(rolling.is_empty());
+    }
+}
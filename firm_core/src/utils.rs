@@ -1,3 +1,9 @@
+#[cfg(not(feature = "no-alloc"))]
+use alloc::string::{String, ToString};
+
+#[cfg(feature = "no-alloc")]
+use crate::fixed::FixedString;
+
 // Precomputed CRC-16/CCITT lookup table for all 256 possible byte values.
 const CRC16_TABLE: [u16; 256] = [
     0x0000, 0x1189, 0x2312, 0x329B, 0x4624, 0x57AD, 0x6536, 0x74BF, 0x8C48, 0x9DC1, 0xAF5A, 0xBED3,
@@ -35,7 +41,21 @@ const CRC16_TABLE: [u16; 256] = [
 ///
 /// - `u16` - The resulting 16-bit CRC-16/CCITT checksum.
 pub(crate) fn crc16_ccitt(data: &[u8]) -> u16 {
-    let mut crc: u16 = 0x0000;
+    crc16_ccitt_update(0x0000, data)
+}
+
+/// Continues a CRC-16/CCITT checksum over more bytes, so one spread over several slices
+/// doesn't need them copied together first.
+///
+/// # Arguments
+///
+/// - `crc` (`u16`) - The checksum of the bytes so far.
+/// - `data` (`&[u8]`) - The bytes that follow them.
+///
+/// # Returns
+///
+/// - `u16` - The checksum of all the bytes.
+pub(crate) fn crc16_ccitt_update(mut crc: u16, data: &[u8]) -> u16 {
     for &byte in data {
         let idx: u8 = crc as u8 ^ byte;
         crc = CRC16_TABLE[idx as usize] ^ (crc >> 8);
@@ -43,6 +63,107 @@ pub(crate) fn crc16_ccitt(data: &[u8]) -> u16 {
     crc
 }
 
+/// CRC-32 (the IEEE polynomial, reflected) lookup table for all 256 possible byte values.
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Computes the CRC-32 checksum for a given byte slice, the one zlib and Ethernet use.
+///
+/// # Arguments
+///
+/// - `data` (`&[u8]`) - The input byte slice to compute the checksum for.
+///
+/// # Returns
+///
+/// - `u32` - The resulting 32-bit CRC-32 checksum.
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    !crc32_update(!0, data)
+}
+
+/// Continues a CRC-32 over more bytes, like `crc16_ccitt_update`. `crc` is the running
+/// register, not a finished checksum: start from `!0` and invert the result, as `crc32` does.
+///
+/// # Arguments
+///
+/// - `crc` (`u32`) - The register after the bytes so far.
+/// - `data` (`&[u8]`) - The bytes that follow them.
+///
+/// # Returns
+///
+/// - `u32` - The register after all the bytes.
+pub(crate) fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+    for &byte in data {
+        let idx = (crc as u8 ^ byte) as usize;
+        crc = CRC32_TABLE[idx] ^ (crc >> 8);
+    }
+    crc
+}
+
+/// The standard base64 alphabet.
+#[cfg(feature = "base64")]
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encodes `bytes` as padded standard base64.
+#[cfg(feature = "base64")]
+pub(crate) fn base64_encode(bytes: &[u8]) -> alloc::string::String {
+    let mut out = alloc::string::String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, &byte)| {
+            group | u32::from(byte) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            out.push(if i <= chunk.len() {
+                BASE64_ALPHABET[(group >> (18 - 6 * i)) as usize & 0x3F] as char
+            } else {
+                '='
+            });
+        }
+    }
+    out
+}
+
+/// Decodes padded standard base64, or returns `None` if `text` isn't that.
+#[cfg(feature = "base64")]
+pub(crate) fn base64_decode(text: &str) -> Option<alloc::vec::Vec<u8>> {
+    let text = text.as_bytes();
+    if !text.len().is_multiple_of(4) {
+        return None;
+    }
+    let mut out = alloc::vec::Vec::with_capacity(text.len() / 4 * 3);
+    for (index, chunk) in text.chunks(4).enumerate() {
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 || (padding > 0 && index + 1 != text.len() / 4) {
+            return None;
+        }
+        let mut group = 0u32;
+        for &c in &chunk[..4 - padding] {
+            let value = BASE64_ALPHABET.iter().position(|&a| a == c)?;
+            group = group << 6 | value as u32;
+        }
+        group <<= 6 * padding;
+        out.extend_from_slice(&group.to_be_bytes()[1..4 - padding]);
+    }
+    Some(out)
+}
+
 /// Converts a string to a fixed-size byte array, padding with zeros if necessary or
 /// truncating if too long.
 ///
@@ -71,15 +192,22 @@ pub(crate) fn str_to_bytes<const N: usize>(string: &str) -> [u8; N] {
 /// # Returns
 ///
 /// - `String` - The resulting string, stopping at the first zero byte.
+#[cfg(not(feature = "no-alloc"))]
 pub(crate) fn bytes_to_str(bytes: &[u8]) -> String {
-    let mut end = bytes.len();
-    for (i, &b) in bytes.iter().enumerate() {
-        if b == 0 {
-            end = i;
-            break;
-        }
-    }
-    String::from_utf8_lossy(&bytes[..end]).to_string()
+    String::from_utf8_lossy(until_nul(bytes)).to_string()
+}
+
+/// Like the default build's `bytes_to_str`, into a `FixedString` that keeps as many whole
+/// characters as fit in `N` bytes.
+#[cfg(feature = "no-alloc")]
+pub(crate) fn bytes_to_str<const N: usize>(bytes: &[u8]) -> FixedString<N> {
+    FixedString::from_utf8_lossy(until_nul(bytes))
+}
+
+/// Returns `bytes` up to the first zero byte, or all of them if there isn't one.
+fn until_nul(bytes: &[u8]) -> &[u8] {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    &bytes[..end]
 }
 
 pub(crate) fn parse_bytes_to_f32(bytes: &[u8], idx: &mut usize) -> f32 {
@@ -88,11 +216,3 @@ pub(crate) fn parse_bytes_to_f32(bytes: &[u8], idx: &mut usize) -> f32 {
     *idx += 4;
     value
 }
-
-pub(crate) fn parse_bytes_to_many_f32s(bytes: &[u8], n: usize, idx: &mut usize) -> Vec<f32> {
-    let mut values = Vec::with_capacity(n);
-    for _ in 0..n {
-        values.push(parse_bytes_to_f32(bytes, idx));
-    }
-    values
-}
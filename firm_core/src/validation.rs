@@ -0,0 +1,283 @@
+//! Plausibility checks for telemetry, see `FIRMDataPacket::validate`.
+//!
+//! A frame can pass its CRC and still hold nonsense, from noise that happens to checksum or a
+//! sensor that has failed. `ValidationConfig` bounds the fields to what a rocket can see, so a
+//! NaN pressure or a 10^30 g reading can be caught before it reaches a plot.
+use crate::firm_packets::FIRMData;
+use alloc::vec::Vec;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+/// An inclusive range of allowed values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Bounds {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Bounds {
+    /// Every finite value.
+    pub const ANY: Bounds = Bounds {
+        min: f32::MIN,
+        max: f32::MAX,
+    };
+
+    /// Values from `-limit` to `limit`.
+    pub const fn symmetric(limit: f32) -> Self {
+        Bounds {
+            min: -limit,
+            max: limit,
+        }
+    }
+
+    pub fn contains(&self, value: f32) -> bool {
+        (self.min..=self.max).contains(&value)
+    }
+}
+
+/// Why a field failed validation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum FaultReason {
+    /// The value is NaN or infinite.
+    NotFinite,
+    /// The value is outside the field's `Bounds`.
+    OutOfRange(Bounds),
+    /// The timestamp went back further than `ValidationConfig::max_timestamp_rewind_seconds`
+    /// from the previous packet's.
+    TimestampRewound { previous_seconds: f64 },
+}
+
+/// A field of a data packet that failed validation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct FieldFault {
+    /// The field's name, one of `FIRMData::field_names()`.
+    pub field: &'static str,
+    pub value: f64,
+    pub reason: FaultReason,
+}
+
+impl core::fmt::Display for FieldFault {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let FieldFault {
+            field,
+            value,
+            reason,
+        } = self;
+        match reason {
+            FaultReason::NotFinite => write!(f, "{field} is {value}"),
+            FaultReason::OutOfRange(Bounds { min, max }) => {
+                write!(f, "{field} {value} is outside {min}..={max}")
+            }
+            FaultReason::TimestampRewound { previous_seconds } => {
+                write!(f, "{field} went back from {previous_seconds} to {value}")
+            }
+        }
+    }
+}
+
+/// The ranges `ValidationConfig::check` holds data packets to. The default suits a
+/// high-power rocket; `permissive` only rejects values that aren't numbers at all.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ValidationConfig {
+    /// Allowed `pressure_pascals`. Defaults to 1–120 kPa, from well above any flight ceiling
+    /// to below sea level.
+    pub pressure_pascals: Bounds,
+    /// Allowed value of each raw and estimated acceleration axis, in g. Defaults to ±400 g.
+    pub acceleration_gs: Bounds,
+    /// Allowed value of each magnetic field axis, in µT. Defaults to ±5000 µT.
+    pub magnetic_field_microteslas: Bounds,
+    /// How many seconds a timestamp may go back from the previous packet's, or `None` to not
+    /// check timestamps against each other. Defaults to 0, so repeats pass but rewinds don't.
+    pub max_timestamp_rewind_seconds: Option<f64>,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        ValidationConfig {
+            pressure_pascals: Bounds {
+                min: 1_000.0,
+                max: 120_000.0,
+            },
+            acceleration_gs: Bounds::symmetric(400.0),
+            magnetic_field_microteslas: Bounds::symmetric(5_000.0),
+            max_timestamp_rewind_seconds: Some(0.0),
+        }
+    }
+}
+
+impl ValidationConfig {
+    /// Accepts every finite value, in any order.
+    pub fn permissive() -> Self {
+        ValidationConfig {
+            pressure_pascals: Bounds::ANY,
+            acceleration_gs: Bounds::ANY,
+            magnetic_field_microteslas: Bounds::ANY,
+            max_timestamp_rewind_seconds: None,
+        }
+    }
+
+    /// Checks every field the device sends against the config.
+    ///
+    /// # Arguments
+    ///
+    /// - `data` (`&FIRMData`) - The packet to check.
+    /// - `previous` (`Option<&FIRMData>`) - The packet before it, to check the timestamp
+    ///   against, or `None` for the first packet.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<(), Vec<FieldFault>>` - Every failing field, in `FIRMData::field_names()`
+    ///   order. A field that isn't finite isn't also reported as out of range.
+    pub fn check(
+        &self,
+        data: &FIRMData,
+        previous: Option<&FIRMData>,
+    ) -> Result<(), Vec<FieldFault>> {
+        let mut faults = Vec::new();
+        let mut fault = |field, value: f64, reason| {
+            faults.push(FieldFault {
+                field,
+                value,
+                reason,
+            })
+        };
+
+        if !data.timestamp_seconds.is_finite() {
+            fault(
+                "timestamp_seconds",
+                data.timestamp_seconds,
+                FaultReason::NotFinite,
+            );
+        } else if let (Some(rewind), Some(previous)) = (self.max_timestamp_rewind_seconds, previous)
+            && data.timestamp_seconds < previous.timestamp_seconds - rewind
+        {
+            fault(
+                "timestamp_seconds",
+                data.timestamp_seconds,
+                FaultReason::TimestampRewound {
+                    previous_seconds: previous.timestamp_seconds,
+                },
+            );
+        }
+
+        // The field names are the timestamp, then the `f32_fields` in order.
+        let names = &FIRMData::field_names()[1..=FIRMData::NUM_F32_FIELDS];
+        for (&field, value) in names.iter().zip(data.f32_fields()) {
+            if !value.is_finite() {
+                fault(field, value as f64, FaultReason::NotFinite);
+                continue;
+            }
+            let bounds = match field {
+                "pressure_pascals" => self.pressure_pascals,
+                field if field.contains("acceleration") => self.acceleration_gs,
+                field if field.starts_with("magnetic_field") => self.magnetic_field_microteslas,
+                _ => continue,
+            };
+            if !bounds.contains(value) {
+                fault(field, value as f64, FaultReason::OutOfRange(bounds));
+            }
+        }
+
+        if faults.is_empty() {
+            Ok(())
+        } else {
+            Err(faults)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::firm_packets::{FIRMData, FIRMDataPacket};
+    use crate::framed_packet::Framed;
+
+    fn on_the_pad(timestamp_seconds: f64) -> FIRMData {
+        let mut data = FIRMData::from_fields(timestamp_seconds, [0.0; FIRMData::NUM_F32_FIELDS]);
+        data.pressure_pascals = 97_000.0;
+        data.raw_acceleration_z_gs = 1.0;
+        data.magnetic_field_x_microteslas = 45.0;
+        data.est_quaternion_w = 1.0;
+        data
+    }
+
+    #[test]
+    fn test_plausible_packets_pass() {
+        let config = ValidationConfig::default();
+        let data = on_the_pad(1.0);
+        assert_eq!(config.check(&data, None), Ok(()));
+        assert_eq!(config.check(&on_the_pad(1.0), Some(&data)), Ok(()));
+        assert_eq!(config.check(&on_the_pad(1.001), Some(&data)), Ok(()));
+        assert_eq!(FIRMDataPacket::new(data).validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_out_of_range_payloads_report_each_failing_field() {
+        let mut data = on_the_pad(2.0);
+        data.pressure_pascals = f32::NAN;
+        data.raw_acceleration_x_gs = 1e30;
+        data.est_acceleration_y_gs = -401.0;
+        data.magnetic_field_z_microteslas = 6_000.0;
+        data.est_velocity_z_meters_per_s = f32::INFINITY;
+        // Decoded from a frame, as the parser would hand it over.
+        let packet = FIRMDataPacket::from_bytes(&FIRMDataPacket::new(data).to_bytes()).unwrap();
+
+        let config = ValidationConfig::default();
+        let faults = packet
+            .validate_with(&config, Some(&on_the_pad(3.0)))
+            .unwrap_err();
+        let summary: Vec<(&str, FaultReason)> = faults
+            .iter()
+            .map(|fault| (fault.field, fault.reason))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (
+                    "timestamp_seconds",
+                    FaultReason::TimestampRewound {
+                        previous_seconds: 3.0
+                    }
+                ),
+                ("pressure_pascals", FaultReason::NotFinite),
+                (
+                    "raw_acceleration_x_gs",
+                    FaultReason::OutOfRange(config.acceleration_gs)
+                ),
+                (
+                    "magnetic_field_z_microteslas",
+                    FaultReason::OutOfRange(config.magnetic_field_microteslas)
+                ),
+                ("est_velocity_z_meters_per_s", FaultReason::NotFinite),
+                (
+                    "est_acceleration_y_gs",
+                    FaultReason::OutOfRange(config.acceleration_gs)
+                ),
+            ]
+        );
+        assert_eq!(faults[2].value, 1e30f32 as f64);
+        assert_eq!(
+            faults[5].to_string(),
+            "est_acceleration_y_gs -401 is outside -400..=400"
+        );
+
+        // The permissive config only objects to what isn't a number.
+        let faults = packet
+            .validate_with(&ValidationConfig::permissive(), Some(&on_the_pad(3.0)))
+            .unwrap_err();
+        let fields: Vec<&str> = faults.iter().map(|fault| fault.field).collect();
+        assert_eq!(fields, ["pressure_pascals", "est_velocity_z_meters_per_s"]);
+
+        let mut thin_air = on_the_pad(4.0);
+        thin_air.pressure_pascals = 500.0;
+        assert!(config.check(&thin_air, None).is_err());
+        assert_eq!(
+            ValidationConfig::permissive().check(&thin_air, None),
+            Ok(())
+        );
+    }
+}
@@ -0,0 +1,308 @@
+/* Generated by cbindgen from firm_ffi/src/lib.rs. Do not edit by hand. */
+
+#ifndef FIRM_FFI_H
+#define FIRM_FFI_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Largest frame any command builder writes, so a buffer of this size always fits.
+ */
+#define FIRM_MAX_COMMAND_FRAME_SIZE 106
+
+/**
+ * Size of `FIRMResponsePacket::firmware_version`, including the NUL terminator.
+ */
+#define FIRM_FIRMWARE_VERSION_SIZE 9
+
+/**
+ * Size of `FIRMResponsePacket::device_name`, including the NUL terminator.
+ */
+#define FIRM_DEVICE_NAME_SIZE 33
+
+/**
+ * Result of every FFI call. Negative values are errors.
+ */
+typedef enum FIRMStatus {
+  /**
+   * The call succeeded.
+   */
+  FIRM_STATUS_OK = 0,
+  /**
+   * The call succeeded, but there was nothing to return (e.g. no packet is ready).
+   */
+  FIRM_STATUS_EMPTY = 1,
+  /**
+   * A required pointer argument was null.
+   */
+  FIRM_STATUS_NULL_POINTER = -1,
+  /**
+   * The output buffer is too small; `written` holds the size that is needed.
+   */
+  FIRM_STATUS_BUFFER_TOO_SMALL = -2,
+  /**
+   * An argument was out of range (e.g. an unknown protocol or a name that isn't UTF-8).
+   */
+  FIRM_STATUS_INVALID_ARGUMENT = -3,
+  /**
+   * The library panicked. The parser it was called with should be freed and not reused.
+   */
+  FIRM_STATUS_PANIC = -4,
+} FIRMStatus;
+
+/**
+ * The command a response answers, with the same values as the command identifiers on the
+ * wire.
+ */
+typedef enum FIRMCommandType {
+  FIRM_COMMAND_TYPE_GET_DEVICE_INFO = 1,
+  FIRM_COMMAND_TYPE_GET_DEVICE_CONFIG = 2,
+  FIRM_COMMAND_TYPE_SET_DEVICE_CONFIG = 3,
+  FIRM_COMMAND_TYPE_REBOOT = 4,
+  FIRM_COMMAND_TYPE_MOCK = 5,
+  FIRM_COMMAND_TYPE_SET_MAGNETOMETER_CALIBRATION = 6,
+  FIRM_COMMAND_TYPE_SET_IMU_CALIBRATION = 7,
+  FIRM_COMMAND_TYPE_GET_CALIBRATION = 8,
+  FIRM_COMMAND_TYPE_CANCEL = 255,
+} FIRMCommandType;
+
+/**
+ * An incremental parser for the byte stream coming from a FIRM device. Opaque to C.
+ */
+typedef struct FIRMParser FIRMParser;
+
+/**
+ * One decoded data packet, laid out like `FIRMDataPacket` in the Python client: the device
+ * timestamp followed by every measurement as a 32-bit float, in the same order.
+ */
+typedef struct FIRMDataPacket {
+  double timestamp_seconds;
+
+  float temperature_celsius;
+  float pressure_pascals;
+
+  float raw_acceleration_x_gs;
+  float raw_acceleration_y_gs;
+  float raw_acceleration_z_gs;
+
+  float raw_angular_rate_x_deg_per_s;
+  float raw_angular_rate_y_deg_per_s;
+  float raw_angular_rate_z_deg_per_s;
+
+  float magnetic_field_x_microteslas;
+  float magnetic_field_y_microteslas;
+  float magnetic_field_z_microteslas;
+
+  float est_position_x_meters;
+  float est_position_y_meters;
+  float est_position_z_meters;
+
+  float est_velocity_x_meters_per_s;
+  float est_velocity_y_meters_per_s;
+  float est_velocity_z_meters_per_s;
+
+  float est_acceleration_x_gs;
+  float est_acceleration_y_gs;
+  float est_acceleration_z_gs;
+
+  float est_angular_rate_x_rad_per_s;
+  float est_angular_rate_y_rad_per_s;
+  float est_angular_rate_z_rad_per_s;
+
+  float est_quaternion_w;
+  float est_quaternion_x;
+  float est_quaternion_y;
+  float est_quaternion_z;
+
+  /**
+   * Not sent by the device: computed by the parser from `pressure_pascals` and the standard
+   * sea level pressure.
+   */
+  float pressure_altitude_meters;
+} FIRMDataPacket;
+
+/**
+ * One secondary barometer reading, see `FIRMBaroPacket` in `firm_core`.
+ */
+typedef struct FIRMBaroPacket {
+  double timestamp_seconds;
+  float pressure_pascals;
+  float temperature_celsius;
+} FIRMBaroPacket;
+
+/**
+ * One decoded command response. `command_type` says which fields are set; the others are
+ * zero. Reboot has no decoded response, so it sets none of them.
+ */
+typedef struct FIRMResponsePacket {
+  FIRMCommandType command_type;
+
+  /**
+   * SetDeviceConfig, SetMagnetometerCalibration, SetIMUCalibration, Mock and Cancel:
+   * whether the device accepted the command.
+   */
+  bool success;
+
+  /**
+   * GetDeviceInfo: the device's unique ID.
+   */
+  uint64_t device_id;
+  /**
+   * GetDeviceInfo: the firmware version, NUL-terminated.
+   */
+  char firmware_version[FIRM_FIRMWARE_VERSION_SIZE];
+
+  /**
+   * GetDeviceConfig: the device name, NUL-terminated.
+   */
+  char device_name[FIRM_DEVICE_NAME_SIZE];
+  /**
+   * GetDeviceConfig: data packet rate in Hz.
+   */
+  uint16_t frequency;
+  /**
+   * GetDeviceConfig: 1 = USB, 2 = UART, 3 = I2C, 4 = SPI.
+   */
+  uint8_t protocol;
+  /**
+   * GetDeviceConfig: set when the response broke the protocol, in which case the other
+   * config fields are the decoder's best guess.
+   */
+  bool suspect;
+
+  /**
+   * GetCalibration: the calibration values, matrices row-major.
+   */
+  float imu_accelerometer_offsets[3];
+  float imu_accelerometer_scale_matrix[9];
+  float imu_gyroscope_offsets[3];
+  float imu_gyroscope_scale_matrix[9];
+  float magnetometer_offsets[3];
+  float magnetometer_scale_matrix[9];
+} FIRMResponsePacket;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Creates a parser. Returns null if it couldn't be created; release it with
+ * `firm_parser_free`.
+ */
+FIRMParser *firm_parser_new(void);
+
+/**
+ * Frees a parser created by `firm_parser_new`. Null is ignored.
+ */
+void firm_parser_free(FIRMParser *parser);
+
+/**
+ * Feeds `len` bytes read from the device into the parser. Frames may be split across calls.
+ * Data packets, barometer readings, command responses and frames the parser couldn't decode
+ * each go to their own queue, read with the matching `firm_parser_next_*` function. A queue
+ * that isn't read stops growing once it's full and drops its oldest entries.
+ */
+FIRMStatus firm_parser_feed(FIRMParser *parser, const uint8_t *bytes, size_t len);
+
+/**
+ * Takes the oldest parsed data packet. Returns `FIRM_STATUS_OK` if a packet was written to
+ * `out`, `FIRM_STATUS_EMPTY` if none is ready.
+ */
+FIRMStatus firm_parser_next_data_packet(FIRMParser *parser, FIRMDataPacket *out);
+
+/**
+ * Takes the oldest parsed secondary barometer reading. Returns `FIRM_STATUS_OK` if a reading
+ * was written to `out`, `FIRM_STATUS_EMPTY` if none is ready.
+ */
+FIRMStatus firm_parser_next_baro_packet(FIRMParser *parser, FIRMBaroPacket *out);
+
+/**
+ * Takes the oldest parsed command response. Returns `FIRM_STATUS_OK` if a response was
+ * written to `out`, `FIRM_STATUS_EMPTY` if none is ready.
+ */
+FIRMStatus firm_parser_next_response(FIRMParser *parser, FIRMResponsePacket *out);
+
+/**
+ * Takes the oldest CRC-valid frame the parser couldn't decode, such as a response to a
+ * command this library doesn't know yet, and copies its bytes into `out` and its length
+ * into `written`. Returns `FIRM_STATUS_EMPTY` if none is ready, or
+ * `FIRM_STATUS_BUFFER_TOO_SMALL`, in which case the frame stays queued.
+ */
+FIRMStatus firm_parser_next_raw_frame(FIRMParser *parser,
+                                      uint8_t *out,
+                                      size_t capacity,
+                                      size_t *written);
+
+/**
+ * Writes a GetDeviceInfo command frame into `out` and its length into `written`.
+ */
+FIRMStatus firm_build_get_device_info_command(uint8_t *out, size_t capacity, size_t *written);
+
+/**
+ * Writes a GetDeviceConfig command frame into `out` and its length into `written`.
+ */
+FIRMStatus firm_build_get_device_config_command(uint8_t *out, size_t capacity, size_t *written);
+
+/**
+ * Writes a GetCalibration command frame into `out` and its length into `written`.
+ */
+FIRMStatus firm_build_get_calibration_command(uint8_t *out, size_t capacity, size_t *written);
+
+/**
+ * Writes a Cancel command frame into `out` and its length into `written`.
+ */
+FIRMStatus firm_build_cancel_command(uint8_t *out, size_t capacity, size_t *written);
+
+/**
+ * Writes a Reboot command frame into `out` and its length into `written`.
+ */
+FIRMStatus firm_build_reboot_command(uint8_t *out, size_t capacity, size_t *written);
+
+/**
+ * Writes a Mock command frame into `out` and its length into `written`.
+ */
+FIRMStatus firm_build_mock_command(uint8_t *out, size_t capacity, size_t *written);
+
+/**
+ * Writes a SetDeviceConfig command frame into `out` and its length into `written`.
+ * `name` is a NUL-terminated UTF-8 string truncated to 32 bytes, `protocol` is
+ * 1 = USB, 2 = UART, 3 = I2C, 4 = SPI.
+ */
+FIRMStatus firm_build_set_device_config_command(const char *name,
+                                                uint16_t frequency,
+                                                uint8_t protocol,
+                                                uint8_t *out,
+                                                size_t capacity,
+                                                size_t *written);
+
+/**
+ * Writes a SetMagnetometerCalibration command frame into `out` and its length into
+ * `written`. `offsets` holds 3 floats and `scale_matrix` 9, row-major.
+ */
+FIRMStatus firm_build_set_magnetometer_calibration_command(const float *offsets,
+                                                           const float *scale_matrix,
+                                                           uint8_t *out,
+                                                           size_t capacity,
+                                                           size_t *written);
+
+/**
+ * Writes a SetIMUCalibration command frame into `out` and its length into `written`.
+ * The offset arrays hold 3 floats and the matrices 9, row-major.
+ */
+FIRMStatus firm_build_set_imu_calibration_command(const float *accel_offsets,
+                                                  const float *accel_scale_matrix,
+                                                  const float *gyro_offsets,
+                                                  const float *gyro_scale_matrix,
+                                                  uint8_t *out,
+                                                  size_t capacity,
+                                                  size_t *written);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* FIRM_FFI_H */
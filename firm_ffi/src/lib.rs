@@ -0,0 +1,777 @@
+//! C ABI for the FIRM packet parser and command builders, for ground tools that can't use the
+//! Rust, Python or TypeScript clients. The matching header is `include/firm_ffi.h`, generated
+//! from this file by cbindgen (see `cbindgen.toml`).
+//!
+//! # Ownership
+//!
+//! - A `FIRMParser` is created by `firm_parser_new` and owned by the caller until it is passed
+//!   to `firm_parser_free`, exactly once. It must not be used from two threads at the same time.
+//! - Byte buffers, strings and calibration arrays passed in are only borrowed for the duration
+//!   of the call; nothing keeps a pointer to them afterwards.
+//! - Output (`out`, `written`) pointers are owned by the caller, who must make them valid for
+//!   writes of the documented size. The library never allocates memory the caller has to free,
+//!   apart from the parser itself.
+//!
+//! # Errors
+//!
+//! Every function returns a `FIRMStatus` (or a null parser). Panics never cross the boundary:
+//! a panic inside the library is caught and reported as `FIRMStatus::Panic`.
+use core::ffi::{CStr, c_char};
+use firm_core::client_packets::FIRMCommandPacket;
+use firm_core::constants::command::{
+    DEVICE_NAME_LENGTH, FIRMCommand, FIRMWARE_VERSION_LENGTH, IMU_CALIBRATION_PAYLOAD_LENGTH,
+    MAX_COMMAND_PAYLOAD_LENGTH, NUMBER_OF_CALIBRATION_OFFSETS,
+    NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS,
+};
+use firm_core::constants::packet::MIN_PACKET_SIZE;
+use firm_core::data_parser::SerialParser;
+use firm_core::firm_packets::{self, DeviceConfig, DeviceProtocol, FIRMData, FIRMResponse};
+use firm_core::framed_packet::{Framed, FramedPacket};
+use std::panic::{self, AssertUnwindSafe};
+use std::slice;
+
+/// Largest frame any command builder writes, so a buffer of this size always fits.
+pub const FIRM_MAX_COMMAND_FRAME_SIZE: usize = 106;
+
+const _: () =
+    assert!(FIRM_MAX_COMMAND_FRAME_SIZE == MIN_PACKET_SIZE + IMU_CALIBRATION_PAYLOAD_LENGTH);
+const _: () = assert!(FIRM_MAX_COMMAND_FRAME_SIZE <= MIN_PACKET_SIZE + MAX_COMMAND_PAYLOAD_LENGTH);
+
+/// Size of `FIRMResponsePacket::firmware_version`, including the NUL terminator.
+pub const FIRM_FIRMWARE_VERSION_SIZE: usize = 9;
+/// Size of `FIRMResponsePacket::device_name`, including the NUL terminator.
+pub const FIRM_DEVICE_NAME_SIZE: usize = 33;
+
+const _: () = assert!(FIRM_FIRMWARE_VERSION_SIZE == FIRMWARE_VERSION_LENGTH + 1);
+const _: () = assert!(FIRM_DEVICE_NAME_SIZE == DEVICE_NAME_LENGTH + 1);
+
+/// Result of every FFI call. Negative values are errors.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FIRMStatus {
+    /// The call succeeded.
+    Ok = 0,
+    /// The call succeeded, but there was nothing to return (e.g. no packet is ready).
+    Empty = 1,
+    /// A required pointer argument was null.
+    NullPointer = -1,
+    /// The output buffer is too small; `written` holds the size that is needed.
+    BufferTooSmall = -2,
+    /// An argument was out of range (e.g. an unknown protocol or a name that isn't UTF-8).
+    InvalidArgument = -3,
+    /// The library panicked. The parser it was called with should be freed and not reused.
+    Panic = -4,
+}
+
+/// One decoded data packet, laid out like `FIRMDataPacket` in the Python client: the device
+/// timestamp followed by every measurement as a 32-bit float, in the same order.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FIRMDataPacket {
+    pub timestamp_seconds: f64,
+
+    pub temperature_celsius: f32,
+    pub pressure_pascals: f32,
+
+    pub raw_acceleration_x_gs: f32,
+    pub raw_acceleration_y_gs: f32,
+    pub raw_acceleration_z_gs: f32,
+
+    pub raw_angular_rate_x_deg_per_s: f32,
+    pub raw_angular_rate_y_deg_per_s: f32,
+    pub raw_angular_rate_z_deg_per_s: f32,
+
+    pub magnetic_field_x_microteslas: f32,
+    pub magnetic_field_y_microteslas: f32,
+    pub magnetic_field_z_microteslas: f32,
+
+    pub est_position_x_meters: f32,
+    pub est_position_y_meters: f32,
+    pub est_position_z_meters: f32,
+
+    pub est_velocity_x_meters_per_s: f32,
+    pub est_velocity_y_meters_per_s: f32,
+    pub est_velocity_z_meters_per_s: f32,
+
+    pub est_acceleration_x_gs: f32,
+    pub est_acceleration_y_gs: f32,
+    pub est_acceleration_z_gs: f32,
+
+    pub est_angular_rate_x_rad_per_s: f32,
+    pub est_angular_rate_y_rad_per_s: f32,
+    pub est_angular_rate_z_rad_per_s: f32,
+
+    pub est_quaternion_w: f32,
+    pub est_quaternion_x: f32,
+    pub est_quaternion_y: f32,
+    pub est_quaternion_z: f32,
+
+    /// Not sent by the device: computed by the parser from `pressure_pascals` and the standard
+    /// sea level pressure.
+    pub pressure_altitude_meters: f32,
+}
+
+impl From<&FIRMData> for FIRMDataPacket {
+    fn from(data: &FIRMData) -> Self {
+        Self {
+            timestamp_seconds: data.timestamp_seconds,
+            temperature_celsius: data.temperature_celsius,
+            pressure_pascals: data.pressure_pascals,
+            raw_acceleration_x_gs: data.raw_acceleration_x_gs,
+            raw_acceleration_y_gs: data.raw_acceleration_y_gs,
+            raw_acceleration_z_gs: data.raw_acceleration_z_gs,
+            raw_angular_rate_x_deg_per_s: data.raw_angular_rate_x_deg_per_s,
+            raw_angular_rate_y_deg_per_s: data.raw_angular_rate_y_deg_per_s,
+            raw_angular_rate_z_deg_per_s: data.raw_angular_rate_z_deg_per_s,
+            magnetic_field_x_microteslas: data.magnetic_field_x_microteslas,
+            magnetic_field_y_microteslas: data.magnetic_field_y_microteslas,
+            magnetic_field_z_microteslas: data.magnetic_field_z_microteslas,
+            est_position_x_meters: data.est_position_x_meters,
+            est_position_y_meters: data.est_position_y_meters,
+            est_position_z_meters: data.est_position_z_meters,
+            est_velocity_x_meters_per_s: data.est_velocity_x_meters_per_s,
+            est_velocity_y_meters_per_s: data.est_velocity_y_meters_per_s,
+            est_velocity_z_meters_per_s: data.est_velocity_z_meters_per_s,
+            est_acceleration_x_gs: data.est_acceleration_x_gs,
+            est_acceleration_y_gs: data.est_acceleration_y_gs,
+            est_acceleration_z_gs: data.est_acceleration_z_gs,
+            est_angular_rate_x_rad_per_s: data.est_angular_rate_x_rad_per_s,
+            est_angular_rate_y_rad_per_s: data.est_angular_rate_y_rad_per_s,
+            est_angular_rate_z_rad_per_s: data.est_angular_rate_z_rad_per_s,
+            est_quaternion_w: data.est_quaternion_w,
+            est_quaternion_x: data.est_quaternion_x,
+            est_quaternion_y: data.est_quaternion_y,
+            est_quaternion_z: data.est_quaternion_z,
+            pressure_altitude_meters: data.pressure_altitude_meters,
+        }
+    }
+}
+
+/// One secondary barometer reading, see `FIRMBaroPacket` in `firm_core`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FIRMBaroPacket {
+    pub timestamp_seconds: f64,
+    pub pressure_pascals: f32,
+    pub temperature_celsius: f32,
+}
+
+impl From<&firm_packets::FIRMBaroPacket> for FIRMBaroPacket {
+    fn from(packet: &firm_packets::FIRMBaroPacket) -> Self {
+        Self {
+            timestamp_seconds: packet.timestamp_seconds,
+            pressure_pascals: packet.pressure_pascals,
+            temperature_celsius: packet.temperature_celsius,
+        }
+    }
+}
+
+/// The command a response answers, with the same values as the command identifiers on the
+/// wire.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FIRMCommandType {
+    GetDeviceInfo = 0x0001,
+    GetDeviceConfig = 0x0002,
+    SetDeviceConfig = 0x0003,
+    Reboot = 0x0004,
+    Mock = 0x0005,
+    SetMagnetometerCalibration = 0x0006,
+    SetIMUCalibration = 0x0007,
+    GetCalibration = 0x0008,
+    Cancel = 0x00FF,
+}
+
+impl From<FIRMCommand> for FIRMCommandType {
+    fn from(command: FIRMCommand) -> Self {
+        match command {
+            FIRMCommand::GetDeviceInfo => Self::GetDeviceInfo,
+            FIRMCommand::GetDeviceConfig => Self::GetDeviceConfig,
+            FIRMCommand::SetDeviceConfig => Self::SetDeviceConfig,
+            FIRMCommand::Reboot => Self::Reboot,
+            FIRMCommand::Mock => Self::Mock,
+            FIRMCommand::SetMagnetometerCalibration => Self::SetMagnetometerCalibration,
+            FIRMCommand::SetIMUCalibration => Self::SetIMUCalibration,
+            FIRMCommand::GetCalibration => Self::GetCalibration,
+            FIRMCommand::Cancel => Self::Cancel,
+        }
+    }
+}
+
+/// One decoded command response. `command_type` says which fields are set; the others are
+/// zero. Reboot has no decoded response, so it sets none of them.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FIRMResponsePacket {
+    pub command_type: FIRMCommandType,
+
+    /// SetDeviceConfig, SetMagnetometerCalibration, SetIMUCalibration, Mock and Cancel:
+    /// whether the device accepted the command.
+    pub success: bool,
+
+    /// GetDeviceInfo: the device's unique ID.
+    pub device_id: u64,
+    /// GetDeviceInfo: the firmware version, NUL-terminated.
+    pub firmware_version: [c_char; FIRM_FIRMWARE_VERSION_SIZE],
+
+    /// GetDeviceConfig: the device name, NUL-terminated.
+    pub device_name: [c_char; FIRM_DEVICE_NAME_SIZE],
+    /// GetDeviceConfig: data packet rate in Hz.
+    pub frequency: u16,
+    /// GetDeviceConfig: 1 = USB, 2 = UART, 3 = I2C, 4 = SPI.
+    pub protocol: u8,
+    /// GetDeviceConfig: set when the response broke the protocol, in which case the other
+    /// config fields are the decoder's best guess.
+    pub suspect: bool,
+
+    /// GetCalibration: the calibration values, matrices row-major.
+    pub imu_accelerometer_offsets: [f32; NUMBER_OF_CALIBRATION_OFFSETS],
+    pub imu_accelerometer_scale_matrix: [f32; NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS],
+    pub imu_gyroscope_offsets: [f32; NUMBER_OF_CALIBRATION_OFFSETS],
+    pub imu_gyroscope_scale_matrix: [f32; NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS],
+    pub magnetometer_offsets: [f32; NUMBER_OF_CALIBRATION_OFFSETS],
+    pub magnetometer_scale_matrix: [f32; NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS],
+}
+
+impl FIRMResponsePacket {
+    fn new(command: FIRMCommand, response: &FIRMResponse) -> Self {
+        let mut packet = Self {
+            command_type: command.into(),
+            success: false,
+            device_id: 0,
+            firmware_version: [0; FIRM_FIRMWARE_VERSION_SIZE],
+            device_name: [0; FIRM_DEVICE_NAME_SIZE],
+            frequency: 0,
+            protocol: 0,
+            suspect: false,
+            imu_accelerometer_offsets: [0.0; NUMBER_OF_CALIBRATION_OFFSETS],
+            imu_accelerometer_scale_matrix: [0.0; NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS],
+            imu_gyroscope_offsets: [0.0; NUMBER_OF_CALIBRATION_OFFSETS],
+            imu_gyroscope_scale_matrix: [0.0; NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS],
+            magnetometer_offsets: [0.0; NUMBER_OF_CALIBRATION_OFFSETS],
+            magnetometer_scale_matrix: [0.0; NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS],
+        };
+        match response {
+            FIRMResponse::GetDeviceInfo(info) => {
+                packet.device_id = info.id;
+                packet.firmware_version = c_string(&info.firmware_version);
+            }
+            FIRMResponse::GetDeviceConfig(config) => {
+                packet.device_name = c_string(&config.name);
+                packet.frequency = config.frequency;
+                packet.protocol = config.protocol as u8;
+                packet.suspect = config.suspect;
+            }
+            FIRMResponse::SetDeviceConfig(success)
+            | FIRMResponse::SetMagnetometerCalibration(success)
+            | FIRMResponse::SetIMUCalibration(success)
+            | FIRMResponse::Mock(success)
+            | FIRMResponse::Cancel(success) => packet.success = *success,
+            FIRMResponse::GetCalibration(calibration) => {
+                packet.imu_accelerometer_offsets = calibration.imu_accelerometer_offsets;
+                packet.imu_accelerometer_scale_matrix = calibration.imu_accelerometer_scale_matrix;
+                packet.imu_gyroscope_offsets = calibration.imu_gyroscope_offsets;
+                packet.imu_gyroscope_scale_matrix = calibration.imu_gyroscope_scale_matrix;
+                packet.magnetometer_offsets = calibration.magnetometer_offsets;
+                packet.magnetometer_scale_matrix = calibration.magnetometer_scale_matrix;
+            }
+            FIRMResponse::Error(_) => {}
+        }
+        packet
+    }
+}
+
+/// Copies `s` into a NUL-terminated C array, cut at the last whole character that fits.
+fn c_string<const N: usize>(s: &str) -> [c_char; N] {
+    let mut end = s.len().min(N - 1);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    let mut out = [0; N];
+    for (out, &byte) in out.iter_mut().zip(&s.as_bytes()[..end]) {
+        *out = byte as c_char;
+    }
+    out
+}
+
+/// An incremental parser for the byte stream coming from a FIRM device. Opaque to C.
+pub struct FIRMParser {
+    inner: SerialParser,
+    /// A raw frame taken off the queue that didn't fit the caller's buffer, handed out first
+    /// by the next `firm_parser_next_raw_frame`.
+    pending_raw_frame: Option<FramedPacket>,
+}
+
+/// Runs `f`, turning a panic into `FIRMStatus::Panic` so it never unwinds into C.
+fn guard(f: impl FnOnce() -> FIRMStatus) -> FIRMStatus {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(FIRMStatus::Panic)
+}
+
+/// Creates a parser.
+///
+/// # Returns
+///
+/// - `*mut FIRMParser` - The new parser, to be released with `firm_parser_free`, or null if
+///   it couldn't be created.
+#[unsafe(no_mangle)]
+pub extern "C" fn firm_parser_new() -> *mut FIRMParser {
+    panic::catch_unwind(|| {
+        Box::into_raw(Box::new(FIRMParser {
+            inner: SerialParser::new(),
+            pending_raw_frame: None,
+        }))
+    })
+    .unwrap_or(core::ptr::null_mut())
+}
+
+/// Frees a parser created by `firm_parser_new`. Null is ignored.
+///
+/// # Safety
+///
+/// `parser` must be null or a pointer returned by `firm_parser_new` that hasn't been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn firm_parser_free(parser: *mut FIRMParser) {
+    if !parser.is_null() {
+        // SAFETY: the caller guarantees `parser` came from `firm_parser_new` and is freed once.
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| drop(unsafe { Box::from_raw(parser) })));
+    }
+}
+
+/// Feeds bytes read from the device into the parser. Frames may be split across calls.
+/// Data packets, barometer readings, command responses and frames the parser couldn't decode
+/// each go to their own queue, read with the matching `firm_parser_next_*` function. A queue
+/// that isn't read stops growing once it's full and drops its oldest entries.
+///
+/// # Arguments
+///
+/// - `parser` (`*mut FIRMParser`) - The parser.
+/// - `bytes` (`*const u8`) - The bytes, may be null if `len` is 0.
+/// - `len` (`usize`) - Number of bytes.
+///
+/// # Safety
+///
+/// `parser` must be a live parser and `bytes` must be valid for reads of `len` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn firm_parser_feed(
+    parser: *mut FIRMParser,
+    bytes: *const u8,
+    len: usize,
+) -> FIRMStatus {
+    guard(|| {
+        // SAFETY: the caller guarantees `parser` is live and not used concurrently.
+        let Some(parser) = (unsafe { parser.as_mut() }) else {
+            return FIRMStatus::NullPointer;
+        };
+        if len == 0 {
+            return FIRMStatus::Ok;
+        }
+        if bytes.is_null() {
+            return FIRMStatus::NullPointer;
+        }
+        // SAFETY: the caller guarantees `bytes` is valid for `len` bytes.
+        let bytes = unsafe { slice::from_raw_parts(bytes, len) };
+        parser.inner.parse_bytes(bytes);
+        FIRMStatus::Ok
+    })
+}
+
+/// Takes the oldest parsed data packet.
+///
+/// # Arguments
+///
+/// - `parser` (`*mut FIRMParser`) - The parser.
+/// - `out` (`*mut FIRMDataPacket`) - Where to write the packet.
+///
+/// # Returns
+///
+/// - `FIRMStatus` - `Ok` if a packet was written to `out`, `Empty` if none is ready.
+///
+/// # Safety
+///
+/// `parser` must be a live parser and `out` must be valid for writing one `FIRMDataPacket`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn firm_parser_next_data_packet(
+    parser: *mut FIRMParser,
+    out: *mut FIRMDataPacket,
+) -> FIRMStatus {
+    guard(|| {
+        // SAFETY: the caller guarantees `parser` is live and not used concurrently.
+        let Some(parser) = (unsafe { parser.as_mut() }) else {
+            return FIRMStatus::NullPointer;
+        };
+        if out.is_null() {
+            return FIRMStatus::NullPointer;
+        }
+        match parser.inner.get_data_packet() {
+            Some(packet) => {
+                // SAFETY: the caller guarantees `out` is valid for writes.
+                unsafe { out.write(FIRMDataPacket::from(packet.data())) };
+                FIRMStatus::Ok
+            }
+            None => FIRMStatus::Empty,
+        }
+    })
+}
+
+/// Takes the oldest parsed secondary barometer reading.
+///
+/// # Arguments
+///
+/// - `parser` (`*mut FIRMParser`) - The parser.
+/// - `out` (`*mut FIRMBaroPacket`) - Where to write the reading.
+///
+/// # Returns
+///
+/// - `FIRMStatus` - `Ok` if a reading was written to `out`, `Empty` if none is ready.
+///
+/// # Safety
+///
+/// `parser` must be a live parser and `out` must be valid for writing one `FIRMBaroPacket`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn firm_parser_next_baro_packet(
+    parser: *mut FIRMParser,
+    out: *mut FIRMBaroPacket,
+) -> FIRMStatus {
+    guard(|| {
+        // SAFETY: the caller guarantees `parser` is live and not used concurrently.
+        let Some(parser) = (unsafe { parser.as_mut() }) else {
+            return FIRMStatus::NullPointer;
+        };
+        if out.is_null() {
+            return FIRMStatus::NullPointer;
+        }
+        match parser.inner.get_baro_packet() {
+            Some(packet) => {
+                // SAFETY: the caller guarantees `out` is valid for writes.
+                unsafe { out.write(FIRMBaroPacket::from(&packet)) };
+                FIRMStatus::Ok
+            }
+            None => FIRMStatus::Empty,
+        }
+    })
+}
+
+/// Takes the oldest parsed command response.
+///
+/// # Arguments
+///
+/// - `parser` (`*mut FIRMParser`) - The parser.
+/// - `out` (`*mut FIRMResponsePacket`) - Where to write the response.
+///
+/// # Returns
+///
+/// - `FIRMStatus` - `Ok` if a response was written to `out`, `Empty` if none is ready.
+///
+/// # Safety
+///
+/// `parser` must be a live parser and `out` must be valid for writing one
+/// `FIRMResponsePacket`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn firm_parser_next_response(
+    parser: *mut FIRMParser,
+    out: *mut FIRMResponsePacket,
+) -> FIRMStatus {
+    guard(|| {
+        // SAFETY: the caller guarantees `parser` is live and not used concurrently.
+        let Some(parser) = (unsafe { parser.as_mut() }) else {
+            return FIRMStatus::NullPointer;
+        };
+        if out.is_null() {
+            return FIRMStatus::NullPointer;
+        }
+        match parser.inner.get_response_packet() {
+            Some(packet) => {
+                let response = FIRMResponsePacket::new(packet.command_type(), packet.response());
+                // SAFETY: the caller guarantees `out` is valid for writes.
+                unsafe { out.write(response) };
+                FIRMStatus::Ok
+            }
+            None => FIRMStatus::Empty,
+        }
+    })
+}
+
+/// Takes the oldest CRC-valid frame the parser couldn't decode, such as a response to a
+/// command this library doesn't know yet, and copies its bytes into `out`.
+///
+/// # Arguments
+///
+/// - `parser` (`*mut FIRMParser`) - The parser.
+/// - `out` (`*mut u8`) - Where to write the frame.
+/// - `capacity` (`usize`) - Size of `out` in bytes.
+/// - `written` (`*mut usize`) - Where to write the frame length.
+///
+/// # Returns
+///
+/// - `FIRMStatus` - `Ok` if a frame was written to `out`, `Empty` if none is ready, or
+///   `BufferTooSmall` with the length in `written`, in which case the frame stays queued.
+///
+/// # Safety
+///
+/// `parser` must be a live parser, `out` must be valid for writes of `capacity` bytes and
+/// `written` for one `usize`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn firm_parser_next_raw_frame(
+    parser: *mut FIRMParser,
+    out: *mut u8,
+    capacity: usize,
+    written: *mut usize,
+) -> FIRMStatus {
+    guard(|| {
+        // SAFETY: the caller guarantees `parser` is live and not used concurrently.
+        let Some(parser) = (unsafe { parser.as_mut() }) else {
+            return FIRMStatus::NullPointer;
+        };
+        if parser.pending_raw_frame.is_none() {
+            parser.pending_raw_frame = parser.inner.get_raw_frame();
+        }
+        let Some(frame) = &parser.pending_raw_frame else {
+            return FIRMStatus::Empty;
+        };
+        // SAFETY: forwarded from the caller.
+        let status = unsafe { write_bytes(&frame.to_bytes(), out, capacity, written) };
+        if status == FIRMStatus::Ok {
+            parser.pending_raw_frame = None;
+        }
+        status
+    })
+}
+
+/// Copies a built command frame into the caller's buffer, or reports the size it needs.
+///
+/// # Safety
+///
+/// `out` must be valid for writes of `capacity` bytes and `written` for one `usize`.
+unsafe fn write_command(
+    command: FIRMCommandPacket,
+    out: *mut u8,
+    capacity: usize,
+    written: *mut usize,
+) -> FIRMStatus {
+    // SAFETY: forwarded from the caller.
+    unsafe { write_bytes(&command.to_bytes(), out, capacity, written) }
+}
+
+/// Copies `bytes` into the caller's buffer, or reports the size it needs.
+///
+/// # Safety
+///
+/// `out` must be valid for writes of `capacity` bytes and `written` for one `usize`.
+unsafe fn write_bytes(
+    bytes: &[u8],
+    out: *mut u8,
+    capacity: usize,
+    written: *mut usize,
+) -> FIRMStatus {
+    if written.is_null() {
+        return FIRMStatus::NullPointer;
+    }
+    // SAFETY: the caller guarantees `written` is valid for writes.
+    unsafe { written.write(bytes.len()) };
+    if bytes.len() > capacity {
+        return FIRMStatus::BufferTooSmall;
+    }
+    if out.is_null() {
+        return FIRMStatus::NullPointer;
+    }
+    // SAFETY: the caller guarantees `out` holds `capacity` bytes, which fits the frame.
+    unsafe { core::ptr::copy_nonoverlapping(bytes.as_ptr(), out, bytes.len()) };
+    FIRMStatus::Ok
+}
+
+/// Reads a fixed number of floats from a caller's array.
+///
+/// # Safety
+///
+/// `values` must be null or valid for reads of `N` floats.
+unsafe fn read_floats<const N: usize>(values: *const f32) -> Option<[f32; N]> {
+    if values.is_null() {
+        return None;
+    }
+    // SAFETY: the caller guarantees `values` holds `N` floats.
+    let values = unsafe { slice::from_raw_parts(values, N) };
+    values.try_into().ok()
+}
+
+/// Defines a builder for a command without arguments.
+macro_rules! simple_command {
+    ($(#[$doc:meta])* $name:ident => $build:ident) => {
+        $(#[$doc])*
+        ///
+        /// # Safety
+        ///
+        /// `out` must be valid for writes of `capacity` bytes and `written` for one `usize`.
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn $name(
+            out: *mut u8,
+            capacity: usize,
+            written: *mut usize,
+        ) -> FIRMStatus {
+            // SAFETY: forwarded from the caller.
+            guard(|| unsafe { write_command(FIRMCommandPacket::$build(), out, capacity, written) })
+        }
+    };
+}
+
+simple_command!(
+    /// Writes a GetDeviceInfo command frame into `out` and its length into `written`.
+    firm_build_get_device_info_command => build_get_device_info_command
+);
+simple_command!(
+    /// Writes a GetDeviceConfig command frame into `out` and its length into `written`.
+    firm_build_get_device_config_command => build_get_device_config_command
+);
+simple_command!(
+    /// Writes a GetCalibration command frame into `out` and its length into `written`.
+    firm_build_get_calibration_command => build_get_calibration_command
+);
+simple_command!(
+    /// Writes a Cancel command frame into `out` and its length into `written`.
+    firm_build_cancel_command => build_cancel_command
+);
+simple_command!(
+    /// Writes a Reboot command frame into `out` and its length into `written`.
+    firm_build_reboot_command => build_reboot_command
+);
+simple_command!(
+    /// Writes a Mock command frame into `out` and its length into `written`.
+    firm_build_mock_command => build_mock_command
+);
+
+/// Writes a SetDeviceConfig command frame into `out` and its length into `written`.
+///
+/// # Arguments
+///
+/// - `name` (`*const c_char`) - NUL-terminated UTF-8 device name, truncated to 32 bytes.
+/// - `frequency` (`u16`) - Data packet rate in Hz.
+/// - `protocol` (`u8`) - 1 = USB, 2 = UART, 3 = I2C, 4 = SPI.
+///
+/// # Safety
+///
+/// `name` must be a valid NUL-terminated string, `out` valid for writes of `capacity` bytes
+/// and `written` for one `usize`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn firm_build_set_device_config_command(
+    name: *const c_char,
+    frequency: u16,
+    protocol: u8,
+    out: *mut u8,
+    capacity: usize,
+    written: *mut usize,
+) -> FIRMStatus {
+    guard(|| {
+        if name.is_null() {
+            return FIRMStatus::NullPointer;
+        }
+        // SAFETY: the caller guarantees `name` is NUL-terminated.
+        let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() else {
+            return FIRMStatus::InvalidArgument;
+        };
+        let protocol = match protocol {
+            1 => DeviceProtocol::USB,
+            2 => DeviceProtocol::UART,
+            3 => DeviceProtocol::I2C,
+            4 => DeviceProtocol::SPI,
+            _ => return FIRMStatus::InvalidArgument,
+        };
+        let config = DeviceConfig {
+            name: name.to_string(),
+            frequency,
+            protocol,
+            suspect: false,
+        };
+        let command = FIRMCommandPacket::build_set_device_config_command(config);
+        // SAFETY: forwarded from the caller.
+        unsafe { write_command(command, out, capacity, written) }
+    })
+}
+
+/// Writes a SetMagnetometerCalibration command frame into `out` and its length into `written`.
+///
+/// # Arguments
+///
+/// - `offsets` (`*const f32`) - 3 hard-iron offsets.
+/// - `scale_matrix` (`*const f32`) - 9 soft-iron matrix elements, row-major.
+///
+/// # Safety
+///
+/// `offsets` and `scale_matrix` must hold 3 and 9 floats, `out` must be valid for writes of
+/// `capacity` bytes and `written` for one `usize`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn firm_build_set_magnetometer_calibration_command(
+    offsets: *const f32,
+    scale_matrix: *const f32,
+    out: *mut u8,
+    capacity: usize,
+    written: *mut usize,
+) -> FIRMStatus {
+    guard(|| {
+        // SAFETY: the caller guarantees the array lengths.
+        let arrays = unsafe {
+            (
+                read_floats::<NUMBER_OF_CALIBRATION_OFFSETS>(offsets),
+                read_floats::<NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS>(scale_matrix),
+            )
+        };
+        let (Some(offsets), Some(scale_matrix)) = arrays else {
+            return FIRMStatus::NullPointer;
+        };
+        let command =
+            FIRMCommandPacket::build_set_magnetometer_calibration_command(offsets, scale_matrix);
+        // SAFETY: forwarded from the caller.
+        unsafe { write_command(command, out, capacity, written) }
+    })
+}
+
+/// Writes a SetIMUCalibration command frame into `out` and its length into `written`.
+///
+/// # Arguments
+///
+/// - `accel_offsets` (`*const f32`) - 3 accelerometer offsets.
+/// - `accel_scale_matrix` (`*const f32`) - 9 accelerometer matrix elements, row-major.
+/// - `gyro_offsets` (`*const f32`) - 3 gyroscope offsets.
+/// - `gyro_scale_matrix` (`*const f32`) - 9 gyroscope matrix elements, row-major.
+///
+/// # Safety
+///
+/// The offset arrays must hold 3 floats and the matrices 9, `out` must be valid for writes
+/// of `capacity` bytes and `written` for one `usize`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn firm_build_set_imu_calibration_command(
+    accel_offsets: *const f32,
+    accel_scale_matrix: *const f32,
+    gyro_offsets: *const f32,
+    gyro_scale_matrix: *const f32,
+    out: *mut u8,
+    capacity: usize,
+    written: *mut usize,
+) -> FIRMStatus {
+    guard(|| {
+        // SAFETY: the caller guarantees the array lengths.
+        let arrays = unsafe {
+            (
+                read_floats::<NUMBER_OF_CALIBRATION_OFFSETS>(accel_offsets),
+                read_floats::<NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS>(accel_scale_matrix),
+                read_floats::<NUMBER_OF_CALIBRATION_OFFSETS>(gyro_offsets),
+                read_floats::<NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS>(gyro_scale_matrix),
+            )
+        };
+        let (
+            Some(accel_offsets),
+            Some(accel_scale_matrix),
+            Some(gyro_offsets),
+            Some(gyro_scale_matrix),
+        ) = arrays
+        else {
+            return FIRMStatus::NullPointer;
+        };
+        let command = FIRMCommandPacket::build_set_imu_calibration_command(
+            accel_offsets,
+            accel_scale_matrix,
+            gyro_offsets,
+            gyro_scale_matrix,
+        );
+        // SAFETY: forwarded from the caller.
+        unsafe { write_command(command, out, capacity, written) }
+    })
+}
@@ -0,0 +1,314 @@
+//! Calls the library only through its exported C symbols, the way a C or C++ caller would.
+use core::ffi::c_char;
+use firm_core::altitude::{STANDARD_SEA_LEVEL_PRESSURE_PASCALS, pressure_altitude_meters};
+use firm_core::constants::packet::PacketHeader;
+use firm_core::firm_packets::FIRMBaroPacket as BaroReading;
+use firm_core::framed_packet::FramedPacket;
+use firm_ffi::{
+    FIRM_MAX_COMMAND_FRAME_SIZE, FIRMBaroPacket, FIRMCommandType, FIRMDataPacket,
+    FIRMResponsePacket, FIRMStatus,
+};
+use std::mem::MaybeUninit;
+use std::ptr;
+
+/// The parser as C sees it: an opaque type only ever used behind a pointer.
+#[repr(C)]
+struct FIRMParser {
+    _private: [u8; 0],
+}
+
+unsafe extern "C" {
+    fn firm_parser_new() -> *mut FIRMParser;
+    fn firm_parser_free(parser: *mut FIRMParser);
+    fn firm_parser_feed(parser: *mut FIRMParser, bytes: *const u8, len: usize) -> FIRMStatus;
+    fn firm_parser_next_data_packet(
+        parser: *mut FIRMParser,
+        out: *mut FIRMDataPacket,
+    ) -> FIRMStatus;
+    fn firm_parser_next_baro_packet(
+        parser: *mut FIRMParser,
+        out: *mut FIRMBaroPacket,
+    ) -> FIRMStatus;
+    fn firm_parser_next_response(
+        parser: *mut FIRMParser,
+        out: *mut FIRMResponsePacket,
+    ) -> FIRMStatus;
+    fn firm_parser_next_raw_frame(
+        parser: *mut FIRMParser,
+        out: *mut u8,
+        capacity: usize,
+        written: *mut usize,
+    ) -> FIRMStatus;
+    fn firm_build_cancel_command(out: *mut u8, capacity: usize, written: *mut usize) -> FIRMStatus;
+    fn firm_build_get_device_info_command(
+        out: *mut u8,
+        capacity: usize,
+        written: *mut usize,
+    ) -> FIRMStatus;
+    fn firm_build_set_device_config_command(
+        name: *const c_char,
+        frequency: u16,
+        protocol: u8,
+        out: *mut u8,
+        capacity: usize,
+        written: *mut usize,
+    ) -> FIRMStatus;
+    fn firm_build_set_imu_calibration_command(
+        accel_offsets: *const f32,
+        accel_scale_matrix: *const f32,
+        gyro_offsets: *const f32,
+        gyro_scale_matrix: *const f32,
+        out: *mut u8,
+        capacity: usize,
+        written: *mut usize,
+    ) -> FIRMStatus;
+}
+
+/// Line noise, a data packet at 1.5 s, a packet with a corrupted payload and a packet at
+/// 2.5 s. Field `i` (0-based, after the timestamp) of each packet is `(i + 1) * 0.5 + t`.
+const DATA_STREAM: &[u8] = include_bytes!("fixtures/data_stream.bin");
+
+const CANCEL_FRAME: [u8; 10] = [0x6B, 0xB6, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x00, 0xDD, 0x81];
+const GET_DEVICE_INFO_FRAME: [u8; 10] =
+    [0x6B, 0xB6, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0xDC, 0x78];
+
+fn next_packet(parser: *mut FIRMParser) -> Option<FIRMDataPacket> {
+    let mut packet = MaybeUninit::<FIRMDataPacket>::uninit();
+    match unsafe { firm_parser_next_data_packet(parser, packet.as_mut_ptr()) } {
+        FIRMStatus::Ok => Some(unsafe { packet.assume_init() }),
+        FIRMStatus::Empty => None,
+        status => panic!("unexpected status {status:?}"),
+    }
+}
+
+#[test]
+fn test_parser_decodes_fixture_fed_in_small_chunks() {
+    let parser = unsafe { firm_parser_new() };
+    assert!(!parser.is_null());
+
+    for chunk in DATA_STREAM.chunks(7) {
+        let status = unsafe { firm_parser_feed(parser, chunk.as_ptr(), chunk.len()) };
+        assert_eq!(status, FIRMStatus::Ok);
+    }
+
+    let first = next_packet(parser).unwrap();
+    assert_eq!(first.timestamp_seconds, 1.5);
+    assert_eq!(first.temperature_celsius, 2.0);
+    assert_eq!(first.pressure_pascals, 2.5);
+    assert_eq!(first.est_quaternion_z, 15.0);
+    assert_eq!(
+        first.pressure_altitude_meters,
+        pressure_altitude_meters(2.5, STANDARD_SEA_LEVEL_PRESSURE_PASCALS)
+    );
+
+    // The corrupted packet is dropped.
+    let second = next_packet(parser).unwrap();
+    assert_eq!(second.timestamp_seconds, 2.5);
+    assert_eq!(second.raw_acceleration_x_gs, 4.0);
+    assert!(next_packet(parser).is_none());
+
+    unsafe { firm_parser_free(parser) };
+}
+
+#[test]
+fn test_parser_keeps_baro_packets_responses_and_raw_frames() {
+    let baro = BaroReading {
+        timestamp_seconds: 1.25,
+        pressure_pascals: 101_000.0,
+        temperature_celsius: 21.5,
+    };
+    let mut config = b"FIRM".to_vec();
+    config.extend_from_slice(&[0; 28]);
+    config.extend_from_slice(&[0x64, 0x00, 0x02]);
+    let unknown = FramedPacket::new(PacketHeader::Response, 0x0042, vec![1, 2, 3]).to_bytes();
+
+    let mut bytes = baro.to_frame().to_bytes();
+    bytes.extend(FramedPacket::new(PacketHeader::Response, 0x0002, config).to_bytes());
+    bytes.extend(FramedPacket::new(PacketHeader::Response, 0x00FF, vec![1]).to_bytes());
+    bytes.extend(&unknown);
+
+    let parser = unsafe { firm_parser_new() };
+    let status = unsafe { firm_parser_feed(parser, bytes.as_ptr(), bytes.len()) };
+    assert_eq!(status, FIRMStatus::Ok);
+    assert!(next_packet(parser).is_none());
+
+    let mut packet = MaybeUninit::<FIRMBaroPacket>::uninit();
+    let status = unsafe { firm_parser_next_baro_packet(parser, packet.as_mut_ptr()) };
+    assert_eq!(status, FIRMStatus::Ok);
+    let packet = unsafe { packet.assume_init() };
+    assert_eq!(packet.timestamp_seconds, 1.25);
+    assert_eq!(packet.pressure_pascals, 101_000.0);
+    assert_eq!(packet.temperature_celsius, 21.5);
+
+    let mut response = MaybeUninit::<FIRMResponsePacket>::uninit();
+    let status = unsafe { firm_parser_next_response(parser, response.as_mut_ptr()) };
+    assert_eq!(status, FIRMStatus::Ok);
+    let response = unsafe { response.assume_init() };
+    assert_eq!(response.command_type, FIRMCommandType::GetDeviceConfig);
+    let name = unsafe { core::ffi::CStr::from_ptr(response.device_name.as_ptr()) };
+    assert_eq!(name, c"FIRM");
+    assert_eq!(response.frequency, 100);
+    assert_eq!(response.protocol, 2);
+    assert!(!response.suspect);
+
+    let mut response = MaybeUninit::<FIRMResponsePacket>::uninit();
+    let status = unsafe { firm_parser_next_response(parser, response.as_mut_ptr()) };
+    assert_eq!(status, FIRMStatus::Ok);
+    let response = unsafe { response.assume_init() };
+    assert_eq!(response.command_type, FIRMCommandType::Cancel);
+    assert!(response.success);
+    let mut response = MaybeUninit::<FIRMResponsePacket>::uninit();
+    let status = unsafe { firm_parser_next_response(parser, response.as_mut_ptr()) };
+    assert_eq!(status, FIRMStatus::Empty);
+
+    // A buffer that's too small leaves the frame queued for the next call.
+    let mut buffer = [0u8; 64];
+    let mut written = 0usize;
+    let status =
+        unsafe { firm_parser_next_raw_frame(parser, buffer.as_mut_ptr(), 4, &mut written) };
+    assert_eq!(status, FIRMStatus::BufferTooSmall);
+    assert_eq!(written, unknown.len());
+    let status = unsafe {
+        firm_parser_next_raw_frame(parser, buffer.as_mut_ptr(), buffer.len(), &mut written)
+    };
+    assert_eq!(status, FIRMStatus::Ok);
+    assert_eq!(&buffer[..written], unknown);
+    let status = unsafe {
+        firm_parser_next_raw_frame(parser, buffer.as_mut_ptr(), buffer.len(), &mut written)
+    };
+    assert_eq!(status, FIRMStatus::Empty);
+
+    unsafe { firm_parser_free(parser) };
+}
+
+#[test]
+fn test_null_arguments_are_rejected() {
+    let mut packet = MaybeUninit::<FIRMDataPacket>::uninit();
+    unsafe {
+        assert_eq!(
+            firm_parser_feed(ptr::null_mut(), DATA_STREAM.as_ptr(), DATA_STREAM.len()),
+            FIRMStatus::NullPointer
+        );
+        assert_eq!(
+            firm_parser_next_data_packet(ptr::null_mut(), packet.as_mut_ptr()),
+            FIRMStatus::NullPointer
+        );
+
+        let parser = firm_parser_new();
+        assert_eq!(
+            firm_parser_feed(parser, ptr::null(), 4),
+            FIRMStatus::NullPointer
+        );
+        assert_eq!(firm_parser_feed(parser, ptr::null(), 0), FIRMStatus::Ok);
+        assert_eq!(
+            firm_parser_next_data_packet(parser, ptr::null_mut()),
+            FIRMStatus::NullPointer
+        );
+        firm_parser_free(parser);
+        firm_parser_free(ptr::null_mut());
+    }
+}
+
+#[test]
+fn test_command_builders_write_golden_frames() {
+    let mut buffer = [0u8; FIRM_MAX_COMMAND_FRAME_SIZE];
+    let mut written = 0usize;
+
+    let status =
+        unsafe { firm_build_cancel_command(buffer.as_mut_ptr(), buffer.len(), &mut written) };
+    assert_eq!(status, FIRMStatus::Ok);
+    assert_eq!(&buffer[..written], CANCEL_FRAME);
+
+    let status = unsafe {
+        firm_build_get_device_info_command(buffer.as_mut_ptr(), buffer.len(), &mut written)
+    };
+    assert_eq!(status, FIRMStatus::Ok);
+    assert_eq!(&buffer[..written], GET_DEVICE_INFO_FRAME);
+
+    let status = unsafe {
+        firm_build_set_device_config_command(
+            c"FIRM".as_ptr(),
+            100,
+            2,
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            &mut written,
+        )
+    };
+    assert_eq!(status, FIRMStatus::Ok);
+    let mut expected = vec![0x6B, 0xB6, 0x03, 0x00, 0x23, 0x00, 0x00, 0x00];
+    expected.extend_from_slice(b"FIRM");
+    expected.extend_from_slice(&[0; 28]);
+    expected.extend_from_slice(&[0x64, 0x00, 0x02, 0x83, 0xB6]);
+    assert_eq!(&buffer[..written], expected);
+}
+
+#[test]
+fn test_command_builders_report_required_size() {
+    let mut buffer = [0u8; 4];
+    let mut written = 0usize;
+    let status =
+        unsafe { firm_build_cancel_command(buffer.as_mut_ptr(), buffer.len(), &mut written) };
+    assert_eq!(status, FIRMStatus::BufferTooSmall);
+    assert_eq!(written, CANCEL_FRAME.len());
+    assert_eq!(buffer, [0; 4]);
+
+    // The largest command still fits the documented maximum.
+    let offsets = [0.0f32; 3];
+    let matrix = [1.0f32, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+    let mut buffer = [0u8; FIRM_MAX_COMMAND_FRAME_SIZE];
+    let status = unsafe {
+        firm_build_set_imu_calibration_command(
+            offsets.as_ptr(),
+            matrix.as_ptr(),
+            offsets.as_ptr(),
+            matrix.as_ptr(),
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            &mut written,
+        )
+    };
+    assert_eq!(status, FIRMStatus::Ok);
+    assert_eq!(written, FIRM_MAX_COMMAND_FRAME_SIZE);
+}
+
+#[test]
+fn test_invalid_device_config_arguments() {
+    let mut buffer = [0u8; FIRM_MAX_COMMAND_FRAME_SIZE];
+    let mut written = 0usize;
+    unsafe {
+        assert_eq!(
+            firm_build_set_device_config_command(
+                c"FIRM".as_ptr(),
+                100,
+                9,
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                &mut written,
+            ),
+            FIRMStatus::InvalidArgument
+        );
+        assert_eq!(
+            firm_build_set_device_config_command(
+                c"\xFF\xFE".as_ptr(),
+                100,
+                1,
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                &mut written,
+            ),
+            FIRMStatus::InvalidArgument
+        );
+        assert_eq!(
+            firm_build_set_device_config_command(
+                ptr::null(),
+                100,
+                1,
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                &mut written,
+            ),
+            FIRMStatus::NullPointer
+        );
+    }
+}
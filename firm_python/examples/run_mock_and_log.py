@@ -4,7 +4,7 @@ import time
 from pathlib import Path
 from typing import Any, Dict, List, Optional
 
-from firm_client import FIRMClient
+from firm_client import FIRMClient, NonFinitePolicy
 
 
 # uv run  .\firm_python\examples\run_mock_and_log.py --out output.csv COM12 "C:\Users\jackg\Downloads\LOG1.TXT"
@@ -15,6 +15,7 @@ REALTIME_DEFAULT = True
 SPEED_DEFAULT = 1.0
 CHUNK_SIZE_DEFAULT = 80_000
 DRAIN_SECONDS_DEFAULT = 1.0
+INF_SENTINEL_DEFAULT = NonFinitePolicy().inf_sentinel
 
 
 FIELDS: List[str] = [
@@ -49,8 +50,10 @@ FIELDS: List[str] = [
 ]
 
 
-def _row_from_packet(pkt: Any) -> Dict[str, Any]:
-    return {f: getattr(pkt, f) for f in FIELDS}
+def _row_from_packet(pkt: Any, policy: NonFinitePolicy) -> Dict[str, Any]:
+    """NaN becomes an empty cell and +/-inf the policy's sentinel, the same as the CSV files
+    the Rust tools write."""
+    return {f: policy.csv_cell(getattr(pkt, f)) for f in FIELDS}
 
 
 def main() -> int:
@@ -105,9 +108,15 @@ def main() -> int:
         default=DRAIN_SECONDS_DEFAULT,
         help=f"How long to keep logging after stream ends (default: {DRAIN_SECONDS_DEFAULT})",
     )
+    parser.add_argument(
+        "--inf-sentinel",
+        default=INF_SENTINEL_DEFAULT,
+        help=f"Value written for infinite readings; NaN is written as an empty cell (default: {INF_SENTINEL_DEFAULT})",
+    )
     args = parser.parse_args()
 
     out_path = Path(args.out)
+    policy = NonFinitePolicy(args.inf_sentinel)
     out_path.parent.mkdir(parents=True, exist_ok=True)
 
     total_rows = 0
@@ -142,7 +151,7 @@ def main() -> int:
                     # Read device output packets
                     packets = client.get_data_packets(block=False)
                     for pkt in packets:
-                        writer.writerow(_row_from_packet(pkt))
+                        writer.writerow(_row_from_packet(pkt, policy))
                         total_rows += 1
 
                     # Detect stream end
@@ -1,15 +1,24 @@
-use firm_core::constants::command::{
-    NUMBER_OF_CALIBRATION_OFFSETS, NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS,
-};
+use firm_core::client_packets::calibration_array;
 use firm_core::constants::packet::PacketHeader;
+use firm_core::csv::{CsvImportError, CsvImportOptions};
+use firm_core::error_codes::ErrorCoded;
 use firm_core::firm_packets::{
     CalibrationValues, DeviceConfig, DeviceInfo, DeviceProtocol, FIRMData,
 };
 use firm_core::framed_packet::FramedPacket;
-use firm_rust::FIRMClient as RustFirmClient;
+use firm_core::non_finite::{DEFAULT_INF_SENTINEL, NonFinitePolicy};
+use firm_rust::alarms::{AlarmEvent as RustAlarmEvent, AlarmRule};
 use firm_rust::mock_serial::MockDeviceHandle as RustMockDeviceHandle;
+use firm_rust::thresholds::{ThresholdDirection, ThresholdEvent as RustThresholdEvent};
+use firm_rust::transport::ReadStrategy;
+use firm_rust::{
+    ClearBuffer, Decimate, ERROR_HISTORY_LENGTH, ErrorEvent as RustErrorEvent,
+    FIRMClient as RustFirmClient, FIRMClientError, SessionInfo as RustSessionInfo,
+};
 use pyo3::prelude::*;
-use std::time::Duration;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::time::{Duration, UNIX_EPOCH};
 
 #[inline]
 fn py_io_err(msg: impl ToString) -> PyErr {
@@ -21,11 +30,272 @@ fn map_io<T>(res: Result<T, impl ToString>) -> PyResult<T> {
     res.map_err(py_io_err)
 }
 
+/// Converts a Python sequence into a fixed-size calibration array, raising `ValueError` with the
+/// same message the Rust and wasm builders use when the length is wrong.
+fn to_calibration_array<const N: usize>(values: &[f32]) -> PyResult<[f32; N]> {
+    calibration_array(values)
+        .map_err(|e| pyo3::exceptions::PyValueError::new_err(format!("Invalid calibration: {e}")))
+}
+
+/// Maps a client error onto the closest Python exception, with a descriptive message for
+/// the usual reasons a serial port won't open.
+fn client_error(e: &FIRMClientError) -> PyErr {
+    use pyo3::exceptions::{
+        PyConnectionError, PyIOError, PyNotImplementedError, PyRuntimeError, PyTimeoutError,
+        PyValueError,
+    };
+    use std::io::ErrorKind;
+
+    let message = match e {
+        FIRMClientError::SerialOpen {
+            port,
+            kind: ErrorKind::NotFound,
+            ..
+        } => format!(
+            "Serial port '{}' not found. \
+            Check the port name (e.g. COM8, /dev/ttyACM0).",
+            port
+        ),
+        FIRMClientError::SerialOpen {
+            port,
+            kind: ErrorKind::PermissionDenied,
+            ..
+        } => format!(
+            "Permission denied opening serial port '{}'. \
+            Try running as admin or fixing udev permissions.",
+            port
+        ),
+        _ => e.to_string(),
+    };
+    // The stable code goes in the args so scripts can match on `err.args[1]`.
+    let args = (message, e.error_code().code);
+    match e {
+        FIRMClientError::SerialOpen {
+            kind: ErrorKind::TimedOut,
+            ..
+        }
+        | FIRMClientError::StopTimedOut { .. }
+        | FIRMClientError::CommandTimeout { .. }
+        | FIRMClientError::NoPacketsAfterReconnect { .. }
+        | FIRMClientError::LinkIdle { .. } => PyTimeoutError::new_err(args),
+        FIRMClientError::Connect { .. }
+        | FIRMClientError::Disconnected { .. }
+        | FIRMClientError::ReconnectTimedOut { .. } => PyConnectionError::new_err(args),
+        FIRMClientError::CallbackPanicked { .. } | FIRMClientError::ThreadPanicked { .. } => {
+            PyRuntimeError::new_err(args)
+        }
+        FIRMClientError::NotSupported { .. } => PyNotImplementedError::new_err(args),
+        FIRMClientError::InvalidSerialSettings { .. } => PyValueError::new_err(args),
+        _ => PyIOError::new_err(args),
+    }
+}
+
+/// Maps a client call's error with `client_error` when it's a `FIRMClientError`, and to
+/// `IOError` otherwise.
+fn map_client<T>(res: anyhow::Result<T>) -> PyResult<T> {
+    res.map_err(|e| match e.downcast_ref::<FIRMClientError>() {
+        Some(client_err) => client_error(client_err),
+        None => py_io_err(e),
+    })
+}
+
+/// Converts an error from opening a device into a descriptive Python exception.
+fn open_error(port_name: &str, e: anyhow::Error) -> PyErr {
+    if let Some(client_err) = e.downcast_ref::<FIRMClientError>() {
+        return client_error(client_err);
+    }
+    if let Some(event) = e.downcast_ref::<RustErrorEvent>() {
+        return client_error(&event.kind);
+    }
+    if let Some(io_err) = e.downcast_ref::<std::io::Error>() {
+        use std::io::ErrorKind;
+
+        match io_err.kind() {
+            ErrorKind::NotFound => py_io_err(format!(
+                "Serial port '{}' not found. \
+                Check the port name (e.g. COM8, /dev/ttyACM0).",
+                port_name
+            )),
+            ErrorKind::TimedOut => pyo3::exceptions::PyTimeoutError::new_err(io_err.to_string()),
+            _ => py_io_err(format!(
+                "Failed to open serial port '{}': {}",
+                port_name, io_err
+            )),
+        }
+    } else {
+        // Non-IO error (logic error, config error, etc.)
+        py_io_err(format!(
+            "Failed to initialize FIRM client for '{}': {}",
+            port_name, e
+        ))
+    }
+}
+
 #[pyclass(unsendable)]
 struct FIRMClient {
     inner: RustFirmClient,
     /// Used only when `get_data_packets(block=true)` is called.
     timeout: f64,
+    /// Non-fatal errors set aside by `ensure_ok` for `get_warnings`, at most
+    /// `ERROR_HISTORY_LENGTH` of them.
+    warnings: RefCell<VecDeque<ErrorEvent>>,
+    /// The first fatal error collected, raised by the next `ensure_ok`.
+    fatal: RefCell<Option<RustErrorEvent>>,
+}
+
+impl FIRMClient {
+    fn wrap(inner: RustFirmClient, timeout: f64) -> Self {
+        Self {
+            inner,
+            timeout,
+            warnings: RefCell::new(VecDeque::new()),
+            fatal: RefCell::new(None),
+        }
+    }
+
+    /// Sorts the client's pending errors into `warnings` and `fatal`.
+    fn collect_errors(&self) {
+        let mut warnings = self.warnings.borrow_mut();
+        let mut fatal = self.fatal.borrow_mut();
+        for event in self.inner.drain_errors() {
+            if event.fatal {
+                fatal.get_or_insert(event);
+                continue;
+            }
+            if warnings.len() == ERROR_HISTORY_LENGTH {
+                warnings.pop_front();
+            }
+            warnings.push_back(ErrorEvent::from(&event));
+        }
+    }
+}
+
+/// An error reported by the client's background threads, as returned by `get_warnings`.
+#[pyclass(get_all)]
+#[derive(Clone)]
+struct ErrorEvent {
+    /// When it was reported, in seconds since the Unix epoch.
+    time: f64,
+    message: String,
+    code: String,
+    fatal: bool,
+}
+
+impl From<&RustErrorEvent> for ErrorEvent {
+    fn from(event: &RustErrorEvent) -> Self {
+        Self {
+            time: event
+                .time
+                .duration_since(UNIX_EPOCH)
+                .map_or(0.0, |since_epoch| since_epoch.as_secs_f64()),
+            message: event.kind.to_string(),
+            code: event.error_code().code.to_string(),
+            fatal: event.fatal,
+        }
+    }
+}
+
+#[pymethods]
+impl ErrorEvent {
+    fn __repr__(&self) -> String {
+        format!("ErrorEvent({}: {:?})", self.code, self.message)
+    }
+}
+
+/// An alarm raised or cleared, passed to callbacks registered with `on_alarm`.
+#[pyclass(get_all)]
+#[derive(Clone)]
+struct AlarmEvent {
+    raised: bool,
+    rule: String,
+    /// What tripped the alarm; empty when it clears.
+    message: String,
+}
+
+impl From<&RustAlarmEvent> for AlarmEvent {
+    fn from(event: &RustAlarmEvent) -> Self {
+        let message = match event {
+            RustAlarmEvent::AlarmRaised { message, .. } => message.clone(),
+            RustAlarmEvent::AlarmCleared { .. } => String::new(),
+        };
+        Self {
+            raised: event.is_raised(),
+            rule: event.rule().to_string(),
+            message,
+        }
+    }
+}
+
+#[pymethods]
+impl AlarmEvent {
+    fn __repr__(&self) -> String {
+        let state = if self.raised { "raised" } else { "cleared" };
+        format!("AlarmEvent({} {state}: {:?})", self.rule, self.message)
+    }
+}
+
+/// What the device reported about itself when the client started, see `session_info`.
+#[pyclass(get_all)]
+#[derive(Clone)]
+struct SessionInfo {
+    device_info: Option<DeviceInfo>,
+    device_config: Option<DeviceConfig>,
+    /// Set when the device didn't answer both requests.
+    partial: bool,
+}
+
+impl From<&RustSessionInfo> for SessionInfo {
+    fn from(session: &RustSessionInfo) -> Self {
+        Self {
+            device_info: session.device_info.clone(),
+            device_config: session.device_config.clone(),
+            partial: session.partial,
+        }
+    }
+}
+
+#[pymethods]
+impl SessionInfo {
+    fn __repr__(&self) -> String {
+        format!(
+            "SessionInfo(device_info={:?}, device_config={:?}, partial={})",
+            self.device_info, self.device_config, self.partial
+        )
+    }
+}
+
+/// A threshold crossing, passed to callbacks registered with `on_threshold`.
+#[pyclass(get_all)]
+#[derive(Clone)]
+struct ThresholdEvent {
+    field: String,
+    threshold: f32,
+    /// "rising" or "falling".
+    direction: String,
+    value: f64,
+    timestamp_seconds: f64,
+}
+
+impl From<&RustThresholdEvent> for ThresholdEvent {
+    fn from(event: &RustThresholdEvent) -> Self {
+        Self {
+            field: event.field.clone(),
+            threshold: event.threshold,
+            direction: event.crossing.as_str().to_string(),
+            value: event.value,
+            timestamp_seconds: event.timestamp_seconds,
+        }
+    }
+}
+
+#[pymethods]
+impl ThresholdEvent {
+    fn __repr__(&self) -> String {
+        format!(
+            "ThresholdEvent({} {} {} at {:.3} s)",
+            self.field, self.direction, self.threshold, self.timestamp_seconds
+        )
+    }
 }
 
 #[pyclass(unsendable)]
@@ -42,39 +312,10 @@ impl FIRMClient {
         let timeout_val = timeout.unwrap_or(0.1);
 
         // Opens the client and gives descriptive error messages on failure
-        let client = RustFirmClient::new(port_name, baudrate, timeout_val).map_err(|e| {
-            if let Some(io_err) = e.downcast_ref::<std::io::Error>() {
-                use std::io::ErrorKind;
-
-                match io_err.kind() {
-                    ErrorKind::NotFound => py_io_err(format!(
-                        "Serial port '{}' not found. \
-                        Check the port name (e.g. COM8, /dev/ttyACM0).",
-                        port_name
-                    )),
-                    ErrorKind::PermissionDenied => py_io_err(format!(
-                        "Permission denied opening serial port '{}'. \
-                        Try running as admin or fixing udev permissions.",
-                        port_name
-                    )),
-                    _ => py_io_err(format!(
-                        "Failed to open serial port '{}': {}",
-                        port_name, io_err
-                    )),
-                }
-            } else {
-                // Non-IO error (logic error, config error, etc.)
-                py_io_err(format!(
-                    "Failed to initialize FIRM client for '{}': {}",
-                    port_name, e
-                ))
-            }
-        })?;
+        let client =
+            RustFirmClient::new(port_name, baudrate, timeout_val).map_err(|e| client_error(&e))?;
 
-        Ok(Self {
-            inner: client,
-            timeout: timeout_val,
-        })
+        Ok(Self::wrap(client, timeout_val))
     }
 
     #[staticmethod]
@@ -82,29 +323,173 @@ impl FIRMClient {
     fn new_mock(timeout: f64) -> PyResult<(Self, MockDeviceHandle)> {
         let (client, device) = RustFirmClient::new_mock(timeout);
         Ok((
-            Self {
-                inner: client,
-                timeout,
-            },
+            Self::wrap(client, timeout),
             MockDeviceHandle { inner: device },
         ))
     }
 
+    #[staticmethod]
+    #[pyo3(signature = (addr, timeout=0.1, reconnect=false))]
+    fn connect_tcp(addr: &str, timeout: f64, reconnect: bool) -> PyResult<Self> {
+        let mut client =
+            RustFirmClient::connect_tcp(addr, timeout).map_err(|e| client_error(&e))?;
+        client.set_reconnect(reconnect);
+        Ok(Self::wrap(client, timeout))
+    }
+
+    #[staticmethod]
+    fn from_raw_recording(path: &str) -> PyResult<Self> {
+        let client =
+            RustFirmClient::from_raw_recording(std::path::Path::new(path)).map_err(py_io_err)?;
+        Ok(Self::wrap(client, 0.1))
+    }
+
+    #[staticmethod]
+    #[pyo3(signature = (path, speed=1.0))]
+    fn from_log_file(path: &str, speed: f64) -> PyResult<Self> {
+        let client =
+            RustFirmClient::from_log_file(std::path::Path::new(path), speed).map_err(py_io_err)?;
+        Ok(Self::wrap(client, 0.1))
+    }
+
     #[inline]
     fn ensure_ok(&self) -> PyResult<()> {
-        if let Some(err) = self.inner.check_error() {
-            return Err(py_io_err(err));
+        self.collect_errors();
+        match self.fatal.borrow_mut().take() {
+            Some(err) => Err(client_error(&err.kind)),
+            None => Ok(()),
         }
-        Ok(())
     }
 
-    fn start(&mut self) -> PyResult<()> {
-        self.inner.start();
+    /// Errors the client carried on after, oldest first, cleared once returned. Fatal errors
+    /// are raised by the next call that checks instead.
+    fn get_warnings(&self) -> Vec<ErrorEvent> {
+        self.collect_errors();
+        self.warnings.borrow_mut().drain(..).collect()
+    }
+
+    fn start(&mut self, py: Python<'_>) -> PyResult<()> {
+        let inner = &mut self.inner;
+        py.detach(|| inner.start()).map_err(|e| client_error(&e))
+    }
+
+    /// Make `start()` wait up to `timeout_seconds` for each of the device's info and config
+    /// replies, see `session_info`. `None` turns it off.
+    #[pyo3(signature = (timeout_seconds=None))]
+    fn set_session_capture(&mut self, timeout_seconds: Option<f64>) {
+        self.inner
+            .set_session_capture(timeout_seconds.map(Duration::from_secs_f64));
+    }
+
+    fn session_info(&self) -> Option<SessionInfo> {
+        self.inner.session_info().map(SessionInfo::from)
+    }
+
+    fn stop(&mut self, py: Python<'_>) {
+        self.stop_detached(py);
+    }
+
+    fn stop_with_timeout(&mut self, py: Python<'_>, timeout_seconds: f64) -> PyResult<()> {
+        let timeout = Duration::from_secs_f64(timeout_seconds);
+        let inner = &mut self.inner;
+        py.detach(|| inner.stop_with_timeout(timeout))
+            .map_err(|e| client_error(&e))
+    }
+
+    /// Evaluate the standard alarm rules (any dropped packet in 5 s, more than 1 CRC failure
+    /// per second, packet rate under 80% of `expected_frequency_hz` for 10 s).
+    fn enable_default_alarms(&mut self, expected_frequency_hz: f64) {
+        self.inner
+            .set_alarm_rules(AlarmRule::defaults(expected_frequency_hz));
+    }
+
+    fn disable_alarms(&mut self) {
+        self.inner.set_alarm_rules(Vec::new());
+    }
+
+    fn active_alarms(&self) -> Vec<String> {
+        self.inner.active_alarms()
+    }
+
+    fn get_alarm_events(&self) -> Vec<AlarmEvent> {
+        self.inner
+            .get_alarm_events()
+            .iter()
+            .map(AlarmEvent::from)
+            .collect()
+    }
+
+    /// Call `callback(event)` from the reader thread whenever an alarm is raised or cleared.
+    /// Exceptions raised by the callback are printed and otherwise ignored.
+    fn on_alarm(&mut self, callback: Py<PyAny>) {
+        self.inner.on_alarm(move |event| {
+            Python::attach(|py| {
+                if let Err(e) = callback.call1(py, (AlarmEvent::from(event),)) {
+                    e.print(py);
+                }
+            });
+        });
+    }
+
+    /// Call `callback(event)` from the reader thread whenever `field` crosses one of
+    /// `thresholds`. Exceptions raised by the callback are printed and otherwise ignored.
+    #[pyo3(signature = (field, thresholds, callback, direction="both", hysteresis=0.0))]
+    fn on_threshold(
+        &mut self,
+        field: &str,
+        thresholds: Vec<f32>,
+        callback: Py<PyAny>,
+        direction: &str,
+        hysteresis: f32,
+    ) -> PyResult<()> {
+        let direction = match direction {
+            "rising" => ThresholdDirection::Rising,
+            "falling" => ThresholdDirection::Falling,
+            "both" => ThresholdDirection::Both,
+            other => {
+                return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                    "Unknown direction '{other}', expected 'rising', 'falling' or 'both'"
+                )));
+            }
+        };
+        self.inner
+            .on_threshold(field, thresholds, direction, hysteresis, move |event| {
+                Python::attach(|py| {
+                    if let Err(e) = callback.call1(py, (ThresholdEvent::from(event),)) {
+                        e.print(py);
+                    }
+                });
+            })
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))?;
         Ok(())
     }
 
-    fn stop(&mut self) {
-        self.inner.stop();
+    fn is_replay(&self) -> bool {
+        self.inner.is_replay()
+    }
+
+    fn pause(&self) -> PyResult<()> {
+        map_client(self.inner.pause())
+    }
+
+    fn resume(&self) -> PyResult<()> {
+        map_client(self.inner.resume())
+    }
+
+    fn seek(&self, timestamp_seconds: f64) -> PyResult<()> {
+        map_client(self.inner.seek(timestamp_seconds))
+    }
+
+    fn set_speed(&self, speed: f64) -> PyResult<()> {
+        map_client(self.inner.set_speed(speed))
+    }
+
+    fn record_raw(&mut self, path: &str) -> PyResult<()> {
+        map_io(self.inner.record_raw(std::path::Path::new(path)))
+    }
+
+    fn stop_recording(&mut self) -> PyResult<bool> {
+        map_io(self.inner.stop_recording())
     }
 
     /// Start streaming a mock log file in the background.
@@ -160,6 +545,73 @@ impl FIRMClient {
         Ok(packets)
     }
 
+    /// Drop packets older than `max_age_seconds` of device time when they are consumed.
+    #[pyo3(signature = (max_age_seconds=None))]
+    fn set_max_packet_age(&mut self, max_age_seconds: Option<f64>) {
+        self.inner
+            .set_max_packet_age(max_age_seconds.map(Duration::from_secs_f64));
+    }
+
+    /// Bytes the reader thread asks the port for per read, from the next `start()`.
+    fn set_read_buffer_size(&mut self, size: usize) -> PyResult<()> {
+        self.inner
+            .set_read_buffer_size(size)
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))
+    }
+
+    /// Poll for available bytes every `poll_interval_seconds` instead of blocking in `read`,
+    /// from the next `start()`. `None` goes back to blocking reads.
+    #[pyo3(signature = (poll_interval_seconds=None))]
+    fn set_read_strategy(&mut self, poll_interval_seconds: Option<f64>) {
+        self.inner.set_read_strategy(match poll_interval_seconds {
+            Some(seconds) => ReadStrategy::PollBytesToRead {
+                interval: Duration::from_secs_f64(seconds),
+            },
+            None => ReadStrategy::BlockingRead,
+        });
+    }
+
+    fn flush_stale(&mut self) -> PyResult<Vec<FIRMData>> {
+        self.ensure_ok()?;
+        Ok(self.inner.flush_stale())
+    }
+
+    fn aged_out_count(&self) -> u64 {
+        self.inner.aged_out_count()
+    }
+
+    fn get_latest_packet(&mut self) -> PyResult<Option<FIRMData>> {
+        self.ensure_ok()?;
+        Ok(self.inner.get_latest_packet())
+    }
+
+    fn set_latest_only(&mut self, enabled: bool) {
+        self.inner.set_latest_only(enabled);
+    }
+
+    /// Keep only every `n`th data packet, dropping the rest in the reader thread. `None` keeps
+    /// every packet again.
+    #[pyo3(signature = (n=None))]
+    fn set_decimation(&mut self, n: Option<u64>) -> PyResult<()> {
+        match n {
+            Some(0) => Err(pyo3::exceptions::PyValueError::new_err(
+                "n must be at least 1",
+            )),
+            Some(n) => {
+                self.inner.set_packet_filter(Decimate::new(n));
+                Ok(())
+            }
+            None => {
+                self.inner.clear_packet_filter();
+                Ok(())
+            }
+        }
+    }
+
+    fn skipped_packet_count(&self) -> u64 {
+        self.inner.skipped_packet_count()
+    }
+
     #[pyo3(signature = (timeout_seconds=5.0))]
     fn get_device_info(&mut self, timeout_seconds: f64) -> PyResult<Option<DeviceInfo>> {
         self.ensure_ok()?;
@@ -170,6 +622,19 @@ impl FIRMClient {
         Ok(info)
     }
 
+    /// Round trip time to the device in seconds, raising TimeoutError if it doesn't answer.
+    #[pyo3(signature = (timeout_seconds=1.0))]
+    fn ping(&mut self, timeout_seconds: f64) -> PyResult<f64> {
+        self.ensure_ok()?;
+        match self.inner.ping(Duration::from_secs_f64(timeout_seconds)) {
+            Ok(round_trip) => Ok(round_trip.as_secs_f64()),
+            Err(e) => match e.downcast_ref::<FIRMClientError>() {
+                Some(client_err) => Err(client_error(client_err)),
+                None => Err(py_io_err(e)),
+            },
+        }
+    }
+
     #[pyo3(signature = (timeout_seconds=5.0))]
     fn get_device_config(&mut self, timeout_seconds: f64) -> PyResult<Option<DeviceConfig>> {
         self.ensure_ok()?;
@@ -180,22 +645,32 @@ impl FIRMClient {
         Ok(cfg)
     }
 
-    #[pyo3(signature = (name, frequency, protocol, timeout_seconds=5.0))]
+    #[pyo3(signature = (name, frequency, protocol, timeout_seconds=5.0, verify=false))]
     fn set_device_config(
         &mut self,
         name: String,
         frequency: u16,
         protocol: DeviceProtocol,
         timeout_seconds: f64,
+        verify: bool,
     ) -> PyResult<bool> {
         self.ensure_ok()?;
 
-        let res = map_io(self.inner.set_device_config(
-            name,
-            frequency,
-            protocol,
-            Duration::from_secs_f64(timeout_seconds),
-        ))?;
+        let timeout = Duration::from_secs_f64(timeout_seconds);
+        let res = if verify {
+            let config = DeviceConfig {
+                name,
+                frequency,
+                protocol,
+                suspect: false,
+            };
+            map_io(self.inner.set_device_config_verified(&config, timeout))?
+        } else {
+            map_io(
+                self.inner
+                    .set_device_config(name, frequency, protocol, timeout),
+            )?
+        };
 
         Ok(res.unwrap_or(false))
     }
@@ -203,15 +678,15 @@ impl FIRMClient {
     #[pyo3(signature = (offsets, scale_matrix, timeout_seconds=5.0))]
     fn set_magnetometer_calibration(
         &mut self,
-        offsets: [f32; NUMBER_OF_CALIBRATION_OFFSETS],
-        scale_matrix: [f32; NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS],
+        offsets: Vec<f32>,
+        scale_matrix: Vec<f32>,
         timeout_seconds: f64,
     ) -> PyResult<bool> {
         self.ensure_ok()?;
 
         let res = map_io(self.inner.set_magnetometer_calibration(
-            offsets,
-            scale_matrix,
+            to_calibration_array(&offsets)?,
+            to_calibration_array(&scale_matrix)?,
             Duration::from_secs_f64(timeout_seconds),
         ))?;
 
@@ -221,19 +696,19 @@ impl FIRMClient {
     #[pyo3(signature = (accel_offsets, accel_scale_matrix, gyro_offsets, gyro_scale_matrix, timeout_seconds=5.0))]
     fn set_imu_calibration(
         &mut self,
-        accel_offsets: [f32; NUMBER_OF_CALIBRATION_OFFSETS],
-        accel_scale_matrix: [f32; NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS],
-        gyro_offsets: [f32; NUMBER_OF_CALIBRATION_OFFSETS],
-        gyro_scale_matrix: [f32; NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS],
+        accel_offsets: Vec<f32>,
+        accel_scale_matrix: Vec<f32>,
+        gyro_offsets: Vec<f32>,
+        gyro_scale_matrix: Vec<f32>,
         timeout_seconds: f64,
     ) -> PyResult<bool> {
         self.ensure_ok()?;
 
         let res = map_io(self.inner.set_imu_calibration(
-            accel_offsets,
-            accel_scale_matrix,
-            gyro_offsets,
-            gyro_scale_matrix,
+            to_calibration_array(&accel_offsets)?,
+            to_calibration_array(&accel_scale_matrix)?,
+            to_calibration_array(&gyro_offsets)?,
+            to_calibration_array(&gyro_scale_matrix)?,
             Duration::from_secs_f64(timeout_seconds),
         ))?;
 
@@ -250,6 +725,20 @@ impl FIRMClient {
         Ok(calibration)
     }
 
+    #[pyo3(signature = (calibration, timeout_seconds=5.0))]
+    fn set_calibration(
+        &mut self,
+        calibration: CalibrationValues,
+        timeout_seconds: f64,
+    ) -> PyResult<bool> {
+        self.ensure_ok()?;
+        let res = map_io(
+            self.inner
+                .set_calibration(&calibration, Duration::from_secs_f64(timeout_seconds)),
+        )?;
+        Ok(res.unwrap_or(false))
+    }
+
     /// Runs a blocking magnetometer calibration sequence.
     ///
     /// This function will:
@@ -285,18 +774,109 @@ impl FIRMClient {
         Ok(res.unwrap_or(false))
     }
 
+    fn send_break(&mut self, py: Python<'_>, duration_seconds: f64) -> PyResult<()> {
+        let duration = Duration::from_secs_f64(duration_seconds);
+        let inner = &mut self.inner;
+        map_io(py.detach(|| inner.send_break(duration)))
+    }
+
+    fn set_baud_rate(&mut self, py: Python<'_>, baud_rate: u32) -> PyResult<()> {
+        let inner = &mut self.inner;
+        map_client(py.detach(|| inner.set_baud_rate(baud_rate)))
+    }
+
+    fn set_dtr(&mut self, py: Python<'_>, level: bool) -> PyResult<()> {
+        let inner = &mut self.inner;
+        map_client(py.detach(|| inner.set_dtr(level)))
+    }
+
+    fn set_rts(&mut self, py: Python<'_>, level: bool) -> PyResult<()> {
+        let inner = &mut self.inner;
+        map_client(py.detach(|| inner.set_rts(level)))
+    }
+
+    /// Drive DTR through `pattern`, a list of `(level, hold_seconds)` pairs.
+    fn pulse_reset(&mut self, py: Python<'_>, pattern: Vec<(bool, f64)>) -> PyResult<()> {
+        let pattern: Vec<(bool, Duration)> = pattern
+            .into_iter()
+            .map(|(level, seconds)| (level, Duration::from_secs_f64(seconds)))
+            .collect();
+        let inner = &mut self.inner;
+        map_client(py.detach(|| inner.pulse_reset(&pattern)))
+    }
+
+    #[pyo3(signature = (direction="all"))]
+    fn purge_buffers(&mut self, py: Python<'_>, direction: &str) -> PyResult<()> {
+        let direction = match direction {
+            "input" => ClearBuffer::Input,
+            "output" => ClearBuffer::Output,
+            "all" => ClearBuffer::All,
+            other => {
+                return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                    "Unknown direction '{other}', expected 'input', 'output' or 'all'"
+                )));
+            }
+        };
+        let inner = &mut self.inner;
+        map_io(py.detach(|| inner.purge_buffers(direction)))
+    }
+
+    #[pyo3(signature = (step_timeout_seconds=1.0))]
+    fn recover_link(
+        &mut self,
+        py: Python<'_>,
+        step_timeout_seconds: f64,
+    ) -> PyResult<Option<&'static str>> {
+        self.ensure_ok()?;
+        let timeout = Duration::from_secs_f64(step_timeout_seconds);
+        let inner = &mut self.inner;
+        let step = map_io(py.detach(|| inner.recover_link(timeout)))?;
+        Ok(step.map(|step| step.as_str()))
+    }
+
+    #[pyo3(signature = (samples=50, timeout_seconds=5.0))]
+    fn zero_out_pressure_altitude(
+        &mut self,
+        py: Python<'_>,
+        samples: usize,
+        timeout_seconds: f64,
+    ) -> PyResult<Option<f32>> {
+        self.ensure_ok()?;
+        let timeout = Duration::from_secs_f64(timeout_seconds);
+        let inner = &mut self.inner;
+        map_io(py.detach(|| inner.zero_out_pressure_altitude(samples, timeout)))
+    }
+
+    fn set_sea_level_pressure(&mut self, pascals: f32) {
+        self.inner.set_sea_level_pressure(pascals);
+    }
+
+    fn ground_reference_altitude(&self) -> Option<f32> {
+        self.inner.ground_reference_altitude()
+    }
+
     fn reboot(&mut self) -> PyResult<()> {
         self.ensure_ok()?;
-        map_io(self.inner.reboot())?;
+        map_client(self.inner.reboot())?;
         Ok(())
     }
 
+    /// Reboot the device and reconnect once its port is back, see
+    /// `FIRMClient::reboot_and_reconnect`.
+    #[pyo3(signature = (timeout_seconds=10.0))]
+    fn reboot_and_reconnect(&mut self, py: Python<'_>, timeout_seconds: f64) -> PyResult<()> {
+        let timeout = Duration::from_secs_f64(timeout_seconds);
+        let inner = &mut self.inner;
+        map_client(py.detach(|| inner.reboot_and_reconnect(timeout)))
+    }
+
     fn is_running(&self) -> bool {
         self.inner.is_running()
     }
 
     fn __enter__(slf: Bound<'_, Self>) -> PyResult<Bound<'_, Self>> {
-        slf.borrow_mut().start()?;
+        let py = slf.py();
+        slf.borrow_mut().start(py)?;
         Ok(slf)
     }
 
@@ -310,7 +890,22 @@ impl FIRMClient {
 
         let _ = this.inner.stop_mock_log_stream(true, true);
 
-        this.inner.stop();
+        this.stop_detached(slf.py());
+    }
+}
+
+impl FIRMClient {
+    /// Stops the reader thread with the GIL released, since an `on_alarm` callback may be
+    /// waiting on the GIL and the thread can't be joined until it gets it.
+    fn stop_detached(&mut self, py: Python<'_>) {
+        let inner = &mut self.inner;
+        py.detach(|| inner.stop());
+    }
+}
+
+impl Drop for FIRMClient {
+    fn drop(&mut self) {
+        Python::attach(|py| self.stop_detached(py));
     }
 }
 
@@ -330,15 +925,50 @@ impl MockDeviceHandle {
     }
 }
 
+/// Opens a FIRM device, waits for the first data packet, and closes the port again.
+///
+/// If `port` is `None`, the only detected FIRM (or serial) port is used, and it's an error if
+/// there are several.
+#[pyfunction]
+#[pyo3(signature = (port=None, timeout=2.0, baud_rate=2_000_000))]
+fn read_one(port: Option<&str>, timeout: f64, baud_rate: u32) -> PyResult<FIRMData> {
+    firm_rust::read_one_packet(port, baud_rate, Duration::from_secs_f64(timeout))
+        .map_err(|e| open_error(port.unwrap_or("<auto>"), e))
+}
+
+/// Read a CSV written by the logging tools back into packets, see `firm_core::csv::parse_csv`.
+#[pyfunction]
+#[pyo3(signature = (path, repair_timestamps=false, inf_sentinel=DEFAULT_INF_SENTINEL.to_string()))]
+fn read_csv(path: &str, repair_timestamps: bool, inf_sentinel: String) -> PyResult<Vec<FIRMData>> {
+    let options = CsvImportOptions {
+        repair_timestamps,
+        non_finite: NonFinitePolicy::new(inf_sentinel),
+    };
+    firm_core::csv::read_csv(path, options).map_err(|e| {
+        let args = (e.to_string(), e.error_code().code);
+        match e {
+            CsvImportError::Io(_) => pyo3::exceptions::PyIOError::new_err(args),
+            _ => pyo3::exceptions::PyValueError::new_err(args),
+        }
+    })
+}
+
 #[pymodule(gil_used = false)]
 fn firm_client(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(read_one, m)?)?;
+    m.add_function(wrap_pyfunction!(read_csv, m)?)?;
     m.add_class::<FIRMClient>()?;
     m.add_class::<MockDeviceHandle>()?;
+    m.add_class::<AlarmEvent>()?;
+    m.add_class::<ThresholdEvent>()?;
+    m.add_class::<SessionInfo>()?;
+    m.add_class::<ErrorEvent>()?;
     m.add_class::<FIRMData>()?;
     m.add_class::<DeviceProtocol>()?;
     m.add_class::<DeviceInfo>()?;
     m.add_class::<DeviceConfig>()?;
     m.add_class::<CalibrationValues>()?;
+    m.add_class::<NonFinitePolicy>()?;
     m.add("__version__", env!("CARGO_PKG_VERSION"))?;
     Ok(())
 }
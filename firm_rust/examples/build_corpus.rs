@@ -0,0 +1,136 @@
+use clap::Parser;
+use firm_core::constants::command::{DEVICE_NAME_LENGTH, FIRMCommand};
+use firm_core::constants::packet::{BARO_PACKET_IDENTIFIER, PacketHeader};
+use firm_core::firm_packets::DeviceProtocol;
+use firm_core::framed_packet::FramedPacket;
+use firm_rust::FIRMClient;
+use firm_rust::mock_serial::MockDeviceHandle;
+use firm_rust::simulator::DeviceSimulator;
+use std::{path::PathBuf, process::ExitCode, thread, time::Duration};
+
+// cargo run -p firm_rust --example build_corpus -- --out firm_rust/tests/corpus
+// cargo run -p firm_rust --example build_corpus -- --port COM8 --seconds 600
+
+#[derive(Parser, Debug)]
+#[command(about = "Collect one example of each frame shape into a replay corpus directory")]
+struct Args {
+    /// Corpus directory to write. Entries already in it are kept.
+    #[arg(long, default_value = "firm_rust/tests/corpus")]
+    out: PathBuf,
+
+    /// Collect from this serial port instead of the simulated device and fixtures.
+    #[arg(long)]
+    port: Option<String>,
+
+    /// How long to collect from the port.
+    #[arg(long, default_value_t = 60.0)]
+    seconds: f64,
+
+    /// Most frame shapes to keep.
+    #[arg(long, default_value_t = 256)]
+    max_entries: usize,
+}
+
+/// Frames the parser has to reject or set aside, taken from the parser tests.
+fn inject_tricky_fixtures(device: &MockDeviceHandle) {
+    let mut bad_crc = FramedPacket::new(PacketHeader::Data, 0, vec![0u8; 120]).to_bytes();
+    bad_crc[40] ^= 0xff;
+    device.inject_bytes(&bad_crc);
+    let mut bad_crc_response = FramedPacket::new(
+        PacketHeader::Response,
+        FIRMCommand::GetDeviceConfig as u16,
+        vec![0u8; DEVICE_NAME_LENGTH + 3],
+    )
+    .to_bytes();
+    let last = bad_crc_response.len() - 1;
+    bad_crc_response[last] ^= 0x01;
+    device.inject_bytes(&bad_crc_response);
+
+    let undecodable = [
+        // A prototype response the client doesn't know.
+        (PacketHeader::Response, 0x0042, vec![1]),
+        // A barometer reading of the wrong length.
+        (PacketHeader::Data, BARO_PACKET_IDENTIFIER, vec![0u8; 12]),
+        // Responses with no payload.
+        (
+            PacketHeader::Response,
+            FIRMCommand::GetDeviceInfo as u16,
+            vec![],
+        ),
+        (
+            PacketHeader::Response,
+            FIRMCommand::GetCalibration as u16,
+            vec![],
+        ),
+    ];
+    for (header, identifier, payload) in undecodable {
+        device.inject_framed_packet(FramedPacket::new(header, identifier, payload));
+    }
+    thread::sleep(Duration::from_millis(200));
+}
+
+/// Runs every command against the simulated device, so each response shape is seen.
+fn exercise_commands(client: &mut FIRMClient) -> anyhow::Result<()> {
+    let timeout = Duration::from_secs(1);
+    client.get_device_info(timeout)?;
+    client.get_device_config(timeout)?;
+    client.set_device_config("Corpus".to_string(), 100, DeviceProtocol::USB, timeout)?;
+    client.get_calibration(timeout)?;
+    client.set_imu_calibration([0.0; 3], [0.0; 9], [0.0; 3], [0.0; 9], timeout)?;
+    client.set_magnetometer_calibration([0.0; 3], [0.0; 9], timeout)?;
+    client.cancel(timeout)?;
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    let (mut client, device) = match &args.port {
+        Some(port) => match FIRMClient::new(port, 2_000_000, 0.1) {
+            Ok(client) => (client, None),
+            Err(e) => {
+                eprintln!("Failed to open {port}: {e}");
+                return ExitCode::FAILURE;
+            }
+        },
+        None => {
+            let (client, device) = FIRMClient::new_mock(0.01);
+            (client, Some(device))
+        }
+    };
+    client.collect_corpus(Some(args.max_entries));
+    if let Err(e) = client.start() {
+        eprintln!("{e}");
+        return ExitCode::FAILURE;
+    }
+
+    match device {
+        Some(device) => {
+            let simulator = DeviceSimulator::new(200.0).spawn(device.clone());
+            if let Err(e) = exercise_commands(&mut client) {
+                eprintln!("{e}");
+                return ExitCode::FAILURE;
+            }
+            simulator.stop();
+            inject_tricky_fixtures(&device);
+        }
+        None => thread::sleep(Duration::from_secs_f64(args.seconds)),
+    }
+    client.stop();
+
+    let Some(corpus) = client.corpus() else {
+        eprintln!("No corpus was collected");
+        return ExitCode::FAILURE;
+    };
+    if let Err(e) = corpus.write_dir(&args.out) {
+        eprintln!("Failed to write {}: {e}", args.out.display());
+        return ExitCode::FAILURE;
+    }
+    println!(
+        "{} shapes from {} frames written to {}",
+        corpus.len(),
+        corpus.frames_seen(),
+        args.out.display()
+    );
+    ExitCode::SUCCESS
+}
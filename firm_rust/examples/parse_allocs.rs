@@ -0,0 +1,139 @@
+//! Counts the heap allocations `SerialParser` and `LogParser` make per `parse_bytes` call once
+//! they're warmed up, using a counting global allocator.
+//!
+//! `SerialParser` is fed through `parse_bytes_with`, so it queues no packets, and each log
+//! packet's own payload is discounted; what's left is the parsers' buffering. Exits with an
+//! error if either still allocates once warmed up, unless `--report-only` is passed.
+//!
+//! Run with: `cargo run --release -p firm_rust --example parse_allocs`
+use clap::Parser;
+use firm_core::constants::log_parsing::HEADER_TOTAL_SIZE;
+use firm_core::constants::packet::PacketHeader;
+use firm_core::data_parser::SerialParser;
+use firm_core::framed_packet::FramedPacket;
+use firm_core::log_parsing::LogParser;
+use firm_rust::simulator::{FlightProfileGenerator, build_mock_log, data_payload};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The system allocator, counting every allocation and reallocation.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static BYTES_ALLOCATED: AtomicU64 = AtomicU64::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        BYTES_ALLOCATED.fetch_add(layout.size() as u64, Ordering::Relaxed);
+        // SAFETY: forwarded unchanged to the system allocator.
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: `ptr` came from `alloc` or `realloc` above, so from the system allocator.
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        BYTES_ALLOCATED.fetch_add(new_size as u64, Ordering::Relaxed);
+        // SAFETY: as for `dealloc`, with the caller's guarantees on `new_size`.
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[derive(Parser, Debug)]
+#[command(about = "Count heap allocations per parse_bytes call in the parsers")]
+struct Args {
+    /// Data packets in the serial stream.
+    #[arg(long, default_value_t = 20_000)]
+    packets: usize,
+
+    /// Bytes handed to each parser per call, like one read.
+    #[arg(long, default_value_t = 100)]
+    chunk: usize,
+
+    /// Print the counts without failing on steady-state allocations.
+    #[arg(long)]
+    report_only: bool,
+}
+
+/// Allocations and bytes allocated while running `f`.
+fn count_allocations(f: impl FnOnce()) -> (u64, u64) {
+    let (allocations, bytes) = (
+        ALLOCATIONS.load(Ordering::Relaxed),
+        BYTES_ALLOCATED.load(Ordering::Relaxed),
+    );
+    f();
+    (
+        ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+        BYTES_ALLOCATED.load(Ordering::Relaxed) - bytes,
+    )
+}
+
+/// Feeds `stream` in `chunk` byte calls, the first tenth as warm-up, and returns how many
+/// allocations the rest made beyond the packets `parse` says it was handed, one each.
+fn measure(name: &str, stream: &[u8], chunk: usize, mut parse: impl FnMut(&[u8]) -> u64) -> u64 {
+    let chunks: Vec<&[u8]> = stream.chunks(chunk).collect();
+    let (warm_up, measured) = chunks.split_at(chunks.len() / 10);
+    warm_up.iter().for_each(|bytes| {
+        parse(bytes);
+    });
+    let mut packets = 0;
+    let (allocations, bytes) =
+        count_allocations(|| measured.iter().for_each(|bytes| packets += parse(bytes)));
+    let extra = allocations.saturating_sub(packets);
+    let calls = measured.len().max(1) as f64;
+    println!(
+        "  {name:<12} {:>8.3} allocations/call beyond packets  {:>10.1} bytes allocated/call",
+        extra as f64 / calls,
+        bytes as f64 / calls
+    );
+    extra
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let chunk = args.chunk.max(1);
+    let profile = FlightProfileGenerator::default();
+
+    let serial_stream: Vec<u8> = (0..args.packets)
+        .flat_map(|i| {
+            let data = profile.sample(i as f64 / 1000.0);
+            FramedPacket::new(PacketHeader::Data, 0, data_payload(&data)).to_bytes()
+        })
+        .collect();
+    let log = build_mock_log(&profile, args.packets as f64 / 1000.0, 1000.0);
+
+    println!("Steady-state allocations in {chunk} byte calls:");
+    let mut serial = SerialParser::new();
+    let mut packets = 0;
+    let serial_allocations = measure("SerialParser", &serial_stream, chunk, |bytes| {
+        // Views borrow from the buffer, so no packet should allocate.
+        serial.parse_bytes_with(bytes, |_| packets += 1);
+        0
+    });
+
+    let mut log_parser = LogParser::new();
+    log_parser.read_header(&log[..HEADER_TOTAL_SIZE]);
+    let log_allocations = measure("LogParser", &log[HEADER_TOTAL_SIZE..], chunk, |bytes| {
+        log_parser.parse_bytes(bytes);
+        // Each log packet owns its payload, so it's one allocation.
+        std::iter::from_fn(|| log_parser.get_packet()).count() as u64
+    });
+
+    if packets != args.packets {
+        eprintln!("Parsed {packets} of {} packets", args.packets);
+        return ExitCode::FAILURE;
+    }
+    if !args.report_only && serial_allocations + log_allocations > 0 {
+        eprintln!("The parsers allocated after warming up");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,125 @@
+//! Compares the owned parsing path, `SerialParser::parse_bytes` then `drain_packets`, with the
+//! borrowed one, `SerialParser::parse_bytes_with`, on a stream of simulated data packets.
+//!
+//! Each path sums one field per packet, so neither can skip its decoding, and the sums are
+//! checked to agree. `--noise` puts line noise between the packets, which times how fast the
+//! parser scans for start words.
+//!
+//! Run with: `cargo run --release -p firm_rust --example parse_bench -- --packets 1000000`
+use clap::Parser;
+use firm_core::constants::packet::PacketHeader;
+use firm_core::data_parser::SerialParser;
+use firm_core::framed_packet::FramedPacket;
+use firm_rust::simulator::{FlightProfileGenerator, data_payload};
+use std::hint::black_box;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+#[derive(Parser, Debug)]
+#[command(about = "Time the owned and borrowed SerialParser paths against each other")]
+struct Args {
+    /// Data packets in the stream.
+    #[arg(long, default_value_t = 200_000)]
+    packets: usize,
+
+    /// Bytes handed to the parser per call, like one serial read.
+    #[arg(long, default_value_t = 512)]
+    chunk: usize,
+
+    /// Times each path is run; the fastest run is reported.
+    #[arg(long, default_value_t = 5)]
+    runs: usize,
+
+    /// Bytes of random noise before each packet.
+    #[arg(long, default_value_t = 0)]
+    noise: usize,
+}
+
+/// Builds `packets` data frames sampled from the default flight profile at 1 kHz, each after
+/// `noise` random bytes.
+fn build_stream(packets: usize, noise: usize) -> Vec<u8> {
+    let profile = FlightProfileGenerator::default();
+    let mut state = 0x2545_F491_4F6C_DD1Du64;
+    let mut stream = Vec::new();
+    for i in 0..packets {
+        stream.extend((0..noise).map(|_| {
+            // xorshift64, enough to look like a noisy line.
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        }));
+        let data = profile.sample(i as f64 / 1000.0);
+        stream.extend(FramedPacket::new(PacketHeader::Data, 0, data_payload(&data)).to_bytes());
+    }
+    stream
+}
+
+/// Runs `parse` `runs` times and returns its fastest time and its result.
+fn fastest(runs: usize, mut parse: impl FnMut() -> (usize, f64)) -> (Duration, (usize, f64)) {
+    (0..runs.max(1))
+        .map(|_| {
+            let start = Instant::now();
+            let result = black_box(parse());
+            (start.elapsed(), result)
+        })
+        .min_by_key(|(elapsed, _)| *elapsed)
+        .unwrap()
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let stream = build_stream(args.packets, args.noise);
+    let chunk = args.chunk.max(1);
+
+    let (owned_time, owned) = fastest(args.runs, || {
+        let mut parser = SerialParser::new();
+        let (mut count, mut sum) = (0, 0.0);
+        for bytes in stream.chunks(chunk) {
+            parser.parse_bytes(bytes);
+            for packet in parser.drain_packets() {
+                count += 1;
+                sum += f64::from(packet.data().raw_acceleration_z_gs);
+            }
+        }
+        (count, sum)
+    });
+
+    let (view_time, viewed) = fastest(args.runs, || {
+        let mut parser = SerialParser::new();
+        let (mut count, mut sum) = (0, 0.0);
+        for bytes in stream.chunks(chunk) {
+            parser.parse_bytes_with(bytes, |view| {
+                count += 1;
+                sum += f64::from(view.raw_acceleration_z_gs());
+            });
+        }
+        (count, sum)
+    });
+
+    let per_packet = |time: Duration| time.as_nanos() as f64 / args.packets.max(1) as f64;
+    let throughput = |time: Duration| stream.len() as f64 / time.as_secs_f64() / 1e6;
+    println!(
+        "{} packets ({} bytes, {} of noise before each) in {chunk} byte chunks, fastest of {} runs:",
+        args.packets,
+        stream.len(),
+        args.noise,
+        args.runs.max(1)
+    );
+    println!(
+        "  owned (parse_bytes + drain_packets): {owned_time:>10.2?}  {:>7.1} ns/packet  {:>7.1} MB/s",
+        per_packet(owned_time),
+        throughput(owned_time)
+    );
+    println!(
+        "  borrowed (parse_bytes_with):         {view_time:>10.2?}  {:>7.1} ns/packet  {:>7.1} MB/s",
+        per_packet(view_time),
+        throughput(view_time)
+    );
+
+    if owned != viewed {
+        eprintln!("The paths disagree: owned {owned:?}, borrowed {viewed:?}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,64 @@
+use clap::Parser;
+use firm_core::log_tools::{CsvDirectorySink, ProcessOptions, process_directory};
+use std::{path::PathBuf, process::ExitCode};
+
+// cargo run -p firm_rust --example process_logs -- path/to/logs --output path/to/csv
+
+#[derive(Parser, Debug)]
+#[command(about = "Decode every FIRM log in a directory to CSV, with a summary table")]
+struct Args {
+    /// Directory containing the log files. Logs are recognized by their header, not extension.
+    dir: PathBuf,
+
+    /// Directory to write the CSVs and summary.csv into. Defaults to `<dir>/csv`.
+    #[arg(long)]
+    output: Option<PathBuf>,
+
+    /// Number of logs to process at once. 0 uses every CPU.
+    #[arg(long, default_value_t = 0)]
+    threads: usize,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let output = args.output.unwrap_or_else(|| args.dir.join("csv"));
+
+    let sink = match CsvDirectorySink::new(&output) {
+        Ok(sink) => sink,
+        Err(e) => {
+            eprintln!("Failed to create {}: {e}", output.display());
+            return ExitCode::FAILURE;
+        }
+    };
+    let options = ProcessOptions {
+        threads: args.threads,
+    };
+    let summary = match process_directory(&args.dir, options, &sink) {
+        Ok(summary) => summary,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    for file in &summary.files {
+        match &file.error {
+            None => println!(
+                "{}: {} packets over {:.1} s",
+                file.path.display(),
+                file.packets,
+                file.duration_seconds
+            ),
+            Some(error) => eprintln!("{}: {error}", file.path.display()),
+        }
+    }
+    println!(
+        "Processed {} logs into {}",
+        summary.files.len(),
+        output.display()
+    );
+    if summary.failures().next().is_some() {
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
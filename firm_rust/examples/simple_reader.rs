@@ -1,37 +1,107 @@
+use clap::Parser;
 use firm_rust::FIRMClient;
-use std::{process::ExitCode, thread, time::Duration};
+use firm_rust::faults::FaultScenario;
+use firm_rust::simulator::{DeviceSimulator, SimulatorHandle};
+use std::{
+    path::PathBuf,
+    process::ExitCode,
+    time::{Duration, Instant},
+};
 
-fn main() -> ExitCode {
-    let ports = serialport::available_ports().expect("No ports found!");
+// cargo run -p firm_rust --example simple_reader -- --simulate --seconds 5
 
-    if ports.is_empty() {
-        eprintln!("No serial ports detected");
-        return ExitCode::FAILURE;
+#[derive(Parser, Debug)]
+#[command(about = "Print every data packet the device sends")]
+struct Args {
+    /// Serial port name (e.g. COM12). If omitted, uses the first detected port.
+    #[arg(long)]
+    port: Option<String>,
+
+    /// Read from a simulated device instead of a serial port.
+    #[arg(long)]
+    simulate: bool,
+
+    /// Fault scenario file to apply to the simulated device's sensors.
+    #[arg(long, requires = "simulate")]
+    faults: Option<PathBuf>,
+
+    /// Stop after this many seconds instead of running until interrupted.
+    #[arg(long)]
+    seconds: Option<f64>,
+}
+
+fn connect(args: &Args) -> Result<(FIRMClient, Option<SimulatorHandle>), String> {
+    if args.simulate {
+        println!("Connecting to simulated device");
+        let mut simulator = DeviceSimulator::new(100.0);
+        if let Some(path) = &args.faults {
+            let faults = FaultScenario::from_file(path).map_err(|e| format!("{e:#}"))?;
+            simulator = simulator.with_faults(faults);
+        }
+        let (client, device) = FIRMClient::new_mock(0.01);
+        let simulator = simulator.spawn(device);
+        return Ok((client, Some(simulator)));
     }
 
-    let port_name = &ports[0].port_name;
+    let port_name = match &args.port {
+        Some(port) => port.clone(),
+        None => {
+            let ports =
+                serialport::available_ports().map_err(|e| format!("No ports found: {e}"))?;
+            ports
+                .first()
+                .ok_or("No serial ports detected")?
+                .port_name
+                .clone()
+        }
+    };
     println!("Connecting to {}", port_name);
 
-    let mut client = match FIRMClient::new(port_name, 2_000_000, 0.1) {
+    let client = FIRMClient::new(&port_name, 2_000_000, 0.1)
+        .map_err(|e| format!("Failed to create client: {}", e))?;
+    Ok((client, None))
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    let (mut client, _simulator) = match connect(&args) {
         Ok(c) => c,
         Err(e) => {
-            eprintln!("Failed to create client: {}", e);
+            eprintln!("{e}");
             return ExitCode::FAILURE;
         }
     };
 
-    client.start();
+    if let Err(e) = client.start() {
+        eprintln!("{e}");
+        return ExitCode::FAILURE;
+    }
+
+    let deadline = args
+        .seconds
+        .map(|s| Instant::now() + Duration::from_secs_f64(s));
+    let mut packets = 0usize;
 
-    loop {
-        while let Ok(packet) = client.get_data_packets(Some(Duration::from_millis(100))) {
-            println!("{:#?}", packet);
+    while deadline.is_none_or(|d| Instant::now() < d) {
+        if let Ok(batch) = client.get_data_packets(Some(Duration::from_millis(100))) {
+            packets += batch.len();
+            for packet in batch {
+                println!("{:#?}", packet);
+            }
         }
 
         if let Some(err) = client.check_error() {
             eprintln!("Error: {}", err);
             return ExitCode::FAILURE;
         }
+    }
 
-        thread::sleep(Duration::from_millis(10));
+    client.stop();
+    println!("Received {packets} packets");
+    if packets == 0 {
+        eprintln!("No packets received");
+        return ExitCode::FAILURE;
     }
+    ExitCode::SUCCESS
 }
@@ -0,0 +1,337 @@
+//! Long-running soak test for FIRMClient against the mock serial device.
+//!
+//! Streams data packets at a realistic rate while concurrently sending commands, restarting
+//! the reader thread and recording packets, and samples memory and link stats periodically.
+//! Fails if live heap bytes grow faster than `--max-growth-bytes-per-minute` over a linear fit.
+//!
+//! Run with: `cargo run --release -p firm_rust --example soak --features soak -- --minutes 360`
+use clap::Parser;
+use firm_core::constants::command::{DEVICE_ID_LENGTH, FIRMCommand, FIRMWARE_VERSION_LENGTH};
+use firm_core::constants::packet::PacketHeader;
+use firm_core::framed_packet::FramedPacket;
+use firm_rust::FIRMClient;
+use firm_rust::mock_serial::MockDeviceHandle;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::io::Write;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Global allocator that tracks live heap bytes and total allocation count.
+struct CountingAllocator;
+
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+static TOTAL_ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            LIVE_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+            TOTAL_ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new_ptr.is_null() {
+            LIVE_BYTES.fetch_add(new_size, Ordering::Relaxed);
+            LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+            TOTAL_ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        }
+        new_ptr
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[derive(Parser, Debug)]
+#[command()]
+struct Args {
+    /// How long to run the soak test, in minutes
+    #[arg(long, default_value_t = 60.0)]
+    minutes: f64,
+
+    /// How often to sample memory and stats, in seconds
+    #[arg(long, default_value_t = 60.0)]
+    sample_seconds: f64,
+
+    /// Data packet rate produced by the mock device, in Hz
+    #[arg(long, default_value_t = 1000.0)]
+    rate_hz: f64,
+
+    /// How often the reader thread is stopped and restarted, in seconds
+    #[arg(long, default_value_t = 30.0)]
+    restart_seconds: f64,
+
+    /// Every Nth command reply is delayed past the client's timeout
+    #[arg(long, default_value_t = 5)]
+    late_reply_every: u64,
+
+    /// How often the device sends an unsolicited Cancel ack, in milliseconds (0 disables)
+    #[arg(long, default_value_t = 500)]
+    unsolicited_ack_ms: u64,
+
+    /// Maximum allowed live heap growth from the linear fit, in bytes per minute
+    #[arg(long, default_value_t = 64.0 * 1024.0)]
+    max_growth_bytes_per_minute: f64,
+
+    /// Maximum allowed growth of buffered responses from the linear fit, per minute
+    #[arg(long, default_value_t = 1.0)]
+    max_buffered_response_growth_per_minute: f64,
+}
+
+/// One periodic measurement.
+struct Sample {
+    minutes: f64,
+    live_bytes: usize,
+    rss_bytes: Option<u64>,
+    buffered_responses: usize,
+    packets_parsed: u64,
+}
+
+/// Resident set size from /proc, where available.
+fn rss_bytes() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let resident_pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    Some(resident_pages * 4096)
+}
+
+/// Least squares slope of `ys` over `xs`.
+fn slope(xs: &[f64], ys: &[f64]) -> f64 {
+    let n = xs.len() as f64;
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = ys.iter().sum::<f64>() / n;
+    let covariance: f64 = xs
+        .iter()
+        .zip(ys)
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let variance: f64 = xs.iter().map(|x| (x - mean_x).powi(2)).sum();
+    if variance == 0.0 {
+        0.0
+    } else {
+        covariance / variance
+    }
+}
+
+fn data_packet(timestamp_seconds: f64) -> FramedPacket {
+    let mut payload = vec![0u8; 120];
+    payload[0..8].copy_from_slice(&timestamp_seconds.to_le_bytes());
+    FramedPacket::new(PacketHeader::Data, 0, payload)
+}
+
+fn device_info_reply() -> FramedPacket {
+    let mut payload = vec![0u8; DEVICE_ID_LENGTH + FIRMWARE_VERSION_LENGTH];
+    payload[DEVICE_ID_LENGTH..DEVICE_ID_LENGTH + 4].copy_from_slice(b"soak");
+    FramedPacket::new(
+        PacketHeader::Response,
+        FIRMCommand::GetDeviceInfo.to_u16(),
+        payload,
+    )
+}
+
+/// Simulated device: streams data packets and answers GetDeviceInfo, delaying some replies
+/// past the client's timeout, and periodically sends acks nobody asked for (as the firmware
+/// does when mock playback ends on its own).
+fn run_device(device: MockDeviceHandle, args: &Args, stop: &AtomicBool) {
+    let tick = Duration::from_millis(10);
+    let packets_per_tick = (args.rate_hz * tick.as_secs_f64()).max(1.0) as usize;
+    let start = Instant::now();
+    let mut commands = 0u64;
+    let mut late_replies: Vec<Instant> = Vec::new();
+    let mut last_unsolicited = Instant::now();
+
+    while !stop.load(Ordering::Relaxed) {
+        let now_seconds = start.elapsed().as_secs_f64();
+        for _ in 0..packets_per_tick {
+            device.inject_framed_packet(data_packet(now_seconds));
+        }
+
+        while let Ok(Some(identifier)) = device.wait_for_command_identifier(Duration::ZERO) {
+            if identifier != FIRMCommand::GetDeviceInfo.to_u16() {
+                continue;
+            }
+            commands += 1;
+            if args.late_reply_every > 0 && commands.is_multiple_of(args.late_reply_every) {
+                late_replies.push(Instant::now() + Duration::from_millis(200));
+            } else {
+                device.inject_framed_packet(device_info_reply());
+            }
+        }
+
+        late_replies.retain(|due| {
+            if Instant::now() >= *due {
+                device.inject_framed_packet(device_info_reply());
+                false
+            } else {
+                true
+            }
+        });
+
+        if args.unsolicited_ack_ms > 0
+            && last_unsolicited.elapsed() >= Duration::from_millis(args.unsolicited_ack_ms)
+        {
+            last_unsolicited = Instant::now();
+            device.inject_framed_packet(FramedPacket::new(
+                PacketHeader::Response,
+                FIRMCommand::Cancel.to_u16(),
+                vec![1],
+            ));
+        }
+
+        thread::sleep(tick);
+    }
+}
+
+fn main() -> ExitCode {
+    let args = Arc::new(Args::parse());
+    let duration = Duration::from_secs_f64(args.minutes * 60.0);
+    let sample_interval = Duration::from_secs_f64(args.sample_seconds);
+    let restart_interval = Duration::from_secs_f64(args.restart_seconds);
+
+    let (mut client, device) = FIRMClient::new_mock(0.01);
+    let stop = Arc::new(AtomicBool::new(false));
+
+    let device_thread = {
+        let args = args.clone();
+        let stop = stop.clone();
+        thread::spawn(move || run_device(device, &args, &stop))
+    };
+
+    // Recorder: formats every packet like a CSV logger would and throws it away.
+    let recording = client.add_receiver();
+    let recorder_thread = thread::spawn(move || {
+        let mut sink = std::io::sink();
+        let mut recorded = 0u64;
+        for packet in recording {
+            let _ = writeln!(
+                sink,
+                "{},{},{}",
+                packet.timestamp_seconds, packet.temperature_celsius, packet.pressure_pascals
+            );
+            recorded += 1;
+        }
+        recorded
+    });
+
+    if let Err(e) = client.start() {
+        eprintln!("Start failed: {e}");
+        return ExitCode::FAILURE;
+    }
+
+    let start = Instant::now();
+    let mut last_sample = start;
+    let mut last_restart = start;
+    let mut samples: Vec<Sample> = Vec::new();
+    let mut commands_answered = 0u64;
+    let mut commands_timed_out = 0u64;
+    let mut restarts = 0u64;
+
+    while start.elapsed() < duration {
+        let _ = client.get_data_packets(Some(Duration::from_millis(10)));
+        while client.check_error().is_some() {}
+
+        match client.get_device_info(Duration::from_millis(50)) {
+            Ok(Some(_)) => commands_answered += 1,
+            Ok(None) => commands_timed_out += 1,
+            Err(e) => {
+                eprintln!("Command failed: {e}");
+                return ExitCode::FAILURE;
+            }
+        }
+
+        if last_restart.elapsed() >= restart_interval {
+            last_restart = Instant::now();
+            client.stop();
+            if let Err(e) = client.start() {
+                eprintln!("Restart failed: {e}");
+                return ExitCode::FAILURE;
+            }
+            restarts += 1;
+        }
+
+        if last_sample.elapsed() >= sample_interval {
+            last_sample = Instant::now();
+            let stats = client.stats();
+            let sample = Sample {
+                minutes: start.elapsed().as_secs_f64() / 60.0,
+                live_bytes: LIVE_BYTES.load(Ordering::Relaxed),
+                rss_bytes: rss_bytes(),
+                buffered_responses: client.buffered_response_count(),
+                packets_parsed: stats.packets_parsed,
+            };
+            println!(
+                "t={:.1}min live={}B rss={} allocs={} buffered_responses={} packets={} rate={:.0}Hz crc_failures={}",
+                sample.minutes,
+                sample.live_bytes,
+                sample
+                    .rss_bytes
+                    .map_or_else(|| "n/a".to_string(), |b| format!("{b}B")),
+                TOTAL_ALLOCATIONS.load(Ordering::Relaxed),
+                sample.buffered_responses,
+                sample.packets_parsed,
+                stats.packet_rate_hz,
+                stats.crc_failures,
+            );
+            samples.push(sample);
+        }
+    }
+
+    stop.store(true, Ordering::Relaxed);
+    let _ = device_thread.join();
+    client.stop();
+    drop(client);
+    let recorded = recorder_thread.join().unwrap_or(0);
+
+    println!(
+        "Done: {commands_answered} commands answered, {commands_timed_out} timed out, {restarts} restarts, {recorded} packets recorded"
+    );
+
+    // Skip the first sample so warm-up allocations (channel blocks, buffers) don't count.
+    let steady: Vec<&Sample> = samples.iter().skip(1).collect();
+    if steady.len() < 2 {
+        eprintln!("Not enough samples for a trend; run longer or sample more often");
+        return ExitCode::FAILURE;
+    }
+    let xs: Vec<f64> = steady.iter().map(|s| s.minutes).collect();
+    let live: Vec<f64> = steady.iter().map(|s| s.live_bytes as f64).collect();
+    let buffered: Vec<f64> = steady.iter().map(|s| s.buffered_responses as f64).collect();
+
+    let live_growth = slope(&xs, &live);
+    let buffered_growth = slope(&xs, &buffered);
+    println!(
+        "Live heap growth: {live_growth:.0} B/min, buffered response growth: {buffered_growth:.2}/min"
+    );
+
+    let mut failed = false;
+    if live_growth > args.max_growth_bytes_per_minute {
+        eprintln!(
+            "FAIL: live heap grows {live_growth:.0} B/min (limit {:.0})",
+            args.max_growth_bytes_per_minute
+        );
+        failed = true;
+    }
+    if buffered_growth > args.max_buffered_response_growth_per_minute {
+        eprintln!(
+            "FAIL: buffered responses grow {buffered_growth:.2}/min (limit {:.2})",
+            args.max_buffered_response_growth_per_minute
+        );
+        failed = true;
+    }
+    if failed {
+        return ExitCode::FAILURE;
+    }
+
+    println!("PASS");
+    ExitCode::SUCCESS
+}
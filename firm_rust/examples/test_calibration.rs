@@ -4,10 +4,12 @@ use firm_core::constants::command::{
     NUMBER_OF_CALIBRATION_OFFSETS, NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS,
 };
 use firm_rust::FIRMClient;
+use firm_rust::simulator::{DeviceSimulator, SimulatorHandle};
 use std::process::ExitCode;
 use std::time::Duration;
 
 // cargo run -p firm_rust --example test_calibration -- --port COM12
+// cargo run -p firm_rust --example test_calibration -- --simulate --collect-seconds 3
 
 #[derive(Parser, Debug)]
 #[command(about = "Reset calibration to identity, then run magnetometer calibration")]
@@ -16,6 +18,10 @@ struct Args {
     #[arg(long)]
     port: Option<String>,
 
+    /// Calibrate a simulated device instead of a serial port.
+    #[arg(long)]
+    simulate: bool,
+
     /// Baud rate for the device.
     #[arg(long, default_value_t = 2_000_000)]
     baud: u32,
@@ -69,6 +75,22 @@ fn pick_port(port: Option<String>) -> Result<String> {
     Ok(first)
 }
 
+fn connect(args: &Args) -> Result<(FIRMClient, Option<SimulatorHandle>)> {
+    if args.simulate {
+        println!("Connecting to simulated device");
+        let (client, device) = FIRMClient::new_mock(args.timeout_s);
+        let simulator = DeviceSimulator::new(100.0).spawn(device);
+        return Ok((client, Some(simulator)));
+    }
+
+    let port_name = pick_port(args.port.clone())?;
+    println!("Connecting to {port_name} @ {} baud", args.baud);
+
+    let client = FIRMClient::new(&port_name, args.baud, args.timeout_s)
+        .with_context(|| format!("Failed to open serial port {port_name}"))?;
+    Ok((client, None))
+}
+
 fn main() -> ExitCode {
     match run() {
         Ok(()) => ExitCode::SUCCESS,
@@ -81,13 +103,8 @@ fn main() -> ExitCode {
 
 fn run() -> Result<()> {
     let args = Args::parse();
-    let port_name = pick_port(args.port)?;
-
-    println!("Connecting to {port_name} @ {} baud", args.baud);
-
-    let mut client = FIRMClient::new(&port_name, args.baud, args.timeout_s)
-        .with_context(|| format!("Failed to open serial port {port_name}"))?;
-    client.start();
+    let (mut client, _simulator) = connect(&args)?;
+    client.start()?;
 
     let offsets = [0.0f32; NUMBER_OF_CALIBRATION_OFFSETS];
     let identity = identity_matrix_row_major();
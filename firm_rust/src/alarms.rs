@@ -0,0 +1,455 @@
+//! Alarm rules evaluated against `LinkStats`, for telling an operator "you are losing data".
+//!
+//! Each rule has a condition and two hold times: the condition has to stay true for
+//! `raise_after` before the alarm is raised, and stay false for `clear_after` before it clears.
+//! A value hovering around a threshold therefore produces one raise and one clear rather than a
+//! stream of flapping events.
+use crate::link_stats::LinkStats;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// A `LinkStats` counter that an alarm rule can watch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum LinkCounter {
+    /// `LinkStats::aged_out_packets`, packets dropped before reaching the consumer.
+    DroppedPackets,
+    /// `LinkStats::crc_failures`.
+    CrcFailures,
+    /// `LinkStats::length_rejects`.
+    LengthRejects,
+    /// `LinkStats::bytes_skipped`.
+    BytesSkipped,
+    /// `LinkStats::resync_events`.
+    ResyncEvents,
+}
+
+impl LinkCounter {
+    /// Reads this counter from a stats snapshot.
+    pub fn value(self, stats: &LinkStats) -> u64 {
+        match self {
+            LinkCounter::DroppedPackets => stats.aged_out_packets,
+            LinkCounter::CrcFailures => stats.crc_failures,
+            LinkCounter::LengthRejects => stats.length_rejects,
+            LinkCounter::BytesSkipped => stats.bytes_skipped,
+            LinkCounter::ResyncEvents => stats.resync_events,
+        }
+    }
+
+    /// Name used in alarm messages.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LinkCounter::DroppedPackets => "dropped packets",
+            LinkCounter::CrcFailures => "CRC failures",
+            LinkCounter::LengthRejects => "length rejects",
+            LinkCounter::BytesSkipped => "skipped bytes",
+            LinkCounter::ResyncEvents => "resync events",
+        }
+    }
+}
+
+/// What an alarm rule checks on every evaluation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum AlarmCondition {
+    /// `counter` went up at all within the last `window`.
+    CounterIncreased {
+        counter: LinkCounter,
+        window: Duration,
+    },
+    /// `counter` went up by more than `per_second` on average over the last `window`.
+    CounterRateAbove {
+        counter: LinkCounter,
+        per_second: f64,
+        window: Duration,
+    },
+    /// `LinkStats::packet_rate_hz` is below `min_hz`.
+    PacketRateBelow { min_hz: f64 },
+}
+
+/// A named alarm condition with hysteresis. See the module docs.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AlarmRule {
+    /// Name reported in `AlarmEvent`s, e.g. "dropped_packets".
+    pub name: String,
+    pub condition: AlarmCondition,
+    /// How long the condition must hold before the alarm is raised.
+    pub raise_after: Duration,
+    /// How long the condition must stay false before the alarm clears.
+    pub clear_after: Duration,
+}
+
+impl AlarmRule {
+    /// Creates a rule that raises and clears as soon as the condition changes.
+    ///
+    /// # Arguments
+    ///
+    /// - `name` (`&str`) - Name reported in `AlarmEvent`s.
+    /// - `condition` (`AlarmCondition`) - What the rule checks.
+    pub fn new(name: &str, condition: AlarmCondition) -> Self {
+        Self {
+            name: name.to_string(),
+            condition,
+            raise_after: Duration::ZERO,
+            clear_after: Duration::ZERO,
+        }
+    }
+
+    /// Sets how long the condition must hold before the alarm is raised.
+    pub fn raise_after(mut self, hold: Duration) -> Self {
+        self.raise_after = hold;
+        self
+    }
+
+    /// Sets how long the condition must stay false before the alarm clears.
+    pub fn clear_after(mut self, hold: Duration) -> Self {
+        self.clear_after = hold;
+        self
+    }
+
+    /// Raised when any packet was dropped in the last `window`, cleared once `window` passes
+    /// without a drop.
+    pub fn dropped_packets(window: Duration) -> Self {
+        Self::new(
+            "dropped_packets",
+            AlarmCondition::CounterIncreased {
+                counter: LinkCounter::DroppedPackets,
+                window,
+            },
+        )
+    }
+
+    /// Raised when CRC failures average more than `per_second` over `window`, cleared once
+    /// they've stayed at or below it for another `window`.
+    pub fn crc_failure_rate(per_second: f64, window: Duration) -> Self {
+        Self::new(
+            "crc_failure_rate",
+            AlarmCondition::CounterRateAbove {
+                counter: LinkCounter::CrcFailures,
+                per_second,
+                window,
+            },
+        )
+        .clear_after(window)
+    }
+
+    /// Raised when the packet rate stays below `fraction` of `expected_hz` for `hold`, cleared
+    /// once it has been back above for `hold`.
+    pub fn packet_rate_below(expected_hz: f64, fraction: f64, hold: Duration) -> Self {
+        Self::new(
+            "packet_rate_low",
+            AlarmCondition::PacketRateBelow {
+                min_hz: expected_hz * fraction,
+            },
+        )
+        .raise_after(hold)
+        .clear_after(hold)
+    }
+
+    /// The rules ops dashboards start from: any drop in 5 s, more than 1 CRC failure per second,
+    /// and packet rate under 80% of `expected_hz` for 10 s.
+    ///
+    /// # Arguments
+    ///
+    /// - `expected_hz` (`f64`) - The device's configured data frequency.
+    pub fn defaults(expected_hz: f64) -> Vec<Self> {
+        vec![
+            Self::dropped_packets(Duration::from_secs(5)),
+            Self::crc_failure_rate(1.0, Duration::from_secs(5)),
+            Self::packet_rate_below(expected_hz, 0.8, Duration::from_secs(10)),
+        ]
+    }
+}
+
+/// Emitted when an alarm changes state.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum AlarmEvent {
+    AlarmRaised {
+        rule: String,
+        message: String,
+        stats: LinkStats,
+    },
+    AlarmCleared {
+        rule: String,
+        stats: LinkStats,
+    },
+}
+
+impl AlarmEvent {
+    /// Name of the rule that changed state.
+    pub fn rule(&self) -> &str {
+        match self {
+            AlarmEvent::AlarmRaised { rule, .. } | AlarmEvent::AlarmCleared { rule, .. } => rule,
+        }
+    }
+
+    /// Returns true for `AlarmRaised`.
+    pub fn is_raised(&self) -> bool {
+        matches!(self, AlarmEvent::AlarmRaised { .. })
+    }
+}
+
+/// A rule together with what it needs to remember between evaluations.
+struct RuleState {
+    rule: AlarmRule,
+    /// Counter samples covering the rule's window, plus one just before it as the baseline.
+    history: VecDeque<(Instant, u64)>,
+    /// When the condition started disagreeing with `active`.
+    pending_since: Option<Instant>,
+    active: bool,
+}
+
+impl RuleState {
+    /// Checks the condition against `stats`, returning a message describing it when true.
+    fn check(&mut self, now: Instant, stats: &LinkStats) -> Option<String> {
+        match self.rule.condition {
+            AlarmCondition::CounterIncreased { counter, window } => {
+                let increase = self.increase_over(now, counter.value(stats), window);
+                (increase > 0).then(|| {
+                    format!(
+                        "{increase} {} in the last {:.1} s",
+                        counter.as_str(),
+                        window.as_secs_f64()
+                    )
+                })
+            }
+            AlarmCondition::CounterRateAbove {
+                counter,
+                per_second,
+                window,
+            } => {
+                let increase = self.increase_over(now, counter.value(stats), window);
+                let rate = increase as f64 / window.as_secs_f64().max(f64::EPSILON);
+                (rate > per_second).then(|| {
+                    format!(
+                        "{rate:.2} {} per second (limit {per_second})",
+                        counter.as_str()
+                    )
+                })
+            }
+            AlarmCondition::PacketRateBelow { min_hz } => {
+                (stats.packet_rate_hz < min_hz).then(|| {
+                    format!(
+                        "packet rate {:.1} Hz below {min_hz:.1} Hz",
+                        stats.packet_rate_hz
+                    )
+                })
+            }
+        }
+    }
+
+    /// Records `value` and returns how much it went up over the last `window`.
+    fn increase_over(&mut self, now: Instant, value: u64, window: Duration) -> u64 {
+        self.history.push_back((now, value));
+        // Keep the newest sample at least `window` old as the baseline to compare against.
+        while self.history.len() > 1 && now.duration_since(self.history[1].0) >= window {
+            self.history.pop_front();
+        }
+        let baseline = self.history.front().map_or(value, |&(_, v)| v);
+        value.saturating_sub(baseline)
+    }
+
+    fn evaluate(&mut self, now: Instant, stats: &LinkStats) -> Option<AlarmEvent> {
+        let message = self.check(now, stats);
+        let condition = message.is_some();
+        if condition == self.active {
+            self.pending_since = None;
+            return None;
+        }
+
+        let since = *self.pending_since.get_or_insert(now);
+        let hold = if condition {
+            self.rule.raise_after
+        } else {
+            self.rule.clear_after
+        };
+        if now.duration_since(since) < hold {
+            return None;
+        }
+
+        self.active = condition;
+        self.pending_since = None;
+        let rule = self.rule.name.clone();
+        Some(match message {
+            Some(message) => AlarmEvent::AlarmRaised {
+                rule,
+                message,
+                stats: *stats,
+            },
+            None => AlarmEvent::AlarmCleared {
+                rule,
+                stats: *stats,
+            },
+        })
+    }
+}
+
+/// Evaluates a set of `AlarmRule`s against successive `LinkStats` snapshots.
+///
+/// `FIRMClient` runs one of these on its reader thread; use it directly to evaluate rules
+/// against stats from somewhere else.
+#[derive(Default)]
+pub struct AlarmMonitor {
+    rules: Vec<RuleState>,
+}
+
+impl AlarmMonitor {
+    /// Creates a monitor with every alarm initially cleared.
+    pub fn new(rules: Vec<AlarmRule>) -> Self {
+        Self {
+            rules: rules
+                .into_iter()
+                .map(|rule| RuleState {
+                    rule,
+                    history: VecDeque::new(),
+                    pending_since: None,
+                    active: false,
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns true if there are no rules to evaluate.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Evaluates every rule against `stats` taken at `now`.
+    ///
+    /// # Arguments
+    ///
+    /// - `now` (`Instant`) - When `stats` was taken. Must not go backwards between calls.
+    /// - `stats` (`&LinkStats`) - The current link statistics.
+    ///
+    /// # Returns
+    ///
+    /// - `Vec<AlarmEvent>` - Alarms that were raised or cleared by this evaluation.
+    pub fn evaluate(&mut self, now: Instant, stats: &LinkStats) -> Vec<AlarmEvent> {
+        self.rules
+            .iter_mut()
+            .filter_map(|state| state.evaluate(now, stats))
+            .collect()
+    }
+
+    /// Returns the names of the alarms currently raised.
+    pub fn active_alarms(&self) -> Vec<String> {
+        self.rules
+            .iter()
+            .filter(|state| state.active)
+            .map(|state| state.rule.name.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feeds `monitor` one snapshot per 100 ms for `steps` steps, with `update` filling in the
+    /// stats at each step, and returns every event emitted.
+    fn drive(
+        monitor: &mut AlarmMonitor,
+        start: Instant,
+        steps: std::ops::Range<u64>,
+        mut update: impl FnMut(u64, &mut LinkStats),
+    ) -> Vec<AlarmEvent> {
+        let mut stats = LinkStats::default();
+        let mut events = Vec::new();
+        for step in steps {
+            update(step, &mut stats);
+            events.extend(monitor.evaluate(start + Duration::from_millis(step * 100), &stats));
+        }
+        events
+    }
+
+    fn raised_and_cleared(events: &[AlarmEvent]) -> (usize, usize) {
+        let raised = events.iter().filter(|e| e.is_raised()).count();
+        (raised, events.len() - raised)
+    }
+
+    #[test]
+    fn test_dropped_packets_raises_then_clears_after_quiet_window() {
+        let mut monitor =
+            AlarmMonitor::new(vec![AlarmRule::dropped_packets(Duration::from_secs(5))]);
+        let start = Instant::now();
+
+        // A burst of drops between 1 s and 2 s, then nothing for the rest of the 10 s.
+        let events = drive(&mut monitor, start, 0..100, |step, stats| {
+            if (10..20).contains(&step) {
+                stats.aged_out_packets += 3;
+            }
+        });
+
+        assert_eq!(raised_and_cleared(&events), (1, 1));
+        assert_eq!(events[0].rule(), "dropped_packets");
+        let AlarmEvent::AlarmCleared { stats, .. } = &events[1] else {
+            panic!("expected a clear, got {:?}", events[1]);
+        };
+        assert_eq!(stats.aged_out_packets, 30);
+        assert!(monitor.active_alarms().is_empty());
+    }
+
+    #[test]
+    fn test_crc_failure_rate_raises_above_limit_and_clears_below() {
+        let mut monitor = AlarmMonitor::new(vec![AlarmRule::crc_failure_rate(
+            1.0,
+            Duration::from_secs(2),
+        )]);
+        let start = Instant::now();
+
+        // 5 failures per second for 5 s, then 1 failure every 2 s (0.5/s) until 20 s.
+        let events = drive(&mut monitor, start, 0..200, |step, stats| {
+            let every = if step < 50 { 2 } else { 20 };
+            if step % every == 0 {
+                stats.crc_failures += 1;
+            }
+        });
+
+        assert_eq!(raised_and_cleared(&events), (1, 1));
+        assert!(events[0].is_raised());
+        assert_eq!(events[0].rule(), "crc_failure_rate");
+    }
+
+    #[test]
+    fn test_packet_rate_below_does_not_flap_around_threshold() {
+        let mut monitor = AlarmMonitor::new(vec![AlarmRule::packet_rate_below(
+            100.0,
+            0.8,
+            Duration::from_secs(2),
+        )]);
+        let start = Instant::now();
+
+        let events = drive(&mut monitor, start, 0..200, |step, stats| {
+            stats.packet_rate_hz = match step {
+                // Healthy, apart from a dip shorter than the hold time.
+                0..20 => 100.0,
+                20..25 => 50.0,
+                25..50 => 100.0,
+                // Flapping across the threshold every 100 ms, then low for good.
+                50..70 if step % 2 == 0 => 100.0,
+                50..100 => 50.0,
+                // Recovered, with another short dip.
+                100..150 => 100.0,
+                150..155 => 50.0,
+                _ => 100.0,
+            };
+        });
+
+        assert_eq!(raised_and_cleared(&events), (1, 1));
+        assert!(events[0].is_raised());
+        assert!(!events[1].is_raised());
+    }
+
+    #[test]
+    fn test_rules_are_independent() {
+        let mut monitor = AlarmMonitor::new(AlarmRule::defaults(100.0));
+        let start = Instant::now();
+
+        let events = drive(&mut monitor, start, 0..10, |_, stats| {
+            stats.packet_rate_hz = 100.0;
+            stats.aged_out_packets += 1;
+        });
+
+        assert_eq!(raised_and_cleared(&events), (1, 0));
+        assert_eq!(monitor.active_alarms(), vec!["dropped_packets".to_string()]);
+    }
+}
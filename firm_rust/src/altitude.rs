@@ -0,0 +1,272 @@
+//! The client's side of `FIRMData::pressure_altitude_meters`, which the reader thread's
+//! `SerialParser` fills in from its reference pressure `p0`. This keeps the settings that
+//! decide `p0` and hands it to the parser before each read.
+//!
+//! Zeroing with `FIRMClient::zero_out_pressure_altitude` re-baselines `p0` to the averaged
+//! ground pressure, so every packet after it reports height above the ground.
+//!
+//! When the board also streams a secondary barometer (`FIRMBaroPacket`), its readings around
+//! each packet's timestamp are averaged with the packet's own pressure, and the altitude is
+//! recomputed from that against the parser's `p0`.
+pub use firm_core::altitude::{STANDARD_SEA_LEVEL_PRESSURE_PASCALS, pressure_altitude_meters};
+use firm_core::firm_packets::{FIRMBaroPacket, FIRMData};
+use std::collections::VecDeque;
+use std::sync::mpsc::Sender;
+
+/// Number of packets `zero_out_pressure_altitude` averages unless told otherwise.
+pub const DEFAULT_ZERO_SAMPLES: usize = 50;
+
+/// Secondary barometer readings within this many seconds of a packet's timestamp are fused
+/// into its altitude.
+pub const BARO_FUSION_WINDOW_SECONDS: f64 = 0.005;
+
+/// Most secondary barometer readings kept for fusion, so they can't pile up if data packets
+/// stop arriving.
+const MAX_BARO_READINGS: usize = 256;
+
+/// An in-progress `zero_out_pressure_altitude` call.
+struct Zeroing {
+    samples: usize,
+    sum_pascals: f64,
+    count: usize,
+    done: Sender<f32>,
+}
+
+/// Altitude settings shared between the client and the reader thread.
+pub(crate) struct AltitudeState {
+    /// Set by `FIRMClient::set_sea_level_pressure`, kept to report the ground's altitude.
+    sea_level_pressure_pascals: f32,
+    /// Averaged ground pressure, used as `p0` instead of the sea level pressure once set.
+    ground_pressure_pascals: Option<f32>,
+    zeroing: Option<Zeroing>,
+    /// Recent secondary barometer readings as `(timestamp_seconds, pressure_pascals)`, oldest
+    /// first.
+    baro_readings: VecDeque<(f64, f32)>,
+}
+
+impl Default for AltitudeState {
+    fn default() -> Self {
+        Self {
+            sea_level_pressure_pascals: STANDARD_SEA_LEVEL_PRESSURE_PASCALS,
+            ground_pressure_pascals: None,
+            zeroing: None,
+            baro_readings: VecDeque::new(),
+        }
+    }
+}
+
+impl AltitudeState {
+    /// Averages the next `samples` packets' pressure into a new ground reference, sending its
+    /// altitude above sea level on `done` when finished. Replaces any zeroing already in
+    /// progress.
+    pub(crate) fn start_zeroing(&mut self, samples: usize, done: Sender<f32>) {
+        self.zeroing = Some(Zeroing {
+            samples,
+            sum_pascals: 0.0,
+            count: 0,
+            done,
+        });
+    }
+
+    pub(crate) fn cancel_zeroing(&mut self) {
+        self.zeroing = None;
+    }
+
+    /// Switches to altitude above sea level computed with `pascals` as the sea level pressure,
+    /// dropping the ground reference.
+    pub(crate) fn set_sea_level_pressure(&mut self, pascals: f32) {
+        self.sea_level_pressure_pascals = pascals;
+        self.ground_pressure_pascals = None;
+        self.zeroing = None;
+    }
+
+    /// Returns the pressure the parser should measure altitude from: the ground pressure once
+    /// zeroing has set it, and the sea level pressure before that.
+    pub(crate) fn reference_pressure_pascals(&self) -> f32 {
+        self.ground_pressure_pascals
+            .unwrap_or(self.sea_level_pressure_pascals)
+    }
+
+    /// Returns the ground's altitude above sea level, if zeroing has set a ground pressure.
+    pub(crate) fn ground_reference_meters(&self) -> Option<f32> {
+        self.ground_pressure_pascals
+            .map(|ground| pressure_altitude_meters(ground, self.sea_level_pressure_pascals))
+    }
+
+    /// Keeps a secondary barometer reading to fuse into the altitude of nearby packets.
+    pub(crate) fn record_baro(&mut self, reading: &FIRMBaroPacket) {
+        if !reading.pressure_pascals.is_finite() {
+            return;
+        }
+        if self.baro_readings.len() >= MAX_BARO_READINGS {
+            self.baro_readings.pop_front();
+        }
+        self.baro_readings
+            .push_back((reading.timestamp_seconds, reading.pressure_pascals));
+    }
+
+    /// Averages `packet.pressure_pascals` with the secondary readings within
+    /// `BARO_FUSION_WINDOW_SECONDS` of it, dropping readings too old for any later packet.
+    fn fused_pressure(&mut self, packet: &FIRMData) -> f32 {
+        let t = packet.timestamp_seconds;
+        while self
+            .baro_readings
+            .front()
+            .is_some_and(|&(reading_t, _)| reading_t < t - BARO_FUSION_WINDOW_SECONDS)
+        {
+            self.baro_readings.pop_front();
+        }
+
+        let (mut sum, mut count) = match packet.pressure_pascals {
+            p if p.is_finite() => (p as f64, 1usize),
+            _ => (0.0, 0),
+        };
+        for &(_, pressure) in self
+            .baro_readings
+            .iter()
+            .filter(|&&(reading_t, _)| reading_t <= t + BARO_FUSION_WINDOW_SECONDS)
+        {
+            sum += pressure as f64;
+            count += 1;
+        }
+        match count {
+            0 => packet.pressure_pascals,
+            _ => (sum / count as f64) as f32,
+        }
+    }
+
+    /// Feeds a parsed packet to any zeroing in progress, and recomputes its
+    /// `pressure_altitude_meters` if secondary barometer readings were fused into it.
+    /// `packet.pressure_pascals` itself stays the primary barometer's reading. A new ground
+    /// pressure only reaches the parser with the next read.
+    ///
+    /// # Arguments
+    ///
+    /// - `packet` (`&mut FIRMData`) - The packet, with the altitude the parser filled in.
+    /// - `reference_pressure_pascals` (`f32`) - The `p0` the parser used for it.
+    pub(crate) fn apply(&mut self, packet: &mut FIRMData, reference_pressure_pascals: f32) {
+        let pressure = self.fused_pressure(packet);
+        if pressure.to_bits() != packet.pressure_pascals.to_bits() {
+            packet.pressure_altitude_meters =
+                pressure_altitude_meters(pressure, reference_pressure_pascals);
+        }
+
+        if let Some(zeroing) = &mut self.zeroing
+            && pressure.is_finite()
+            && pressure > 0.0
+        {
+            zeroing.sum_pascals += pressure as f64;
+            zeroing.count += 1;
+            if zeroing.count >= zeroing.samples {
+                let ground = (zeroing.sum_pascals / zeroing.count as f64) as f32;
+                let _ = zeroing.done.send(pressure_altitude_meters(
+                    ground,
+                    self.sea_level_pressure_pascals,
+                ));
+                self.ground_pressure_pascals = Some(ground);
+                self.zeroing = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::channel;
+
+    fn packet_at(pressure_pascals: f32) -> FIRMData {
+        let mut packet = FIRMData::from_fields(0.0, [0.0; FIRMData::NUM_F32_FIELDS]);
+        packet.pressure_pascals = pressure_pascals;
+        packet.pressure_altitude_meters =
+            pressure_altitude_meters(pressure_pascals, STANDARD_SEA_LEVEL_PRESSURE_PASCALS);
+        packet
+    }
+
+    fn baro_at(timestamp_seconds: f64, pressure_pascals: f32) -> FIRMBaroPacket {
+        FIRMBaroPacket {
+            timestamp_seconds,
+            pressure_pascals,
+            temperature_celsius: 20.0,
+        }
+    }
+
+    #[test]
+    fn test_standard_atmosphere_altitude() {
+        assert_eq!(pressure_altitude_meters(101_325.0, 101_325.0), 0.0);
+        // 1 km in the standard atmosphere is about 89.87 kPa.
+        let altitude = pressure_altitude_meters(89_874.6, STANDARD_SEA_LEVEL_PRESSURE_PASCALS);
+        assert!((altitude - 1000.0).abs() < 1.0, "{altitude}");
+        // A higher sea level pressure puts the same reading higher up.
+        assert!(pressure_altitude_meters(100_000.0, 102_000.0) > 100.0);
+    }
+
+    #[test]
+    fn test_zeroing_averages_noisy_samples() {
+        let mut state = AltitudeState::default();
+        let (done, finished) = channel();
+        state.start_zeroing(4, done);
+
+        let pressures = [99_990.0, 100_010.0, 99_990.0, 100_010.0];
+        for pressure in pressures {
+            let mut packet = packet_at(pressure);
+            state.apply(&mut packet, STANDARD_SEA_LEVEL_PRESSURE_PASCALS);
+            // Without secondary readings the parser's altitude is left alone.
+            assert_eq!(
+                packet.pressure_altitude_meters,
+                packet_at(pressure).pressure_altitude_meters
+            );
+        }
+        // The ground reference is the altitude of the averaged pressure, which becomes `p0`.
+        let reference = finished.try_recv().unwrap();
+        let expected = pressure_altitude_meters(100_000.0, STANDARD_SEA_LEVEL_PRESSURE_PASCALS);
+        assert!((reference - expected).abs() < 1e-3);
+        assert_eq!(state.ground_reference_meters(), Some(reference));
+        assert_eq!(state.reference_pressure_pascals(), 100_000.0);
+
+        state.set_sea_level_pressure(102_000.0);
+        assert_eq!(state.ground_reference_meters(), None);
+        assert_eq!(state.reference_pressure_pascals(), 102_000.0);
+    }
+
+    #[test]
+    fn test_secondary_baro_readings_are_fused_by_timestamp() {
+        let mut state = AltitudeState::default();
+        state.set_sea_level_pressure(100_000.0);
+        // Too old for the packet at t = 1 s, then three within the window and one after it.
+        for (t, pressure) in [(0.9, 50_000.0), (0.996, 99_000.0), (1.0, 99_100.0)] {
+            state.record_baro(&baro_at(t, pressure));
+        }
+        state.record_baro(&baro_at(1.004, 99_200.0));
+        state.record_baro(&baro_at(1.1, 50_000.0));
+
+        let mut packet = packet_at(99_300.0);
+        packet.timestamp_seconds = 1.0;
+        state.apply(&mut packet, 100_000.0);
+        let expected = pressure_altitude_meters(99_150.0, 100_000.0);
+        assert!(
+            (packet.pressure_altitude_meters - expected).abs() < 1e-3,
+            "{} != {expected}",
+            packet.pressure_altitude_meters
+        );
+        assert_eq!(packet.pressure_pascals, 99_300.0);
+
+        // A primary reading that's NaN leaves just the secondary one.
+        let mut packet = packet_at(f32::NAN);
+        packet.timestamp_seconds = 1.1;
+        state.apply(&mut packet, 100_000.0);
+        assert_eq!(
+            packet.pressure_altitude_meters,
+            pressure_altitude_meters(50_000.0, 100_000.0)
+        );
+
+        // With no readings nearby it's the parser's altitude from the primary barometer.
+        let mut packet = packet_at(99_300.0);
+        packet.timestamp_seconds = 5.0;
+        state.apply(&mut packet, 100_000.0);
+        assert_eq!(
+            packet.pressure_altitude_meters,
+            packet_at(99_300.0).pressure_altitude_meters
+        );
+    }
+}
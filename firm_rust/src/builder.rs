@@ -0,0 +1,241 @@
+//! Serial port settings beyond baud rate and timeout, see `FIRMClientBuilder`.
+//!
+//! FIRM itself talks 8N1 without flow control, which is what `FIRMClient::new` opens. Adapters
+//! in between, e.g. an RS-422 converter on a long cable, can need other parity or stop bits.
+use crate::{FIRMClient, FIRMClientError};
+use serialport::{DataBits, FlowControl, Parity, SerialPortBuilder, StopBits};
+use std::time::Duration;
+
+/// How characters are framed on the serial line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SerialFraming {
+    pub data_bits: DataBits,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+    pub flow_control: FlowControl,
+}
+
+impl Default for SerialFraming {
+    /// 8N1 without flow control, as FIRM uses.
+    fn default() -> Self {
+        Self {
+            data_bits: DataBits::Eight,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            flow_control: FlowControl::None,
+        }
+    }
+}
+
+impl SerialFraming {
+    /// Checks that FIRM's binary frames can get through a line framed like this.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<(), FIRMClientError>` - `FIRMClientError::InvalidSerialSettings` naming the
+    ///   problem.
+    pub fn validate(&self) -> Result<(), FIRMClientError> {
+        let invalid = |reason| Err(FIRMClientError::InvalidSerialSettings { reason });
+        if self.data_bits != DataBits::Eight {
+            return invalid("FIRM frames are binary, so they need 8 data bits");
+        }
+        if self.flow_control == FlowControl::Software {
+            return invalid(
+                "software flow control would take the XON/XOFF bytes out of binary frames",
+            );
+        }
+        Ok(())
+    }
+
+    /// Applies the framing to `builder`.
+    fn apply(&self, builder: SerialPortBuilder) -> SerialPortBuilder {
+        builder
+            .data_bits(self.data_bits)
+            .parity(self.parity)
+            .stop_bits(self.stop_bits)
+            .flow_control(self.flow_control)
+    }
+}
+
+/// Builds the `serialport` settings a FIRM serial port is opened with.
+pub(crate) fn serial_port_builder(
+    port_name: &str,
+    baud_rate: u32,
+    timeout: Duration,
+    framing: &SerialFraming,
+) -> SerialPortBuilder {
+    framing.apply(serialport::new(port_name, baud_rate).timeout(timeout))
+}
+
+/// Opens a `FIRMClient` on a serial port with settings other than `FIRMClient::new`'s.
+///
+/// ```no_run
+/// use firm_rust::builder::FIRMClientBuilder;
+/// use firm_rust::{Parity, StopBits};
+///
+/// let client = FIRMClientBuilder::new()
+///     .parity(Parity::Even)
+///     .stop_bits(StopBits::Two)
+///     .open("/dev/ttyUSB0")?;
+/// # Ok::<(), firm_rust::FIRMClientError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct FIRMClientBuilder {
+    baud_rate: u32,
+    timeout: Duration,
+    framing: SerialFraming,
+}
+
+impl Default for FIRMClientBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FIRMClientBuilder {
+    /// Creates a builder with `FIRMClient::new`'s usual settings: 2,000,000 baud, a 0.1 s read
+    /// timeout and 8N1 without flow control.
+    pub fn new() -> Self {
+        Self {
+            baud_rate: 2_000_000,
+            timeout: Duration::from_millis(100),
+            framing: SerialFraming::default(),
+        }
+    }
+
+    /// Sets the baud rate.
+    pub fn baud(mut self, baud_rate: u32) -> Self {
+        self.baud_rate = baud_rate;
+        self
+    }
+
+    /// Sets the read timeout for the serial port.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets the number of data bits. FIRM needs `DataBits::Eight`.
+    pub fn data_bits(mut self, data_bits: DataBits) -> Self {
+        self.framing.data_bits = data_bits;
+        self
+    }
+
+    /// Sets the parity bit.
+    pub fn parity(mut self, parity: Parity) -> Self {
+        self.framing.parity = parity;
+        self
+    }
+
+    /// Sets the number of stop bits.
+    pub fn stop_bits(mut self, stop_bits: StopBits) -> Self {
+        self.framing.stop_bits = stop_bits;
+        self
+    }
+
+    /// Sets the flow control. `FlowControl::Software` can't carry FIRM's binary frames.
+    pub fn flow_control(mut self, flow_control: FlowControl) -> Self {
+        self.framing.flow_control = flow_control;
+        self
+    }
+
+    /// Checks the settings and returns the `serialport` builder they open the port with.
+    ///
+    /// # Arguments
+    ///
+    /// - `port_name` (`&str`) - The serial port to open, e.g. "/dev/ttyUSB0".
+    ///
+    /// # Returns
+    ///
+    /// - `Result<SerialPortBuilder, FIRMClientError>` - The port settings, or
+    ///   `FIRMClientError::InvalidSerialSettings` if FIRM can't work over them.
+    pub fn port_builder(&self, port_name: &str) -> Result<SerialPortBuilder, FIRMClientError> {
+        self.check()?;
+        Ok(serial_port_builder(
+            port_name,
+            self.baud_rate,
+            self.timeout,
+            &self.framing,
+        ))
+    }
+
+    /// Checks the settings and opens the client. The settings are kept for reopening the
+    /// port, e.g. after a reboot.
+    ///
+    /// # Arguments
+    ///
+    /// - `port_name` (`&str`) - The serial port to open, e.g. "/dev/ttyUSB0".
+    ///
+    /// # Returns
+    ///
+    /// - `Result<FIRMClient, FIRMClientError>` - The client,
+    ///   `FIRMClientError::InvalidSerialSettings` if FIRM can't work over the settings, or
+    ///   `FIRMClientError::SerialOpen` if the port couldn't be opened.
+    pub fn open(&self, port_name: &str) -> Result<FIRMClient, FIRMClientError> {
+        self.check()?;
+        FIRMClient::open_serial(port_name, self.baud_rate, self.timeout, self.framing)
+    }
+
+    fn check(&self) -> Result<(), FIRMClientError> {
+        if self.baud_rate == 0 {
+            return Err(FIRMClientError::InvalidSerialSettings {
+                reason: "the baud rate must be above 0",
+            });
+        }
+        self.framing.validate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_sets_up_the_port() {
+        assert_eq!(
+            FIRMClientBuilder::new().port_builder("COM8").unwrap(),
+            serialport::new("COM8", 2_000_000)
+                .timeout(Duration::from_millis(100))
+                .data_bits(DataBits::Eight)
+                .parity(Parity::None)
+                .stop_bits(StopBits::One)
+                .flow_control(FlowControl::None)
+        );
+
+        let rs422 = FIRMClientBuilder::new()
+            .baud(115_200)
+            .timeout(Duration::from_millis(250))
+            .parity(Parity::Even)
+            .stop_bits(StopBits::Two)
+            .flow_control(FlowControl::Hardware)
+            .port_builder("/dev/ttyUSB0")
+            .unwrap();
+        assert_eq!(
+            rs422,
+            serialport::new("/dev/ttyUSB0", 115_200)
+                .timeout(Duration::from_millis(250))
+                .parity(Parity::Even)
+                .stop_bits(StopBits::Two)
+                .flow_control(FlowControl::Hardware)
+        );
+    }
+
+    #[test]
+    fn test_builder_rejects_settings_firm_cant_use() {
+        let builders = [
+            FIRMClientBuilder::new().baud(0),
+            FIRMClientBuilder::new().data_bits(DataBits::Seven),
+            FIRMClientBuilder::new().flow_control(FlowControl::Software),
+        ];
+        for builder in builders {
+            assert!(matches!(
+                builder.port_builder("COM8"),
+                Err(FIRMClientError::InvalidSerialSettings { .. })
+            ));
+            assert!(matches!(
+                builder.open("COM8"),
+                Err(FIRMClientError::InvalidSerialSettings { .. })
+            ));
+        }
+    }
+}
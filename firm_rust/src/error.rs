@@ -0,0 +1,558 @@
+//! Errors reported by `FIRMClient`, either returned from a call or delivered from the
+//! background threads through `check_error`.
+use firm_core::constants::command::FIRMCommand;
+use firm_core::data_parser::ResyncReason;
+use firm_core::error_codes::{self, ErrorCode, ErrorCoded};
+use firm_core::firm_packets::ResponseViolation;
+use firm_core::framed_packet::FrameError;
+use firm_core::validation::FieldFault;
+use std::collections::VecDeque;
+use std::io;
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// What went wrong in a `FIRMClient`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FIRMClientError {
+    /// The serial port couldn't be opened.
+    SerialOpen {
+        port: String,
+        kind: io::ErrorKind,
+        message: String,
+    },
+    /// Connecting to a TCP bridge failed.
+    Connect {
+        address: String,
+        kind: io::ErrorKind,
+        message: String,
+    },
+    /// The port was abandoned by `stop_with_timeout` or lost with a panicked reader thread, and
+    /// this kind of connection (e.g. a custom transport) can't be opened again by `start()`.
+    CannotReopen { connection: &'static str },
+    /// Reading from the device failed.
+    Read {
+        kind: io::ErrorKind,
+        message: String,
+    },
+    /// Writing a command to the device failed.
+    Write {
+        kind: io::ErrorKind,
+        message: String,
+    },
+    /// The device didn't answer `command` within `timeout`.
+    CommandTimeout {
+        command: FIRMCommand,
+        timeout: Duration,
+    },
+    /// The device went away, e.g. it was unplugged or the bridge closed the connection.
+    Disconnected { message: String },
+    /// A callback registered with `subscribe`, `on_alarm` or `on_threshold` panicked and was
+    /// removed.
+    CallbackPanicked { callback: String, message: String },
+    /// Writing the raw recording failed. The recording stops; reading carries on.
+    Recording { message: String },
+    /// Streaming a mock log to the device failed.
+    MockStream { message: String },
+    /// `operation` doesn't apply to this kind of connection, e.g. sending a command to a client
+    /// replaying a log, or pausing a live device.
+    NotSupported {
+        operation: &'static str,
+        connection: &'static str,
+    },
+    /// The reader thread panicked and stopped. Its port is dropped and reopened by the next
+    /// `start()`, as after `StopTimedOut`.
+    ThreadPanicked { message: String },
+    /// `stop_with_timeout` gave up waiting for the reader thread. The thread is left running
+    /// detached until its read returns, and the port it held is reopened by the next `start()`.
+    StopTimedOut { timeout: Duration },
+    /// `reboot_and_reconnect` sent the reboot, but the port didn't come back within `timeout`.
+    ReconnectTimedOut { port: String, timeout: Duration },
+    /// `reboot_and_reconnect` reopened the port, but no data packet arrived within `timeout`.
+    NoPacketsAfterReconnect { port: String, timeout: Duration },
+    /// `Controller::shutdown` closed the client, so the call couldn't be made.
+    ShutDown,
+    /// No data packet arrived for `since`, past the limit set by `set_idle_timeout` or
+    /// `expect_first_packet_within`. Reported again each interval the link stays quiet.
+    LinkIdle { since: Duration },
+    /// The frame starting `byte_offset` bytes into the stream was rejected or couldn't be
+    /// decoded. Only reported after `collect_parse_errors(true)`.
+    Parse { byte_offset: u64, error: FrameError },
+    /// The parser stopped finding frames at `byte_offset` bytes into the stream, because of
+    /// `reason`. Only reported after `set_resync_reporting(true)`.
+    LostSync {
+        byte_offset: u64,
+        reason: ResyncReason,
+    },
+    /// A data packet's timestamp wasn't after the previous packet's, while
+    /// `TimestampPolicy::Warn` was set. The packet was passed on anyway.
+    TimestampOutOfOrder {
+        previous_seconds: f64,
+        timestamp_seconds: f64,
+    },
+    /// The device's timestamps started over near zero, as they do after a reboot. Reported
+    /// under every `TimestampPolicy` but `Off`.
+    DeviceRestartDetected {
+        previous_seconds: f64,
+        timestamp_seconds: f64,
+    },
+    /// `TIMESTAMP_RESYNC_PACKETS` packets in a row were behind `previous_seconds` under
+    /// `TimestampPolicy::Strict` or `Nudge`, so the packet at `timestamp_seconds` became the new
+    /// reference.
+    TimestampResynced {
+        previous_seconds: f64,
+        timestamp_seconds: f64,
+    },
+    /// A response broke the protocol while `ResponseValidation::Strict` was set, so it was
+    /// dropped. `payload` is the response's payload as it arrived.
+    ProtocolViolation {
+        command: FIRMCommand,
+        violations: Vec<ResponseViolation>,
+        payload: Vec<u8>,
+    },
+    /// `FIRMClientBuilder` was given serial settings FIRM can't work over.
+    InvalidSerialSettings { reason: &'static str },
+    /// A data packet failed validation while `DataValidation::Flag` was set. It was still
+    /// passed on.
+    ImplausibleData {
+        timestamp_seconds: f64,
+        faults: Vec<FieldFault>,
+    },
+}
+
+impl FIRMClientError {
+    /// Classifies a failed read, treating a dropped connection as `Disconnected`.
+    pub(crate) fn read(e: &io::Error) -> Self {
+        if is_disconnect(e.kind()) {
+            return FIRMClientError::Disconnected {
+                message: e.to_string(),
+            };
+        }
+        FIRMClientError::Read {
+            kind: e.kind(),
+            message: e.to_string(),
+        }
+    }
+
+    /// Classifies a failed write, treating a dropped connection as `Disconnected`.
+    pub(crate) fn write(e: &io::Error) -> Self {
+        if is_disconnect(e.kind()) {
+            return FIRMClientError::Disconnected {
+                message: e.to_string(),
+            };
+        }
+        FIRMClientError::Write {
+            kind: e.kind(),
+            message: e.to_string(),
+        }
+    }
+
+    /// Returns the I/O error kind behind this error, for the variants that wrap one.
+    pub fn io_kind(&self) -> Option<io::ErrorKind> {
+        match self {
+            FIRMClientError::SerialOpen { kind, .. }
+            | FIRMClientError::Connect { kind, .. }
+            | FIRMClientError::Read { kind, .. }
+            | FIRMClientError::Write { kind, .. } => Some(*kind),
+            _ => None,
+        }
+    }
+}
+
+fn is_disconnect(kind: io::ErrorKind) -> bool {
+    matches!(
+        kind,
+        io::ErrorKind::BrokenPipe
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NotConnected
+    )
+}
+
+impl std::fmt::Display for FIRMClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FIRMClientError::SerialOpen { port, message, .. } => {
+                write!(f, "Failed to open serial port '{port}': {message}")
+            }
+            FIRMClientError::Connect {
+                address, message, ..
+            } => write!(f, "Failed to connect to {address}: {message}"),
+            FIRMClientError::CannotReopen { connection } => write!(
+                f,
+                "The port was lost after a timed out stop or a reader thread panic, and a {connection} connection can't be reopened"
+            ),
+            FIRMClientError::Read { message, .. } => write!(f, "Read failed: {message}"),
+            FIRMClientError::Write { message, .. } => write!(f, "Write failed: {message}"),
+            FIRMClientError::CommandTimeout { command, timeout } => {
+                write!(f, "No response to {command:?} within {timeout:?}")
+            }
+            FIRMClientError::Disconnected { message } => {
+                write!(f, "Device disconnected: {message}")
+            }
+            FIRMClientError::CallbackPanicked { callback, message } => {
+                write!(f, "{callback} panicked: {message}")
+            }
+            FIRMClientError::Recording { message } => write!(f, "Raw recording failed: {message}"),
+            FIRMClientError::MockStream { message } => {
+                write!(f, "Mock log stream failed: {message}")
+            }
+            FIRMClientError::NotSupported {
+                operation,
+                connection,
+            } => write!(
+                f,
+                "{operation} isn't supported on a {connection} connection"
+            ),
+            FIRMClientError::ThreadPanicked { message } => {
+                write!(f, "Reader thread panicked: {message}")
+            }
+            FIRMClientError::StopTimedOut { timeout } => write!(
+                f,
+                "Reader thread did not stop within {timeout:?}; its port was abandoned"
+            ),
+            FIRMClientError::ReconnectTimedOut { port, timeout } => write!(
+                f,
+                "'{port}' didn't come back within {timeout:?} of the reboot"
+            ),
+            FIRMClientError::NoPacketsAfterReconnect { port, timeout } => write!(
+                f,
+                "'{port}' reopened after the reboot, but no packets arrived within {timeout:?}"
+            ),
+            FIRMClientError::ShutDown => write!(f, "The client was shut down"),
+            FIRMClientError::LinkIdle { since } => {
+                write!(f, "No packets received for {since:?}")
+            }
+            FIRMClientError::Parse { byte_offset, error } => {
+                write!(f, "Bad frame at byte {byte_offset} of the stream: {error}")
+            }
+            FIRMClientError::LostSync {
+                byte_offset,
+                reason,
+            } => write!(f, "Lost sync at byte {byte_offset} of the stream: {reason}"),
+            FIRMClientError::TimestampOutOfOrder {
+                previous_seconds,
+                timestamp_seconds,
+            } => write!(
+                f,
+                "Packet timestamp {timestamp_seconds} s isn't after the previous {previous_seconds} s"
+            ),
+            FIRMClientError::DeviceRestartDetected {
+                previous_seconds,
+                timestamp_seconds,
+            } => write!(
+                f,
+                "The device restarted: timestamps went from {previous_seconds} s back to {timestamp_seconds} s"
+            ),
+            FIRMClientError::TimestampResynced {
+                previous_seconds,
+                timestamp_seconds,
+            } => write!(
+                f,
+                "Timestamps resynced from {previous_seconds} s to {timestamp_seconds} s after too many packets behind it"
+            ),
+            FIRMClientError::ProtocolViolation {
+                command,
+                violations,
+                ..
+            } => {
+                write!(f, "The {command:?} response broke the protocol: ")?;
+                for (i, violation) in violations.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{violation}")?;
+                }
+                Ok(())
+            }
+            FIRMClientError::InvalidSerialSettings { reason } => {
+                write!(f, "Invalid serial settings: {reason}")
+            }
+            FIRMClientError::ImplausibleData {
+                timestamp_seconds,
+                faults,
+            } => {
+                write!(f, "Implausible data packet at {timestamp_seconds} s: ")?;
+                for (i, fault) in faults.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{fault}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for FIRMClientError {}
+
+impl ErrorCoded for FIRMClientError {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            FIRMClientError::SerialOpen { kind, .. } => match kind {
+                io::ErrorKind::NotFound => error_codes::PORT_NOT_FOUND,
+                io::ErrorKind::PermissionDenied => error_codes::PORT_PERMISSION,
+                io::ErrorKind::ResourceBusy => error_codes::PORT_BUSY,
+                io::ErrorKind::TimedOut => error_codes::TIMEOUT,
+                _ => error_codes::PORT_OPEN,
+            },
+            FIRMClientError::Connect { .. } => error_codes::CONNECT,
+            FIRMClientError::CannotReopen { .. } => error_codes::CANNOT_REOPEN,
+            FIRMClientError::Read { .. } => error_codes::READ,
+            FIRMClientError::Write { .. } => error_codes::WRITE,
+            FIRMClientError::CommandTimeout { .. } => error_codes::TIMEOUT,
+            FIRMClientError::Disconnected { .. } => error_codes::DISCONNECTED,
+            FIRMClientError::CallbackPanicked { .. } => error_codes::CALLBACK_PANICKED,
+            FIRMClientError::Recording { .. } => error_codes::RECORDING,
+            FIRMClientError::MockStream { .. } => error_codes::MOCK_STREAM,
+            FIRMClientError::NotSupported { .. } => error_codes::NOT_SUPPORTED,
+            FIRMClientError::ThreadPanicked { .. } => error_codes::THREAD_PANICKED,
+            FIRMClientError::StopTimedOut { .. } => error_codes::STOP_TIMEOUT,
+            FIRMClientError::ReconnectTimedOut { .. } => error_codes::RECONNECT_TIMEOUT,
+            FIRMClientError::NoPacketsAfterReconnect { .. } => {
+                error_codes::NO_PACKETS_AFTER_RECONNECT
+            }
+            FIRMClientError::ShutDown => error_codes::SHUT_DOWN,
+            FIRMClientError::LinkIdle { .. } => error_codes::LINK_IDLE,
+            FIRMClientError::Parse { error, .. } => error.error_code(),
+            FIRMClientError::LostSync { .. } => error_codes::LOST_SYNC,
+            FIRMClientError::TimestampOutOfOrder { .. } => error_codes::TIMESTAMP_ORDER,
+            FIRMClientError::DeviceRestartDetected { .. } => error_codes::DEVICE_RESTART,
+            FIRMClientError::TimestampResynced { .. } => error_codes::TIMESTAMP_RESYNC,
+            FIRMClientError::ProtocolViolation { .. } => error_codes::PROTOCOL_VIOLATION,
+            FIRMClientError::InvalidSerialSettings { .. } => error_codes::INVALID_ARGUMENT,
+            FIRMClientError::ImplausibleData { .. } => error_codes::IMPLAUSIBLE_DATA,
+        }
+    }
+}
+
+/// An error reported by one of the client's background threads, as returned by `check_error`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorEvent {
+    /// When the error was reported.
+    pub time: SystemTime,
+    pub kind: FIRMClientError,
+    /// Set when the reader thread stopped because of the error, e.g. the port went away.
+    /// `is_running()` is already false by the time a fatal event is reported.
+    pub fatal: bool,
+}
+
+impl ErrorEvent {
+    /// An error the client carries on after.
+    pub(crate) fn now(kind: FIRMClientError) -> Self {
+        Self {
+            time: SystemTime::now(),
+            kind,
+            fatal: false,
+        }
+    }
+
+    /// An error that stopped the reader thread.
+    pub(crate) fn fatal(kind: FIRMClientError) -> Self {
+        Self {
+            fatal: true,
+            ..Self::now(kind)
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.kind.fmt(f)
+    }
+}
+
+impl std::error::Error for ErrorEvent {}
+
+impl ErrorCoded for ErrorEvent {
+    fn error_code(&self) -> ErrorCode {
+        self.kind.error_code()
+    }
+}
+
+/// Most events kept for `FIRMClient::recent_errors`.
+pub const ERROR_HISTORY_LENGTH: usize = 64;
+
+/// Hands error events from the background threads to the client, keeping the last
+/// `ERROR_HISTORY_LENGTH` of them whether or not they've been read.
+#[derive(Clone)]
+pub(crate) struct ErrorReporter {
+    sender: Sender<ErrorEvent>,
+    history: Arc<Mutex<VecDeque<ErrorEvent>>>,
+}
+
+impl ErrorReporter {
+    /// Creates a reporter and the receiver its events arrive on.
+    pub(crate) fn new() -> (Self, Receiver<ErrorEvent>) {
+        let (sender, receiver) = channel();
+        let reporter = Self {
+            sender,
+            history: Arc::new(Mutex::new(VecDeque::with_capacity(ERROR_HISTORY_LENGTH))),
+        };
+        (reporter, receiver)
+    }
+
+    pub(crate) fn report(&self, event: ErrorEvent) {
+        {
+            let mut history = self
+                .history
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if history.len() == ERROR_HISTORY_LENGTH {
+                history.pop_front();
+            }
+            history.push_back(event.clone());
+        }
+        let _ = self.sender.send(event);
+    }
+
+    /// Returns the kept events, oldest first.
+    pub(crate) fn history(&self) -> Vec<ErrorEvent> {
+        self.history
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_io_errors_are_classified() {
+        let reset = io::Error::new(io::ErrorKind::ConnectionReset, "reset by peer");
+        assert_eq!(
+            FIRMClientError::read(&reset),
+            FIRMClientError::Disconnected {
+                message: "reset by peer".to_string()
+            }
+        );
+
+        let denied = io::Error::new(io::ErrorKind::PermissionDenied, "denied");
+        let error = FIRMClientError::write(&denied);
+        assert_eq!(error.io_kind(), Some(io::ErrorKind::PermissionDenied));
+        assert_eq!(error.to_string(), "Write failed: denied");
+    }
+
+    #[test]
+    fn test_every_client_error_has_a_registered_code() {
+        let message = String::new();
+        let errors = [
+            FIRMClientError::SerialOpen {
+                port: "COM8".to_string(),
+                kind: io::ErrorKind::ResourceBusy,
+                message: message.clone(),
+            },
+            FIRMClientError::Connect {
+                address: "localhost:5000".to_string(),
+                kind: io::ErrorKind::ConnectionRefused,
+                message: message.clone(),
+            },
+            FIRMClientError::CannotReopen {
+                connection: "custom",
+            },
+            FIRMClientError::Read {
+                kind: io::ErrorKind::Other,
+                message: message.clone(),
+            },
+            FIRMClientError::Write {
+                kind: io::ErrorKind::Other,
+                message: message.clone(),
+            },
+            FIRMClientError::CommandTimeout {
+                command: FIRMCommand::GetDeviceInfo,
+                timeout: Duration::from_secs(1),
+            },
+            FIRMClientError::Disconnected {
+                message: message.clone(),
+            },
+            FIRMClientError::CallbackPanicked {
+                callback: "subscribe".to_string(),
+                message: message.clone(),
+            },
+            FIRMClientError::Recording {
+                message: message.clone(),
+            },
+            FIRMClientError::MockStream {
+                message: message.clone(),
+            },
+            FIRMClientError::NotSupported {
+                operation: "Seeking",
+                connection: "serial",
+            },
+            FIRMClientError::ThreadPanicked {
+                message: message.clone(),
+            },
+            FIRMClientError::StopTimedOut {
+                timeout: Duration::from_secs(1),
+            },
+            FIRMClientError::ReconnectTimedOut {
+                port: "COM8".to_string(),
+                timeout: Duration::from_secs(1),
+            },
+            FIRMClientError::NoPacketsAfterReconnect {
+                port: "COM8".to_string(),
+                timeout: Duration::from_secs(1),
+            },
+            FIRMClientError::ShutDown,
+            FIRMClientError::LinkIdle {
+                since: Duration::from_secs(1),
+            },
+            FIRMClientError::ProtocolViolation {
+                command: FIRMCommand::GetDeviceConfig,
+                violations: vec![ResponseViolation::EmptyName],
+                payload: vec![0],
+            },
+            FIRMClientError::InvalidSerialSettings {
+                reason: "the baud rate must be above 0",
+            },
+            FIRMClientError::ImplausibleData {
+                timestamp_seconds: 1.0,
+                faults: Vec::new(),
+            },
+            FIRMClientError::TimestampResynced {
+                previous_seconds: 1e9,
+                timestamp_seconds: 1.0,
+            },
+        ];
+        for error in &errors {
+            let code = error.error_code();
+            assert!(
+                error_codes::REGISTRY.contains(&code),
+                "{error:?} has unregistered code {code:?}"
+            );
+        }
+        assert_eq!(errors[0].error_code(), error_codes::PORT_BUSY);
+        assert_eq!(
+            ErrorEvent::now(errors[5].clone()).error_code(),
+            error_codes::TIMEOUT
+        );
+    }
+
+    #[test]
+    fn test_reporter_keeps_the_latest_events() {
+        let (reporter, receiver) = ErrorReporter::new();
+        for i in 0..ERROR_HISTORY_LENGTH + 6 {
+            let since = Duration::from_secs(i as u64);
+            reporter.report(ErrorEvent::now(FIRMClientError::LinkIdle { since }));
+        }
+        reporter.report(ErrorEvent::fatal(FIRMClientError::ShutDown));
+
+        let history = reporter.history();
+        assert_eq!(history.len(), ERROR_HISTORY_LENGTH);
+        assert_eq!(
+            history[0].kind,
+            FIRMClientError::LinkIdle {
+                since: Duration::from_secs(7)
+            }
+        );
+        assert!(history.last().unwrap().fatal);
+        assert!(history[..ERROR_HISTORY_LENGTH - 1].iter().all(|e| !e.fatal));
+        // Every event still reaches the receiver.
+        assert_eq!(receiver.try_iter().count(), ERROR_HISTORY_LENGTH + 7);
+    }
+}
@@ -0,0 +1,428 @@
+//! Scripted sensor failures for the `DeviceSimulator`, so ground software can be tested
+//! against a barometer dying mid-flight, a magnetometer stuck on one value and the like.
+//!
+//! A `FaultScenario` is a list of faults, each active during a window of device time. It can
+//! be built in code or parsed from a small text format, one fault per line:
+//!
+//! ```text
+//! # The barometer dies during coast and the magnetometer sticks for the rest of the flight.
+//! 3.0..4.5 barometer nan
+//! 2.0.. magnetometer freeze
+//! 1.0..3.0 accelerometer drift 0.05
+//! 4.0..4.2 gyroscope dropout
+//! 5.0..5.5 timestamp stuck
+//! ```
+//!
+//! Windows are `start..end` in seconds of packet timestamp, with the end left out for "until
+//! the end". Blank lines and lines starting with `#` are ignored.
+use anyhow::{Context, Result};
+use firm_core::firm_packets::FIRMData;
+use std::path::Path;
+
+/// A sensor whose readings a fault can corrupt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Sensor {
+    /// Temperature and pressure.
+    Barometer,
+    /// Raw acceleration.
+    Accelerometer,
+    /// Raw angular rate.
+    Gyroscope,
+    /// Magnetic field.
+    Magnetometer,
+}
+
+impl Sensor {
+    pub const ALL: [Sensor; 4] = [
+        Sensor::Barometer,
+        Sensor::Accelerometer,
+        Sensor::Gyroscope,
+        Sensor::Magnetometer,
+    ];
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Sensor::Barometer => "barometer",
+            Sensor::Accelerometer => "accelerometer",
+            Sensor::Gyroscope => "gyroscope",
+            Sensor::Magnetometer => "magnetometer",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|sensor| sensor.as_str() == name)
+    }
+
+    /// Returns the fields of `data` this sensor reports.
+    fn fields(self, data: &mut FIRMData) -> Vec<&mut f32> {
+        match self {
+            Sensor::Barometer => vec![&mut data.temperature_celsius, &mut data.pressure_pascals],
+            Sensor::Accelerometer => vec![
+                &mut data.raw_acceleration_x_gs,
+                &mut data.raw_acceleration_y_gs,
+                &mut data.raw_acceleration_z_gs,
+            ],
+            Sensor::Gyroscope => vec![
+                &mut data.raw_angular_rate_x_deg_per_s,
+                &mut data.raw_angular_rate_y_deg_per_s,
+                &mut data.raw_angular_rate_z_deg_per_s,
+            ],
+            Sensor::Magnetometer => vec![
+                &mut data.magnetic_field_x_microteslas,
+                &mut data.magnetic_field_y_microteslas,
+                &mut data.magnetic_field_z_microteslas,
+            ],
+        }
+    }
+}
+
+/// How a faulty sensor misbehaves.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SensorFault {
+    /// Keeps reporting the reading it had when the fault started.
+    Freeze,
+    /// Reports NaN.
+    Nan,
+    /// Stops reporting. The data frame has no per-sensor valid flags, so its fields read 0,
+    /// which is what the firmware sends for a sensor that never answered.
+    Dropout,
+    /// Reports the true reading scaled by `1 + scale_per_second * seconds_since_fault_start`.
+    Drift { scale_per_second: f32 },
+}
+
+/// One kind of failure.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fault {
+    /// A sensor misbehaves.
+    Sensor { sensor: Sensor, mode: SensorFault },
+    /// Packets keep the timestamp of the last packet before the fault.
+    StuckTimestamp,
+}
+
+/// A fault active from `start_seconds` (inclusive) to `end_seconds` (exclusive) of device time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScheduledFault {
+    pub start_seconds: f64,
+    pub end_seconds: f64,
+    pub fault: Fault,
+}
+
+impl ScheduledFault {
+    fn is_active(&self, t: f64) -> bool {
+        self.start_seconds <= t && t < self.end_seconds
+    }
+}
+
+/// A set of faults making up one failure scenario.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FaultScenario {
+    pub faults: Vec<ScheduledFault>,
+}
+
+impl FaultScenario {
+    /// Adds a fault active between `start_seconds` and `end_seconds`.
+    pub fn with_fault(mut self, start_seconds: f64, end_seconds: f64, fault: Fault) -> Self {
+        self.faults.push(ScheduledFault {
+            start_seconds,
+            end_seconds,
+            fault,
+        });
+        self
+    }
+
+    /// Parses a scenario in the format described in the module documentation.
+    ///
+    /// # Arguments
+    ///
+    /// - `text` (`&str`) - The scenario, one fault per line.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<Self>` - The scenario, or an error naming the first line that doesn't parse.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut scenario = Self::default();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fault = parse_line(line).with_context(|| format!("line {}", index + 1))?;
+            scenario.faults.push(fault);
+        }
+        Ok(scenario)
+    }
+
+    /// Reads and parses a scenario file.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read fault scenario {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Invalid fault scenario {}", path.display()))
+    }
+}
+
+fn parse_line(line: &str) -> Result<ScheduledFault> {
+    let mut words = line.split_whitespace();
+    let window = words.next().context("missing time window")?;
+    let (start, end) = window
+        .split_once("..")
+        .with_context(|| format!("expected a start..end window, got '{window}'"))?;
+    let start_seconds: f64 = start
+        .parse()
+        .with_context(|| format!("invalid start time '{start}'"))?;
+    let end_seconds = match end {
+        "" => f64::INFINITY,
+        end => end
+            .parse()
+            .with_context(|| format!("invalid end time '{end}'"))?,
+    };
+    if end_seconds <= start_seconds {
+        anyhow::bail!("window {window} ends before it starts");
+    }
+
+    let target = words.next().context("missing sensor")?;
+    let mode = words.next().context("missing fault mode")?;
+    let fault = if target == "timestamp" {
+        match mode {
+            "stuck" => Fault::StuckTimestamp,
+            other => anyhow::bail!("unknown timestamp fault '{other}', expected 'stuck'"),
+        }
+    } else {
+        let sensor =
+            Sensor::from_name(target).with_context(|| format!("unknown sensor '{target}'"))?;
+        let mode = match mode {
+            "freeze" => SensorFault::Freeze,
+            "nan" => SensorFault::Nan,
+            "dropout" => SensorFault::Dropout,
+            "drift" => {
+                let rate = words.next().context("drift needs a scale per second")?;
+                SensorFault::Drift {
+                    scale_per_second: rate
+                        .parse()
+                        .with_context(|| format!("invalid drift rate '{rate}'"))?,
+                }
+            }
+            other => anyhow::bail!("unknown fault mode '{other}'"),
+        };
+        Fault::Sensor { sensor, mode }
+    };
+
+    if let Some(extra) = words.next() {
+        anyhow::bail!("unexpected '{extra}' after the fault");
+    }
+    Ok(ScheduledFault {
+        start_seconds,
+        end_seconds,
+        fault,
+    })
+}
+
+/// Applies a `FaultScenario` to a stream of packets, remembering what frozen sensors and
+/// stuck timestamps should keep reporting.
+#[derive(Debug, Clone, Default)]
+pub struct FaultInjector {
+    scenario: FaultScenario,
+    /// Per fault in `scenario`, the readings captured when it became active.
+    frozen: Vec<Option<Vec<f32>>>,
+    /// Timestamp of the last packet no `StuckTimestamp` fault applied to.
+    last_good_timestamp: Option<f64>,
+}
+
+impl FaultInjector {
+    pub fn new(scenario: FaultScenario) -> Self {
+        Self {
+            frozen: vec![None; scenario.faults.len()],
+            scenario,
+            last_good_timestamp: None,
+        }
+    }
+
+    /// Corrupts `data` according to the faults active at its timestamp. Packets must be
+    /// passed in order.
+    pub fn apply(&mut self, data: &mut FIRMData) {
+        let t = data.timestamp_seconds;
+        let mut timestamp_stuck = false;
+
+        for (fault, frozen) in self.scenario.faults.iter().zip(&mut self.frozen) {
+            if !fault.is_active(t) {
+                *frozen = None;
+                continue;
+            }
+            match fault.fault {
+                Fault::StuckTimestamp => timestamp_stuck = true,
+                Fault::Sensor { sensor, mode } => {
+                    let mut fields = sensor.fields(data);
+                    match mode {
+                        SensorFault::Freeze => {
+                            let held =
+                                frozen.get_or_insert_with(|| fields.iter().map(|v| **v).collect());
+                            for (field, value) in fields.iter_mut().zip(held.iter()) {
+                                **field = *value;
+                            }
+                        }
+                        SensorFault::Nan => fields.iter_mut().for_each(|v| **v = f32::NAN),
+                        SensorFault::Dropout => fields.iter_mut().for_each(|v| **v = 0.0),
+                        SensorFault::Drift { scale_per_second } => {
+                            let scale = 1.0 + scale_per_second * (t - fault.start_seconds) as f32;
+                            fields.iter_mut().for_each(|v| **v *= scale);
+                        }
+                    }
+                }
+            }
+        }
+
+        match (timestamp_stuck, self.last_good_timestamp) {
+            (true, Some(last)) => data.timestamp_seconds = last,
+            _ => self.last_good_timestamp = Some(data.timestamp_seconds),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator::FlightProfileGenerator;
+
+    /// Runs the default flight through `scenario` at 100 Hz for `seconds`.
+    fn faulty_flight(scenario: FaultScenario, seconds: f64) -> (Vec<FIRMData>, Vec<FIRMData>) {
+        let profile = FlightProfileGenerator::default();
+        let mut injector = FaultInjector::new(scenario);
+        let clean: Vec<FIRMData> = (0..(seconds * 100.0) as usize)
+            .map(|i| profile.sample(i as f64 * 0.01))
+            .collect();
+        let faulty = clean
+            .iter()
+            .map(|packet| {
+                let mut packet = packet.clone();
+                injector.apply(&mut packet);
+                packet
+            })
+            .collect();
+        (clean, faulty)
+    }
+
+    fn sensor(sensor: Sensor, mode: SensorFault) -> Fault {
+        Fault::Sensor { sensor, mode }
+    }
+
+    #[test]
+    fn test_freeze_holds_first_faulted_reading() {
+        let scenario = FaultScenario::default().with_fault(
+            2.0,
+            3.0,
+            sensor(Sensor::Magnetometer, SensorFault::Freeze),
+        );
+        let (clean, faulty) = faulty_flight(scenario, 4.0);
+        for packet in &faulty[200..300] {
+            assert_eq!(
+                packet.magnetic_field_x_microteslas,
+                clean[200].magnetic_field_x_microteslas
+            );
+        }
+        // Other sensors and the time around the window are untouched.
+        assert_eq!(faulty[250].pressure_pascals, clean[250].pressure_pascals);
+        assert_eq!(faulty[199], clean[199]);
+        assert_eq!(faulty[300], clean[300]);
+    }
+
+    #[test]
+    fn test_nan_and_dropout_affect_only_their_sensor() {
+        let scenario = FaultScenario::default()
+            .with_fault(1.0, 2.0, sensor(Sensor::Barometer, SensorFault::Nan))
+            .with_fault(1.5, 2.0, sensor(Sensor::Gyroscope, SensorFault::Dropout));
+        let (clean, faulty) = faulty_flight(scenario, 2.5);
+
+        assert!(faulty[120].pressure_pascals.is_nan());
+        assert!(faulty[120].temperature_celsius.is_nan());
+        assert_eq!(
+            faulty[120].raw_acceleration_z_gs,
+            clean[120].raw_acceleration_z_gs
+        );
+        assert_eq!(faulty[170].raw_angular_rate_z_deg_per_s, 0.0);
+        assert!(!faulty[210].pressure_pascals.is_nan());
+    }
+
+    #[test]
+    fn test_drift_grows_with_time_in_the_window() {
+        let scenario = FaultScenario::default().with_fault(
+            1.0,
+            f64::INFINITY,
+            sensor(
+                Sensor::Accelerometer,
+                SensorFault::Drift {
+                    scale_per_second: 0.5,
+                },
+            ),
+        );
+        let (clean, faulty) = faulty_flight(scenario, 3.0);
+        assert_eq!(faulty[100], clean[100]);
+        let scale = faulty[200].raw_acceleration_z_gs / clean[200].raw_acceleration_z_gs;
+        assert!((scale - 1.5).abs() < 1e-4, "{scale}");
+    }
+
+    #[test]
+    fn test_stuck_timestamp_repeats_last_good_one() {
+        let scenario = FaultScenario::default().with_fault(1.0, 1.5, Fault::StuckTimestamp);
+        let (clean, faulty) = faulty_flight(scenario, 2.0);
+        for packet in &faulty[100..150] {
+            assert_eq!(packet.timestamp_seconds, clean[99].timestamp_seconds);
+        }
+        assert_eq!(faulty[150].timestamp_seconds, clean[150].timestamp_seconds);
+    }
+
+    #[test]
+    fn test_parse_scenario() {
+        let scenario = FaultScenario::parse(
+            "# barometer dies during coast\n\
+             3.0..4.5 barometer nan\n\
+             \n\
+             2.0.. magnetometer freeze\n\
+             1.0..3.0 accelerometer drift 0.05\n\
+             5..5.5 timestamp stuck\n",
+        )
+        .unwrap();
+        let expected = FaultScenario::default()
+            .with_fault(3.0, 4.5, sensor(Sensor::Barometer, SensorFault::Nan))
+            .with_fault(
+                2.0,
+                f64::INFINITY,
+                sensor(Sensor::Magnetometer, SensorFault::Freeze),
+            )
+            .with_fault(
+                1.0,
+                3.0,
+                sensor(
+                    Sensor::Accelerometer,
+                    SensorFault::Drift {
+                        scale_per_second: 0.05,
+                    },
+                ),
+            )
+            .with_fault(5.0, 5.5, Fault::StuckTimestamp);
+        assert_eq!(scenario, expected);
+    }
+
+    #[test]
+    fn test_parse_reports_the_bad_line() {
+        for (text, message) in [
+            (
+                "1..2 barometer nan\n2..1 barometer nan",
+                "ends before it starts",
+            ),
+            ("1..2 lidar nan", "unknown sensor"),
+            ("1..2 barometer melt", "unknown fault mode"),
+            ("1..2 barometer drift", "drift needs"),
+            ("1..2 timestamp nan", "unknown timestamp fault"),
+            ("1-2 barometer nan", "start..end"),
+            ("1..2 barometer nan now", "unexpected 'now'"),
+        ] {
+            let error = format!("{:#}", FaultScenario::parse(text).unwrap_err());
+            assert!(error.contains(message), "{text}: {error}");
+            assert!(error.starts_with("line "), "{error}");
+        }
+        let error = format!(
+            "{:#}",
+            FaultScenario::parse("\n1..0 barometer nan").unwrap_err()
+        );
+        assert!(error.starts_with("line 2"), "{error}");
+    }
+}
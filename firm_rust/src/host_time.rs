@@ -0,0 +1,284 @@
+//! Host receive times for data packets, and relating them to device time.
+//!
+//! The reader thread stamps every data packet with the host time it was parsed at, see
+//! `FIRMClient::get_packets_with_host_time`. `ClockDriftEstimator` fits a line through recent
+//! `(device time, host time)` pairs, so a device timestamp can be put on the wall clock (e.g.
+//! to line it up with video) and the other way round.
+use firm_core::firm_packets::FIRMData;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant, SystemTime};
+
+/// A monotonic clock reading in system time. It's anchored to the system time once and then
+/// follows `Instant`, so it never goes backwards when the system clock is adjusted.
+pub(crate) struct HostClock {
+    instant: Instant,
+    system: SystemTime,
+}
+
+impl HostClock {
+    pub(crate) fn new() -> Self {
+        Self {
+            instant: Instant::now(),
+            system: SystemTime::now(),
+        }
+    }
+
+    pub(crate) fn now(&self) -> SystemTime {
+        self.system + self.instant.elapsed()
+    }
+}
+
+/// A fitted relation between device time and host time: a straight line through
+/// `(device_seconds, host_time)` with slope `rate`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClockFit {
+    pub device_seconds: f64,
+    pub host_time: SystemTime,
+    /// Host seconds per device second. Above 1 when the device clock runs slow.
+    pub rate: f64,
+}
+
+impl ClockFit {
+    /// Returns how far the device clock drifts from the host clock, in parts per million.
+    /// Positive when the device clock runs slow.
+    pub fn drift_ppm(&self) -> f64 {
+        (self.rate - 1.0) * 1e6
+    }
+
+    /// Returns the host time a device timestamp corresponds to.
+    ///
+    /// # Arguments
+    ///
+    /// - `device_seconds` (`f64`) - A device timestamp, as in `FIRMData::timestamp_seconds`.
+    pub fn device_to_host(&self, device_seconds: f64) -> SystemTime {
+        offset_time(
+            self.host_time,
+            (device_seconds - self.device_seconds) * self.rate,
+        )
+    }
+
+    /// Returns the device timestamp a host time corresponds to.
+    ///
+    /// # Arguments
+    ///
+    /// - `host_time` (`SystemTime`) - A wall-clock time.
+    pub fn host_to_device(&self, host_time: SystemTime) -> f64 {
+        self.device_seconds + seconds_between(self.host_time, host_time) / self.rate
+    }
+}
+
+/// Fits the offset and drift between device time and host time over the last `window`
+/// packets, by least squares.
+///
+/// Host times include the variable delay before a packet is read, so the fit lands on the
+/// average delay; bigger windows average out more of that jitter but follow drift changes
+/// more slowly.
+#[derive(Debug, Clone)]
+pub struct ClockDriftEstimator {
+    window: usize,
+    /// `(device seconds, host time)` pairs, oldest first.
+    samples: VecDeque<(f64, SystemTime)>,
+}
+
+impl ClockDriftEstimator {
+    /// Creates an estimator over the last `window` samples. Panics if `window` is less than 2.
+    pub fn new(window: usize) -> Self {
+        assert!(
+            window >= 2,
+            "the window needs at least 2 samples, got {window}"
+        );
+        Self {
+            window,
+            samples: VecDeque::with_capacity(window),
+        }
+    }
+
+    /// Adds a sample, dropping the oldest once the window is full. A device time earlier than
+    /// the previous sample's means the device restarted, so the window starts over.
+    ///
+    /// # Arguments
+    ///
+    /// - `device_seconds` (`f64`) - The packet's device timestamp.
+    /// - `host_time` (`SystemTime`) - When the host received it.
+    pub fn add(&mut self, device_seconds: f64, host_time: SystemTime) {
+        if self
+            .samples
+            .back()
+            .is_some_and(|&(last, _)| device_seconds < last)
+        {
+            self.samples.clear();
+        }
+        if self.samples.len() == self.window {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((device_seconds, host_time));
+    }
+
+    /// Adds every packet from `get_packets_with_host_time`, in order.
+    pub fn add_packets(&mut self, packets: &[(FIRMData, SystemTime)]) {
+        for (packet, host_time) in packets {
+            self.add(packet.timestamp_seconds, *host_time);
+        }
+    }
+
+    /// Returns the number of samples in the window.
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Returns true if no samples have been added since the last restart.
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Fits a line through the samples in the window.
+    ///
+    /// # Returns
+    ///
+    /// - `Option<ClockFit>` - The fit, or `None` with fewer than 2 samples or if they all have
+    ///   the same device time.
+    pub fn fit(&self) -> Option<ClockFit> {
+        let &(first_device, first_host) = self.samples.front()?;
+        // Relative to the first sample, so the sums keep their precision.
+        let points: Vec<(f64, f64)> = self
+            .samples
+            .iter()
+            .map(|&(device, host)| (device - first_device, seconds_between(first_host, host)))
+            .collect();
+        let n = points.len() as f64;
+        let mean_device = points.iter().map(|(d, _)| d).sum::<f64>() / n;
+        let mean_host = points.iter().map(|(_, h)| h).sum::<f64>() / n;
+        let (mut covariance, mut variance) = (0.0, 0.0);
+        for (d, h) in &points {
+            covariance += (d - mean_device) * (h - mean_host);
+            variance += (d - mean_device) * (d - mean_device);
+        }
+        if variance <= 0.0 {
+            return None;
+        }
+        Some(ClockFit {
+            device_seconds: first_device + mean_device,
+            host_time: offset_time(first_host, mean_host),
+            rate: covariance / variance,
+        })
+    }
+}
+
+/// Returns `to - from` in seconds, negative if `to` is earlier.
+fn seconds_between(from: SystemTime, to: SystemTime) -> f64 {
+    match to.duration_since(from) {
+        Ok(later) => later.as_secs_f64(),
+        Err(earlier) => -earlier.duration().as_secs_f64(),
+    }
+}
+
+/// Returns `time` moved by `seconds`, which may be negative.
+fn offset_time(time: SystemTime, seconds: f64) -> SystemTime {
+    if seconds >= 0.0 {
+        time + Duration::from_secs_f64(seconds)
+    } else {
+        time - Duration::from_secs_f64(-seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FIRMClient;
+    use firm_core::constants::packet::PacketHeader;
+    use firm_core::framed_packet::FramedPacket;
+
+    /// Small deterministic generator for the receive delay jitter.
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next_f64(&mut self) -> f64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (self.0 >> 11) as f64 / (1u64 << 53) as f64
+        }
+    }
+
+    #[test]
+    fn test_host_clock_is_monotonic() {
+        let clock = HostClock::new();
+        let mut last = clock.now();
+        for _ in 0..10_000 {
+            let now = clock.now();
+            assert!(now >= last);
+            last = now;
+        }
+    }
+
+    #[test]
+    fn test_packets_carry_increasing_host_times() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        client.start().unwrap();
+        let before = SystemTime::now();
+        for i in 0..20 {
+            let mut payload = vec![0u8; 120];
+            payload[0..8].copy_from_slice(&(i as f64 * 0.01).to_le_bytes());
+            device.inject_framed_packet(FramedPacket::new(PacketHeader::Data, 0, payload));
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        let mut packets = Vec::new();
+        while packets.len() < 20 {
+            packets.extend(
+                client
+                    .get_packets_with_host_time(Some(Duration::from_secs(1)))
+                    .unwrap(),
+            );
+        }
+        let after = SystemTime::now() + Duration::from_millis(50);
+        assert!(packets.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+        // The anchor is taken when the reader thread starts, just before `before`.
+        let earliest = before - Duration::from_millis(50);
+        assert!(packets.iter().all(|(_, t)| (earliest..after).contains(t)));
+    }
+
+    #[test]
+    fn test_drift_estimate_converges_on_synthetic_clocks() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_760_000_000);
+        let (offset, rate) = (12.5, 1.0 + 80e-6);
+        let mut rng = Lcg(3);
+        let mut estimator = ClockDriftEstimator::new(2000);
+        for i in 0..5000 {
+            let device = i as f64 * 0.01;
+            // Each packet is read 1-3 ms after the line, as the OS buffers it.
+            let delay = 0.001 + rng.next_f64() * 0.002;
+            estimator.add(device, offset_time(start, offset + device * rate + delay));
+        }
+        assert_eq!(estimator.len(), 2000);
+
+        let fit = estimator.fit().unwrap();
+        assert!((fit.drift_ppm() - 80.0).abs() < 2.0, "{}", fit.drift_ppm());
+        // Conversions land within the jitter, on the average delay.
+        let expected = offset_time(start, offset + 42.0 * rate + 0.002);
+        assert!(seconds_between(expected, fit.device_to_host(42.0)).abs() < 0.0005);
+        assert!((fit.host_to_device(fit.device_to_host(10.0)) - 10.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_estimator_restarts_with_the_device() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let mut estimator = ClockDriftEstimator::new(10);
+        assert_eq!(estimator.fit(), None);
+        estimator.add(5.0, start);
+        estimator.add(5.0, start);
+        assert_eq!(estimator.fit(), None);
+
+        for i in 0..20 {
+            estimator.add(10.0 + i as f64, offset_time(start, i as f64));
+        }
+        // The device rebooted, so its time started over at 0.
+        estimator.add(0.0, offset_time(start, 30.0));
+        estimator.add(1.0, offset_time(start, 31.0));
+        assert_eq!(estimator.len(), 2);
+        let fit = estimator.fit().unwrap();
+        assert!((fit.rate - 1.0).abs() < 1e-9);
+        assert!(seconds_between(offset_time(start, 30.0), fit.device_to_host(0.0)).abs() < 1e-6);
+    }
+}
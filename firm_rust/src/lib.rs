@@ -1,28 +1,150 @@
+use alarms::{AlarmEvent, AlarmMonitor, AlarmRule};
+use altitude::AltitudeState;
 use anyhow::Result;
+pub use builder::FIRMClientBuilder;
+use builder::SerialFraming;
+use error::ErrorReporter;
+pub use error::{ERROR_HISTORY_LENGTH, ErrorEvent, FIRMClientError};
 use firm_core::calibration::{MagnetometerCalibration, MagnetometerCalibrator};
 use firm_core::client_packets::{FIRMCommandPacket, FIRMLogPacket};
+use firm_core::constants::command::MAX_COMMAND_PAYLOAD_LENGTH;
 use firm_core::constants::command::{
-    NUMBER_OF_CALIBRATION_OFFSETS, NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS,
+    FIRMCommand, NUMBER_OF_CALIBRATION_OFFSETS, NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS,
 };
-use firm_core::constants::log_parsing::{FIRMLogPacketType, HEADER_PARSE_DELAY, HEADER_TOTAL_SIZE};
+use firm_core::constants::packet::PacketHeader;
+use firm_core::corpus::FrameCorpus;
 use firm_core::data_parser::SerialParser;
+pub use firm_core::data_parser::TimestampPolicy;
 use firm_core::firm_packets::{
-    CalibrationValues, DeviceConfig, DeviceInfo, DeviceProtocol, FIRMData, FIRMResponse,
+    CalibrationValues, DeviceConfig, DeviceInfo, DeviceProtocol, FIRMBaroPacket, FIRMData,
+    FIRMResponse,
 };
-use firm_core::framed_packet::Framed;
-use firm_core::log_parsing::LogParser;
+use firm_core::framed_packet::{FrameError, Framed, FramedPacket};
+pub use firm_core::validation::ValidationConfig;
+pub use host_time::{ClockDriftEstimator, ClockFit};
+use link_stats::{IdleThresholds, LinkCounters, LinkStats};
+pub use mock_stream::MockOptions;
+pub use packet_filter::{Decimate, IncreasingTimestamps, PacketFilter};
+use packet_queue::{PacketReceiver, PacketSender, QueueDepth, RawFrameQueue};
+pub use ports::{FirmPortInfo, ListPortsOptions, list_firm_ports, list_firm_ports_with};
+use reader_loop::ReaderLoop;
+use recording::RecordingHeader;
 use serialport::SerialPort;
+pub use serialport::{ClearBuffer, DataBits, FlowControl, Parity, StopBits};
+pub use session::SessionInfo;
+pub use split::{Controller, PacketStream};
 use std::collections::VecDeque;
 use std::fs::File;
-use std::io::{self, Read, Write};
-use std::sync::Arc;
+use std::io::{self, BufWriter, Read, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
 use std::sync::RwLock;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender, channel};
+use std::sync::{Arc, Mutex};
 use std::thread::{self, JoinHandle};
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
+use thresholds::{ThresholdDirection, ThresholdEvent, ThresholdMonitor};
+use transport::{
+    ConnectionKind, ConnectionSettings, LogPlaybackTransport, PlaybackControl, ReadStrategy,
+    ReadWriteTransport, RecoveryStep, ReplayTransport, TcpTransport, Transport,
+};
 
+pub mod alarms;
+pub mod altitude;
+pub mod builder;
+pub mod error;
+pub mod faults;
+pub mod host_time;
+pub mod link_stats;
+pub mod live_export;
 pub mod mock_serial;
+pub mod mock_stream;
+pub mod packet_filter;
+mod packet_queue;
+pub mod ports;
+mod reader_loop;
+pub mod recording;
+pub mod response_script;
+pub mod rx_drainer;
+pub mod session;
+pub mod simulator;
+pub mod split;
+pub mod thresholds;
+pub mod transport;
+
+/// Callback invoked from the reader thread for every parsed data packet or alarm event.
+type Callback<T> = Box<dyn FnMut(&T) + Send>;
+
+/// A data packet and the host time the reader thread parsed it at, see
+/// `FIRMClient::get_packets_with_host_time`.
+type TimedPacket = (FIRMData, SystemTime);
+
+/// A line control request carried out by the reader thread, which owns the transport while
+/// the client is running. The result is sent back on the included channel.
+enum LinkControl {
+    Break(Duration, Sender<io::Result<()>>),
+    ClearBuffers(ClearBuffer, Sender<io::Result<()>>),
+    SetBaudRate(u32, Sender<io::Result<()>>),
+    SetDtr(bool, Sender<io::Result<()>>),
+    SetRts(bool, Sender<io::Result<()>>),
+    /// DTR levels to drive in turn, each held for its duration.
+    PulseDtr(Vec<(bool, Duration)>, Sender<io::Result<()>>),
+}
+
+/// What the reader thread does with a response that breaks the protocol, see
+/// `FIRMClient::set_response_validation`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResponseValidation {
+    /// Pass the response on, flagged as suspect where its type has a flag for it.
+    #[default]
+    Lenient,
+    /// Drop the response and report `FIRMClientError::ProtocolViolation` through
+    /// `check_error` instead, with the raw payload attached.
+    Strict,
+}
+
+/// What the reader thread does with a data packet that fails validation, see
+/// `FIRMClient::set_data_validation`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DataValidation {
+    /// Don't validate data packets.
+    #[default]
+    Off,
+    /// Pass the packet on and report `FIRMClientError::ImplausibleData` through
+    /// `check_error`.
+    Flag,
+    /// Drop the packet before it reaches `get_data_packets`, subscribers or extra receivers.
+    Drop,
+}
+
+/// A registered callback together with its cancellation flag.
+struct Subscriber<T> {
+    callback: Callback<T>,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// Handle returned by `FIRMClient::subscribe` and `FIRMClient::on_alarm`, used to stop
+/// delivery to that callback.
+///
+/// Dropping the handle does not unsubscribe; call `cancel()` explicitly.
+#[derive(Clone)]
+pub struct SubscriptionHandle {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl SubscriptionHandle {
+    /// Stops delivering packets to the callback. The callback is dropped by the reader thread
+    /// the next time it delivers packets.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true until `cancel()` is called or the callback panics.
+    pub fn is_active(&self) -> bool {
+        !self.cancelled.load(Ordering::Relaxed)
+    }
+}
 
 /// Interface to the FIRM Client device.
 ///
@@ -34,7 +156,7 @@ pub mod mock_serial;
 ///
 /// fn main() {
 ///    let mut client = FIRMClient::new("/dev/ttyUSB0", 2_000_000, 0.1);
-///    client.start();
+///    client.start().unwrap();
 ///
 ///    loop {
 ///         while let Ok(packet) = client.get_packets(Some(Duration::from_millis(100))) {
@@ -43,65 +165,201 @@ pub mod mock_serial;
 ///     }
 /// }
 pub struct FIRMClient {
-    packet_receiver: Receiver<FIRMData>,
+    /// Data packets with the host time they were received at.
+    packet_receiver: PacketReceiver,
     response_receiver: Receiver<FIRMResponse>,
-    error_receiver: Receiver<String>,
+    error_receiver: Receiver<ErrorEvent>,
     running: Arc<AtomicBool>,
-    join_handle: Option<JoinHandle<Box<dyn SerialPort>>>,
-    sender: Sender<FIRMData>,
+    /// Returns the port when the reader thread exits, or `None` if it panicked.
+    join_handle: Option<JoinHandle<Option<Box<dyn Transport>>>>,
+    /// Set once `stop_with_timeout` gives up on the reader thread, so it exits as soon as its
+    /// read returns instead of handing on anything more. Each thread gets its own.
+    detached: Arc<AtomicBool>,
+    sender: PacketSender,
+    /// How many packets are waiting in `packet_receiver`, see `backlog`.
+    packet_depth: Arc<QueueDepth>,
     response_sender: Sender<FIRMResponse>,
-    error_sender: Sender<String>,
-    command_sender: Sender<FIRMCommandPacket>,
-    command_receiver: Option<Receiver<FIRMCommandPacket>>,
+    error_sender: ErrorReporter,
+    /// Outgoing bytes (already framed, or raw via `send_raw_bytes`) for the reader thread.
+    command_sender: Sender<Vec<u8>>,
+    command_receiver: Option<Receiver<Vec<u8>>>,
+    /// Break and purge requests for the reader thread.
+    control_sender: Sender<LinkControl>,
+    control_receiver: Option<Receiver<LinkControl>>,
     mock_sender: Sender<FIRMLogPacket>,
     mock_receiver: Option<Receiver<FIRMLogPacket>>,
-    port: Option<Box<dyn SerialPort>>,
+    port: Option<Box<dyn Transport>>,
 
     response_buffer: VecDeque<FIRMResponse>,
+    /// Commands sent at least once, whose buffered replies are stale by the time the command
+    /// is sent again.
+    commands_sent: Vec<FIRMCommand>,
 
     mock_stream_stop: Arc<AtomicBool>,
     mock_stream_handle: Option<JoinHandle<anyhow::Result<usize>>>,
 
     calibration_snoop: Arc<RwLock<Option<Sender<FIRMData>>>>,
     calibration_handle: Option<JoinHandle<Option<MagnetometerCalibration>>>,
+
+    subscribers: Arc<Mutex<Vec<Subscriber<FIRMData>>>>,
+    /// Extra packet channels handed out by `add_receiver`.
+    extra_senders: Arc<Mutex<Vec<Sender<FIRMData>>>>,
+
+    /// Packets older than this many seconds of device time (relative to the newest packet
+    /// seen) are dropped when consumed. `None` keeps everything.
+    max_packet_age_seconds: Option<f64>,
+    /// Bits of the newest device timestamp seen by the reader thread, stored as `f64` bits.
+    newest_timestamp_bits: Arc<AtomicU64>,
+    /// Number of packets dropped by the max-age policy.
+    aged_out_packets: Arc<AtomicU64>,
+
+    /// When set, the reader thread keeps only the newest packet in `latest_packet` instead of
+    /// queueing every packet.
+    latest_only: Arc<AtomicBool>,
+    latest_packet: Arc<Mutex<Option<FIRMData>>>,
+    /// Number of packets passed over by `get_latest_packet` or overwritten in latest-only mode.
+    skipped_packets: Arc<AtomicU64>,
+
+    link_counters: Arc<Mutex<LinkCounters>>,
+
+    /// When set, the reader thread queues undecodable frames in `raw_frames`.
+    raw_frame_tap: Arc<AtomicBool>,
+    raw_frames: Arc<RawFrameQueue>,
+    /// When set, the reader thread reports where the stream lost sync as `LostSync` errors.
+    resync_reporting: Arc<AtomicBool>,
+    /// When set, the reader thread reports each rejected frame as a `Parse` error.
+    parse_error_reporting: Arc<AtomicBool>,
+
+    /// When set, the reader thread reconnects after a connection error instead of stopping.
+    reconnect: Arc<AtomicBool>,
+    /// When the reader thread reports `LinkIdle`, adjustable while it runs.
+    idle_thresholds: Arc<Mutex<IdleThresholds>>,
+    /// When set, the reader thread drops responses that break the protocol, see
+    /// `ResponseValidation::Strict`.
+    strict_responses: Arc<AtomicBool>,
+    /// Applied by the reader thread to each data packet before it's passed on.
+    packet_filter: Arc<Mutex<Option<Box<dyn PacketFilter>>>>,
+    /// Checked by the reader thread against each data packet, before the packet filter.
+    data_validation: Arc<Mutex<(DataValidation, ValidationConfig)>>,
+
+    /// Size of the reader thread's read buffer and how it waits for bytes, both picked up by
+    /// the next `start()`.
+    read_buffer_size: usize,
+    read_strategy: ReadStrategy,
+    /// Applied to the reader thread's parser at the next `start()`.
+    timestamp_policy: TimestampPolicy,
+    /// Frames collected by `collect_corpus`, copied out by the reader thread as shapes are
+    /// added.
+    corpus: Arc<Mutex<Option<FrameCorpus>>>,
+    /// How long `start()` waits for each of the device's info and config replies, or `None`
+    /// to not ask.
+    session_capture: Option<Duration>,
+    /// What the device reported at the last `start()`, or what a raw recording's header says.
+    session: Option<SessionInfo>,
+
+    /// How the transport was opened, written into raw recordings.
+    connection: ConnectionSettings,
+    /// Character framing for reopening a serial port, see `FIRMClientBuilder`.
+    serial_framing: SerialFraming,
+    /// Pause, speed and seek handle for clients playing back a log file.
+    playback: Option<Arc<PlaybackControl>>,
+    /// Raw recording started by `record_raw`, appended to by the reader thread.
+    raw_recorder: Arc<Mutex<Option<BufWriter<File>>>>,
+
+    /// Alarm rules evaluated by the reader thread, set by `set_alarm_rules`.
+    alarms: Arc<Mutex<AlarmMonitor>>,
+    alarm_subscribers: Arc<Mutex<Vec<Subscriber<AlarmEvent>>>>,
+    alarm_sender: Sender<AlarmEvent>,
+    alarm_receiver: Receiver<AlarmEvent>,
+
+    /// Secondary barometer readings, see `get_baro_packets`.
+    baro_sender: Sender<FIRMBaroPacket>,
+    baro_receiver: Receiver<FIRMBaroPacket>,
+
+    /// Sea level pressure and ground reference used to fill in `pressure_altitude_meters`.
+    altitude: Arc<Mutex<AltitudeState>>,
 }
 
 impl FIRMClient {
-    /// Creates a new FIRMClient instance connected to the specified serial port.
+    /// Creates a new FIRMClient instance connected to the specified serial port, framed 8N1
+    /// without flow control. See `FIRMClientBuilder` for other serial settings.
     ///
     /// # Arguments
     ///
     /// - `port_name` (`&str`) - The name of the serial port to connect to (e.g., "/dev/ttyUSB0").
     /// - `baud_rate` (`u32`) - The baud rate for the serial connection. Commonly 2,000,000 for FIRM devices.
     /// - `timeout` (`f64`) - Read timeout in seconds for the serial port.
-    pub fn new(port_name: &str, baud_rate: u32, timeout: f64) -> Result<Self> {
-        // Sets up the serial port
-        let mut port: Box<dyn SerialPort> = serialport::new(port_name, baud_rate)
+    ///
+    /// # Returns
+    ///
+    /// - `Result<Self, FIRMClientError>` - The client, or `FIRMClientError::SerialOpen` if the port couldn't be opened.
+    pub fn new(port_name: &str, baud_rate: u32, timeout: f64) -> Result<Self, FIRMClientError> {
+        FIRMClientBuilder::new()
+            .baud(baud_rate)
             .timeout(Duration::from_millis((timeout * 1000.0) as u64))
-            .open()
-            .map_err(io::Error::other)?;
+            .open(port_name)
+    }
 
-        // Sets DTR to true, this is important for Linux/Windows to both act the same
-        port.write_data_terminal_ready(true)?;
-        // Give the device a moment to settle after opening the port
-        std::thread::sleep(Duration::from_millis(50));
+    /// Opens a serial port with settings `FIRMClientBuilder` already checked.
+    pub(crate) fn open_serial(
+        port_name: &str,
+        baud_rate: u32,
+        timeout: Duration,
+        framing: SerialFraming,
+    ) -> Result<Self, FIRMClientError> {
+        let port = open_serial_port(port_name, baud_rate, timeout, &framing)?;
+
+        let mut client = Self::from_transport(Box::new(port));
+        client.connection = ConnectionSettings {
+            kind: ConnectionKind::Serial,
+            address: Some(port_name.to_string()),
+            baud_rate: Some(baud_rate),
+            timeout_seconds: Some(timeout.as_secs_f64()),
+        };
+        client.serial_framing = framing;
+        Ok(client)
+    }
 
-        Ok(Self::new_from_port(port))
+    /// Creates a client from a separate reader and writer, see `ReadWriteTransport`.
+    pub fn from_read_write(
+        reader: impl Read + Send + 'static,
+        writer: impl Write + Send + 'static,
+    ) -> Self {
+        Self::from_transport(Box::new(ReadWriteTransport::new(reader, writer)))
     }
 
     /// Creates a mocked client with a paired mock serial port and device handle.
     pub fn new_mock(timeout: f64) -> (Self, mock_serial::MockDeviceHandle) {
         let (port, device) = mock_serial::MockSerialPort::pair(Duration::from_secs_f64(timeout));
-        let client = Self::new_from_port(port);
+        let mut client = Self::from_transport(Box::new(port));
+        client.connection = ConnectionSettings {
+            kind: ConnectionKind::Mock,
+            address: None,
+            baud_rate: None,
+            timeout_seconds: Some(timeout),
+        };
         (client, device)
     }
 
-    fn new_from_port(port: Box<dyn SerialPort>) -> Self {
-        let (sender, receiver) = channel();
+    /// Most non-matching responses kept in `response_buffer`. Late replies to timed-out
+    /// commands would otherwise pile up forever; the oldest are dropped first.
+    const MAX_BUFFERED_RESPONSES: usize = 64;
+
+    /// Creates a client that talks to a FIRM device over any `Transport`, e.g. a TCP-to-serial
+    /// bridge or a recorded byte stream. See `Transport` for the read timeout expectations.
+    ///
+    /// # Arguments
+    ///
+    /// - `port` (`Box<dyn Transport>`) - The byte stream to read packets from and write commands to.
+    pub fn from_transport(port: Box<dyn Transport>) -> Self {
+        let (sender, receiver, packet_depth) = packet_queue::packet_queue();
         let (response_sender, response_receiver) = channel();
-        let (error_sender, error_receiver) = channel();
+        let (error_sender, error_receiver) = ErrorReporter::new();
         let (command_sender, command_receiver) = channel();
+        let (control_sender, control_receiver) = channel();
         let (mock_sender, mock_receiver) = channel();
+        let (alarm_sender, alarm_receiver) = channel();
+        let (baro_sender, baro_receiver) = channel();
 
         Self {
             packet_receiver: receiver,
@@ -109,150 +367,564 @@ impl FIRMClient {
             error_receiver,
             running: Arc::new(AtomicBool::new(false)),
             join_handle: None,
+            detached: Arc::new(AtomicBool::new(false)),
             sender,
+            packet_depth,
             response_sender,
             error_sender,
             command_sender,
             command_receiver: Some(command_receiver),
+            control_sender,
+            control_receiver: Some(control_receiver),
             mock_sender,
             mock_receiver: Some(mock_receiver),
             port: Some(port),
             response_buffer: VecDeque::new(),
+            commands_sent: Vec::new(),
 
             mock_stream_stop: Arc::new(AtomicBool::new(false)),
             mock_stream_handle: None,
 
             calibration_snoop: Arc::new(RwLock::new(None)),
             calibration_handle: None,
+
+            subscribers: Arc::new(Mutex::new(Vec::new())),
+            extra_senders: Arc::new(Mutex::new(Vec::new())),
+
+            max_packet_age_seconds: None,
+            newest_timestamp_bits: Arc::new(AtomicU64::new(f64::NEG_INFINITY.to_bits())),
+            aged_out_packets: Arc::new(AtomicU64::new(0)),
+
+            latest_only: Arc::new(AtomicBool::new(false)),
+            latest_packet: Arc::new(Mutex::new(None)),
+            skipped_packets: Arc::new(AtomicU64::new(0)),
+
+            link_counters: Arc::new(Mutex::new(LinkCounters::default())),
+
+            raw_frame_tap: Arc::new(AtomicBool::new(false)),
+            raw_frames: Arc::new(RawFrameQueue::new(FIRMClient::MAX_TAPPED_RAW_FRAMES)),
+            resync_reporting: Arc::new(AtomicBool::new(false)),
+            parse_error_reporting: Arc::new(AtomicBool::new(false)),
+
+            reconnect: Arc::new(AtomicBool::new(false)),
+            idle_thresholds: Arc::new(Mutex::new(IdleThresholds::default())),
+            strict_responses: Arc::new(AtomicBool::new(false)),
+            packet_filter: Arc::new(Mutex::new(None)),
+            data_validation: Arc::new(Mutex::new(Default::default())),
+
+            read_buffer_size: Self::DEFAULT_READ_BUFFER_SIZE,
+            read_strategy: ReadStrategy::default(),
+            timestamp_policy: TimestampPolicy::Off,
+            corpus: Arc::new(Mutex::new(None)),
+            session_capture: None,
+            session: None,
+
+            connection: ConnectionSettings::custom(),
+            serial_framing: SerialFraming::default(),
+            playback: None,
+            raw_recorder: Arc::new(Mutex::new(None)),
+
+            alarms: Arc::new(Mutex::new(AlarmMonitor::default())),
+            alarm_subscribers: Arc::new(Mutex::new(Vec::new())),
+            alarm_sender,
+            alarm_receiver,
+
+            baro_sender,
+            baro_receiver,
+
+            altitude: Arc::new(Mutex::new(AltitudeState::default())),
         }
     }
 
-    /// Starts the background thread to read from the serial port and parse packets.
-    pub fn start(&mut self) {
-        // Return early if already running
-        if self.join_handle.is_some() {
-            return;
+    /// Creates a client connected to a FIRM bridge that serves the serial stream over TCP.
+    ///
+    /// # Arguments
+    ///
+    /// - `addr` (`&str`) - The bridge address, e.g. "192.168.1.20:5000".
+    /// - `timeout` (`f64`) - Connect and read timeout in seconds.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<Self, FIRMClientError>` - The client, or `FIRMClientError::Connect` if the bridge couldn't be reached.
+    pub fn connect_tcp(addr: &str, timeout: f64) -> Result<Self, FIRMClientError> {
+        let transport = connect_tcp_transport(addr, timeout)?;
+        let mut client = Self::from_transport(Box::new(transport));
+        client.connection = ConnectionSettings {
+            kind: ConnectionKind::Tcp,
+            address: Some(addr.to_string()),
+            baud_rate: None,
+            timeout_seconds: Some(timeout),
+        };
+        Ok(client)
+    }
+
+    /// Sets whether the reader thread reconnects after the connection drops.
+    ///
+    /// When enabled, a connection error is still reported through `check_error`, but the
+    /// reader thread keeps retrying every `RECONNECT_INTERVAL` instead of stopping. Only
+    /// transports that implement `Transport::reconnect` (such as TCP) can reconnect; others
+    /// stop as usual.
+    ///
+    /// # Arguments
+    ///
+    /// - `enabled` (`bool`) - Whether to reconnect.
+    pub fn set_reconnect(&mut self, enabled: bool) {
+        self.reconnect.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Reports `FIRMClientError::LinkIdle` through `check_error` once no data packet has
+    /// arrived for `timeout`, and again every `timeout` while the link stays quiet. It doesn't
+    /// fire before the first packet, see `expect_first_packet_within` for that. Applies to a
+    /// running client straight away.
+    ///
+    /// # Arguments
+    ///
+    /// - `timeout` (`Option<Duration>`) - The longest gap between packets, or `None` to turn
+    ///   the watchdog off.
+    pub fn set_idle_timeout(&mut self, timeout: Option<Duration>) {
+        self.idle_thresholds.lock().unwrap().idle = timeout;
+    }
+
+    /// Reports `FIRMClientError::LinkIdle` if no data packet arrives within `timeout` of the
+    /// reader thread starting, e.g. for a device that was never configured to stream. Applies
+    /// to a running client straight away.
+    ///
+    /// # Arguments
+    ///
+    /// - `timeout` (`Option<Duration>`) - The longest wait for the first packet, or `None` to
+    ///   not wait for it.
+    pub fn expect_first_packet_within(&mut self, timeout: Option<Duration>) {
+        self.idle_thresholds.lock().unwrap().first_packet = timeout;
+    }
+
+    /// Sets how responses with out-of-range fields are handled, e.g. a `DeviceConfig` with a
+    /// frequency the device can't run at. Applies to a running client straight away.
+    ///
+    /// # Arguments
+    ///
+    /// - `mode` (`ResponseValidation`) - Whether to flag or drop such responses. Defaults to
+    ///   `ResponseValidation::Lenient`.
+    pub fn set_response_validation(&mut self, mode: ResponseValidation) {
+        self.strict_responses
+            .store(mode == ResponseValidation::Strict, Ordering::Relaxed);
+    }
+
+    /// Drops data packets in the reader thread unless `filter` keeps them, so they never
+    /// reach `get_data_packets`, subscribers or extra receivers. Dropped packets are counted
+    /// in `stats().packets_filtered`. Replaces any previous filter and applies to a running
+    /// client straight away.
+    ///
+    /// # Arguments
+    ///
+    /// - `filter` (`impl PacketFilter`) - A closure returning true for packets to keep, or a
+    ///   built-in filter such as `Decimate`.
+    pub fn set_packet_filter(&mut self, filter: impl PacketFilter + 'static) {
+        *self.packet_filter.lock().unwrap() = Some(Box::new(filter));
+    }
+
+    /// Removes the filter set by `set_packet_filter`, so every packet is passed on again.
+    pub fn clear_packet_filter(&mut self) {
+        *self.packet_filter.lock().unwrap() = None;
+    }
+
+    /// Checks each data packet in the reader thread against `config`, with the last packet
+    /// that passed for the timestamp check, and flags or drops the ones that fail. They're counted in
+    /// `stats().packets_invalid`. Dropped packets never reach the packet filter. Applies to a
+    /// running client straight away.
+    ///
+    /// # Arguments
+    ///
+    /// - `mode` (`DataValidation`) - What to do with failing packets. Defaults to
+    ///   `DataValidation::Off`.
+    /// - `config` (`ValidationConfig`) - The ranges to check, e.g. `ValidationConfig::default()`.
+    pub fn set_data_validation(&mut self, mode: DataValidation, config: ValidationConfig) {
+        *self.data_validation.lock().unwrap() = (mode, config);
+    }
+
+    /// How long the reader thread waits between reconnect attempts.
+    pub const RECONNECT_INTERVAL: Duration = Duration::from_millis(250);
+
+    /// Read buffer size used unless `set_read_buffer_size` is called.
+    pub const DEFAULT_READ_BUFFER_SIZE: usize = 1024;
+
+    /// Largest read buffer `set_read_buffer_size` accepts.
+    pub const MAX_READ_BUFFER_SIZE: usize = 64 * 1024;
+
+    /// Most frames the raw frame tap keeps for `get_raw_frames`, see `set_raw_frame_tap`.
+    pub const MAX_TAPPED_RAW_FRAMES: usize = 1024;
+
+    /// Sets how many bytes the reader thread asks the port for per read. At high baud rates a
+    /// bigger buffer means fewer syscalls per second; a smaller one hands packets over sooner.
+    /// Takes effect at the next `start()`.
+    ///
+    /// # Arguments
+    ///
+    /// - `size` (`usize`) - Buffer size in bytes, from 1 to `MAX_READ_BUFFER_SIZE`.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<()>` - An error if `size` is out of range, leaving the current size in place.
+    pub fn set_read_buffer_size(&mut self, size: usize) -> Result<()> {
+        if !(1..=Self::MAX_READ_BUFFER_SIZE).contains(&size) {
+            return Err(anyhow::anyhow!(
+                "Read buffer size must be between 1 and {} bytes, got {size}",
+                Self::MAX_READ_BUFFER_SIZE
+            ));
         }
+        self.read_buffer_size = size;
+        Ok(())
+    }
 
-        // Gets the port or return if not available
-        let mut port = match self.port.take() {
-            Some(s) => s,
-            None => return,
-        };
+    /// Sets how the reader thread waits for incoming bytes, trading latency for CPU. Takes
+    /// effect at the next `start()`.
+    ///
+    /// # Arguments
+    ///
+    /// - `strategy` (`ReadStrategy`) - The wait strategy. Defaults to `ReadStrategy::BlockingRead`.
+    pub fn set_read_strategy(&mut self, strategy: ReadStrategy) {
+        self.read_strategy = strategy;
+    }
+
+    /// Sets what happens to data packets whose timestamp isn't after the previous packet's.
+    /// Takes effect at the next `start()`, and the corrections are counted in `stats()`.
+    /// `TimestampPolicy::Warn` reports each one as a `TimestampOutOfOrder` error, and every
+    /// policy but `Off` reports a device reboot once as `DeviceRestartDetected`.
+    ///
+    /// # Arguments
+    ///
+    /// - `policy` (`TimestampPolicy`) - The policy. Defaults to `TimestampPolicy::Off`.
+    pub fn set_timestamp_policy(&mut self, policy: TimestampPolicy) {
+        self.timestamp_policy = policy;
+    }
+
+    /// Starts or stops collecting one example of each frame shape the parser sees, accepted or
+    /// rejected, for a replay corpus. See `firm_core::corpus`. Takes effect at the next
+    /// `start()`, and a corpus already collected carries on growing across restarts.
+    ///
+    /// # Arguments
+    ///
+    /// - `max_entries` (`Option<usize>`) - Most shapes to keep, or `None` to stop collecting
+    ///   and drop the corpus.
+    pub fn collect_corpus(&mut self, max_entries: Option<usize>) {
+        *self.corpus.lock().unwrap() = max_entries.map(FrameCorpus::new);
+    }
+
+    /// Returns the frames collected since `collect_corpus` was called. While running, this is
+    /// updated each time a new shape is kept.
+    ///
+    /// # Returns
+    ///
+    /// - `Option<FrameCorpus>` - The corpus, or `None` if collection isn't on. Write it out with
+    ///   `FrameCorpus::write_dir`.
+    pub fn corpus(&self) -> Option<FrameCorpus> {
+        self.corpus.lock().unwrap().clone()
+    }
+
+    /// Makes `start()` ask the device for its `DeviceInfo` and `DeviceConfig` once the reader
+    /// is running, so recordings and exports begun afterwards can name the device they came
+    /// from. See `session_info`. A device that doesn't answer doesn't stop streaming; `start()`
+    /// just returns after the timeouts with a partial session.
+    ///
+    /// # Arguments
+    ///
+    /// - `timeout` (`Option<Duration>`) - How long to wait for each of the two replies, or
+    ///   `None` to not ask. Defaults to `None`.
+    pub fn set_session_capture(&mut self, timeout: Option<Duration>) {
+        self.session_capture = timeout;
+    }
+
+    /// Returns what the device reported about itself at the last `start()` with session
+    /// capture on.
+    ///
+    /// # Returns
+    ///
+    /// - `Option<&SessionInfo>` - The session, with `partial` set if the device didn't answer
+    ///   both requests, or `None` if nothing was captured.
+    pub fn session_info(&self) -> Option<&SessionInfo> {
+        self.session.as_ref()
+    }
 
-        let command_receiver = match self.command_receiver.take() {
-            Some(r) => r,
-            None => return,
+    /// Creates a client that replays a raw recording made by `record_raw` through the parser
+    /// as fast as possible, for offline analysis. The reader thread stops by itself once the
+    /// recording ends. Commands fail with `FIRMClientError::NotSupported`, and `session_info()`
+    /// returns the session stored in the recording's header, if any.
+    ///
+    /// # Arguments
+    ///
+    /// - `path` (`&Path`) - The raw recording to replay.
+    pub fn from_raw_recording(path: &Path) -> Result<Self> {
+        let (header, reader) = recording::open_raw_recording(path)?;
+        let mut client = Self::from_transport(Box::new(ReplayTransport::new(reader)));
+        client.session = header.session;
+        client.connection = ConnectionSettings {
+            kind: ConnectionKind::Replay,
+            address: Some(path.display().to_string()),
+            baud_rate: None,
+            timeout_seconds: None,
         };
+        Ok(client)
+    }
 
-        let mock_receiver = match self.mock_receiver.take() {
-            Some(r) => r,
-            None => return,
+    /// Creates a client that plays back a FIRM log file (`.frm`) as if a device were streaming
+    /// it, paced by the log's timestamps. Only the raw sensor fields are filled in, see
+    /// `firm_core::log_decoding`. The reader thread stops by itself when the log ends, so
+    /// `is_running()` turns false. Commands fail with `FIRMClientError::NotSupported`; the
+    /// playback is controlled with `pause`, `resume`, `seek` and `set_speed` instead.
+    ///
+    /// # Arguments
+    ///
+    /// - `path` (`&Path`) - The log file to play back.
+    /// - `speed` (`f64`) - Playback speed relative to real time; 0 plays as fast as possible.
+    pub fn from_log_file(path: &Path, speed: f64) -> Result<Self> {
+        let file = io::BufReader::new(File::open(path)?);
+        let transport = LogPlaybackTransport::new(file, speed)?;
+        let playback = transport.control();
+        let mut client = Self::from_transport(Box::new(transport));
+        client.playback = Some(playback);
+        client.connection = ConnectionSettings {
+            kind: ConnectionKind::LogFile,
+            address: Some(path.display().to_string()),
+            baud_rate: None,
+            timeout_seconds: None,
         };
+        Ok(client)
+    }
 
-        self.running.store(true, Ordering::Relaxed);
-        // Clone variables for the thread. This way we can move them in, and the original ones
-        // are still owned by self.
-        let running_clone = self.running.clone();
-        let sender = self.sender.clone();
-        let response_sender = self.response_sender.clone();
-        let error_sender = self.error_sender.clone();
+    /// Returns the settings the client's transport was opened with.
+    pub fn connection_settings(&self) -> &ConnectionSettings {
+        &self.connection
+    }
 
-        let calibration_snoop = self.calibration_snoop.clone();
+    /// Returns true for clients replaying a log or raw recording, which have no device to send
+    /// commands to.
+    pub fn is_replay(&self) -> bool {
+        matches!(
+            self.connection.kind,
+            ConnectionKind::Replay | ConnectionKind::LogFile
+        )
+    }
 
-        let handle: JoinHandle<Box<dyn SerialPort>> = thread::spawn(move || {
-            let mut parser = SerialParser::new();
-            // Buffer for reading from serial port
-            let mut buffer: [u8; 1024] = [0; 1024];
-
-            while running_clone.load(Ordering::Relaxed) {
-                // Drain pending command packets first and write them to the port.
-                while let Ok(cmd) = command_receiver.try_recv() {
-                    let cmd_bytes = cmd.to_bytes();
-                    // let hex = cmd_bytes
-                    //     .iter()
-                    //     .map(|b| format!("{:02X}", b))
-                    //     .collect::<Vec<_>>()
-                    //     .join(" ");
-                    // println!("Command packet bytes: {hex}");
-                    if let Err(e) = port.write_all(&cmd_bytes) {
-                        let _ = error_sender.send(e.to_string());
-                        running_clone.store(false, Ordering::Relaxed);
-                        return port;
-                    }
-                }
-                let _ = port.flush();
-
-                // Then drain pending mock packets and write them to the port.
-                while let Ok(packet) = mock_receiver.try_recv() {
-                    let packet_bytes = packet.to_bytes();
-                    // let hex = packet_bytes
-                    //     .iter()
-                    //     .map(|b| format!("{:02X}", b))
-                    //     .collect::<Vec<_>>()
-                    //     .join(" ");
-                    // println!("Mock packet bytes: {hex}");
-
-                    if let Err(e) = port.write_all(&packet_bytes) {
-                        let _ = error_sender.send(e.to_string());
-                        running_clone.store(false, Ordering::Relaxed);
-                        return port;
-                    }
-                }
-                let _ = port.flush();
-
-                // Read bytes from the serial port
-                match port.read(&mut buffer) {
-                    Ok(bytes_read @ 1..) => {
-                        // Feed the read bytes into the parser
-                        parser.parse_bytes(&buffer[..bytes_read]);
-
-                        // Reads all available data packets and send them to the main thread and calibration if wanted
-                        while let Some(firm_data_packet) = parser.get_data_packet() {
-                            let packet = firm_data_packet.data().clone();
-
-                            if sender.send(packet.clone()).is_err() {
-                                return port; // Receiver dropped
-                            }
-
-                            // We use a read lock which is very fast if no one is writing.
-                            if let Ok(guard) = calibration_snoop.read()
-                                && let Some(cal_tx) = &*guard
-                            {
-                                // Ignore errors (if cal thread died, we don't care)
-                                let _ = cal_tx.send(packet);
-                            }
-                        }
+    /// Pauses a log file playback. The client keeps running; packets resume from the same
+    /// point after `resume`.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<()>` - `FIRMClientError::NotSupported` unless the client was created with
+    ///   `from_log_file`.
+    pub fn pause(&self) -> Result<()> {
+        self.playback_control("Pausing playback")?.pause();
+        Ok(())
+    }
 
-                        // Reads all available response packets and send them to the main thread
-                        while let Some(firm_response_packet) = parser.get_response_packet() {
-                            let response = firm_response_packet.response().clone();
-                            if response_sender.send(response).is_err() {
-                                return port; // Receiver dropped
-                            }
-                        }
-                    }
-                    Ok(0) => {}
-                    // Timeouts might happen; just continue reading
-                    Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {}
-                    // Other errors should be reported and stop the thread:
-                    Err(e) => {
-                        let _ = error_sender.send(e.to_string());
-                        running_clone.store(false, Ordering::Relaxed);
-                        break;
-                    }
-                }
+    /// Resumes a paused log file playback. Pacing restarts from the next packet rather than
+    /// catching up on the time spent paused.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<()>` - `FIRMClientError::NotSupported` unless the client was created with
+    ///   `from_log_file`.
+    pub fn resume(&self) -> Result<()> {
+        self.playback_control("Resuming playback")?.resume();
+        Ok(())
+    }
+
+    /// Jumps a log file playback to the first packet at or after `timestamp_seconds`, forwards
+    /// or backwards. Once the log has ended, `stop()` and `start()` play it again from there.
+    ///
+    /// # Arguments
+    ///
+    /// - `timestamp_seconds` (`f64`) - Log time to continue from, as in `FIRMData::timestamp_seconds`.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<()>` - `FIRMClientError::NotSupported` unless the client was created with
+    ///   `from_log_file`.
+    pub fn seek(&self, timestamp_seconds: f64) -> Result<()> {
+        self.playback_control("Seeking")?.seek(timestamp_seconds);
+        Ok(())
+    }
+
+    /// Changes the speed of a log file playback, from the next packet on.
+    ///
+    /// # Arguments
+    ///
+    /// - `speed` (`f64`) - Playback speed relative to real time; 0 plays as fast as possible.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<()>` - `FIRMClientError::NotSupported` unless the client was created with
+    ///   `from_log_file`, or an error if `speed` is negative.
+    pub fn set_speed(&self, speed: f64) -> Result<()> {
+        self.playback_control("Changing playback speed")?
+            .set_speed(speed)?;
+        Ok(())
+    }
+
+    fn playback_control(&self, operation: &'static str) -> Result<&PlaybackControl> {
+        self.playback.as_deref().ok_or_else(|| {
+            FIRMClientError::NotSupported {
+                operation,
+                connection: self.connection.kind.as_str(),
             }
-            port
-        });
+            .into()
+        })
+    }
+
+    /// Starts recording every byte read from the device to `path`, before parsing, so the raw
+    /// stream can be re-parsed later with `from_raw_recording`. The file starts with a
+    /// `RecordingHeader` holding the start time, connection settings and `session_info()`, so
+    /// call this after a `start()` with session capture on to have the device named in it.
+    ///
+    /// Recording continues across `stop()`/`start()` (the file is flushed on `stop()`) until
+    /// `stop_recording` is called. Starting a new recording ends the previous one.
+    ///
+    /// # Arguments
+    ///
+    /// - `path` (`&Path`) - The file to record to. It is created or truncated.
+    pub fn record_raw(&mut self, path: &Path) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        RecordingHeader::now(self.connection.clone(), self.session.clone())
+            .write_to(&mut writer)?;
+        let previous = self.raw_recorder.lock().unwrap().replace(writer);
+        if let Some(mut previous) = previous {
+            previous.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Stops the raw recording started by `record_raw` and flushes it to disk.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<bool>` - `false` if no recording was running.
+    pub fn stop_recording(&mut self) -> Result<bool> {
+        match self.raw_recorder.lock().unwrap().take() {
+            Some(mut writer) => {
+                writer.flush()?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Returns `true` while a raw recording is running.
+    pub fn is_recording(&self) -> bool {
+        self.raw_recorder.lock().unwrap().is_some()
+    }
+
+    /// Starts the background thread to read from the serial port and parse packets.
+    ///
+    /// Calling it while the reader is running does nothing. If the reader stopped by itself
+    /// after a connection error, it's cleaned up as `stop` would, and the port is reopened by
+    /// name (serial and TCP connections) before starting again. With `set_session_capture` on,
+    /// it then waits for the device's info and config, see `session_info`.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<(), FIRMClientError>` - The error reopening the port, e.g.
+    ///   `FIRMClientError::PortNotFound`, or `FIRMClientError::CannotReopen` for a connection
+    ///   that can't be reopened.
+    pub fn start(&mut self) -> Result<(), FIRMClientError> {
+        if let Some(handle) = &self.join_handle {
+            if !handle.is_finished() {
+                return Ok(());
+            }
+            self.stop();
+        }
+
+        // A port abandoned by `stop_with_timeout`, or dropped by a reader thread that failed
+        // or panicked, is reopened from the connection settings.
+        let port = match self.port.take() {
+            Some(port) => port,
+            None => self.reopen_port()?,
+        };
+
+        let (Some(command_receiver), Some(control_receiver), Some(mock_receiver)) = (
+            self.command_receiver.take(),
+            self.control_receiver.take(),
+            self.mock_receiver.take(),
+        ) else {
+            unreachable!("the receivers are recreated by stop");
+        };
+
+        self.running.store(true, Ordering::Relaxed);
+        // The device's clock may have started over since the last run, e.g. after
+        // `reboot_and_reconnect`, so packets are only aged against ones from this run.
+        reset_watermark(&self.newest_timestamp_bits);
+        self.detached = Arc::new(AtomicBool::new(false));
+        let reader = ReaderLoop::new(
+            self,
+            port,
+            command_receiver,
+            control_receiver,
+            mock_receiver,
+        );
+
+        let panic_running = self.running.clone();
+        let panic_error_sender = self.error_sender.clone();
+        // A panic (e.g. a parser bug) must not leave `is_running()` reporting true with nothing
+        // reading, so it's caught here and reported. The port is dropped with the thread since
+        // its state is unknown; `start()` reopens it like an abandoned one.
+        let handle =
+            thread::spawn(
+                move || match panic::catch_unwind(AssertUnwindSafe(|| reader.run())) {
+                    Ok(port) => port,
+                    Err(payload) => {
+                        panic_running.store(false, Ordering::Relaxed);
+                        panic_error_sender.report(ErrorEvent::fatal(
+                            FIRMClientError::ThreadPanicked {
+                                message: panic_message(payload.as_ref()),
+                            },
+                        ));
+                        None
+                    }
+                },
+            );
 
         self.join_handle = Some(handle);
+        if let Some(timeout) = self.session_capture
+            && !self.is_replay()
+        {
+            self.capture_session(timeout);
+        }
+        Ok(())
+    }
+
+    /// Asks the now running device for its info and config, keeping whatever arrives in time.
+    fn capture_session(&mut self, timeout: Duration) {
+        let device_info = self.get_device_info(timeout).ok().flatten();
+        let device_config = self.get_device_config(timeout).ok().flatten();
+        self.session = Some(SessionInfo::new(device_info, device_config));
     }
 
-    /// Stops the background thread and closes the serial port.
+    /// Stops the background thread and closes the serial port. Bytes that had already arrived
+    /// are read and parsed first, so their packets can still be retrieved afterwards.
     pub fn stop(&mut self) {
+        let _ = self.stop_inner(None);
+    }
+
+    /// Like `stop`, but gives up waiting for the reader thread after `timeout`, e.g. when a
+    /// driver blocks a read far longer than the port's read timeout. The stuck thread is
+    /// detached and its port abandoned; the next `start()` reopens the port by name (serial
+    /// and TCP connections) or reports that it can't.
+    ///
+    /// # Arguments
+    ///
+    /// - `timeout` (`Duration`) - How long to wait for the reader thread to finish.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<(), FIRMClientError>` - `FIRMClientError::StopTimedOut` if the thread was detached instead of joined.
+    pub fn stop_with_timeout(&mut self, timeout: Duration) -> Result<(), FIRMClientError> {
+        self.stop_inner(Some(timeout))
+    }
+
+    fn stop_inner(&mut self, timeout: Option<Duration>) -> Result<(), FIRMClientError> {
         if let Err(e) = self.stop_mock_log_stream(false, true) {
-            let _ = self.error_sender.send(e.to_string());
+            self.error_sender
+                .report(ErrorEvent::now(FIRMClientError::MockStream {
+                    message: e.to_string(),
+                }));
         }
 
         if self.calibration_handle.is_some() {
@@ -260,11 +932,40 @@ impl FIRMClient {
         }
 
         self.running.store(false, Ordering::Relaxed);
+        let mut result = Ok(());
         // todo: explain this properly when I understand it better (it's mostly for restarting)
-        if let Some(handle) = self.join_handle.take()
-            && let Ok(port) = handle.join()
+        if let Some(handle) = self.join_handle.take() {
+            let finished = match timeout {
+                Some(timeout) => {
+                    let deadline = Instant::now() + timeout;
+                    while !handle.is_finished() && Instant::now() < deadline {
+                        thread::sleep(Duration::from_millis(1));
+                    }
+                    handle.is_finished()
+                }
+                None => true,
+            };
+            if finished {
+                if let Ok(Some(port)) = handle.join() {
+                    self.port = Some(port);
+                }
+            } else if let Some(timeout) = timeout {
+                // Dropping the handle detaches the thread; it exits once its read returns.
+                // It keeps the old running flag, so it can't see or clear the next thread's.
+                self.detached.store(true, Ordering::Relaxed);
+                self.running = Arc::new(AtomicBool::new(false));
+                result = Err(FIRMClientError::StopTimedOut { timeout });
+            }
+        }
+
+        // Make everything read so far reach the disk, the recording itself carries on.
+        if let Some(writer) = self.raw_recorder.lock().unwrap().as_mut()
+            && let Err(e) = writer.flush()
         {
-            self.port = Some(port);
+            self.error_sender
+                .report(ErrorEvent::now(FIRMClientError::Recording {
+                    message: format!("failed to flush: {e}"),
+                }));
         }
 
         // The receivers are moved into the background thread on start()
@@ -275,11 +976,42 @@ impl FIRMClient {
             self.command_receiver = Some(new_receiver);
         }
 
+        if self.control_receiver.is_none() {
+            let (new_sender, new_receiver) = channel();
+            self.control_sender = new_sender;
+            self.control_receiver = Some(new_receiver);
+        }
+
         if self.mock_receiver.is_none() {
             let (new_sender, new_receiver) = channel();
             self.mock_sender = new_sender;
             self.mock_receiver = Some(new_receiver);
         }
+        result
+    }
+
+    /// Opens the port again from `connection`, after the previous one was abandoned.
+    fn reopen_port(&self) -> Result<Box<dyn Transport>, FIRMClientError> {
+        let settings = &self.connection;
+        let timeout = settings.timeout_seconds.unwrap_or(0.1);
+        match (settings.kind, settings.address.as_deref()) {
+            (ConnectionKind::Serial, Some(name)) => {
+                let baud_rate = settings.baud_rate.unwrap_or(2_000_000);
+                let timeout = Duration::from_millis((timeout * 1000.0) as u64);
+                Ok(Box::new(open_serial_port(
+                    name,
+                    baud_rate,
+                    timeout,
+                    &self.serial_framing,
+                )?))
+            }
+            (ConnectionKind::Tcp, Some(addr)) => {
+                Ok(Box::new(connect_tcp_transport(addr, timeout)?))
+            }
+            _ => Err(FIRMClientError::CannotReopen {
+                connection: settings.kind.as_str(),
+            }),
+        }
     }
 
     /// Retrieves all available data packets, optionally blocking until at least one is available.
@@ -291,20 +1023,408 @@ impl FIRMClient {
         &mut self,
         timeout: Option<Duration>,
     ) -> Result<Vec<FIRMData>, RecvTimeoutError> {
-        let mut packets = Vec::new();
-
-        // If blocking, wait for at most one packet. The next loop will drain any others.
-        if let Some(duration) = timeout {
-            packets.push(self.packet_receiver.recv_timeout(duration)?);
-        }
+        Ok(self
+            .get_packets_with_host_time(timeout)?
+            .into_iter()
+            .map(|(packet, _)| packet)
+            .collect())
+    }
+
+    /// Like `get_data_packets`, but with the host time each packet was received at, for lining
+    /// device time up with wall-clock events. See `ClockDriftEstimator` to convert between the
+    /// two.
+    ///
+    /// The host times come from a monotonic clock anchored to the system time when the reader
+    /// thread started, so they never go backwards, even if the system clock is adjusted.
+    ///
+    /// # Arguments
+    ///
+    /// - `timeout` (`Option<Duration>`) - If `Some(duration)`, the method will block for up to `duration` waiting for a packet.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<Vec<(FIRMData, SystemTime)>, RecvTimeoutError>` - The packets with their receive times.
+    pub fn get_packets_with_host_time(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<(FIRMData, SystemTime)>, RecvTimeoutError> {
+        let mut packets = Vec::new();
+
+        // If blocking, wait for at most one packet. The next loop will drain any others.
+        if let Some(duration) = timeout {
+            packets.push(self.packet_receiver.recv_timeout(duration)?);
+        }
 
         // Drains the rest of the available packets without blocking
         while let Ok(packet) = self.packet_receiver.try_recv() {
             packets.push(packet);
         }
+
+        packets.retain(|(packet, _)| !self.age_out(packet));
+        Ok(packets)
+    }
+
+    /// Retrieves all available secondary barometer readings, see `FIRMBaroPacket`. They're also
+    /// fused into `pressure_altitude_meters` of the data packets around them.
+    ///
+    /// # Arguments
+    ///
+    /// - `timeout` (`Option<Duration>`) - If `Some(duration)`, the method will block for up to `duration` waiting for a reading.
+    pub fn get_baro_packets(
+        &self,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<FIRMBaroPacket>, RecvTimeoutError> {
+        let mut packets = Vec::new();
+        if let Some(duration) = timeout {
+            packets.push(self.baro_receiver.recv_timeout(duration)?);
+        }
+        packets.extend(self.baro_receiver.try_iter());
         Ok(packets)
     }
 
+    /// Sets the maximum age of packets handed out by `get_data_packets` and the packet iterators.
+    ///
+    /// Age is measured in device time against the newest packet the reader thread has seen,
+    /// so after a consumer stall only the most recent `max_age` worth of packets is returned.
+    /// The newest packet is forgotten when the reader starts or reconnects, and when the
+    /// device's timestamps start over as they do after a reboot (see `is_device_restart`).
+    /// Subscribers registered with `subscribe` always see every packet.
+    ///
+    /// # Arguments
+    ///
+    /// - `max_age` (`Option<Duration>`) - The maximum packet age, or `None` to keep every packet.
+    pub fn set_max_packet_age(&mut self, max_age: Option<Duration>) {
+        self.max_packet_age_seconds = max_age.map(|age| age.as_secs_f64());
+    }
+
+    /// Drops queued packets that are older than the max-age policy and returns the fresh ones.
+    ///
+    /// Useful right after a consumer stall to jump back to near real time. If no max age is set,
+    /// this simply returns every queued packet.
+    pub fn flush_stale(&mut self) -> Vec<FIRMData> {
+        let mut packets = Vec::new();
+        while let Ok((packet, _)) = self.packet_receiver.try_recv() {
+            if !self.age_out(&packet) {
+                packets.push(packet);
+            }
+        }
+        packets
+    }
+
+    /// Returns the newest available data packet, discarding any older ones queued before it.
+    ///
+    /// Meant for displays that only care about the current state, where draining a large
+    /// backlog through `get_data_packets` every frame would be wasted work. The discarded
+    /// packets are counted by `skipped_packet_count`.
+    ///
+    /// # Returns
+    ///
+    /// - `Option<FIRMData>` - The newest packet, or `None` if none arrived since the last call.
+    pub fn get_latest_packet(&self) -> Option<FIRMData> {
+        let mut latest = self.latest_packet.lock().unwrap().take();
+        let mut seen = u64::from(latest.is_some());
+        while let Ok((packet, _)) = self.packet_receiver.try_recv() {
+            seen += 1;
+            // The held packet from latest-only mode can be newer than what was queued after
+            // the mode was turned off, so keep whichever is newest.
+            if latest
+                .as_ref()
+                .is_none_or(|held| packet.timestamp_seconds >= held.timestamp_seconds)
+            {
+                latest = Some(packet);
+            }
+        }
+        if seen > 1 {
+            self.skipped_packets.fetch_add(seen - 1, Ordering::Relaxed);
+        }
+        latest
+    }
+
+    /// Switches latest-only mode on or off. While on, the reader thread keeps just the newest
+    /// packet for `get_latest_packet` instead of queueing every packet, so nothing builds up
+    /// when the consumer falls behind. `get_data_packets` and the packet iterators only see
+    /// packets queued while it is off. Subscribers and `add_receiver` channels are unaffected.
+    ///
+    /// # Arguments
+    ///
+    /// - `enabled` (`bool`) - Whether to keep only the newest packet.
+    pub fn set_latest_only(&mut self, enabled: bool) {
+        self.latest_only.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Returns the number of packets dropped so far because a newer one replaced them, either
+    /// in `get_latest_packet` or by the reader thread in latest-only mode.
+    pub fn skipped_packet_count(&self) -> u64 {
+        self.skipped_packets.load(Ordering::Relaxed)
+    }
+
+    /// Returns a snapshot of serial link health: bytes read, packets parsed, parser errors and
+    /// the current packet rate.
+    ///
+    /// # Returns
+    ///
+    /// - `LinkStats` - Counters accumulated since the client was created.
+    pub fn stats(&self) -> LinkStats {
+        self.link_counters.lock().unwrap().snapshot(
+            Instant::now(),
+            self.aged_out_count(),
+            &self.packet_depth,
+        )
+    }
+
+    /// Returns how many parsed data packets are queued and not yet consumed, e.g. by
+    /// `get_data_packets`. A backlog that keeps growing means the consumer is falling behind.
+    /// Packets held for subscribers or in latest-only mode aren't queued, so don't count.
+    pub fn backlog(&self) -> usize {
+        self.packet_depth.current()
+    }
+
+    /// Returns the largest `backlog` since the client was created or
+    /// `reset_max_backlog_seen` was last called.
+    pub fn max_backlog_seen(&self) -> usize {
+        self.packet_depth.high_water()
+    }
+
+    /// Starts `max_backlog_seen` over from the current backlog.
+    pub fn reset_max_backlog_seen(&self) {
+        self.packet_depth.reset_high_water();
+    }
+
+    /// Returns the number of packets dropped so far by the max-age policy.
+    pub fn aged_out_count(&self) -> u64 {
+        self.aged_out_packets.load(Ordering::Relaxed)
+    }
+
+    /// Sets the ground level that `pressure_altitude_meters` is measured from, by averaging
+    /// the pressure of the next `samples` packets and using it as the reference pressure.
+    /// Every packet after that reports its height above that ground level. Calling it again
+    /// re-baselines.
+    ///
+    /// # Arguments
+    ///
+    /// - `samples` (`usize`) - How many packets to average, e.g. `altitude::DEFAULT_ZERO_SAMPLES`.
+    /// - `timeout` (`Duration`) - How long to wait for those packets.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<Option<f32>>` - The new ground altitude above sea level in meters, `None` if
+    ///   not enough packets arrived in time (the previous reference is kept), or an error if the
+    ///   client isn't running or `samples` is 0.
+    pub fn zero_out_pressure_altitude(
+        &mut self,
+        samples: usize,
+        timeout: Duration,
+    ) -> Result<Option<f32>> {
+        if samples == 0 {
+            return Err(anyhow::anyhow!("Need at least one sample to zero altitude"));
+        }
+        if !self.is_running() {
+            return Err(anyhow::anyhow!("Client is not running"));
+        }
+
+        let (done, finished) = channel();
+        self.altitude.lock().unwrap().start_zeroing(samples, done);
+        match finished.recv_timeout(timeout) {
+            Ok(reference) => Ok(Some(reference)),
+            Err(_) => {
+                self.altitude.lock().unwrap().cancel_zeroing();
+                Ok(None)
+            }
+        }
+    }
+
+    /// Reports `pressure_altitude_meters` as altitude above mean sea level, from the standard
+    /// atmosphere formula with `pascals` as the sea level pressure (e.g. the local QNH). This
+    /// drops the ground reference set by `zero_out_pressure_altitude`. The reader thread's
+    /// parser measures from it starting with its next read, see
+    /// `SerialParser::set_sea_level_pressure`.
+    ///
+    /// # Arguments
+    ///
+    /// - `pascals` (`f32`) - Sea level pressure; `altitude::STANDARD_SEA_LEVEL_PRESSURE_PASCALS`
+    ///   is the default.
+    pub fn set_sea_level_pressure(&mut self, pascals: f32) {
+        self.altitude
+            .lock()
+            .unwrap()
+            .set_sea_level_pressure(pascals);
+    }
+
+    /// Returns the ground altitude above sea level set by `zero_out_pressure_altitude`, if any.
+    pub fn ground_reference_altitude(&self) -> Option<f32> {
+        self.altitude.lock().unwrap().ground_reference_meters()
+    }
+
+    /// Replaces the alarm rules evaluated by the reader thread, clearing every alarm.
+    ///
+    /// Rules are checked every 100 ms while the client is running, against the same counters
+    /// `stats` returns. Events are delivered to `on_alarm` callbacks and `get_alarm_events`.
+    ///
+    /// # Arguments
+    ///
+    /// - `rules` (`Vec<AlarmRule>`) - The rules to evaluate, e.g. `AlarmRule::defaults(100.0)`.
+    ///   An empty list turns alarms off.
+    pub fn set_alarm_rules(&mut self, rules: Vec<AlarmRule>) {
+        *self.alarms.lock().unwrap() = AlarmMonitor::new(rules);
+    }
+
+    /// Returns the names of the alarms currently raised.
+    pub fn active_alarms(&self) -> Vec<String> {
+        self.alarms.lock().unwrap().active_alarms()
+    }
+
+    /// Returns the alarm events emitted since the last call, oldest first.
+    pub fn get_alarm_events(&self) -> Vec<AlarmEvent> {
+        self.alarm_receiver.try_iter().collect()
+    }
+
+    /// Registers a callback that runs on the reader thread whenever an alarm is raised or
+    /// cleared. Events are still delivered through `get_alarm_events` as well. A callback that
+    /// panics is removed and the panic is reported through `check_error`.
+    ///
+    /// # Arguments
+    ///
+    /// - `callback` (`impl FnMut(&AlarmEvent) + Send + 'static`) - Called with each event.
+    ///
+    /// # Returns
+    ///
+    /// - `SubscriptionHandle` - Handle used to cancel the callback.
+    pub fn on_alarm(
+        &mut self,
+        callback: impl FnMut(&AlarmEvent) + Send + 'static,
+    ) -> SubscriptionHandle {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.alarm_subscribers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(Subscriber {
+                callback: Box::new(callback),
+                cancelled: cancelled.clone(),
+            });
+        SubscriptionHandle { cancelled }
+    }
+
+    /// Returns true (and counts it) if `packet` is older than the configured max age.
+    fn age_out(&self, packet: &FIRMData) -> bool {
+        is_aged_out(
+            packet,
+            self.max_packet_age_seconds,
+            &self.newest_timestamp_bits,
+            &self.aged_out_packets,
+        )
+    }
+
+    /// Returns a blocking iterator over incoming data packets.
+    ///
+    /// The iterator waits until the next packet arrives and ends once the client stops running
+    /// (e.g. the reader thread hit a serial error), so `for packet in client.iter_packets()`
+    /// does not hang forever on a dead connection.
+    pub fn iter_packets(&self) -> PacketIter<'_> {
+        PacketIter {
+            source: self,
+            idle_timeout: None,
+            finished: false,
+        }
+    }
+
+    /// Like `iter_packets`, but also ends when no packet arrives for `idle_timeout`.
+    ///
+    /// # Arguments
+    ///
+    /// - `idle_timeout` (`Duration`) - How long the stream may stay quiet before the iterator ends.
+    pub fn iter_packets_timeout(&self, idle_timeout: Duration) -> PacketIter<'_> {
+        PacketIter {
+            source: self,
+            idle_timeout: Some(idle_timeout),
+            finished: false,
+        }
+    }
+
+    /// Registers a callback that runs on the reader thread for every parsed data packet.
+    ///
+    /// Packets are still delivered through `get_data_packets` as well. Callbacks should return
+    /// quickly since they hold up reading. A callback that panics is removed and the panic is
+    /// reported through `check_error`.
+    ///
+    /// # Arguments
+    ///
+    /// - `callback` (`impl FnMut(&FIRMData) + Send + 'static`) - Called with each packet, in arrival order.
+    ///
+    /// # Returns
+    ///
+    /// - `SubscriptionHandle` - Handle used to cancel the subscription.
+    pub fn subscribe(
+        &mut self,
+        callback: impl FnMut(&FIRMData) + Send + 'static,
+    ) -> SubscriptionHandle {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.subscribers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(Subscriber {
+                callback: Box::new(callback),
+                cancelled: cancelled.clone(),
+            });
+        SubscriptionHandle { cancelled }
+    }
+
+    /// Registers a callback that runs on the reader thread whenever `field` crosses one of
+    /// `thresholds`, e.g. altitude callouts every 500 ft. Each crossing is reported once per
+    /// direction; the value has to leave the `hysteresis` band around a threshold on the far
+    /// side before it counts as crossed, so noise around a threshold doesn't repeat events.
+    /// The first packet only sets which side of each threshold the value starts on.
+    ///
+    /// For altitude, `est_position_z_meters` is the device's filtered estimate and
+    /// `pressure_altitude_meters` the host-computed barometric altitude.
+    ///
+    /// # Arguments
+    ///
+    /// - `field` (`&str`) - One of `FIRMData::field_names()`.
+    /// - `thresholds` (`Vec<f32>`) - The values to report crossings of.
+    /// - `direction` (`ThresholdDirection`) - Which crossings to report.
+    /// - `hysteresis` (`f32`) - Half-width of the band around each threshold, in the field's units.
+    /// - `callback` (`impl FnMut(&ThresholdEvent) + Send + 'static`) - Called with each crossing.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<SubscriptionHandle>` - Handle used to cancel the callback, or an error if `field` isn't a packet field.
+    pub fn on_threshold(
+        &mut self,
+        field: &str,
+        thresholds: Vec<f32>,
+        direction: ThresholdDirection,
+        hysteresis: f32,
+        mut callback: impl FnMut(&ThresholdEvent) + Send + 'static,
+    ) -> Result<SubscriptionHandle> {
+        let mut monitor = ThresholdMonitor::new(field, thresholds, direction, hysteresis)
+            .ok_or_else(|| anyhow::anyhow!("Unknown packet field '{field}'"))?;
+        Ok(self.subscribe(move |packet| {
+            for event in monitor.update(packet) {
+                callback(&event);
+            }
+        }))
+    }
+
+    /// Creates an additional receiver that gets its own copy of every data packet parsed from
+    /// now on, independent of `get_data_packets` and of any other receiver.
+    ///
+    /// Each receiver buffers independently and without bound: a receiver that is never read
+    /// keeps every packet in memory (about 120 bytes each, so roughly 7 MB per minute at
+    /// 1 kHz). Drop receivers you no longer need; the reader thread removes disconnected ones
+    /// the next time it delivers a packet, without affecting the others.
+    ///
+    /// # Returns
+    ///
+    /// - `Receiver<FIRMData>` - A channel receiving a clone of each data packet.
+    pub fn add_receiver(&mut self) -> Receiver<FIRMData> {
+        let (sender, receiver) = channel();
+        self.extra_senders
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(sender);
+        receiver
+    }
+
     /// Retrieves all available response packets, optionally blocking until at least one is available.
     ///
     /// # Arguments
@@ -330,22 +1450,99 @@ impl FIRMClient {
         Ok(responses)
     }
 
+    /// Returns the number of received responses held back because no command was waiting for
+    /// them (e.g. late replies to timed-out commands). Bounded by an internal limit.
+    pub fn buffered_response_count(&self) -> usize {
+        self.response_buffer.len()
+    }
+
     /// Requests device info and waits for the response.
+    ///
+    /// Can be called while data is streaming: data packets keep going to `get_data_packets`,
+    /// and other responses arriving first are kept for the calls waiting on them.
     pub fn get_device_info(&mut self, timeout: Duration) -> Result<Option<DeviceInfo>> {
-        self.send_command(FIRMCommandPacket::build_get_device_info_command())?;
-        self.wait_for_matching_response(timeout, |res| match res {
-            FIRMResponse::GetDeviceInfo(info) => Some(info.clone()),
-            _ => None,
-        })
+        self.send_and_wait(
+            FIRMCommandPacket::build_get_device_info_command(),
+            timeout,
+            |res| match res {
+                FIRMResponse::GetDeviceInfo(info) => Some(info.clone()),
+                _ => None,
+            },
+        )
+    }
+
+    /// Checks that the device is answering, even while it isn't streaming data, by sending a
+    /// `GetDeviceInfo` command and timing the reply. Data packets keep flowing meanwhile.
+    ///
+    /// # Arguments
+    ///
+    /// - `timeout` (`Duration`) - How long to wait for the reply.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<Duration>` - The round trip time, or `FIRMClientError::CommandTimeout` if the
+    ///   device didn't answer in time.
+    pub fn ping(&mut self, timeout: Duration) -> Result<Duration> {
+        let sent = Instant::now();
+        match self.get_device_info(timeout)? {
+            Some(_) => Ok(sent.elapsed()),
+            None => Err(FIRMClientError::CommandTimeout {
+                command: FIRMCommand::GetDeviceInfo,
+                timeout,
+            }
+            .into()),
+        }
     }
 
     /// Requests device configuration and waits for the response.
     pub fn get_device_config(&mut self, timeout: Duration) -> Result<Option<DeviceConfig>> {
-        self.send_command(FIRMCommandPacket::build_get_device_config_command())?;
-        self.wait_for_matching_response(timeout, |res| match res {
-            FIRMResponse::GetDeviceConfig(cfg) => Some(cfg.clone()),
-            _ => None,
-        })
+        self.send_and_wait(
+            FIRMCommandPacket::build_get_device_config_command(),
+            timeout,
+            |res| match res {
+                FIRMResponse::GetDeviceConfig(cfg) => Some(cfg.clone()),
+                _ => None,
+            },
+        )
+    }
+
+    /// Sets the device configuration, then reads it back to check the device applied it.
+    ///
+    /// The name is cut to `DEVICE_NAME_LENGTH` bytes on the way to the device, so the
+    /// read-back is compared against `DeviceConfig::as_stored`.
+    ///
+    /// # Arguments
+    ///
+    /// - `config` (`&DeviceConfig`) - The configuration to apply.
+    /// - `timeout` (`Duration`) - How long to wait for each of the two replies.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<Option<bool>>` - `Some(true)` if the device acknowledged and reports the new
+    ///   config, `Some(false)` if it rejected the config or reports something else, and `None`
+    ///   if either reply timed out.
+    pub fn set_device_config_verified(
+        &mut self,
+        config: &DeviceConfig,
+        timeout: Duration,
+    ) -> Result<Option<bool>> {
+        match self.set_device_config(
+            config.name.clone(),
+            config.frequency,
+            config.protocol,
+            timeout,
+        )? {
+            Some(true) => {}
+            other => return Ok(other),
+        }
+        let applied = self.get_device_config(timeout)?;
+        // Out-of-range values the device accepted still count as applied.
+        Ok(applied.map(|applied| {
+            DeviceConfig {
+                suspect: false,
+                ..applied
+            } == config.as_stored()
+        }))
     }
 
     /// Sets device configuration and waits for acknowledgement.
@@ -360,12 +1557,16 @@ impl FIRMClient {
             name,
             frequency,
             protocol,
+            suspect: false,
         };
-        self.send_command(FIRMCommandPacket::build_set_device_config_command(config))?;
-        self.wait_for_matching_response(timeout, |res| match res {
-            FIRMResponse::SetDeviceConfig(ok) => Some(*ok),
-            _ => None,
-        })
+        self.send_and_wait(
+            FIRMCommandPacket::build_set_device_config_command(config),
+            timeout,
+            |res| match res {
+                FIRMResponse::SetDeviceConfig(ok) => Some(*ok),
+                _ => None,
+            },
+        )
     }
 
     pub fn set_magnetometer_calibration(
@@ -374,13 +1575,14 @@ impl FIRMClient {
         scale_matrix: [f32; NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS],
         timeout: Duration,
     ) -> Result<Option<bool>> {
-        self.send_command(
+        self.send_and_wait(
             FIRMCommandPacket::build_set_magnetometer_calibration_command(offsets, scale_matrix),
-        )?;
-        self.wait_for_matching_response(timeout, |res| match res {
-            FIRMResponse::SetMagnetometerCalibration(ok) => Some(*ok),
-            _ => None,
-        })
+            timeout,
+            |res| match res {
+                FIRMResponse::SetMagnetometerCalibration(ok) => Some(*ok),
+                _ => None,
+            },
+        )
     }
 
     pub fn set_imu_calibration(
@@ -391,32 +1593,119 @@ impl FIRMClient {
         gyro_scale_matrix: [f32; NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS],
         timeout: Duration,
     ) -> Result<Option<bool>> {
-        self.send_command(FIRMCommandPacket::build_set_imu_calibration_command(
-            accel_offsets,
-            accel_scale_matrix,
-            gyro_offsets,
-            gyro_scale_matrix,
-        ))?;
-        self.wait_for_matching_response(timeout, |res| match res {
-            FIRMResponse::SetIMUCalibration(ok) => Some(*ok),
-            _ => None,
-        })
+        self.send_and_wait(
+            FIRMCommandPacket::build_set_imu_calibration_command(
+                accel_offsets,
+                accel_scale_matrix,
+                gyro_offsets,
+                gyro_scale_matrix,
+            ),
+            timeout,
+            |res| match res {
+                FIRMResponse::SetIMUCalibration(ok) => Some(*ok),
+                _ => None,
+            },
+        )
     }
 
+    /// Reads the device's current IMU and magnetometer calibration.
+    ///
+    /// # Arguments
+    ///
+    /// - `timeout` (`Duration`) - How long to wait for the reply.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<Option<CalibrationValues>>` - The calibration, or `None` on timeout. It can be
+    ///   modified and written back with `set_calibration`.
     pub fn get_calibration(&mut self, timeout: Duration) -> Result<Option<CalibrationValues>> {
-        self.send_command(FIRMCommandPacket::build_get_calibration_command())?;
-        self.wait_for_matching_response(timeout, |res| match res {
-            FIRMResponse::GetCalibration(calibration) => Some(calibration.clone()),
-            _ => None,
-        })
+        self.send_and_wait(
+            FIRMCommandPacket::build_get_calibration_command(),
+            timeout,
+            |res| match res {
+                FIRMResponse::GetCalibration(calibration) => Some(calibration.clone()),
+                _ => None,
+            },
+        )
+    }
+
+    /// Writes a full calibration, e.g. one read with `get_calibration` and modified, by sending
+    /// the IMU and then the magnetometer calibration.
+    ///
+    /// # Arguments
+    ///
+    /// - `calibration` (`&CalibrationValues`) - The calibration to apply.
+    /// - `timeout` (`Duration`) - How long to wait for each acknowledgement.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<Option<bool>>` - `Some(true)` if both were accepted, `Some(false)` if the device
+    ///   rejected one, or `None` on timeout. The magnetometer calibration isn't sent unless the
+    ///   IMU one was accepted.
+    pub fn set_calibration(
+        &mut self,
+        calibration: &CalibrationValues,
+        timeout: Duration,
+    ) -> Result<Option<bool>> {
+        match self.set_imu_calibration(
+            calibration.imu_accelerometer_offsets,
+            calibration.imu_accelerometer_scale_matrix,
+            calibration.imu_gyroscope_offsets,
+            calibration.imu_gyroscope_scale_matrix,
+            timeout,
+        )? {
+            Some(true) => {}
+            other => return Ok(other),
+        }
+        self.set_magnetometer_calibration(
+            calibration.magnetometer_offsets,
+            calibration.magnetometer_scale_matrix,
+            timeout,
+        )
+    }
+
+    /// Puts the device in mock mode and streams the `.frm` log at `log_path` to it on a
+    /// background thread, see `mock_stream`. Cancel is sent once the whole log has been sent.
+    ///
+    /// Data packets keep arriving as usual meanwhile, through `get_data_packets()` or any
+    /// other API. Use `is_mock_log_streaming()` to check whether it's done, and `stop_mock()`
+    /// to end it early or collect the sent packet count.
+    ///
+    /// # Arguments
+    ///
+    /// - `log_path` (`&Path`) - The mock log to stream.
+    /// - `options` (`MockOptions`) - Pacing and read size.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<()>` - An error if a stream is already running or the device didn't
+    ///   acknowledge mock mode within `MOCK_START_TIMEOUT`.
+    pub fn start_mock(&mut self, log_path: &Path, options: MockOptions) -> Result<()> {
+        self.start_mock_inner(
+            log_path.to_path_buf(),
+            Self::MOCK_START_TIMEOUT,
+            options,
+            true,
+        )
     }
 
-    /// Starts streaming a `.frm` mock log file on a background thread.
+    /// How long `start_mock` waits for the device to acknowledge mock mode.
+    pub const MOCK_START_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Stops the stream started by `start_mock`, tells the device to leave mock mode and
+    /// waits for the streaming thread to finish.
+    ///
+    /// # Returns
     ///
-    /// While the mock stream is running you can continue to call `get_data_packets()` or other
-    /// APIs to read/log FIRM's output.
+    /// - `Result<usize>` - The number of log packets sent, or 0 if no stream was started.
+    pub fn stop_mock(&mut self) -> Result<usize> {
+        Ok(self.stop_mock_log_stream(true, true)?.unwrap_or(0))
+    }
+
+    /// Starts streaming a `.frm` mock log file on a background thread, like `start_mock` with
+    /// every setting spelled out.
     ///
-    /// Use `is_mock_log_streaming()` to check status and `try_join_mock_log_stream()` to
+    /// Use `is_mock_log_streaming()` to check status and `stop_mock_log_stream()` to
     /// retrieve the sent packet count when finished.
     pub fn start_mock_log_stream(
         &mut self,
@@ -426,13 +1715,33 @@ impl FIRMClient {
         speed: f64,
         chunk_size: usize,
         cancel_on_finish: bool,
+    ) -> Result<()> {
+        let options = MockOptions {
+            realtime,
+            speed,
+            chunk_size,
+        };
+        self.start_mock_inner(
+            PathBuf::from(log_path),
+            start_timeout,
+            options,
+            cancel_on_finish,
+        )
+    }
+
+    fn start_mock_inner(
+        &mut self,
+        log_path: PathBuf,
+        start_timeout: Duration,
+        options: MockOptions,
+        cancel_on_finish: bool,
     ) -> Result<()> {
         if self.is_mock_log_streaming() {
             return Err(anyhow::anyhow!("Mock stream already running"));
         }
 
         if !self.is_running() {
-            self.start();
+            self.start()?;
         }
 
         // If a previous stream finished but wasn't joined, join it now.
@@ -453,22 +1762,17 @@ impl FIRMClient {
         let error_sender = self.error_sender.clone();
 
         let handle = thread::spawn(move || {
-            let result = stream_mock_log_file_worker(
-                &log_path,
-                realtime,
-                speed,
-                chunk_size,
-                &stop,
-                &mock_sender,
-            );
+            let result = mock_stream::stream_mock_log(&log_path, options, &stop, &mock_sender);
 
             if cancel_on_finish {
                 // Fire-and-forget: we can't wait for ack from this background thread.
-                let _ = command_sender.send(FIRMCommandPacket::build_cancel_command());
+                let _ = command_sender.send(FIRMCommandPacket::build_cancel_command().to_bytes());
             }
 
             if let Err(ref e) = result {
-                let _ = error_sender.send(e.to_string());
+                error_sender.report(ErrorEvent::now(FIRMClientError::MockStream {
+                    message: e.to_string(),
+                }));
             }
             result
         });
@@ -494,7 +1798,7 @@ impl FIRMClient {
         if cancel_device {
             let _ = self
                 .command_sender
-                .send(FIRMCommandPacket::build_cancel_command());
+                .send(FIRMCommandPacket::build_cancel_command().to_bytes());
         }
 
         if !block {
@@ -520,11 +1824,14 @@ impl FIRMClient {
 
     /// Sends a cancel command and waits for acknowledgement.
     pub fn cancel(&mut self, timeout: Duration) -> Result<Option<bool>> {
-        self.send_command(FIRMCommandPacket::build_cancel_command())?;
-        self.wait_for_matching_response(timeout, |res| match res {
-            FIRMResponse::Cancel(ok) => Some(*ok),
-            _ => None,
-        })
+        self.send_and_wait(
+            FIRMCommandPacket::build_cancel_command(),
+            timeout,
+            |res| match res {
+                FIRMResponse::Cancel(ok) => Some(*ok),
+                _ => None,
+            },
+        )
     }
 
     /// Sends a reboot command.
@@ -532,30 +1839,192 @@ impl FIRMClient {
         self.send_command(FIRMCommandPacket::build_reboot_command())
     }
 
-    /// Checks for any errors that have occurred in the background thread.
+    /// Reboots the device and reconnects once it's back, e.g. after `set_device_config`.
     ///
-    /// # Returns
+    /// The reader thread is stopped and the reboot command written straight to the port. The
+    /// port is then reopened with the same settings, polling every `RECONNECT_INTERVAL`:
+    /// through the transport's own `Transport::reconnect` where it has one, and otherwise by
+    /// name, falling back to the port with the same USB VID/PID in case the OS renamed it.
+    /// Streaming restarts as soon as it opens.
     ///
-    /// - `Option<String>` - `Some(error_message)` if an error has occurred, otherwise `None`.
-    pub fn check_error(&self) -> Option<String> {
+    /// # Arguments
+    ///
+    /// - `timeout` (`Duration`) - How long to wait for the port to come back and the first
+    ///   packet to arrive, in total.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<()>` - `FIRMClientError::ReconnectTimedOut` if the port never came back, or
+    ///   `FIRMClientError::NoPacketsAfterReconnect` if it reopened but no packet arrived. The
+    ///   client is left running in the second case.
+    pub fn reboot_and_reconnect(&mut self, timeout: Duration) -> Result<()> {
+        if self.is_replay() {
+            return Err(FIRMClientError::NotSupported {
+                operation: "Rebooting",
+                connection: self.connection.kind.as_str(),
+            }
+            .into());
+        }
+        let deadline = Instant::now() + timeout;
+        let port_name = self
+            .connection
+            .address
+            .clone()
+            .unwrap_or_else(|| self.connection.kind.as_str().to_string());
+        // Looked up while the device is still there, since it's gone once it reboots.
+        let usb_ids = match self.connection.kind {
+            ConnectionKind::Serial => serial_port_usb_ids(&port_name),
+            _ => None,
+        };
+
+        self.stop();
+        let mut port = match self.port.take() {
+            Some(port) => port,
+            None => self.reopen_port()?,
+        };
+        let reboot = FIRMCommandPacket::build_reboot_command().to_bytes();
+        port.write_all(&reboot)
+            .and_then(|()| port.flush())
+            .map_err(|e| FIRMClientError::write(&e))?;
+
+        let port = self
+            .wait_for_rebooted_port(port, usb_ids, deadline)
+            .ok_or_else(|| FIRMClientError::ReconnectTimedOut {
+                port: port_name.clone(),
+                timeout,
+            })??;
+
+        let packets_before = self.stats().packets_parsed;
+        self.port = Some(port);
+        self.start()?;
+        while self.stats().packets_parsed == packets_before {
+            if Instant::now() >= deadline {
+                return Err(FIRMClientError::NoPacketsAfterReconnect {
+                    port: port_name,
+                    timeout,
+                }
+                .into());
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        Ok(())
+    }
+
+    /// Polls until the port is open again after a reboot, see `reboot_and_reconnect`.
+    ///
+    /// # Returns
+    ///
+    /// - `Option<Result<Box<dyn Transport>, FIRMClientError>>` - The reopened port, an error if
+    ///   this kind of connection can't be reopened at all, or `None` if `deadline` passed.
+    fn wait_for_rebooted_port(
+        &mut self,
+        port: Box<dyn Transport>,
+        usb_ids: Option<(u16, u16)>,
+        deadline: Instant,
+    ) -> Option<Result<Box<dyn Transport>, FIRMClientError>> {
+        let mut port = Some(port);
+        loop {
+            match port.as_mut().map(|port| port.reconnect()) {
+                Some(Ok(())) => return port.map(Ok),
+                // The old handle is useless without its own reconnect; open a new one instead.
+                Some(Err(e)) if e.kind() == io::ErrorKind::Unsupported => {
+                    port = None;
+                    continue;
+                }
+                Some(Err(_)) => {}
+                None => match self.reopen_port() {
+                    Ok(port) => return Some(Ok(port)),
+                    Err(e @ FIRMClientError::CannotReopen { .. }) => return Some(Err(e)),
+                    Err(_) => {
+                        let renamed = usb_ids.and_then(find_usb_serial_port).filter(|name| {
+                            self.connection.address.as_deref() != Some(name.as_str())
+                        });
+                        if let Some(name) = renamed {
+                            self.connection.address = Some(name);
+                            continue;
+                        }
+                    }
+                },
+            }
+            if Instant::now() >= deadline {
+                return None;
+            }
+            thread::sleep(
+                Self::RECONNECT_INTERVAL.min(deadline.saturating_duration_since(Instant::now())),
+            );
+        }
+    }
+
+    /// Checks for any errors that have occurred in the background thread.
+    ///
+    /// # Returns
+    ///
+    /// - `Option<ErrorEvent>` - The oldest error not yet returned, with when it happened, or `None`.
+    pub fn check_error(&self) -> Option<ErrorEvent> {
         self.error_receiver.try_recv().ok()
     }
 
+    /// Returns every error not yet returned by `check_error` or `drain_errors`, oldest first.
+    pub fn drain_errors(&self) -> Vec<ErrorEvent> {
+        self.error_receiver.try_iter().collect()
+    }
+
+    /// Returns the last `ERROR_HISTORY_LENGTH` errors reported, oldest first, whether or not
+    /// they were already returned by `check_error` or `drain_errors`. Kept after the reader
+    /// thread exits, e.g. to see what led up to a fatal error.
+    pub fn recent_errors(&self) -> Vec<ErrorEvent> {
+        self.error_sender.history()
+    }
+
     /// Returns true if the client is currently running and reading data.
+    ///
+    /// This also checks that the reader thread is still alive, so it goes false if the thread
+    /// panicked. `check_error` then reports `FIRMClientError::ThreadPanicked`. It's false
+    /// before any fatal `ErrorEvent` is reported.
     pub fn is_running(&self) -> bool {
         self.running.load(Ordering::Relaxed)
+            && self
+                .join_handle
+                .as_ref()
+                .is_some_and(|handle| !handle.is_finished())
+    }
+
+    /// Starts the client, waits for the first data packet, and stops the client again.
+    ///
+    /// If the reader thread reported an error while waiting, that error is returned instead of
+    /// the timeout so callers see the real cause (e.g. the device being unplugged).
+    fn read_first_packet(&mut self, timeout: Duration) -> Result<FIRMData> {
+        self.start()?;
+        let result = self.get_data_packets(Some(timeout));
+        let error = self.check_error();
+        self.stop();
+
+        match (result, error) {
+            (Ok(packets), _) if !packets.is_empty() => Ok(packets[0].clone()),
+            (_, Some(err)) => Err(err.into()),
+            _ => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!(
+                    "No data packet received within {:.3}s",
+                    timeout.as_secs_f64()
+                ),
+            )
+            .into()),
+        }
     }
 
     /// Enter mock mode and require an acknowledgement.
     ///
     /// Returns `Ok(())` only if the device explicitly acknowledges mock mode.
     fn start_mock_mode(&mut self, timeout: Duration) -> Result<()> {
-        self.send_command(FIRMCommandPacket::build_mock_command())?;
-
-        match self.wait_for_matching_response(timeout, |res| match res {
-            FIRMResponse::Mock(ok) => Some(*ok),
-            _ => None,
-        })? {
+        match self.send_and_wait(
+            FIRMCommandPacket::build_mock_command(),
+            timeout,
+            |res| match res {
+                FIRMResponse::Mock(ok) => Some(*ok),
+                _ => None,
+            },
+        )? {
             Some(true) => Ok(()),
             Some(false) => Err(anyhow::anyhow!("Device rejected mock mode")),
             None => Err(anyhow::anyhow!(
@@ -583,7 +2052,9 @@ impl FIRMClient {
     ) -> Result<Option<bool>> {
         // Reset magnetometer calibration to a known state before collecting.
         // This avoids using stale calibration while we gather new samples.
-        let zero_offsets: [f32; NUMBER_OF_CALIBRATION_OFFSETS] = [0.0; NUMBER_OF_CALIBRATION_OFFSETS];
+        let zero_offsets: [f32; NUMBER_OF_CALIBRATION_OFFSETS] =
+            [0.0; NUMBER_OF_CALIBRATION_OFFSETS];
+        #[rustfmt::skip]
         let identity_matrix: [f32; NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS] = [
             1.0, 0.0, 0.0,
             0.0, 1.0, 0.0,
@@ -635,18 +2106,322 @@ impl FIRMClient {
 
     /// Sends a high-level command to the device.
     fn send_command(&self, command: FIRMCommandPacket) -> Result<()> {
+        self.send_raw_bytes(&command.to_bytes())
+    }
+
+    /// Frames and sends an arbitrary packet, for experimenting with identifiers this crate
+    /// doesn't have a typed command for yet. The frame is written by the reader thread, so the
+    /// client must be started for it to go out.
+    ///
+    /// # Arguments
+    ///
+    /// - `header` (`PacketHeader`) - The frame header, usually `PacketHeader::Command`.
+    /// - `identifier` (`u16`) - The frame identifier.
+    /// - `payload` (`&[u8]`) - The payload, at most `MAX_COMMAND_PAYLOAD_LENGTH` bytes.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<()>` - `FrameError::PayloadTooLarge` if the firmware couldn't receive the frame.
+    pub fn send_frame(&self, header: PacketHeader, identifier: u16, payload: &[u8]) -> Result<()> {
+        if payload.len() > MAX_COMMAND_PAYLOAD_LENGTH {
+            return Err(FrameError::PayloadTooLarge {
+                max: MAX_COMMAND_PAYLOAD_LENGTH,
+                got: payload.len(),
+            }
+            .into());
+        }
+        let frame = FramedPacket::new(header, identifier, payload.to_vec());
+        self.send_raw_bytes(&frame.to_bytes())
+    }
+
+    /// Sends bytes to the device exactly as given.
+    ///
+    /// This is unchecked: nothing validates framing, CRC or size, and a malformed write can
+    /// leave the firmware's parser out of sync. Prefer `send_frame`.
+    ///
+    /// Every command goes through here, so replay clients reject them all with
+    /// `FIRMClientError::NotSupported` rather than pretending they were sent.
+    pub fn send_raw_bytes(&self, bytes: &[u8]) -> Result<()> {
+        if self.is_replay() {
+            return Err(FIRMClientError::NotSupported {
+                operation: "Sending commands",
+                connection: self.connection.kind.as_str(),
+            }
+            .into());
+        }
         self.command_sender
-            .send(command)
+            .send(bytes.to_vec())
             .map_err(|_| io::Error::other("Command channel closed"))?;
         Ok(())
     }
 
-    fn wait_for_response(&mut self, timeout: Duration) -> Result<Option<FIRMResponse>> {
-        // Prefer already-buffered responses.
-        if let Some(res) = self.response_buffer.pop_front() {
-            return Ok(Some(res));
+    /// Holds the serial line in the break condition for `duration`. Safe to call while
+    /// streaming: the reader thread sends the break between reads.
+    ///
+    /// # Arguments
+    ///
+    /// - `duration` (`Duration`) - How long to hold the break.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<()>` - An error if the transport isn't a serial port or the break failed.
+    pub fn send_break(&mut self, duration: Duration) -> Result<()> {
+        self.link_control(duration, |reply| LinkControl::Break(duration, reply))
+    }
+
+    /// Discards bytes waiting in the OS receive and/or transmit buffers. Purging the receive
+    /// side also drops any partial frame the parser was holding, so the next frame parses
+    /// cleanly. Safe to call while streaming.
+    ///
+    /// # Arguments
+    ///
+    /// - `direction` (`ClearBuffer`) - Which buffers to purge.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<()>` - An error if the transport isn't a serial port or the purge failed.
+    pub fn purge_buffers(&mut self, direction: ClearBuffer) -> Result<()> {
+        self.link_control(Duration::ZERO, |reply| {
+            LinkControl::ClearBuffers(direction, reply)
+        })
+    }
+
+    /// Switches the host side of the serial link to `baud_rate`, e.g. after `set_device_config`
+    /// changed what the device streams, without recreating the client. Safe to call while
+    /// streaming: the reader thread switches between reads, then drops the bytes received at
+    /// the old rate and any partial frame. The new rate is also used when the port is reopened.
+    ///
+    /// # Arguments
+    ///
+    /// - `baud_rate` (`u32`) - The new baud rate.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<()>` - `FIRMClientError::NotSupported` if the transport isn't a serial port
+    ///   (e.g. TCP or a replay), or the error from the port.
+    pub fn set_baud_rate(&mut self, baud_rate: u32) -> Result<()> {
+        if baud_rate == 0 {
+            return Err(anyhow::anyhow!("Baud rate must be > 0"));
+        }
+        self.serial_control("Changing the baud rate", Duration::ZERO, |reply| {
+            LinkControl::SetBaudRate(baud_rate, reply)
+        })?;
+        if self.connection.kind == ConnectionKind::Serial {
+            self.connection.baud_rate = Some(baud_rate);
+        }
+        Ok(())
+    }
+
+    /// Drives the serial port's DTR line. Safe to call while streaming: the reader thread
+    /// changes it between reads.
+    ///
+    /// # Arguments
+    ///
+    /// - `level` (`bool`) - `true` to assert DTR.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<()>` - `FIRMClientError::NotSupported` if the transport isn't a serial port,
+    ///   or the error from the port.
+    pub fn set_dtr(&mut self, level: bool) -> Result<()> {
+        self.serial_control("Setting DTR", Duration::ZERO, |reply| {
+            LinkControl::SetDtr(level, reply)
+        })
+    }
+
+    /// Drives the serial port's RTS line. Safe to call while streaming: the reader thread
+    /// changes it between reads.
+    ///
+    /// # Arguments
+    ///
+    /// - `level` (`bool`) - `true` to assert RTS.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<()>` - `FIRMClientError::NotSupported` if the transport isn't a serial port,
+    ///   or the error from the port.
+    pub fn set_rts(&mut self, level: bool) -> Result<()> {
+        self.serial_control("Setting RTS", Duration::ZERO, |reply| {
+            LinkControl::SetRts(level, reply)
+        })
+    }
+
+    /// Toggles DTR through `pattern`, e.g. to reset a board into its bootloader. Safe to call
+    /// while streaming: the reader thread stops reading for the length of the pattern and
+    /// picks up where it left off afterwards. DTR is left at the last level in the pattern.
+    ///
+    /// # Arguments
+    ///
+    /// - `pattern` (`&[(bool, Duration)]`) - DTR levels to drive in turn, each held for its
+    ///   duration.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<()>` - `FIRMClientError::NotSupported` if the transport isn't a serial port,
+    ///   or the error from the port, which ends the pattern early.
+    pub fn pulse_reset(&mut self, pattern: &[(bool, Duration)]) -> Result<()> {
+        let total = pattern.iter().map(|(_, hold)| *hold).sum();
+        let pattern = pattern.to_vec();
+        self.serial_control("Pulsing DTR", total, |reply| {
+            LinkControl::PulseDtr(pattern, reply)
+        })
+    }
+
+    /// Like `link_control`, but reports transports without serial lines as
+    /// `FIRMClientError::NotSupported`.
+    fn serial_control(
+        &mut self,
+        operation: &'static str,
+        duration: Duration,
+        request: impl FnOnce(Sender<io::Result<()>>) -> LinkControl,
+    ) -> Result<()> {
+        let connection = self.connection.kind.as_str();
+        self.link_control(duration, request)
+            .map_err(|e| match e.downcast_ref::<io::Error>() {
+                Some(io_error) if io_error.kind() == io::ErrorKind::Unsupported => {
+                    FIRMClientError::NotSupported {
+                        operation,
+                        connection,
+                    }
+                    .into()
+                }
+                _ => e,
+            })
+    }
+
+    /// How long `recover_link` holds the serial break.
+    pub const RECOVERY_BREAK_DURATION: Duration = Duration::from_millis(100);
+
+    /// Tries to get a wedged device streaming again without power cycling it. Each step is
+    /// tried in turn, returning as soon as a valid frame arrives within `step_timeout` of it:
+    /// purge the OS buffers, send a serial break (`RECOVERY_BREAK_DURATION`), then send a
+    /// Cancel command. Steps the transport doesn't support are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// - `step_timeout` (`Duration`) - How long to wait for a valid frame after each step.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<Option<RecoveryStep>>` - The step after which frames arrived again, `None` if
+    ///   none of them helped, or an error if the client isn't running or a step failed.
+    pub fn recover_link(&mut self, step_timeout: Duration) -> Result<Option<RecoveryStep>> {
+        if !self.is_running() {
+            return Err(anyhow::anyhow!("Client is not running"));
+        }
+
+        let steps = [
+            RecoveryStep::PurgeBuffers,
+            RecoveryStep::Break,
+            RecoveryStep::Cancel,
+        ];
+        for step in steps {
+            let result = match step {
+                RecoveryStep::PurgeBuffers => self.purge_buffers(ClearBuffer::All),
+                RecoveryStep::Break => self.send_break(Self::RECOVERY_BREAK_DURATION),
+                RecoveryStep::Cancel => {
+                    self.send_command(FIRMCommandPacket::build_cancel_command())
+                }
+            };
+            match result {
+                Ok(()) => {}
+                Err(e)
+                    if e.downcast_ref::<io::Error>()
+                        .is_some_and(|e| e.kind() == io::ErrorKind::Unsupported) =>
+                {
+                    continue;
+                }
+                Err(e) => return Err(e),
+            }
+
+            if self.wait_for_valid_frame(step_timeout) {
+                return Ok(Some(step));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Waits until the reader thread parses a data packet or response, counting from now.
+    fn wait_for_valid_frame(&self, timeout: Duration) -> bool {
+        let frames_parsed = |client: &Self| {
+            let stats = client.stats();
+            stats.packets_parsed + stats.responses_parsed
+        };
+        let before = frames_parsed(self);
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if frames_parsed(self) > before {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        frames_parsed(self) > before
+    }
+
+    /// Runs a line control request, on the reader thread if it's running or directly on the
+    /// transport otherwise.
+    fn link_control(
+        &mut self,
+        duration: Duration,
+        request: impl FnOnce(Sender<io::Result<()>>) -> LinkControl,
+    ) -> Result<()> {
+        let (reply, result) = channel();
+        let control = request(reply);
+
+        if let Some(port) = self.port.as_mut() {
+            let mut parser = SerialParser::new();
+            apply_link_control(port, &mut parser, &self.link_counters, control);
+        } else if !self.is_running() || self.control_sender.send(control).is_err() {
+            return Err(anyhow::anyhow!("Client is not running"));
         }
 
+        // The reader thread might be in the middle of a read, so allow for its read timeout.
+        match result.recv_timeout(duration + Duration::from_secs(1)) {
+            Ok(result) => Ok(result?),
+            Err(_) => Err(anyhow::anyhow!("Reader thread did not handle the request")),
+        }
+    }
+
+    /// Enables or disables the raw frame tap. While enabled, CRC-valid frames that can't be
+    /// decoded as a data packet or response (e.g. unknown identifiers) are kept for
+    /// `get_raw_frames`; while disabled (the default) they are discarded. At most
+    /// `MAX_TAPPED_RAW_FRAMES` are kept, dropping the oldest first and counting them in
+    /// `stats().raw_frames_dropped`.
+    pub fn set_raw_frame_tap(&mut self, enabled: bool) {
+        self.raw_frame_tap.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Enables or disables resync reporting. While enabled, each time the stream loses sync
+    /// (a bad CRC, an impossible length or bytes between frames) the reader thread reports a
+    /// non-fatal `FIRMClientError::LostSync` through `check_error`, with the offset into the
+    /// stream where it happened. Disabled by default, as a noisy link reports a lot of them.
+    pub fn set_resync_reporting(&mut self, enabled: bool) {
+        self.resync_reporting.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Enables or disables parse error reporting. While enabled, the reader thread reports a
+    /// non-fatal `FIRMClientError::Parse` through `check_error` for every frame it rejects,
+    /// e.g. for a bad CRC, with the frame's offset into the stream. Disabled by default.
+    pub fn collect_parse_errors(&mut self, enabled: bool) {
+        self.parse_error_reporting.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Retrieves frames captured by the raw frame tap, optionally blocking until at least one
+    /// is available.
+    ///
+    /// # Arguments
+    ///
+    /// - `timeout` (`Option<Duration>`) - If `Some(duration)`, the method will block for up to `duration` waiting for a frame.
+    pub fn get_raw_frames(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<FramedPacket>, RecvTimeoutError> {
+        self.raw_frames.take(timeout)
+    }
+
+    /// Waits for the next response from the reader thread. Buffered responses have already
+    /// been searched by the caller, so handing one back would just spin until the timeout.
+    fn wait_for_response(&mut self, timeout: Duration) -> Result<Option<FIRMResponse>> {
         match self.response_receiver.recv_timeout(timeout) {
             Ok(res) => Ok(Some(res)),
             Err(RecvTimeoutError::Timeout) => Ok(None),
@@ -656,6 +2431,40 @@ impl FIRMClient {
         }
     }
 
+    /// Sends `command` and waits for the response `matcher` picks out, see
+    /// `wait_for_matching_response`.
+    ///
+    /// If this command was sent before, buffered responses of its kind are discarded first:
+    /// they arrived after the previous one was answered or gave up, so they're duplicates or
+    /// late replies to it rather than answers to this one. A late reply that only arrives
+    /// after this command went out can't be told apart from the real answer.
+    fn send_and_wait<T>(
+        &mut self,
+        command: FIRMCommandPacket,
+        timeout: Duration,
+        mut matcher: impl FnMut(&FIRMResponse) -> Option<T>,
+    ) -> Result<Option<T>> {
+        let kind = command.command_type();
+        if self.commands_sent.contains(&kind) {
+            while let Ok(res) = self.response_receiver.try_recv() {
+                self.buffer_response(res);
+            }
+            self.response_buffer.retain(|res| matcher(res).is_none());
+        } else {
+            self.commands_sent.push(kind);
+        }
+        self.send_command(command)?;
+        self.wait_for_matching_response(timeout, matcher)
+    }
+
+    /// Buffers a response for later matching, dropping the oldest once the buffer is full.
+    fn buffer_response(&mut self, response: FIRMResponse) {
+        if self.response_buffer.len() >= Self::MAX_BUFFERED_RESPONSES {
+            self.response_buffer.pop_front();
+        }
+        self.response_buffer.push_back(response);
+    }
+
     /// Wait for a response matching `matcher` up to `timeout`.
     ///
     /// Looks through buffered responses first and keeps non-matching responses. It makes sure
@@ -667,7 +2476,7 @@ impl FIRMClient {
     ) -> Result<Option<T>> {
         // Pull any immediately-available responses into our buffer so we can search them first.
         while let Ok(res) = self.response_receiver.try_recv() {
-            self.response_buffer.push_back(res);
+            self.buffer_response(res);
         }
 
         let mut try_get_response = |response_buffer: &mut VecDeque<FIRMResponse>| {
@@ -708,7 +2517,7 @@ impl FIRMClient {
             };
 
             // Keep the response in the buffer so non-matching responses are kept for other calls
-            self.response_buffer.push_back(next);
+            self.buffer_response(next);
 
             // Re-scan the buffer for a match now that we have new data.
             if let Some(result) = try_get_response(&mut self.response_buffer) {
@@ -787,195 +2596,489 @@ impl FIRMClient {
     }
 }
 
-fn sleep_interruptible(total: Duration, stop: &AtomicBool) {
-    let step = Duration::from_millis(10);
-    let mut remaining = total;
-    while remaining > Duration::ZERO && !stop.load(Ordering::Relaxed) {
-        let s = remaining.min(step);
-        thread::sleep(s);
-        remaining = remaining.saturating_sub(s);
-    }
+/// Runs every active subscriber on `packet`, dropping cancelled ones.
+///
+/// Panics are caught so a bad callback can't take down the reader thread; the panicking
+/// subscriber is removed and the panic message is reported on the error channel.
+fn notify_subscribers<T>(
+    subscribers: &Mutex<Vec<Subscriber<T>>>,
+    item: &T,
+    kind: &str,
+    error_sender: &ErrorReporter,
+) {
+    let mut subscribers = subscribers
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    subscribers.retain_mut(|subscriber| {
+        if subscriber.cancelled.load(Ordering::Relaxed) {
+            return false;
+        }
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| (subscriber.callback)(item)));
+        if let Err(payload) = result {
+            error_sender.report(ErrorEvent::now(FIRMClientError::CallbackPanicked {
+                callback: kind.to_string(),
+                message: panic_message(payload.as_ref()),
+            }));
+            subscriber.cancelled.store(true, Ordering::Relaxed);
+            return false;
+        }
+        true
+    });
+}
+
+/// Returns the message a panic was raised with.
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
 }
 
-fn stream_mock_log_file_worker(
-    log_path: &str,
-    realtime: bool,
-    speed: f64,
-    chunk_size: usize,
-    stop: &AtomicBool,
-    mock_sender: &Sender<FIRMLogPacket>,
-) -> Result<usize> {
-    if speed <= 0.0 {
-        return Err(anyhow::anyhow!("speed must be > 0"));
+/// Appends bytes read from the device to the raw recording, if one is running. A failed write
+/// is reported and ends the recording rather than the reader thread.
+fn record_raw_bytes(
+    raw_recorder: &Mutex<Option<BufWriter<File>>>,
+    bytes: &[u8],
+    error_sender: &ErrorReporter,
+) {
+    let mut recorder = raw_recorder.lock().unwrap();
+    if let Some(writer) = recorder.as_mut()
+        && let Err(e) = writer.write_all(bytes)
+    {
+        error_sender.report(ErrorEvent::now(FIRMClientError::Recording {
+            message: format!("recording stopped: {e}"),
+        }));
+        *recorder = None;
     }
+}
 
-    const PRELOAD_COUNT: usize = 75;
-    const BATCH_SIZE: usize = 10;
+/// Reports a connection error and reconnects if reconnecting is enabled. If the reader thread
+/// has to stop instead, the client is marked stopped and the error reported again as fatal,
+/// unless it was `stop()` that ended the reconnect attempts.
+///
+/// # Returns
+///
+/// - `bool` - `true` if the port reconnected and the reader thread should carry on.
+fn recover_connection(
+    error: FIRMClientError,
+    port: &mut Box<dyn Transport>,
+    parser: &mut SerialParser,
+    link_counters: &Mutex<LinkCounters>,
+    running: &AtomicBool,
+    reconnect: &AtomicBool,
+    error_sender: &ErrorReporter,
+) -> bool {
+    let reconnecting = reconnect.load(Ordering::Relaxed);
+    if reconnecting {
+        error_sender.report(ErrorEvent::now(error.clone()));
+        if try_reconnect(port, parser, link_counters, running, reconnect) {
+            return true;
+        }
+    }
+    let stopped_by_user = !running.swap(false, Ordering::Relaxed);
+    if !reconnecting || !stopped_by_user {
+        error_sender.report(ErrorEvent::fatal(error));
+    }
+    false
+}
 
-    let mut file = File::open(log_path)?;
-    let mut header = vec![0u8; HEADER_TOTAL_SIZE];
-    file.read_exact(&mut header)?;
+/// Reconnects `port` after a connection error if reconnecting is enabled, retrying until it
+/// succeeds or the client is stopped. The parser is reset so a frame cut off by the drop isn't
+/// glued onto the new stream.
+///
+/// # Returns
+///
+/// - `bool` - `true` if the port reconnected and the reader thread should carry on.
+fn try_reconnect(
+    port: &mut Box<dyn Transport>,
+    parser: &mut SerialParser,
+    link_counters: &Mutex<LinkCounters>,
+    running: &AtomicBool,
+    reconnect: &AtomicBool,
+) -> bool {
+    while running.load(Ordering::Relaxed) && reconnect.load(Ordering::Relaxed) {
+        match port.reconnect() {
+            Ok(()) => {
+                parser.reset();
+                link_counters.lock().unwrap().start_new_parser();
+                return true;
+            }
+            Err(e) if e.kind() == io::ErrorKind::Unsupported => return false,
+            Err(_) => thread::sleep(FIRMClient::RECONNECT_INTERVAL),
+        }
+    }
+    false
+}
 
-    // Send the log header to the device, framed as a mock packet.
-    let header_packet = FIRMLogPacket::new(FIRMLogPacketType::HeaderPacket, header.clone());
-    mock_sender
-        .send(header_packet)
-        .map_err(|_| io::Error::other("Mock channel closed"))?;
+/// Returns the USB vendor and product ID of serial port `port_name`, or `None` if it isn't a
+/// USB device or isn't connected.
+fn serial_port_usb_ids(port_name: &str) -> Option<(u16, u16)> {
+    serialport::available_ports()
+        .ok()?
+        .into_iter()
+        .find(|port| port.port_name == port_name)
+        .and_then(|port| match port.port_type {
+            serialport::SerialPortType::UsbPort(info) => Some((info.vid, info.pid)),
+            _ => None,
+        })
+}
 
-    let mut parser = LogParser::new();
-    parser.read_header(&header);
+/// Returns the name of the first connected serial port with the USB vendor and product ID
+/// `(vid, pid)`.
+fn find_usb_serial_port((vid, pid): (u16, u16)) -> Option<String> {
+    serialport::available_ports()
+        .ok()?
+        .into_iter()
+        .find(|port| {
+            matches!(&port.port_type, serialport::SerialPortType::UsbPort(info)
+                if info.vid == vid && info.pid == pid)
+        })
+        .map(|port| port.port_name)
+}
 
-    // After we send the header we pause for a short time to let the device process it.
-    sleep_interruptible(HEADER_PARSE_DELAY, stop);
+/// Opens a FIRM serial port the way `FIRMClient::new` expects it.
+fn open_serial_port(
+    port_name: &str,
+    baud_rate: u32,
+    timeout: Duration,
+    framing: &SerialFraming,
+) -> Result<Box<dyn SerialPort>, FIRMClientError> {
+    let open_error = |e: serialport::Error| {
+        // Keeps the error kind, e.g. a missing device becomes `NotFound`.
+        let e = io::Error::from(e);
+        FIRMClientError::SerialOpen {
+            port: port_name.to_string(),
+            kind: e.kind(),
+            message: e.to_string(),
+        }
+    };
 
-    let mut buf = vec![0u8; chunk_size];
-    let mut packets_sent = 0usize;
+    // Sets up the serial port
+    let mut port: Box<dyn SerialPort> =
+        builder::serial_port_builder(port_name, baud_rate, timeout, framing)
+            .open()
+            .map_err(open_error)?;
 
-    // Staging queue of parsed packets + their requested delay.
-    let mut staged: std::collections::VecDeque<(FIRMLogPacket, f64)> =
-        std::collections::VecDeque::new();
+    // Sets DTR to true, this is important for Linux/Windows to both act the same
+    port.write_data_terminal_ready(true).map_err(open_error)?;
+    // Give the device a moment to settle after opening the port
+    std::thread::sleep(Duration::from_millis(50));
+    Ok(port)
+}
 
-    // Read+parse enough bytes to stage more packets.
-    // Returns Ok(true) if it read more bytes, Ok(false) if file is exhausted.
-    let refill = |file: &mut File,
-                  buf: &mut [u8],
-                  parser: &mut LogParser,
-                  staged: &mut std::collections::VecDeque<(FIRMLogPacket, f64)>|
-     -> Result<bool> {
-        let n = file.read(buf)?;
-        if n == 0 {
-            return Ok(false);
+/// Connects to a TCP bridge the way `FIRMClient::connect_tcp` expects it.
+fn connect_tcp_transport(addr: &str, timeout: f64) -> Result<TcpTransport, FIRMClientError> {
+    TcpTransport::connect(addr, Duration::from_secs_f64(timeout.max(0.0))).map_err(|e| {
+        FIRMClientError::Connect {
+            address: addr.to_string(),
+            kind: e.kind(),
+            message: e.to_string(),
         }
+    })
+}
 
-        parser.parse_bytes(&buf[..n]);
-        while let Some((packet, delay_seconds)) = parser.get_packet_and_time_delay() {
-            staged.push_back((packet, delay_seconds));
+/// Carries out a `LinkControl` request on `port` and sends back the result.
+fn apply_link_control(
+    port: &mut Box<dyn Transport>,
+    parser: &mut SerialParser,
+    link_counters: &Mutex<LinkCounters>,
+    control: LinkControl,
+) {
+    match control {
+        LinkControl::Break(duration, reply) => {
+            let _ = reply.send(port.send_break(duration));
         }
-
-        Ok(true)
-    };
-
-    // -------------------------
-    // 1) PRELOAD: send 75 ASAP
-    // -------------------------
-    let mut sent_preload = 0usize;
-    while sent_preload < PRELOAD_COUNT && !stop.load(Ordering::Relaxed) {
-        // Ensure we have at least one staged packet.
-        while staged.is_empty() {
-            let read_more = refill(&mut file, &mut buf, &mut parser, &mut staged)?;
-            if !read_more {
-                // File ended; drain anything remaining in parser.
-                while let Some((packet, delay_seconds)) = parser.get_packet_and_time_delay() {
-                    staged.push_back((packet, delay_seconds));
-                }
-                break;
+        LinkControl::ClearBuffers(direction, reply) => {
+            let result = port.clear_buffers(direction);
+            if result.is_ok() && matches!(direction, ClearBuffer::Input | ClearBuffer::All) {
+                // Whatever the parser holds belongs to the bytes that were just thrown away.
+                parser.reset();
+                link_counters.lock().unwrap().start_new_parser();
             }
+            let _ = reply.send(result);
         }
-
-        let Some((packet, _delay_seconds)) = staged.pop_front() else {
-            break; // no more packets available
-        };
-
-        // Send immediately (no pacing / no sleeping).
-        mock_sender
-            .send(packet)
-            .map_err(|_| io::Error::other("Mock channel closed"))?;
-        packets_sent += 1;
-        sent_preload += 1;
-    }
-
-    // After the burst, reset pacing so realtime timing starts “from here”.
-    let stream_start = Instant::now();
-    let mut total_delay_seconds = 0.0f64;
-
-    // -----------------------------------------
-    // 2) MAIN: send in batches of 10 packets
-    // -----------------------------------------
-    loop {
-        if stop.load(Ordering::Relaxed) {
-            break;
-        }
-
-        // Make sure we can form a batch (or hit EOF).
-        while staged.len() < BATCH_SIZE {
-            let read_more = refill(&mut file, &mut buf, &mut parser, &mut staged)?;
-            if !read_more {
-                // File ended; drain any remaining packets parser can produce.
-                while let Some((packet, delay_seconds)) = parser.get_packet_and_time_delay() {
-                    staged.push_back((packet, delay_seconds));
+        LinkControl::SetBaudRate(baud_rate, reply) => {
+            // Anything already received was sent at the old rate, so it's thrown away along
+            // with the partial frame the parser holds.
+            let result = port.set_baud_rate(baud_rate).and_then(|()| {
+                match port.clear_buffers(ClearBuffer::Input) {
+                    Err(e) if e.kind() == io::ErrorKind::Unsupported => Ok(()),
+                    result => result,
                 }
-                break;
+            });
+            if result.is_ok() {
+                parser.reset();
+                link_counters.lock().unwrap().start_new_parser();
             }
+            let _ = reply.send(result);
         }
-
-        if staged.is_empty() {
-            break;
+        LinkControl::SetDtr(level, reply) => {
+            let _ = reply.send(port.set_dtr(level));
         }
-
-        // Pop up to BATCH_SIZE packets.
-        let mut batch_delay = 0.0f64;
-        let mut batch = Vec::with_capacity(BATCH_SIZE);
-        for _ in 0..BATCH_SIZE {
-            if let Some((packet, delay_seconds)) = staged.pop_front() {
-                batch_delay += delay_seconds;
-                batch.push(packet);
-            } else {
-                break;
-            }
+        LinkControl::SetRts(level, reply) => {
+            let _ = reply.send(port.set_rts(level));
         }
-
-        // Send the whole batch (still individual send() calls, but no per-packet sleep).
-        for packet in batch {
-            if stop.load(Ordering::Relaxed) {
-                break;
-            }
-            mock_sender
-                .send(packet)
-                .map_err(|_| io::Error::other("Mock channel closed"))?;
-            packets_sent += 1;
+        LinkControl::PulseDtr(pattern, reply) => {
+            let result = pattern.into_iter().try_for_each(|(level, hold)| {
+                port.set_dtr(level)?;
+                thread::sleep(hold);
+                Ok(())
+            });
+            let _ = reply.send(result);
         }
+    }
+}
+
+/// Most `LostSync` events the parser keeps between reads; older ones in the same read are
+/// dropped.
+const RESYNC_LOG_LENGTH: usize = 64;
+
+/// How often the reader thread evaluates alarm rules.
+const ALARM_CHECK_INTERVAL: Duration = Duration::from_millis(100);
 
-        // Sleep once per batch to approximate original pacing.
-        if realtime && batch_delay > 0.0 {
-            total_delay_seconds += batch_delay;
+/// What the reader thread needs to evaluate alarm rules and deliver their events.
+struct AlarmContext {
+    monitor: Arc<Mutex<AlarmMonitor>>,
+    subscribers: Arc<Mutex<Vec<Subscriber<AlarmEvent>>>>,
+    sender: Sender<AlarmEvent>,
+    aged_out_packets: Arc<AtomicU64>,
+    packet_depth: Arc<QueueDepth>,
+}
 
-            let stream_elapsed = stream_start.elapsed().as_secs_f64();
-            if stream_elapsed <= total_delay_seconds / speed {
-                sleep_interruptible(Duration::from_secs_f64(batch_delay / speed), stop);
+impl AlarmContext {
+    /// Evaluates the alarm rules against the current link stats and delivers any events to
+    /// `on_alarm` callbacks and `get_alarm_events`.
+    fn evaluate(&self, link_counters: &Mutex<LinkCounters>, error_sender: &ErrorReporter) {
+        let events = {
+            let mut monitor = self.monitor.lock().unwrap();
+            if monitor.is_empty() {
+                return;
             }
-        }
+            let now = Instant::now();
+            let stats = link_counters.lock().unwrap().snapshot(
+                now,
+                self.aged_out_packets.load(Ordering::Relaxed),
+                &self.packet_depth,
+            );
+            monitor.evaluate(now, &stats)
+        };
 
-        // If parser knows it hit EOF and nothing is staged, we’re done.
-        if parser.eof_reached() && staged.is_empty() {
-            break;
+        for event in events {
+            notify_subscribers(&self.subscribers, &event, "Alarm subscriber", error_sender);
+            let _ = self.sender.send(event);
         }
     }
+}
 
-    Ok(packets_sent)
+/// Sends a copy of `packet` to every receiver created by `add_receiver`, dropping the ones
+/// whose receiving end has been dropped.
+fn broadcast_packet(senders: &Mutex<Vec<Sender<FIRMData>>>, packet: &FIRMData) {
+    senders
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .retain(|sender| sender.send(packet.clone()).is_ok());
 }
 
-/// Ensures that the client is properly stopped when dropped, i.e. .stop() is called.
-impl Drop for FIRMClient {
-    fn drop(&mut self) {
-        self.stop();
-    }
+/// Forgets the newest timestamp seen, so `is_aged_out` measures from the next packet's.
+fn reset_watermark(newest_timestamp_bits: &AtomicU64) {
+    newest_timestamp_bits.store(f64::NEG_INFINITY.to_bits(), Ordering::Relaxed);
 }
 
-#[cfg(test)]
-mod tests {
-    use firm_core::{
-        constants::{
-            command::{
-                DEVICE_ID_LENGTH, DEVICE_NAME_LENGTH, FIRMCommand, FIRMWARE_VERSION_LENGTH,
-                FREQUENCY_LENGTH,
-            },
-            packet::PacketHeader,
-        },
-        firm_packets::FIRMResponsePacket,
-        framed_packet::FramedPacket,
+/// Returns true (and counts it in `aged_out_packets`) if `packet` is more than `max_age_seconds`
+/// older than the newest packet seen.
+fn is_aged_out(
+    packet: &FIRMData,
+    max_age_seconds: Option<f64>,
+    newest_timestamp_bits: &AtomicU64,
+    aged_out_packets: &AtomicU64,
+) -> bool {
+    let Some(max_age) = max_age_seconds else {
+        return false;
     };
-
+    let newest = f64::from_bits(newest_timestamp_bits.load(Ordering::Relaxed));
+    if newest - packet.timestamp_seconds > max_age {
+        aged_out_packets.fetch_add(1, Ordering::Relaxed);
+        return true;
+    }
+    false
+}
+
+/// Where a `PacketIter` takes its packets from: a client, or the `PacketStream` half of one.
+trait PacketSource {
+    fn packet_receiver(&self) -> &PacketReceiver;
+    fn age_out(&self, packet: &FIRMData) -> bool;
+    fn is_running(&self) -> bool;
+}
+
+impl PacketSource for FIRMClient {
+    fn packet_receiver(&self) -> &PacketReceiver {
+        &self.packet_receiver
+    }
+
+    fn age_out(&self, packet: &FIRMData) -> bool {
+        FIRMClient::age_out(self, packet)
+    }
+
+    fn is_running(&self) -> bool {
+        FIRMClient::is_running(self)
+    }
+}
+
+/// Blocking iterator over data packets, created by `FIRMClient::iter_packets` or
+/// `PacketStream::iter_packets`.
+pub struct PacketIter<'a> {
+    source: &'a dyn PacketSource,
+    idle_timeout: Option<Duration>,
+    finished: bool,
+}
+
+impl PacketIter<'_> {
+    /// How often the iterator wakes up to check whether the client is still running.
+    const POLL_INTERVAL: Duration = Duration::from_millis(50);
+}
+
+impl Iterator for PacketIter<'_> {
+    type Item = FIRMData;
+
+    fn next(&mut self) -> Option<FIRMData> {
+        if self.finished {
+            return None;
+        }
+
+        let deadline = self.idle_timeout.map(|timeout| Instant::now() + timeout);
+        let receiver = self.source.packet_receiver();
+
+        let packet = loop {
+            let wait = match deadline {
+                Some(deadline) => deadline
+                    .saturating_duration_since(Instant::now())
+                    .min(Self::POLL_INTERVAL),
+                None => Self::POLL_INTERVAL,
+            };
+
+            match receiver.recv_timeout(wait) {
+                Ok((packet, _)) if self.source.age_out(&packet) => {}
+                Ok((packet, _)) => break Some(packet),
+                Err(RecvTimeoutError::Timeout) => {
+                    // Once the reader thread is gone, hand out whatever it managed to send
+                    // and then end the iteration.
+                    if !self.source.is_running() {
+                        break receiver.try_recv().ok().map(|(packet, _)| packet);
+                    }
+                    if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                        break None;
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => break None,
+            }
+        };
+
+        self.finished = packet.is_none();
+        packet
+    }
+}
+
+impl std::iter::FusedIterator for PacketIter<'_> {}
+
+/// Opens a FIRM device, waits for the first valid data packet, and closes the port again.
+///
+/// This is meant for quick scripts that want one fresh packet without managing `start()` and
+/// `stop()` themselves. The packet goes through the same background reader and parser as a
+/// normal `FIRMClient`.
+///
+/// # Arguments
+///
+/// - `port_name` (`Option<&str>`) - The serial port to open, or `None` to use the only FIRM
+///   port detected, or the only serial port at all if none look like FIRM. It's an error to
+///   pass `None` when there's more than one candidate, so a script never reads whichever
+///   device happens to be enumerated first.
+/// - `baud_rate` (`u32`) - The baud rate for the serial connection. Commonly 2,000,000 for FIRM devices.
+/// - `timeout` (`Duration`) - How long to wait for a packet before giving up.
+///
+/// # Returns
+///
+/// - `Result<FIRMData>` - The first packet received, an `io::ErrorKind::TimedOut` error if
+///   none arrived in time, or an `io::ErrorKind::InvalidInput` error naming the candidates if
+///   `port_name` is `None` and there's more than one.
+pub fn read_one_packet(
+    port_name: Option<&str>,
+    baud_rate: u32,
+    timeout: Duration,
+) -> Result<FIRMData> {
+    let port_name = match port_name {
+        Some(name) => name.to_string(),
+        None => {
+            let firm_ports = list_firm_ports()
+                .into_iter()
+                .filter(|port| port.is_firm)
+                .map(|port| port.port_name)
+                .collect();
+            let all_ports = serialport::available_ports()
+                .map_err(io::Error::other)?
+                .into_iter()
+                .map(|port| port.port_name)
+                .collect();
+            choose_only_port(firm_ports, all_ports)?
+        }
+    };
+
+    let mut client = FIRMClient::new(&port_name, baud_rate, 0.1)?;
+    client.read_first_packet(timeout)
+}
+
+/// Picks the port `read_one_packet` opens when it isn't given one: the only FIRM port, else
+/// the only serial port.
+fn choose_only_port(firm_ports: Vec<String>, all_ports: Vec<String>) -> io::Result<String> {
+    let candidates = if firm_ports.is_empty() {
+        all_ports
+    } else {
+        firm_ports
+    };
+    match <[String; 1]>::try_from(candidates) {
+        Ok([port]) => Ok(port),
+        Err(candidates) if candidates.is_empty() => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "No serial ports detected",
+        )),
+        Err(candidates) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Found several serial ports ({}); pass the one to read from",
+                candidates.join(", ")
+            ),
+        )),
+    }
+}
+
+/// Ensures that the client is properly stopped when dropped, i.e. .stop() is called.
+impl Drop for FIRMClient {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use firm_core::{
+        constants::{
+            command::{
+                DEVICE_ID_LENGTH, DEVICE_NAME_LENGTH, FIRMCommand, FIRMWARE_VERSION_LENGTH,
+                FREQUENCY_LENGTH, MAX_FREQUENCY_HZ,
+            },
+            packet::{HEADER_SIZE, IDENTIFIER_SIZE, LENGTH_SIZE, PacketHeader},
+        },
+        corpus::FrameOutcome,
+        data_parser::ResyncReason,
+        error_codes::{self, ErrorCoded},
+        firm_packets::{FIRMResponsePacket, ResponseViolation},
+        framed_packet::FramedPacket,
+    };
+
     use super::*;
+    use crate::simulator::{FlightProfileGenerator, build_mock_log};
 
     fn str_to_bytes<const N: usize>(string: &str) -> [u8; N] {
         let mut out = [0u8; N];
@@ -997,16 +3100,186 @@ mod tests {
         let (mut client, _device) = FIRMClient::new_mock(0.01);
 
         assert!(!client.is_running());
-        client.start();
+        client.start().unwrap();
         assert!(client.is_running());
         client.stop();
         assert!(!client.is_running());
     }
 
+    /// A transport whose reads block until `release` is called, like a driver that ignores
+    /// the read timeout.
+    struct BlockingTransport {
+        released: Arc<(Mutex<bool>, std::sync::Condvar)>,
+        /// What the blocked read returns once released, instead of timing out.
+        bytes: Vec<u8>,
+    }
+
+    impl BlockingTransport {
+        fn new() -> (Self, Arc<(Mutex<bool>, std::sync::Condvar)>) {
+            Self::with_bytes(Vec::new())
+        }
+
+        fn with_bytes(bytes: Vec<u8>) -> (Self, Arc<(Mutex<bool>, std::sync::Condvar)>) {
+            let released = Arc::new((Mutex::new(false), std::sync::Condvar::new()));
+            let transport = Self {
+                released: released.clone(),
+                bytes,
+            };
+            (transport, released)
+        }
+    }
+
+    fn release(released: &(Mutex<bool>, std::sync::Condvar)) {
+        *released.0.lock().unwrap() = true;
+        released.1.notify_all();
+    }
+
+    impl Read for BlockingTransport {
+        fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+            let (lock, condvar) = &*self.released;
+            let _released = condvar
+                .wait_while(lock.lock().unwrap(), |released| !*released)
+                .unwrap();
+            if self.bytes.is_empty() {
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            let len = self.bytes.len().min(out.len());
+            out[..len].copy_from_slice(&self.bytes[..len]);
+            self.bytes.drain(..len);
+            Ok(len)
+        }
+    }
+
+    impl Write for BlockingTransport {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            Ok(bytes.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Transport for BlockingTransport {}
+
+    #[test]
+    fn test_stop_with_timeout_detaches_blocked_reader() {
+        let (transport, released) = BlockingTransport::new();
+        let mut client = FIRMClient::from_transport(Box::new(transport));
+        client.start().unwrap();
+        thread::sleep(Duration::from_millis(20));
+
+        let started = Instant::now();
+        let error = client
+            .stop_with_timeout(Duration::from_millis(50))
+            .unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(
+            error,
+            FIRMClientError::StopTimedOut {
+                timeout: Duration::from_millis(50)
+            }
+        );
+        assert!(!client.is_running());
+
+        // A custom transport can't be reopened, so starting again reports why.
+        assert_eq!(
+            client.start(),
+            Err(FIRMClientError::CannotReopen {
+                connection: "custom"
+            })
+        );
+        assert!(!client.is_running());
+        release(&released);
+    }
+
+    #[test]
+    fn test_detached_reader_does_not_run_alongside_the_next_one() {
+        let frame = data_packet_with_timestamp(1.0).to_bytes();
+        let (stuck, stuck_released) = BlockingTransport::with_bytes(frame);
+        let mut client = FIRMClient::from_transport(Box::new(stuck));
+        client.start().unwrap();
+        thread::sleep(Duration::from_millis(20));
+        assert!(client.stop_with_timeout(Duration::from_millis(50)).is_err());
+
+        // Start a new reader on another port, then let the old one's read return a packet.
+        let (next, next_released) = BlockingTransport::new();
+        client.port = Some(Box::new(next));
+        client.start().unwrap();
+        release(&stuck_released);
+        thread::sleep(Duration::from_millis(100));
+        assert!(client.is_running());
+        assert!(client.get_data_packets(None).unwrap().is_empty());
+        assert_eq!(client.stats().packets_parsed, 0);
+
+        release(&next_released);
+        client.stop();
+    }
+
+    /// Panics on the first read, like a parser bug would.
+    struct PanickingTransport;
+
+    impl Read for PanickingTransport {
+        fn read(&mut self, _out: &mut [u8]) -> io::Result<usize> {
+            panic!("parser exploded");
+        }
+    }
+
+    impl Write for PanickingTransport {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            Ok(bytes.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Transport for PanickingTransport {}
+
+    #[test]
+    fn test_reader_thread_panic_is_reported() {
+        let mut client = FIRMClient::from_transport(Box::new(PanickingTransport));
+        client.start().unwrap();
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while client.is_running() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(!client.is_running());
+        assert_eq!(
+            client.check_error().unwrap().kind,
+            FIRMClientError::ThreadPanicked {
+                message: "parser exploded".to_string()
+            }
+        );
+
+        // The port went down with the thread, and a custom transport can't be reopened.
+        client.stop();
+        assert_eq!(
+            client.start(),
+            Err(FIRMClientError::CannotReopen {
+                connection: "custom"
+            })
+        );
+        assert!(!client.is_running());
+    }
+
+    #[test]
+    fn test_stop_with_timeout_joins_responsive_reader() {
+        let (mut client, _device) = FIRMClient::new_mock(0.01);
+        client.start().unwrap();
+        client
+            .stop_with_timeout(Duration::from_millis(500))
+            .unwrap();
+        // The port was handed back, so the client restarts as usual.
+        client.start().unwrap();
+        assert!(client.is_running());
+    }
+
     #[test]
     fn test_get_data_packet_over_mock_serial() {
         let (mut client, device) = FIRMClient::new_mock(0.01);
-        client.start();
+        client.start().unwrap();
 
         let timestamp_seconds = 1.5f64;
 
@@ -1026,120 +3299,2633 @@ mod tests {
     }
 
     #[test]
-    fn test_get_response_packet_over_mock_serial() {
+    fn test_stats_count_link_errors() {
         let (mut client, device) = FIRMClient::new_mock(0.01);
-        client.start();
-
-        let payload = [1u8];
-
-        let bytes = FramedPacket::new(
-            PacketHeader::Response,
-            FIRMCommand::SetDeviceConfig.to_u16(),
-            payload.to_vec(),
-        )
-        .to_bytes();
-        let response_packet = FIRMResponsePacket::from_bytes(&bytes).unwrap();
+        client.start().unwrap();
 
-        device.inject_framed_packet(response_packet.frame().clone());
+        let mut corrupted = data_packet_with_timestamp(1.0).to_bytes();
+        corrupted[HEADER_SIZE + IDENTIFIER_SIZE + LENGTH_SIZE] ^= 0x01;
+        device.inject_bytes(&[0x00, 0x11]);
+        device.inject_bytes(&corrupted);
+        let mut bad_length = (PacketHeader::Data as u16).to_le_bytes().to_vec();
+        bad_length.extend_from_slice(&[0, 0, 0xFF, 0xFF, 0xFF, 0xFF]);
+        device.inject_bytes(&bad_length);
+        device.inject_framed_packet(data_packet_with_timestamp(2.0));
 
-        let packet = client
-            .get_response_packets(Some(Duration::from_millis(100)))
+        let packets = client
+            .get_data_packets(Some(Duration::from_millis(100)))
             .unwrap();
+        assert_eq!(packets.len(), 1);
+
+        let stats = client.stats();
+        assert_eq!(stats.bytes_read, 2 + 8 + 2 * corrupted.len() as u64);
+        assert_eq!(stats.packets_parsed, 1);
+        assert_eq!(stats.crc_failures, 1);
+        assert_eq!(stats.length_rejects, 1);
+        assert_eq!(stats.resync_events, 1);
+        assert!(stats.bytes_skipped >= 3 + 8);
+        assert!(stats.packet_rate_hz > 0.0);
+        assert!(stats.seconds_since_last_packet.is_some());
+    }
 
-        // Make sure we didn't get any other type of packets
-        assert!(matches!(
-            client.get_data_packets(Some(Duration::from_millis(10))),
-            Err(RecvTimeoutError::Timeout)
-        ));
+    #[test]
+    fn test_resync_reporting_gives_stream_offsets() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        client.start().unwrap();
 
-        // Make sure we got the expected response
-        assert_eq!(packet.len(), payload.len());
-        assert_eq!(packet[0], FIRMResponse::SetDeviceConfig(true));
+        // Before reporting is enabled, noise isn't reported.
+        device.inject_bytes(&[0x00, 0x11]);
+        device.inject_framed_packet(data_packet_with_timestamp(1.0));
+        client
+            .get_data_packets(Some(Duration::from_millis(200)))
+            .unwrap();
+        assert!(client.drain_errors().is_empty());
+
+        client.set_resync_reporting(true);
+        let frame_len = data_packet_with_timestamp(1.0).to_bytes().len() as u64;
+        let mut corrupted = data_packet_with_timestamp(2.0).to_bytes();
+        corrupted[HEADER_SIZE + IDENTIFIER_SIZE + LENGTH_SIZE] ^= 0x01;
+        device.inject_bytes(&corrupted);
+        device.inject_framed_packet(data_packet_with_timestamp(3.0));
+        client
+            .get_data_packets(Some(Duration::from_millis(200)))
+            .unwrap();
 
-        // Make sure we didn't get any extra response packets
-        assert!(matches!(
-            client.get_response_packets(Some(Duration::from_millis(10))),
-            Err(RecvTimeoutError::Timeout)
-        ));
+        let errors = client.drain_errors();
+        assert_eq!(errors.len(), 1);
+        assert!(!errors[0].fatal);
+        assert_eq!(
+            errors[0].kind,
+            FIRMClientError::LostSync {
+                byte_offset: 2 + frame_len,
+                reason: ResyncReason::BadCrc,
+            }
+        );
+        assert_eq!(errors[0].error_code(), error_codes::LOST_SYNC);
     }
 
     #[test]
-    fn test_set_device_config_command() {
+    fn test_parse_errors_are_reported_when_collected() {
         let (mut client, device) = FIRMClient::new_mock(0.01);
-        client.start();
+        client.collect_parse_errors(true);
+        client.start().unwrap();
+
+        let frame_len = data_packet_with_timestamp(1.0).to_bytes().len();
+        let mut corrupted = data_packet_with_timestamp(1.0).to_bytes();
+        let expected = u32::from(u16::from_le_bytes([
+            corrupted[frame_len - 2],
+            corrupted[frame_len - 1],
+        ]));
+        corrupted[frame_len - 1] ^= 0x10;
+        device.inject_framed_packet(data_packet_with_timestamp(0.5));
+        device.inject_bytes(&corrupted);
+        device.inject_framed_packet(data_packet_with_timestamp(2.0));
+        let mut packets = Vec::new();
+        while packets.len() < 2 {
+            packets.extend(
+                client
+                    .get_data_packets(Some(Duration::from_millis(200)))
+                    .unwrap(),
+            );
+        }
 
-        // Prepare the response packet to be injected
-        let response_payload = [1u8]; // Acknowledgement byte
-        let response_packet = FramedPacket::new(
-            PacketHeader::Response,
-            FIRMCommand::SetDeviceConfig.to_u16(),
-            response_payload.to_vec(),
+        let errors = client.drain_errors();
+        assert_eq!(errors.len(), 1);
+        assert!(!errors[0].fatal);
+        assert_eq!(
+            errors[0].kind,
+            FIRMClientError::Parse {
+                byte_offset: frame_len as u64,
+                error: FrameError::BadCrc {
+                    expected,
+                    got: expected ^ 0x1000,
+                },
+            }
         );
-        device.inject_framed_packet(response_packet);
+        assert_eq!(errors[0].error_code(), error_codes::CRC);
+    }
 
-        // Send the set device config command
-        let result = client.set_device_config(
-            "TestDevice".to_string(),
-            100,
-            DeviceProtocol::UART,
-            Duration::from_millis(100),
-        );
+    #[test]
+    fn test_backlog_counts_unconsumed_packets() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        client.start().unwrap();
+        let bytes: Vec<u8> = (0..10_000)
+            .flat_map(|i| data_packet_with_timestamp(i as f64 * 0.001).to_bytes())
+            .collect();
+        device.inject_bytes(&bytes);
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while client.stats().packets_parsed < 10_000 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(client.backlog(), 10_000);
+        assert_eq!(client.max_backlog_seen(), 10_000);
+        let stats = client.stats();
+        assert_eq!((stats.backlog, stats.max_backlog_seen), (10_000, 10_000));
+
+        assert_eq!(client.iter_packets().take(4_000).count(), 4_000);
+        assert_eq!(client.backlog(), 6_000);
+        assert_eq!(client.max_backlog_seen(), 10_000);
+        client.reset_max_backlog_seen();
+        assert_eq!(client.max_backlog_seen(), 6_000);
+
+        assert_eq!(client.get_data_packets(None).unwrap().len(), 6_000);
+        assert_eq!(client.backlog(), 0);
+        assert_eq!(client.max_backlog_seen(), 6_000);
+        client.stop();
+    }
 
-        // Verify the result
-        assert_eq!(result.unwrap(), Some(true));
+    /// Collects the `LinkIdle` errors reported within `duration`.
+    fn link_idle_errors_within(client: &FIRMClient, duration: Duration) -> Vec<Duration> {
+        thread::sleep(duration);
+        std::iter::from_fn(|| client.check_error())
+            .filter_map(|event| match event.kind {
+                FIRMClientError::LinkIdle { since } => Some(since),
+                _ => None,
+            })
+            .collect()
     }
 
     #[test]
-    fn test_get_device_info_command() {
+    fn test_idle_timeout_reports_a_stream_that_stops() {
         let (mut client, device) = FIRMClient::new_mock(0.01);
-        client.start();
+        client.set_idle_timeout(Some(Duration::from_millis(100)));
+        client.start().unwrap();
+        // Quiet before the first packet doesn't count.
+        assert!(link_idle_errors_within(&client, Duration::from_millis(250)).is_empty());
+
+        for i in 0..5 {
+            device.inject_framed_packet(data_packet_with_timestamp(i as f64 * 0.01));
+            thread::sleep(Duration::from_millis(20));
+        }
+        // Then the transport stops producing bytes: reported once per interval, not every read.
+        let reports = link_idle_errors_within(&client, Duration::from_millis(450));
+        assert!((2..=5).contains(&reports.len()), "{reports:?}");
+        assert!(reports.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(reports[0] >= Duration::from_millis(100));
+
+        // Loosening the threshold at runtime quiets it.
+        client.set_idle_timeout(Some(Duration::from_secs(60)));
+        let _ = link_idle_errors_within(&client, Duration::from_millis(50));
+        assert!(link_idle_errors_within(&client, Duration::from_millis(250)).is_empty());
+        client.set_idle_timeout(None);
+    }
 
-        let id = 0x1122334455667788u64;
-        let mut payload = vec![0u8; DEVICE_ID_LENGTH + FIRMWARE_VERSION_LENGTH];
-        payload[0..DEVICE_ID_LENGTH].copy_from_slice(&id.to_le_bytes());
-        let fw_bytes = str_to_bytes::<FIRMWARE_VERSION_LENGTH>("v1.2.3");
-        payload[DEVICE_ID_LENGTH..DEVICE_ID_LENGTH + FIRMWARE_VERSION_LENGTH]
-            .copy_from_slice(&fw_bytes);
+    #[test]
+    fn test_expect_first_packet_within_reports_a_silent_device() {
+        let (mut client, _device) = FIRMClient::new_mock(0.01);
+        client.set_idle_timeout(Some(Duration::from_secs(60)));
+        client.expect_first_packet_within(Some(Duration::from_millis(50)));
+        client.start().unwrap();
+        let reports = link_idle_errors_within(&client, Duration::from_millis(200));
+        assert!(!reports.is_empty());
+        assert!(reports[0] >= Duration::from_millis(50));
+    }
 
-        let response_packet = FramedPacket::new(
+    /// A `GetDeviceConfig` reply with a frequency the device can't run at.
+    fn out_of_range_config_response() -> (FramedPacket, Vec<u8>) {
+        let mut payload = vec![0u8; DEVICE_NAME_LENGTH + FREQUENCY_LENGTH + 1];
+        payload[..4].copy_from_slice(b"FIRM");
+        payload[DEVICE_NAME_LENGTH..DEVICE_NAME_LENGTH + FREQUENCY_LENGTH]
+            .copy_from_slice(&5000u16.to_le_bytes());
+        payload[DEVICE_NAME_LENGTH + FREQUENCY_LENGTH] = 1;
+        let frame = FramedPacket::new(
             PacketHeader::Response,
-            FIRMCommand::GetDeviceInfo.to_u16(),
-            payload,
+            FIRMCommand::GetDeviceConfig as u16,
+            payload.clone(),
         );
-        device.inject_framed_packet(response_packet);
+        (frame, payload)
+    }
 
-        let result = client.get_device_info(Duration::from_millis(100));
+    #[test]
+    fn test_lenient_validation_flags_an_out_of_range_config() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        client.start().unwrap();
+        let (frame, _) = out_of_range_config_response();
+        device.inject_framed_packet(frame);
+
+        let config = client
+            .get_device_config(Duration::from_millis(500))
+            .unwrap()
+            .unwrap();
+        assert!(config.suspect);
+        assert_eq!(config.frequency, 5000);
+        assert!(client.check_error().is_none());
+    }
+
+    #[test]
+    fn test_strict_validation_reports_an_out_of_range_config() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        client.set_response_validation(ResponseValidation::Strict);
+        client.start().unwrap();
+        let (frame, payload) = out_of_range_config_response();
+        device.inject_framed_packet(frame);
 
+        let result = client
+            .get_device_config(Duration::from_millis(200))
+            .unwrap();
+        assert_eq!(result, None);
+        let event = client.check_error().unwrap();
         assert_eq!(
-            result.unwrap(),
-            Some(DeviceInfo {
-                firmware_version: "v1.2.3".to_string(),
-                id,
-            })
+            event.kind,
+            FIRMClientError::ProtocolViolation {
+                command: FIRMCommand::GetDeviceConfig,
+                violations: vec![ResponseViolation::FrequencyOutOfRange(5000)],
+                payload,
+            }
         );
+        assert_eq!(event.error_code(), error_codes::PROTOCOL_VIOLATION);
     }
 
     #[test]
-    fn test_get_device_config_command() {
+    fn test_stop_passes_on_packets_that_already_arrived() {
         let (mut client, device) = FIRMClient::new_mock(0.01);
-        client.start();
+        // The reader thread sleeps between polls, so both packets are still waiting in the
+        // port when stop() is called.
+        client.set_read_strategy(ReadStrategy::PollBytesToRead {
+            interval: Duration::from_millis(200),
+        });
+        client.start().unwrap();
+        thread::sleep(Duration::from_millis(20));
+        let mut bytes = data_packet_with_timestamp(1.0).to_bytes();
+        bytes.extend(data_packet_with_timestamp(2.0).to_bytes());
+        device.inject_bytes(&bytes);
+        client.stop();
 
-        let name = "TestDevice";
-        let frequency: u16 = 100;
-        let protocol = DeviceProtocol::UART;
+        let timestamps: Vec<f64> = client
+            .get_data_packets(None)
+            .unwrap()
+            .iter()
+            .map(|packet| packet.timestamp_seconds)
+            .collect();
+        assert_eq!(timestamps, [1.0, 2.0]);
+        assert!(!client.is_running());
+    }
 
-        let mut payload = vec![0u8; DEVICE_NAME_LENGTH + FREQUENCY_LENGTH + 1];
-        let name_bytes = str_to_bytes::<DEVICE_NAME_LENGTH>(name);
-        payload[0..DEVICE_NAME_LENGTH].copy_from_slice(&name_bytes);
-        payload[DEVICE_NAME_LENGTH..DEVICE_NAME_LENGTH + FREQUENCY_LENGTH]
-            .copy_from_slice(&frequency.to_le_bytes());
-        payload[DEVICE_NAME_LENGTH + FREQUENCY_LENGTH] = 2;
+    #[test]
+    fn test_packet_filter_drops_packets_before_the_receiver() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        client.set_packet_filter(Decimate::new(4));
+        let subscribed = Arc::new(Mutex::new(Vec::new()));
+        let seen = subscribed.clone();
+        client.subscribe(move |packet: &FIRMData| {
+            seen.lock().unwrap().push(packet.timestamp_seconds);
+        });
+        client.start().unwrap();
+        for i in 0..12 {
+            device.inject_framed_packet(data_packet_with_timestamp(i as f64));
+        }
 
-        let response_packet = FramedPacket::new(
-            PacketHeader::Response,
-            FIRMCommand::GetDeviceConfig.to_u16(),
-            payload,
-        );
+        let timestamps: Vec<f64> = client
+            .iter_packets_timeout(Duration::from_millis(200))
+            .map(|packet| packet.timestamp_seconds)
+            .collect();
+        assert_eq!(timestamps, [0.0, 4.0, 8.0]);
+        assert_eq!(*subscribed.lock().unwrap(), timestamps);
+        assert_eq!(client.stats().packets_filtered, 9);
+
+        // Filters apply straight away, and any closure will do.
+        client.set_packet_filter(|packet: &FIRMData| packet.timestamp_seconds >= 20.0);
+        for t in [13.0, 21.0, 14.0, 22.0] {
+            device.inject_framed_packet(data_packet_with_timestamp(t));
+        }
+        let timestamps: Vec<f64> = client
+            .iter_packets_timeout(Duration::from_millis(200))
+            .map(|packet| packet.timestamp_seconds)
+            .collect();
+        assert_eq!(timestamps, [21.0, 22.0]);
+
+        client.clear_packet_filter();
+        for t in [30.0, 31.0] {
+            device.inject_framed_packet(data_packet_with_timestamp(t));
+        }
+        assert_eq!(
+            client
+                .iter_packets_timeout(Duration::from_millis(200))
+                .count(),
+            2
+        );
+        assert_eq!(client.stats().packets_filtered, 11);
+    }
+
+    #[test]
+    fn test_data_validation_flags_or_drops_implausible_packets() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        // The mock packets are all zeros, so only check the timestamps.
+        let config = ValidationConfig {
+            max_timestamp_rewind_seconds: Some(0.0),
+            ..ValidationConfig::permissive()
+        };
+        client.set_data_validation(DataValidation::Drop, config);
+        client.start().unwrap();
+        for t in [1.0, 2.0, 1.5, 3.0] {
+            device.inject_framed_packet(data_packet_with_timestamp(t));
+        }
+        let timestamps: Vec<f64> = client
+            .iter_packets_timeout(Duration::from_millis(200))
+            .map(|packet| packet.timestamp_seconds)
+            .collect();
+        assert_eq!(timestamps, [1.0, 2.0, 3.0]);
+        assert_eq!(client.stats().packets_invalid, 1);
+        assert!(client.check_error().is_none());
+
+        client.set_data_validation(DataValidation::Flag, config);
+        for t in [4.0, 3.5] {
+            device.inject_framed_packet(data_packet_with_timestamp(t));
+        }
+        let timestamps: Vec<f64> = client
+            .iter_packets_timeout(Duration::from_millis(200))
+            .map(|packet| packet.timestamp_seconds)
+            .collect();
+        assert_eq!(timestamps, [4.0, 3.5]);
+        assert_eq!(client.stats().packets_invalid, 2);
+        match client.check_error().map(|event| event.kind) {
+            Some(FIRMClientError::ImplausibleData {
+                timestamp_seconds,
+                faults,
+            }) => {
+                assert_eq!(timestamp_seconds, 3.5);
+                assert_eq!(faults.len(), 1);
+                assert_eq!(faults[0].field, "timestamp_seconds");
+            }
+            other => panic!("expected ImplausibleData, got {other:?}"),
+        }
+
+        // The default ranges reject the mock's zero pressure.
+        client.set_data_validation(DataValidation::Drop, ValidationConfig::default());
+        device.inject_framed_packet(data_packet_with_timestamp(5.0));
+        assert_eq!(
+            client
+                .iter_packets_timeout(Duration::from_millis(200))
+                .count(),
+            0
+        );
+        assert_eq!(client.stats().packets_invalid, 3);
+    }
+
+    #[test]
+    fn test_data_validation_compares_with_the_last_valid_packet() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        let config = ValidationConfig {
+            max_timestamp_rewind_seconds: Some(0.0),
+            ..ValidationConfig::permissive()
+        };
+        client.set_data_validation(DataValidation::Drop, config);
+        client.start().unwrap();
+        // A corrupt packet's far-future timestamp doesn't become the baseline the next
+        // packets are held to.
+        device.inject_framed_packet(data_packet_with_timestamp(1.0));
+        device.inject_framed_packet(data_packet_with_pressure(1e9, f32::NAN));
+        for t in [2.0, 30.0] {
+            device.inject_framed_packet(data_packet_with_timestamp(t));
+        }
+        let timestamps: Vec<f64> = client
+            .iter_packets_timeout(Duration::from_millis(200))
+            .map(|packet| packet.timestamp_seconds)
+            .collect();
+        assert_eq!(timestamps, [1.0, 2.0, 30.0]);
+        assert_eq!(client.stats().packets_invalid, 1);
+
+        // And a device restart starts the comparison over.
+        for t in [0.5, 0.6] {
+            device.inject_framed_packet(data_packet_with_timestamp(t));
+        }
+        assert_eq!(
+            client
+                .iter_packets_timeout(Duration::from_millis(200))
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_timestamp_policy_applies_to_the_reader_thread() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        client.set_timestamp_policy(TimestampPolicy::Strict);
+        client.start().unwrap();
+        for timestamp in [1.0, 1.0, 2.0, 1.5, 3.0] {
+            device.inject_framed_packet(data_packet_with_timestamp(timestamp));
+        }
+
+        let timestamps: Vec<f64> = client
+            .iter_packets_timeout(Duration::from_millis(200))
+            .take(3)
+            .map(|packet| packet.timestamp_seconds)
+            .collect();
+        assert_eq!(timestamps, vec![1.0, 2.0, 3.0]);
+        let stats = client.stats();
+        assert_eq!(stats.timestamps_dropped, 2);
+        assert_eq!(stats.packets_parsed, 3);
+    }
+
+    #[test]
+    fn test_timestamp_warnings_and_restarts_are_reported() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        client.set_timestamp_policy(TimestampPolicy::Warn);
+        client.start().unwrap();
+        for timestamp in [10.0, 11.0, 10.5, 12.0, 0.5, 0.6] {
+            device.inject_framed_packet(data_packet_with_timestamp(timestamp));
+        }
+
+        let timestamps: Vec<f64> = client
+            .iter_packets_timeout(Duration::from_millis(200))
+            .take(6)
+            .map(|packet| packet.timestamp_seconds)
+            .collect();
+        assert_eq!(timestamps, vec![10.0, 11.0, 10.5, 12.0, 0.5, 0.6]);
+        let errors: Vec<FIRMClientError> = client
+            .drain_errors()
+            .into_iter()
+            .map(|event| event.kind)
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                FIRMClientError::TimestampOutOfOrder {
+                    previous_seconds: 11.0,
+                    timestamp_seconds: 10.5,
+                },
+                FIRMClientError::DeviceRestartDetected {
+                    previous_seconds: 12.0,
+                    timestamp_seconds: 0.5,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_add_receiver_broadcasts_to_every_receiver() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        let ui = client.add_receiver();
+        let recorder = client.add_receiver();
+        client.start().unwrap();
+
+        for t in [1.0, 2.0, 3.0] {
+            device.inject_framed_packet(data_packet_with_timestamp(t));
+        }
+
+        let timeout = Duration::from_millis(200);
+        for receiver in [&ui, &recorder] {
+            let seen: Vec<f64> = (0..3)
+                .map(|_| receiver.recv_timeout(timeout).unwrap().timestamp_seconds)
+                .collect();
+            assert_eq!(seen, vec![1.0, 2.0, 3.0]);
+        }
+        // The main channel still gets its own copy.
+        assert_eq!(client.iter_packets_timeout(timeout).take(3).count(), 3);
+    }
+
+    #[test]
+    fn test_unread_receiver_buffers_independently() {
+        // add_receiver documents roughly 120 bytes buffered per packet.
+        assert!(std::mem::size_of::<FIRMData>() <= 128);
+
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        let unread = client.add_receiver();
+        let read = client.add_receiver();
+        client.start().unwrap();
+
+        for i in 0..50 {
+            device.inject_framed_packet(data_packet_with_timestamp(i as f64));
+        }
+        let timeout = Duration::from_millis(200);
+        for _ in 0..50 {
+            read.recv_timeout(timeout).unwrap();
+        }
+        client.stop();
+
+        // Reading one receiver leaves the other's backlog untouched.
+        assert_eq!(unread.try_iter().count(), 50);
+        assert!(read.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_dropped_receiver_does_not_stop_others() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        let dropped = client.add_receiver();
+        let kept = client.add_receiver();
+        client.start().unwrap();
+        drop(dropped);
+
+        device.inject_framed_packet(data_packet_with_timestamp(1.0));
+        device.inject_framed_packet(data_packet_with_timestamp(2.0));
+
+        let timeout = Duration::from_millis(200);
+        assert_eq!(kept.recv_timeout(timeout).unwrap().timestamp_seconds, 1.0);
+        assert_eq!(kept.recv_timeout(timeout).unwrap().timestamp_seconds, 2.0);
+        assert!(client.is_running());
+        assert_eq!(client.extra_senders.lock().unwrap().len(), 1);
+    }
+
+    /// One direction of an in-memory duplex pipe. Reads time out like a serial port when
+    /// nothing arrives within 10 ms.
+    #[derive(Clone, Default)]
+    struct PipeEnd {
+        queue: Arc<(Mutex<VecDeque<u8>>, std::sync::Condvar)>,
+    }
+
+    impl Read for PipeEnd {
+        fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+            let (queue, ready) = &*self.queue;
+            let (mut queue, _) = ready
+                .wait_timeout_while(queue.lock().unwrap(), Duration::from_millis(10), |q| {
+                    q.is_empty()
+                })
+                .unwrap();
+            if queue.is_empty() {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "pipe timeout"));
+            }
+            let n = out.len().min(queue.len());
+            for (slot, byte) in out.iter_mut().zip(queue.drain(..n)) {
+                *slot = byte;
+            }
+            Ok(n)
+        }
+    }
+
+    impl Write for PipeEnd {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            let (queue, ready) = &*self.queue;
+            queue.lock().unwrap().extend(bytes);
+            ready.notify_all();
+            Ok(bytes.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_client_over_in_memory_transport() {
+        let to_client = PipeEnd::default();
+        let mut to_device = PipeEnd::default();
+        let mut client = FIRMClient::from_read_write(to_client.clone(), to_device.clone());
+        client.start().unwrap();
+
+        to_client
+            .clone()
+            .write_all(&data_packet_with_timestamp(1.0).to_bytes())
+            .unwrap();
+        let packets = client
+            .get_data_packets(Some(Duration::from_millis(200)))
+            .unwrap();
+        assert_eq!(packets[0].timestamp_seconds, 1.0);
+
+        // The port is handed back on stop and reused on the next start.
+        client.stop();
+        client.start().unwrap();
+
+        client.reboot().unwrap();
+        let mut sent = Vec::new();
+        let deadline = Instant::now() + Duration::from_millis(500);
+        while sent.len() < FramedPacket::MIN_SIZE && Instant::now() < deadline {
+            let mut chunk = [0u8; FramedPacket::MIN_SIZE];
+            if let Ok(n) = to_device.read(&mut chunk) {
+                sent.extend_from_slice(&chunk[..n]);
+            }
+        }
+        let sent = FramedPacket::from_bytes(&sent).unwrap();
+        assert_eq!(sent.header(), PacketHeader::Command);
+        assert_eq!(sent.identifier(), FIRMCommand::Reboot.to_u16());
+
+        to_client
+            .clone()
+            .write_all(&data_packet_with_timestamp(2.0).to_bytes())
+            .unwrap();
+        let packets = client
+            .get_data_packets(Some(Duration::from_millis(200)))
+            .unwrap();
+        assert_eq!(packets[0].timestamp_seconds, 2.0);
+        assert!(client.check_error().is_none());
+    }
+
+    #[test]
+    fn test_reader_thread_ends_with_a_finite_stream() {
+        let mut stream = data_packet_with_timestamp(1.0).to_bytes();
+        stream.extend(data_packet_with_timestamp(2.0).to_bytes());
+        let mut client = FIRMClient::from_read_write(io::Cursor::new(stream), io::sink());
+        client.start().unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while client.is_running() {
+            assert!(
+                Instant::now() < deadline,
+                "reader thread kept reading past the end"
+            );
+            thread::sleep(Duration::from_millis(1));
+        }
+        let timestamps: Vec<f64> = client
+            .get_data_packets(None)
+            .unwrap()
+            .iter()
+            .map(|packet| packet.timestamp_seconds)
+            .collect();
+        assert_eq!(timestamps, [1.0, 2.0]);
+        assert!(client.check_error().is_none());
+    }
+
+    /// Writes data packets with the given timestamps, plus a GetDeviceInfo response, to `stream`.
+    fn write_canned_packets(stream: &mut std::net::TcpStream, timestamps: &[f64]) {
+        let mut bytes = Vec::new();
+        for &timestamp in timestamps {
+            bytes.extend(data_packet_with_timestamp(timestamp).to_bytes());
+        }
+        let mut info = vec![0u8; DEVICE_ID_LENGTH + FIRMWARE_VERSION_LENGTH];
+        info[..DEVICE_ID_LENGTH].copy_from_slice(&7u64.to_le_bytes());
+        bytes.extend(
+            FramedPacket::new(
+                PacketHeader::Response,
+                FIRMCommand::GetDeviceInfo.to_u16(),
+                info,
+            )
+            .to_bytes(),
+        );
+        stream.write_all(&bytes).unwrap();
+    }
+
+    #[test]
+    fn test_connect_tcp_decodes_streamed_packets() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            write_canned_packets(&mut stream, &[1.0, 2.0, 3.0]);
+            // Hold the connection open until the client has read everything.
+            thread::sleep(Duration::from_millis(500));
+        });
+
+        let mut client = FIRMClient::connect_tcp(&addr, 0.01).unwrap();
+        client.start().unwrap();
+
+        let timestamps: Vec<f64> = client
+            .iter_packets_timeout(Duration::from_millis(300))
+            .take(3)
+            .map(|packet| packet.timestamp_seconds)
+            .collect();
+        assert_eq!(timestamps, vec![1.0, 2.0, 3.0]);
+        let responses = client
+            .get_response_packets(Some(Duration::from_millis(300)))
+            .unwrap();
+        assert!(matches!(
+            responses[0],
+            FIRMResponse::GetDeviceInfo(DeviceInfo { id: 7, .. })
+        ));
+        // Read timeouts while the bridge is idle aren't errors.
+        assert!(client.check_error().is_none());
+        assert!(client.is_running());
+
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_tcp_disconnect_stops_reader_thread() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            write_canned_packets(&mut stream, &[1.0]);
+        });
+
+        let mut client = FIRMClient::connect_tcp(&addr, 0.01).unwrap();
+        client.start().unwrap();
+        server.join().unwrap();
+
+        let error = client
+            .error_receiver
+            .recv_timeout(Duration::from_secs(1))
+            .unwrap();
+        assert!(
+            matches!(&error.kind, FIRMClientError::Disconnected { message } if message.contains("closed by peer")),
+            "{error}"
+        );
+        // The reader is already marked stopped when the fatal error arrives.
+        assert!(error.fatal);
+        assert!(!client.is_running());
+        assert!(client.drain_errors().is_empty());
+        assert_eq!(client.recent_errors(), [error]);
+    }
+
+    #[test]
+    fn test_tcp_reconnects_when_enabled() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            let (mut first, _) = listener.accept().unwrap();
+            write_canned_packets(&mut first, &[1.0]);
+            drop(first);
+
+            let (mut second, _) = listener.accept().unwrap();
+            write_canned_packets(&mut second, &[2.0]);
+            thread::sleep(Duration::from_millis(500));
+        });
+
+        let mut client = FIRMClient::connect_tcp(&addr, 0.01).unwrap();
+        client.set_reconnect(true);
+        client.start().unwrap();
+
+        let timestamps: Vec<f64> = client
+            .iter_packets_timeout(Duration::from_secs(2))
+            .take(2)
+            .map(|packet| packet.timestamp_seconds)
+            .collect();
+        assert_eq!(timestamps, vec![1.0, 2.0]);
+        // The drop is still reported, but the reader thread kept going.
+        let errors = client.drain_errors();
+        assert_eq!(errors.len(), 1);
+        assert!(!errors[0].fatal);
+        assert!(client.is_running());
+
+        server.join().unwrap();
+        client.stop();
+    }
+
+    #[test]
+    fn test_start_after_disconnect_reopens_the_connection() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            let (mut first, _) = listener.accept().unwrap();
+            write_canned_packets(&mut first, &[1.0]);
+            drop(first);
+
+            let (mut second, _) = listener.accept().unwrap();
+            write_canned_packets(&mut second, &[2.0]);
+            thread::sleep(Duration::from_millis(500));
+        });
+
+        let mut client = FIRMClient::connect_tcp(&addr, 0.01).unwrap();
+        client.start().unwrap();
+        let first = client
+            .get_data_packets(Some(Duration::from_secs(1)))
+            .unwrap();
+        assert_eq!(first[0].timestamp_seconds, 1.0);
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while client.is_running() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(!client.is_running());
+        assert!(client.check_error().is_some());
+
+        // Without a `stop()` in between, starting again reopens the dead connection.
+        client.start().unwrap();
+        assert!(client.is_running());
+        let second = client
+            .get_data_packets(Some(Duration::from_secs(1)))
+            .unwrap();
+        assert_eq!(second[0].timestamp_seconds, 2.0);
+
+        // Starting a running client is a no-op.
+        client.start().unwrap();
+        assert!(client.is_running());
+        server.join().unwrap();
+        client.stop();
+    }
+
+    /// A path in the temp directory that is unique to this test process.
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("firm_rust_{}_{name}", std::process::id()))
+    }
+
+    #[test]
+    fn test_record_raw_then_replay_gives_same_packets() {
+        let path = temp_path("record_raw.bin");
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        client.record_raw(&path).unwrap();
+        assert!(client.is_recording());
+        client.start().unwrap();
+
+        device.inject_bytes(&[0x00, 0xFF, 0x13]);
+        for i in 0..10 {
+            device.inject_framed_packet(data_packet_with_timestamp(i as f64 * 0.001));
+        }
+        let live: Vec<FIRMData> = client
+            .iter_packets_timeout(Duration::from_millis(200))
+            .take(10)
+            .collect();
+        assert_eq!(live.len(), 10);
+        client.stop();
+        assert!(client.stop_recording().unwrap());
+        assert!(!client.stop_recording().unwrap());
+
+        let (header, _) = recording::open_raw_recording(&path).unwrap();
+        assert_eq!(header.connection.kind, ConnectionKind::Mock);
+        assert_eq!(header.connection.timeout_seconds, Some(0.01));
+        assert!(header.start_unix_seconds > 0.0);
+
+        let mut replay = FIRMClient::from_raw_recording(&path).unwrap();
+        replay.start().unwrap();
+        let replayed: Vec<FIRMData> = replay
+            .iter_packets_timeout(Duration::from_millis(200))
+            .collect();
+        assert_eq!(replayed, live);
+        // The replay ends by itself, and running out of bytes isn't an error.
+        assert!(!replay.is_running());
+        assert!(replay.check_error().is_none());
+        assert_eq!(replay.stats().bytes_skipped, 3);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_from_raw_recording_rejects_other_files() {
+        let path = temp_path("not_a_recording.bin");
+        std::fs::write(&path, data_packet_with_timestamp(1.0).to_bytes()).unwrap();
+        assert!(FIRMClient::from_raw_recording(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_session_capture_is_partial_when_the_device_stays_quiet() {
+        use crate::live_export::LiveExporter;
+        use crate::response_script::{ResponseBehavior, ResponseScript};
+        use crate::simulator::DeviceSimulator;
+
+        let script = ResponseScript::default()
+            .with_rule(Some(FIRMCommand::GetDeviceConfig), ResponseBehavior::Drop);
+        let (mut client, device) = FIRMClient::new_mock(0.001);
+        let _simulator = DeviceSimulator::new(200.0)
+            .with_response_script(script)
+            .spawn(device);
+        client.start().unwrap();
+        assert!(client.session_info().is_none());
+        client.stop();
+
+        client.set_session_capture(Some(Duration::from_millis(100)));
+        client.start().unwrap();
+        let session = client.session_info().unwrap().clone();
+        assert!(session.partial);
+        assert_eq!(
+            session.device_info.as_ref().unwrap().firmware_version,
+            "v1.0.0"
+        );
+        assert!(session.device_config.is_none());
+        // Streaming carries on regardless.
+        assert!(
+            client
+                .get_data_packets(Some(Duration::from_secs(1)))
+                .is_ok()
+        );
+        assert!(client.is_running());
+
+        let raw_path = temp_path("session.bin");
+        let csv_path = temp_path("session.csv");
+        client.record_raw(&raw_path).unwrap();
+        let exporter = LiveExporter::start(&mut client, &csv_path).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        client.stop();
+        client.stop_recording().unwrap();
+        exporter.finish().unwrap();
+
+        let replay = FIRMClient::from_raw_recording(&raw_path).unwrap();
+        assert_eq!(replay.session_info(), Some(&session));
+        let text = std::fs::read_to_string(&csv_path).unwrap();
+        let comments = text.lines().map_while(|line| line.strip_prefix("# "));
+        assert_eq!(SessionInfo::from_header_lines(comments), Some(session));
+        let rows = firm_core::csv::parse_csv(&text, Default::default()).unwrap();
+        assert!(!rows.is_empty());
+
+        std::fs::remove_file(&raw_path).unwrap();
+        std::fs::remove_file(&csv_path).unwrap();
+    }
+
+    /// Writes a mock log of `seconds` of magnetometer readings at 100 Hz and returns its path
+    /// along with the profile the readings came from.
+    fn write_mock_log(name: &str, seconds: f64) -> (std::path::PathBuf, FlightProfileGenerator) {
+        let profile = FlightProfileGenerator::default();
+        let path = temp_path(name);
+        std::fs::write(&path, build_mock_log(&profile, seconds, 100.0)).unwrap();
+        (path, profile)
+    }
+
+    #[test]
+    fn test_from_log_file_plays_back_in_realtime() {
+        let (path, profile) = write_mock_log("playback_realtime.frm", 0.5);
+        let mut client = FIRMClient::from_log_file(&path, 1.0).unwrap();
+
+        let started = Instant::now();
+        client.start().unwrap();
+        let packets: Vec<FIRMData> = client.iter_packets().collect();
+        let elapsed = started.elapsed();
+
+        assert_eq!(packets.len(), 50);
+        for (i, packet) in packets.iter().enumerate() {
+            let t = i as f64 / 100.0;
+            assert!((packet.timestamp_seconds - t).abs() < 1e-6);
+            let expected = profile.magnetic_field(t);
+            assert!((packet.magnetic_field_x_microteslas - expected[0]).abs() < 0.01);
+            assert!((packet.magnetic_field_z_microteslas - expected[2]).abs() < 0.01);
+        }
+        // The last packet is due 0.49 s after the first.
+        assert!(elapsed >= Duration::from_millis(450), "{elapsed:?}");
+        assert!(elapsed < Duration::from_millis(1500), "{elapsed:?}");
+        assert!(!client.is_running());
+        assert!(client.check_error().is_none());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_from_log_file_speed_scales_pacing() {
+        let (path, _) = write_mock_log("playback_fast.frm", 1.0);
+
+        let mut fast = FIRMClient::from_log_file(&path, 4.0).unwrap();
+        let started = Instant::now();
+        fast.start().unwrap();
+        assert_eq!(fast.iter_packets().count(), 100);
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(200), "{elapsed:?}");
+        assert!(elapsed < Duration::from_millis(800), "{elapsed:?}");
+
+        let mut unpaced = FIRMClient::from_log_file(&path, 0.0).unwrap();
+        let started = Instant::now();
+        unpaced.start().unwrap();
+        assert_eq!(unpaced.iter_packets().count(), 100);
+        assert!(started.elapsed() < Duration::from_millis(200));
+        assert!(!unpaced.is_running());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_from_log_file_rejects_other_files() {
+        let path = temp_path("not_a_log.frm");
+        std::fs::write(
+            &path,
+            vec![0u8; firm_core::constants::log_parsing::HEADER_TOTAL_SIZE + 10],
+        )
+        .unwrap();
+        assert!(FIRMClient::from_log_file(&path, 1.0).is_err());
+
+        let (log, _) = write_mock_log("negative_speed.frm", 0.1);
+        assert!(FIRMClient::from_log_file(&log, -1.0).is_err());
+        let _ = std::fs::remove_file(path);
+        let _ = std::fs::remove_file(log);
+    }
+
+    fn not_supported(result: Result<impl std::fmt::Debug>) -> FIRMClientError {
+        result
+            .unwrap_err()
+            .downcast::<FIRMClientError>()
+            .expect("a FIRMClientError")
+    }
+
+    #[test]
+    fn test_log_file_client_rejects_commands() {
+        let (path, _) = write_mock_log("playback_commands.frm", 0.2);
+        let mut client = FIRMClient::from_log_file(&path, 0.0).unwrap();
+        client.start().unwrap();
+
+        let expected = FIRMClientError::NotSupported {
+            operation: "Sending commands",
+            connection: "log_file",
+        };
+        assert_eq!(not_supported(client.send_raw_bytes(&[0x01])), expected);
+        assert_eq!(not_supported(client.reboot()), expected);
+        assert_eq!(
+            not_supported(client.get_device_info(Duration::from_millis(100))),
+            expected
+        );
+
+        // Everything else behaves as for a live client.
+        assert_eq!(client.iter_packets().count(), 20);
+        assert!(!client.is_running());
+        assert_eq!(client.stats().packets_parsed, 20);
+        assert!(client.check_error().is_none());
+
+        // And live clients can't be paused.
+        let (live, _device) = FIRMClient::new_mock(0.01);
+        assert!(!live.is_replay());
+        assert_eq!(
+            not_supported(live.pause()),
+            FIRMClientError::NotSupported {
+                operation: "Pausing playback",
+                connection: "mock",
+            }
+        );
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_log_file_playback_controls() {
+        let (path, _) = write_mock_log("playback_controls.frm", 1.0);
+        let mut client = FIRMClient::from_log_file(&path, 1.0).unwrap();
+        assert!(client.is_replay());
+
+        client.pause().unwrap();
+        client.start().unwrap();
+        thread::sleep(Duration::from_millis(200));
+        assert!(client.get_data_packets(None).unwrap().is_empty());
+        assert!(client.is_running());
+
+        client.seek(0.495).unwrap();
+        client.set_speed(0.0).unwrap();
+        assert!(client.set_speed(-1.0).is_err());
+        client.resume().unwrap();
+        let timestamps: Vec<f64> = client
+            .iter_packets()
+            .map(|packet| packet.timestamp_seconds)
+            .collect();
+        assert_eq!(timestamps.len(), 50);
+        assert!((timestamps[0] - 0.5).abs() < 1e-6, "{}", timestamps[0]);
+        assert!(!client.is_running());
+
+        // Seeking back after the end replays from there on the next start.
+        client.stop();
+        client.seek(0.0).unwrap();
+        client.start().unwrap();
+        let replayed = client.iter_packets().count();
+        assert_eq!(replayed, 100);
+        assert_eq!(client.stats().packets_parsed, 150);
+        assert!(client.check_error().is_none());
+        let _ = std::fs::remove_file(path);
+    }
+
+    fn data_packet_with_timestamp(timestamp_seconds: f64) -> FramedPacket {
+        let mut payload = vec![0u8; 120];
+        payload[0..8].copy_from_slice(&timestamp_seconds.to_le_bytes());
+        FramedPacket::new(PacketHeader::Data, 0, payload)
+    }
+
+    #[test]
+    fn test_collect_corpus_keeps_each_frame_shape_across_restarts() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        assert!(client.corpus().is_none());
+        client.collect_corpus(Some(8));
+        client.start().unwrap();
+        for t in [1.0, 2.0, 3.0] {
+            device.inject_framed_packet(data_packet_with_timestamp(t));
+        }
+        let mut corrupted = data_packet_with_timestamp(4.0).to_bytes();
+        corrupted[20] ^= 0x01;
+        device.inject_bytes(&corrupted);
+        assert_eq!(
+            client.iter_packets_timeout(Duration::from_secs(1)).count(),
+            3
+        );
+        client.stop();
+
+        client.start().unwrap();
+        device.inject_framed_packet(FramedPacket::new(PacketHeader::Response, 0x0042, vec![7]));
+        thread::sleep(Duration::from_millis(100));
+        let corpus = client.corpus().unwrap();
+        let outcomes: Vec<(u16, FrameOutcome)> = corpus
+            .entries()
+            .map(|(shape, _)| (shape.identifier, shape.outcome))
+            .collect();
+        assert_eq!(
+            outcomes,
+            [
+                (0, FrameOutcome::Accepted),
+                (0, FrameOutcome::BadCrc),
+                (0x0042, FrameOutcome::Undecodable),
+            ]
+        );
+
+        client.collect_corpus(None);
+        assert!(client.corpus().is_none());
+    }
+
+    #[test]
+    fn test_iter_packets_timeout_yields_every_packet_in_order() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        client.start().unwrap();
+
+        for i in 0..20 {
+            device.inject_framed_packet(data_packet_with_timestamp(i as f64));
+        }
+
+        let timestamps: Vec<f64> = client
+            .iter_packets_timeout(Duration::from_millis(100))
+            .map(|packet| packet.timestamp_seconds)
+            .collect();
+        assert_eq!(timestamps, (0..20).map(|i| i as f64).collect::<Vec<_>>());
+    }
+
+    /// An in-memory byte stream that counts how many reads it served.
+    struct CountingReader {
+        bytes: io::Cursor<Vec<u8>>,
+        reads: Arc<AtomicU64>,
+    }
+
+    impl Read for CountingReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.reads.fetch_add(1, Ordering::Relaxed);
+            self.bytes.read(buf)
+        }
+    }
+
+    /// Parses `packets` data packets from memory with a `buffer_size` read buffer and returns
+    /// the packet count and the number of reads.
+    fn read_from_memory(packets: usize, buffer_size: usize) -> (usize, u64) {
+        let stream: Vec<u8> = (0..packets)
+            .flat_map(|i| data_packet_with_timestamp(i as f64).to_bytes())
+            .collect();
+        let reads = Arc::new(AtomicU64::new(0));
+        let reader = CountingReader {
+            bytes: io::Cursor::new(stream),
+            reads: reads.clone(),
+        };
+        let mut client = FIRMClient::from_transport(Box::new(ReplayTransport::new(reader)));
+        client.set_read_buffer_size(buffer_size).unwrap();
+
+        client.start().unwrap();
+        let parsed = client.iter_packets().count();
+        client.stop();
+        (parsed, reads.load(Ordering::Relaxed))
+    }
+
+    #[test]
+    fn test_read_buffer_size_trades_reads_for_throughput() {
+        let packets = 5_000;
+        let (small_parsed, small_reads) = read_from_memory(packets, 64);
+        let (large_parsed, large_reads) =
+            read_from_memory(packets, FIRMClient::MAX_READ_BUFFER_SIZE);
+
+        assert_eq!(small_parsed, packets);
+        assert_eq!(large_parsed, packets);
+        // Frames are 130 bytes, so a 64 byte buffer needs a few reads per packet.
+        assert!(small_reads >= 2 * packets as u64, "{small_reads}");
+        assert!(
+            large_reads * 100 < small_reads,
+            "{large_reads} vs {small_reads}"
+        );
+    }
+
+    #[test]
+    fn test_read_buffer_size_is_validated() {
+        let (mut client, _device) = FIRMClient::new_mock(0.01);
+        assert!(client.set_read_buffer_size(0).is_err());
+        assert!(
+            client
+                .set_read_buffer_size(FIRMClient::MAX_READ_BUFFER_SIZE + 1)
+                .is_err()
+        );
+        client.set_read_buffer_size(4096).unwrap();
+    }
+
+    #[test]
+    fn test_poll_bytes_to_read_strategy_streams_packets() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        client.set_read_strategy(ReadStrategy::PollBytesToRead {
+            interval: Duration::from_millis(1),
+        });
+        client.start().unwrap();
+        inject_packet_burst(&client, &device, 50);
+        let timestamps: Vec<f64> = client
+            .get_data_packets(None)
+            .unwrap()
+            .iter()
+            .map(|packet| packet.timestamp_seconds)
+            .collect();
+        assert_eq!(timestamps, (0..50).map(|i| i as f64).collect::<Vec<_>>());
+
+        // The strategy survives a stop/start handoff of the port.
+        client.stop();
+        client.start().unwrap();
+        device.inject_framed_packet(data_packet_with_timestamp(50.0));
+        let packets = client
+            .get_data_packets(Some(Duration::from_secs(1)))
+            .unwrap();
+        assert_eq!(packets[0].timestamp_seconds, 50.0);
+        client.stop();
+    }
+
+    /// Injects a burst of `count` packets and waits for the reader thread to parse them all.
+    fn inject_packet_burst(
+        client: &FIRMClient,
+        device: &mock_serial::MockDeviceHandle,
+        count: u64,
+    ) {
+        for i in 0..count {
+            device.inject_framed_packet(data_packet_with_timestamp(i as f64));
+        }
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while client.stats().packets_parsed < count {
+            assert!(Instant::now() < deadline, "burst was not parsed in time");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_get_latest_packet_skips_the_backlog() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        client.start().unwrap();
+        inject_packet_burst(&client, &device, 100);
+
+        let latest = client.get_latest_packet().unwrap();
+        assert_eq!(latest.timestamp_seconds, 99.0);
+        assert_eq!(client.skipped_packet_count(), 99);
+        assert!(client.get_latest_packet().is_none());
+        assert!(client.get_data_packets(None).unwrap().is_empty());
+        client.stop();
+    }
+
+    #[test]
+    fn test_latest_only_mode_keeps_one_packet() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        let receiver = client.add_receiver();
+        client.set_latest_only(true);
+        client.start().unwrap();
+        inject_packet_burst(&client, &device, 100);
+
+        // Nothing was queued, and the reader thread already dropped the older packets.
+        assert_eq!(client.skipped_packet_count(), 99);
+        assert_eq!(client.get_latest_packet().unwrap().timestamp_seconds, 99.0);
+        assert!(client.get_latest_packet().is_none());
+        assert!(client.get_data_packets(None).unwrap().is_empty());
+        assert_eq!(receiver.try_iter().count(), 100);
+
+        client.set_latest_only(false);
+        device.inject_framed_packet(data_packet_with_timestamp(100.0));
+        let packets = client
+            .get_data_packets(Some(Duration::from_secs(1)))
+            .unwrap();
+        assert_eq!(packets[0].timestamp_seconds, 100.0);
+        client.stop();
+    }
+
+    #[test]
+    fn test_iter_packets_ends_when_reader_thread_dies() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        client.start().unwrap();
+
+        device.inject_framed_packet(data_packet_with_timestamp(1.0));
+        device.inject_framed_packet(data_packet_with_timestamp(2.0));
+
+        let mut iter = client.iter_packets();
+        assert_eq!(iter.next().unwrap().timestamp_seconds, 1.0);
+        assert_eq!(iter.next().unwrap().timestamp_seconds, 2.0);
+
+        device.disconnect();
+        assert!(iter.next().is_none());
+        assert!(iter.next().is_none());
+        assert!(client.check_error().is_some());
+    }
+
+    #[test]
+    fn test_subscribers_see_packets_in_order() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+
+        let first = Arc::new(Mutex::new(Vec::new()));
+        let second = Arc::new(Mutex::new(Vec::new()));
+        let first_clone = first.clone();
+        let second_clone = second.clone();
+        client.subscribe(move |packet| first_clone.lock().unwrap().push(packet.timestamp_seconds));
+        client.subscribe(move |packet| second_clone.lock().unwrap().push(packet.timestamp_seconds));
+        client.start().unwrap();
+
+        for i in 0..10 {
+            device.inject_framed_packet(data_packet_with_timestamp(i as f64));
+        }
+        let received = client
+            .iter_packets_timeout(Duration::from_millis(100))
+            .count();
+        assert_eq!(received, 10);
+
+        let expected: Vec<f64> = (0..10).map(|i| i as f64).collect();
+        assert_eq!(*first.lock().unwrap(), expected);
+        assert_eq!(*second.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_cancelled_subscriber_stops_receiving() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_clone = seen.clone();
+        let handle = client
+            .subscribe(move |packet| seen_clone.lock().unwrap().push(packet.timestamp_seconds));
+        client.start().unwrap();
+
+        device.inject_framed_packet(data_packet_with_timestamp(1.0));
+        client
+            .get_data_packets(Some(Duration::from_millis(100)))
+            .unwrap();
+        assert!(handle.is_active());
+
+        handle.cancel();
+        device.inject_framed_packet(data_packet_with_timestamp(2.0));
+        client
+            .get_data_packets(Some(Duration::from_millis(100)))
+            .unwrap();
+
+        assert_eq!(*seen.lock().unwrap(), vec![1.0]);
+        assert!(!handle.is_active());
+    }
+
+    #[test]
+    fn test_on_threshold_reports_each_crossing_once_per_direction() {
+        use thresholds::Crossing;
+
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let events_clone = events.clone();
+        // Callouts every 500 ft.
+        let thresholds = vec![152.4, 304.8, 457.2];
+        client
+            .on_threshold(
+                "est_position_z_meters",
+                thresholds.clone(),
+                ThresholdDirection::Both,
+                5.0,
+                move |event| {
+                    events_clone
+                        .lock()
+                        .unwrap()
+                        .push((event.threshold, event.crossing));
+                },
+            )
+            .unwrap();
+        assert!(
+            client
+                .on_threshold("altitude", vec![], ThresholdDirection::Both, 0.0, |_| {})
+                .is_err()
+        );
+        client.start().unwrap();
+
+        // Up to 600 m and back down, with +-3 m of noise well inside the hysteresis band.
+        let count = 1200;
+        for i in 0..count {
+            let t = i as f64 * 0.01;
+            let progress = i as f32 / (count - 1) as f32;
+            let truth = 600.0 * (1.0 - (2.0 * progress - 1.0).abs());
+            let mut packet = FIRMData::from_fields(t, [0.0; FIRMData::NUM_F32_FIELDS]);
+            packet.est_position_z_meters = truth + 3.0 * (i as f32 * 2.3).sin();
+            device.inject_framed_packet(FramedPacket::new(
+                PacketHeader::Data,
+                0,
+                simulator::data_payload(&packet),
+            ));
+        }
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while client.stats().packets_parsed < count as u64 {
+            assert!(Instant::now() < deadline, "packets were not parsed in time");
+            thread::sleep(Duration::from_millis(5));
+        }
+        client.stop();
+
+        let mut expected: Vec<(f32, Crossing)> =
+            thresholds.iter().map(|&t| (t, Crossing::Rising)).collect();
+        expected.extend(thresholds.iter().rev().map(|&t| (t, Crossing::Falling)));
+        assert_eq!(*events.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_panicking_subscriber_is_reported_and_removed() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+
+        let handle = client.subscribe(|_| panic!("subscriber failure"));
+        client.start().unwrap();
+
+        device.inject_framed_packet(data_packet_with_timestamp(1.0));
+        device.inject_framed_packet(data_packet_with_timestamp(2.0));
+        let received = client
+            .iter_packets_timeout(Duration::from_millis(100))
+            .count();
+
+        // The reader thread survives the panic and keeps delivering packets.
+        assert_eq!(received, 2);
+        assert!(client.is_running());
+        assert!(!handle.is_active());
+
+        let error = client.check_error().unwrap();
+        assert_eq!(
+            error.kind,
+            FIRMClientError::CallbackPanicked {
+                callback: "Packet subscriber".to_string(),
+                message: "subscriber failure".to_string()
+            }
+        );
+        assert!(client.check_error().is_none());
+    }
+
+    /// Injects 30 seconds of 10 Hz packets and waits until the reader thread has parsed them all,
+    /// simulating a consumer that stalled while the device kept streaming.
+    fn data_packet_with_pressure(timestamp_seconds: f64, pressure_pascals: f32) -> FramedPacket {
+        let mut data = FIRMData::from_fields(timestamp_seconds, [0.0; FIRMData::NUM_F32_FIELDS]);
+        data.pressure_pascals = pressure_pascals;
+        FramedPacket::new(PacketHeader::Data, 0, simulator::data_payload(&data))
+    }
+
+    /// Injects `count` packets alternating 10 Pa either side of `pressure_pascals`, after a
+    /// short delay so the caller can start zeroing first.
+    fn inject_noisy_pressure(
+        device: &mock_serial::MockDeviceHandle,
+        pressure_pascals: f32,
+        count: usize,
+    ) -> thread::JoinHandle<()> {
+        let device = device.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            for i in 0..count {
+                let noise = if i % 2 == 0 { 10.0 } else { -10.0 };
+                device.inject_framed_packet(data_packet_with_pressure(
+                    i as f64,
+                    pressure_pascals + noise,
+                ));
+            }
+        })
+    }
+
+    /// Skips ahead to the packet stamped `timestamp_seconds` and returns its altitude, since
+    /// the last zeroing packets may still be on their way when zeroing returns.
+    fn altitude_of_packet_at(client: &FIRMClient, timestamp_seconds: f64) -> f32 {
+        client
+            .iter_packets_timeout(Duration::from_millis(200))
+            .find(|packet| packet.timestamp_seconds == timestamp_seconds)
+            .unwrap()
+            .pressure_altitude_meters
+    }
+
+    #[test]
+    fn test_zero_out_pressure_altitude_averages_and_rebaselines() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        assert!(
+            client
+                .zero_out_pressure_altitude(50, Duration::from_millis(100))
+                .is_err()
+        );
+        client.start().unwrap();
+        // Nothing is streaming, so there's nothing to average.
+        assert_eq!(
+            client
+                .zero_out_pressure_altitude(50, Duration::from_millis(100))
+                .unwrap(),
+            None
+        );
+
+        let injector = inject_noisy_pressure(&device, 100_000.0, 50);
+        let first = client
+            .zero_out_pressure_altitude(50, Duration::from_secs(2))
+            .unwrap()
+            .unwrap();
+        injector.join().unwrap();
+        assert!((first - 110.9).abs() < 0.5, "{first}");
+        device.inject_framed_packet(data_packet_with_pressure(100.0, 100_000.0));
+        assert!(altitude_of_packet_at(&client, 100.0).abs() < 0.1);
+
+        // Zeroing again replaces the reference.
+        let injector = inject_noisy_pressure(&device, 99_000.0, 50);
+        let second = client
+            .zero_out_pressure_altitude(50, Duration::from_secs(2))
+            .unwrap()
+            .unwrap();
+        injector.join().unwrap();
+        assert!(second > first + 50.0, "{first} -> {second}");
+        assert_eq!(client.ground_reference_altitude(), Some(second));
+
+        client.set_sea_level_pressure(102_000.0);
+        assert_eq!(client.ground_reference_altitude(), None);
+        device.inject_framed_packet(data_packet_with_pressure(200.0, 102_000.0));
+        assert!(altitude_of_packet_at(&client, 200.0).abs() < 0.1);
+    }
+
+    /// Polls `get_alarm_events` until one arrives or `timeout` passes.
+    fn wait_for_alarm_event(client: &FIRMClient, timeout: Duration) -> Option<AlarmEvent> {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if let Some(event) = client.get_alarm_events().into_iter().next() {
+                return Some(event);
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        None
+    }
+
+    #[test]
+    fn test_packet_rate_alarm_is_raised_and_cleared_by_reader_thread() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        client.set_alarm_rules(vec![AlarmRule::packet_rate_below(
+            100.0,
+            0.8,
+            Duration::ZERO,
+        )]);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_clone = seen.clone();
+        client.on_alarm(move |event| seen_clone.lock().unwrap().push(event.is_raised()));
+        client.start().unwrap();
+
+        // Nothing is streaming yet, so the rate is zero.
+        let raised = wait_for_alarm_event(&client, Duration::from_secs(2)).unwrap();
+        assert!(raised.is_raised());
+        assert_eq!(client.active_alarms(), vec!["packet_rate_low".to_string()]);
+
+        for i in 0..200 {
+            device.inject_framed_packet(data_packet_with_timestamp(i as f64));
+        }
+        let cleared = wait_for_alarm_event(&client, Duration::from_secs(2)).unwrap();
+        assert!(!cleared.is_raised());
+        assert_eq!(*seen.lock().unwrap(), vec![true, false]);
+        client.stop();
+    }
+
+    fn stall_consumer(client: &FIRMClient, device: &mock_serial::MockDeviceHandle) {
+        for i in 0..=300 {
+            device.inject_framed_packet(data_packet_with_timestamp(i as f64 / 10.0));
+        }
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while f64::from_bits(client.newest_timestamp_bits.load(Ordering::Relaxed)) < 30.0 {
+            assert!(Instant::now() < deadline, "reader thread did not catch up");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_max_packet_age_drops_backlog_after_stall() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        client.set_max_packet_age(Some(Duration::from_secs(1)));
+
+        let subscriber_count = Arc::new(AtomicU64::new(0));
+        let subscriber_count_clone = subscriber_count.clone();
+        client.subscribe(move |_| {
+            subscriber_count_clone.fetch_add(1, Ordering::Relaxed);
+        });
+        client.start().unwrap();
+
+        stall_consumer(&client, &device);
+
+        let packets = client.get_data_packets(None).unwrap();
+        assert_eq!(packets.len(), 11);
+        assert!(packets.iter().all(|p| 30.0 - p.timestamp_seconds <= 1.0));
+        assert_eq!(client.aged_out_count(), 290);
+
+        // Subscribers are exempt from the policy.
+        assert_eq!(subscriber_count.load(Ordering::Relaxed), 301);
+    }
+
+    #[test]
+    fn test_max_packet_age_restarts_with_the_device_clock() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        client.set_max_packet_age(Some(Duration::from_secs(1)));
+        client.start().unwrap();
+        stall_consumer(&client, &device);
+        assert_eq!(client.get_data_packets(None).unwrap().len(), 11);
+
+        // The device rebooted, so its timestamps start over near zero.
+        for t in [0.5, 0.6, 0.7] {
+            device.inject_framed_packet(data_packet_with_timestamp(t));
+        }
+        let timestamps: Vec<f64> = client
+            .iter_packets_timeout(Duration::from_millis(200))
+            .map(|packet| packet.timestamp_seconds)
+            .collect();
+        assert_eq!(timestamps, [0.5, 0.6, 0.7]);
+
+        // Restarting the reader forgets the newest timestamp too, as after
+        // `reboot_and_reconnect`.
+        device.inject_framed_packet(data_packet_with_timestamp(20.0));
+        assert_eq!(
+            client
+                .iter_packets_timeout(Duration::from_millis(200))
+                .count(),
+            1
+        );
+        client.stop();
+        client.start().unwrap();
+        for t in [10.0, 10.1] {
+            device.inject_framed_packet(data_packet_with_timestamp(t));
+        }
+        let timestamps: Vec<f64> = client
+            .iter_packets_timeout(Duration::from_millis(200))
+            .map(|packet| packet.timestamp_seconds)
+            .collect();
+        assert_eq!(timestamps, [10.0, 10.1]);
+        assert_eq!(client.aged_out_count(), 290);
+    }
+
+    #[test]
+    fn test_flush_stale_returns_fresh_packets() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        client.start().unwrap();
+
+        stall_consumer(&client, &device);
+
+        client.set_max_packet_age(Some(Duration::from_millis(500)));
+        let fresh = client.flush_stale();
+        let timestamps: Vec<f64> = fresh.iter().map(|p| p.timestamp_seconds).collect();
+        assert_eq!(timestamps, vec![29.5, 29.6, 29.7, 29.8, 29.9, 30.0]);
+        assert_eq!(client.aged_out_count(), 295);
+        assert!(client.get_data_packets(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_iter_packets_ends_when_not_running() {
+        let (client, _device) = FIRMClient::new_mock(0.01);
+        assert!(client.iter_packets().next().is_none());
+    }
+
+    #[test]
+    fn test_baro_packets_interleaved_with_data_packets() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        client.set_sea_level_pressure(100_000.0);
+        client.start().unwrap();
+
+        let mut expected_baro = Vec::new();
+        for i in 0..20 {
+            let t = i as f64 * 0.01;
+            let mut data = FIRMData::from_fields(t, [0.0; FIRMData::NUM_F32_FIELDS]);
+            data.pressure_pascals = 99_000.0;
+            let baro = FIRMBaroPacket {
+                timestamp_seconds: t,
+                pressure_pascals: 99_100.0,
+                temperature_celsius: 20.0,
+            };
+            device.inject_framed_packet(baro.to_frame());
+            device.inject_framed_packet(FramedPacket::new(
+                PacketHeader::Data,
+                0,
+                crate::simulator::data_payload(&data),
+            ));
+            expected_baro.push(baro);
+        }
+
+        let packets: Vec<FIRMData> = client
+            .iter_packets_timeout(Duration::from_millis(200))
+            .take(20)
+            .collect();
+        assert_eq!(packets.len(), 20);
+        let fused = altitude::pressure_altitude_meters(99_050.0, 100_000.0);
+        for packet in &packets {
+            assert_eq!(packet.pressure_pascals, 99_000.0);
+            assert!((packet.pressure_altitude_meters - fused).abs() < 1e-3);
+        }
+        assert_eq!(client.get_baro_packets(None).unwrap(), expected_baro);
+        assert_eq!(client.stats().packets_parsed, 20);
+    }
+
+    #[test]
+    fn test_read_first_packet_over_mock_serial() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+
+        let mut payload = vec![0u8; 120];
+        payload[0..8].copy_from_slice(&2.5f64.to_le_bytes());
+        device.inject_framed_packet(FramedPacket::new(PacketHeader::Data, 0, payload));
+
+        let packet = client
+            .read_first_packet(Duration::from_millis(200))
+            .unwrap();
+        assert_eq!(packet.timestamp_seconds, 2.5);
+        assert!(!client.is_running());
+    }
+
+    #[test]
+    fn test_read_first_packet_times_out() {
+        let (mut client, _device) = FIRMClient::new_mock(0.01);
+
+        let err = client
+            .read_first_packet(Duration::from_millis(50))
+            .unwrap_err();
+        let io_err = err.downcast_ref::<io::Error>().unwrap();
+        assert_eq!(io_err.kind(), io::ErrorKind::TimedOut);
+        assert!(!client.is_running());
+    }
+
+    #[test]
+    fn test_read_one_packet_invalid_port() {
+        let result = read_one_packet(
+            Some("invalid_port_name"),
+            2_000_000,
+            Duration::from_millis(50),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_read_one_packet_needs_a_port_when_there_are_several() {
+        let ports = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+
+        let port = choose_only_port(ports(&["COM8"]), ports(&["COM1", "COM8"])).unwrap();
+        assert_eq!(port, "COM8");
+        let port = choose_only_port(ports(&[]), ports(&["/dev/ttyACM0"])).unwrap();
+        assert_eq!(port, "/dev/ttyACM0");
+
+        let error = choose_only_port(ports(&["COM8", "COM9"]), ports(&[])).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(error.to_string().contains("COM8, COM9"), "{error}");
+        let error = choose_only_port(ports(&[]), ports(&["COM1", "COM3"])).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        let error = choose_only_port(ports(&[]), ports(&[])).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_missing_serial_port_keeps_error_kind() {
+        let Err(error) = FIRMClient::new("/dev/firm_does_not_exist", 2_000_000, 0.1) else {
+            panic!("opened a port that doesn't exist");
+        };
+        assert!(
+            matches!(&error, FIRMClientError::SerialOpen { port, .. } if port == "/dev/firm_does_not_exist"),
+            "{error:?}"
+        );
+        assert_eq!(error.io_kind(), Some(io::ErrorKind::NotFound));
+    }
+
+    #[test]
+    fn test_get_response_packet_over_mock_serial() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        client.start().unwrap();
+
+        let payload = [1u8];
+
+        let bytes = FramedPacket::new(
+            PacketHeader::Response,
+            FIRMCommand::SetDeviceConfig.to_u16(),
+            payload.to_vec(),
+        )
+        .to_bytes();
+        let response_packet = FIRMResponsePacket::from_bytes(&bytes).unwrap();
+
+        device.inject_framed_packet(response_packet.frame().clone());
+
+        let packet = client
+            .get_response_packets(Some(Duration::from_millis(100)))
+            .unwrap();
+
+        // Make sure we didn't get any other type of packets
+        assert!(matches!(
+            client.get_data_packets(Some(Duration::from_millis(10))),
+            Err(RecvTimeoutError::Timeout)
+        ));
+
+        // Make sure we got the expected response
+        assert_eq!(packet.len(), payload.len());
+        assert_eq!(packet[0], FIRMResponse::SetDeviceConfig(true));
+
+        // Make sure we didn't get any extra response packets
+        assert!(matches!(
+            client.get_response_packets(Some(Duration::from_millis(10))),
+            Err(RecvTimeoutError::Timeout)
+        ));
+    }
+
+    #[test]
+    fn test_set_device_config_command() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        client.start().unwrap();
+
+        // Prepare the response packet to be injected
+        let response_payload = [1u8]; // Acknowledgement byte
+        let response_packet = FramedPacket::new(
+            PacketHeader::Response,
+            FIRMCommand::SetDeviceConfig.to_u16(),
+            response_payload.to_vec(),
+        );
+        device.inject_framed_packet(response_packet);
+
+        // Send the set device config command
+        let result = client.set_device_config(
+            "TestDevice".to_string(),
+            100,
+            DeviceProtocol::UART,
+            Duration::from_millis(100),
+        );
+
+        // Verify the result
+        assert_eq!(result.unwrap(), Some(true));
+    }
+
+    #[test]
+    fn test_set_device_config_verified_round_trips_name_and_frequency() {
+        use crate::simulator::DeviceSimulator;
+
+        let (mut client, device) = FIRMClient::new_mock(0.001);
+        let _simulator = DeviceSimulator::new(200.0).spawn(device);
+        client.start().unwrap();
+        let timeout = Duration::from_secs(1);
+
+        // Names longer than 32 bytes are cut by the set command, and verification expects that.
+        let long_name = "A very long flight computer name that won't fit".to_string();
+        let config = DeviceConfig {
+            name: long_name.clone(),
+            frequency: 250,
+            protocol: DeviceProtocol::UART,
+            suspect: false,
+        };
+        assert_eq!(
+            client.set_device_config_verified(&config, timeout).unwrap(),
+            Some(true)
+        );
+        let applied = client.get_device_config(timeout).unwrap().unwrap();
+        assert_eq!(applied.name, long_name[..DEVICE_NAME_LENGTH]);
+
+        for frequency in [1, 100, 1000, u16::MAX] {
+            let config = DeviceConfig {
+                name: "Freq".to_string(),
+                frequency,
+                protocol: DeviceProtocol::USB,
+                suspect: false,
+            };
+            assert_eq!(
+                client.set_device_config_verified(&config, timeout).unwrap(),
+                Some(true)
+            );
+            let suspect = frequency > MAX_FREQUENCY_HZ;
+            assert_eq!(
+                client.get_device_config(timeout).unwrap(),
+                Some(DeviceConfig { suspect, ..config })
+            );
+        }
+    }
+
+    #[test]
+    fn test_set_device_config_verified_reports_nak_and_timeout() {
+        use crate::response_script::{ResponseBehavior, ResponseScript};
+        use crate::simulator::DeviceSimulator;
+
+        let config = DeviceConfig {
+            name: "Nak".to_string(),
+            frequency: 100,
+            protocol: DeviceProtocol::USB,
+            suspect: false,
+        };
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        client.start().unwrap();
+        device.inject_framed_packet(FramedPacket::new(
+            PacketHeader::Response,
+            FIRMCommand::SetDeviceConfig.to_u16(),
+            vec![0],
+        ));
+        assert_eq!(
+            client
+                .set_device_config_verified(&config, Duration::from_millis(100))
+                .unwrap(),
+            Some(false)
+        );
+
+        let script = ResponseScript::default()
+            .with_rule(Some(FIRMCommand::GetDeviceConfig), ResponseBehavior::Drop);
+        let (mut client, device) = FIRMClient::new_mock(0.001);
+        let _simulator = DeviceSimulator::new(200.0)
+            .with_response_script(script)
+            .spawn(device);
+        client.start().unwrap();
+        assert_eq!(
+            client
+                .set_device_config_verified(&config, Duration::from_millis(100))
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_get_device_info_command() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        client.start().unwrap();
+
+        let id = 0x1122334455667788u64;
+        let mut payload = vec![0u8; DEVICE_ID_LENGTH + FIRMWARE_VERSION_LENGTH];
+        payload[0..DEVICE_ID_LENGTH].copy_from_slice(&id.to_le_bytes());
+        let fw_bytes = str_to_bytes::<FIRMWARE_VERSION_LENGTH>("v1.2.3");
+        payload[DEVICE_ID_LENGTH..DEVICE_ID_LENGTH + FIRMWARE_VERSION_LENGTH]
+            .copy_from_slice(&fw_bytes);
+
+        let response_packet = FramedPacket::new(
+            PacketHeader::Response,
+            FIRMCommand::GetDeviceInfo.to_u16(),
+            payload,
+        );
+        device.inject_framed_packet(response_packet);
+
+        let result = client.get_device_info(Duration::from_millis(100));
+
+        assert_eq!(
+            result.unwrap(),
+            Some(DeviceInfo {
+                firmware_version: "v1.2.3".to_string(),
+                id,
+            })
+        );
+    }
+
+    /// Answers the first command written to it with `reply`, then reads out nothing but
+    /// timeouts.
+    struct ScriptedTransport {
+        reply: Vec<u8>,
+        pending: io::Cursor<Vec<u8>>,
+    }
+
+    impl Read for ScriptedTransport {
+        fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+            match self.pending.read(out)? {
+                0 => {
+                    thread::sleep(Duration::from_millis(1));
+                    Err(io::ErrorKind::TimedOut.into())
+                }
+                n => Ok(n),
+            }
+        }
+    }
+
+    impl Write for ScriptedTransport {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            if !self.reply.is_empty() {
+                self.pending = io::Cursor::new(std::mem::take(&mut self.reply));
+            }
+            Ok(bytes.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Transport for ScriptedTransport {}
+
+    #[test]
+    fn test_get_device_info_skips_interleaved_packets() {
+        let mut payload = 7u64.to_le_bytes().to_vec();
+        payload.extend_from_slice(&str_to_bytes::<FIRMWARE_VERSION_LENGTH>("v2.0.0"));
+        let info = FramedPacket::new(
+            PacketHeader::Response,
+            FIRMCommand::GetDeviceInfo.to_u16(),
+            payload,
+        );
+        let unrelated = FramedPacket::new(
+            PacketHeader::Response,
+            FIRMCommand::Cancel.to_u16(),
+            vec![1],
+        );
+
+        // Data packets around and between the replies, all in one stream.
+        let mut reply = Vec::new();
+        for (i, frame) in [None, Some(&unrelated), None, Some(&info), None]
+            .into_iter()
+            .enumerate()
+        {
+            for j in 0..3 {
+                reply.extend(data_packet_with_timestamp((i * 3 + j) as f64).to_bytes());
+            }
+            if let Some(frame) = frame {
+                reply.extend(frame.to_bytes());
+            }
+        }
+        let transport = ScriptedTransport {
+            reply,
+            pending: io::Cursor::new(Vec::new()),
+        };
+        let mut client = FIRMClient::from_transport(Box::new(transport));
+        client.start().unwrap();
+
+        let result = client.get_device_info(Duration::from_secs(1)).unwrap();
+        assert_eq!(
+            result,
+            Some(DeviceInfo {
+                firmware_version: "v2.0.0".to_string(),
+                id: 7,
+            })
+        );
+        // The unrelated ack is kept for whoever asks for it.
+        assert_eq!(client.buffered_response_count(), 1);
+
+        let timestamps: Vec<f64> = client
+            .iter_packets_timeout(Duration::from_millis(100))
+            .take(15)
+            .map(|packet| packet.timestamp_seconds)
+            .collect();
+        assert_eq!(timestamps, (0..15).map(|i| i as f64).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_send_frame_round_trips_custom_identifier() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        client.set_raw_frame_tap(true);
+        client.start().unwrap();
+
+        client
+            .send_frame(PacketHeader::Command, 0x0042, &[1, 2, 3])
+            .unwrap();
+
+        // Echo the prototype command back as a response with the same identifier.
+        let sent = device
+            .wait_for_command_frame(Duration::from_millis(200))
+            .unwrap()
+            .expect("expected a frame from the client");
+        assert_eq!(sent.header(), PacketHeader::Command);
+        device.inject_framed_packet(FramedPacket::new(
+            PacketHeader::Response,
+            sent.identifier(),
+            sent.payload().to_vec(),
+        ));
+
+        let frames = client
+            .get_raw_frames(Some(Duration::from_millis(200)))
+            .unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].identifier(), 0x0042);
+        assert_eq!(frames[0].payload(), &[1, 2, 3]);
+        assert!(client.get_response_packets(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_send_frame_rejects_oversized_payload() {
+        let (client, _device) = FIRMClient::new_mock(0.01);
+        let payload = vec![0u8; MAX_COMMAND_PAYLOAD_LENGTH + 1];
+
+        let err = client
+            .send_frame(PacketHeader::Command, 0x0042, &payload)
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<FrameError>(),
+            Some(&FrameError::PayloadTooLarge {
+                max: MAX_COMMAND_PAYLOAD_LENGTH,
+                got: MAX_COMMAND_PAYLOAD_LENGTH + 1,
+            })
+        );
+    }
+
+    #[test]
+    fn test_raw_frames_are_dropped_without_tap() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        client.start().unwrap();
+
+        device.inject_framed_packet(FramedPacket::new(PacketHeader::Response, 0x0042, vec![]));
+        device.inject_framed_packet(data_packet_with_timestamp(1.0));
+        client
+            .get_data_packets(Some(Duration::from_millis(200)))
+            .unwrap();
+
+        assert!(client.get_raw_frames(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_raw_frame_tap_keeps_the_newest_frames_and_counts_the_rest() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        client.set_raw_frame_tap(true);
+        client.start().unwrap();
+
+        // Inject in batches the parser's own raw frame queue can hold, waiting for each batch
+        // to reach the tap before sending the next, so only the tap has to drop anything. The
+        // reader hands over a read's data packets before its raw frames, so a frame has reached
+        // the tap once the data packet after its batch's has arrived.
+        let overflow = 5;
+        let identifiers: Vec<u16> = (0..(FIRMClient::MAX_TAPPED_RAW_FRAMES + overflow) as u16)
+            .map(|identifier| 0x1000 + identifier)
+            .collect();
+        for (batch, identifiers) in identifiers
+            .chunks(SerialParser::MAX_RAW_FRAMES / 2)
+            .enumerate()
+        {
+            for &identifier in identifiers {
+                device.inject_framed_packet(FramedPacket::new(
+                    PacketHeader::Response,
+                    identifier,
+                    vec![],
+                ));
+            }
+            device.inject_framed_packet(data_packet_with_timestamp(batch as f64));
+            client
+                .get_data_packets(Some(Duration::from_millis(500)))
+                .unwrap();
+        }
+        device.inject_framed_packet(data_packet_with_timestamp(1000.0));
+        client
+            .get_data_packets(Some(Duration::from_millis(500)))
+            .unwrap();
+
+        let frames = client.get_raw_frames(None).unwrap();
+        assert_eq!(frames.len(), FIRMClient::MAX_TAPPED_RAW_FRAMES);
+        assert_eq!(frames[0].identifier(), 0x1000 + overflow as u16);
+        assert_eq!(client.stats().raw_frames_dropped, overflow as u64);
+    }
+
+    #[test]
+    fn test_send_raw_bytes_are_written_unchanged() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        client.start().unwrap();
+
+        let frame = FramedPacket::new(PacketHeader::Command, 0x0043, vec![9]);
+        client.send_raw_bytes(&frame.to_bytes()).unwrap();
+
+        let sent = device
+            .wait_for_command_frame(Duration::from_millis(200))
+            .unwrap();
+        assert_eq!(sent, Some(frame));
+    }
+
+    /// A device whose TX is wedged: it only sends half a frame over and over until the step
+    /// in `unstick_on` happens, then streams valid data packets. Every line control and write
+    /// is logged in order.
+    struct WedgedTransport {
+        unstick_on: RecoveryStep,
+        unstuck: bool,
+        log: Arc<Mutex<Vec<&'static str>>>,
+        next_timestamp: f64,
+    }
+
+    impl WedgedTransport {
+        fn new(unstick_on: RecoveryStep) -> (Self, Arc<Mutex<Vec<&'static str>>>) {
+            let log = Arc::new(Mutex::new(Vec::new()));
+            let transport = Self {
+                unstick_on,
+                unstuck: false,
+                log: log.clone(),
+                next_timestamp: 0.0,
+            };
+            (transport, log)
+        }
+    }
+
+    impl Read for WedgedTransport {
+        fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+            thread::sleep(Duration::from_millis(2));
+            let bytes = data_packet_with_timestamp(self.next_timestamp).to_bytes();
+            let bytes = if self.unstuck {
+                self.next_timestamp += 0.01;
+                &bytes[..]
+            } else {
+                &bytes[..bytes.len() / 2]
+            };
+            out[..bytes.len()].copy_from_slice(bytes);
+            Ok(bytes.len())
+        }
+    }
+
+    impl Write for WedgedTransport {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            self.log.lock().unwrap().push("write");
+            self.unstuck |= self.unstick_on == RecoveryStep::Cancel;
+            Ok(bytes.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Transport for WedgedTransport {
+        fn send_break(&mut self, _duration: Duration) -> io::Result<()> {
+            self.log.lock().unwrap().push("break");
+            self.unstuck |= self.unstick_on == RecoveryStep::Break;
+            Ok(())
+        }
+
+        fn clear_buffers(&mut self, _direction: ClearBuffer) -> io::Result<()> {
+            self.log.lock().unwrap().push("purge");
+            Ok(())
+        }
+    }
+
+    /// A mock serial port that logs every reconfiguration call in order.
+    struct ReconfiguringTransport {
+        port: Box<dyn SerialPort>,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Read for ReconfiguringTransport {
+        fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+            self.port.read(out)
+        }
+    }
+
+    impl Write for ReconfiguringTransport {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            self.port.write(bytes)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.port.flush()
+        }
+    }
+
+    impl Transport for ReconfiguringTransport {
+        fn set_baud_rate(&mut self, baud_rate: u32) -> io::Result<()> {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("set_baud_rate {baud_rate}"));
+            self.port.set_baud_rate(baud_rate)?;
+            Ok(())
+        }
+
+        fn clear_buffers(&mut self, direction: ClearBuffer) -> io::Result<()> {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("clear {direction:?}"));
+            self.port.clear(direction)?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_set_baud_rate_reconfigures_between_reads() {
+        let (port, device) = mock_serial::MockSerialPort::pair(Duration::from_millis(5));
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let transport = ReconfiguringTransport {
+            port,
+            log: log.clone(),
+        };
+        let mut client = FIRMClient::from_transport(Box::new(transport));
+        client.start().unwrap();
+        device.inject_framed_packet(data_packet_with_timestamp(1.0));
+        let before = client
+            .get_data_packets(Some(Duration::from_millis(200)))
+            .unwrap();
+        assert_eq!(before[0].timestamp_seconds, 1.0);
+
+        // Half a frame received at the old rate is dropped by the switch.
+        let stale = data_packet_with_timestamp(2.0).to_bytes();
+        device.inject_bytes(&stale[..stale.len() / 2]);
+        thread::sleep(Duration::from_millis(30));
+        client.set_baud_rate(115_200).unwrap();
+        assert_eq!(
+            *log.lock().unwrap(),
+            ["set_baud_rate 115200", "clear Input"]
+        );
+
+        device.inject_framed_packet(data_packet_with_timestamp(3.0));
+        let after = client
+            .get_data_packets(Some(Duration::from_millis(200)))
+            .unwrap();
+        assert_eq!(after.len(), 1);
+        assert_eq!(after[0].timestamp_seconds, 3.0);
+        assert_eq!(client.stats().bytes_skipped, 0);
+        assert!(client.is_running());
+    }
+
+    #[test]
+    fn test_set_baud_rate_needs_a_serial_port() {
+        let mut client = FIRMClient::from_transport(Box::new(PanickingTransport));
+        let error = client.set_baud_rate(115_200).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<FIRMClientError>(),
+            Some(&FIRMClientError::NotSupported {
+                operation: "Changing the baud rate",
+                connection: "custom",
+            })
+        );
+        assert!(client.set_baud_rate(0).is_err());
+    }
+
+    /// A pin change: the line, its new level and when it changed.
+    type PinChange = (&'static str, bool, Instant);
+
+    /// A mock serial port that logs each DTR and RTS change.
+    struct PinTransport {
+        port: Box<dyn SerialPort>,
+        log: Arc<Mutex<Vec<PinChange>>>,
+    }
+
+    impl Read for PinTransport {
+        fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+            self.port.read(out)
+        }
+    }
+
+    impl Write for PinTransport {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            self.port.write(bytes)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.port.flush()
+        }
+    }
+
+    impl Transport for PinTransport {
+        fn set_dtr(&mut self, level: bool) -> io::Result<()> {
+            self.log
+                .lock()
+                .unwrap()
+                .push(("dtr", level, Instant::now()));
+            Ok(())
+        }
+
+        fn set_rts(&mut self, level: bool) -> io::Result<()> {
+            self.log
+                .lock()
+                .unwrap()
+                .push(("rts", level, Instant::now()));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_pulse_reset_drives_dtr_while_streaming() {
+        let (port, device) = mock_serial::MockSerialPort::pair(Duration::from_millis(5));
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let transport = PinTransport {
+            port,
+            log: log.clone(),
+        };
+        let mut client = FIRMClient::from_transport(Box::new(transport));
+        client.set_rts(false).unwrap();
+        client.start().unwrap();
+        device.inject_framed_packet(data_packet_with_timestamp(1.0));
+
+        let pattern = [
+            (false, Duration::from_millis(20)),
+            (true, Duration::from_millis(40)),
+            (false, Duration::ZERO),
+        ];
+        let started = Instant::now();
+        client.pulse_reset(&pattern).unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(60));
+        client.set_dtr(true).unwrap();
+
+        let log = log.lock().unwrap();
+        let levels: Vec<(&str, bool)> = log.iter().map(|&(pin, level, _)| (pin, level)).collect();
+        assert_eq!(
+            levels,
+            [
+                ("rts", false),
+                ("dtr", false),
+                ("dtr", true),
+                ("dtr", false),
+                ("dtr", true)
+            ]
+        );
+        // Each level is held for its duration before the next one.
+        for (i, (_, hold)) in pattern.iter().enumerate().take(2) {
+            assert!(log[i + 2].2 - log[i + 1].2 >= *hold);
+        }
+
+        // Packets from before and after the pattern both come through.
+        device.inject_framed_packet(data_packet_with_timestamp(2.0));
+        let packets: Vec<f64> = client
+            .iter_packets_timeout(Duration::from_millis(200))
+            .take(2)
+            .map(|data| data.timestamp_seconds)
+            .collect();
+        assert_eq!(packets, [1.0, 2.0]);
+        assert!(client.is_running());
+    }
+
+    #[test]
+    fn test_dtr_and_rts_need_a_serial_port() {
+        let mut client = FIRMClient::from_transport(Box::new(PanickingTransport));
+        let not_supported = |operation| FIRMClientError::NotSupported {
+            operation,
+            connection: "custom",
+        };
+        for (result, operation) in [
+            (client.set_dtr(true), "Setting DTR"),
+            (client.set_rts(true), "Setting RTS"),
+            (
+                client.pulse_reset(&[(true, Duration::from_millis(1))]),
+                "Pulsing DTR",
+            ),
+        ] {
+            assert_eq!(
+                result.unwrap_err().downcast_ref::<FIRMClientError>(),
+                Some(&not_supported(operation))
+            );
+        }
+    }
+
+    #[test]
+    fn test_recover_link_stops_at_the_break() {
+        let (transport, log) = WedgedTransport::new(RecoveryStep::Break);
+        let mut client = FIRMClient::from_transport(Box::new(transport));
+        assert!(client.recover_link(Duration::from_millis(50)).is_err());
+        client.start().unwrap();
+
+        let step = client.recover_link(Duration::from_millis(200)).unwrap();
+        assert_eq!(step, Some(RecoveryStep::Break));
+        // The Cancel command was never needed.
+        assert_eq!(*log.lock().unwrap(), ["purge", "break"]);
+        assert!(
+            !client
+                .get_data_packets(Some(Duration::from_millis(100)))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_recover_link_falls_back_to_cancel() {
+        let (transport, log) = WedgedTransport::new(RecoveryStep::Cancel);
+        let mut client = FIRMClient::from_transport(Box::new(transport));
+        client.start().unwrap();
+
+        let step = client.recover_link(Duration::from_millis(100)).unwrap();
+        assert_eq!(step, Some(RecoveryStep::Cancel));
+        assert_eq!(*log.lock().unwrap(), ["purge", "break", "write"]);
+        assert!(client.stats().packets_parsed > 0);
+    }
+
+    /// A mock serial port that disappears on the reboot command, and then fails to reconnect
+    /// `absent_for` times before it's back.
+    struct RebootingTransport {
+        port: Box<dyn SerialPort>,
+        absent_for: usize,
+        reconnects: Arc<AtomicU64>,
+    }
+
+    impl RebootingTransport {
+        fn new(absent_for: usize) -> (Self, mock_serial::MockDeviceHandle, Arc<AtomicU64>) {
+            let (port, device) = mock_serial::MockSerialPort::pair(Duration::from_millis(10));
+            let reconnects = Arc::new(AtomicU64::new(0));
+            let transport = Self {
+                port,
+                absent_for,
+                reconnects: reconnects.clone(),
+            };
+            (transport, device, reconnects)
+        }
+    }
+
+    impl Read for RebootingTransport {
+        fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+            self.port.read(out)
+        }
+    }
+
+    impl Write for RebootingTransport {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            self.port.write(bytes)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.port.flush()
+        }
+    }
+
+    impl Transport for RebootingTransport {
+        fn reconnect(&mut self) -> io::Result<()> {
+            self.reconnects.fetch_add(1, Ordering::Relaxed);
+            if self.absent_for > 0 {
+                self.absent_for -= 1;
+                return Err(io::Error::new(io::ErrorKind::NotFound, "no such device"));
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_reboot_and_reconnect_waits_for_the_port() {
+        use crate::simulator::DeviceSimulator;
+
+        let (transport, device, reconnects) = RebootingTransport::new(3);
+        let mut client = FIRMClient::from_transport(Box::new(transport));
+        let _simulator = DeviceSimulator::new(200.0).spawn(device);
+        client.start().unwrap();
+
+        client.reboot_and_reconnect(Duration::from_secs(2)).unwrap();
+        assert_eq!(reconnects.load(Ordering::Relaxed), 4);
+        assert!(client.is_running());
+        assert!(
+            !client
+                .get_data_packets(Some(Duration::from_millis(100)))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_reboot_and_reconnect_reports_a_missing_port() {
+        let (transport, device, _reconnects) = RebootingTransport::new(usize::MAX);
+        let mut client = FIRMClient::from_transport(Box::new(transport));
+        client.start().unwrap();
+
+        let error = client
+            .reboot_and_reconnect(Duration::from_millis(300))
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<FIRMClientError>(),
+            Some(FIRMClientError::ReconnectTimedOut { .. })
+        ));
+        assert_eq!(
+            device
+                .wait_for_command_identifier(Duration::from_millis(50))
+                .unwrap(),
+            Some(FIRMCommand::Reboot.to_u16())
+        );
+        assert!(!client.is_running());
+    }
+
+    #[test]
+    fn test_reboot_and_reconnect_reports_a_silent_device() {
+        let (transport, device, _reconnects) = RebootingTransport::new(1);
+        let mut client = FIRMClient::from_transport(Box::new(transport));
+
+        let error = client
+            .reboot_and_reconnect(Duration::from_millis(400))
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<FIRMClientError>(),
+            Some(FIRMClientError::NoPacketsAfterReconnect { .. })
+        ));
+        assert_eq!(
+            device
+                .wait_for_command_identifier(Duration::from_millis(50))
+                .unwrap(),
+            Some(FIRMCommand::Reboot.to_u16())
+        );
+        // It's reconnected, just quiet.
+        assert!(client.is_running());
+    }
+
+    #[test]
+    fn test_break_and_purge_reach_the_serial_port() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        // Stale bytes from before the purge never reach the parser.
+        device.inject_framed_packet(data_packet_with_timestamp(1.0));
+        client.purge_buffers(ClearBuffer::Input).unwrap();
+        client.start().unwrap();
+        assert!(
+            client
+                .get_data_packets(Some(Duration::from_millis(50)))
+                .is_err()
+        );
+
+        client.send_break(Duration::from_millis(1)).unwrap();
+        assert_eq!(device.break_count(), 1);
+        device.inject_framed_packet(data_packet_with_timestamp(2.0));
+        let packets = client
+            .get_data_packets(Some(Duration::from_millis(100)))
+            .unwrap();
+        assert_eq!(packets[0].timestamp_seconds, 2.0);
+
+        // Transports that aren't serial ports can't send breaks.
+        let mut client = FIRMClient::from_read_write(io::empty(), io::sink());
+        let error = client.send_break(Duration::from_millis(1)).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<io::Error>().unwrap().kind(),
+            io::ErrorKind::Unsupported
+        );
+    }
+
+    #[test]
+    fn test_ping_measures_round_trip_without_disturbing_data() {
+        use crate::response_script::{ResponseBehavior, ResponseScript};
+        use crate::simulator::DeviceSimulator;
+
+        let script = ResponseScript::default().with_rule(
+            Some(FIRMCommand::GetDeviceInfo),
+            ResponseBehavior::Delay {
+                min: Duration::from_millis(30),
+                max: Duration::from_millis(30),
+            },
+        );
+        let (mut client, device) = FIRMClient::new_mock(0.001);
+        let _simulator = DeviceSimulator::new(200.0)
+            .with_response_script(script)
+            .spawn(device);
+        client.start().unwrap();
+
+        let round_trip = client.ping(Duration::from_secs(1)).unwrap();
+        assert!(round_trip >= Duration::from_millis(30), "{round_trip:?}");
+        assert!(round_trip < Duration::from_millis(500), "{round_trip:?}");
+
+        let error = client.ping(Duration::from_millis(10)).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<FIRMClientError>(),
+            Some(&FIRMClientError::CommandTimeout {
+                command: FIRMCommand::GetDeviceInfo,
+                timeout: Duration::from_millis(10),
+            })
+        );
+        let packets = client
+            .get_data_packets(Some(Duration::from_millis(100)))
+            .unwrap();
+        assert!(!packets.is_empty());
+        assert!(client.is_running());
+    }
+
+    #[test]
+    fn test_unmatched_responses_are_bounded() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        client.start().unwrap();
+
+        // Acks nobody is waiting for, e.g. replies to commands that already timed out.
+        for _ in 0..200 {
+            device.inject_framed_packet(FramedPacket::new(
+                PacketHeader::Response,
+                FIRMCommand::Cancel.to_u16(),
+                vec![1],
+            ));
+        }
+        std::thread::sleep(Duration::from_millis(50));
+
+        let result = client.get_device_info(Duration::from_millis(20)).unwrap();
+        assert_eq!(result, None);
+        assert_eq!(
+            client.buffered_response_count(),
+            FIRMClient::MAX_BUFFERED_RESPONSES
+        );
+    }
+
+    #[test]
+    fn test_get_device_config_command() {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        client.start().unwrap();
+
+        let name = "TestDevice";
+        let frequency: u16 = 100;
+        let protocol = DeviceProtocol::UART;
+
+        let mut payload = vec![0u8; DEVICE_NAME_LENGTH + FREQUENCY_LENGTH + 1];
+        let name_bytes = str_to_bytes::<DEVICE_NAME_LENGTH>(name);
+        payload[0..DEVICE_NAME_LENGTH].copy_from_slice(&name_bytes);
+        payload[DEVICE_NAME_LENGTH..DEVICE_NAME_LENGTH + FREQUENCY_LENGTH]
+            .copy_from_slice(&frequency.to_le_bytes());
+        payload[DEVICE_NAME_LENGTH + FREQUENCY_LENGTH] = 2;
+
+        let response_packet = FramedPacket::new(
+            PacketHeader::Response,
+            FIRMCommand::GetDeviceConfig.to_u16(),
+            payload,
+        );
         device.inject_framed_packet(response_packet);
 
         let result = client.get_device_config(Duration::from_millis(100));
@@ -1150,14 +5936,34 @@ mod tests {
                 name: name.to_string(),
                 frequency,
                 protocol,
+                suspect: false,
             })
         );
     }
 
+    #[test]
+    fn test_calibration_read_modify_write() {
+        use crate::simulator::DeviceSimulator;
+
+        let (mut client, device) = FIRMClient::new_mock(0.001);
+        let _simulator = DeviceSimulator::new(200.0).spawn(device);
+        client.start().unwrap();
+        let timeout = Duration::from_secs(1);
+
+        let mut calibration = client.get_calibration(timeout).unwrap().unwrap();
+        calibration.magnetometer_offsets = [10.0, -20.0, 30.0];
+        calibration.imu_gyroscope_scale_matrix[4] = 1.5;
+        assert_eq!(
+            client.set_calibration(&calibration, timeout).unwrap(),
+            Some(true)
+        );
+        assert_eq!(client.get_calibration(timeout).unwrap(), Some(calibration));
+    }
+
     #[test]
     fn test_get_calibration_command() {
         let (mut client, device) = FIRMClient::new_mock(0.01);
-        client.start();
+        client.start().unwrap();
 
         let expected = CalibrationValues {
             imu_accelerometer_offsets: [1.0, 2.0, 3.0],
@@ -1203,7 +6009,7 @@ mod tests {
     #[test]
     fn test_cancel_command() {
         let (mut client, device) = FIRMClient::new_mock(0.01);
-        client.start();
+        client.start().unwrap();
 
         let response_payload = [1u8];
         let response_packet = FramedPacket::new(
@@ -0,0 +1,332 @@
+use crate::packet_queue::QueueDepth;
+use firm_core::data_parser::ParserStats;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Snapshot of serial link health, returned by `FIRMClient::stats`.
+///
+/// Counters accumulate from the moment the client is created and survive `stop()`/`start()`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct LinkStats {
+    /// Raw bytes read from the serial port.
+    pub bytes_read: u64,
+    /// Data packets successfully parsed.
+    pub packets_parsed: u64,
+    /// Command responses successfully parsed.
+    pub responses_parsed: u64,
+    /// Frames dropped because their CRC did not match.
+    pub crc_failures: u64,
+    /// Start words dropped because the length after them was longer than any frame.
+    pub length_rejects: u64,
+    /// Start words dropped because their frame wouldn't fit in the parser's buffer.
+    pub overflow_discards: u64,
+    /// Bytes discarded while searching for the next valid frame.
+    pub bytes_skipped: u64,
+    /// Frames that passed their CRC but didn't decode as a known packet.
+    pub decode_failures: u64,
+    /// Times the parser found a valid frame again after skipping bytes.
+    pub resync_events: u64,
+    /// Data packets dropped by the max packet age policy.
+    pub aged_out_packets: u64,
+    /// Data packets dropped by `TimestampPolicy::Strict` for being out of order.
+    pub timestamps_dropped: u64,
+    /// Data packets moved forward by `TimestampPolicy::Nudge`.
+    pub timestamps_nudged: u64,
+    /// Data packets dropped by the filter set with `FIRMClient::set_packet_filter`.
+    pub packets_filtered: u64,
+    /// Data packets that failed `FIRMClient::set_data_validation`, whether dropped or flagged.
+    pub packets_invalid: u64,
+    /// Frames the raw frame tap dropped because `FIRMClient::get_raw_frames` fell behind, see
+    /// `FIRMClient::MAX_TAPPED_RAW_FRAMES`.
+    pub raw_frames_dropped: u64,
+    /// Data packets queued and not yet consumed, see `FIRMClient::backlog`.
+    pub backlog: usize,
+    /// The largest backlog since it was last reset, see `FIRMClient::max_backlog_seen`.
+    pub max_backlog_seen: usize,
+    /// Data packet rate in Hz over the last `LinkCounters::RATE_WINDOW`.
+    pub packet_rate_hz: f64,
+    /// Seconds since the last data packet was parsed, or `None` if none has been yet.
+    pub seconds_since_last_packet: Option<f64>,
+}
+
+/// Counters shared between the reader thread and `FIRMClient::stats`.
+#[derive(Default)]
+pub(crate) struct LinkCounters {
+    bytes_read: u64,
+    packets_parsed: u64,
+    responses_parsed: u64,
+    packets_filtered: u64,
+    packets_invalid: u64,
+    raw_frames_dropped: u64,
+    /// Parser counters from previous runs of the reader thread.
+    previous_parsers: ParserStats,
+    /// Parser counters from the current run of the reader thread.
+    current_parser: ParserStats,
+    /// Arrival times of data packets within the rate window.
+    recent_packets: VecDeque<Instant>,
+    last_packet: Option<Instant>,
+}
+
+impl LinkCounters {
+    /// Sliding window over which `packet_rate_hz` is measured.
+    pub(crate) const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+    /// Records a chunk of bytes read from the port.
+    pub(crate) fn record_bytes(&mut self, bytes_read: usize) {
+        self.bytes_read += bytes_read as u64;
+    }
+
+    /// Records a parsed data packet arriving at `now`.
+    pub(crate) fn record_packet(&mut self, now: Instant) {
+        self.packets_parsed += 1;
+        self.last_packet = Some(now);
+        self.recent_packets.push_back(now);
+        self.prune(now);
+    }
+
+    /// Records a parsed command response.
+    pub(crate) fn record_response(&mut self) {
+        self.responses_parsed += 1;
+    }
+
+    /// Records a data packet dropped by the packet filter.
+    pub(crate) fn record_filtered(&mut self) {
+        self.packets_filtered += 1;
+    }
+
+    /// Records a data packet that failed validation.
+    pub(crate) fn record_invalid(&mut self) {
+        self.packets_invalid += 1;
+    }
+
+    /// Records a frame the raw frame tap had no room for.
+    pub(crate) fn record_raw_frame_dropped(&mut self) {
+        self.raw_frames_dropped += 1;
+    }
+
+    /// Updates the counters of the parser owned by the current reader thread run.
+    pub(crate) fn update_parser(&mut self, stats: ParserStats) {
+        self.current_parser = stats;
+    }
+
+    /// Folds the current parser's counters into the totals, called when a new parser is
+    /// created for a fresh reader thread run.
+    pub(crate) fn start_new_parser(&mut self) {
+        let current = std::mem::take(&mut self.current_parser);
+        self.previous_parsers.crc_failures += current.crc_failures;
+        self.previous_parsers.length_rejects += current.length_rejects;
+        self.previous_parsers.overflow_discards += current.overflow_discards;
+        self.previous_parsers.bytes_skipped += current.bytes_skipped;
+        self.previous_parsers.decode_failures += current.decode_failures;
+        self.previous_parsers.resyncs += current.resyncs;
+        self.previous_parsers.timestamps_dropped += current.timestamps_dropped;
+        self.previous_parsers.timestamps_nudged += current.timestamps_nudged;
+    }
+
+    /// Builds a `LinkStats` snapshot as of `now`.
+    pub(crate) fn snapshot(
+        &mut self,
+        now: Instant,
+        aged_out_packets: u64,
+        queue: &QueueDepth,
+    ) -> LinkStats {
+        self.prune(now);
+        LinkStats {
+            bytes_read: self.bytes_read,
+            packets_parsed: self.packets_parsed,
+            responses_parsed: self.responses_parsed,
+            crc_failures: self.previous_parsers.crc_failures + self.current_parser.crc_failures,
+            length_rejects: self.previous_parsers.length_rejects
+                + self.current_parser.length_rejects,
+            overflow_discards: self.previous_parsers.overflow_discards
+                + self.current_parser.overflow_discards,
+            bytes_skipped: self.previous_parsers.bytes_skipped + self.current_parser.bytes_skipped,
+            decode_failures: self.previous_parsers.decode_failures
+                + self.current_parser.decode_failures,
+            resync_events: self.previous_parsers.resyncs + self.current_parser.resyncs,
+            aged_out_packets,
+            timestamps_dropped: self.previous_parsers.timestamps_dropped
+                + self.current_parser.timestamps_dropped,
+            timestamps_nudged: self.previous_parsers.timestamps_nudged
+                + self.current_parser.timestamps_nudged,
+            packets_filtered: self.packets_filtered,
+            packets_invalid: self.packets_invalid,
+            raw_frames_dropped: self.raw_frames_dropped,
+            backlog: queue.current(),
+            max_backlog_seen: queue.high_water(),
+            packet_rate_hz: self.recent_packets.len() as f64 / Self::RATE_WINDOW.as_secs_f64(),
+            seconds_since_last_packet: self
+                .last_packet
+                .map(|last| now.duration_since(last).as_secs_f64()),
+        }
+    }
+
+    /// Drops packet arrival times that have fallen out of the rate window.
+    fn prune(&mut self, now: Instant) {
+        while let Some(&oldest) = self.recent_packets.front() {
+            if now.duration_since(oldest) <= Self::RATE_WINDOW {
+                break;
+            }
+            self.recent_packets.pop_front();
+        }
+    }
+}
+
+/// Thresholds for the idle watchdog, set by `FIRMClient::set_idle_timeout` and
+/// `FIRMClient::expect_first_packet_within` and read by the reader thread as it runs.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct IdleThresholds {
+    /// Longest gap between data packets before `LinkIdle` is reported.
+    pub(crate) idle: Option<Duration>,
+    /// Longest wait for the first data packet of a run before `LinkIdle` is reported.
+    pub(crate) first_packet: Option<Duration>,
+}
+
+/// The reader thread's side of the idle watchdog, for one run of the thread.
+pub(crate) struct IdleWatchdog {
+    started: Instant,
+    last_packet: Option<Instant>,
+    last_report: Option<Instant>,
+}
+
+impl IdleWatchdog {
+    pub(crate) fn new(now: Instant) -> Self {
+        Self {
+            started: now,
+            last_packet: None,
+            last_report: None,
+        }
+    }
+
+    pub(crate) fn record_packet(&mut self, now: Instant) {
+        self.last_packet = Some(now);
+        self.last_report = None;
+    }
+
+    /// Checks the thresholds at `now`.
+    ///
+    /// # Returns
+    ///
+    /// - `Option<Duration>` - How long the link has been idle, if that's past the threshold
+    ///   and it hasn't been reported within the last threshold's worth of time.
+    pub(crate) fn check(&mut self, thresholds: IdleThresholds, now: Instant) -> Option<Duration> {
+        let (quiet_since, limit) = match self.last_packet {
+            Some(last_packet) => (last_packet, thresholds.idle?),
+            None => (self.started, thresholds.first_packet?),
+        };
+        let since = now.saturating_duration_since(quiet_since);
+        if since < limit
+            || self
+                .last_report
+                .is_some_and(|report| now.saturating_duration_since(report) < limit)
+        {
+            return None;
+        }
+        self.last_report = Some(now);
+        Some(since)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idle_watchdog_waits_for_the_first_packet_and_repeats_once_per_interval() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let idle = IdleThresholds {
+            idle: Some(Duration::from_millis(100)),
+            first_packet: None,
+        };
+        let mut watchdog = IdleWatchdog::new(start);
+        // Nothing before the first packet unless asked for.
+        assert_eq!(watchdog.check(idle, at(1000)), None);
+
+        watchdog.record_packet(at(1000));
+        assert_eq!(watchdog.check(idle, at(1099)), None);
+        assert_eq!(
+            watchdog.check(idle, at(1100)),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(watchdog.check(idle, at(1150)), None);
+        assert_eq!(
+            watchdog.check(idle, at(1200)),
+            Some(Duration::from_millis(200))
+        );
+
+        // A packet clears it, and a looser threshold applies straight away.
+        watchdog.record_packet(at(1300));
+        let loose = IdleThresholds {
+            idle: Some(Duration::from_millis(500)),
+            ..idle
+        };
+        assert_eq!(watchdog.check(loose, at(1700)), None);
+        assert_eq!(watchdog.check(IdleThresholds::default(), at(9000)), None);
+
+        let mut watchdog = IdleWatchdog::new(start);
+        let first = IdleThresholds {
+            first_packet: Some(Duration::from_millis(50)),
+            ..idle
+        };
+        assert_eq!(
+            watchdog.check(first, at(60)),
+            Some(Duration::from_millis(60))
+        );
+    }
+
+    #[test]
+    fn test_packet_rate_uses_sliding_window() {
+        let mut counters = LinkCounters::default();
+        let start = Instant::now();
+        for i in 0..20 {
+            counters.record_packet(start + Duration::from_millis(i * 100));
+        }
+
+        // At t=1.9s only the packets from 0.9s onward are inside the window.
+        let stats = counters.snapshot(
+            start + Duration::from_millis(1900),
+            0,
+            &QueueDepth::default(),
+        );
+        assert_eq!(stats.packets_parsed, 20);
+        assert_eq!(stats.packet_rate_hz, 11.0);
+        assert_eq!(stats.seconds_since_last_packet, Some(0.0));
+
+        let stats = counters.snapshot(start + Duration::from_secs(10), 0, &QueueDepth::default());
+        assert_eq!(stats.packet_rate_hz, 0.0);
+        assert!((stats.seconds_since_last_packet.unwrap() - 8.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_parser_counters_accumulate_across_runs() {
+        let mut counters = LinkCounters::default();
+        let run = ParserStats {
+            packets_parsed: 9,
+            crc_failures: 1,
+            length_rejects: 6,
+            overflow_discards: 7,
+            bytes_skipped: 5,
+            resyncs: 2,
+            timestamps_dropped: 3,
+            timestamps_nudged: 4,
+            packets_dropped: 0,
+            decode_failures: 8,
+        };
+        counters.update_parser(run);
+        counters.start_new_parser();
+        counters.update_parser(run);
+
+        let stats = counters.snapshot(Instant::now(), 0, &QueueDepth::default());
+        assert_eq!(stats.crc_failures, 2);
+        assert_eq!(stats.length_rejects, 12);
+        assert_eq!(stats.overflow_discards, 14);
+        assert_eq!(stats.bytes_skipped, 10);
+        assert_eq!(stats.decode_failures, 16);
+        assert_eq!(stats.resync_events, 4);
+        assert_eq!(stats.timestamps_dropped, 6);
+        assert_eq!(stats.timestamps_nudged, 8);
+        assert_eq!(stats.seconds_since_last_packet, None);
+    }
+}
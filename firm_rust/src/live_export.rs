@@ -0,0 +1,245 @@
+//! Live CSV export of the packets a client receives, see `LiveExporter`.
+//!
+//! Rows are written by a subscriber on the reader thread in the `firm_core::csv` format, and
+//! the file can be rotated from any thread (e.g. a GUI's "new file" button) without losing or
+//! splitting rows. Each file starts with the client's `SessionInfo`, if it has one, as `#`
+//! comment lines above the header row, which `firm_core::csv::parse_csv` skips.
+use crate::{FIRMClient, SessionInfo, SubscriptionHandle};
+use firm_core::csv::{csv_header, write_csv_row};
+use firm_core::firm_packets::FIRMData;
+use firm_core::non_finite::NonFinitePolicy;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Describes a CSV file the exporter finished writing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RotationReceipt {
+    /// The finished file.
+    pub path: PathBuf,
+    /// Data rows written to it, not counting the header.
+    pub rows: u64,
+    /// The file rows go to from now on, or `None` for the receipt from `finish`.
+    pub new_path: Option<PathBuf>,
+}
+
+/// The file currently being written.
+struct ExportFile {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    rows: u64,
+    /// First write error, reported by the next `rotate` or `finish`. Rows after it are
+    /// dropped rather than written to a file that's already missing some.
+    error: Option<io::Error>,
+    row: String,
+}
+
+impl ExportFile {
+    /// Creates `path` and writes the session comments and header row.
+    fn create(path: &Path, session: Option<&SessionInfo>) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        for line in session.map(SessionInfo::header_lines).unwrap_or_default() {
+            writeln!(writer, "# {line}")?;
+        }
+        writeln!(writer, "{}", csv_header())?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer,
+            rows: 0,
+            error: None,
+            row: String::new(),
+        })
+    }
+
+    fn write_row(&mut self, data: &FIRMData) {
+        if self.error.is_some() {
+            return;
+        }
+        self.row.clear();
+        write_csv_row(&mut self.row, data, &NonFinitePolicy::default());
+        match self.writer.write_all(self.row.as_bytes()) {
+            Ok(()) => self.rows += 1,
+            Err(e) => self.error = Some(e),
+        }
+    }
+
+    /// Flushes the file and returns its receipt, or the first error writing it.
+    fn finish(mut self, new_path: Option<PathBuf>) -> io::Result<RotationReceipt> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        self.writer.flush()?;
+        self.writer.get_ref().sync_data()?;
+        Ok(RotationReceipt {
+            path: self.path,
+            rows: self.rows,
+            new_path,
+        })
+    }
+}
+
+/// Writes every data packet a client receives to a CSV file, one row per packet.
+///
+/// `LiveExporter` is `Sync`, so `rotate` can be called from any thread while the reader thread
+/// keeps writing.
+pub struct LiveExporter {
+    /// The current file, taken by `finish`.
+    file: Arc<Mutex<Option<ExportFile>>>,
+    subscription: SubscriptionHandle,
+    /// The client's session when exporting started, written to every file.
+    session: Option<SessionInfo>,
+}
+
+impl LiveExporter {
+    /// Creates `path`, writes the header row and starts writing packets from `client` to it.
+    /// The client's `session_info()` at this point heads this and every rotated file.
+    ///
+    /// # Arguments
+    ///
+    /// - `client` (`&mut FIRMClient`) - The client to export from. It may already be running.
+    /// - `path` (`&Path`) - The CSV file to write. It is created or truncated.
+    pub fn start(client: &mut FIRMClient, path: &Path) -> io::Result<Self> {
+        let session = client.session_info().cloned();
+        let file = Arc::new(Mutex::new(Some(ExportFile::create(
+            path,
+            session.as_ref(),
+        )?)));
+        let writer = file.clone();
+        let subscription = client.subscribe(move |data| {
+            if let Some(file) = writer
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .as_mut()
+            {
+                file.write_row(data);
+            }
+        });
+        Ok(Self {
+            file,
+            subscription,
+            session,
+        })
+    }
+
+    /// Finishes the current file and continues in `new_path`.
+    ///
+    /// The new file is created with its header before the switch, and the switch itself only
+    /// swaps the writer, so packets arriving meanwhile wait for it instead of being dropped.
+    /// Each row lands whole in exactly one of the two files.
+    ///
+    /// # Arguments
+    ///
+    /// - `new_path` (`&Path`) - The file to continue in. It is created or truncated.
+    ///
+    /// # Returns
+    ///
+    /// - `io::Result<RotationReceipt>` - The finished file and its row count. An error creating
+    ///   `new_path` leaves the current file in use; an error finishing the old file is returned
+    ///   after the switch, so the new file is in use either way.
+    pub fn rotate(&self, new_path: &Path) -> io::Result<RotationReceipt> {
+        let next = ExportFile::create(new_path, self.session.as_ref())?;
+        let finished = self.lock().replace(next).expect("exporting until finish");
+        finished.finish(Some(new_path.to_path_buf()))
+    }
+
+    /// Returns the file rows are currently written to.
+    pub fn path(&self) -> PathBuf {
+        self.current(|file| file.path.clone())
+    }
+
+    /// Returns the number of rows written to the current file so far.
+    pub fn rows(&self) -> u64 {
+        self.current(|file| file.rows)
+    }
+
+    /// Stops exporting and finishes the current file.
+    ///
+    /// # Returns
+    ///
+    /// - `io::Result<RotationReceipt>` - The finished file and its row count, or the first
+    ///   error writing it.
+    pub fn finish(self) -> io::Result<RotationReceipt> {
+        self.subscription.cancel();
+        let finished = self.lock().take().expect("exporting until finish");
+        finished.finish(None)
+    }
+
+    fn current<T>(&self, f: impl FnOnce(&ExportFile) -> T) -> T {
+        f(self.lock().as_ref().expect("exporting until finish"))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<ExportFile>> {
+        self.file
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Drop for LiveExporter {
+    fn drop(&mut self) {
+        self.subscription.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator::DeviceSimulator;
+    use firm_core::csv::{CsvImportOptions, parse_csv};
+    use std::thread;
+    use std::time::Duration;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("firm_live_export_{}_{name}", std::process::id()))
+    }
+
+    #[test]
+    fn test_rotating_mid_stream_keeps_every_row() {
+        let (mut client, device) = FIRMClient::new_mock(0.001);
+        let _simulator = DeviceSimulator::new(1000.0).spawn(device);
+
+        let paths: Vec<PathBuf> = (0..4)
+            .map(|i| temp_path(&format!("rotate_{i}.csv")))
+            .collect();
+        let exporter = LiveExporter::start(&mut client, &paths[0]).unwrap();
+        let delivered = Arc::new(Mutex::new(Vec::new()));
+        let seen = delivered.clone();
+        client.subscribe(move |data| seen.lock().unwrap().push(data.timestamp_seconds));
+        client.start().unwrap();
+
+        // Rotate from another thread while packets keep arriving.
+        let mut receipts = thread::scope(|scope| {
+            scope
+                .spawn(|| {
+                    paths[1..]
+                        .iter()
+                        .map(|path| {
+                            thread::sleep(Duration::from_millis(60));
+                            exporter.rotate(path).unwrap()
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .join()
+                .unwrap()
+        });
+        thread::sleep(Duration::from_millis(60));
+        client.stop();
+        receipts.push(exporter.finish().unwrap());
+
+        let mut exported = Vec::new();
+        for (receipt, path) in receipts.iter().zip(&paths) {
+            assert_eq!(&receipt.path, path);
+            assert!(receipt.rows > 0);
+            let text = std::fs::read_to_string(path).unwrap();
+            assert!(text.starts_with(&csv_header()));
+            let rows = parse_csv(&text, CsvImportOptions::default()).unwrap();
+            assert_eq!(rows.len() as u64, receipt.rows);
+            exported.extend(rows.iter().map(|data| data.timestamp_seconds));
+            let _ = std::fs::remove_file(path);
+        }
+        assert_eq!(receipts[0].new_path.as_ref(), Some(&paths[1]));
+        assert_eq!(receipts[3].new_path, None);
+        assert_eq!(exported, *delivered.lock().unwrap());
+    }
+}
@@ -1,10 +1,8 @@
-use firm_core::constants::packet::{
-    CRC_SIZE, HEADER_SIZE, IDENTIFIER_SIZE, LENGTH_SIZE, MIN_PACKET_SIZE,
-};
-use firm_core::framed_packet::FramedPacket;
+use firm_core::framed_packet::{FrameScanner, FramedPacket};
 use serialport::{ClearBuffer, DataBits, FlowControl, Parity, SerialPort, StopBits};
 use std::collections::VecDeque;
 use std::io::{self, Read, Write};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
@@ -16,55 +14,50 @@ struct State {
     client_to_device: Mutex<VecDeque<u8>>,
     /// Simulated read timeout for the mock serial port.
     timeout: Mutex<Duration>,
+    /// When set, reads and writes fail as if the device was unplugged.
+    disconnected: AtomicBool,
+    /// Number of serial breaks the client has sent.
+    breaks: AtomicU64,
 }
 
 #[derive(Clone)]
 pub struct MockDeviceHandle {
     state: Arc<State>,
-    command_buffer: Arc<Mutex<Vec<u8>>>,
+    commands: Arc<Mutex<FrameScanner>>,
 }
 
 impl MockDeviceHandle {
     fn new(state: Arc<State>) -> Self {
         Self {
             state,
-            command_buffer: Arc::new(Mutex::new(Vec::new())),
+            commands: Arc::new(Mutex::new(FrameScanner::new())),
         }
     }
 
     /// Waits for a framed command and returns its identifier, or None on timeout.
     pub fn wait_for_command_identifier(&self, timeout: Duration) -> io::Result<Option<u16>> {
+        Ok(self
+            .wait_for_command_frame(timeout)?
+            .map(|frame| frame.identifier()))
+    }
+
+    /// Waits for a full frame written by the client and returns it, or None on timeout. Bytes
+    /// that aren't part of a valid frame are skipped.
+    pub fn wait_for_command_frame(&self, timeout: Duration) -> io::Result<Option<FramedPacket>> {
         let deadline = Instant::now() + timeout;
-        let mut command_buffer = self.command_buffer.lock().unwrap();
+        let mut commands = self.commands.lock().unwrap();
 
         loop {
             {
                 let mut queue = self.state.client_to_device.lock().unwrap();
-
-                while let Some(byte) = queue.pop_front() {
-                    command_buffer.push(byte);
-                }
+                let (front, back) = queue.as_slices();
+                commands.push(front);
+                commands.push(back);
+                queue.clear();
             }
 
-            if command_buffer.len() >= MIN_PACKET_SIZE {
-                let len_start = HEADER_SIZE + IDENTIFIER_SIZE;
-                if command_buffer.len() >= len_start + LENGTH_SIZE {
-                    let payload_len = u32::from_le_bytes(
-                        command_buffer[len_start..len_start + LENGTH_SIZE]
-                            .try_into()
-                            .unwrap(),
-                    ) as usize;
-                    let frame_len =
-                        HEADER_SIZE + IDENTIFIER_SIZE + LENGTH_SIZE + payload_len + CRC_SIZE;
-                    if command_buffer.len() >= frame_len {
-                        let frame = FramedPacket::from_bytes(&command_buffer[..frame_len])
-                            .map_err(|e| {
-                                io::Error::new(io::ErrorKind::InvalidData, format!("{e:?}"))
-                            })?;
-                        command_buffer.drain(..frame_len);
-                        return Ok(Some(frame.identifier()));
-                    }
-                }
+            if let Some((_, frame)) = commands.next_frame() {
+                return Ok(Some(frame));
             }
 
             if Instant::now() >= deadline {
@@ -80,6 +73,23 @@ impl MockDeviceHandle {
         let mut queue = self.state.device_to_client.lock().unwrap();
         queue.extend(bytes);
     }
+
+    /// Injects raw bytes (e.g. line noise or a corrupted frame) into the client's read stream.
+    pub fn inject_bytes(&self, bytes: &[u8]) {
+        let mut queue = self.state.device_to_client.lock().unwrap();
+        queue.extend(bytes);
+    }
+
+    /// Returns how many serial breaks the client has sent.
+    pub fn break_count(&self) -> u64 {
+        self.state.breaks.load(Ordering::Relaxed)
+    }
+
+    /// Simulates the device being unplugged: every following read and write on the paired
+    /// port fails with `io::ErrorKind::BrokenPipe`.
+    pub fn disconnect(&self) {
+        self.state.disconnected.store(true, Ordering::Relaxed);
+    }
 }
 
 #[derive(Clone)]
@@ -103,10 +113,21 @@ impl MockSerialPort {
     fn timeout(&self) -> Duration {
         *self.state.timeout.lock().unwrap()
     }
+
+    fn check_connected(&self) -> io::Result<()> {
+        if self.state.disconnected.load(Ordering::Relaxed) {
+            return Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "mock device disconnected",
+            ));
+        }
+        Ok(())
+    }
 }
 
 impl Read for MockSerialPort {
     fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        self.check_connected()?;
         let mut queue = self.state.device_to_client.lock().unwrap();
         if queue.is_empty() {
             drop(queue);
@@ -125,6 +146,7 @@ impl Read for MockSerialPort {
 
 impl Write for MockSerialPort {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.check_connected()?;
         let mut queue = self.state.client_to_device.lock().unwrap();
         queue.extend(buf);
         Ok(buf.len())
@@ -199,13 +221,20 @@ impl SerialPort for MockSerialPort {
     fn bytes_to_write(&self) -> serialport::Result<u32> {
         Ok(0)
     }
-    fn clear(&self, _buffer_to_clear: ClearBuffer) -> serialport::Result<()> {
+    fn clear(&self, buffer_to_clear: ClearBuffer) -> serialport::Result<()> {
+        if matches!(buffer_to_clear, ClearBuffer::Input | ClearBuffer::All) {
+            self.state.device_to_client.lock().unwrap().clear();
+        }
+        if matches!(buffer_to_clear, ClearBuffer::Output | ClearBuffer::All) {
+            self.state.client_to_device.lock().unwrap().clear();
+        }
         Ok(())
     }
     fn try_clone(&self) -> serialport::Result<Box<dyn SerialPort>> {
         Ok(Box::new(self.clone()))
     }
     fn set_break(&self) -> serialport::Result<()> {
+        self.state.breaks.fetch_add(1, Ordering::Relaxed);
         Ok(())
     }
     fn clear_break(&self) -> serialport::Result<()> {
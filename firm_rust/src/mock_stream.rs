@@ -0,0 +1,351 @@
+//! Streaming a `.frm` mock log to a device in mock mode, see `FIRMClient::start_mock`.
+//!
+//! The log is parsed with `LogParser` on a background thread and its packets are handed to the
+//! reader thread, which writes them between reads, so data packets keep arriving as usual.
+use anyhow::Result;
+use firm_core::client_packets::FIRMLogPacket;
+use firm_core::constants::log_parsing::{FIRMLogPacketType, HEADER_PARSE_DELAY, HEADER_TOTAL_SIZE};
+use firm_core::log_parsing::LogParser;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How `FIRMClient::start_mock` plays a log back.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MockOptions {
+    /// Pace packets by the log's timestamps. When false they're sent as fast as possible.
+    pub realtime: bool,
+    /// Playback speed multiplier when `realtime` is set, e.g. 2.0 for twice as fast.
+    pub speed: f64,
+    /// Bytes read from the log file at a time.
+    pub chunk_size: usize,
+}
+
+impl Default for MockOptions {
+    fn default() -> Self {
+        Self {
+            realtime: true,
+            speed: 1.0,
+            chunk_size: 8192,
+        }
+    }
+}
+
+/// Packets sent as fast as possible before pacing starts, so the device has a backlog to work
+/// through.
+const PRELOAD_COUNT: usize = 75;
+/// Packets sent between pacing sleeps.
+const BATCH_SIZE: usize = 10;
+
+/// Time source for `Pacer`, so the pacing can be tested without sleeping.
+pub(crate) trait Clock {
+    /// Time since an arbitrary fixed point.
+    fn now(&self) -> Duration;
+
+    /// Sleeps for `duration`, returning early once `stop` is set.
+    fn sleep(&mut self, duration: Duration, stop: &AtomicBool);
+}
+
+pub(crate) struct SystemClock {
+    origin: Instant,
+}
+
+impl SystemClock {
+    pub(crate) fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+
+    fn sleep(&mut self, duration: Duration, stop: &AtomicBool) {
+        let step = Duration::from_millis(10);
+        let mut remaining = duration;
+        while remaining > Duration::ZERO && !stop.load(Ordering::Relaxed) {
+            let s = remaining.min(step);
+            thread::sleep(s);
+            remaining = remaining.saturating_sub(s);
+        }
+    }
+}
+
+/// Keeps a stream on the log's schedule, scaled by `MockOptions::speed`.
+pub(crate) struct Pacer<C: Clock> {
+    clock: C,
+    realtime: bool,
+    speed: f64,
+    /// When the schedule started, by `clock`.
+    start: Duration,
+    /// Log time sent since `start`, in seconds.
+    logged_seconds: f64,
+}
+
+impl<C: Clock> Pacer<C> {
+    pub(crate) fn new(clock: C, options: &MockOptions) -> Self {
+        let start = clock.now();
+        Self {
+            clock,
+            realtime: options.realtime,
+            speed: options.speed,
+            start,
+            logged_seconds: 0.0,
+        }
+    }
+
+    /// Starts the schedule over from now, e.g. after the preload burst.
+    pub(crate) fn restart(&mut self) {
+        self.start = self.clock.now();
+        self.logged_seconds = 0.0;
+    }
+
+    /// Accounts for `delay_seconds` of log time just sent, and sleeps until the stream is back
+    /// on schedule. A stream running behind doesn't sleep, so it catches up.
+    pub(crate) fn pace(&mut self, delay_seconds: f64, stop: &AtomicBool) {
+        if !self.realtime {
+            return;
+        }
+        self.logged_seconds += delay_seconds.max(0.0);
+        let target = Duration::from_secs_f64(self.logged_seconds / self.speed);
+        let elapsed = self.clock.now().saturating_sub(self.start);
+        if let Some(wait) = target.checked_sub(elapsed)
+            && !wait.is_zero()
+        {
+            self.clock.sleep(wait, stop);
+        }
+    }
+
+    /// Sleeps for `duration` regardless of the schedule.
+    pub(crate) fn sleep(&mut self, duration: Duration, stop: &AtomicBool) {
+        self.clock.sleep(duration, stop);
+    }
+}
+
+/// Streams the log at `log_path` to `mock_sender` until it ends or `stop` is set.
+///
+/// # Returns
+///
+/// - `Result<usize>` - The number of log packets sent, not counting the header.
+pub(crate) fn stream_mock_log(
+    log_path: &Path,
+    options: MockOptions,
+    stop: &AtomicBool,
+    mock_sender: &Sender<FIRMLogPacket>,
+) -> Result<usize> {
+    if options.speed <= 0.0 {
+        return Err(anyhow::anyhow!("speed must be > 0"));
+    }
+    let send = |packet: FIRMLogPacket| {
+        mock_sender
+            .send(packet)
+            .map_err(|_| io::Error::other("Mock channel closed"))
+    };
+
+    let mut file = File::open(log_path)?;
+    let mut header = vec![0u8; HEADER_TOTAL_SIZE];
+    file.read_exact(&mut header)?;
+
+    // Send the log header to the device, framed as a mock packet.
+    send(FIRMLogPacket::new(
+        FIRMLogPacketType::HeaderPacket,
+        header.clone(),
+    ))?;
+
+    let mut parser = LogParser::new();
+    parser.read_header(&header);
+
+    let mut pacer = Pacer::new(SystemClock::new(), &options);
+    // After we send the header we pause for a short time to let the device process it.
+    pacer.sleep(HEADER_PARSE_DELAY, stop);
+
+    let mut buf = vec![0u8; options.chunk_size.max(1)];
+    let mut packets_sent = 0usize;
+    // Parsed packets waiting to be sent, with the log time since the previous one.
+    let mut staged: VecDeque<(FIRMLogPacket, f64)> = VecDeque::new();
+
+    // Reads and parses until at least `count` packets are staged or the file ends.
+    let mut refill = |staged: &mut VecDeque<(FIRMLogPacket, f64)>, count: usize| -> Result<()> {
+        while staged.len() < count {
+            let n = file.read(&mut buf)?;
+            if n > 0 {
+                parser.parse_bytes(&buf[..n]);
+            }
+            while let Some(packet) = parser.get_packet_and_time_delay() {
+                staged.push_back(packet);
+            }
+            if n == 0 {
+                break;
+            }
+        }
+        Ok(())
+    };
+
+    // Send the preload burst without pacing.
+    refill(&mut staged, PRELOAD_COUNT)?;
+    while packets_sent < PRELOAD_COUNT && !stop.load(Ordering::Relaxed) {
+        let Some((packet, _)) = staged.pop_front() else {
+            break;
+        };
+        send(packet)?;
+        packets_sent += 1;
+    }
+
+    // Then send the rest in batches, keeping to the log's schedule from here.
+    pacer.restart();
+    while !stop.load(Ordering::Relaxed) {
+        refill(&mut staged, BATCH_SIZE)?;
+        if staged.is_empty() {
+            break;
+        }
+
+        let mut batch_delay = 0.0f64;
+        for (packet, delay_seconds) in staged.drain(..BATCH_SIZE.min(staged.len())) {
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+            send(packet)?;
+            packets_sent += 1;
+            batch_delay += delay_seconds;
+        }
+        pacer.pace(batch_delay, stop);
+    }
+
+    Ok(packets_sent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FIRMClient;
+    use crate::simulator::{DeviceSimulator, FlightProfileGenerator, build_mock_log};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// A clock that only moves when told to or slept on, recording every sleep.
+    #[derive(Clone, Default)]
+    struct FakeClock {
+        now: Rc<RefCell<Duration>>,
+        sleeps: Rc<RefCell<Vec<Duration>>>,
+    }
+
+    impl FakeClock {
+        fn advance(&self, duration: Duration) {
+            *self.now.borrow_mut() += duration;
+        }
+
+        fn total_slept(&self) -> Duration {
+            self.sleeps.borrow().iter().sum()
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> Duration {
+            *self.now.borrow()
+        }
+
+        fn sleep(&mut self, duration: Duration, _stop: &AtomicBool) {
+            self.sleeps.borrow_mut().push(duration);
+            self.advance(duration);
+        }
+    }
+
+    fn fake_pacer(clock: &FakeClock, realtime: bool, speed: f64) -> Pacer<FakeClock> {
+        let options = MockOptions {
+            realtime,
+            speed,
+            ..Default::default()
+        };
+        Pacer::new(clock.clone(), &options)
+    }
+
+    fn assert_close(actual: Duration, expected: Duration) {
+        let error = actual.abs_diff(expected);
+        assert!(
+            error < Duration::from_micros(1),
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn test_pacer_keeps_to_the_log_schedule_scaled_by_speed() {
+        let stop = AtomicBool::new(false);
+        for speed in [0.5, 1.0, 4.0] {
+            let clock = FakeClock::default();
+            let mut pacer = fake_pacer(&clock, true, speed);
+            for _ in 0..100 {
+                pacer.pace(0.01, &stop);
+            }
+            assert_close(clock.now(), Duration::from_secs_f64(1.0 / speed));
+        }
+    }
+
+    #[test]
+    fn test_pacer_catches_up_when_behind() {
+        let stop = AtomicBool::new(false);
+        let clock = FakeClock::default();
+        let mut pacer = fake_pacer(&clock, true, 1.0);
+
+        // Sending took longer than the log time it covered, so there's nothing to wait for.
+        clock.advance(Duration::from_millis(250));
+        pacer.pace(0.1, &stop);
+        pacer.pace(0.1, &stop);
+        assert!(clock.sleeps.borrow().is_empty());
+
+        // Once the log is ahead again, it only waits out the difference.
+        pacer.pace(0.1, &stop);
+        assert_close(clock.total_slept(), Duration::from_millis(50));
+        assert_close(clock.now(), Duration::from_millis(300));
+    }
+
+    #[test]
+    fn test_pacer_restart_and_unpaced() {
+        let stop = AtomicBool::new(false);
+        let clock = FakeClock::default();
+        let mut pacer = fake_pacer(&clock, true, 1.0);
+        pacer.pace(0.5, &stop);
+        // Time before the restart, e.g. the preload burst, isn't held against the schedule.
+        clock.advance(Duration::from_secs(10));
+        pacer.restart();
+        pacer.pace(0.2, &stop);
+        assert_close(clock.total_slept(), Duration::from_millis(700));
+
+        let clock = FakeClock::default();
+        let mut pacer = fake_pacer(&clock, false, 1.0);
+        pacer.pace(1.0, &stop);
+        pacer.pace(-1.0, &stop);
+        assert!(clock.sleeps.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_start_mock_streams_the_whole_log() {
+        let path = std::env::temp_dir().join(format!(
+            "firm_mock_stream_{}_whole_log.frm",
+            std::process::id()
+        ));
+        let log = build_mock_log(&FlightProfileGenerator::default(), 1.0, 100.0);
+        std::fs::write(&path, log).unwrap();
+
+        let (mut client, device) = FIRMClient::new_mock(0.001);
+        let _simulator = DeviceSimulator::new(200.0).spawn(device);
+        let options = MockOptions {
+            speed: 4.0,
+            ..Default::default()
+        };
+        client.start_mock(&path, options).unwrap();
+        while client.is_mock_log_streaming() {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(client.stop_mock().unwrap(), 100);
+        let _ = std::fs::remove_file(&path);
+        assert!(client.stats().packets_parsed > 0);
+    }
+}
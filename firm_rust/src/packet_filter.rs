@@ -0,0 +1,84 @@
+//! Filters run on each data packet in the reader thread, see `FIRMClient::set_packet_filter`.
+//!
+//! Dropping packets there, before they're queued, saves the bindings from converting packets
+//! only to throw them away. Any `FnMut(&FIRMData) -> bool` closure is a filter; `Decimate` and
+//! `IncreasingTimestamps` cover the common cases.
+use firm_core::firm_packets::FIRMData;
+
+/// Decides which data packets the client passes on. The reader thread calls it once per
+/// packet, in order.
+pub trait PacketFilter: Send {
+    /// Returns true to pass `packet` on, or false to drop it.
+    fn keep(&mut self, packet: &FIRMData) -> bool;
+}
+
+impl<F: FnMut(&FIRMData) -> bool + Send> PacketFilter for F {
+    fn keep(&mut self, packet: &FIRMData) -> bool {
+        self(packet)
+    }
+}
+
+/// Keeps every `n`th packet, starting with the first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decimate {
+    n: u64,
+    seen: u64,
+}
+
+impl Decimate {
+    /// Creates a filter keeping one packet in `n`. Panics if `n` is 0.
+    pub fn new(n: u64) -> Self {
+        assert!(n > 0, "can't keep one packet in 0");
+        Self { n, seen: 0 }
+    }
+}
+
+impl PacketFilter for Decimate {
+    fn keep(&mut self, _packet: &FIRMData) -> bool {
+        let keep = self.seen.is_multiple_of(self.n);
+        self.seen += 1;
+        keep
+    }
+}
+
+/// Keeps only packets whose timestamp is after every packet kept before it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IncreasingTimestamps {
+    last: Option<f64>,
+}
+
+impl PacketFilter for IncreasingTimestamps {
+    fn keep(&mut self, packet: &FIRMData) -> bool {
+        if self
+            .last
+            .is_some_and(|last| packet.timestamp_seconds <= last)
+        {
+            return false;
+        }
+        self.last = Some(packet.timestamp_seconds);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(timestamp_seconds: f64) -> FIRMData {
+        FIRMData::from_fields(timestamp_seconds, [0.0; FIRMData::NUM_F32_FIELDS])
+    }
+
+    #[test]
+    fn test_builtin_filters() {
+        let mut decimate = Decimate::new(3);
+        let kept: Vec<bool> = (0..7).map(|i| decimate.keep(&packet(i as f64))).collect();
+        assert_eq!(kept, [true, false, false, true, false, false, true]);
+
+        let mut increasing = IncreasingTimestamps::default();
+        let kept: Vec<f64> = [1.0, 1.0, 2.0, 1.5, 3.0]
+            .into_iter()
+            .filter(|&t| increasing.keep(&packet(t)))
+            .collect();
+        assert_eq!(kept, [1.0, 2.0, 3.0]);
+    }
+}
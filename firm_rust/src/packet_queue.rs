@@ -0,0 +1,220 @@
+//! The queue of parsed data packets between the reader thread and the consumer, which keeps
+//! count of how many packets are waiting in it. See `FIRMClient::backlog`. Also the sink the
+//! reader thread parses into on the way there, and the bounded queue of frames caught by the
+//! raw frame tap.
+use crate::TimedPacket;
+use firm_core::firm_packets::{FIRMDataPacket, FIRMResponsePacket};
+use firm_core::framed_packet::FramedPacket;
+use firm_core::packet_sink::PacketSink;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, SendError, Sender, TryRecvError, channel};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+/// How many packets are queued, and the most there have been since the last reset.
+#[derive(Debug, Default)]
+pub(crate) struct QueueDepth {
+    current: AtomicUsize,
+    high_water: AtomicUsize,
+}
+
+impl QueueDepth {
+    pub(crate) fn current(&self) -> usize {
+        self.current.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn high_water(&self) -> usize {
+        self.high_water.load(Ordering::Relaxed)
+    }
+
+    /// Starts the high-water mark over from the current depth.
+    pub(crate) fn reset_high_water(&self) {
+        self.high_water.store(self.current(), Ordering::Relaxed);
+    }
+
+    fn pushed(&self) {
+        let depth = self.current.fetch_add(1, Ordering::Relaxed) + 1;
+        self.high_water.fetch_max(depth, Ordering::Relaxed);
+    }
+
+    fn popped(&self) {
+        self.current.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Creates a packet queue and the depth counters shared by both ends.
+pub(crate) fn packet_queue() -> (PacketSender, PacketReceiver, Arc<QueueDepth>) {
+    let (sender, receiver) = channel();
+    let depth = Arc::new(QueueDepth::default());
+    (
+        PacketSender {
+            sender,
+            depth: depth.clone(),
+        },
+        PacketReceiver {
+            receiver,
+            depth: depth.clone(),
+        },
+        depth,
+    )
+}
+
+/// The reader thread's end of the packet queue.
+#[derive(Clone)]
+pub(crate) struct PacketSender {
+    sender: Sender<TimedPacket>,
+    depth: Arc<QueueDepth>,
+}
+
+impl PacketSender {
+    /// Queues `packet`. Fails if the receiver is gone, dropping the packet.
+    pub(crate) fn send(&self, packet: TimedPacket) -> Result<(), SendError<()>> {
+        // Counted before sending, so the receiver never takes a packet it wasn't told about.
+        self.depth.pushed();
+        self.sender.send(packet).map_err(|_| {
+            self.depth.popped();
+            SendError(())
+        })
+    }
+}
+
+/// The consumer's end of the packet queue.
+pub(crate) struct PacketReceiver {
+    receiver: Receiver<TimedPacket>,
+    depth: Arc<QueueDepth>,
+}
+
+impl PacketReceiver {
+    /// A receiver nothing will ever be sent to, with counters of its own.
+    pub(crate) fn disconnected() -> Self {
+        Self {
+            receiver: channel().1,
+            depth: Arc::new(QueueDepth::default()),
+        }
+    }
+
+    pub(crate) fn recv_timeout(&self, timeout: Duration) -> Result<TimedPacket, RecvTimeoutError> {
+        let packet = self.receiver.recv_timeout(timeout)?;
+        self.depth.popped();
+        Ok(packet)
+    }
+
+    pub(crate) fn try_recv(&self) -> Result<TimedPacket, TryRecvError> {
+        let packet = self.receiver.try_recv()?;
+        self.depth.popped();
+        Ok(packet)
+    }
+
+    /// Returns how many packets are queued.
+    pub(crate) fn depth(&self) -> usize {
+        self.depth.current()
+    }
+
+    /// Takes every packet queued right now, without waiting.
+    pub(crate) fn try_iter(&self) -> impl Iterator<Item = TimedPacket> + '_ {
+        std::iter::from_fn(|| self.try_recv().ok())
+    }
+}
+
+/// The reader thread's `PacketSink`. Holds one read's packets, in wire order, from the parser
+/// straight to the channels, so they needn't be queued in the parser and drained into a new
+/// `Vec` each read. Handed on once the read's stats and errors are published, so a consumer
+/// sees those first. Its buffers are reused from read to read.
+#[derive(Default)]
+pub(crate) struct ChannelSink {
+    pub(crate) data_packets: Vec<FIRMDataPacket>,
+    pub(crate) responses: Vec<FIRMResponsePacket>,
+}
+
+impl PacketSink for ChannelSink {
+    fn on_data(&mut self, packet: FIRMDataPacket) {
+        self.data_packets.push(packet);
+    }
+
+    fn on_response(&mut self, packet: FIRMResponsePacket) {
+        self.responses.push(packet);
+    }
+}
+
+/// The frames caught by the raw frame tap, waiting for `FIRMClient::get_raw_frames`. Holds at
+/// most `capacity` frames and, like the parser's packet queues by default, drops the oldest
+/// to make room for a new one, so a consumer that stops reading doesn't grow it without bound.
+pub(crate) struct RawFrameQueue {
+    frames: Mutex<VecDeque<FramedPacket>>,
+    available: Condvar,
+    capacity: usize,
+}
+
+impl RawFrameQueue {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            frames: Mutex::new(VecDeque::new()),
+            available: Condvar::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Queues `frame`, returning whether the oldest queued frame was dropped for it.
+    pub(crate) fn push(&self, frame: FramedPacket) -> bool {
+        let mut frames = self.frames.lock().unwrap();
+        let dropped = frames.len() >= self.capacity;
+        if dropped {
+            frames.pop_front();
+        }
+        frames.push_back(frame);
+        self.available.notify_all();
+        dropped
+    }
+
+    /// Takes every queued frame, oldest first, after waiting up to `timeout` for one if it's
+    /// given and none is queued.
+    pub(crate) fn take(
+        &self,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<FramedPacket>, RecvTimeoutError> {
+        let mut frames = self.frames.lock().unwrap();
+        if let Some(timeout) = timeout {
+            frames = self
+                .available
+                .wait_timeout_while(frames, timeout, |frames| frames.is_empty())
+                .unwrap()
+                .0;
+            if frames.is_empty() {
+                return Err(RecvTimeoutError::Timeout);
+            }
+        }
+        Ok(frames.drain(..).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use firm_core::constants::packet::PacketHeader;
+
+    fn frame(identifier: u16) -> FramedPacket {
+        FramedPacket::new(PacketHeader::Response, identifier, Vec::new())
+    }
+
+    #[test]
+    fn test_raw_frame_queue_drops_the_oldest_frames_when_full() {
+        let queue = RawFrameQueue::new(2);
+        assert!(!queue.push(frame(1)));
+        assert!(!queue.push(frame(2)));
+        assert!(queue.push(frame(3)));
+
+        let identifiers: Vec<u16> = queue
+            .take(None)
+            .unwrap()
+            .iter()
+            .map(|f| f.identifier())
+            .collect();
+        assert_eq!(identifiers, vec![2, 3]);
+        assert!(queue.take(None).unwrap().is_empty());
+        assert_eq!(
+            queue.take(Some(Duration::from_millis(10))),
+            Err(RecvTimeoutError::Timeout)
+        );
+    }
+}
@@ -0,0 +1,183 @@
+//! Finding the serial ports FIRM devices are plugged into, see `list_firm_ports`.
+//!
+//! Only USB ports are listed, so Bluetooth and built-in serial ports don't show up as
+//! candidates. The results are plain data so the bindings can expose them as they are.
+use crate::FIRMClient;
+use firm_core::firm_packets::DeviceInfo;
+use serialport::{SerialPortInfo, SerialPortType};
+use std::time::Duration;
+
+/// Strings a FIRM device reports as its USB product name. Matched case-insensitively, as a
+/// substring, since the OS sometimes appends the interface name.
+pub const FIRM_USB_PRODUCT_NAMES: &[&str] = &["FIRM"];
+
+/// A USB serial port that could have a FIRM device on it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FirmPortInfo {
+    /// The name to pass to `FIRMClient::new`, e.g. "COM8" or "/dev/ttyACM0".
+    pub port_name: String,
+    pub vid: u16,
+    pub pid: u16,
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+    pub serial_number: Option<String>,
+    /// The USB descriptors match a known FIRM device, or a probe got an answer.
+    pub is_firm: bool,
+    /// What the device reported when probed, or `None` if it wasn't probed or didn't answer.
+    pub device_info: Option<DeviceInfo>,
+}
+
+impl FirmPortInfo {
+    /// Builds the info for a port `serialport` found, or `None` if it isn't a USB port.
+    fn from_serial_port(port: SerialPortInfo) -> Option<Self> {
+        let SerialPortType::UsbPort(usb) = port.port_type else {
+            return None;
+        };
+        let is_firm = usb
+            .product
+            .as_deref()
+            .is_some_and(matches_firm_product_name);
+        Some(Self {
+            port_name: port.port_name,
+            vid: usb.vid,
+            pid: usb.pid,
+            manufacturer: usb.manufacturer,
+            product: usb.product,
+            serial_number: usb.serial_number,
+            is_firm,
+            device_info: None,
+        })
+    }
+}
+
+/// How `list_firm_ports_with` looks for devices.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ListPortsOptions {
+    /// Open each USB port and ask it for its device info. Slower, and it briefly takes the
+    /// port from any other program using it, but it finds devices with unknown descriptors.
+    pub probe: bool,
+    /// Baud rate used when probing.
+    pub baud_rate: u32,
+    /// How long to wait for each port to answer when probing.
+    pub probe_timeout: Duration,
+}
+
+impl Default for ListPortsOptions {
+    fn default() -> Self {
+        Self {
+            probe: false,
+            baud_rate: 2_000_000,
+            probe_timeout: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Lists the USB serial ports, marking the ones whose descriptors match a FIRM device.
+/// Ports aren't opened, see `list_firm_ports_with` to probe them.
+///
+/// # Returns
+///
+/// - `Vec<FirmPortInfo>` - Every USB serial port, or an empty list if they couldn't be listed.
+pub fn list_firm_ports() -> Vec<FirmPortInfo> {
+    list_firm_ports_with(&ListPortsOptions::default())
+}
+
+/// Lists the USB serial ports like `list_firm_ports`, probing each one if `options.probe` is set.
+///
+/// # Arguments
+///
+/// - `options` (`&ListPortsOptions`) - Whether and how to probe the ports.
+///
+/// # Returns
+///
+/// - `Vec<FirmPortInfo>` - Every USB serial port. Probed ports that answered have
+///   `device_info` filled in and are marked `is_firm`.
+pub fn list_firm_ports_with(options: &ListPortsOptions) -> Vec<FirmPortInfo> {
+    let mut ports = usb_ports(serialport::available_ports().unwrap_or_default());
+    if options.probe {
+        for port in &mut ports {
+            port.device_info = probe_port(&port.port_name, options);
+            port.is_firm |= port.device_info.is_some();
+        }
+    }
+    ports
+}
+
+/// Keeps the USB ports out of `ports`, in order.
+fn usb_ports(ports: Vec<SerialPortInfo>) -> Vec<FirmPortInfo> {
+    ports
+        .into_iter()
+        .filter_map(FirmPortInfo::from_serial_port)
+        .collect()
+}
+
+fn matches_firm_product_name(product: &str) -> bool {
+    let product = product.to_ascii_uppercase();
+    FIRM_USB_PRODUCT_NAMES
+        .iter()
+        .any(|name| product.contains(&name.to_ascii_uppercase()))
+}
+
+/// Opens `port_name` and asks for its device info, returning `None` if it can't be opened or
+/// doesn't answer in time.
+fn probe_port(port_name: &str, options: &ListPortsOptions) -> Option<DeviceInfo> {
+    let mut client = FIRMClient::new(port_name, options.baud_rate, 0.05).ok()?;
+    client.start().ok()?;
+    let info = client.get_device_info(options.probe_timeout).ok().flatten();
+    client.stop();
+    info
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serialport::UsbPortInfo;
+
+    fn usb_port(name: &str, product: Option<&str>) -> SerialPortInfo {
+        SerialPortInfo {
+            port_name: name.to_string(),
+            port_type: SerialPortType::UsbPort(UsbPortInfo {
+                vid: 0x0483,
+                pid: 0x5740,
+                serial_number: Some("0001".to_string()),
+                manufacturer: Some("NCSU HPRC".to_string()),
+                product: product.map(str::to_string),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_only_usb_ports_are_listed_and_firm_ones_are_marked() {
+        let ports = usb_ports(vec![
+            SerialPortInfo {
+                port_name: "/dev/rfcomm0".to_string(),
+                port_type: SerialPortType::BluetoothPort,
+            },
+            usb_port("/dev/ttyACM0", Some("FIRM Flight Computer")),
+            SerialPortInfo {
+                port_name: "/dev/ttyS0".to_string(),
+                port_type: SerialPortType::PciPort,
+            },
+            usb_port("/dev/ttyACM1", Some("usb serial")),
+            usb_port("/dev/ttyACM2", None),
+        ]);
+
+        let names: Vec<&str> = ports.iter().map(|p| p.port_name.as_str()).collect();
+        assert_eq!(names, ["/dev/ttyACM0", "/dev/ttyACM1", "/dev/ttyACM2"]);
+        assert_eq!(
+            ports.iter().map(|p| p.is_firm).collect::<Vec<_>>(),
+            [true, false, false]
+        );
+        assert_eq!(ports[0].manufacturer.as_deref(), Some("NCSU HPRC"));
+        assert_eq!(ports[0].serial_number.as_deref(), Some("0001"));
+        assert_eq!((ports[0].vid, ports[0].pid), (0x0483, 0x5740));
+        assert!(ports.iter().all(|p| p.device_info.is_none()));
+    }
+
+    #[test]
+    fn test_product_name_match_ignores_case() {
+        assert!(matches_firm_product_name("firm"));
+        assert!(matches_firm_product_name("FIRM CDC Interface"));
+        assert!(!matches_firm_product_name("CP2102 USB to UART"));
+    }
+}
@@ -0,0 +1,579 @@
+use crate::altitude::AltitudeState;
+use crate::error::{ErrorEvent, ErrorReporter, FIRMClientError};
+use crate::host_time::HostClock;
+use crate::link_stats::{IdleThresholds, IdleWatchdog, LinkCounters};
+use crate::packet_filter::PacketFilter;
+use crate::packet_queue::{ChannelSink, PacketSender, RawFrameQueue};
+use crate::transport::{ReadStrategy, Transport};
+use crate::{
+    ALARM_CHECK_INTERVAL, AlarmContext, DataValidation, FIRMClient, LinkControl, RESYNC_LOG_LENGTH,
+    Subscriber, apply_link_control, broadcast_packet, notify_subscribers, record_raw_bytes,
+    recover_connection, reset_watermark,
+};
+use firm_core::client_packets::FIRMLogPacket;
+use firm_core::corpus::FrameCorpus;
+use firm_core::data_parser::{SerialParser, TimestampEvent, is_device_restart};
+use firm_core::firm_packets::{FIRMBaroPacket, FIRMData, FIRMResponse};
+use firm_core::framed_packet::Framed;
+use firm_core::validation::ValidationConfig;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::{Instant, SystemTime};
+
+/// Why a step of the reader loop cut the current pass short.
+enum Interrupt {
+    /// Start the next pass, e.g. after reconnecting or finding nothing to read.
+    Restart,
+    /// End the loop and hand the port back to the client.
+    HandBack,
+    /// End the loop and drop the port, so the next `start()` reopens it.
+    Abandon,
+}
+
+/// The body of the reader thread started by `FIRMClient::start`. It owns the transport and
+/// the parser, and holds its own handles to the state it shares with the client.
+///
+/// Each pass of `run` goes through the steps in order: write what the client queued, `read`,
+/// `parse`, `record_stats`, report the parser's events, then for each data packet `age_out`,
+/// validate, filter and `notify`, and finally `tap` the raw frames and pass on the responses.
+pub(crate) struct ReaderLoop {
+    port: Box<dyn Transport>,
+    parser: SerialParser,
+    sink: ChannelSink,
+    /// Buffer for reading from the port, `read_buffer_size` bytes long.
+    buffer: Vec<u8>,
+    read_strategy: ReadStrategy,
+    host_clock: HostClock,
+    last_alarm_check: Instant,
+    idle_watchdog: IdleWatchdog,
+    /// Number of frames the shared corpus held when it was last copied out.
+    corpus_len: usize,
+    /// The last data packet that passed validation, for `set_data_validation`'s timestamp
+    /// check. Forgotten on a reconnect or a device restart.
+    previous_packet: Option<FIRMData>,
+    /// Once stopped, the bytes that had already arrived are still read and passed on, so the
+    /// last packets aren't left in the port's buffer. `None` while running.
+    final_bytes: Option<usize>,
+
+    command_receiver: Receiver<Vec<u8>>,
+    control_receiver: Receiver<LinkControl>,
+    mock_receiver: Receiver<FIRMLogPacket>,
+
+    running: Arc<AtomicBool>,
+    detached: Arc<AtomicBool>,
+    reconnect: Arc<AtomicBool>,
+    sender: PacketSender,
+    response_sender: Sender<FIRMResponse>,
+    error_sender: ErrorReporter,
+    baro_sender: Sender<FIRMBaroPacket>,
+    calibration_snoop: Arc<RwLock<Option<Sender<FIRMData>>>>,
+    subscribers: Arc<Mutex<Vec<Subscriber<FIRMData>>>>,
+    extra_senders: Arc<Mutex<Vec<Sender<FIRMData>>>>,
+    newest_timestamp_bits: Arc<AtomicU64>,
+    latest_only: Arc<AtomicBool>,
+    latest_packet: Arc<Mutex<Option<FIRMData>>>,
+    skipped_packets: Arc<AtomicU64>,
+    link_counters: Arc<Mutex<LinkCounters>>,
+    raw_frame_tap: Arc<AtomicBool>,
+    raw_frames: Arc<RawFrameQueue>,
+    resync_reporting: Arc<AtomicBool>,
+    parse_error_reporting: Arc<AtomicBool>,
+    idle_thresholds: Arc<Mutex<IdleThresholds>>,
+    strict_responses: Arc<AtomicBool>,
+    packet_filter: Arc<Mutex<Option<Box<dyn PacketFilter>>>>,
+    data_validation: Arc<Mutex<(DataValidation, ValidationConfig)>>,
+    raw_recorder: Arc<Mutex<Option<BufWriter<File>>>>,
+    altitude: Arc<Mutex<AltitudeState>>,
+    corpus: Arc<Mutex<Option<FrameCorpus>>>,
+    alarm_context: AlarmContext,
+}
+
+impl ReaderLoop {
+    /// Sets up a reader for `client`'s current settings, reading from `port`.
+    ///
+    /// # Arguments
+    ///
+    /// - `client` (`&FIRMClient`) - The client whose shared state the reader reports into.
+    /// - `port` (`Box<dyn Transport>`) - The transport to read from and write commands to.
+    /// - `command_receiver` (`Receiver<Vec<u8>>`) - Framed commands and raw bytes to write.
+    /// - `control_receiver` (`Receiver<LinkControl>`) - Line control requests to carry out.
+    /// - `mock_receiver` (`Receiver<FIRMLogPacket>`) - Mock packets to write.
+    pub(crate) fn new(
+        client: &FIRMClient,
+        port: Box<dyn Transport>,
+        command_receiver: Receiver<Vec<u8>>,
+        control_receiver: Receiver<LinkControl>,
+        mock_receiver: Receiver<FIRMLogPacket>,
+    ) -> Self {
+        let mut parser = SerialParser::new();
+        parser.set_timestamp_policy(client.timestamp_policy);
+        parser.set_corpus(client.corpus.lock().unwrap().clone());
+        parser.set_resync_log(Some(RESYNC_LOG_LENGTH));
+        let corpus_len = parser.corpus().map_or(0, FrameCorpus::len);
+        client.link_counters.lock().unwrap().start_new_parser();
+
+        Self {
+            port,
+            parser,
+            sink: ChannelSink::default(),
+            buffer: vec![0u8; client.read_buffer_size],
+            read_strategy: client.read_strategy,
+            host_clock: HostClock::new(),
+            last_alarm_check: Instant::now(),
+            idle_watchdog: IdleWatchdog::new(Instant::now()),
+            corpus_len,
+            previous_packet: None,
+            final_bytes: None,
+
+            command_receiver,
+            control_receiver,
+            mock_receiver,
+
+            running: client.running.clone(),
+            detached: client.detached.clone(),
+            reconnect: client.reconnect.clone(),
+            sender: client.sender.clone(),
+            response_sender: client.response_sender.clone(),
+            error_sender: client.error_sender.clone(),
+            baro_sender: client.baro_sender.clone(),
+            calibration_snoop: client.calibration_snoop.clone(),
+            subscribers: client.subscribers.clone(),
+            extra_senders: client.extra_senders.clone(),
+            newest_timestamp_bits: client.newest_timestamp_bits.clone(),
+            latest_only: client.latest_only.clone(),
+            latest_packet: client.latest_packet.clone(),
+            skipped_packets: client.skipped_packets.clone(),
+            link_counters: client.link_counters.clone(),
+            raw_frame_tap: client.raw_frame_tap.clone(),
+            raw_frames: client.raw_frames.clone(),
+            resync_reporting: client.resync_reporting.clone(),
+            parse_error_reporting: client.parse_error_reporting.clone(),
+            idle_thresholds: client.idle_thresholds.clone(),
+            strict_responses: client.strict_responses.clone(),
+            packet_filter: client.packet_filter.clone(),
+            data_validation: client.data_validation.clone(),
+            raw_recorder: client.raw_recorder.clone(),
+            altitude: client.altitude.clone(),
+            corpus: client.corpus.clone(),
+            alarm_context: AlarmContext {
+                monitor: client.alarms.clone(),
+                subscribers: client.alarm_subscribers.clone(),
+                sender: client.alarm_sender.clone(),
+                aged_out_packets: client.aged_out_packets.clone(),
+                packet_depth: client.packet_depth.clone(),
+            },
+        }
+    }
+
+    /// Reads and passes on packets until the client stops or detaches the thread.
+    ///
+    /// # Returns
+    ///
+    /// - `Option<Box<dyn Transport>>` - The port once stopped, or `None` after an error it
+    ///   couldn't reconnect from, so the next `start()` reopens it instead of reusing it.
+    pub(crate) fn run(mut self) -> Option<Box<dyn Transport>> {
+        loop {
+            match self.pass() {
+                ControlFlow::Continue(()) | ControlFlow::Break(Interrupt::Restart) => {}
+                ControlFlow::Break(Interrupt::HandBack) => return Some(self.port),
+                ControlFlow::Break(Interrupt::Abandon) => return None,
+            }
+        }
+    }
+
+    /// Goes through every step once.
+    fn pass(&mut self) -> ControlFlow<Interrupt> {
+        self.check_stopped()?;
+        self.service_link();
+        self.write_pending()?;
+
+        let bytes_read = self.read()?;
+        if bytes_read == 0 {
+            return ControlFlow::Continue(());
+        }
+        self.parse(bytes_read);
+        let received_at = self.host_clock.now();
+        self.record_stats(bytes_read);
+        self.report_parser_events();
+
+        // Barometer readings go first so packets from the same read can fuse them.
+        while let Some(baro) = self.parser.get_baro_packet() {
+            self.altitude.lock().unwrap().record_baro(&baro);
+            let _ = self.baro_sender.send(baro);
+        }
+        for firm_data_packet in std::mem::take(&mut self.sink.data_packets) {
+            self.handle_packet(firm_data_packet.data().clone(), received_at)?;
+        }
+        self.tap();
+        self.deliver_responses()
+    }
+
+    /// Ends the loop once the thread is detached, or once stopped and the bytes that had
+    /// already arrived are read.
+    fn check_stopped(&mut self) -> ControlFlow<Interrupt> {
+        if self.detached.load(Ordering::Relaxed) {
+            return ControlFlow::Break(Interrupt::Abandon);
+        }
+        if !self.running.load(Ordering::Relaxed) {
+            let remaining = match self.final_bytes {
+                Some(remaining) => remaining,
+                None => self.port.bytes_to_read().ok().flatten().unwrap_or(0),
+            };
+            if remaining == 0 {
+                return ControlFlow::Break(Interrupt::HandBack);
+            }
+            self.final_bytes = Some(remaining);
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// Evaluates the alarm rules and idle watchdog, and carries out line control requests.
+    fn service_link(&mut self) {
+        if self.last_alarm_check.elapsed() >= ALARM_CHECK_INTERVAL {
+            self.last_alarm_check = Instant::now();
+            self.alarm_context
+                .evaluate(&self.link_counters, &self.error_sender);
+        }
+
+        let thresholds = *self.idle_thresholds.lock().unwrap();
+        if let Some(since) = self.idle_watchdog.check(thresholds, Instant::now()) {
+            self.error_sender
+                .report(ErrorEvent::now(FIRMClientError::LinkIdle { since }));
+        }
+
+        while let Ok(control) = self.control_receiver.try_recv() {
+            apply_link_control(
+                &mut self.port,
+                &mut self.parser,
+                &self.link_counters,
+                control,
+            );
+        }
+    }
+
+    /// Writes the pending commands, then the pending mock packets, to the port.
+    fn write_pending(&mut self) -> ControlFlow<Interrupt> {
+        while let Ok(cmd_bytes) = self.command_receiver.try_recv() {
+            if let Err(e) = self.port.write_all(&cmd_bytes) {
+                return self.recover(FIRMClientError::write(&e));
+            }
+        }
+        let _ = self.port.flush();
+
+        while let Ok(packet) = self.mock_receiver.try_recv() {
+            if let Err(e) = self.port.write_all(&packet.to_bytes()) {
+                return self.recover(FIRMClientError::write(&e));
+            }
+        }
+        let _ = self.port.flush();
+        ControlFlow::Continue(())
+    }
+
+    /// Reads from the port into `buffer`, waiting for bytes as `read_strategy` says.
+    ///
+    /// # Returns
+    ///
+    /// - `ControlFlow<Interrupt, usize>` - The number of bytes read, 0 if none arrived in
+    ///   time.
+    fn read(&mut self) -> ControlFlow<Interrupt, usize> {
+        let read_len = match self.read_strategy {
+            ReadStrategy::BlockingRead => self.buffer.len(),
+            // Errors fall through to `read`, which reports them.
+            ReadStrategy::PollBytesToRead { interval } => match self.port.bytes_to_read() {
+                Ok(Some(0)) => {
+                    thread::sleep(interval);
+                    return ControlFlow::Break(Interrupt::Restart);
+                }
+                Ok(Some(available)) => available.min(self.buffer.len()),
+                Ok(None) | Err(_) => self.buffer.len(),
+            },
+        };
+        let read_len = self
+            .final_bytes
+            .map_or(read_len, |remaining| read_len.min(remaining));
+
+        let read = self.port.read(&mut self.buffer[..read_len]);
+        if self.detached.load(Ordering::Relaxed) {
+            return ControlFlow::Break(Interrupt::Abandon);
+        }
+        match read {
+            Ok(bytes_read @ 1..) => {
+                if let Some(remaining) = self.final_bytes.as_mut() {
+                    *remaining = remaining.saturating_sub(bytes_read);
+                }
+                ControlFlow::Continue(bytes_read)
+            }
+            Ok(0) if self.final_bytes.is_some() => ControlFlow::Break(Interrupt::HandBack),
+            Ok(0) => ControlFlow::Continue(0),
+            // A replayed stream ran out; that's the end, not an error.
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                self.running.store(false, Ordering::Relaxed);
+                ControlFlow::Break(Interrupt::HandBack)
+            }
+            // Timeouts might happen; just continue reading
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+                ) =>
+            {
+                if self.final_bytes.is_some() {
+                    return ControlFlow::Break(Interrupt::HandBack);
+                }
+                ControlFlow::Continue(0)
+            }
+            // Other errors (e.g. a connection reset) should be reported and stop the thread,
+            // unless we can reconnect:
+            Err(e) => {
+                self.recover(FIRMClientError::read(&e))?;
+                ControlFlow::Continue(0)
+            }
+        }
+    }
+
+    /// Records the bytes read and feeds them into the parser. Every packet completed by this
+    /// read had its final byte arrive in it.
+    fn parse(&mut self, bytes_read: usize) {
+        let bytes = &self.buffer[..bytes_read];
+        record_raw_bytes(&self.raw_recorder, bytes, &self.error_sender);
+
+        self.parser
+            .collect_parse_errors(self.parse_error_reporting.load(Ordering::Relaxed));
+        // The parser fills in the altitude, from the client's current reference.
+        self.parser
+            .set_sea_level_pressure(self.altitude.lock().unwrap().reference_pressure_pascals());
+        self.parser.parse_bytes_into(bytes, &mut self.sink);
+    }
+
+    /// Updates the link counters from the read, and copies the corpus out if it grew.
+    fn record_stats(&mut self, bytes_read: usize) {
+        {
+            let mut counters = self.link_counters.lock().unwrap();
+            counters.record_bytes(bytes_read);
+            counters.update_parser(self.parser.stats());
+        }
+        if let Some(collected) = self.parser.corpus()
+            && collected.len() != self.corpus_len
+        {
+            self.corpus_len = collected.len();
+            *self.corpus.lock().unwrap() = Some(collected.clone());
+        }
+    }
+
+    /// Reports the parse errors, lost sync and timestamp events from the read. Called before
+    /// the read's packets are handed on, so they're in order with them.
+    fn report_parser_events(&mut self) {
+        // Always drained, but only reported if asked for.
+        while let Some(parse_error) = self.parser.get_parse_error() {
+            self.error_sender
+                .report(ErrorEvent::now(FIRMClientError::Parse {
+                    byte_offset: parse_error.byte_offset,
+                    error: parse_error.error,
+                }));
+        }
+        for event in self.parser.take_resync_events() {
+            if self.resync_reporting.load(Ordering::Relaxed) {
+                self.error_sender
+                    .report(ErrorEvent::now(FIRMClientError::LostSync {
+                        byte_offset: event.byte_offset,
+                        reason: event.reason,
+                    }));
+            }
+        }
+        while let Some(event) = self.parser.get_timestamp_event() {
+            self.error_sender.report(ErrorEvent::now(match event {
+                TimestampEvent::OutOfOrder {
+                    previous_seconds,
+                    timestamp_seconds,
+                } => FIRMClientError::TimestampOutOfOrder {
+                    previous_seconds,
+                    timestamp_seconds,
+                },
+                TimestampEvent::DeviceRestartDetected {
+                    previous_seconds,
+                    timestamp_seconds,
+                } => FIRMClientError::DeviceRestartDetected {
+                    previous_seconds,
+                    timestamp_seconds,
+                },
+                TimestampEvent::Resynced {
+                    previous_seconds,
+                    timestamp_seconds,
+                } => FIRMClientError::TimestampResynced {
+                    previous_seconds,
+                    timestamp_seconds,
+                },
+            }));
+        }
+    }
+
+    /// Fills in, checks and filters one data packet, then passes it on.
+    fn handle_packet(
+        &mut self,
+        mut packet: FIRMData,
+        received_at: SystemTime,
+    ) -> ControlFlow<Interrupt> {
+        let parsed_at = Instant::now();
+        self.link_counters.lock().unwrap().record_packet(parsed_at);
+        self.idle_watchdog.record_packet(parsed_at);
+        self.altitude
+            .lock()
+            .unwrap()
+            .apply(&mut packet, self.parser.sea_level_pressure());
+        self.age_out(&packet);
+
+        let (mode, config) = *self.data_validation.lock().unwrap();
+        if mode != DataValidation::Off
+            && let Err(faults) = config.check(&packet, self.previous_packet.as_ref())
+        {
+            self.link_counters.lock().unwrap().record_invalid();
+            if mode == DataValidation::Drop {
+                return ControlFlow::Continue(());
+            }
+            self.error_sender
+                .report(ErrorEvent::now(FIRMClientError::ImplausibleData {
+                    timestamp_seconds: packet.timestamp_seconds,
+                    faults,
+                }));
+        } else {
+            // Only a packet that passed is compared with, so one bad timestamp can't fail the
+            // good ones after it.
+            self.previous_packet = Some(packet.clone());
+        }
+        self.advance_watermark(&packet);
+
+        let keep = self
+            .packet_filter
+            .lock()
+            .unwrap()
+            .as_mut()
+            .is_none_or(|filter| filter.keep(&packet));
+        if !keep {
+            self.link_counters.lock().unwrap().record_filtered();
+        } else {
+            self.notify(&packet, received_at)?;
+        }
+
+        // We use a read lock which is very fast if no one is writing.
+        if let Ok(guard) = self.calibration_snoop.read()
+            && let Some(cal_tx) = &*guard
+        {
+            // Ignore errors (if cal thread died, we don't care)
+            let _ = cal_tx.send(packet);
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// Forgets the packets seen so far when `packet` shows the device restarted. After a
+    /// reboot the device's clock starts over, so the packets from before it mustn't age out
+    /// or fail the new ones.
+    fn age_out(&mut self, packet: &FIRMData) {
+        if is_device_restart(
+            f64::from_bits(self.newest_timestamp_bits.load(Ordering::Relaxed)),
+            packet.timestamp_seconds,
+        ) {
+            reset_watermark(&self.newest_timestamp_bits);
+            self.previous_packet = None;
+        }
+    }
+
+    /// Raises the newest timestamp seen, which the client ages packets out against.
+    fn advance_watermark(&self, packet: &FIRMData) {
+        if packet.timestamp_seconds
+            > f64::from_bits(self.newest_timestamp_bits.load(Ordering::Relaxed))
+        {
+            self.newest_timestamp_bits
+                .store(packet.timestamp_seconds.to_bits(), Ordering::Relaxed);
+        }
+    }
+
+    /// Hands a kept packet to the subscribers, the extra receivers and the client.
+    fn notify(&mut self, packet: &FIRMData, received_at: SystemTime) -> ControlFlow<Interrupt> {
+        // Notify subscribers first so a packet returned by `get_data_packets` has always been
+        // seen by them too.
+        notify_subscribers(
+            &self.subscribers,
+            packet,
+            "Packet subscriber",
+            &self.error_sender,
+        );
+
+        broadcast_packet(&self.extra_senders, packet);
+
+        if self.latest_only.load(Ordering::Relaxed) {
+            let replaced = self.latest_packet.lock().unwrap().replace(packet.clone());
+            if replaced.is_some() {
+                self.skipped_packets.fetch_add(1, Ordering::Relaxed);
+            }
+        } else if self.sender.send((packet.clone(), received_at)).is_err() {
+            return ControlFlow::Break(Interrupt::HandBack); // Receiver dropped
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// Queues the raw frames for `get_raw_frames`. They're always drained so the parser
+    /// doesn't hold on to them, but only forwarded if someone asked for the tap.
+    fn tap(&mut self) {
+        while let Some(frame) = self.parser.get_raw_frame() {
+            if self.raw_frame_tap.load(Ordering::Relaxed) && self.raw_frames.push(frame) {
+                self.link_counters
+                    .lock()
+                    .unwrap()
+                    .record_raw_frame_dropped();
+            }
+        }
+    }
+
+    /// Sends the read's responses to the client, dropping the ones that break the protocol
+    /// when responses are strict.
+    fn deliver_responses(&mut self) -> ControlFlow<Interrupt> {
+        for firm_response_packet in self.sink.responses.drain(..) {
+            let response = firm_response_packet.response().clone();
+            self.link_counters.lock().unwrap().record_response();
+            if self.strict_responses.load(Ordering::Relaxed) {
+                let violations = firm_response_packet.violations();
+                if !violations.is_empty() {
+                    self.error_sender
+                        .report(ErrorEvent::now(FIRMClientError::ProtocolViolation {
+                            command: firm_response_packet.command_type(),
+                            violations,
+                            payload: firm_response_packet.frame().payload().to_vec(),
+                        }));
+                    continue;
+                }
+            }
+            if self.response_sender.send(response).is_err() {
+                return ControlFlow::Break(Interrupt::HandBack); // Receiver dropped
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// Reports a connection error and reconnects if the client asked for it.
+    ///
+    /// # Returns
+    ///
+    /// - `ControlFlow<Interrupt>` - `Interrupt::Restart` after reconnecting, or
+    ///   `Interrupt::Abandon` if the thread has to stop.
+    fn recover(&mut self, error: FIRMClientError) -> ControlFlow<Interrupt> {
+        if recover_connection(
+            error,
+            &mut self.port,
+            &mut self.parser,
+            &self.link_counters,
+            &self.running,
+            &self.reconnect,
+            &self.error_sender,
+        ) {
+            reset_watermark(&self.newest_timestamp_bits);
+            self.previous_packet = None;
+            return ControlFlow::Break(Interrupt::Restart);
+        }
+        ControlFlow::Break(Interrupt::Abandon)
+    }
+}
@@ -0,0 +1,196 @@
+//! Raw recordings of the bytes read from a FIRM device, see `FIRMClient::record_raw`.
+//!
+//! A recording is a short text header followed by the raw byte stream exactly as it was read:
+//!
+//! ```text
+//! FIRM RAW RECORDING v1
+//! start_unix_seconds=1760400000.25
+//! connection=serial
+//! address=/dev/ttyACM0
+//! baud_rate=2000000
+//! timeout_seconds=0.1
+//! device_id=1234605616436508552
+//! ...
+//!
+//! <raw bytes...>
+//! ```
+//!
+//! Keys that don't apply to the connection are left out, and unknown keys are ignored so
+//! newer writers stay readable. The device keys are the `SessionInfo` header lines, present
+//! when the client had captured one.
+use crate::session::SessionInfo;
+use crate::transport::{ConnectionKind, ConnectionSettings};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// First line of every raw recording.
+pub const RAW_RECORDING_MAGIC: &str = "FIRM RAW RECORDING v1";
+
+/// Describes when and how a raw recording was made.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordingHeader {
+    /// Wall clock time the recording started, in seconds since the Unix epoch.
+    pub start_unix_seconds: f64,
+    /// The connection the bytes were read from.
+    pub connection: ConnectionSettings,
+    /// The device the bytes came from, if the client had captured it when recording began.
+    pub session: Option<SessionInfo>,
+}
+
+impl RecordingHeader {
+    /// Creates a header for a recording starting now.
+    pub fn now(connection: ConnectionSettings, session: Option<SessionInfo>) -> Self {
+        let start_unix_seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0.0, |since_epoch| since_epoch.as_secs_f64());
+        Self {
+            start_unix_seconds,
+            connection,
+            session,
+        }
+    }
+
+    /// Writes the header, including the blank line that ends it.
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        writeln!(writer, "{RAW_RECORDING_MAGIC}")?;
+        writeln!(writer, "start_unix_seconds={}", self.start_unix_seconds)?;
+        writeln!(writer, "connection={}", self.connection.kind.as_str())?;
+        if let Some(address) = &self.connection.address {
+            writeln!(writer, "address={address}")?;
+        }
+        if let Some(baud_rate) = self.connection.baud_rate {
+            writeln!(writer, "baud_rate={baud_rate}")?;
+        }
+        if let Some(timeout) = self.connection.timeout_seconds {
+            writeln!(writer, "timeout_seconds={timeout}")?;
+        }
+        if let Some(session) = &self.session {
+            for line in session.header_lines() {
+                writeln!(writer, "{line}")?;
+            }
+        }
+        writeln!(writer)
+    }
+
+    /// Reads a header, leaving `reader` positioned at the first recorded byte.
+    ///
+    /// # Returns
+    ///
+    /// - `io::Result<Self>` - `io::ErrorKind::InvalidData` if this isn't a raw recording.
+    pub fn read_from(reader: &mut impl BufRead) -> io::Result<Self> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        if line.trim_end() != RAW_RECORDING_MAGIC {
+            return Err(invalid(format!(
+                "not a FIRM raw recording (first line {:?})",
+                line.trim_end()
+            )));
+        }
+
+        let mut header = Self {
+            start_unix_seconds: 0.0,
+            connection: ConnectionSettings::custom(),
+            session: None,
+        };
+        let mut other_lines = Vec::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(invalid(
+                    "raw recording header is not terminated".to_string(),
+                ));
+            }
+            let line = line.trim_end_matches(['\r', '\n']);
+            if line.is_empty() {
+                header.session =
+                    SessionInfo::from_header_lines(other_lines.iter().map(String::as_str));
+                return Ok(header);
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("malformed header line {line:?}")))?;
+            let bad_value = || invalid(format!("bad value for {key}: {value:?}"));
+            match key {
+                "start_unix_seconds" => {
+                    header.start_unix_seconds = value.parse().map_err(|_| bad_value())?
+                }
+                "connection" => {
+                    header.connection.kind =
+                        ConnectionKind::from_name(value).ok_or_else(bad_value)?
+                }
+                "address" => header.connection.address = Some(value.to_string()),
+                "baud_rate" => {
+                    header.connection.baud_rate = Some(value.parse().map_err(|_| bad_value())?)
+                }
+                "timeout_seconds" => {
+                    header.connection.timeout_seconds =
+                        Some(value.parse().map_err(|_| bad_value())?)
+                }
+                _ => other_lines.push(line.to_string()),
+            }
+        }
+    }
+}
+
+/// Opens a raw recording and reads its header.
+///
+/// # Arguments
+///
+/// - `path` (`&Path`) - The recording to open.
+///
+/// # Returns
+///
+/// - `io::Result<(RecordingHeader, BufReader<File>)>` - The header and a reader positioned at
+///   the first recorded byte.
+pub fn open_raw_recording(path: &Path) -> io::Result<(RecordingHeader, BufReader<File>)> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let header = RecordingHeader::read_from(&mut reader)?;
+    Ok((header, reader))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Read};
+
+    #[test]
+    fn test_header_round_trips() {
+        let header = RecordingHeader {
+            start_unix_seconds: 1_760_400_000.25,
+            connection: ConnectionSettings {
+                kind: ConnectionKind::Serial,
+                address: Some("/dev/ttyACM0".to_string()),
+                baud_rate: Some(2_000_000),
+                timeout_seconds: Some(0.1),
+            },
+            session: Some(SessionInfo::new(
+                Some(firm_core::firm_packets::DeviceInfo {
+                    firmware_version: "v1.2.0".to_string(),
+                    id: 42,
+                }),
+                None,
+            )),
+        };
+        let mut bytes = Vec::new();
+        header.write_to(&mut bytes).unwrap();
+        bytes.extend_from_slice(&[0xA5, 0x5A, b'\n', 0]);
+
+        let mut reader = Cursor::new(bytes);
+        assert_eq!(RecordingHeader::read_from(&mut reader).unwrap(), header);
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, [0xA5, 0x5A, b'\n', 0]);
+    }
+
+    #[test]
+    fn test_rejects_other_files() {
+        let mut reader = Cursor::new(b"FIRM LOG v1.0\n\n".to_vec());
+        let err = RecordingHeader::read_from(&mut reader).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
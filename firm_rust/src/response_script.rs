@@ -0,0 +1,374 @@
+//! Scripted response timing for the `DeviceSimulator`, to reproduce the races a real link can
+//! produce: slow replies, lost replies, duplicated acks, replies arriving out of order and
+//! replies buried in the middle of a burst of data packets.
+//!
+//! A `ResponseScript` is a list of rules, each applying to one command or to all of them
+//! (`*`). It can be built in code or parsed from a small text format, one rule per line:
+//!
+//! ```text
+//! # Every reply takes 20-80 ms, chosen uniformly at random per reply.
+//! seed 7
+//! * delay 20ms..80ms
+//! set_device_config duplicate 1
+//! cancel drop
+//! get_device_config hold
+//! get_calibration mid_burst 20
+//! ```
+//!
+//! - `delay <min>ms[..<max>ms]` sends the reply after a fixed or uniformly random delay.
+//! - `drop` never sends the reply.
+//! - `duplicate <n>` sends `n` extra copies right after the reply.
+//! - `hold` keeps the reply back until the next reply to another command has been sent, so the
+//!   two arrive in the opposite order.
+//! - `mid_burst <n>` sends the reply in the middle of a burst of `n` data packets, written to
+//!   the link in one go.
+//!
+//! When several rules match a command, the last `delay` and `mid_burst` win and the
+//! `duplicate` counts add up. `seed` makes the random delays repeatable. Blank lines and lines
+//! starting with `#` are ignored.
+use anyhow::{Context, Result};
+use firm_core::constants::command::FIRMCommand;
+use firm_core::framed_packet::FramedPacket;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Every command a script can refer to, with its name in the text format.
+const COMMAND_NAMES: [(FIRMCommand, &str); 9] = [
+    (FIRMCommand::GetDeviceInfo, "get_device_info"),
+    (FIRMCommand::GetDeviceConfig, "get_device_config"),
+    (FIRMCommand::SetDeviceConfig, "set_device_config"),
+    (FIRMCommand::Reboot, "reboot"),
+    (FIRMCommand::Mock, "mock"),
+    (
+        FIRMCommand::SetMagnetometerCalibration,
+        "set_magnetometer_calibration",
+    ),
+    (FIRMCommand::SetIMUCalibration, "set_imu_calibration"),
+    (FIRMCommand::GetCalibration, "get_calibration"),
+    (FIRMCommand::Cancel, "cancel"),
+];
+
+/// How the simulator misbehaves when replying. See the module docs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseBehavior {
+    /// Sends the reply after a delay picked uniformly from `min..=max`.
+    Delay { min: Duration, max: Duration },
+    /// Never sends the reply.
+    Drop,
+    /// Sends `copies` extra copies of the reply.
+    Duplicate { copies: u32 },
+    /// Sends the reply only after the next reply to another command.
+    Hold,
+    /// Sends the reply in the middle of a burst of `packets` data packets.
+    MidBurst { packets: usize },
+}
+
+/// A behavior for replies to `command`, or to every command if it's `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResponseRule {
+    pub command: Option<FIRMCommand>,
+    pub behavior: ResponseBehavior,
+}
+
+/// A set of rules making up one response timing scenario.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResponseScript {
+    pub rules: Vec<ResponseRule>,
+    /// Seed for the random delays.
+    pub seed: u64,
+}
+
+impl ResponseScript {
+    /// Adds a rule for replies to `command`, or to every command if it's `None`.
+    pub fn with_rule(mut self, command: Option<FIRMCommand>, behavior: ResponseBehavior) -> Self {
+        self.rules.push(ResponseRule { command, behavior });
+        self
+    }
+
+    /// Parses a script in the format described in the module documentation.
+    ///
+    /// # Arguments
+    ///
+    /// - `text` (`&str`) - The script, one rule per line.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<Self>` - The script, or an error naming the first line that doesn't parse.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut script = Self::default();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let context = || format!("line {}", index + 1);
+            if let Some(seed) = line.strip_prefix("seed ") {
+                script.seed = seed
+                    .trim()
+                    .parse()
+                    .with_context(|| format!("invalid seed '{}'", seed.trim()))
+                    .with_context(context)?;
+                continue;
+            }
+            script.rules.push(parse_line(line).with_context(context)?);
+        }
+        Ok(script)
+    }
+
+    /// Reads and parses a script file.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read response script {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Invalid response script {}", path.display()))
+    }
+}
+
+fn parse_line(line: &str) -> Result<ResponseRule> {
+    let mut words = line.split_whitespace();
+    let target = words.next().context("missing command")?;
+    let command = match target {
+        "*" => None,
+        name => Some(
+            COMMAND_NAMES
+                .iter()
+                .find(|(_, n)| *n == name)
+                .map(|(command, _)| *command)
+                .with_context(|| format!("unknown command '{name}'"))?,
+        ),
+    };
+
+    let behavior = match words.next().context("missing behavior")? {
+        "delay" => {
+            let range = words.next().context("delay needs a duration, e.g. 20ms")?;
+            let (min, max) = match range.split_once("..") {
+                Some((min, max)) => (parse_millis(min)?, parse_millis(max)?),
+                None => (parse_millis(range)?, parse_millis(range)?),
+            };
+            if max < min {
+                anyhow::bail!("delay {range} ends before it starts");
+            }
+            ResponseBehavior::Delay { min, max }
+        }
+        "drop" => ResponseBehavior::Drop,
+        "duplicate" => {
+            let copies = words.next().unwrap_or("1");
+            ResponseBehavior::Duplicate {
+                copies: copies
+                    .parse()
+                    .with_context(|| format!("invalid copy count '{copies}'"))?,
+            }
+        }
+        "hold" => ResponseBehavior::Hold,
+        "mid_burst" => {
+            let packets = words.next().context("mid_burst needs a packet count")?;
+            ResponseBehavior::MidBurst {
+                packets: packets
+                    .parse()
+                    .with_context(|| format!("invalid packet count '{packets}'"))?,
+            }
+        }
+        other => anyhow::bail!("unknown behavior '{other}'"),
+    };
+
+    if let Some(extra) = words.next() {
+        anyhow::bail!("unexpected '{extra}' after the behavior");
+    }
+    Ok(ResponseRule { command, behavior })
+}
+
+fn parse_millis(text: &str) -> Result<Duration> {
+    let millis = text
+        .strip_suffix("ms")
+        .with_context(|| format!("expected a duration in ms, got '{text}'"))?;
+    let millis: f64 = millis
+        .parse()
+        .with_context(|| format!("invalid duration '{text}'"))?;
+    if !(millis >= 0.0 && millis.is_finite()) {
+        anyhow::bail!("invalid duration '{text}'");
+    }
+    Ok(Duration::from_secs_f64(millis / 1000.0))
+}
+
+/// A reply the simulator still has to send.
+pub(crate) struct ScheduledReply {
+    pub due: Instant,
+    pub frame: FramedPacket,
+    /// If set, the reply goes out in the middle of a burst of this many data packets.
+    pub mid_burst: Option<usize>,
+}
+
+/// Decides when the simulator sends each reply according to a `ResponseScript`.
+pub(crate) struct ResponseScheduler {
+    script: ResponseScript,
+    rng_state: u64,
+    pending: Vec<ScheduledReply>,
+    held: Vec<FramedPacket>,
+}
+
+impl ResponseScheduler {
+    pub fn new(script: ResponseScript) -> Self {
+        Self {
+            // Xorshift gets stuck on an all zero state.
+            rng_state: script.seed ^ 0x9E37_79B9_7F4A_7C15,
+            script,
+            pending: Vec::new(),
+            held: Vec::new(),
+        }
+    }
+
+    /// Queues `frame`, the reply to `command`, according to the script.
+    pub fn schedule(&mut self, command: FIRMCommand, frame: FramedPacket, now: Instant) {
+        let mut delay = Duration::ZERO;
+        let mut copies = 0;
+        let mut mid_burst = None;
+        let mut hold = false;
+        let rules = self
+            .script
+            .rules
+            .iter()
+            .filter(|rule| rule.command.is_none_or(|c| c == command));
+        for rule in rules {
+            match rule.behavior {
+                ResponseBehavior::Drop => return,
+                ResponseBehavior::Delay { min, max } => {
+                    delay = min + (max - min).mul_f64(next_unit(&mut self.rng_state));
+                }
+                ResponseBehavior::Duplicate { copies: n } => copies += n,
+                ResponseBehavior::Hold => hold = true,
+                ResponseBehavior::MidBurst { packets } => mid_burst = Some(packets),
+            }
+        }
+
+        if hold {
+            self.held
+                .extend(std::iter::repeat_n(frame, copies as usize + 1));
+            return;
+        }
+        let due = now + delay;
+        for frame in std::iter::repeat_n(frame, copies as usize + 1).chain(self.held.drain(..)) {
+            self.pending.push(ScheduledReply {
+                due,
+                frame,
+                mid_burst,
+            });
+        }
+    }
+
+    /// Removes and returns the replies due by `now`, in the order they should be sent.
+    pub fn take_due(&mut self, now: Instant) -> Vec<ScheduledReply> {
+        // A stable sort keeps duplicates and released held replies behind their reply.
+        self.pending.sort_by_key(|reply| reply.due);
+        let due = self.pending.partition_point(|reply| reply.due <= now);
+        self.pending.drain(..due).collect()
+    }
+}
+
+/// Advances the xorshift generator in `state` and returns a number in `0..1`.
+fn next_unit(state: &mut u64) -> f64 {
+    let mut x = *state;
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    *state = x;
+    (x >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use firm_core::constants::packet::PacketHeader;
+
+    fn reply(command: FIRMCommand, payload: u8) -> FramedPacket {
+        FramedPacket::new(PacketHeader::Response, command.to_u16(), vec![payload])
+    }
+
+    fn payloads(replies: &[ScheduledReply]) -> Vec<u8> {
+        replies
+            .iter()
+            .map(|reply| reply.frame.payload()[0])
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_script() {
+        let script = ResponseScript::parse(
+            "# comment\nseed 3\n* delay 5ms..10ms\n\ncancel drop\nmock duplicate\nget_calibration mid_burst 8\n",
+        )
+        .unwrap();
+        assert_eq!(script.seed, 3);
+        assert_eq!(
+            script.rules,
+            vec![
+                ResponseRule {
+                    command: None,
+                    behavior: ResponseBehavior::Delay {
+                        min: Duration::from_millis(5),
+                        max: Duration::from_millis(10),
+                    },
+                },
+                ResponseRule {
+                    command: Some(FIRMCommand::Cancel),
+                    behavior: ResponseBehavior::Drop,
+                },
+                ResponseRule {
+                    command: Some(FIRMCommand::Mock),
+                    behavior: ResponseBehavior::Duplicate { copies: 1 },
+                },
+                ResponseRule {
+                    command: Some(FIRMCommand::GetCalibration),
+                    behavior: ResponseBehavior::MidBurst { packets: 8 },
+                },
+            ]
+        );
+
+        let error = ResponseScript::parse("cancel drop\nping delay 5ms").unwrap_err();
+        assert_eq!(format!("{error:#}"), "line 2: unknown command 'ping'");
+        let error = ResponseScript::parse("* delay 5").unwrap_err();
+        assert_eq!(
+            format!("{error:#}"),
+            "line 1: expected a duration in ms, got '5'"
+        );
+    }
+
+    #[test]
+    fn test_scheduler_delays_duplicates_holds_and_drops() {
+        let script = ResponseScript::default()
+            .with_rule(
+                Some(FIRMCommand::GetDeviceInfo),
+                ResponseBehavior::Delay {
+                    min: Duration::from_millis(10),
+                    max: Duration::from_millis(20),
+                },
+            )
+            .with_rule(None, ResponseBehavior::Duplicate { copies: 1 })
+            .with_rule(Some(FIRMCommand::GetDeviceConfig), ResponseBehavior::Hold)
+            .with_rule(Some(FIRMCommand::Cancel), ResponseBehavior::Drop);
+        let mut scheduler = ResponseScheduler::new(script);
+        let start = Instant::now();
+
+        scheduler.schedule(FIRMCommand::Cancel, reply(FIRMCommand::Cancel, 0), start);
+        scheduler.schedule(
+            FIRMCommand::GetDeviceConfig,
+            reply(FIRMCommand::GetDeviceConfig, 1),
+            start,
+        );
+        assert!(
+            scheduler
+                .take_due(start + Duration::from_secs(1))
+                .is_empty()
+        );
+
+        scheduler.schedule(
+            FIRMCommand::GetDeviceInfo,
+            reply(FIRMCommand::GetDeviceInfo, 2),
+            start,
+        );
+        assert!(
+            scheduler
+                .take_due(start + Duration::from_millis(9))
+                .is_empty()
+        );
+        let due = scheduler.take_due(start + Duration::from_millis(20));
+        assert_eq!(payloads(&due), [2, 2, 1, 1]);
+    }
+}
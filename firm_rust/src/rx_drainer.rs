@@ -0,0 +1,247 @@
+use firm_core::data_parser::SerialParser;
+use serialport::SerialPort;
+use std::io;
+use std::time::{Duration, Instant};
+
+/// Callback that receives every chunk of raw bytes read from the port.
+type HexCallback = Box<dyn FnMut(&[u8]) + Send>;
+/// Callback invoked at the status interval with the current count.
+type StatusCallback = Box<dyn FnMut(u64) + Send>;
+
+/// Which kinds of parsed frames an `RxDrainer` counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CountMode {
+    /// Count only data packets.
+    DataPackets,
+    /// Count only command responses.
+    Responses,
+    /// Count data packets and command responses together.
+    Both,
+}
+
+/// Keeps the RX side of a serial port drained while the caller is busy writing to it.
+///
+/// Bytes are only read when the port reports them as available, so polling a quiet port
+/// never blocks for the port's read timeout. Everything read is parsed and discarded, with
+/// the parsed frames counted according to the `CountMode`.
+///
+/// # Example:
+///
+///
+/// use firm_rust::rx_drainer::{CountMode, RxDrainer};
+/// use std::time::Duration;
+///
+/// let mut drainer = RxDrainer::new(CountMode::DataPackets)
+///     .with_status(Duration::from_millis(250), |count| println!("RX FIRMDataPackets: {count}"));
+/// drainer.drain_for(&mut *port, Duration::from_secs(1))?;
+pub struct RxDrainer {
+    parser: SerialParser,
+    buffer: Vec<u8>,
+    count_mode: CountMode,
+    data_packets: u64,
+    responses: u64,
+    hex_callback: Option<HexCallback>,
+    status_callback: Option<(Duration, StatusCallback)>,
+    last_status: Instant,
+}
+
+impl RxDrainer {
+    /// Creates a new drainer that counts the given kinds of frames.
+    ///
+    /// # Arguments
+    ///
+    /// - `count_mode` (`CountMode`) - Which parsed frames contribute to `count()`.
+    pub fn new(count_mode: CountMode) -> Self {
+        Self {
+            parser: SerialParser::new(),
+            buffer: vec![0u8; 4096],
+            count_mode,
+            data_packets: 0,
+            responses: 0,
+            hex_callback: None,
+            status_callback: None,
+            last_status: Instant::now(),
+        }
+    }
+
+    /// Sets a callback that receives every chunk of raw bytes read, e.g. to print a hex dump.
+    pub fn with_hex_callback(mut self, callback: impl FnMut(&[u8]) + Send + 'static) -> Self {
+        self.hex_callback = Some(Box::new(callback));
+        self
+    }
+
+    /// Sets a callback that is invoked with the current count at most once per `interval`
+    /// while draining.
+    pub fn with_status(
+        mut self,
+        interval: Duration,
+        callback: impl FnMut(u64) + Send + 'static,
+    ) -> Self {
+        self.status_callback = Some((interval, Box::new(callback)));
+        self.last_status = Instant::now();
+        self
+    }
+
+    /// Reads and parses whatever is currently available on the port without waiting.
+    ///
+    /// # Arguments
+    ///
+    /// - `port` (`&mut dyn SerialPort`) - The port to drain.
+    ///
+    /// # Returns
+    ///
+    /// - `io::Result<()>` - Read timeouts are ignored; any other read error is returned.
+    pub fn drain_nonblocking(&mut self, port: &mut dyn SerialPort) -> io::Result<()> {
+        // With a non-zero port timeout, `read()` can wait for the whole timeout when nothing is
+        // available. Callers poll this a lot while streaming, so skip the read entirely.
+        if let Ok(0) = port.bytes_to_read() {
+            return Ok(());
+        }
+
+        match port.read(&mut self.buffer) {
+            Ok(n @ 1..) => {
+                if let Some(callback) = self.hex_callback.as_mut() {
+                    callback(&self.buffer[..n]);
+                }
+                self.parser.parse_bytes(&self.buffer[..n]);
+                // Count and drain both queues, before a full queue starts dropping packets.
+                self.data_packets += self.parser.drain_packets().len() as u64;
+                self.responses += self.parser.drain_responses().len() as u64;
+                Ok(())
+            }
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Keeps draining the port for `duration`, reporting status at the configured interval.
+    ///
+    /// # Arguments
+    ///
+    /// - `port` (`&mut dyn SerialPort`) - The port to drain.
+    /// - `duration` (`Duration`) - How long to keep draining.
+    ///
+    /// # Returns
+    ///
+    /// - `io::Result<()>` - The first non-timeout read error, if any.
+    pub fn drain_for(&mut self, port: &mut dyn SerialPort, duration: Duration) -> io::Result<()> {
+        let start = Instant::now();
+        while start.elapsed() < duration {
+            self.drain_nonblocking(port)?;
+            self.report_status();
+
+            // Avoid a tight spin when the device is quiet.
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        Ok(())
+    }
+
+    /// Invokes the status callback if the status interval has elapsed since the last report.
+    pub fn report_status(&mut self) {
+        let count = self.count();
+        if let Some((interval, callback)) = self.status_callback.as_mut()
+            && self.last_status.elapsed() >= *interval
+        {
+            self.last_status = Instant::now();
+            callback(count);
+        }
+    }
+
+    /// Returns the number of frames counted so far according to the `CountMode`.
+    pub fn count(&self) -> u64 {
+        match self.count_mode {
+            CountMode::DataPackets => self.data_packets,
+            CountMode::Responses => self.responses,
+            CountMode::Both => self.data_packets + self.responses,
+        }
+    }
+
+    /// Returns the number of data packets parsed so far.
+    pub fn data_packet_count(&self) -> u64 {
+        self.data_packets
+    }
+
+    /// Returns the number of command responses parsed so far.
+    pub fn response_count(&self) -> u64 {
+        self.responses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_serial::MockSerialPort;
+    use firm_core::constants::command::FIRMCommand;
+    use firm_core::constants::packet::PacketHeader;
+    use firm_core::framed_packet::FramedPacket;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_drain_nonblocking_skips_read_when_nothing_available() {
+        // A long mock timeout would make a real read block; the fast path must avoid it.
+        let (mut port, _device) = MockSerialPort::pair(Duration::from_secs(1));
+        let mut drainer = RxDrainer::new(CountMode::Both);
+
+        let start = Instant::now();
+        drainer.drain_nonblocking(&mut *port).unwrap();
+        assert!(start.elapsed() < Duration::from_millis(500));
+        assert_eq!(drainer.count(), 0);
+    }
+
+    #[test]
+    fn test_drain_counts_by_mode_and_reports_bytes() {
+        let (mut port, device) = MockSerialPort::pair(Duration::from_millis(1));
+        device.inject_framed_packet(FramedPacket::new(PacketHeader::Data, 0, vec![0u8; 120]));
+        device.inject_framed_packet(FramedPacket::new(PacketHeader::Data, 0, vec![0u8; 120]));
+        device.inject_framed_packet(FramedPacket::new(
+            PacketHeader::Response,
+            FIRMCommand::Cancel as u16,
+            vec![1],
+        ));
+
+        let seen = Arc::new(Mutex::new(0usize));
+        let seen_clone = seen.clone();
+        let mut drainer = RxDrainer::new(CountMode::DataPackets)
+            .with_hex_callback(move |bytes| *seen_clone.lock().unwrap() += bytes.len());
+        drainer.drain_nonblocking(&mut *port).unwrap();
+
+        assert_eq!(drainer.count(), 2);
+        assert_eq!(drainer.data_packet_count(), 2);
+        assert_eq!(drainer.response_count(), 1);
+        assert_eq!(*seen.lock().unwrap(), 2 * (8 + 120 + 2) + (8 + 1 + 2));
+    }
+
+    #[test]
+    fn test_drain_for_times_out_on_quiet_port() {
+        let (mut port, _device) = MockSerialPort::pair(Duration::from_millis(1));
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let reports_clone = reports.clone();
+        let mut drainer = RxDrainer::new(CountMode::Both)
+            .with_status(Duration::from_millis(10), move |count| {
+                reports_clone.lock().unwrap().push(count)
+            });
+
+        let start = Instant::now();
+        drainer
+            .drain_for(&mut *port, Duration::from_millis(50))
+            .unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        assert_eq!(drainer.count(), 0);
+
+        let reports = reports.lock().unwrap();
+        assert!(!reports.is_empty());
+        assert!(reports.iter().all(|&c| c == 0));
+    }
+
+    #[test]
+    fn test_drain_returns_read_errors() {
+        let (mut port, device) = MockSerialPort::pair(Duration::from_millis(1));
+        device.inject_framed_packet(FramedPacket::new(PacketHeader::Data, 0, vec![0u8; 120]));
+        device.disconnect();
+
+        let mut drainer = RxDrainer::new(CountMode::Both);
+        let err = drainer.drain_nonblocking(&mut *port).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    }
+}
@@ -0,0 +1,188 @@
+//! Device identity and configuration captured when a client starts, see
+//! `FIRMClient::set_session_capture`.
+//!
+//! Raw recordings and CSV exports carry the captured `SessionInfo` in their headers as
+//! `key=value` lines, so a file can be traced back to the device and settings it came from:
+//!
+//! ```text
+//! device_id=1234605616436508552
+//! firmware_version=v1.2.0
+//! device_name=Booster
+//! frequency_hz=100
+//! protocol=USB
+//! session_partial=false
+//! ```
+use firm_core::firm_packets::{DeviceConfig, DeviceInfo, DeviceProtocol};
+
+/// What the device reported about itself when the client started.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionInfo {
+    /// The device's answer to `GetDeviceInfo`, or `None` if it didn't answer in time.
+    pub device_info: Option<DeviceInfo>,
+    /// The device's answer to `GetDeviceConfig`, or `None` if it didn't answer in time.
+    pub device_config: Option<DeviceConfig>,
+    /// Set when either answer is missing.
+    pub partial: bool,
+}
+
+impl SessionInfo {
+    /// Creates the session info from the two answers, flagging it partial if either is missing.
+    pub fn new(device_info: Option<DeviceInfo>, device_config: Option<DeviceConfig>) -> Self {
+        let partial = device_info.is_none() || device_config.is_none();
+        Self {
+            device_info,
+            device_config,
+            partial,
+        }
+    }
+
+    /// Returns the header lines describing this session, without line endings.
+    pub fn header_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let Some(info) = &self.device_info {
+            lines.push(format!("device_id={}", info.id));
+            lines.push(format!(
+                "firmware_version={}",
+                single_line(&info.firmware_version)
+            ));
+        }
+        if let Some(config) = &self.device_config {
+            lines.push(format!("device_name={}", single_line(&config.name)));
+            lines.push(format!("frequency_hz={}", config.frequency));
+            lines.push(format!("protocol={:?}", config.protocol));
+        }
+        lines.push(format!("session_partial={}", self.partial));
+        lines
+    }
+
+    /// Rebuilds the session info from header lines written by `header_lines`. Other lines are
+    /// skipped, as are values that don't parse.
+    ///
+    /// # Arguments
+    ///
+    /// - `lines` (`impl IntoIterator<Item = &str>`) - The header's `key=value` lines.
+    ///
+    /// # Returns
+    ///
+    /// - `Option<Self>` - The session info, or `None` if the lines don't describe one.
+    pub fn from_header_lines<'a>(lines: impl IntoIterator<Item = &'a str>) -> Option<Self> {
+        let mut id = None;
+        let mut firmware_version = None;
+        let mut name = None;
+        let mut frequency = None;
+        let mut protocol = None;
+        let mut partial = None;
+        for line in lines {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            match key {
+                "device_id" => id = value.parse().ok(),
+                "firmware_version" => firmware_version = Some(value.to_string()),
+                "device_name" => name = Some(value.to_string()),
+                "frequency_hz" => frequency = value.parse().ok(),
+                "protocol" => protocol = protocol_from_name(value),
+                "session_partial" => partial = value.parse().ok(),
+                _ => {}
+            }
+        }
+        // `session_partial` is always written, so its absence means there's no session here.
+        let partial = partial?;
+
+        let device_info = match (id, firmware_version) {
+            (Some(id), Some(firmware_version)) => Some(DeviceInfo {
+                firmware_version,
+                id,
+            }),
+            _ => None,
+        };
+        let device_config = match (name, frequency, protocol) {
+            (Some(name), Some(frequency), Some(protocol)) => Some(DeviceConfig {
+                name,
+                frequency,
+                protocol,
+                suspect: false,
+            }),
+            _ => None,
+        };
+        Some(Self {
+            partial: partial || device_info.is_none() || device_config.is_none(),
+            device_info,
+            device_config,
+        })
+    }
+}
+
+/// Keeps a device-provided string from breaking the line-based headers.
+fn single_line(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect()
+}
+
+fn protocol_from_name(name: &str) -> Option<DeviceProtocol> {
+    match name {
+        "USB" => Some(DeviceProtocol::USB),
+        "UART" => Some(DeviceProtocol::UART),
+        "I2C" => Some(DeviceProtocol::I2C),
+        "SPI" => Some(DeviceProtocol::SPI),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_lines_round_trip() {
+        let complete = SessionInfo::new(
+            Some(DeviceInfo {
+                firmware_version: "v1.2.0".to_string(),
+                id: 0x1122_3344_5566_7788,
+            }),
+            Some(DeviceConfig {
+                name: "Booster".to_string(),
+                frequency: 100,
+                protocol: DeviceProtocol::UART,
+                suspect: false,
+            }),
+        );
+        assert!(!complete.partial);
+        let lines = complete.header_lines();
+        assert_eq!(
+            SessionInfo::from_header_lines(lines.iter().map(String::as_str)),
+            Some(complete)
+        );
+
+        let partial = SessionInfo::new(None, None);
+        assert!(partial.partial);
+        assert_eq!(partial.header_lines(), ["session_partial=true"]);
+        let lines = partial.header_lines();
+        assert_eq!(
+            SessionInfo::from_header_lines(lines.iter().map(String::as_str)),
+            Some(partial)
+        );
+
+        assert_eq!(SessionInfo::from_header_lines(["connection=mock"]), None);
+    }
+
+    #[test]
+    fn test_device_strings_stay_on_one_line() {
+        let session = SessionInfo::new(
+            None,
+            Some(DeviceConfig {
+                name: "two\nlines".to_string(),
+                frequency: 50,
+                protocol: DeviceProtocol::USB,
+                suspect: false,
+            }),
+        );
+        assert!(
+            session
+                .header_lines()
+                .contains(&"device_name=two lines".to_string())
+        );
+    }
+}
@@ -0,0 +1,731 @@
+//! A simulated FIRM device for running examples and tests without hardware.
+//!
+//! `DeviceSimulator` drives the device side of a `MockSerialPort` pair: it streams data packets
+//! generated by a `FlightProfileGenerator` and answers commands the way the firmware does.
+use crate::faults::{FaultInjector, FaultScenario};
+use crate::mock_serial::MockDeviceHandle;
+use crate::response_script::{ResponseScheduler, ResponseScript};
+use firm_core::constants::command::{
+    DEVICE_ID_LENGTH, DEVICE_NAME_LENGTH, FIRMCommand, FIRMWARE_VERSION_LENGTH,
+    IMU_CALIBRATION_PAYLOAD_LENGTH, MAGNETOMETER_CALIBRATION_PAYLOAD_LENGTH,
+    NUMBER_OF_CALIBRATION_OFFSETS, NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS,
+};
+use firm_core::constants::log_parsing::{
+    FIRMLogPacketType, HEADER_SIZE_TEXT, HEADER_TOTAL_SIZE, LOG_CLOCK_HZ,
+    LOG_FILE_EOF_PADDING_LENGTH, MMC5983MA_ID,
+};
+use firm_core::constants::packet::PacketHeader;
+use firm_core::firm_packets::{DeviceConfig, DeviceInfo, DeviceProtocol, FIRMData};
+use firm_core::framed_packet::FramedPacket;
+use firm_core::log_decoding::encode_magnetometer;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Standard gravity in m/s^2.
+const GRAVITY: f32 = 9.80665;
+/// Sea level pressure used by the barometric formula, in pascals.
+const SEA_LEVEL_PRESSURE_PASCALS: f32 = 101_325.0;
+
+/// Generates a deterministic, physically plausible flight: sitting on the pad, a constant
+/// thrust boost, an unpowered coast to apogee and a descent under a parachute.
+///
+/// The magnetic field vector sweeps the whole sphere throughout so magnetometer calibration
+/// has something to fit, with a fixed hard iron offset added on top.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlightProfileGenerator {
+    /// Seconds spent on the pad before launch.
+    pub pad_seconds: f64,
+    /// Length of the motor burn in seconds.
+    pub burn_seconds: f64,
+    /// Net upward acceleration during the burn, in gs.
+    pub boost_acceleration_gs: f64,
+    /// Descent rate under the parachute, in m/s.
+    pub descent_rate_meters_per_s: f64,
+    /// Earth's field strength seen by the magnetometer, in µT.
+    pub magnetic_field_strength_microteslas: f32,
+    /// Constant offset added to every magnetometer reading, in µT.
+    pub magnetometer_hard_iron_microteslas: [f32; 3],
+    /// Ground temperature in °C.
+    pub ground_temperature_celsius: f32,
+}
+
+impl Default for FlightProfileGenerator {
+    fn default() -> Self {
+        Self {
+            pad_seconds: 1.0,
+            burn_seconds: 2.0,
+            boost_acceleration_gs: 5.0,
+            descent_rate_meters_per_s: 20.0,
+            magnetic_field_strength_microteslas: 50.0,
+            magnetometer_hard_iron_microteslas: [12.0, -7.0, 4.0],
+            ground_temperature_celsius: 20.0,
+        }
+    }
+}
+
+/// Times (seconds from start) and values that split the flight into its phases.
+struct FlightPhases {
+    launch: f64,
+    burnout: f64,
+    apogee: f64,
+    landing: f64,
+    burnout_velocity: f64,
+    burnout_altitude: f64,
+    apogee_altitude: f64,
+}
+
+impl FlightProfileGenerator {
+    /// Returns the key times and values of the flight.
+    fn phases(&self) -> FlightPhases {
+        let g = GRAVITY as f64;
+        let boost = self.boost_acceleration_gs * g;
+        let burnout_velocity = boost * self.burn_seconds;
+        let burnout_altitude = 0.5 * boost * self.burn_seconds.powi(2);
+        let apogee_altitude = burnout_altitude + burnout_velocity.powi(2) / (2.0 * g);
+
+        let launch = self.pad_seconds;
+        let burnout = launch + self.burn_seconds;
+        let apogee = burnout + burnout_velocity / g;
+        FlightPhases {
+            launch,
+            burnout,
+            apogee,
+            landing: apogee + apogee_altitude / self.descent_rate_meters_per_s,
+            burnout_velocity,
+            burnout_altitude,
+            apogee_altitude,
+        }
+    }
+
+    /// Returns the vertical (altitude in m, velocity in m/s, net acceleration in gs) at
+    /// `t` seconds after the generator started.
+    fn vertical_state(&self, t: f64) -> (f64, f64, f64) {
+        let g = GRAVITY as f64;
+        let phases = self.phases();
+
+        if t < phases.launch || t >= phases.landing {
+            (0.0, 0.0, 0.0)
+        } else if t < phases.burnout {
+            let dt = t - phases.launch;
+            let boost = self.boost_acceleration_gs * g;
+            (
+                0.5 * boost * dt * dt,
+                boost * dt,
+                self.boost_acceleration_gs,
+            )
+        } else if t < phases.apogee {
+            let dt = t - phases.burnout;
+            (
+                phases.burnout_altitude + phases.burnout_velocity * dt - 0.5 * g * dt * dt,
+                phases.burnout_velocity - g * dt,
+                -1.0,
+            )
+        } else {
+            let dt = t - phases.apogee;
+            (
+                phases.apogee_altitude - self.descent_rate_meters_per_s * dt,
+                -self.descent_rate_meters_per_s,
+                0.0,
+            )
+        }
+    }
+
+    /// Returns the time from start until the rocket lands, in seconds.
+    pub fn flight_seconds(&self) -> f64 {
+        self.phases().landing
+    }
+
+    /// Returns the magnetometer reading at `t`, including the hard iron offset.
+    ///
+    /// The direction spins about two axes at incommensurate rates so it covers the sphere.
+    pub fn magnetic_field(&self, t: f64) -> [f32; 3] {
+        let polar = (1.3 * t) as f32;
+        let azimuth = (2.9 * t) as f32;
+        let strength = self.magnetic_field_strength_microteslas;
+        let offset = self.magnetometer_hard_iron_microteslas;
+        [
+            strength * polar.sin() * azimuth.cos() + offset[0],
+            strength * polar.sin() * azimuth.sin() + offset[1],
+            strength * polar.cos() + offset[2],
+        ]
+    }
+
+    /// Generates the data packet the device would report `t` seconds after starting.
+    ///
+    /// # Arguments
+    ///
+    /// - `t` (`f64`) - Seconds since the generator started, also used as the packet timestamp.
+    ///
+    /// # Returns
+    ///
+    /// - `FIRMData` - The simulated packet.
+    pub fn sample(&self, t: f64) -> FIRMData {
+        let (altitude, velocity, net_acceleration_gs) = self.vertical_state(t);
+        let altitude = altitude as f32;
+        // Accelerometers measure specific force, so they read 1g at rest and 0g in free fall.
+        let specific_force_gs = net_acceleration_gs as f32 + 1.0;
+        let [mag_x, mag_y, mag_z] = self.magnetic_field(t);
+
+        FIRMData {
+            timestamp_seconds: t,
+            temperature_celsius: self.ground_temperature_celsius - 0.0065 * altitude,
+            pressure_pascals: SEA_LEVEL_PRESSURE_PASCALS
+                * (1.0 - 2.255_77e-5 * altitude).powf(5.255_88),
+            raw_acceleration_x_gs: 0.0,
+            raw_acceleration_y_gs: 0.0,
+            raw_acceleration_z_gs: specific_force_gs,
+            raw_angular_rate_x_deg_per_s: 0.0,
+            raw_angular_rate_y_deg_per_s: 0.0,
+            raw_angular_rate_z_deg_per_s: 0.0,
+            magnetic_field_x_microteslas: mag_x,
+            magnetic_field_y_microteslas: mag_y,
+            magnetic_field_z_microteslas: mag_z,
+            est_position_x_meters: 0.0,
+            est_position_y_meters: 0.0,
+            est_position_z_meters: altitude,
+            est_velocity_x_meters_per_s: 0.0,
+            est_velocity_y_meters_per_s: 0.0,
+            est_velocity_z_meters_per_s: velocity as f32,
+            est_acceleration_x_gs: 0.0,
+            est_acceleration_y_gs: 0.0,
+            est_acceleration_z_gs: specific_force_gs,
+            est_angular_rate_x_rad_per_s: 0.0,
+            est_angular_rate_y_rad_per_s: 0.0,
+            est_angular_rate_z_rad_per_s: 0.0,
+            est_quaternion_w: 1.0,
+            est_quaternion_x: 0.0,
+            est_quaternion_y: 0.0,
+            est_quaternion_z: 0.0,
+            pressure_altitude_meters: 0.0,
+        }
+    }
+}
+
+/// Encodes a `FIRMData` as the payload of a data frame, in the firmware's field order. The
+/// same as `FIRMData::to_bytes`.
+pub fn data_payload(data: &FIRMData) -> Vec<u8> {
+    data.to_bytes()
+}
+
+/// Builds a small mock log file (`.frm` format) of magnetometer readings taken from `profile`,
+/// suitable for `FIRMClient::start_mock_log_stream` and the mock streaming examples.
+///
+/// # Arguments
+///
+/// - `profile` (`&FlightProfileGenerator`) - Source of the logged readings.
+/// - `duration_seconds` (`f64`) - Length of the log.
+/// - `rate_hz` (`f64`) - Rate at which readings are logged.
+///
+/// # Returns
+///
+/// - `Vec<u8>` - The log file contents: header, sensor packets and end-of-data padding.
+pub fn build_mock_log(
+    profile: &FlightProfileGenerator,
+    duration_seconds: f64,
+    rate_hz: f64,
+) -> Vec<u8> {
+    let mut log = vec![0u8; HEADER_TOTAL_SIZE];
+    let text = b"FIRM LOG v1.0";
+    log[..text.len().min(HEADER_SIZE_TEXT)].copy_from_slice(text);
+
+    let packet_count = (duration_seconds * rate_hz) as usize;
+    for i in 0..packet_count {
+        let t = i as f64 / rate_hz;
+        let clock_count = (t * LOG_CLOCK_HZ) as u64 as u32;
+        log.push(MMC5983MA_ID);
+        log.extend_from_slice(&clock_count.to_le_bytes());
+        log.extend_from_slice(&encode_magnetometer(profile.magnetic_field(t)));
+    }
+
+    log.extend(std::iter::repeat_n(0u8, LOG_FILE_EOF_PADDING_LENGTH + 1));
+    log
+}
+
+/// Device side state the simulator answers commands from.
+struct SimulatedDeviceState {
+    device_info: DeviceInfo,
+    device_config: DeviceConfig,
+    /// `[IMU calibration payload][magnetometer calibration payload]`, in the order
+    /// `GetCalibration` reports them.
+    calibration: Vec<u8>,
+    in_mock_mode: bool,
+}
+
+impl SimulatedDeviceState {
+    /// Returns the response payload for `command`, or `None` if the firmware doesn't reply.
+    fn handle_command(&mut self, command: FIRMCommand, payload: &[u8]) -> Option<Vec<u8>> {
+        match command {
+            FIRMCommand::GetDeviceInfo => {
+                let mut reply = Vec::with_capacity(DEVICE_ID_LENGTH + FIRMWARE_VERSION_LENGTH);
+                reply.extend_from_slice(&self.device_info.id.to_le_bytes());
+                reply.extend_from_slice(&padded::<FIRMWARE_VERSION_LENGTH>(
+                    &self.device_info.firmware_version,
+                ));
+                Some(reply)
+            }
+            FIRMCommand::GetDeviceConfig => {
+                let mut reply = padded::<DEVICE_NAME_LENGTH>(&self.device_config.name).to_vec();
+                reply.extend_from_slice(&self.device_config.frequency.to_le_bytes());
+                reply.push(self.device_config.protocol as u8);
+                Some(reply)
+            }
+            FIRMCommand::SetDeviceConfig => {
+                let accepted = payload.len() > DEVICE_NAME_LENGTH + 2;
+                if accepted {
+                    let name = &payload[..DEVICE_NAME_LENGTH];
+                    let name_end = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+                    self.device_config.name = String::from_utf8_lossy(&name[..name_end]).into();
+                    self.device_config.frequency = u16::from_le_bytes([
+                        payload[DEVICE_NAME_LENGTH],
+                        payload[DEVICE_NAME_LENGTH + 1],
+                    ]);
+                    self.device_config.protocol =
+                        DeviceProtocol::try_from(payload[DEVICE_NAME_LENGTH + 2])
+                            .unwrap_or(DeviceProtocol::USB);
+                }
+                Some(vec![accepted as u8])
+            }
+            FIRMCommand::SetIMUCalibration => {
+                let accepted = payload.len() == IMU_CALIBRATION_PAYLOAD_LENGTH;
+                if accepted {
+                    self.calibration[..IMU_CALIBRATION_PAYLOAD_LENGTH].copy_from_slice(payload);
+                }
+                Some(vec![accepted as u8])
+            }
+            FIRMCommand::SetMagnetometerCalibration => {
+                let accepted = payload.len() == MAGNETOMETER_CALIBRATION_PAYLOAD_LENGTH;
+                if accepted {
+                    self.calibration[IMU_CALIBRATION_PAYLOAD_LENGTH..].copy_from_slice(payload);
+                }
+                Some(vec![accepted as u8])
+            }
+            FIRMCommand::GetCalibration => Some(self.calibration.clone()),
+            FIRMCommand::Mock => {
+                self.in_mock_mode = true;
+                Some(vec![1])
+            }
+            FIRMCommand::Cancel => {
+                self.in_mock_mode = false;
+                Some(vec![1])
+            }
+            FIRMCommand::Reboot => {
+                self.in_mock_mode = false;
+                None
+            }
+        }
+    }
+}
+
+/// Pads or truncates `text` into a fixed-size, zero-filled field.
+fn padded<const N: usize>(text: &str) -> [u8; N] {
+    let mut field = [0u8; N];
+    let len = text.len().min(N);
+    field[..len].copy_from_slice(&text.as_bytes()[..len]);
+    field
+}
+
+/// Identity calibration for every sensor, laid out as a `GetCalibration` payload.
+fn identity_calibration() -> Vec<u8> {
+    let offsets = [0.0f32; NUMBER_OF_CALIBRATION_OFFSETS];
+    let mut identity = [0.0f32; NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS];
+    identity[0] = 1.0;
+    identity[4] = 1.0;
+    identity[8] = 1.0;
+
+    let mut payload = Vec::with_capacity(
+        IMU_CALIBRATION_PAYLOAD_LENGTH + MAGNETOMETER_CALIBRATION_PAYLOAD_LENGTH,
+    );
+    for _ in 0..3 {
+        for value in offsets.iter().chain(identity.iter()) {
+            payload.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    payload
+}
+
+/// A simulated FIRM device.
+///
+/// Outside mock mode it streams `FlightProfileGenerator` packets at `rate_hz`, restarting the
+/// flight once it lands. In mock mode it stops streaming and instead answers every logged
+/// sensor packet it receives with one data packet, timestamped from the log's clock, like the
+/// firmware does during playback.
+pub struct DeviceSimulator {
+    rate_hz: f64,
+    profile: FlightProfileGenerator,
+    device_info: DeviceInfo,
+    device_config: DeviceConfig,
+    faults: FaultScenario,
+    responses: ResponseScript,
+}
+
+impl DeviceSimulator {
+    /// Creates a simulator streaming the default flight profile.
+    ///
+    /// # Arguments
+    ///
+    /// - `rate_hz` (`f64`) - Data packet rate, in Hz.
+    pub fn new(rate_hz: f64) -> Self {
+        Self {
+            rate_hz,
+            profile: FlightProfileGenerator::default(),
+            device_info: DeviceInfo {
+                firmware_version: "v1.0.0".to_string(),
+                id: 0x5157_4D55_4C41_5445,
+            },
+            device_config: DeviceConfig {
+                name: "FIRM Simulator".to_string(),
+                frequency: rate_hz.round().clamp(1.0, u16::MAX as f64) as u16,
+                protocol: DeviceProtocol::USB,
+                suspect: false,
+            },
+            faults: FaultScenario::default(),
+            responses: ResponseScript::default(),
+        }
+    }
+
+    /// Streams `profile` instead of the default flight.
+    pub fn with_profile(mut self, profile: FlightProfileGenerator) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Reports `device_info` in response to `GetDeviceInfo`.
+    pub fn with_device_info(mut self, device_info: DeviceInfo) -> Self {
+        self.device_info = device_info;
+        self
+    }
+
+    /// Corrupts the streamed packets according to `faults`, with windows measured in packet
+    /// timestamp seconds.
+    pub fn with_faults(mut self, faults: FaultScenario) -> Self {
+        self.faults = faults;
+        self
+    }
+
+    /// Delays, drops, duplicates or reorders command replies according to `responses`.
+    pub fn with_response_script(mut self, responses: ResponseScript) -> Self {
+        self.responses = responses;
+        self
+    }
+
+    /// Starts simulating on a background thread, driving `device`.
+    ///
+    /// # Arguments
+    ///
+    /// - `device` (`MockDeviceHandle`) - The device side of a `MockSerialPort` pair, e.g. from
+    ///   `FIRMClient::new_mock`.
+    ///
+    /// # Returns
+    ///
+    /// - `SimulatorHandle` - Stops the simulation when dropped.
+    pub fn spawn(self, device: MockDeviceHandle) -> SimulatorHandle {
+        let stop = Arc::new(AtomicBool::new(false));
+        let packets_sent = Arc::new(AtomicU64::new(0));
+        let join_handle = {
+            let stop = stop.clone();
+            let packets_sent = packets_sent.clone();
+            thread::spawn(move || self.run(&device, &stop, &packets_sent))
+        };
+
+        SimulatorHandle {
+            stop,
+            packets_sent,
+            join_handle: Some(join_handle),
+        }
+    }
+
+    fn run(self, device: &MockDeviceHandle, stop: &AtomicBool, packets_sent: &AtomicU64) {
+        let tick = Duration::from_millis(5);
+        let period = Duration::from_secs_f64(1.0 / self.rate_hz.max(f64::MIN_POSITIVE));
+        let mut state = SimulatedDeviceState {
+            device_info: self.device_info.clone(),
+            device_config: self.device_config.clone(),
+            calibration: identity_calibration(),
+            in_mock_mode: false,
+        };
+        let mut faults = FaultInjector::new(self.faults.clone());
+        let mut replies = ResponseScheduler::new(self.responses.clone());
+
+        let start = Instant::now();
+        let mut next_packet = start;
+        while !stop.load(Ordering::Relaxed) {
+            while let Ok(Some(frame)) = device.wait_for_command_frame(Duration::ZERO) {
+                self.handle_frame(
+                    device,
+                    &mut state,
+                    &mut faults,
+                    &mut replies,
+                    &frame,
+                    packets_sent,
+                );
+            }
+
+            let now = Instant::now();
+            for reply in replies.take_due(now) {
+                match reply.mid_burst {
+                    Some(packets) if !state.in_mock_mode => {
+                        // Send the next `packets` data packets early, all in one write, with
+                        // the reply in the middle.
+                        let mut burst = Vec::new();
+                        for i in 0..packets {
+                            if i == packets / 2 {
+                                burst.extend_from_slice(&reply.frame.to_bytes());
+                            }
+                            let t = next_packet.duration_since(start).as_secs_f64();
+                            burst.extend_from_slice(&self.data_packet(t, &mut faults).to_bytes());
+                            next_packet += period;
+                        }
+                        if packets == 0 {
+                            burst.extend_from_slice(&reply.frame.to_bytes());
+                        }
+                        device.inject_bytes(&burst);
+                        packets_sent.fetch_add(packets as u64, Ordering::Relaxed);
+                    }
+                    _ => device.inject_framed_packet(reply.frame),
+                }
+            }
+
+            if state.in_mock_mode {
+                next_packet = now;
+            } else {
+                // Catch up on packets due since the last tick so the rate holds at high rates.
+                while next_packet <= now {
+                    let t = next_packet.duration_since(start).as_secs_f64();
+                    device.inject_framed_packet(self.data_packet(t, &mut faults));
+                    packets_sent.fetch_add(1, Ordering::Relaxed);
+                    next_packet += period;
+                }
+            }
+
+            thread::sleep(tick);
+        }
+    }
+
+    /// Returns the streamed data packet for `t` seconds after the simulation started.
+    fn data_packet(&self, t: f64, faults: &mut FaultInjector) -> FramedPacket {
+        let flight_seconds = self.profile.flight_seconds() + 1.0;
+        let mut data = FIRMData {
+            timestamp_seconds: t,
+            ..self.profile.sample(t % flight_seconds)
+        };
+        faults.apply(&mut data);
+        FramedPacket::new(PacketHeader::Data, 0, data_payload(&data))
+    }
+
+    fn handle_frame(
+        &self,
+        device: &MockDeviceHandle,
+        state: &mut SimulatedDeviceState,
+        faults: &mut FaultInjector,
+        replies: &mut ResponseScheduler,
+        frame: &FramedPacket,
+        packets_sent: &AtomicU64,
+    ) {
+        match frame.header() {
+            PacketHeader::Command => {
+                let Ok(command) = FIRMCommand::from_u16(frame.identifier()) else {
+                    return;
+                };
+                if let Some(reply) = state.handle_command(command, frame.payload()) {
+                    replies.schedule(
+                        command,
+                        FramedPacket::new(PacketHeader::Response, command.to_u16(), reply),
+                        Instant::now(),
+                    );
+                }
+            }
+            PacketHeader::LogSensor if state.in_mock_mode => {
+                if frame.identifier() == FIRMLogPacketType::HeaderPacket.as_u16() {
+                    return;
+                }
+                let Some(clock) = frame.payload().first_chunk::<4>() else {
+                    return;
+                };
+                let t = u32::from_le_bytes(*clock) as f64 / LOG_CLOCK_HZ;
+                let mut data = self.profile.sample(t);
+                faults.apply(&mut data);
+                device.inject_framed_packet(FramedPacket::new(
+                    PacketHeader::Data,
+                    0,
+                    data_payload(&data),
+                ));
+                packets_sent.fetch_add(1, Ordering::Relaxed);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Handle to a running `DeviceSimulator`. Dropping it stops the simulation.
+pub struct SimulatorHandle {
+    stop: Arc<AtomicBool>,
+    packets_sent: Arc<AtomicU64>,
+    join_handle: Option<JoinHandle<()>>,
+}
+
+impl SimulatorHandle {
+    /// Returns how many data packets the simulator has sent so far.
+    pub fn packets_sent(&self) -> u64 {
+        self.packets_sent.load(Ordering::Relaxed)
+    }
+
+    /// Stops the simulation and waits for its thread to exit.
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.join_handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for SimulatorHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FIRMClient;
+    use firm_core::calibration::MagnetometerCalibrator;
+    use firm_core::framed_packet::Framed;
+    use firm_core::log_parsing::LogParser;
+
+    #[test]
+    fn test_data_payload_round_trips_through_parser() {
+        let profile = FlightProfileGenerator::default();
+        let data = profile.sample(2.0);
+        assert_eq!(FIRMData::from_bytes(&data_payload(&data)), Ok(data));
+    }
+
+    #[test]
+    fn test_flight_profile_climbs_then_lands() {
+        let profile = FlightProfileGenerator::default();
+        assert_eq!(profile.sample(0.0).est_position_z_meters, 0.0);
+        assert_eq!(profile.sample(0.0).raw_acceleration_z_gs, 1.0);
+
+        let burnout = profile.sample(profile.pad_seconds + profile.burn_seconds);
+        assert!(burnout.est_position_z_meters > 0.0);
+        assert!(burnout.pressure_pascals < SEA_LEVEL_PRESSURE_PASCALS);
+
+        let landed = profile.sample(profile.flight_seconds() + 0.5);
+        assert_eq!(landed.est_position_z_meters, 0.0);
+        assert_eq!(landed.est_velocity_z_meters_per_s, 0.0);
+    }
+
+    #[test]
+    fn test_magnetic_field_is_calibratable() {
+        let profile = FlightProfileGenerator::default();
+        let mut calibrator = MagnetometerCalibrator::new();
+        calibrator.start();
+        for i in 0..200 {
+            let [x, y, z] = profile.magnetic_field(i as f64 * 0.01);
+            calibrator.add_sample_xyz(x, y, z);
+        }
+        let (offsets, _) = calibrator.calculate().unwrap().to_arrays();
+        for (found, expected) in offsets
+            .iter()
+            .zip(profile.magnetometer_hard_iron_microteslas)
+        {
+            assert!((found - expected).abs() < 0.5, "{offsets:?}");
+        }
+    }
+
+    #[test]
+    fn test_mock_log_parses() {
+        let log = build_mock_log(&FlightProfileGenerator::default(), 0.5, 100.0);
+        let mut parser = LogParser::new();
+        parser.read_header(&log[..HEADER_TOTAL_SIZE]);
+        parser.parse_bytes(&log[HEADER_TOTAL_SIZE..]);
+        assert!(parser.eof_reached());
+
+        let mut count = 0;
+        while let Some((packet, delay)) = parser.get_packet_and_time_delay() {
+            if count > 0 {
+                assert!((delay - 0.01).abs() < 1e-6);
+            }
+            assert_eq!(
+                packet.to_bytes()[2..4],
+                FIRMLogPacketType::MagnetometerPacket.as_u16().to_le_bytes()
+            );
+            count += 1;
+        }
+        assert_eq!(count, 50);
+    }
+
+    #[test]
+    fn test_simulator_streams_and_answers_commands() {
+        let (mut client, device) = FIRMClient::new_mock(0.001);
+        let simulator = DeviceSimulator::new(200.0).spawn(device);
+        client.start().unwrap();
+
+        let info = client
+            .get_device_info(Duration::from_secs(1))
+            .unwrap()
+            .unwrap();
+        assert_eq!(info.firmware_version, "v1.0.0");
+
+        let config = DeviceConfig {
+            name: "bench".to_string(),
+            frequency: 50,
+            protocol: DeviceProtocol::UART,
+            suspect: false,
+        };
+        assert_eq!(
+            client
+                .set_device_config(
+                    config.name.clone(),
+                    config.frequency,
+                    config.protocol,
+                    Duration::from_secs(1)
+                )
+                .unwrap(),
+            Some(true)
+        );
+        assert_eq!(
+            client.get_device_config(Duration::from_secs(1)).unwrap(),
+            Some(config)
+        );
+
+        let packets = client
+            .iter_packets_timeout(Duration::from_secs(1))
+            .take(20)
+            .count();
+        assert_eq!(packets, 20);
+        assert!(simulator.packets_sent() >= 20);
+
+        client.stop();
+        simulator.stop();
+    }
+
+    #[test]
+    fn test_simulator_applies_fault_scenario() {
+        let scenario = FaultScenario::parse("0.2.. barometer nan").unwrap();
+        let (mut client, device) = FIRMClient::new_mock(0.001);
+        let simulator = DeviceSimulator::new(200.0)
+            .with_faults(scenario)
+            .spawn(device);
+        client.start().unwrap();
+
+        let packets: Vec<FIRMData> = client
+            .iter_packets_timeout(Duration::from_secs(2))
+            .take_while(|packet| packet.timestamp_seconds < 0.4)
+            .collect();
+        client.stop();
+        simulator.stop();
+
+        assert!(packets.iter().any(|p| p.timestamp_seconds >= 0.2));
+        for packet in &packets {
+            let faulted = packet.timestamp_seconds >= 0.2;
+            assert_eq!(packet.pressure_pascals.is_nan(), faulted, "{packet:?}");
+            assert!(!packet.raw_acceleration_z_gs.is_nan());
+        }
+    }
+}
@@ -0,0 +1,403 @@
+//! Splitting a client between a packet consumer and a control task, see `FIRMClient::split`.
+//!
+//! Both halves share the client. The reader thread keeps running until both are dropped or
+//! `Controller::shutdown` is called, so either side can finish first.
+use crate::link_stats::{LinkCounters, LinkStats};
+use crate::packet_queue::{PacketReceiver, QueueDepth};
+use crate::{ErrorEvent, FIRMClient, FIRMClientError, PacketIter, PacketSource, is_aged_out};
+use anyhow::Result;
+use firm_core::firm_packets::{DeviceConfig, DeviceInfo, DeviceProtocol, FIRMBaroPacket, FIRMData};
+use std::mem;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, channel};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+/// The client both halves share. `None` once `Controller::shutdown` has dropped it.
+type SharedClient = Arc<Mutex<Option<FIRMClient>>>;
+
+/// The packet consuming half of a split client, e.g. for a UI thread.
+///
+/// Dropping it hands the packet queue back to the client, which then keeps only the newest
+/// packet so the queue doesn't grow while nobody reads it.
+pub struct PacketStream {
+    /// `Some` until dropped.
+    packet_receiver: Option<PacketReceiver>,
+    baro_receiver: Receiver<FIRMBaroPacket>,
+    running: Arc<AtomicBool>,
+    max_packet_age_seconds: Option<f64>,
+    newest_timestamp_bits: Arc<AtomicU64>,
+    aged_out_packets: Arc<AtomicU64>,
+    client: SharedClient,
+}
+
+/// The control half of a split client, for sending commands and stopping it from another
+/// task than the one reading packets.
+pub struct Controller {
+    client: SharedClient,
+    running: Arc<AtomicBool>,
+    link_counters: Arc<Mutex<LinkCounters>>,
+    aged_out_packets: Arc<AtomicU64>,
+    packet_depth: Arc<QueueDepth>,
+}
+
+impl FIRMClient {
+    /// Splits the client into a `PacketStream` that receives packets and a `Controller` that
+    /// sends commands, so the two can live on different threads. Settings like
+    /// `set_max_packet_age` carry over as they were at the split.
+    ///
+    /// # Returns
+    ///
+    /// - `(PacketStream, Controller)` - The two halves, both `Send`.
+    pub fn split(mut self) -> (PacketStream, Controller) {
+        // The client keeps a receiver with no sender, so anything it reads itself comes up empty.
+        let packet_receiver =
+            mem::replace(&mut self.packet_receiver, PacketReceiver::disconnected());
+        let baro_receiver = mem::replace(&mut self.baro_receiver, channel().1);
+        let controller = Controller {
+            client: Arc::new(Mutex::new(None)),
+            running: self.running.clone(),
+            link_counters: self.link_counters.clone(),
+            aged_out_packets: self.aged_out_packets.clone(),
+            packet_depth: self.packet_depth.clone(),
+        };
+        let stream = PacketStream {
+            packet_receiver: Some(packet_receiver),
+            baro_receiver,
+            running: self.running.clone(),
+            max_packet_age_seconds: self.max_packet_age_seconds,
+            newest_timestamp_bits: self.newest_timestamp_bits.clone(),
+            aged_out_packets: self.aged_out_packets.clone(),
+            client: controller.client.clone(),
+        };
+        *controller.client.lock().unwrap() = Some(self);
+        (stream, controller)
+    }
+}
+
+impl PacketStream {
+    /// Retrieves all available data packets, like `FIRMClient::get_data_packets`.
+    ///
+    /// # Arguments
+    ///
+    /// - `timeout` (`Option<Duration>`) - If `Some(duration)`, the method will block for up to `duration` waiting for a packet.
+    pub fn get_data_packets(
+        &self,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<FIRMData>, RecvTimeoutError> {
+        Ok(self
+            .get_packets_with_host_time(timeout)?
+            .into_iter()
+            .map(|(packet, _)| packet)
+            .collect())
+    }
+
+    /// Like `get_data_packets`, but with the host time each packet was received at, like
+    /// `FIRMClient::get_packets_with_host_time`.
+    ///
+    /// # Arguments
+    ///
+    /// - `timeout` (`Option<Duration>`) - If `Some(duration)`, the method will block for up to `duration` waiting for a packet.
+    pub fn get_packets_with_host_time(
+        &self,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<(FIRMData, SystemTime)>, RecvTimeoutError> {
+        let receiver = self.packet_receiver();
+        let mut packets = Vec::new();
+        if let Some(duration) = timeout {
+            packets.push(receiver.recv_timeout(duration)?);
+        }
+        packets.extend(receiver.try_iter());
+        packets.retain(|(packet, _)| !PacketSource::age_out(self, packet));
+        Ok(packets)
+    }
+
+    /// Retrieves all available secondary barometer readings, like `FIRMClient::get_baro_packets`.
+    ///
+    /// # Arguments
+    ///
+    /// - `timeout` (`Option<Duration>`) - If `Some(duration)`, the method will block for up to `duration` waiting for a reading.
+    pub fn get_baro_packets(
+        &self,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<FIRMBaroPacket>, RecvTimeoutError> {
+        let mut packets = Vec::new();
+        if let Some(duration) = timeout {
+            packets.push(self.baro_receiver.recv_timeout(duration)?);
+        }
+        packets.extend(self.baro_receiver.try_iter());
+        Ok(packets)
+    }
+
+    /// Returns a blocking iterator over incoming data packets, like `FIRMClient::iter_packets`.
+    /// It ends once the client stops, e.g. after `Controller::shutdown`.
+    pub fn iter_packets(&self) -> PacketIter<'_> {
+        PacketIter {
+            source: self,
+            idle_timeout: None,
+            finished: false,
+        }
+    }
+
+    /// Like `iter_packets`, but also ends when no packet arrives for `idle_timeout`.
+    ///
+    /// # Arguments
+    ///
+    /// - `idle_timeout` (`Duration`) - How long the stream may stay quiet before the iterator ends.
+    pub fn iter_packets_timeout(&self, idle_timeout: Duration) -> PacketIter<'_> {
+        PacketIter {
+            source: self,
+            idle_timeout: Some(idle_timeout),
+            finished: false,
+        }
+    }
+
+    /// Returns true while the reader thread is running.
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
+    }
+
+    /// Returns how many packets are queued for this stream, see `FIRMClient::backlog`.
+    pub fn backlog(&self) -> usize {
+        self.packet_receiver().depth()
+    }
+}
+
+impl PacketSource for PacketStream {
+    fn packet_receiver(&self) -> &PacketReceiver {
+        self.packet_receiver
+            .as_ref()
+            .expect("the receiver is only taken on drop")
+    }
+
+    fn age_out(&self, packet: &FIRMData) -> bool {
+        is_aged_out(
+            packet,
+            self.max_packet_age_seconds,
+            &self.newest_timestamp_bits,
+            &self.aged_out_packets,
+        )
+    }
+
+    fn is_running(&self) -> bool {
+        PacketStream::is_running(self)
+    }
+}
+
+impl Drop for PacketStream {
+    fn drop(&mut self) {
+        // The reader thread stops once its packet receiver is gone, so the client takes it back.
+        let mut client = self
+            .client
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let (Some(client), Some(receiver)) = (client.as_mut(), self.packet_receiver.take()) {
+            client.packet_receiver = receiver;
+            client.set_latest_only(true);
+        }
+    }
+}
+
+impl Controller {
+    /// Runs `f` on the shared client, or returns `FIRMClientError::ShutDown` after `shutdown`.
+    fn with_client<T>(&self, f: impl FnOnce(&mut FIRMClient) -> T) -> Result<T, FIRMClientError> {
+        let mut client = self
+            .client
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        client.as_mut().map(f).ok_or(FIRMClientError::ShutDown)
+    }
+
+    /// Starts the reader thread, see `FIRMClient::start`.
+    pub fn start(&self) -> Result<(), FIRMClientError> {
+        self.with_client(FIRMClient::start)?
+    }
+
+    /// Stops the reader thread. The client can be started again, unlike after `shutdown`.
+    pub fn stop(&self) {
+        let _ = self.with_client(FIRMClient::stop);
+    }
+
+    /// Stops the client and closes the port for both halves, without waiting for the
+    /// `PacketStream` to be dropped. Its iterators end once they've handed out what was read.
+    pub fn shutdown(&self) {
+        let client = self
+            .client
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take();
+        drop(client);
+    }
+
+    /// Returns true while the reader thread is running.
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
+    }
+
+    /// Pauses a log file playback, see `FIRMClient::pause`.
+    pub fn pause(&self) -> Result<()> {
+        self.with_client(|client| client.pause())?
+    }
+
+    /// Resumes a paused log file playback, see `FIRMClient::resume`.
+    pub fn resume(&self) -> Result<()> {
+        self.with_client(|client| client.resume())?
+    }
+
+    /// Returns a snapshot of link health, see `FIRMClient::stats`. Doesn't wait for a command
+    /// in progress, and keeps working after `shutdown`.
+    pub fn stats(&self) -> LinkStats {
+        self.link_counters.lock().unwrap().snapshot(
+            Instant::now(),
+            self.aged_out_packets.load(Ordering::Relaxed),
+            &self.packet_depth,
+        )
+    }
+
+    /// Returns the oldest error from the background threads not yet returned, see
+    /// `FIRMClient::check_error`.
+    pub fn check_error(&self) -> Option<ErrorEvent> {
+        self.with_client(|client| client.check_error()).ok()?
+    }
+
+    /// Returns every error from the background threads not yet returned, see
+    /// `FIRMClient::drain_errors`.
+    pub fn drain_errors(&self) -> Vec<ErrorEvent> {
+        self.with_client(|client| client.drain_errors())
+            .unwrap_or_default()
+    }
+
+    /// Requests device info and waits for the response, see `FIRMClient::get_device_info`.
+    pub fn get_device_info(&self, timeout: Duration) -> Result<Option<DeviceInfo>> {
+        self.with_client(|client| client.get_device_info(timeout))?
+    }
+
+    /// Requests device configuration and waits for the response.
+    pub fn get_device_config(&self, timeout: Duration) -> Result<Option<DeviceConfig>> {
+        self.with_client(|client| client.get_device_config(timeout))?
+    }
+
+    /// Sets device configuration and waits for acknowledgement.
+    pub fn set_device_config(
+        &self,
+        name: String,
+        frequency: u16,
+        protocol: DeviceProtocol,
+        timeout: Duration,
+    ) -> Result<Option<bool>> {
+        self.with_client(|client| client.set_device_config(name, frequency, protocol, timeout))?
+    }
+
+    /// Sends a cancel command and waits for acknowledgement.
+    pub fn cancel(&self, timeout: Duration) -> Result<Option<bool>> {
+        self.with_client(|client| client.cancel(timeout))?
+    }
+
+    /// Sends a reboot command.
+    pub fn reboot(&self) -> Result<()> {
+        self.with_client(|client| client.reboot())?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_serial::MockDeviceHandle;
+    use firm_core::constants::packet::PacketHeader;
+    use firm_core::framed_packet::FramedPacket;
+    use std::thread;
+
+    fn assert_send<T: Send>() {}
+
+    fn inject_packets(device: &MockDeviceHandle, timestamps: impl IntoIterator<Item = f64>) {
+        for t in timestamps {
+            let mut payload = vec![0u8; 120];
+            payload[0..8].copy_from_slice(&t.to_le_bytes());
+            device.inject_framed_packet(FramedPacket::new(PacketHeader::Data, 0, payload));
+        }
+    }
+
+    fn started_split() -> (PacketStream, Controller, MockDeviceHandle) {
+        let (mut client, device) = FIRMClient::new_mock(0.01);
+        client.start().unwrap();
+        let (stream, controller) = client.split();
+        (stream, controller, device)
+    }
+
+    #[test]
+    fn test_halves_are_send_and_share_the_reader() {
+        assert_send::<PacketStream>();
+        assert_send::<Controller>();
+
+        let (stream, controller, device) = started_split();
+        let reader = thread::spawn(move || {
+            let packets: Vec<f64> = stream
+                .iter_packets_timeout(Duration::from_secs(2))
+                .take(3)
+                .map(|p| p.timestamp_seconds)
+                .collect();
+            (stream, packets)
+        });
+        inject_packets(&device, [1.0, 2.0, 3.0]);
+        let (stream, packets) = reader.join().unwrap();
+        assert_eq!(packets, [1.0, 2.0, 3.0]);
+        assert!(controller.stats().packets_parsed >= 3);
+        assert!(stream.is_running() && controller.is_running());
+    }
+
+    #[test]
+    fn test_dropping_the_stream_keeps_the_controller_working() {
+        let (stream, controller, device) = started_split();
+        drop(stream);
+        inject_packets(&device, [1.0, 2.0]);
+        thread::sleep(Duration::from_millis(100));
+        assert!(controller.is_running());
+        assert!(controller.stats().packets_parsed >= 2);
+
+        // The reader thread stops with the last half.
+        let running = controller.running.clone();
+        drop(controller);
+        assert!(!running.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_dropping_the_controller_keeps_packets_flowing() {
+        let (stream, controller, device) = started_split();
+        drop(controller);
+        inject_packets(&device, [1.0]);
+        let packets = stream
+            .get_data_packets(Some(Duration::from_secs(1)))
+            .unwrap();
+        assert_eq!(packets.len(), 1);
+        assert!(stream.is_running());
+
+        let running = stream.running.clone();
+        drop(stream);
+        assert!(!running.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_shutdown_ends_the_stream_from_another_thread() {
+        let (stream, controller, device) = started_split();
+        inject_packets(&device, [1.0, 2.0]);
+        let reader = thread::spawn(move || stream.iter_packets().count());
+        thread::sleep(Duration::from_millis(100));
+
+        controller.shutdown();
+        // The iterator still hands out what was read before the shutdown, then ends.
+        assert_eq!(reader.join().unwrap(), 2);
+        assert!(!controller.is_running());
+        assert_eq!(controller.start(), Err(FIRMClientError::ShutDown));
+        assert!(
+            controller
+                .get_device_info(Duration::from_millis(10))
+                .unwrap_err()
+                .downcast_ref::<FIRMClientError>()
+                .is_some_and(|e| *e == FIRMClientError::ShutDown)
+        );
+        // Stats stay readable, and stopping twice is harmless.
+        assert_eq!(controller.stats().packets_parsed, 2);
+        controller.stop();
+        controller.shutdown();
+    }
+}
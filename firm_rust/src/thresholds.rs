@@ -0,0 +1,211 @@
+//! Threshold crossing events for a packet field, e.g. altitude callouts every 500 ft.
+//!
+//! Each threshold has a hysteresis band of `threshold ± hysteresis`. A value is only
+//! considered to have moved to the other side of the threshold once it leaves the band, so a
+//! reading jittering around a threshold produces a single crossing rather than a stream of
+//! them. The first packet only establishes which side each threshold starts on.
+use firm_core::firm_packets::FIRMData;
+use serde::Serialize;
+
+/// Which way a value crossed a threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Crossing {
+    /// From below the threshold to above it.
+    Rising,
+    /// From above the threshold to below it.
+    Falling,
+}
+
+impl Crossing {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Crossing::Rising => "rising",
+            Crossing::Falling => "falling",
+        }
+    }
+}
+
+/// Which crossings `FIRMClient::on_threshold` reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThresholdDirection {
+    Rising,
+    Falling,
+    Both,
+}
+
+impl ThresholdDirection {
+    fn includes(self, crossing: Crossing) -> bool {
+        matches!(
+            (self, crossing),
+            (ThresholdDirection::Both, _)
+                | (ThresholdDirection::Rising, Crossing::Rising)
+                | (ThresholdDirection::Falling, Crossing::Falling)
+        )
+    }
+}
+
+/// Emitted when a watched field crosses one of its thresholds.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ThresholdEvent {
+    pub field: String,
+    pub threshold: f32,
+    pub crossing: Crossing,
+    /// The field's value in the packet that completed the crossing.
+    pub value: f64,
+    pub timestamp_seconds: f64,
+}
+
+/// Tracks one field against a set of thresholds. See the module docs.
+pub struct ThresholdMonitor {
+    field: String,
+    direction: ThresholdDirection,
+    hysteresis: f32,
+    /// Each threshold with whether the value is currently above it, once known.
+    thresholds: Vec<(f32, Option<bool>)>,
+}
+
+impl ThresholdMonitor {
+    /// Creates a monitor for `field`.
+    ///
+    /// # Arguments
+    ///
+    /// - `field` (`&str`) - One of `FIRMData::field_names()`, e.g. "est_position_z_meters".
+    /// - `thresholds` (`Vec<f32>`) - The values to report crossings of.
+    /// - `direction` (`ThresholdDirection`) - Which crossings to report.
+    /// - `hysteresis` (`f32`) - Half-width of the band around each threshold the value must leave to cross it.
+    ///
+    /// # Returns
+    ///
+    /// - `Option<Self>` - `None` if `field` isn't a packet field.
+    pub fn new(
+        field: &str,
+        thresholds: Vec<f32>,
+        direction: ThresholdDirection,
+        hysteresis: f32,
+    ) -> Option<Self> {
+        if !FIRMData::field_names().contains(&field) {
+            return None;
+        }
+        Some(Self {
+            field: field.to_string(),
+            direction,
+            hysteresis: hysteresis.abs(),
+            thresholds: thresholds.into_iter().map(|t| (t, None)).collect(),
+        })
+    }
+
+    /// Feeds one packet through the monitor and returns the crossings it completed, in
+    /// threshold order. NaN readings are ignored.
+    pub fn update(&mut self, packet: &FIRMData) -> Vec<ThresholdEvent> {
+        let mut events = Vec::new();
+        let Some(value) = packet.field_value(&self.field).filter(|v| !v.is_nan()) else {
+            return events;
+        };
+
+        for (threshold, above) in &mut self.thresholds {
+            let upper = (*threshold + self.hysteresis) as f64;
+            let lower = (*threshold - self.hysteresis) as f64;
+            let crossing = match *above {
+                None => {
+                    *above = Some(value >= *threshold as f64);
+                    continue;
+                }
+                Some(false) if value >= upper => Crossing::Rising,
+                Some(true) if value < lower => Crossing::Falling,
+                Some(_) => continue,
+            };
+            *above = Some(crossing == Crossing::Rising);
+            if self.direction.includes(crossing) {
+                events.push(ThresholdEvent {
+                    field: self.field.clone(),
+                    threshold: *threshold,
+                    crossing,
+                    value,
+                    timestamp_seconds: packet.timestamp_seconds,
+                });
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn altitude(t: f64, meters: f32) -> FIRMData {
+        let mut packet = FIRMData::from_fields(t, [0.0; FIRMData::NUM_F32_FIELDS]);
+        packet.est_position_z_meters = meters;
+        packet
+    }
+
+    fn crossings(monitor: &mut ThresholdMonitor, altitudes: &[f32]) -> Vec<(f32, Crossing)> {
+        altitudes
+            .iter()
+            .enumerate()
+            .flat_map(|(i, &meters)| monitor.update(&altitude(i as f64, meters)))
+            .map(|event| (event.threshold, event.crossing))
+            .collect()
+    }
+
+    #[test]
+    fn test_jitter_inside_the_band_crosses_once() {
+        let mut monitor = ThresholdMonitor::new(
+            "est_position_z_meters",
+            vec![100.0],
+            ThresholdDirection::Both,
+            2.0,
+        )
+        .unwrap();
+        let events = crossings(
+            &mut monitor,
+            &[
+                95.0, 99.0, 101.0, 99.5, 101.5, 103.0, 101.0, 98.5, 102.0, 97.0,
+            ],
+        );
+        assert_eq!(
+            events,
+            vec![(100.0, Crossing::Rising), (100.0, Crossing::Falling)]
+        );
+    }
+
+    #[test]
+    fn test_direction_filter_and_jumps_over_several_thresholds() {
+        let mut monitor = ThresholdMonitor::new(
+            "est_position_z_meters",
+            vec![100.0, 200.0, 300.0],
+            ThresholdDirection::Falling,
+            0.0,
+        )
+        .unwrap();
+        let events = crossings(&mut monitor, &[0.0, 350.0, 250.0, 50.0]);
+        assert_eq!(
+            events,
+            vec![
+                (300.0, Crossing::Falling),
+                (100.0, Crossing::Falling),
+                (200.0, Crossing::Falling),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_first_packet_only_sets_the_side() {
+        let mut monitor = ThresholdMonitor::new(
+            "pressure_altitude_meters",
+            vec![10.0],
+            ThresholdDirection::Both,
+            0.0,
+        )
+        .unwrap();
+        let mut packet = altitude(0.0, 0.0);
+        packet.pressure_altitude_meters = 50.0;
+        assert!(monitor.update(&packet).is_empty());
+        packet.pressure_altitude_meters = f32::NAN;
+        assert!(monitor.update(&packet).is_empty());
+        packet.pressure_altitude_meters = 5.0;
+        assert_eq!(monitor.update(&packet)[0].crossing, Crossing::Falling);
+
+        assert!(ThresholdMonitor::new("altitude", vec![], ThresholdDirection::Both, 0.0).is_none());
+    }
+}
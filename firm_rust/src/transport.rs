@@ -0,0 +1,666 @@
+use crate::simulator::data_payload;
+use firm_core::constants::log_parsing::{HEADER_TOTAL_SIZE, LOG_FILE_MAGIC};
+use firm_core::constants::packet::PacketHeader;
+use firm_core::firm_packets::FIRMData;
+use firm_core::framed_packet::FramedPacket;
+use firm_core::log_decoding::LogDecoder;
+use firm_core::log_parsing::LogParser;
+use serialport::{ClearBuffer, SerialPort};
+use std::collections::VecDeque;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A byte stream the client can talk to a FIRM device over.
+///
+/// The reader thread owns the transport while the client is running and alternates between
+/// writing queued commands and reading. Reads should therefore not block indefinitely: when no
+/// data arrives within a short period they should return `io::ErrorKind::TimedOut` (or
+/// `WouldBlock`), which the reader thread treats as "nothing yet". Any other error stops the
+/// reader thread and is reported through `check_error`, unless reconnecting is enabled and the
+/// transport supports `reconnect`. Finite streams (e.g. `ReplayTransport`) return
+/// `io::ErrorKind::UnexpectedEof` once they run out, which stops the reader thread without an
+/// error.
+pub trait Transport: Read + Write + Send {
+    /// Returns the underlying serial port for transports backed by one, so serial-only
+    /// controls (DTR, baud rate, ...) can be reached. Other transports return `None`.
+    fn as_serial_port(&mut self) -> Option<&mut dyn SerialPort> {
+        None
+    }
+
+    /// Re-establishes the connection after the read or write side failed. Transports that
+    /// can't reconnect return `io::ErrorKind::Unsupported`.
+    fn reconnect(&mut self) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "transport does not support reconnecting",
+        ))
+    }
+
+    /// Holds the line in the break condition for `duration`, which unsticks a device whose
+    /// TX got wedged mid-frame. Serial ports do this through `SerialPort::set_break`; other
+    /// transports return `io::ErrorKind::Unsupported`.
+    fn send_break(&mut self, duration: Duration) -> io::Result<()> {
+        let port = self.as_serial_port().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                "transport does not support serial breaks",
+            )
+        })?;
+        port.set_break()?;
+        thread::sleep(duration);
+        port.clear_break()?;
+        Ok(())
+    }
+
+    /// Discards bytes queued in the OS receive and/or transmit buffers. Serial ports do this
+    /// through `SerialPort::clear`; other transports return `io::ErrorKind::Unsupported`.
+    fn clear_buffers(&mut self, direction: ClearBuffer) -> io::Result<()> {
+        let port = self.as_serial_port().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                "transport does not support purging buffers",
+            )
+        })?;
+        port.clear(direction)?;
+        Ok(())
+    }
+
+    /// Switches the line to `baud_rate`. Serial ports do this through
+    /// `SerialPort::set_baud_rate`; other transports return `io::ErrorKind::Unsupported`.
+    fn set_baud_rate(&mut self, baud_rate: u32) -> io::Result<()> {
+        let port = self.as_serial_port().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                "transport does not have a baud rate",
+            )
+        })?;
+        port.set_baud_rate(baud_rate)?;
+        Ok(())
+    }
+
+    /// Drives the DTR (data terminal ready) line. Serial ports do this through
+    /// `SerialPort::write_data_terminal_ready`; other transports return
+    /// `io::ErrorKind::Unsupported`.
+    fn set_dtr(&mut self, level: bool) -> io::Result<()> {
+        let port = self.as_serial_port().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::Unsupported, "transport has no DTR line")
+        })?;
+        port.write_data_terminal_ready(level)?;
+        Ok(())
+    }
+
+    /// Drives the RTS (request to send) line. Serial ports do this through
+    /// `SerialPort::write_request_to_send`; other transports return
+    /// `io::ErrorKind::Unsupported`.
+    fn set_rts(&mut self, level: bool) -> io::Result<()> {
+        let port = self.as_serial_port().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::Unsupported, "transport has no RTS line")
+        })?;
+        port.write_request_to_send(level)?;
+        Ok(())
+    }
+
+    /// Returns how many bytes can be read without waiting, or `None` for transports that
+    /// can't tell. Serial ports ask `SerialPort::bytes_to_read`.
+    fn bytes_to_read(&mut self) -> io::Result<Option<usize>> {
+        match self.as_serial_port() {
+            Some(port) => Ok(Some(port.bytes_to_read()? as usize)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// How the reader thread waits for incoming bytes. See `FIRMClient::set_read_strategy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReadStrategy {
+    /// Calls `read` straight away and lets it wait up to the port's read timeout. Lowest
+    /// latency.
+    #[default]
+    BlockingRead,
+    /// Checks `Transport::bytes_to_read` first and sleeps for `interval` while nothing is
+    /// waiting, then reads just what is there. Fewer, larger reads for less CPU, at the cost of
+    /// up to `interval` of extra latency. Transports that can't report it read as usual.
+    PollBytesToRead { interval: Duration },
+}
+
+/// The step of `FIRMClient::recover_link` after which valid frames arrived again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecoveryStep {
+    /// Purging the OS receive and transmit buffers.
+    PurgeBuffers,
+    /// Sending a serial break.
+    Break,
+    /// Sending a Cancel command.
+    Cancel,
+}
+
+impl RecoveryStep {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            RecoveryStep::PurgeBuffers => "purge_buffers",
+            RecoveryStep::Break => "break",
+            RecoveryStep::Cancel => "cancel",
+        }
+    }
+}
+
+impl Transport for Box<dyn SerialPort> {
+    fn as_serial_port(&mut self) -> Option<&mut dyn SerialPort> {
+        Some(self.as_mut())
+    }
+}
+
+/// TCP streams, e.g. a TCP-to-serial bridge. Set a read timeout on the stream first
+/// (`TcpStream::set_read_timeout`) so the reader thread can keep servicing writes.
+impl Transport for TcpStream {}
+
+/// A TCP connection to a networked FIRM bridge (e.g. a ser2net style TCP-to-serial server)
+/// that remembers its address so it can reconnect.
+///
+/// Unlike a bare `TcpStream`, the peer closing the connection is reported as
+/// `io::ErrorKind::ConnectionAborted` instead of a zero length read, so it isn't mistaken for
+/// "no data yet".
+pub struct TcpTransport {
+    stream: TcpStream,
+    addr: SocketAddr,
+    timeout: Duration,
+}
+
+impl TcpTransport {
+    /// Connects to `addr`, waiting at most `timeout` for the connection and then for each read.
+    ///
+    /// # Arguments
+    ///
+    /// - `addr` (`&str`) - The bridge address, e.g. "192.168.1.20:5000" or "firm-pi.local:5000".
+    /// - `timeout` (`Duration`) - Connect and read timeout. Zero is rounded up to 1 ms.
+    ///
+    /// # Returns
+    ///
+    /// - `io::Result<Self>` - The connected transport, or the error from resolving or connecting.
+    pub fn connect(addr: &str, timeout: Duration) -> io::Result<Self> {
+        let addr = addr.to_socket_addrs()?.next().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{addr} did not resolve to an address"),
+            )
+        })?;
+        let timeout = timeout.max(Duration::from_millis(1));
+        let stream = Self::open(addr, timeout)?;
+        Ok(Self {
+            stream,
+            addr,
+            timeout,
+        })
+    }
+
+    /// Returns the address of the bridge.
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    fn open(addr: SocketAddr, timeout: Duration) -> io::Result<TcpStream> {
+        let stream = TcpStream::connect_timeout(&addr, timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        // Commands are tiny and latency matters more than throughput for them.
+        stream.set_nodelay(true)?;
+        Ok(stream)
+    }
+}
+
+impl Read for TcpTransport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.stream.read(buf)? {
+            0 if !buf.is_empty() => Err(io::Error::new(
+                io::ErrorKind::ConnectionAborted,
+                format!("connection to {} closed by peer", self.addr),
+            )),
+            n => Ok(n),
+        }
+    }
+}
+
+impl Write for TcpTransport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl Transport for TcpTransport {
+    fn reconnect(&mut self) -> io::Result<()> {
+        self.stream = Self::open(self.addr, self.timeout)?;
+        Ok(())
+    }
+}
+
+/// How a client's transport was opened, e.g. for describing a raw recording.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionKind {
+    Serial,
+    Tcp,
+    Mock,
+    Replay,
+    LogFile,
+    Custom,
+}
+
+impl ConnectionKind {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Serial => "serial",
+            Self::Tcp => "tcp",
+            Self::Mock => "mock",
+            Self::Replay => "replay",
+            Self::LogFile => "log_file",
+            Self::Custom => "custom",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "serial" => Some(Self::Serial),
+            "tcp" => Some(Self::Tcp),
+            "mock" => Some(Self::Mock),
+            "replay" => Some(Self::Replay),
+            "log_file" => Some(Self::LogFile),
+            "custom" => Some(Self::Custom),
+            _ => None,
+        }
+    }
+}
+
+/// The settings a client's transport was opened with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectionSettings {
+    pub kind: ConnectionKind,
+    /// Serial port name, network address or file path, if the transport has one.
+    pub address: Option<String>,
+    pub baud_rate: Option<u32>,
+    /// Read timeout in seconds.
+    pub timeout_seconds: Option<f64>,
+}
+
+impl ConnectionSettings {
+    /// Settings for a transport the client knows nothing about.
+    pub fn custom() -> Self {
+        Self {
+            kind: ConnectionKind::Custom,
+            address: None,
+            baud_rate: None,
+            timeout_seconds: None,
+        }
+    }
+}
+
+/// Replays a finite byte stream, e.g. a raw recording, and discards everything written to it.
+///
+/// Reads return `io::ErrorKind::UnexpectedEof` once the stream runs out, so the reader thread
+/// stops cleanly instead of spinning on zero length reads.
+pub struct ReplayTransport<R> {
+    reader: R,
+}
+
+impl<R: Read + Send> ReplayTransport<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader }
+    }
+}
+
+impl<R: Read> Read for ReplayTransport<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.reader.read(buf)? {
+            0 if !buf.is_empty() => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "end of replayed stream",
+            )),
+            n => Ok(n),
+        }
+    }
+}
+
+impl<R> Write for ReplayTransport<R> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<R: Read + Send> Transport for ReplayTransport<R> {}
+
+/// Pause, speed and seek requests for a `LogPlaybackTransport`, shared with the client so they
+/// take effect while the reader thread owns the transport.
+#[derive(Debug)]
+pub struct PlaybackControl {
+    paused: AtomicBool,
+    /// Playback speed, stored as `f64` bits.
+    speed_bits: AtomicU64,
+    /// Log timestamp to jump to before the next packet, in seconds.
+    seek_seconds: Mutex<Option<f64>>,
+}
+
+impl PlaybackControl {
+    fn new(speed: f64) -> Self {
+        Self {
+            paused: AtomicBool::new(false),
+            speed_bits: AtomicU64::new(speed.to_bits()),
+            seek_seconds: Mutex::new(None),
+        }
+    }
+
+    /// Stops handing out packets until `resume` is called. The log position is kept.
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::Relaxed);
+    }
+
+    /// Continues a paused playback. Pacing restarts from the next packet, so the time spent
+    /// paused isn't caught up on.
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Changes the playback speed, from the next packet on.
+    ///
+    /// # Arguments
+    ///
+    /// - `speed` (`f64`) - Playback speed relative to real time; 0 plays as fast as possible.
+    pub fn set_speed(&self, speed: f64) -> io::Result<()> {
+        check_speed(speed)?;
+        self.speed_bits.store(speed.to_bits(), Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn speed(&self) -> f64 {
+        f64::from_bits(self.speed_bits.load(Ordering::Relaxed))
+    }
+
+    /// Jumps to the first packet at or after `timestamp_seconds` of log time, backwards or
+    /// forwards. The packet after the jump is handed out straight away.
+    ///
+    /// # Arguments
+    ///
+    /// - `timestamp_seconds` (`f64`) - Log time to continue from, as in `FIRMData::timestamp_seconds`.
+    pub fn seek(&self, timestamp_seconds: f64) {
+        *self.seek_seconds.lock().unwrap() = Some(timestamp_seconds);
+    }
+
+    fn take_seek(&self) -> Option<f64> {
+        self.seek_seconds.lock().unwrap().take()
+    }
+}
+
+fn check_speed(speed: f64) -> io::Result<()> {
+    if !(speed >= 0.0 && speed.is_finite()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Playback speed must be >= 0, got {speed}"),
+        ));
+    }
+    Ok(())
+}
+
+/// Plays a FIRM log file back as the data packets the device would have streamed, paced by the
+/// log's timestamps. The raw sensor readings are converted with `LogDecoder`, so only the raw
+/// sensor fields are filled in. Everything written to it is discarded.
+///
+/// Playback can be paused, sped up or moved while running through `control()`. Seeking
+/// backwards rewinds the reader, which is why it has to be `Seek`.
+///
+/// Like `ReplayTransport`, reads return `io::ErrorKind::UnexpectedEof` at the end of the log.
+pub struct LogPlaybackTransport<R> {
+    reader: R,
+    header: Vec<u8>,
+    parser: LogParser,
+    decoder: LogDecoder,
+    /// Decoded packets with their delay since the previous one, in seconds of log time.
+    queued: VecDeque<(FIRMData, f64)>,
+    /// Delay of packets that didn't decode, added to the next one so pacing stays right.
+    carried_delay_seconds: f64,
+    /// Timestamp of the last packet handed out or skipped over by a seek.
+    last_timestamp_seconds: Option<f64>,
+    /// Encoded frame currently being read out, and how much of it has been returned.
+    frame: Vec<u8>,
+    frame_offset: usize,
+    /// When the front of `queued` is due; the previous packet's due time anchors the next one
+    /// so slow reads don't accumulate drift.
+    due: Option<Instant>,
+    last_due: Option<Instant>,
+    /// The speed `due` was computed with.
+    speed: f64,
+    control: Arc<PlaybackControl>,
+    end_of_log: bool,
+}
+
+impl<R: Read + Seek + Send> LogPlaybackTransport<R> {
+    /// Longest a single read sleeps before returning `TimedOut`, so the reader thread stays
+    /// responsive to `stop()` and queued commands during long gaps in the log.
+    const MAX_READ_SLEEP: Duration = Duration::from_millis(50);
+
+    /// Reads the log header from `reader` and prepares to play the log back.
+    ///
+    /// # Arguments
+    ///
+    /// - `reader` (`R`) - The log file contents, starting with the header.
+    /// - `speed` (`f64`) - Playback speed relative to real time, e.g. 2.0 for twice as fast.
+    ///   0 plays the log back as fast as possible.
+    pub fn new(mut reader: R, speed: f64) -> io::Result<Self> {
+        check_speed(speed)?;
+
+        let mut header = vec![0u8; HEADER_TOTAL_SIZE];
+        reader.read_exact(&mut header)?;
+        if !header.starts_with(LOG_FILE_MAGIC) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not a FIRM log file (missing \"FIRM LOG\" header)",
+            ));
+        }
+        let mut parser = LogParser::new();
+        parser.read_header(&header);
+
+        Ok(Self {
+            reader,
+            header,
+            parser,
+            decoder: LogDecoder::new(),
+            queued: VecDeque::new(),
+            carried_delay_seconds: 0.0,
+            last_timestamp_seconds: None,
+            frame: Vec::new(),
+            frame_offset: 0,
+            due: None,
+            last_due: None,
+            speed,
+            control: Arc::new(PlaybackControl::new(speed)),
+            end_of_log: false,
+        })
+    }
+
+    /// Returns the handle for pausing, re-timing and seeking this playback.
+    pub fn control(&self) -> Arc<PlaybackControl> {
+        self.control.clone()
+    }
+
+    /// Reads and decodes more of the log until at least one packet is queued or the log ends.
+    fn refill(&mut self) -> io::Result<()> {
+        let mut chunk = [0u8; 4096];
+        while self.queued.is_empty() && !self.end_of_log {
+            let n = self.reader.read(&mut chunk)?;
+            if n == 0 {
+                self.end_of_log = true;
+            } else {
+                self.parser.parse_bytes(&chunk[..n]);
+                self.end_of_log = self.parser.eof_reached();
+            }
+
+            while let Some((packet, delay_seconds)) = self.parser.get_packet_and_time_delay() {
+                let delay_seconds = delay_seconds + std::mem::take(&mut self.carried_delay_seconds);
+                match self.decoder.decode(&packet) {
+                    Some(data) => self.queued.push_back((data, delay_seconds)),
+                    None => self.carried_delay_seconds = delay_seconds,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves playback to the first packet at or after `target_seconds`, rewinding to the start
+    /// of the log first if that packet was already handed out.
+    fn seek_to(&mut self, target_seconds: f64) -> io::Result<()> {
+        if self
+            .last_timestamp_seconds
+            .is_some_and(|last| last >= target_seconds)
+        {
+            self.reader
+                .seek(SeekFrom::Start(self.header.len() as u64))?;
+            self.parser.read_header(&self.header);
+            self.decoder = LogDecoder::new();
+            self.queued.clear();
+            self.carried_delay_seconds = 0.0;
+            self.last_timestamp_seconds = None;
+            self.end_of_log = false;
+        }
+
+        loop {
+            self.refill()?;
+            match self.queued.front() {
+                Some((data, _)) if data.timestamp_seconds < target_seconds => {
+                    self.last_timestamp_seconds = Some(data.timestamp_seconds);
+                    self.queued.pop_front();
+                }
+                _ => break,
+            }
+        }
+        if let Some((_, delay_seconds)) = self.queued.front_mut() {
+            *delay_seconds = 0.0;
+        }
+        self.due = None;
+        self.last_due = None;
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek + Send> Read for LogPlaybackTransport<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.frame_offset == self.frame.len() {
+            if let Some(target_seconds) = self.control.take_seek() {
+                self.seek_to(target_seconds)?;
+            }
+            if self.control.is_paused() {
+                // Pace from wherever playback resumes rather than catching up afterwards.
+                self.due = None;
+                self.last_due = None;
+                thread::sleep(Self::MAX_READ_SLEEP);
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "playback paused"));
+            }
+
+            self.refill()?;
+            let Some((_, delay_seconds)) = self.queued.front() else {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "end of log file",
+                ));
+            };
+
+            let speed = self.control.speed();
+            if speed != self.speed {
+                self.speed = speed;
+                self.due = None;
+            }
+            if speed > 0.0 {
+                let due = *self.due.get_or_insert_with(|| {
+                    let anchor = self.last_due.unwrap_or_else(Instant::now);
+                    anchor + Duration::from_secs_f64(delay_seconds / speed)
+                });
+                let now = Instant::now();
+                if now < due {
+                    thread::sleep((due - now).min(Self::MAX_READ_SLEEP));
+                    if Instant::now() < due {
+                        return Err(io::Error::new(
+                            io::ErrorKind::TimedOut,
+                            "next packet not due",
+                        ));
+                    }
+                }
+                self.last_due = self.due.take();
+            } else {
+                self.last_due = None;
+            }
+
+            let (data, _) = self.queued.pop_front().expect("checked above");
+            self.last_timestamp_seconds = Some(data.timestamp_seconds);
+            self.frame = FramedPacket::new(PacketHeader::Data, 0, data_payload(&data)).to_bytes();
+            self.frame_offset = 0;
+        }
+
+        let n = buf.len().min(self.frame.len() - self.frame_offset);
+        buf[..n].copy_from_slice(&self.frame[self.frame_offset..self.frame_offset + n]);
+        self.frame_offset += n;
+        Ok(n)
+    }
+}
+
+impl<R> Write for LogPlaybackTransport<R> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek + Send> Transport for LogPlaybackTransport<R> {}
+
+/// Adapts a separate reader and writer into a `Transport`, e.g. a recorded byte stream
+/// paired with `io::sink()`. The reader running out (`Ok(0)`) is reported as
+/// `io::ErrorKind::UnexpectedEof`, which ends the client's reader thread.
+pub struct ReadWriteTransport<R, W> {
+    reader: R,
+    writer: W,
+}
+
+impl<R: Read + Send, W: Write + Send> ReadWriteTransport<R, W> {
+    /// Creates a transport that reads from `reader` and writes to `writer`.
+    pub fn new(reader: R, writer: W) -> Self {
+        Self { reader, writer }
+    }
+
+    /// Returns the reader and writer.
+    pub fn into_inner(self) -> (R, W) {
+        (self.reader, self.writer)
+    }
+}
+
+impl<R: Read, W> Read for ReadWriteTransport<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.reader.read(buf)? {
+            0 if !buf.is_empty() => Err(io::ErrorKind::UnexpectedEof.into()),
+            n => Ok(n),
+        }
+    }
+}
+
+impl<R, W: Write> Write for ReadWriteTransport<R, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl<R: Read + Send, W: Write + Send> Transport for ReadWriteTransport<R, W> {}
@@ -0,0 +1,57 @@
+//! Replays the frame corpus in `tests/corpus` through the parser, checking that every frame is
+//! still handled the way the manifest recorded. Regenerate or grow the corpus with the
+//! `build_corpus` example.
+use firm_core::corpus::{FrameCorpus, FrameOutcome, FrameShape};
+use firm_core::data_parser::SerialParser;
+use std::path::Path;
+
+fn corpus() -> Vec<(FrameShape, Vec<u8>)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+    FrameCorpus::read_dir(&dir).unwrap()
+}
+
+#[test]
+fn test_corpus_frames_keep_their_recorded_outcome() {
+    let entries = corpus();
+    assert!(!entries.is_empty());
+    for (shape, bytes) in &entries {
+        assert_eq!(
+            FrameCorpus::classify(bytes).as_ref(),
+            Some(shape),
+            "{} is handled differently now",
+            shape.file_name()
+        );
+    }
+    for outcome in [
+        FrameOutcome::Accepted,
+        FrameOutcome::BadCrc,
+        FrameOutcome::Undecodable,
+    ] {
+        assert!(entries.iter().any(|(shape, _)| shape.outcome == outcome));
+    }
+}
+
+#[test]
+fn test_corpus_replayed_as_one_stream_in_any_chunking() {
+    let entries = corpus();
+    let stream: Vec<u8> = entries
+        .iter()
+        .flat_map(|(_, bytes)| bytes.clone())
+        .collect();
+    let accepted = entries
+        .iter()
+        .filter(|(shape, _)| shape.outcome == FrameOutcome::Accepted)
+        .count();
+
+    for chunk_size in [1, 3, 7, 64, stream.len()] {
+        let mut parser = SerialParser::new();
+        for chunk in stream.chunks(chunk_size) {
+            parser.parse_bytes(chunk);
+        }
+        let mut decoded = 0;
+        decoded += std::iter::from_fn(|| parser.get_data_packet()).count();
+        decoded += std::iter::from_fn(|| parser.get_baro_packet()).count();
+        decoded += std::iter::from_fn(|| parser.get_response_packet()).count();
+        assert_eq!(decoded, accepted, "chunks of {chunk_size}");
+    }
+}
@@ -0,0 +1,93 @@
+//! Runs each hardware example against the simulated device and checks it exits cleanly.
+//!
+//! `cargo test` builds the examples next to the test binaries, so they're run from there.
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Longest an example may run before it's considered hung.
+const EXAMPLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+fn example_path(name: &str) -> PathBuf {
+    // target/<profile>/deps/examples-<hash> -> target/<profile>/examples/<name>
+    let exe = std::env::current_exe().unwrap();
+    let profile_dir = exe.parent().and_then(|deps| deps.parent()).unwrap();
+    profile_dir
+        .join("examples")
+        .join(format!("{name}{}", std::env::consts::EXE_SUFFIX))
+}
+
+fn run_example(name: &str, args: &[&str]) {
+    let path = example_path(name);
+    assert!(
+        path.exists(),
+        "{} not found; run this test with `cargo test` so the examples are built",
+        path.display()
+    );
+
+    let mut child = Command::new(&path)
+        .args(args)
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap_or_else(|e| panic!("Failed to start {name}: {e}"));
+
+    let deadline = Instant::now() + EXAMPLE_TIMEOUT;
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            panic!("{name} did not exit within {EXAMPLE_TIMEOUT:?}");
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+    assert!(status.success(), "{name} exited with {status}");
+}
+
+#[test]
+fn test_simple_reader_simulated() {
+    run_example("simple_reader", &["--simulate", "--seconds", "2"]);
+}
+
+#[test]
+fn test_calibration_simulated() {
+    run_example(
+        "test_calibration",
+        &["--simulate", "--collect-seconds", "2"],
+    );
+}
+
+#[test]
+fn test_run_mock_print_bytes_simulated() {
+    run_example("run_mock_print_bytes", &["--simulate", "--quiet"]);
+}
+
+#[test]
+fn test_parse_bench_paths_agree() {
+    run_example("parse_bench", &["--packets", "2000", "--runs", "1"]);
+}
+
+#[test]
+fn test_parse_allocs_steady_state_allocates_nothing() {
+    run_example("parse_allocs", &["--packets", "2000"]);
+}
+
+#[test]
+fn test_process_logs() {
+    use firm_rust::simulator::{FlightProfileGenerator, build_mock_log};
+
+    let dir = std::env::temp_dir().join(format!("firm_rust_{}_process_logs", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let log = build_mock_log(&FlightProfileGenerator::default(), 1.0, 50.0);
+    std::fs::write(dir.join("first.bin"), &log).unwrap();
+    std::fs::write(dir.join("second.log"), &log).unwrap();
+    std::fs::write(dir.join("readme.txt"), "not a log").unwrap();
+
+    run_example("process_logs", &[dir.to_str().unwrap()]);
+    let summary = std::fs::read_to_string(dir.join("csv").join("summary.csv")).unwrap();
+    assert_eq!(summary.lines().count(), 3);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,426 @@
+//! Randomized streams through the parsers: valid frames mixed with garbage, bad CRCs, bogus
+//! lengths and truncated frames, then split into reads at random points. The parsers must
+//! never panic, `SerialParser` must recover every valid frame in order whatever the split, and
+//! its buffer must stay within `max_buffer_len`.
+//!
+//! The streams come from a seeded generator, so a failing seed reproduces exactly. Raise
+//! `FUZZ_SEEDS` to run more of them.
+use firm_core::constants::command::FIRMCommand;
+use firm_core::constants::log_parsing::*;
+use firm_core::constants::packet::{
+    BARO_PACKET_IDENTIFIER, DATA_PACKET_PAYLOAD_LENGTH, PacketHeader,
+    RAW_SENSOR_PACKET_PAYLOAD_LENGTH,
+};
+use firm_core::data_parser::SerialParser;
+use firm_core::firm_packets::{
+    DataLayout, FIRMBaroPacket, FIRMData, FIRMDataPacket, FIRMResponse, FIRMResponsePacket,
+};
+use firm_core::framed_packet::{Framed, FramedPacket};
+use firm_core::log_parsing::LogParser;
+use firm_core::parser_storage::{AllocStorage, FixedStorage, ParserStorage};
+
+/// How many seeds each test runs, unless the `FUZZ_SEEDS` environment variable says otherwise.
+const DEFAULT_SEEDS: u64 = 200;
+
+fn seeds() -> std::ops::Range<u64> {
+    let count = std::env::var("FUZZ_SEEDS")
+        .ok()
+        .and_then(|seeds| seeds.parse().ok())
+        .unwrap_or(DEFAULT_SEEDS);
+    0..count
+}
+
+struct Lcg(u64);
+
+impl Lcg {
+    fn new(seed: u64) -> Self {
+        // Spread nearby seeds apart before the first draw.
+        Self(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ 0xD1B5_4A32_D192_ED03)
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 32) as u32
+    }
+
+    /// Returns a number in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        self.next_u32() as usize % n.max(1)
+    }
+
+    fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+
+    fn bytes(&mut self, len: usize) -> Vec<u8> {
+        (0..len).map(|_| self.next_u32() as u8).collect()
+    }
+
+    fn bytes_up_to(&mut self, max_len: usize) -> Vec<u8> {
+        let len = self.below(max_len + 1);
+        self.bytes(len)
+    }
+}
+
+/// The frames a stream holds, as the parser should hand them back.
+#[derive(Debug, Default, PartialEq)]
+struct Recovered {
+    data_payloads: Vec<Vec<u8>>,
+    baro_packets: Vec<FIRMBaroPacket>,
+    responses: Vec<Vec<u8>>,
+    raw_frames: Vec<Vec<u8>>,
+}
+
+/// Bytes that must not hold a valid frame: noise, start words with lengths that lead nowhere,
+/// frames with a corrupted byte and frames cut short.
+fn garbage(rng: &mut Lcg) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for _ in 0..rng.below(4) {
+        match rng.below(5) {
+            0 => bytes.extend(rng.bytes_up_to(64)),
+            1 => {
+                let header = if rng.chance(50) {
+                    PacketHeader::Data
+                } else {
+                    PacketHeader::Response
+                };
+                bytes.extend(header.as_u16().to_le_bytes());
+                bytes.extend(rng.bytes(2));
+                // Anything from a plausible length to one far past any limit.
+                let length = match rng.below(3) {
+                    0 => rng.below(200) as u32,
+                    1 => rng.below(3000) as u32,
+                    _ => rng.next_u32(),
+                };
+                bytes.extend(length.to_le_bytes());
+                bytes.extend(rng.bytes_up_to(32));
+            }
+            2 => {
+                let mut frame = valid_frame(rng).0;
+                let index = rng.below(frame.len());
+                frame[index] ^= 1 << rng.below(8);
+                bytes.extend(frame);
+            }
+            3 => {
+                let frame = valid_frame(rng).0;
+                // Cut well short, so the bytes after it can't happen to finish the frame.
+                let cut = 1 + rng.below(frame.len() / 2);
+                bytes.extend(&frame[..cut]);
+            }
+            _ => bytes.extend(std::iter::repeat_n(0xA5, rng.below(8))),
+        }
+    }
+    bytes
+}
+
+/// The kinds of frame the parser queues in different places.
+enum Expected {
+    Data(Vec<u8>),
+    Baro(FIRMBaroPacket),
+    Response,
+    Raw,
+}
+
+/// A random frame the parser should accept, with where it should end up.
+fn valid_frame(rng: &mut Lcg) -> (Vec<u8>, Expected) {
+    match rng.below(5) {
+        0 | 1 => {
+            let length = if rng.chance(50) {
+                DATA_PACKET_PAYLOAD_LENGTH
+            } else {
+                RAW_SENSOR_PACKET_PAYLOAD_LENGTH
+            };
+            let mut payload = rng.bytes(length);
+            // Any field bits are valid apart from a non-finite timestamp.
+            let timestamp = rng.below(1_000_000) as f64 / 1000.0;
+            payload[..8].copy_from_slice(&timestamp.to_le_bytes());
+            let frame = FramedPacket::new(PacketHeader::Data, 0, payload.clone());
+            (frame.to_bytes(), Expected::Data(payload))
+        }
+        2 => {
+            let packet = FIRMBaroPacket {
+                timestamp_seconds: rng.below(1_000_000) as f64 / 1000.0,
+                pressure_pascals: rng.below(120_000) as f32,
+                temperature_celsius: rng.below(60) as f32,
+            };
+            let frame = FramedPacket::new(
+                PacketHeader::Data,
+                BARO_PACKET_IDENTIFIER,
+                packet.to_bytes(),
+            );
+            (frame.to_bytes(), Expected::Baro(packet))
+        }
+        3 => {
+            let command = [
+                FIRMCommand::SetDeviceConfig,
+                FIRMCommand::Mock,
+                FIRMCommand::Cancel,
+                FIRMCommand::GetDeviceInfo,
+            ][rng.below(4)];
+            let length = FIRMResponse::min_payload_length(command) + rng.below(4);
+            let frame =
+                FramedPacket::new(PacketHeader::Response, command.to_u16(), rng.bytes(length));
+            (frame.to_bytes(), Expected::Response)
+        }
+        _ => {
+            // A response to a command this client doesn't know, kept as a raw frame.
+            let length = rng.below(40);
+            let frame = FramedPacket::new(PacketHeader::Response, 0x7700, rng.bytes(length));
+            (frame.to_bytes(), Expected::Raw)
+        }
+    }
+}
+
+/// Builds a stream of valid frames with garbage between them, and what it should parse to.
+fn serial_stream(rng: &mut Lcg) -> (Vec<u8>, Recovered) {
+    let mut stream = Vec::new();
+    let mut expected = Recovered::default();
+    for _ in 0..rng.below(40) {
+        stream.extend(garbage(rng));
+        let (frame, kind) = valid_frame(rng);
+        match kind {
+            Expected::Data(payload) => expected.data_payloads.push(payload),
+            Expected::Baro(packet) => expected.baro_packets.push(packet),
+            Expected::Response => expected.responses.push(frame.clone()),
+            Expected::Raw => expected.raw_frames.push(frame.clone()),
+        }
+        stream.extend(frame);
+    }
+    stream.extend(garbage(rng));
+    // Enough quiet line afterwards for any start word in the garbage to run out of length.
+    stream.extend(std::iter::repeat_n(
+        0,
+        SerialParser::MAX_PAYLOAD_LENGTH + 16,
+    ));
+    (stream, expected)
+}
+
+/// Splits `stream` into reads of random sizes, from single bytes to several frames.
+fn random_chunks<'a>(rng: &mut Lcg, stream: &'a [u8]) -> Vec<&'a [u8]> {
+    let mut chunks = Vec::new();
+    let mut rest = stream;
+    while !rest.is_empty() {
+        let size = match rng.below(3) {
+            0 => 1 + rng.below(4),
+            1 => 1 + rng.below(64),
+            _ => 1 + rng.below(700),
+        };
+        let (chunk, tail) = rest.split_at(size.min(rest.len()));
+        chunks.push(chunk);
+        rest = tail;
+    }
+    chunks
+}
+
+/// Feeds `chunks` to a parser, draining it after every read, and returns what came out.
+fn parse_chunks<S: ParserStorage>(chunks: &[&[u8]], borrowed: bool) -> Recovered {
+    let mut parser = SerialParser::<S>::with_storage();
+    let mut recovered = Recovered::default();
+    for chunk in chunks {
+        if borrowed {
+            parser.parse_bytes_with(chunk, |view| {
+                recovered.data_payloads.push(view.payload().to_vec());
+            });
+        } else {
+            parser.parse_bytes(chunk);
+            recovered.data_payloads.extend(
+                parser
+                    .drain_packets()
+                    .iter()
+                    .map(|packet| packet.frame().payload().to_vec()),
+            );
+        }
+        assert!(parser.buffered_len() <= parser.max_buffer_len());
+        recovered
+            .baro_packets
+            .extend(std::iter::from_fn(|| parser.get_baro_packet()));
+        recovered.responses.extend(
+            parser
+                .drain_responses()
+                .iter()
+                .map(|response: &FIRMResponsePacket| response.frame().to_bytes()),
+        );
+        recovered
+            .raw_frames
+            .extend(std::iter::from_fn(|| parser.get_raw_frame()).map(|frame| frame.to_bytes()));
+    }
+    assert_eq!(parser.stats().packets_dropped, 0);
+    recovered
+}
+
+fn check_serial_streams<S: ParserStorage>() {
+    for seed in seeds() {
+        let mut rng = Lcg::new(seed);
+        let (stream, expected) = serial_stream(&mut rng);
+        let chunks = random_chunks(&mut rng, &stream);
+        for borrowed in [false, true] {
+            assert!(
+                parse_chunks::<S>(&chunks, borrowed) == expected,
+                "seed {seed} (borrowed: {borrowed}) didn't recover every frame"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_serial_parser_recovers_every_frame_from_noisy_streams() {
+    check_serial_streams::<AllocStorage>();
+}
+
+#[test]
+fn test_fixed_storage_parser_recovers_every_frame_from_noisy_streams() {
+    check_serial_streams::<FixedStorage<2048, 64>>();
+}
+
+#[test]
+fn test_serial_parser_survives_arbitrary_bytes() {
+    for seed in seeds() {
+        let mut rng = Lcg::new(seed);
+        // Mostly start-word bytes, so the scan gets far into frames before giving up.
+        let stream: Vec<u8> = (0..rng.below(8000))
+            .map(|_| match rng.below(4) {
+                0 => 0xA5,
+                1 => 0x5A,
+                _ => rng.next_u32() as u8,
+            })
+            .collect();
+        let mut parser = SerialParser::new();
+        parser.set_max_buffer_len(SerialParser::DEFAULT_MAX_BUFFER_LEN >> rng.below(6));
+        parser.collect_parse_errors(true);
+        for chunk in random_chunks(&mut rng, &stream) {
+            parser.parse_bytes(chunk);
+            assert!(parser.buffered_len() <= parser.max_buffer_len());
+            while parser.get_parse_error().is_some() {}
+        }
+        assert_eq!(parser.bytes_received(), stream.len() as u64);
+
+        // A snapshot taken midway, then damaged, must restore or fail cleanly.
+        let mut snapshot = parser.snapshot();
+        for _ in 0..rng.below(4) {
+            let index = rng.below(snapshot.len());
+            snapshot[index] = rng.next_u32() as u8;
+        }
+        snapshot.truncate(rng.below(snapshot.len() + 1));
+        if let Ok(mut restored) = SerialParser::restore(&snapshot) {
+            restored.parse_bytes(&stream[..stream.len().min(512)]);
+        }
+    }
+}
+
+#[test]
+fn test_response_decoding_survives_arbitrary_payloads() {
+    for seed in seeds() {
+        let mut rng = Lcg::new(seed);
+        let identifier = rng.below(0x0A) as u16;
+        let payload = rng.bytes_up_to(80);
+        let frame = FramedPacket::new(PacketHeader::Response, identifier, payload).to_bytes();
+        let _ = FIRMResponsePacket::from_bytes(&frame);
+    }
+}
+
+#[test]
+fn test_data_decoding_survives_arbitrary_payloads() {
+    for seed in seeds() {
+        let mut rng = Lcg::new(seed);
+        let payload = rng.bytes_up_to(DATA_PACKET_PAYLOAD_LENGTH + 8);
+        let layout = DataLayout::from_payload_length(payload.len());
+        let timestamp_finite = payload
+            .first_chunk()
+            .is_some_and(|bytes| f64::from_le_bytes(*bytes).is_finite());
+        let decoded = FIRMData::from_bytes(&payload);
+        assert_eq!(decoded.is_ok(), layout.is_some() && timestamp_finite);
+        let _ = FIRMData::from_bytes_unchecked(&payload);
+
+        let frame = FramedPacket::new(PacketHeader::Data, 0, payload).to_bytes();
+        assert_eq!(FIRMDataPacket::from_bytes(&frame).is_ok(), decoded.is_ok());
+    }
+}
+
+/// Builds a log stream of known packets with garbage between them, which never holds a packet
+/// id, and the packets and delays it should parse to.
+fn log_stream(rng: &mut Lcg) -> (Vec<u8>, Vec<(Vec<u8>, u32)>) {
+    let mut stream = Vec::new();
+    let mut expected = Vec::new();
+    let mut clock_count = rng.next_u32();
+    let mut last_clock_count = None;
+    for _ in 0..rng.below(60) {
+        for _ in 0..rng.below(6) {
+            let byte = loop {
+                let byte = rng.next_u32() as u8;
+                if ![0, BMP581_ID, ICM45686_ID, MMC5983MA_ID].contains(&byte) {
+                    break byte;
+                }
+            };
+            stream.push(byte);
+        }
+        // Padding between packets, short of the run that marks the end of the data.
+        stream.extend(std::iter::repeat_n(
+            0,
+            rng.below(LOG_FILE_EOF_PADDING_LENGTH),
+        ));
+
+        let (id, size) = [
+            (BMP581_ID, BMP581_SIZE),
+            (ICM45686_ID, ICM45686_SIZE),
+            (MMC5983MA_ID, MMC5983MA_SIZE),
+        ][rng.below(3)];
+        clock_count = clock_count.wrapping_add(rng.below(1_000_000) as u32);
+        let mut payload = clock_count.to_le_bytes().to_vec();
+        payload.extend(rng.bytes(size));
+        stream.push(id);
+        stream.extend(&payload);
+        let delta = last_clock_count.map_or(0, |last| clock_count.wrapping_sub(last));
+        last_clock_count = Some(clock_count);
+        expected.push((payload, delta));
+    }
+    (stream, expected)
+}
+
+fn parse_log(chunks: &[&[u8]]) -> Vec<(Vec<u8>, f64)> {
+    let mut parser = LogParser::new();
+    parser.read_header(&[0; HEADER_TOTAL_SIZE]);
+    let mut packets = Vec::new();
+    for chunk in chunks {
+        parser.parse_bytes(chunk);
+        packets.extend(
+            std::iter::from_fn(|| parser.get_packet_and_time_delay())
+                .map(|(packet, delay)| (packet.payload().to_vec(), delay)),
+        );
+    }
+    packets
+}
+
+#[test]
+fn test_log_parser_recovers_every_packet_from_noisy_streams() {
+    for seed in seeds() {
+        let mut rng = Lcg::new(seed);
+        let (stream, expected) = log_stream(&mut rng);
+        let expected: Vec<(Vec<u8>, f64)> = expected
+            .into_iter()
+            .map(|(payload, delta)| (payload, delta as f64 / LOG_CLOCK_HZ))
+            .collect();
+        assert_eq!(parse_log(&[&stream]), expected, "seed {seed}");
+        let chunks = random_chunks(&mut rng, &stream);
+        assert_eq!(parse_log(&chunks), expected, "seed {seed} split into reads");
+    }
+}
+
+#[test]
+fn test_log_parser_survives_arbitrary_bytes() {
+    for seed in seeds() {
+        let mut rng = Lcg::new(seed);
+        let stream: Vec<u8> = (0..rng.below(4000))
+            .map(|_| match rng.below(5) {
+                0 => 0,
+                1 => [BMP581_ID, ICM45686_ID, MMC5983MA_ID][rng.below(3)],
+                _ => rng.next_u32() as u8,
+            })
+            .collect();
+        let chunks = random_chunks(&mut rng, &stream);
+        // However it's split, the same bytes parse the same way.
+        assert_eq!(parse_log(&chunks), parse_log(&[&stream]), "seed {seed}");
+    }
+}
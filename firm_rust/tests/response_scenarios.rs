@@ -0,0 +1,157 @@
+//! Runs the client against the simulated device under each canned response timing scenario in
+//! `tests/response_scenarios`, see `firm_rust::response_script` for the format.
+use firm_core::firm_packets::{DeviceConfig, DeviceProtocol};
+use firm_rust::FIRMClient;
+use firm_rust::response_script::ResponseScript;
+use firm_rust::simulator::{DeviceSimulator, SimulatorHandle};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+fn start(scenario: &str) -> (FIRMClient, SimulatorHandle) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/response_scenarios")
+        .join(format!("{scenario}.txt"));
+    let script = ResponseScript::from_file(&path).unwrap();
+    let (mut client, device) = FIRMClient::new_mock(0.001);
+    let simulator = DeviceSimulator::new(200.0)
+        .with_response_script(script)
+        .spawn(device);
+    client.start().unwrap();
+    (client, simulator)
+}
+
+fn config(name: &str) -> DeviceConfig {
+    DeviceConfig {
+        name: name.to_string(),
+        frequency: 100,
+        protocol: DeviceProtocol::USB,
+        suspect: false,
+    }
+}
+
+fn set_config(client: &mut FIRMClient, config: &DeviceConfig, timeout: Duration) -> Option<bool> {
+    client
+        .set_device_config(
+            config.name.clone(),
+            config.frequency,
+            config.protocol,
+            timeout,
+        )
+        .unwrap()
+}
+
+#[test]
+fn test_slow_replies() {
+    let (mut client, _simulator) = start("slow_replies");
+    for _ in 0..5 {
+        let started = Instant::now();
+        assert!(client.get_device_info(TIMEOUT).unwrap().is_some());
+        assert!(started.elapsed() >= Duration::from_millis(20));
+    }
+    assert_eq!(
+        set_config(&mut client, &config("slow"), TIMEOUT),
+        Some(true)
+    );
+    assert_eq!(
+        client.get_device_config(TIMEOUT).unwrap(),
+        Some(config("slow"))
+    );
+}
+
+#[test]
+fn test_duplicate_acks() {
+    let (mut client, _simulator) = start("duplicate_acks");
+    let original = client.get_device_config(TIMEOUT).unwrap().unwrap();
+    assert_eq!(original.name, "FIRM Simulator");
+
+    // The duplicate of the first reply must not answer the second request.
+    assert_eq!(
+        set_config(&mut client, &config("first"), TIMEOUT),
+        Some(true)
+    );
+    assert_eq!(
+        client.get_device_config(TIMEOUT).unwrap(),
+        Some(config("first"))
+    );
+    assert_eq!(
+        set_config(&mut client, &config("second"), TIMEOUT),
+        Some(true)
+    );
+    assert_eq!(
+        client.get_device_config(TIMEOUT).unwrap(),
+        Some(config("second"))
+    );
+}
+
+#[test]
+fn test_out_of_order_replies() {
+    let (mut client, _simulator) = start("out_of_order");
+    let short = Duration::from_millis(100);
+    assert_eq!(set_config(&mut client, &config("first"), short), None);
+
+    // The held ack arrives right behind this reply, which must still be matched correctly.
+    assert_eq!(
+        client.get_device_config(TIMEOUT).unwrap(),
+        Some(config("first"))
+    );
+    thread::sleep(Duration::from_millis(50));
+
+    // That ack answered the first request; this one is held again.
+    assert_eq!(set_config(&mut client, &config("second"), short), None);
+    assert_eq!(
+        client.get_device_config(TIMEOUT).unwrap(),
+        Some(config("second"))
+    );
+}
+
+#[test]
+fn test_late_reply() {
+    let (mut client, _simulator) = start("late_reply");
+    assert_eq!(
+        client.get_device_config(Duration::from_millis(50)).unwrap(),
+        None
+    );
+    // Let the late reply to the timed out request arrive.
+    thread::sleep(Duration::from_millis(200));
+
+    assert_eq!(
+        set_config(&mut client, &config("late"), TIMEOUT),
+        Some(true)
+    );
+    assert_eq!(
+        client.get_device_config(TIMEOUT).unwrap(),
+        Some(config("late"))
+    );
+}
+
+#[test]
+fn test_replies_in_the_middle_of_data_bursts() {
+    let (mut client, _simulator) = start("mid_burst");
+    for _ in 0..5 {
+        assert!(client.get_device_info(TIMEOUT).unwrap().is_some());
+        assert!(client.get_calibration(TIMEOUT).unwrap().is_some());
+    }
+
+    let packets = client.get_data_packets(Some(TIMEOUT)).unwrap();
+    assert!(!packets.is_empty());
+    let stats = client.stats();
+    assert_eq!(stats.crc_failures, 0);
+    assert_eq!(stats.bytes_skipped, 0);
+    assert!(stats.packets_parsed >= 5 * 2 * 20);
+}
+
+#[test]
+fn test_lost_replies() {
+    let (mut client, _simulator) = start("lost_replies");
+    let short = Duration::from_millis(50);
+    assert_eq!(client.cancel(short).unwrap(), None);
+    assert!(client.get_device_info(TIMEOUT).unwrap().is_some());
+    assert_eq!(client.cancel(short).unwrap(), None);
+    assert_eq!(
+        set_config(&mut client, &config("lossy"), TIMEOUT),
+        Some(true)
+    );
+}
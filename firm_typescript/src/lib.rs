@@ -1,9 +1,9 @@
+mod protocol;
+
 use firm_core::client_packets::{FIRMCommandPacket, FIRMLogPacket};
-use firm_core::constants::command::{
-    NUMBER_OF_CALIBRATION_OFFSETS, NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS,
-};
 use firm_core::constants::log_parsing::{FIRMLogPacketType, HEADER_TOTAL_SIZE};
 use firm_core::data_parser::SerialParser;
+use firm_core::error_codes::{self, ErrorCode, ErrorCoded};
 use firm_core::firm_packets::{DeviceConfig, DeviceProtocol};
 use firm_core::framed_packet::Framed;
 use firm_core::log_parsing::LogParser;
@@ -14,6 +14,13 @@ use wasm_bindgen::prelude::*;
 use firm_core::calibration::MagnetometerCalibrator;
 use firm_core::firm_packets::FIRMData;
 
+/// Throws a JS `Error` with `message`, and the stable error code on its `code` property.
+fn throw_coded(message: &str, code: ErrorCode) -> ! {
+    let error = js_sys::Error::new(message);
+    let _ = Reflect::set(&error, &"code".into(), &code.code.into());
+    wasm_bindgen::throw_val(error.into())
+}
+
 #[wasm_bindgen]
 pub struct FIRMCommandBuilder;
 
@@ -40,6 +47,7 @@ impl FIRMCommandBuilder {
             name,
             frequency,
             protocol,
+            suspect: false,
         };
 
         FIRMCommandPacket::build_set_device_config_command(config).to_bytes()
@@ -51,83 +59,31 @@ impl FIRMCommandBuilder {
         gyro_offsets: Vec<f32>,
         gyro_scale_matrix: Vec<f32>,
     ) -> Vec<u8> {
-        if accel_offsets.len() != NUMBER_OF_CALIBRATION_OFFSETS {
-            wasm_bindgen::throw_str("accel_offsets must have length 3");
-        }
-        if accel_scale_matrix.len() != NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS {
-            wasm_bindgen::throw_str("accel_scale_matrix must have length 9");
-        }
-        if gyro_offsets.len() != NUMBER_OF_CALIBRATION_OFFSETS {
-            wasm_bindgen::throw_str("gyro_offsets must have length 3");
+        match FIRMCommandPacket::try_build_set_imu_calibration_command(
+            &accel_offsets,
+            &accel_scale_matrix,
+            &gyro_offsets,
+            &gyro_scale_matrix,
+        ) {
+            Ok(packet) => packet.to_bytes(),
+            Err(e) => throw_coded(&format!("Invalid IMU calibration: {e}"), e.error_code()),
         }
-        if gyro_scale_matrix.len() != NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS {
-            wasm_bindgen::throw_str("gyro_scale_matrix must have length 9");
-        }
-
-        let accel_offsets_arr: [f32; NUMBER_OF_CALIBRATION_OFFSETS] =
-            [accel_offsets[0], accel_offsets[1], accel_offsets[2]];
-        let accel_scale_arr: [f32; NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS] = [
-            accel_scale_matrix[0],
-            accel_scale_matrix[1],
-            accel_scale_matrix[2],
-            accel_scale_matrix[3],
-            accel_scale_matrix[4],
-            accel_scale_matrix[5],
-            accel_scale_matrix[6],
-            accel_scale_matrix[7],
-            accel_scale_matrix[8],
-        ];
-
-        let gyro_offsets_arr: [f32; NUMBER_OF_CALIBRATION_OFFSETS] =
-            [gyro_offsets[0], gyro_offsets[1], gyro_offsets[2]];
-        let gyro_scale_arr: [f32; NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS] = [
-            gyro_scale_matrix[0],
-            gyro_scale_matrix[1],
-            gyro_scale_matrix[2],
-            gyro_scale_matrix[3],
-            gyro_scale_matrix[4],
-            gyro_scale_matrix[5],
-            gyro_scale_matrix[6],
-            gyro_scale_matrix[7],
-            gyro_scale_matrix[8],
-        ];
-
-        FIRMCommandPacket::build_set_imu_calibration_command(
-            accel_offsets_arr,
-            accel_scale_arr,
-            gyro_offsets_arr,
-            gyro_scale_arr,
-        )
-        .to_bytes()
     }
 
     pub fn build_set_magnetometer_calibration(
         offsets: Vec<f32>,
         scale_matrix: Vec<f32>,
     ) -> Vec<u8> {
-        if offsets.len() != NUMBER_OF_CALIBRATION_OFFSETS {
-            wasm_bindgen::throw_str("offsets must have length 3");
-        }
-        if scale_matrix.len() != NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS {
-            wasm_bindgen::throw_str("scale_matrix must have length 9");
+        match FIRMCommandPacket::try_build_set_magnetometer_calibration_command(
+            &offsets,
+            &scale_matrix,
+        ) {
+            Ok(packet) => packet.to_bytes(),
+            Err(e) => throw_coded(
+                &format!("Invalid magnetometer calibration: {e}"),
+                e.error_code(),
+            ),
         }
-
-        let offsets_arr: [f32; NUMBER_OF_CALIBRATION_OFFSETS] =
-            [offsets[0], offsets[1], offsets[2]];
-        let scale_arr: [f32; NUMBER_OF_CALIBRATION_SCALE_MATRIX_ELEMENTS] = [
-            scale_matrix[0],
-            scale_matrix[1],
-            scale_matrix[2],
-            scale_matrix[3],
-            scale_matrix[4],
-            scale_matrix[5],
-            scale_matrix[6],
-            scale_matrix[7],
-            scale_matrix[8],
-        ];
-
-        FIRMCommandPacket::build_set_magnetometer_calibration_command(offsets_arr, scale_arr)
-            .to_bytes()
     }
 
     pub fn build_cancel() -> Vec<u8> {
@@ -168,11 +124,16 @@ impl FIRMDataParser {
         }
     }
 
+    /// Parses `data` and returns what the call did as a
+    /// `{ data_packets, responses, bytes_consumed, bytes_buffered }` object.
     #[wasm_bindgen]
-    pub fn parse_bytes(&mut self, data: &[u8]) {
-        self.inner.parse_bytes(data);
+    pub fn parse_bytes(&mut self, data: &[u8]) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.inner.parse_bytes(data)).unwrap()
     }
 
+    /// Returns the next data packet as a plain JS object, or `null`. Every field is a JS
+    /// number, and `timestamp_seconds` is passed through as the `f64` it is (never stringified
+    /// or narrowed), so it is bit-exact with the Rust and CSV values.
     #[wasm_bindgen]
     pub fn get_packet(&mut self) -> JsValue {
         match self.inner.get_data_packet() {
@@ -181,6 +142,21 @@ impl FIRMDataParser {
         }
     }
 
+    /// Returns every queued data packet as an array of the objects `get_packet` returns,
+    /// oldest first, in one call across the wasm boundary.
+    #[wasm_bindgen]
+    pub fn get_packets(&mut self) -> JsValue {
+        let packets = self.inner.drain_packets();
+        let data: Vec<_> = packets.iter().map(|frame| frame.data()).collect();
+        serde_wasm_bindgen::to_value(&data).unwrap()
+    }
+
+    /// Returns how many data packets `get_packets` would return.
+    #[wasm_bindgen]
+    pub fn packet_count(&self) -> usize {
+        self.inner.packet_count()
+    }
+
     #[wasm_bindgen]
     pub fn get_response(&mut self) -> JsValue {
         match self.inner.get_response_packet() {
@@ -188,6 +164,55 @@ impl FIRMDataParser {
             None => JsValue::NULL,
         }
     }
+
+    /// Returns every queued response as an array of the objects `get_response` returns,
+    /// oldest first.
+    #[wasm_bindgen]
+    pub fn get_responses(&mut self) -> JsValue {
+        let frames = self.inner.drain_responses();
+        let responses: Vec<_> = frames.iter().map(|frame| frame.response()).collect();
+        serde_wasm_bindgen::to_value(&responses).unwrap()
+    }
+
+    /// Returns how many responses `get_responses` would return.
+    #[wasm_bindgen]
+    pub fn response_count(&self) -> usize {
+        self.inner.response_count()
+    }
+
+    /// Starts recording where the stream loses sync, keeping the latest `capacity` events for
+    /// `take_resync_events`, or stops recording when passed `undefined`.
+    #[wasm_bindgen]
+    pub fn set_resync_log(&mut self, capacity: Option<usize>) {
+        self.inner.set_resync_log(capacity);
+    }
+
+    /// Returns the events recorded since the last call as an array of
+    /// `{ byte_offset, reason }` objects, oldest first, for the web console. `byte_offset`
+    /// counts from the first byte parsed, and `reason` is `"LengthMismatch"`, `"BadCrc"` or
+    /// `"NoStartBytes"`.
+    #[wasm_bindgen]
+    pub fn take_resync_events(&mut self) -> JsValue {
+        serde_wasm_bindgen::to_value(&self.inner.take_resync_events()).unwrap()
+    }
+
+    /// Returns the parser's state (buffered bytes, stats and queued packets) as a
+    /// `Uint8Array`, e.g. to keep in `sessionStorage` across a page reload.
+    #[wasm_bindgen]
+    pub fn snapshot(&self) -> Vec<u8> {
+        self.inner.snapshot()
+    }
+
+    /// Replaces the parser's state with a `snapshot`. Throws an `Error` with `code`
+    /// `E_SNAPSHOT_VERSION` if the snapshot is from another version, or `E_SNAPSHOT_CORRUPT`
+    /// if it is damaged; the current state is kept either way.
+    #[wasm_bindgen]
+    pub fn restore(&mut self, bytes: &[u8]) {
+        match SerialParser::restore(bytes) {
+            Ok(inner) => self.inner = inner,
+            Err(e) => throw_coded(&e.to_string(), e.error_code()),
+        }
+    }
 }
 
 #[wasm_bindgen(js_name = MockLogParser)]
@@ -305,7 +330,10 @@ impl MagnetometerCalibratorWasm {
     #[wasm_bindgen]
     pub fn add_sample(&mut self, packet: JsValue) {
         let data: FIRMData = serde_wasm_bindgen::from_value(packet).unwrap_or_else(|e| {
-            wasm_bindgen::throw_str(&format!("Failed to parse FIRMPacket for calibration: {e}"))
+            throw_coded(
+                &format!("Failed to parse FIRMPacket for calibration: {e}"),
+                error_codes::INVALID_ARGUMENT,
+            )
         });
         self.inner.add_sample(&data);
     }
@@ -0,0 +1,266 @@
+use firm_core::constants::command::*;
+use firm_core::constants::packet::*;
+use firm_core::firm_packets::DeviceProtocol;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+/// Wire protocol constants exported to TypeScript as the `PROTOCOL` object.
+///
+/// Everything here is built from the `firm_core` constants so the frontend never has to
+/// hardcode them.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProtocolConstants {
+    packet_headers: PacketHeaders,
+    command_markers: CommandMarkers,
+    frame_sizes: FrameSizes,
+    payload_lengths: PayloadLengths,
+    device_name_length: usize,
+    min_frequency_hz: u16,
+    max_frequency_hz: u16,
+    device_protocols: DeviceProtocols,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PacketHeaders {
+    data: u16,
+    response: u16,
+    log_sensor: u16,
+    command: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CommandMarkers {
+    get_device_info: u16,
+    get_device_config: u16,
+    set_device_config: u16,
+    reboot: u16,
+    mock: u16,
+    set_magnetometer_calibration: u16,
+    set_imu_calibration: u16,
+    get_calibration: u16,
+    cancel: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FrameSizes {
+    header: usize,
+    identifier: usize,
+    length: usize,
+    crc: usize,
+    min_packet: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PayloadLengths {
+    set_device_config: usize,
+    set_magnetometer_calibration: usize,
+    set_imu_calibration: usize,
+    max_command: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+struct DeviceProtocols {
+    usb: u8,
+    uart: u8,
+    i2c: u8,
+    spi: u8,
+}
+
+fn protocol_constants() -> ProtocolConstants {
+    ProtocolConstants {
+        packet_headers: PacketHeaders {
+            data: PacketHeader::Data.as_u16(),
+            response: PacketHeader::Response.as_u16(),
+            log_sensor: PacketHeader::LogSensor.as_u16(),
+            command: PacketHeader::Command.as_u16(),
+        },
+        command_markers: CommandMarkers {
+            get_device_info: FIRMCommand::GetDeviceInfo.to_u16(),
+            get_device_config: FIRMCommand::GetDeviceConfig.to_u16(),
+            set_device_config: FIRMCommand::SetDeviceConfig.to_u16(),
+            reboot: FIRMCommand::Reboot.to_u16(),
+            mock: FIRMCommand::Mock.to_u16(),
+            set_magnetometer_calibration: FIRMCommand::SetMagnetometerCalibration.to_u16(),
+            set_imu_calibration: FIRMCommand::SetIMUCalibration.to_u16(),
+            get_calibration: FIRMCommand::GetCalibration.to_u16(),
+            cancel: FIRMCommand::Cancel.to_u16(),
+        },
+        frame_sizes: FrameSizes {
+            header: HEADER_SIZE,
+            identifier: IDENTIFIER_SIZE,
+            length: LENGTH_SIZE,
+            crc: CRC_SIZE,
+            min_packet: MIN_PACKET_SIZE,
+        },
+        payload_lengths: PayloadLengths {
+            set_device_config: SET_DEVICE_CONFIG_PAYLOAD_LENGTH,
+            set_magnetometer_calibration: MAGNETOMETER_CALIBRATION_PAYLOAD_LENGTH,
+            set_imu_calibration: IMU_CALIBRATION_PAYLOAD_LENGTH,
+            max_command: MAX_COMMAND_PAYLOAD_LENGTH,
+        },
+        device_name_length: DEVICE_NAME_LENGTH,
+        min_frequency_hz: MIN_FREQUENCY_HZ,
+        max_frequency_hz: MAX_FREQUENCY_HZ,
+        device_protocols: DeviceProtocols {
+            usb: DeviceProtocol::USB as u8,
+            uart: DeviceProtocol::UART as u8,
+            i2c: DeviceProtocol::I2C as u8,
+            spi: DeviceProtocol::SPI as u8,
+        },
+    }
+}
+
+/// Returns the wire protocol constants as a plain JS object.
+#[wasm_bindgen(js_name = protocolConstants)]
+pub fn protocol_constants_js() -> JsValue {
+    serde_wasm_bindgen::to_value(&protocol_constants()).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use firm_core::client_packets::FIRMCommandPacket;
+    use firm_core::firm_packets::DeviceConfig;
+    use firm_core::framed_packet::Framed;
+
+    fn header_and_marker(bytes: &[u8]) -> (u16, u16) {
+        (
+            u16::from_le_bytes([bytes[0], bytes[1]]),
+            u16::from_le_bytes([bytes[2], bytes[3]]),
+        )
+    }
+
+    #[test]
+    fn test_exported_markers_match_built_commands() {
+        let protocol = protocol_constants();
+        let markers = &protocol.command_markers;
+        let command = protocol.packet_headers.command;
+
+        let cases = [
+            (
+                FIRMCommandPacket::build_get_device_info_command(),
+                markers.get_device_info,
+            ),
+            (
+                FIRMCommandPacket::build_get_device_config_command(),
+                markers.get_device_config,
+            ),
+            (FIRMCommandPacket::build_reboot_command(), markers.reboot),
+            (FIRMCommandPacket::build_mock_command(), markers.mock),
+            (
+                FIRMCommandPacket::build_get_calibration_command(),
+                markers.get_calibration,
+            ),
+            (FIRMCommandPacket::build_cancel_command(), markers.cancel),
+        ];
+        for (packet, marker) in cases {
+            assert_eq!(header_and_marker(&packet.to_bytes()), (command, marker));
+        }
+    }
+
+    #[test]
+    fn test_exported_payload_lengths_match_built_commands() {
+        let protocol = protocol_constants();
+        let lengths = &protocol.payload_lengths;
+        let sizes = &protocol.frame_sizes;
+        let frame_length = |payload: usize| sizes.min_packet + payload;
+
+        let config = FIRMCommandPacket::build_set_device_config_command(DeviceConfig {
+            name: "a".repeat(protocol.device_name_length),
+            frequency: protocol.max_frequency_hz,
+            protocol: DeviceProtocol::USB,
+            suspect: false,
+        })
+        .to_bytes();
+        assert_eq!(config.len(), frame_length(lengths.set_device_config));
+        assert_eq!(
+            header_and_marker(&config).1,
+            protocol.command_markers.set_device_config
+        );
+
+        let mag =
+            FIRMCommandPacket::try_build_set_magnetometer_calibration_command(&[0.0; 3], &[0.0; 9])
+                .unwrap()
+                .to_bytes();
+        assert_eq!(
+            mag.len(),
+            frame_length(lengths.set_magnetometer_calibration)
+        );
+
+        let imu = FIRMCommandPacket::try_build_set_imu_calibration_command(
+            &[0.0; 3], &[0.0; 9], &[0.0; 3], &[0.0; 9],
+        )
+        .unwrap()
+        .to_bytes();
+        assert_eq!(imu.len(), frame_length(lengths.set_imu_calibration));
+        assert!(lengths.max_command >= lengths.set_imu_calibration);
+    }
+
+    #[test]
+    fn test_exported_values_match_wire_constants() {
+        let protocol = protocol_constants();
+        assert_eq!(protocol.packet_headers.data, 0xA55A);
+        assert_eq!(protocol.packet_headers.response, 0x5AA5);
+        assert_eq!(protocol.packet_headers.log_sensor, 0x6BB6);
+        assert_eq!(protocol.packet_headers.command, 0xB66B);
+        assert_eq!(
+            protocol.frame_sizes.header
+                + protocol.frame_sizes.identifier
+                + protocol.frame_sizes.length
+                + protocol.frame_sizes.crc,
+            protocol.frame_sizes.min_packet
+        );
+        assert_eq!(protocol.device_name_length, DEVICE_NAME_LENGTH);
+        // Everything exported must agree with the authoritative table in firm_core.
+        let table = firm_core::constants::PROTOCOL;
+        assert_eq!(
+            [
+                protocol.packet_headers.data,
+                protocol.packet_headers.response,
+                protocol.packet_headers.log_sensor,
+                protocol.packet_headers.command,
+            ],
+            [
+                table.data_header,
+                table.response_header,
+                table.log_sensor_header,
+                table.command_header,
+            ]
+        );
+        assert_eq!(
+            [
+                protocol.frame_sizes.header,
+                protocol.frame_sizes.identifier,
+                protocol.frame_sizes.length,
+                protocol.frame_sizes.crc,
+                protocol.device_name_length,
+            ],
+            [
+                table.header_size,
+                table.identifier_size,
+                table.length_size,
+                table.crc_size,
+                table.device_name_length,
+            ]
+        );
+        assert_eq!(
+            (protocol.min_frequency_hz, protocol.max_frequency_hz),
+            (1, 1000)
+        );
+        assert_eq!(
+            protocol.device_protocols,
+            DeviceProtocols {
+                usb: 1,
+                uart: 2,
+                i2c: 3,
+                spi: 4
+            }
+        );
+    }
+}
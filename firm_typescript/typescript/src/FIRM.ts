@@ -12,7 +12,9 @@ import {
   DeviceConfig,
   DeviceProtocol,
   CalibrationValues,
+  ResyncEvent,
 } from './types.js';
+import { getProtocol } from './protocol.js';
 
 const RESPONSE_TIMEOUT_MS = 5000;
 
@@ -136,10 +138,8 @@ export class FIRMClient {
             }
           });
           this.dataParser.parse_bytes(value);
-          let pkt;
-          while ((pkt = this.dataParser.get_packet() as FIRMPacket | null)) this.enqueuePacket(pkt);
-          let res;
-          while ((res = this.dataParser.get_response() as FIRMResponse | null))
+          for (const pkt of this.dataParser.get_packets() as FIRMPacket[]) this.enqueuePacket(pkt);
+          for (const res of this.dataParser.get_responses() as FIRMResponse[])
             this.enqueueResponse(res);
         }
       }
@@ -177,6 +177,49 @@ export class FIRMClient {
     await this.writer.write(bytes);
   }
 
+  /**
+   * Saves the parser's state (partial frame, stats and queued packets), e.g. before a page
+   * reload. Pass the result to `restoreParser` to carry on mid-stream.
+   * @returns The snapshot bytes.
+   */
+  snapshotParser(): Uint8Array {
+    return this.dataParser.snapshot();
+  }
+
+  /**
+   * Restores parser state saved by `snapshotParser`.
+   * @param snapshot The snapshot bytes.
+   * @throws An `Error` with `code` `E_SNAPSHOT_VERSION` or `E_SNAPSHOT_CORRUPT` if the snapshot
+   * can't be used. The current parser state is kept.
+   */
+  restoreParser(snapshot: Uint8Array): void {
+    this.dataParser.restore(snapshot);
+  }
+
+  /**
+   * Starts recording where the stream loses sync (bad CRCs, impossible lengths and bytes
+   * between frames), for `takeResyncEvents`.
+   * @param capacity Most events kept between calls to `takeResyncEvents`.
+   */
+  enableResyncLog(capacity = 64): void {
+    this.dataParser.set_resync_log(capacity);
+  }
+
+  /**
+   * Stops recording resync events and drops the ones not yet taken.
+   */
+  disableResyncLog(): void {
+    this.dataParser.set_resync_log(undefined);
+  }
+
+  /**
+   * Takes the resync events recorded since the last call, oldest first.
+   * @returns The events, each with its offset into the stream and reason.
+   */
+  takeResyncEvents(): ResyncEvent[] {
+    return this.dataParser.take_resync_events() as ResyncEvent[];
+  }
+
   /**
    * @param listener Callback invoked with each incoming chunk.
    * @returns Unsubscribe function.
@@ -431,6 +474,13 @@ export class FIRMClient {
     frequency: number,
     protocol: DeviceProtocol,
   ): Promise<boolean> {
+    const { deviceNameLength, minFrequencyHz, maxFrequencyHz } = getProtocol();
+    if (new TextEncoder().encode(name).length > deviceNameLength) {
+      throw new Error(`Device name must be at most ${deviceNameLength} bytes`);
+    }
+    if (frequency < minFrequencyHz || frequency > maxFrequencyHz) {
+      throw new Error(`Frequency must be between ${minFrequencyHz} and ${maxFrequencyHz} Hz`);
+    }
     return (
       (await this.sendAndWait(
         () => FIRMCommandBuilder.build_set_device_config(name, frequency, protocol),
@@ -1,5 +1,6 @@
 // Re-export the main class and types from your wrapper
 export { FIRMClient as FIRM, type FIRMConnectOptions } from './FIRM.js';
+export { getProtocol, type ProtocolConstants } from './protocol.js';
 
 // Re-export all the shared types from your types file
 export {
@@ -9,4 +10,6 @@ export {
   type DeviceConfig,
   type DeviceProtocol,
   type CalibrationValues,
+  type ResyncEvent,
+  type ParseSummary,
 } from './types.js';
@@ -0,0 +1,56 @@
+import { protocolConstants } from '../../pkg/firm_client.js';
+
+/** Wire protocol constants, generated from the Rust `firm_core` constants. */
+export interface ProtocolConstants {
+  packetHeaders: {
+    data: number;
+    response: number;
+    logSensor: number;
+    command: number;
+  };
+  commandMarkers: {
+    getDeviceInfo: number;
+    getDeviceConfig: number;
+    setDeviceConfig: number;
+    reboot: number;
+    mock: number;
+    setMagnetometerCalibration: number;
+    setImuCalibration: number;
+    getCalibration: number;
+    cancel: number;
+  };
+  frameSizes: {
+    header: number;
+    identifier: number;
+    length: number;
+    crc: number;
+    minPacket: number;
+  };
+  payloadLengths: {
+    setDeviceConfig: number;
+    setMagnetometerCalibration: number;
+    setImuCalibration: number;
+    maxCommand: number;
+  };
+  deviceNameLength: number;
+  minFrequencyHz: number;
+  maxFrequencyHz: number;
+  deviceProtocols: {
+    USB: number;
+    UART: number;
+    I2C: number;
+    SPI: number;
+  };
+}
+
+let cached: ProtocolConstants | null = null;
+
+/**
+ * Returns the protocol constants exported by the WASM module.
+ *
+ * The WASM module must already be initialized (e.g. by `FIRM.connect()`).
+ */
+export function getProtocol(): ProtocolConstants {
+  cached ??= protocolConstants() as ProtocolConstants;
+  return cached;
+}
@@ -64,6 +64,8 @@ export interface FIRMPacket {
   est_quaternion_y: number;
   /** Estimated orientation quaternion vector component (Z). */
   est_quaternion_z: number;
+  /** Barometric altitude computed by the client, in meters (0 if not computed). */
+  pressure_altitude_meters: number;
 }
 
 export enum DeviceProtocol {
@@ -82,6 +84,8 @@ export interface DeviceConfig {
   name: string;
   frequency: number;
   protocol: DeviceProtocol;
+  /** True if the device reported fields out of protocol range, so the others are a best guess. */
+  suspect: boolean;
 }
 
 export interface CalibrationValues {
@@ -123,6 +127,25 @@ export interface CalibrationValues {
   ];
 }
 
+/** Where the byte stream lost sync, from `FIRMClient.takeResyncEvents`. */
+export interface ResyncEvent {
+  /** Offset of the first skipped byte, counted from the first byte parsed. */
+  byte_offset: number;
+  reason: 'LengthMismatch' | 'BadCrc' | 'NoStartBytes';
+}
+
+/** What one `FIRMDataParser.parse_bytes` call did. */
+export interface ParseSummary {
+  /** Data packets queued by the call. */
+  data_packets: number;
+  /** Command responses queued by the call. */
+  responses: number;
+  /** Buffered bytes parsed into frames or skipped, including ones left from earlier calls. */
+  bytes_consumed: number;
+  /** Bytes left buffered waiting for the rest of a frame. */
+  bytes_buffered: number;
+}
+
 export type FIRMResponse =
   | { GetDeviceInfo: DeviceInfo }
   | { GetDeviceConfig: DeviceConfig }
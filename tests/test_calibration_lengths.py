@@ -0,0 +1,15 @@
+import pytest
+
+import firm_client
+
+
+def test_set_magnetometer_calibration_rejects_wrong_length() -> None:
+    client, _device = firm_client.FIRMClient.new_mock(timeout=0.01)
+    client.start()
+    try:
+        with pytest.raises(ValueError, match="expected 3, got 2"):
+            client.set_magnetometer_calibration(
+                (0.0, 0.0), (1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0)
+            )
+    finally:
+        client.stop()
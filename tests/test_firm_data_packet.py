@@ -96,6 +96,7 @@ def test_firm_data_packet_default_zero() -> None:
     assert firm_data_packet.est_quaternion_x == 0.0
     assert firm_data_packet.est_quaternion_y == 0.0
     assert firm_data_packet.est_quaternion_z == 0.0
+    assert firm_data_packet.pressure_altitude_meters == 0.0
 
 
 def test_firm_data_packet_struct_fields() -> None:
@@ -0,0 +1,8 @@
+import pytest
+
+import firm_client
+
+
+def test_read_one_invalid_port_raises() -> None:
+    with pytest.raises(OSError):
+        firm_client.read_one("invalid_port_name", timeout=0.1)